@@ -0,0 +1,468 @@
+//! Adversarial tests
+//!
+//! The threat model of the shielded pool, as attacks run against the real pallet. Each
+//! attack must fail with one precise error, so a check that silently stops applying (or
+//! starts failing for another reason) breaks its test:
+//!
+//! - replaying a spend with another recipient
+//! - reusing a nullifier across local and cross-chain withdrawals
+//! - spending a note as another asset, or proving it against a foreign tree's root
+//! - shielding or spending the reserved zero leaf
+//! - claiming another amount than the proof was made for
+//! - submitting a proof made for older circuits than the verifying key's
+//!
+//! Attacks on the checks around the proof run with the mock verifier's placeholder proof;
+//! attacks on what a proof binds (the claimed amounts) run against real Groth16 proofs.
+
+use crate::{mock::*, Error, NullifierSet, Pallet, SetupMetadata, VerifyingKeySetup};
+use crate::circuit::CIRCUIT_VERSION;
+use crate::hasher::{HasherId, SimpleHasher};
+use crate::shield::{PayoutTarget, ProofEnvelope, ShieldInterface, UnshieldInputs};
+use crate::test_support::{
+	bundle_setup, lone_leaf_path, membership_setup, test_setup, TEST_RECIPIENT_HASH,
+};
+use crate::xcm_config::CustodyMode;
+use crate::{merkle_tree, simple_hash, zksnark};
+use frame::deps::frame_support::traits::{fungible::{Inspect, Mutate}, fungibles};
+use frame::testing_prelude::*;
+use sp_core::H256;
+use staging_xcm::v5::{AssetId, Location};
+
+/// Local ID of the first XCM asset registered in a test
+const RELAY_ASSET: u32 = 1;
+
+fn fund_pool(amount: u128) {
+	assert_ok!(Balances::mint_into(&Pallet::<Test>::account_id(), amount));
+}
+
+/// Shield a native note of `amount`, returning its commitment and nullifier
+fn native_note(amount: u128, seed: u8) -> (H256, H256) {
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), amount, 0, [seed; 32]));
+	let commitment = Pallet::<Test>::generate_commitment(amount, 0, &[seed; 32]);
+	(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[seed.wrapping_add(100); 32]))
+}
+
+/// Shield a note of the relay chain's asset through XCM, returning its commitment and nullifier
+fn relay_note(amount: u128, seed: u8) -> (H256, H256) {
+	let asset_id = AssetId(Location::parent());
+	if Pallet::<Test>::registered_asset_by_local(RELAY_ASSET).is_none() {
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+	}
+	assert_ok!(PrivacyBridge::deposit_from_xcm(
+		RuntimeOrigin::signed(1),
+		asset_id,
+		amount,
+		Location::parent(),
+		[seed; 32]
+	));
+	let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+		amount,
+		RELAY_ASSET,
+		&[seed; 32],
+		&Location::parent(),
+	);
+	(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[seed.wrapping_add(100); 32]))
+}
+
+fn envelope(circuit_version: u32) -> ProofEnvelope {
+	ProofEnvelope { circuit_version, proof: mock_proof().into_inner() }
+}
+
+fn inputs(nullifier: H256, commitment: H256, asset_id: u32, amount: u128) -> UnshieldInputs {
+	UnshieldInputs {
+		nullifier,
+		commitment,
+		asset_id,
+		amount,
+		anchor_block_hash: None,
+		merkle_root: None,
+	}
+}
+
+fn destination() -> Location {
+	Location::new(1, [])
+}
+
+#[test]
+fn replayed_spend_cannot_pay_another_recipient() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (commitment, nullifier) = native_note(500, 1);
+		let spend = inputs(nullifier, commitment, 0, 500);
+
+		assert_ok!(UnshieldCaller::recover(
+			RuntimeOrigin::root(),
+			envelope(CIRCUIT_VERSION),
+			spend,
+			PayoutTarget::Local(7)
+		));
+		assert_eq!(Balances::balance(&7), 500);
+
+		// The same proof and inputs, paid to someone else, locally or remotely
+		assert_noop!(
+			Pallet::<Test>::validate_unshield(&envelope(CIRCUIT_VERSION), &spend),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert_noop!(
+			UnshieldCaller::recover(
+				RuntimeOrigin::root(),
+				envelope(CIRCUIT_VERSION),
+				spend,
+				PayoutTarget::Local(8)
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert_noop!(
+			UnshieldCaller::recover(
+				RuntimeOrigin::root(),
+				envelope(CIRCUIT_VERSION),
+				spend,
+				PayoutTarget::Remote(destination(), Location::new(0, []))
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(8),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+
+		// A fresh nullifier does not make the spent note spendable again
+		let other_nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[3u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(8),
+				other_nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteAlreadySpent
+		);
+		assert_eq!(Balances::balance(&8), 0);
+	});
+}
+
+#[test]
+fn nullifier_cannot_be_reused_across_local_and_remote_withdrawals() {
+	new_test_ext().execute_with(|| {
+		fund_pool(2000);
+		let (local, local_nullifier) = native_note(500, 1);
+		let (relay, relay_nullifier) = relay_note(100, 2);
+
+		// Spent locally, the nullifier cannot export another note
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			local_nullifier,
+			local,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				local_nullifier,
+				relay,
+				PrivacyBridge::merkle_root(),
+				RELAY_ASSET,
+				100,
+				destination(),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+
+		// Spent cross-chain, it cannot withdraw another note locally
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			relay_nullifier,
+			relay,
+			PrivacyBridge::merkle_root(),
+			RELAY_ASSET,
+			100,
+			destination(),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+		let (second, _) = native_note(500, 3);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				relay_nullifier,
+				second,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert!(NullifierSet::<Test>::get(local_nullifier) && NullifierSet::<Test>::get(relay_nullifier));
+	});
+}
+
+#[test]
+fn note_cannot_be_spent_as_another_asset_or_against_a_foreign_root() {
+	new_test_ext().execute_with(|| {
+		fund_pool(2000);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), TEST_ASSET, 1, true, 1));
+		assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(TEST_ASSET, &1, 1000));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [9u8; 32]));
+		let token = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[9u8; 32]);
+		let (native, nullifier) = native_note(500, 1);
+
+		// A native note claimed as the other asset
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				native,
+				PrivacyBridge::merkle_root(),
+				400,
+				TEST_ASSET,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteAssetMismatch
+		);
+		assert_noop!(
+			Pallet::<Test>::validate_unshield(
+				&envelope(CIRCUIT_VERSION),
+				&inputs(nullifier, native, TEST_ASSET, 400)
+			),
+			Error::<Test>::NoteAssetMismatch
+		);
+
+		// Bounded assets are proven against a root of this pool's tree, not a tree of
+		// another asset's notes
+		let native_asset = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			native_asset.clone(),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		assert_ok!(PrivacyBridge::set_note_age_bounds(RuntimeOrigin::root(), native_asset, None, Some(100)));
+		let foreign_root = merkle_tree::root_of::<MockHasher>(&[token]).unwrap();
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				native,
+				foreign_root,
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::UnknownMerkleRoot
+		);
+
+		let mut anchored = inputs(nullifier, native, 0, 500);
+		anchored.merkle_root = Some(PrivacyBridge::merkle_root());
+		assert_ok!(Pallet::<Test>::validate_unshield(&envelope(CIRCUIT_VERSION), &anchored));
+	});
+}
+
+#[test]
+fn zero_leaf_can_be_neither_shielded_nor_spent() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), TEST_ASSET, 1, true, 1));
+		assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(TEST_ASSET, &1, 1000));
+		let zero = simple_hash::RESERVED_HASH;
+
+		// Precomputed commitments: other pallets and batchers
+		assert_noop!(
+			<Pallet<Test> as ShieldInterface<u64>>::shield(1, zero, TEST_ASSET, 100),
+			Error::<Test>::ReservedCommitmentValue
+		);
+		assert_ok!(PrivacyBridge::register_batcher(RuntimeOrigin::root(), 1));
+		let batch = vec![H256::repeat_byte(1), zero];
+		assert_noop!(
+			PrivacyBridge::submit_commitment_batch(
+				RuntimeOrigin::signed(1),
+				batch.clone().try_into().unwrap(),
+				merkle_tree::root_of::<MockHasher>(&batch).unwrap(),
+				200,
+				TEST_ASSET,
+			),
+			Error::<Test>::ReservedCommitmentValue
+		);
+
+		// A deposit whose randomness hashes to the zero leaf
+		MockHasherId::set(HasherId::Simple);
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, zero_commitment_randomness(100, 0)),
+			Error::<Test>::ReservedCommitmentValue
+		);
+		MockHasherId::set(HasherId::Blake2);
+
+		// Empty tree slots are not notes, and the zero nullifier is not a nullifier
+		let (commitment, nullifier) = native_note(100, 1);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				zero,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::CommitmentNotFound
+		);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				zero,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::ReservedCommitmentValue
+		);
+	});
+}
+
+#[test]
+fn claimed_amounts_must_be_the_proven_ones() {
+	let (pk, vk) = bundle_setup();
+	let assets = vec![(0u32, 100u128), (RELAY_ASSET, 40)];
+	let secret = [2u8; 32];
+	let randomness = simple_hash::generate_note_randomness(&secret, &[0u8; 32]);
+	let commitment = simple_hash::generate_bundle_commitment(&assets, &randomness);
+	let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+	let proof = zksnark::generate_bundle_proof::<SimpleHasher>(
+		pk,
+		nullifier.as_bytes().to_vec(),
+		commitment.as_bytes().to_vec(),
+		assets.clone(),
+		TEST_RECIPIENT_HASH.to_vec(),
+		[0u8; 32],
+		secret,
+	)
+	.unwrap();
+	let verify = |claimed: &[(u32, u128)]| {
+		zksnark::verify_bundle_proof(
+			&zksnark::prepare_vk(vk),
+			&proof,
+			nullifier.as_bytes(),
+			commitment.as_bytes(),
+			claimed,
+			&TEST_RECIPIENT_HASH,
+		)
+	};
+
+	assert_eq!(verify(&assets), Ok(true));
+	// More of an asset, an asset the note never held, or an asset left out
+	assert_eq!(verify(&[(0, 101), (RELAY_ASSET, 40)]), Ok(false));
+	assert_eq!(verify(&[(0, 100), (RELAY_ASSET, 40), (2, 1)]), Ok(false));
+	assert_eq!(verify(&[(0, 100)]), Ok(false));
+}
+
+#[test]
+fn proof_for_older_circuits_is_refused_by_the_new_key() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (commitment, nullifier) = native_note(500, 1);
+		let spend = inputs(nullifier, commitment, 0, 500);
+		let failures = RecordedMetrics::get().proof_failures;
+
+		// The key is for the current circuits, the proof for the previous ones
+		assert_noop!(
+			Pallet::<Test>::validate_unshield(&envelope(CIRCUIT_VERSION - 1), &spend),
+			Error::<Test>::CircuitVersionMismatch
+		);
+		assert_noop!(
+			UnshieldCaller::recover(
+				RuntimeOrigin::root(),
+				envelope(CIRCUIT_VERSION - 1),
+				spend,
+				PayoutTarget::Local(7)
+			),
+			Error::<Test>::CircuitVersionMismatch
+		);
+		assert_eq!(RecordedMetrics::get().proof_failures, failures + 2);
+
+		// Once the key moves on, proofs of the circuits it replaced are refused as well
+		VerifyingKeySetup::<Test>::put(SetupMetadata {
+			hasher: MockHasherId::get(),
+			circuit_version: CIRCUIT_VERSION + 1,
+		});
+		assert_noop!(
+			Pallet::<Test>::validate_unshield(&envelope(CIRCUIT_VERSION), &spend),
+			Error::<Test>::CircuitVersionMismatch
+		);
+		assert!(!NullifierSet::<Test>::get(nullifier));
+	});
+
+	// A key from another setup refuses the proof outright or fails its pairing check
+	let (pk, _) = test_setup();
+	let (_, other_vk) = membership_setup();
+	let randomness = simple_hash::generate_note_randomness(&[2u8; 32], &[0u8; 32]);
+	let commitment = simple_hash::generate_commitment(100, 0, &randomness);
+	let nullifier = simple_hash::generate_nullifier(&commitment, &[2u8; 32]);
+	let (nullifier, commitment) = (nullifier.as_bytes(), commitment.as_bytes());
+	let (merkle_path, path_indices) = lone_leaf_path();
+	let proof = zksnark::generate_proof::<SimpleHasher>(
+		pk,
+		nullifier.to_vec(),
+		commitment.to_vec(),
+		commitment.to_vec(),
+		TEST_RECIPIENT_HASH.to_vec(),
+		100,
+		0,
+		[0u8; 32],
+		[2u8; 32],
+		merkle_path,
+		path_indices,
+	)
+	.unwrap();
+	assert_eq!(
+		zksnark::verify_proof(
+			other_vk,
+			&proof,
+			nullifier,
+			commitment,
+			commitment,
+			0,
+			100,
+			&TEST_RECIPIENT_HASH
+		),
+		Err(zksnark::VerifyError::PublicInputCount)
+	);
+	assert_eq!(
+		zksnark::verify_membership_proof(other_vk, &proof, commitment, nullifier),
+		Ok(false)
+	);
+}
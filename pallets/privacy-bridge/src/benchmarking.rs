@@ -1,7 +1,18 @@
 //! Benchmarking setup for pallet-privacy-bridge
 
 use super::*;
-use frame::{deps::frame_benchmarking::v2::*, prelude::*};
+use alloc::vec::Vec;
+use crate::hasher::NoteHasher;
+use crate::merkle_tree::{self, TREE_DEPTH};
+use crate::xcm_config::CustodyMode;
+use crate::zksnark::{BenchmarkProof, Groth16Verifier, VerifyError, WithdrawalVerifier};
+use frame::{
+	deps::{frame_benchmarking::v2::*, frame_support::traits::fungible::{Inspect, Mutate}},
+	prelude::*,
+};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use staging_xcm::v5::{AssetId as XcmAssetId, Location};
 
 #[benchmarks]
 mod benchmarks {
@@ -10,31 +21,386 @@ mod benchmarks {
 	use crate::pallet::Pallet as PrivacyBridge;
 	use frame_system::RawOrigin;
 
-	#[benchmark]
-	fn deposit() {
+	/// Largest tree the deposit and withdraw benchmarks start from
+	const MAX_FILL: u32 = 100_000;
+
+	/// Whitelisted caller, funded for the native deposits of a benchmark
+	///
+	/// The pool account is created beforehand, so deposits below the existential deposit
+	/// can be transferred into it.
+	fn funded_caller<T: Config>() -> T::AccountId {
 		let caller: T::AccountId = whitelisted_caller();
+		let minimum = T::Currency::minimum_balance();
+		T::Currency::set_balance(&caller, minimum.saturating_add(1_000_000));
+		T::Currency::set_balance(&Pallet::<T>::account_id(), minimum);
+		caller
+	}
+
+	/// A benchmark verifying key, bounded as `announce_verifying_key` takes it
+	fn bounded_key(verifying_key: Vec<u8>) -> Result<VerifyingKeyBytes, BenchmarkError> {
+		VerifyingKeyBytes::try_from(verifying_key)
+			.map_err(|_| BenchmarkError::Stop("benchmark verifying key too long"))
+	}
+
+	/// Announce `vk` for `circuit`, with applying it approved as under dual control
+	fn announce_approved_key<T: Config>(
+		circuit: CircuitId,
+		vk: VerifyingKeyBytes,
+	) -> Result<(), BenchmarkError> {
+		let action = AdminAction::ApplyVerifyingKey { circuit, vk_hash: BlakeTwo256::hash(&vk) };
+		Pallet::<T>::announce_verifying_key(RawOrigin::Root.into(), circuit, vk, T::Hasher::id())?;
+		let expires_at = frame_system::Pallet::<T>::block_number()
+			.saturating_add(T::AdminApprovalTtl::get());
+		AdminApprovals::<T>::insert(action.hash(), AdminApproval { expires_at, approved: true });
+		Ok(())
+	}
+
+	/// Make `vk` the verifying key of `circuit`, announced and applied as governance would
+	fn install_key<T: Config>(
+		circuit: CircuitId,
+		vk: VerifyingKeyBytes,
+	) -> Result<(), BenchmarkError> {
+		announce_approved_key::<T>(circuit, vk)?;
+		Pallet::<T>::apply_verifying_key(RawOrigin::Signed(whitelisted_caller()).into(), circuit)?;
+		Ok(())
+	}
+
+	/// Deposit into a tree holding `n` leaves, hashing its leaf right away
+	///
+	/// The cost only depends on `TREE_DEPTH`: the result should show no slope over `n`.
+	#[benchmark]
+	fn deposit(n: Linear<0, MAX_FILL>) -> Result<(), BenchmarkError> {
+		if T::BatchTreeInsertion::get() {
+			return Err(BenchmarkError::Skip);
+		}
+		Pallet::<T>::force_populate_tree(n)?;
+		let caller = funded_caller::<T>();
 		let amount = 100u128;
 		let asset_id = 0u32;
-		let randomness = [1u8; 32];
+		let note_key = [1u8; 32];
 
 		#[extrinsic_call]
-		deposit(RawOrigin::Signed(caller), amount, asset_id, randomness);
+		deposit(RawOrigin::Signed(caller), amount, asset_id, note_key);
 
-		assert_eq!(CommitmentCount::<T>::get(), 1);
+		assert_eq!(CommitmentCount::<T>::get(), n + 1);
+		assert_eq!(TreeSize::<T>::get(), n + 1);
+
+		Ok(())
 	}
 
+	/// Deposit into a tree holding `n` leaves, with the leaf's share of the end-of-block
+	/// tree update
+	///
+	/// Loading and storing the frontier happens once per block and is charged by
+	/// `on_initialize`, so only the hashing of the leaf is measured here.
 	#[benchmark]
-	fn withdraw() {
-		let caller: T::AccountId = whitelisted_caller();
-		let amount = 100u128;
+	fn deposit_batched(n: Linear<0, MAX_FILL>) -> Result<(), BenchmarkError> {
+		if !T::BatchTreeInsertion::get() {
+			return Err(BenchmarkError::Skip);
+		}
+		Pallet::<T>::force_populate_tree(n)?;
+		let mut filled_subtrees: [H256; TREE_DEPTH] =
+			core::array::from_fn(|level| FilledSubtrees::<T>::get(level as u8));
+		let caller = funded_caller::<T>();
+
+		#[block]
+		{
+			Pallet::<T>::deposit(RawOrigin::Signed(caller).into(), 100, 0, [1u8; 32])?;
+			let leaf = MerkleLeaves::<T>::get(n).unwrap_or_default();
+			merkle_tree::append_leaf::<T::Hasher>(&mut filled_subtrees, n, leaf);
+		}
+
+		assert_eq!(CommitmentCount::<T>::get(), n + 1);
+		assert_eq!(TreeSize::<T>::get(), n);
+
+		Ok(())
+	}
+
+	/// Deposit split into `s` notes, each hashed into the tree right away
+	#[benchmark]
+	fn deposit_split(s: Linear<1, { T::MaxSplit::get() }>) -> Result<(), BenchmarkError> {
+		if T::BatchTreeInsertion::get() {
+			return Err(BenchmarkError::Skip);
+		}
+		let caller = funded_caller::<T>();
+		let commitments: BoundedVec<H256, T::MaxSplit> = (0..s)
+			.map(|i| Pallet::<T>::generate_commitment(100, 0, &[i as u8 + 1; 32]))
+			.collect::<Vec<_>>()
+			.try_into()
+			.map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		deposit_split(RawOrigin::Signed(caller), 0, commitments, 100);
+
+		assert_eq!(CommitmentCount::<T>::get(), s);
+		assert_eq!(TreeSize::<T>::get(), s);
+
+		Ok(())
+	}
+
+	/// Withdraw from a tree holding `n` leaves besides the note spent
+	#[benchmark]
+	fn withdraw(n: Linear<0, MAX_FILL>) -> Result<(), BenchmarkError> {
+		Pallet::<T>::force_populate_tree(n)?;
+		let caller = funded_caller::<T>();
 		let asset_id = 0u32;
-		let nullifier = sp_core::H256::from([1u8; 32]);
+		let case = T::ProofVerifier::benchmark_proof(asset_id, &primitives::recipient_hash(&caller))
+			.map_err(|_| BenchmarkError::Stop("no proof to benchmark withdrawals with"))?;
+		let (nullifier, commitment, amount) = (case.nullifier, case.commitment, case.amount);
+		let proof = BoundedVec::try_from(case.proof)
+			.map_err(|_| BenchmarkError::Stop("benchmark proof too long"))?;
+		install_key::<T>(CircuitId::Withdraw, bounded_key(case.verifying_key)?)?;
+
+		// The note is shielded as the proof's circuit commits to it, whatever the runtime's hasher
+		Pallet::<T>::do_shield(caller.clone(), commitment, asset_id, amount)?;
+		// The proof is made against a tree of the note alone; verifying it costs the same
+		MerkleRoot::<T>::put(case.merkle_root);
+		Pallet::<T>::record_root(case.merkle_root);
+		let merkle_root = case.merkle_root;
 
 		#[extrinsic_call]
-		withdraw(RawOrigin::Signed(caller), nullifier, amount, asset_id);
+		withdraw(
+			RawOrigin::Signed(caller),
+			nullifier,
+			commitment,
+			merkle_root,
+			amount,
+			asset_id,
+			proof,
+			None,
+		);
 
 		assert!(NullifierSet::<T>::get(&nullifier));
+
+		Ok(())
+	}
+
+	/// Apply an announced, approved key for the withdrawal circuit, the one withdrawals are
+	/// benchmarked with
+	#[benchmark]
+	fn apply_verifying_key() -> Result<(), BenchmarkError> {
+		let case = T::ProofVerifier::benchmark_proof(0, &H256::zero())
+			.map_err(|_| BenchmarkError::Stop("no verifying key to benchmark with"))?;
+		announce_approved_key::<T>(CircuitId::Withdraw, bounded_key(case.verifying_key)?)?;
+		let caller: T::AccountId = whitelisted_caller();
+
+		#[extrinsic_call]
+		apply_verifying_key(RawOrigin::Signed(caller), CircuitId::Withdraw);
+
+		assert!(VerifyingKeys::<T>::contains_key(CircuitId::Withdraw));
+		assert!(!PendingVerifyingKeys::<T>::contains_key(CircuitId::Withdraw));
+
+		Ok(())
+	}
+
+	/// Groth16 verification of the benchmark withdrawal proof against `prepared_key`
+	fn verify_benchmark_proof(
+		prepared_key: &[u8],
+		case: &BenchmarkProof,
+	) -> Result<bool, VerifyError> {
+		Groth16Verifier::verify(
+			prepared_key,
+			&case.proof,
+			&case.nullifier,
+			&case.commitment,
+			&case.merkle_root,
+			case.asset_id,
+			case.amount,
+			&H256::zero(),
+		)
+	}
+
+	/// Verify a withdrawal proof against the key prepared beforehand, as
+	/// `PreparedVerifyingKeys` holds it
+	///
+	/// Groth16 whatever the runtime's verifier, for comparison with `verify_unprepared`.
+	#[benchmark]
+	fn verify_prepared() -> Result<(), BenchmarkError> {
+		let case = Groth16Verifier::benchmark_proof(0, &H256::zero())
+			.map_err(|_| BenchmarkError::Stop("no proof to benchmark with"))?;
+		let prepared_key = Groth16Verifier::prepare_key(&case.verifying_key)
+			.map_err(|_| BenchmarkError::Stop("benchmark key does not decode"))?;
+		let verified;
+
+		#[block]
+		{
+			verified = verify_benchmark_proof(&prepared_key, &case);
+		}
+
+		assert_eq!(verified, Ok(true));
+
+		Ok(())
+	}
+
+	/// `verify_prepared`, preparing the key for the proof as verification did before
+	/// `PreparedVerifyingKeys`
+	#[benchmark]
+	fn verify_unprepared() -> Result<(), BenchmarkError> {
+		let case = Groth16Verifier::benchmark_proof(0, &H256::zero())
+			.map_err(|_| BenchmarkError::Stop("no proof to benchmark with"))?;
+		let verified;
+
+		#[block]
+		{
+			verified = Groth16Verifier::prepare_key(&case.verifying_key)
+				.and_then(|prepared_key| verify_benchmark_proof(&prepared_key, &case));
+		}
+
+		assert_eq!(verified, Ok(true));
+
+		Ok(())
+	}
+
+	/// Register the relay chain's asset under the next local ID
+	#[benchmark]
+	fn register_asset() {
+		let asset_id = XcmAssetId(Location::parent());
+
+		#[extrinsic_call]
+		register_asset(RawOrigin::Root, asset_id.clone(), 1, CustodyMode::Pot, 0);
+
+		assert!(Pallet::<T>::registered_asset(&asset_id).is_some());
+	}
+
+	/// Cross-chain deposit into a tree holding `n` leaves, hashing its leaf right away
+	///
+	/// Skipped on chains without signed XCM deposits, whose cross-chain deposits only
+	/// arrive through `ShieldDepositTransactor`.
+	#[benchmark]
+	fn deposit_from_xcm(n: Linear<0, MAX_FILL>) -> Result<(), BenchmarkError> {
+		if !T::AllowSignedXcmDeposits::get() || T::BatchTreeInsertion::get() {
+			return Err(BenchmarkError::Skip);
+		}
+		Pallet::<T>::force_populate_tree(n)?;
+		let asset_id = XcmAssetId(Location::parent());
+		Pallet::<T>::do_register_asset(asset_id.clone(), 0, CustodyMode::Pot, 0)?;
+		let caller: T::AccountId = whitelisted_caller();
+
+		#[extrinsic_call]
+		deposit_from_xcm(RawOrigin::Signed(caller), asset_id, 100, Location::parent(), [1u8; 32]);
+
+		assert_eq!(CommitmentCount::<T>::get(), n + 1);
+
+		Ok(())
+	}
+
+	/// Withdraw a note of the relay chain's asset to the relay chain
+	///
+	/// The verifying key and the note are the ones `withdraw` is benchmarked with.
+	#[benchmark]
+	fn withdraw_to_parachain() -> Result<(), BenchmarkError> {
+		let caller = funded_caller::<T>();
+		let asset_id = XcmAssetId(Location::parent());
+		let local_id = Pallet::<T>::do_register_asset(asset_id.clone(), 0, CustodyMode::Pot, 0)?;
+		let beneficiary = Location::new(0, []);
+		let case = T::ProofVerifier::benchmark_proof(
+			local_id,
+			&primitives::recipient_hash(&beneficiary),
+		)
+		.map_err(|_| BenchmarkError::Stop("no proof to benchmark withdrawals with"))?;
+		let (nullifier, commitment, merkle_root) =
+			(case.nullifier, case.commitment, case.merkle_root);
+		let proof = BoundedVec::try_from(case.proof)
+			.map_err(|_| BenchmarkError::Stop("benchmark proof too long"))?;
+		install_key::<T>(CircuitId::Withdraw, bounded_key(case.verifying_key)?)?;
+		// The proven note, shielded as a deposit from the relay chain
+		let registered = Pallet::<T>::registered_asset(&asset_id)
+			.ok_or(BenchmarkError::Stop("relay chain asset not registered"))?;
+		Pallet::<T>::shield_xcm_commitment(&registered, 100, commitment, &Location::parent())?;
+		// As for `withdraw`, the proof is made against a tree of the note alone
+		MerkleRoot::<T>::put(merkle_root);
+		Pallet::<T>::record_root(merkle_root);
+
+		#[extrinsic_call]
+		withdraw_to_parachain(
+			RawOrigin::Signed(caller),
+			nullifier,
+			commitment,
+			merkle_root,
+			local_id,
+			100,
+			Location::parent(),
+			beneficiary,
+			0,
+			proof,
+		);
+
+		assert!(NullifierSet::<T>::get(&nullifier));
+
+		Ok(())
+	}
+
+	/// Merkle path of the last of `n` leaves, read from `MerkleNodes`
+	///
+	/// One node is read per level: the result should show no slope over `n`.
+	#[benchmark]
+	fn merkle_path(n: Linear<1, MAX_FILL>) -> Result<(), BenchmarkError> {
+		Pallet::<T>::force_populate_tree(n)?;
+		let path;
+
+		#[block]
+		{
+			path = Pallet::<T>::merkle_path(n - 1);
+		}
+
+		assert_eq!(path.map(|path| path.len()), Some(TREE_DEPTH));
+
+		Ok(())
 	}
 
 	impl_benchmark_test_suite!(PrivacyBridge, crate::mock::new_test_ext(), crate::mock::Test);
 }
+
+/// Cost of a benchmark's extrinsic, as the weight regression tests compare it
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Measurement {
+	/// Fastest of the runs' execution times, in picoseconds
+	pub ref_time: u64,
+	/// Size of what the extrinsic reads, at the bounds of its storage items
+	pub proof_size: u64,
+	pub reads: u32,
+	pub writes: u32,
+}
+
+/// Run benchmark `name` at `components` on the mock runtime, `repeats` times
+///
+/// The proof size is estimated the way `frame-benchmarking-cli` does with
+/// `MaxEncodedLen`: every key read that is not whitelisted counts its length and the
+/// largest value its storage item can hold, so it only moves with the storage layout.
+#[cfg(test)]
+pub(crate) fn measure(
+	name: &[u8],
+	components: &[(BenchmarkParameter, u32)],
+	repeats: u32,
+) -> Result<Measurement, BenchmarkError> {
+	use crate::mock::{AllPalletsWithSystem, Test};
+	use frame::deps::{
+		frame_benchmarking::Benchmarking, frame_support::traits::StorageInfoTrait,
+	};
+
+	let results = Pallet::<Test>::run_benchmark(name, components, &[], true, repeats)?;
+	let first = results.first().ok_or(BenchmarkError::Weightless)?;
+	let storage_info = AllPalletsWithSystem::storage_info();
+	let max_size = |key: &[u8]| {
+		storage_info
+			.iter()
+			.find(|info| key.starts_with(&info.prefix))
+			.and_then(|info| info.max_size)
+			.unwrap_or_default()
+	};
+	let proof_size = first
+		.keys
+		.iter()
+		.filter(|(_, reads, _, whitelisted)| *reads > 0 && !whitelisted)
+		.map(|(key, ..)| key.len() as u64 + u64::from(max_size(key)))
+		.sum();
+	let fastest = results.iter().map(|result| result.extrinsic_time).min().unwrap_or_default();
+
+	Ok(Measurement {
+		ref_time: u64::try_from(fastest.saturating_mul(1_000)).unwrap_or(u64::MAX),
+		proof_size,
+		reads: first.reads,
+		writes: first.writes,
+	})
+}
@@ -0,0 +1,230 @@
+//! Outbound bridge adapters
+//!
+//! Cross-chain withdrawals leave the pool through an `ExportWithdrawal` implementation,
+//! so exits are not tied to XCM:
+//!
+//! ```text
+//! withdraw_to_remote ──> Config::Exporter ──┬─ XcmExporter ──> XCM router ──> Parachain
+//!                                           └─ (other bridges, e.g. Ethereum)
+//! ```
+//!
+//! Destinations are expressed as XCM `Location`s, which already cover non-XCM consensus
+//! systems through `GlobalConsensus` (e.g. `GlobalConsensus(Ethereum { chain_id })`).
+//! Beneficiaries are opaque bytes interpreted by the exporter.
+//!
+//! Assets reserved on another chain cannot be sent directly to destinations other than
+//! their reserve; `XcmExporter` routes those through the reserve (see `ReserveRouting`).
+//!
+//! Deposit receipts confirming shielded cross-chain deposits to their origin chain go
+//! out through a `SendDepositReceipt` implementation (`XcmReceiptSender` over XCM).
+
+use alloc::vec::Vec;
+use frame::prelude::*;
+use staging_xcm::v5::{send_xcm, AssetId, InteriorLocation, Location, SendError, SendXcm, Xcm};
+
+use crate::xcm_config::{
+	batch_withdrawal_program, deposit_receipt_program, reserve_batch_withdrawal_program,
+	DepositReceipt, WithdrawalRoute,
+};
+
+/// Identifier of an exported withdrawal message
+pub type MessageId = [u8; 32];
+
+/// Maximum length of an encoded beneficiary
+pub const MAX_BENEFICIARY_LEN: u32 = 128;
+
+/// Destination of a cross-chain withdrawal
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum VersionedDestination {
+	/// XCM v5 location
+	V5(Location),
+}
+
+impl From<Location> for VersionedDestination {
+	fn from(location: Location) -> Self {
+		VersionedDestination::V5(location)
+	}
+}
+
+/// Reasons an export can fail
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ExportError {
+	/// The beneficiary bytes are not valid for this exporter
+	InvalidBeneficiary,
+	/// The destination cannot be reached by this exporter
+	Unroutable,
+	/// The message could not be sent
+	SendFailed,
+	/// The amount does not cover the execution fees of the route
+	FeesExceedAmount,
+}
+
+/// Outbound leg of a cross-chain withdrawal
+pub trait ExportWithdrawal {
+	/// Send `amount` of `asset` to `beneficiary` on `destination`
+	///
+	/// Returns the ID of the sent message.
+	fn export(
+		asset: &AssetId,
+		amount: u128,
+		destination: &VersionedDestination,
+		beneficiary: &[u8],
+	) -> Result<MessageId, ExportError>;
+
+	/// Send several withdrawals of `asset` to `destination` in one message
+	///
+	/// `payouts` are (amount, beneficiary) pairs. The batch succeeds or fails as a whole.
+	fn export_batch(
+		asset: &AssetId,
+		destination: &VersionedDestination,
+		payouts: &[(u128, Vec<u8>)],
+	) -> Result<MessageId, ExportError>;
+}
+
+/// Two-hop routing of assets reserved on another chain
+pub trait ReserveRouting {
+	/// Reserve chain of `asset`, or `None` when this chain is the reserve
+	fn reserve_of(asset: &AssetId) -> Option<Location>;
+
+	/// Execution fee charged per hop, in units of `asset`
+	fn hop_fee(asset: &AssetId) -> u128;
+
+	/// Universal location of this chain, used to reanchor assets for each hop
+	fn universal_location() -> InteriorLocation;
+
+	/// Execute the local program starting the withdrawal, returning its message ID
+	fn execute(program: Xcm<()>) -> Result<MessageId, ExportError>;
+}
+
+/// Every asset is reserved on this chain: always route directly
+impl ReserveRouting for () {
+	fn reserve_of(_asset: &AssetId) -> Option<Location> {
+		None
+	}
+
+	fn hop_fee(_asset: &AssetId) -> u128 {
+		0
+	}
+
+	fn universal_location() -> InteriorLocation {
+		InteriorLocation::Here
+	}
+
+	fn execute(_program: Xcm<()>) -> Result<MessageId, ExportError> {
+		Err(ExportError::Unroutable)
+	}
+}
+
+/// Export withdrawals as XCM messages through `Router`
+///
+/// The beneficiary is the SCALE-encoded XCM `Location` of the recipient. Assets with a
+/// remote reserve (per `Routing`) are withdrawn at the reserve and deposited onward,
+/// unless the destination is the reserve itself; the route's fees are taken from
+/// the withdrawn amount.
+pub struct XcmExporter<Router, Routing = ()>(PhantomData<(Router, Routing)>);
+
+impl<Router: SendXcm, Routing: ReserveRouting> ExportWithdrawal for XcmExporter<Router, Routing> {
+	fn export(
+		asset: &AssetId,
+		amount: u128,
+		destination: &VersionedDestination,
+		beneficiary: &[u8],
+	) -> Result<MessageId, ExportError> {
+		Self::export_batch(asset, destination, &[(amount, beneficiary.to_vec())])
+	}
+
+	fn export_batch(
+		asset: &AssetId,
+		destination: &VersionedDestination,
+		payouts: &[(u128, Vec<u8>)],
+	) -> Result<MessageId, ExportError> {
+		let VersionedDestination::V5(destination) = destination;
+		let payouts = payouts
+			.iter()
+			.map(|(amount, beneficiary)| {
+				Location::decode(&mut &beneficiary[..])
+					.map(|beneficiary| (*amount, beneficiary))
+					.map_err(|_| ExportError::InvalidBeneficiary)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let route = WithdrawalRoute::select(Routing::reserve_of(asset).as_ref(), destination);
+		if let WithdrawalRoute::ViaReserve(reserve) = &route {
+			let hop_fee = Routing::hop_fee(asset);
+			let fee_share = route.estimate_fee(hop_fee) / (payouts.len().max(1) as u128);
+			ensure!(
+				payouts.iter().all(|(amount, _)| *amount > fee_share),
+				ExportError::FeesExceedAmount
+			);
+
+			let program = reserve_batch_withdrawal_program(
+				asset.clone(),
+				&payouts,
+				reserve.clone(),
+				destination.clone(),
+				hop_fee,
+				&Routing::universal_location(),
+			)
+			.ok_or(ExportError::Unroutable)?;
+
+			return Routing::execute(program);
+		}
+
+		let message = batch_withdrawal_program(asset.clone(), &payouts, 0);
+
+		let (message_id, _) =
+			send_xcm::<Router>(destination.clone(), message).map_err(send_error)?;
+
+		Ok(message_id)
+	}
+}
+
+/// Map a router error onto an export error
+fn send_error(error: SendError) -> ExportError {
+	match error {
+		SendError::NotApplicable | SendError::Unroutable => ExportError::Unroutable,
+		_ => ExportError::SendFailed,
+	}
+}
+
+/// Outbound leg of deposit receipts
+pub trait SendDepositReceipt {
+	/// Send `receipt` to `origin` as a call to `receipt_call` (pallet index, call index)
+	///
+	/// Returns the ID of the sent message.
+	fn send_receipt(
+		origin: &Location,
+		receipt_call: [u8; 2],
+		receipt: &DepositReceipt,
+	) -> Result<MessageId, ExportError>;
+}
+
+/// No receipts are sent
+impl SendDepositReceipt for () {
+	fn send_receipt(
+		_origin: &Location,
+		_receipt_call: [u8; 2],
+		_receipt: &DepositReceipt,
+	) -> Result<MessageId, ExportError> {
+		Err(ExportError::Unroutable)
+	}
+}
+
+/// Send deposit receipts as unpaid `Transact` messages through `Router`
+///
+/// See `deposit_receipt_program` for the message; the origin chain must admit unpaid
+/// execution from this chain.
+pub struct XcmReceiptSender<Router>(PhantomData<Router>);
+
+impl<Router: SendXcm> SendDepositReceipt for XcmReceiptSender<Router> {
+	fn send_receipt(
+		origin: &Location,
+		receipt_call: [u8; 2],
+		receipt: &DepositReceipt,
+	) -> Result<MessageId, ExportError> {
+		let message = deposit_receipt_program(receipt_call, receipt);
+		let (message_id, _) = send_xcm::<Router>(origin.clone(), message).map_err(send_error)?;
+
+		Ok(message_id)
+	}
+}
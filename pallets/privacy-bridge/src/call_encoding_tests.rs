@@ -0,0 +1,246 @@
+//! Call encoding snapshots
+//!
+//! Wallets encode extrinsics themselves, so a change to a call's parameters (their order,
+//! types or the call's index) breaks them without any error on the chain's side. The
+//! pallet's calls are compared against checked-in SCALE encodings, and every call's index
+//! against a pinned table. A failing test means the encoding changed: if that is
+//! deliberate, bump the runtime's `transaction_version` and update the fixture, so wallets
+//! learn about it.
+
+use crate::{mock::*, Call, CircuitId};
+use crate::hasher::HasherId;
+use crate::xcm_config::CustodyMode;
+use frame::deps::frame_support::traits::{GetCallIndex, GetCallName};
+use frame::testing_prelude::*;
+use sp_core::{hexdisplay::HexDisplay, H256};
+use staging_xcm::v5::{
+	AssetId,
+	Junction::{AccountId32, Parachain},
+	Location,
+};
+
+/// Index of every call; new calls are appended with the next free index
+const CALL_INDICES: &[(&str, u8)] = &[
+	("deposit", 0),
+	("withdraw", 1),
+	// 2: `set_verifying_key`, removed: keys only change through `announce_verifying_key`
+	("register_asset", 3),
+	("deposit_from_xcm", 4),
+	("withdraw_to_parachain", 5),
+	("schedule_decoys", 6),
+	("reclaim_decoy", 7),
+	("set_abandonment_period", 8),
+	("sweep_abandoned", 9),
+	("claim", 10),
+	("withdraw_to_remote", 11),
+	("reclaim_trapped", 12),
+	("set_reserve_location", 13),
+	("queue_withdrawal_to_remote", 14),
+	("retry_withdrawal", 15),
+	("sync_open_channels", 16),
+	("set_destination_fee", 17),
+	("set_receipt_call", 18),
+	// 19: `set_withdrawals_paused`, removed: withdrawals pause through `pause`
+	("refund_unattributed", 20),
+	("announce_verifying_key", 21),
+	("apply_verifying_key", 22),
+	("deposit_split", 23),
+	("withdraw_anchored", 24),
+	("deposit_bundle", 25),
+	("withdraw_bundle", 26),
+	("deposit_with_receipt", 27),
+	// 28: `withdraw_with_root`, folded into `withdraw`
+	("set_note_age_bounds", 29),
+	("top_up_sponsorship_pot", 30),
+	("shielded_swap", 31),
+	("set_destination_account_format", 32),
+	("withdraw_to_parachain_encoded", 33),
+	("register_batcher", 34),
+	("remove_batcher", 35),
+	("submit_commitment_batch", 36),
+	("set_note_backup", 37),
+	("clear_note_backup", 38),
+	("register_alert_tags", 39),
+	("start_tree_rebuild", 40),
+	("rebuild_tree", 41),
+	("post_proving_job", 42),
+	("claim_proving_job", 43),
+	("fulfill_proving_job", 44),
+	("expire_proving_job", 45),
+	("begin_asset_winddown", 46),
+	("open_winddown_refunds", 47),
+	("refund_winddown_note", 48),
+	("finalize_winddown", 49),
+	("withdraw_split", 50),
+	("propose_admin_action", 51),
+	("approve_admin_action", 52),
+	("private_transfer", 53),
+	// 54: `set_transfer_verifying_key`, folded into `announce_verifying_key`
+	("withdraw_join", 55),
+	// 56: `set_join_verifying_key`, folded into `announce_verifying_key`
+	("set_asset_status", 57),
+	("update_asset_min_deposit", 58),
+	("set_denominations", 59),
+	("withdraw_via_relayer", 60),
+	("pause", 61),
+	("unpause", 62),
+];
+
+/// Check `call` encodes to the hex `fixture`, and decodes back from it
+fn assert_encoding(name: &str, call: Call<Test>, fixture: &str) {
+	let encoded = HexDisplay::from(&call.encode()).to_string();
+	assert!(
+		encoded == fixture,
+		"the encoding of `{name}` changed, breaking wallets that build it:\n  \
+		 fixture: {fixture}\n  \
+		 now:     {encoded}\n\
+		 If this is deliberate, bump `transaction_version` and update the fixture."
+	);
+	assert_eq!(Call::<Test>::decode(&mut &call.encode()[..]).unwrap(), call);
+}
+
+/// Decode the call of `call_index` from its encoded `arguments`, or `None`
+fn decode_call(call_index: u8, arguments: impl Encode) -> Option<Call<Test>> {
+	Call::<Test>::decode(&mut &(call_index, arguments).encode()[..]).ok()
+}
+
+fn sibling() -> Location {
+	Location::new(1, [Parachain(2000)])
+}
+
+#[test]
+fn every_call_has_a_pinned_index() {
+	let mut calls: Vec<(&str, u8)> = Call::<Test>::get_call_names()
+		.iter()
+		.copied()
+		.zip(Call::<Test>::get_call_indices().iter().copied())
+		.collect();
+	calls.sort_by_key(|(_, index)| *index);
+
+	assert_eq!(
+		calls, CALL_INDICES,
+		"call indices changed: pin new calls with `#[pallet::call_index]` to the next free \
+		 index and list them in `CALL_INDICES`; never renumber existing calls"
+	);
+}
+
+#[test]
+fn deposit_encoding_is_stable() {
+	assert_encoding(
+		"deposit",
+		Call::deposit { amount: 1000, asset_id: 1, note_key: [7u8; 32] },
+		"00e8030000000000000000000000000000010000000707070707070707070707070707070707070707070707\
+		 070707070707070707",
+	);
+}
+
+#[test]
+fn withdraw_encoding_is_stable() {
+	assert_encoding(
+		"withdraw",
+		Call::withdraw {
+			nullifier: H256::repeat_byte(1),
+			commitment: H256::repeat_byte(2),
+			merkle_root: H256::repeat_byte(3),
+			amount: 1000,
+			asset_id: 1,
+			proof: BoundedVec::truncate_from(vec![7u8; 128]),
+			recipient: Some(5),
+		},
+		"0101010101010101010101010101010101010101010101010101010101010101010202020202020202020202\
+		 0202020202020202020202020202020202020202020303030303030303030303030303030303030303030303\
+		 030303030303030303e803000000000000000000000000000001000000010207070707070707070707070707\
+		 0707070707070707070707070707070707070707070707070707070707070707070707070707070707070707\
+		 0707070707070707070707070707070707070707070707070707070707070707070707070707070707070707\
+		 070707070707070707070707070707070707070707070707070707010500000000000000",
+	);
+}
+
+#[test]
+fn announce_verifying_key_encoding_is_stable() {
+	assert_encoding(
+		"announce_verifying_key",
+		Call::announce_verifying_key {
+			circuit: CircuitId::Withdraw,
+			vk_bytes: BoundedVec::truncate_from(vec![0xab; 4]),
+			hasher: HasherId::Blake2,
+		},
+		"150010abababab00",
+	);
+}
+
+#[test]
+fn register_asset_encoding_is_stable() {
+	assert_encoding(
+		"register_asset",
+		Call::register_asset {
+			asset_id: AssetId(Location::parent()),
+			min_deposit: 100,
+			custody_mode: CustodyMode::Pot,
+			remote_min_balance: 5,
+		},
+		"030100640000000000000000000000000000000005000000000000000000000000000000",
+	);
+}
+
+#[test]
+fn deposit_from_xcm_encoding_is_stable() {
+	assert_encoding(
+		"deposit_from_xcm",
+		Call::deposit_from_xcm {
+			asset_id: AssetId(Location::parent()),
+			amount: 1000,
+			origin_location: sibling(),
+			note_key: [7u8; 32],
+		},
+		"040100e8030000000000000000000000000000010100411f0707070707070707070707070707070707070707\
+		 070707070707070707070707",
+	);
+}
+
+#[test]
+fn withdraw_to_parachain_encoding_is_stable() {
+	assert_encoding(
+		"withdraw_to_parachain",
+		Call::withdraw_to_parachain {
+			nullifier: H256::repeat_byte(1),
+			commitment: H256::repeat_byte(2),
+			merkle_root: H256::repeat_byte(3),
+			asset_id: 1,
+			amount: 1000,
+			destination: sibling(),
+			beneficiary: Location::new(0, [AccountId32 { network: None, id: [5u8; 32] }]),
+			max_fee: 10,
+			proof: BoundedVec::truncate_from(vec![7u8; 4]),
+		},
+		"0501010101010101010101010101010101010101010101010101010101010101010202020202020202020202\
+		 0202020202020202020202020202020202020202020303030303030303030303030303030303030303030303\
+		 03030303030303030301000000e8030000000000000000000000000000010100411f00010100050505050505\
+		 05050505050505050505050505050505050505050505050505050a0000000000000000000000000000001007\
+		 070707",
+	);
+}
+
+#[test]
+fn proof_over_max_proof_size_fails_to_decode() {
+	let max = crate::ProofOf::<Test>::bound();
+	let withdraw = |proof_len: usize| {
+		let arguments =
+			(H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3), 1000u128, 1u32);
+		decode_call(1, (arguments, vec![7u8; proof_len], None::<u64>))
+	};
+
+	assert!(matches!(withdraw(max), Some(Call::withdraw { proof, .. }) if proof.len() == max));
+	assert_eq!(withdraw(max + 1), None);
+}
+
+#[test]
+fn oversized_verifying_key_fails_to_decode() {
+	let max = crate::VerifyingKeyBytes::bound();
+	let announce_key = |key_len: usize| {
+		decode_call(21, (CircuitId::Withdraw, vec![0xab; key_len], HasherId::Blake2))
+	};
+
+	assert!(announce_key(max).is_some());
+	assert_eq!(announce_key(max + 1), None);
+}
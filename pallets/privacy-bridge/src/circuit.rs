@@ -4,36 +4,1084 @@
 //! 1. They own a commitment (without revealing which one)
 //! 2. They haven't spent it before (nullifier is fresh)
 //! 3. The amounts balance correctly
+//! 4. The commitment is a leaf of the commitment tree (the anonymity set)
 //!
-//! Week 2 MVP: Simple ownership proof
-//! Week 3+: Will add merkle tree membership proof
+//! Every circuit hashes notes and the tree with a `CircuitHasher`, the in-circuit twin of
+//! a `NoteHasher`. A setup is made for one hasher, and its keys only verify proofs over
+//! notes and roots of that hasher, which `SetupMetadata::hasher` records. Runtimes hash
+//! with `PoseidonHasher`; the XOR-based `SimpleHasher` only exists for tests and with the
+//! `insecure-test-hash` feature.
 
-use ark_r1cs_std::prelude::*;
+use ark_ff::{Field, One};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
 use ark_relations::r1cs::{
 	ConstraintSynthesizer, ConstraintSystemRef, SynthesisError,
 };
 use ark_bn254::Fr as ScalarField; // BN254 scalar field
 use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
 
-/// Circuit for proving ownership of a commitment and generating a valid nullifier
+#[cfg(any(test, feature = "insecure-test-hash"))]
+use crate::hasher::SimpleHasher;
+use crate::hasher::{NoteHasher, PoseidonHasher};
+use crate::merkle_tree::TREE_DEPTH;
+use crate::poseidon::poseidon_hash_gadget;
+use crate::primitives::{bundle_assets, BUNDLE_COMMITMENT_TAG, MAX_BUNDLE_SIZE, RESERVED_HASH};
+
+/// Version of the withdrawal circuits
+///
+/// Bumped whenever their constraints or public inputs change: verifying keys generated for
+/// an older version cannot verify proofs of the current circuits.
+pub const CIRCUIT_VERSION: u32 = 10;
+
+/// A `NoteHasher` the circuits can evaluate, hashing exactly as it does natively
+///
+/// Values are the little-endian bytes the hasher takes and returns, as public inputs
+/// carry them.
+pub trait CircuitHasher: NoteHasher {
+	/// `NoteHasher::hash_commitment` of `amount`, `asset_id` and `randomness`
+	fn commitment_gadget(
+		amount: &[UInt8<ScalarField>],
+		asset_id: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError>;
+
+	/// `NoteHasher::hash_bundle_commitment` of `assets`, encoded as
+	/// `primitives::bundle_assets`, and `randomness`
+	fn bundle_commitment_gadget(
+		assets: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError>;
+
+	/// `NoteHasher::hash_nullifier` of `commitment` and `secret`
+	fn nullifier_gadget(
+		commitment: &[UInt8<ScalarField>],
+		secret: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError>;
+
+	/// `NoteHasher::hash_pair` of `left` and `right`
+	fn pair_gadget(
+		left: &[UInt8<ScalarField>],
+		right: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError>;
+}
+
+/// Circuit for proving ownership of a commitment in the tree and generating a valid nullifier
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifier: Hash(commitment || secret) - prevents double-spending
+/// - commitment: The commitment being spent
+/// - merkle_root: Root of the commitment tree holding it
+/// - asset_id: The asset type, so a note only pays out in the asset it was shielded as
+/// - amount: The note's value, so a withdrawal pays out exactly what the note holds
+/// - recipient_hash: Hash of who is paid (`primitives::recipient_hash`), so a copied proof
+///   cannot redirect the withdrawal; for a relayed withdrawal, of the recipient, relayer
+///   and fee (`primitives::relayed_recipient_hash`), so the fee split cannot change either
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - tweak: Tweak of the note's randomness, zero but for XCM notes
+///   (`primitives::note_randomness`)
+/// - secret: Secret key the note's randomness and nullifier derive from
+/// - merkle_path: Sibling hashes from leaf to root (`TREE_DEPTH` entries)
+/// - path_indices: Position at each level (`true` = node is the right child)
+#[derive(Clone)]
+pub struct PrivateTransferCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The nullifier (prevents double-spend)
+	pub nullifier: Option<Vec<u8>>,
+
+	/// The commitment being spent
+	pub commitment: Option<Vec<u8>>,
+
+	/// The merkle root
+	pub merkle_root: Option<Vec<u8>>,
+
+	/// Asset ID of the note
+	pub asset_id: Option<u32>,
+
+	/// Value of the note, paid out by the withdrawal
+	pub amount: Option<u128>,
+
+	/// Hash of the withdrawal's recipient
+	pub recipient_hash: Option<Vec<u8>>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// Tweak of the randomness used in commitment (hidden!)
+	pub tweak: Option<[u8; 32]>,
+
+	/// Secret for nullifier generation (hidden!)
+	pub secret: Option<[u8; 32]>,
+
+	/// Merkle path siblings (hidden!)
+	pub merkle_path: Option<Vec<[u8; 32]>>,
+
+	/// Merkle path positions (hidden!)
+	pub path_indices: Option<Vec<bool>>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> PrivateTransferCircuit<H> {
+	/// Create a new circuit for proof generation
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		nullifier: Vec<u8>,
+		commitment: Vec<u8>,
+		merkle_root: Vec<u8>,
+		recipient_hash: Vec<u8>,
+		amount: u128,
+		asset_id: u32,
+		tweak: [u8; 32],
+		secret: [u8; 32],
+		merkle_path: Vec<[u8; 32]>,
+		path_indices: Vec<bool>,
+	) -> Self {
+		Self {
+			nullifier: Some(nullifier),
+			commitment: Some(commitment),
+			merkle_root: Some(merkle_root),
+			asset_id: Some(asset_id),
+			amount: Some(amount),
+			recipient_hash: Some(recipient_hash),
+			tweak: Some(tweak),
+			secret: Some(secret),
+			merkle_path: Some(merkle_path),
+			path_indices: Some(path_indices),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			nullifier: None,
+			commitment: None,
+			merkle_root: None,
+			asset_id: None,
+			amount: None,
+			recipient_hash: None,
+			tweak: None,
+			secret: None,
+			merkle_path: None,
+			path_indices: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for PrivateTransferCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// The circuit shape must not depend on the witness
+		let (merkle_path, path_indices) = match (self.merkle_path, self.path_indices) {
+			(Some(path), Some(indices)) => (path, indices),
+			_ if cs.is_in_setup_mode() => (vec![[0u8; 32]; TREE_DEPTH], vec![false; TREE_DEPTH]),
+			_ => return Err(SynthesisError::AssignmentMissing),
+		};
+		if merkle_path.len() != TREE_DEPTH || path_indices.len() != TREE_DEPTH {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+
+		// === ALLOCATE PUBLIC INPUTS ===
+		// Week 3: 32-byte inputs, zeros for the empty circuit of a setup
+		let nullifier_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.nullifier, 32)?
+		)?;
+
+		let commitment_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.commitment, 32)?
+		)?;
+
+		let root_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.merkle_root, 32)?
+		)?;
+
+		let asset_id_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.asset_id.map(|id| id.to_le_bytes().to_vec()), 4)?
+		)?;
+
+		let amount_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.amount.map(|amount| amount.to_le_bytes().to_vec()), 16)?
+		)?;
+
+		// Takes part in no other constraint: allocating it as an input, bit-decomposed,
+		// is enough for the proof to hold only for this recipient
+		UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, self.recipient_hash, 32)?)?;
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+
+		let tweak_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.tweak)?
+		)?;
+
+		let secret_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.secret)?
+		)?;
+
+		// === CONSTRAINT 1: Verify commitment is correctly formed ===
+		// commitment = Hash(amount || asset_id || randomness), the randomness derived
+		// from the secret
+		let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+		let computed_commitment =
+			H::commitment_gadget(&amount_var, &asset_id_var, &randomness_var)?;
+
+		// Enforce: computed_commitment == commitment
+		computed_commitment.enforce_equal(&commitment_var)?;
+
+		// === CONSTRAINT 2: Verify nullifier is correctly formed ===
+		// nullifier = Hash(commitment || secret)
+		let computed_nullifier = H::nullifier_gadget(&commitment_var, &secret_var)?;
+
+		// Enforce: computed_nullifier == nullifier
+		computed_nullifier.enforce_equal(&nullifier_var)?;
+
+		// === CONSTRAINT 3: The commitment hashes up to the public root ===
+		let computed_root =
+			merkle_root_gadget::<H>(cs, commitment_var, &merkle_path, &path_indices)?;
+		computed_root.enforce_equal(&root_var)?;
+
+		// === SUCCESS ===
+		// If we reach here, the prover knows:
+		// 1. The amount and the secret that create the commitment
+		// 2. The nullifier of that secret, the only one the note has
+		// 3. The path placing the commitment in the tree
+		// But the verifier learns NOTHING except that the proof is valid!
+
+		Ok(())
+	}
+}
+
+/// Circuit for proving ownership of a multi-asset (bundle) note
+///
+/// Like `PrivateTransferCircuit`, but the assets are public: the pallet pays each of them
+/// out, so the proof must bind the payout to the note's contents.
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifier: Hash(commitment || secret)
+/// - commitment: The bundle commitment being spent
+/// - assets: `primitives::bundle_assets`, the `(asset_id, amount)` pairs padded to
+///   `MAX_BUNDLE_SIZE`
+/// - recipient_hash: Hash of who is paid (`primitives::recipient_hash`), so a copied proof
+///   cannot redirect the withdrawal
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - tweak: Tweak of the note's randomness, zero but for XCM notes
+///   (`primitives::note_randomness`)
+/// - secret: Secret key the note's randomness and nullifier derive from
+#[derive(Clone)]
+pub struct BundleWithdrawalCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The nullifier (prevents double-spend)
+	pub nullifier: Option<Vec<u8>>,
+
+	/// The commitment being spent
+	pub commitment: Option<Vec<u8>>,
+
+	/// Assets of the note, in order
+	pub assets: Option<Vec<(u32, u128)>>,
+
+	/// Hash of the withdrawal's recipient
+	pub recipient_hash: Option<Vec<u8>>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// Tweak of the randomness used in commitment (hidden!)
+	pub tweak: Option<[u8; 32]>,
+
+	/// Secret for nullifier generation (hidden!)
+	pub secret: Option<[u8; 32]>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> BundleWithdrawalCircuit<H> {
+	/// Create a new circuit for proof generation
+	pub fn new(
+		nullifier: Vec<u8>,
+		commitment: Vec<u8>,
+		assets: Vec<(u32, u128)>,
+		recipient_hash: Vec<u8>,
+		tweak: [u8; 32],
+		secret: [u8; 32],
+	) -> Self {
+		Self {
+			nullifier: Some(nullifier),
+			commitment: Some(commitment),
+			assets: Some(assets),
+			recipient_hash: Some(recipient_hash),
+			tweak: Some(tweak),
+			secret: Some(secret),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			nullifier: None,
+			commitment: None,
+			assets: None,
+			recipient_hash: None,
+			tweak: None,
+			secret: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for BundleWithdrawalCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// The circuit shape must not depend on the witness
+		let assets = witness(&cs, self.assets)?;
+		if assets.len() > MAX_BUNDLE_SIZE as usize {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+
+		// === ALLOCATE PUBLIC INPUTS ===
+		let nullifier_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.nullifier, 32)?
+		)?;
+
+		let commitment_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.commitment, 32)?
+		)?;
+
+		let assets_var = UInt8::new_input_vec(cs.clone(), &bundle_assets(&assets))?;
+
+		// Bound to the proof as in `PrivateTransferCircuit`, by allocation alone
+		UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, self.recipient_hash, 32)?)?;
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+		let tweak_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.tweak)?
+		)?;
+
+		let secret_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.secret)?
+		)?;
+
+		// === CONSTRAINT 1: Verify commitment is correctly formed ===
+		// commitment = Hash(BUNDLE_COMMITMENT_TAG || assets || randomness)
+		let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+		let computed_commitment = H::bundle_commitment_gadget(&assets_var, &randomness_var)?;
+		computed_commitment.enforce_equal(&commitment_var)?;
+
+		// === CONSTRAINT 2: Verify nullifier is correctly formed ===
+		// nullifier = Hash(commitment || secret)
+		let computed_nullifier = H::nullifier_gadget(&commitment_var, &secret_var)?;
+		computed_nullifier.enforce_equal(&nullifier_var)?;
+
+		Ok(())
+	}
+}
+
+/// Circuit for spending a note into a new note for another owner (shielded swaps)
+///
+/// Like `PrivateTransferCircuit`, and binds the output note: it holds the spent note's
+/// amount and asset under randomness chosen by the recipient, so the value moves to
+/// them without being revealed or leaving the pool.
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifier: Hash(commitment || secret)
+/// - commitment: The commitment being spent
+/// - output_commitment: Hash(amount || asset_id || output_randomness)
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - amount, asset_id, tweak: The spent note
+/// - secret: Secret key the note's randomness and nullifier derive from
+/// - output_randomness: Randomness of the recipient's note
+#[derive(Clone)]
+pub struct SwapCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The nullifier (prevents double-spend)
+	pub nullifier: Option<Vec<u8>>,
+
+	/// The commitment being spent
+	pub commitment: Option<Vec<u8>>,
+
+	/// The commitment of the recipient's note
+	pub output_commitment: Option<Vec<u8>>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// The amount (hidden!)
+	pub amount: Option<u128>,
+
+	/// Asset ID (hidden!)
+	pub asset_id: Option<u32>,
+
+	/// Tweak of the randomness used in commitment (hidden!)
+	pub tweak: Option<[u8; 32]>,
+
+	/// Secret for nullifier generation (hidden!)
+	pub secret: Option<[u8; 32]>,
+
+	/// Randomness of the recipient's note (hidden!)
+	pub output_randomness: Option<[u8; 32]>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> SwapCircuit<H> {
+	/// Create a new circuit for proof generation
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		nullifier: Vec<u8>,
+		commitment: Vec<u8>,
+		output_commitment: Vec<u8>,
+		amount: u128,
+		asset_id: u32,
+		tweak: [u8; 32],
+		secret: [u8; 32],
+		output_randomness: [u8; 32],
+	) -> Self {
+		Self {
+			nullifier: Some(nullifier),
+			commitment: Some(commitment),
+			output_commitment: Some(output_commitment),
+			amount: Some(amount),
+			asset_id: Some(asset_id),
+			tweak: Some(tweak),
+			secret: Some(secret),
+			output_randomness: Some(output_randomness),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			nullifier: None,
+			commitment: None,
+			output_commitment: None,
+			amount: None,
+			asset_id: None,
+			tweak: None,
+			secret: None,
+			output_randomness: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for SwapCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// === ALLOCATE PUBLIC INPUTS ===
+		let nullifier_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.nullifier, 32)?
+		)?;
+
+		let commitment_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.commitment, 32)?
+		)?;
+
+		let output_commitment_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.output_commitment, 32)?
+		)?;
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+		let amount_var =
+			UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.amount)?.to_le_bytes())?;
+		let asset_id_var =
+			UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.asset_id)?.to_le_bytes())?;
+		let tweak_var = UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.tweak)?)?;
+		let secret_var = UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.secret)?)?;
+		let output_randomness_var =
+			UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.output_randomness)?)?;
+
+		// === CONSTRAINT 1: commitment = Hash(amount || asset_id || randomness) ===
+		let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+		H::commitment_gadget(&amount_var, &asset_id_var, &randomness_var)?
+			.enforce_equal(&commitment_var)?;
+
+		// === CONSTRAINT 2: nullifier = Hash(commitment || secret) ===
+		H::nullifier_gadget(&commitment_var, &secret_var)?.enforce_equal(&nullifier_var)?;
+
+		// === CONSTRAINT 3: output_commitment = Hash(amount || asset_id || output_randomness) ===
+		// Same amount and asset: the swap moves value, it never creates any
+		H::commitment_gadget(&amount_var, &asset_id_var, &output_randomness_var)?
+			.enforce_equal(&output_commitment_var)?;
+
+		Ok(())
+	}
+}
+
+/// Circuit for splitting a note of the tree into two new notes (private transfers)
+///
+/// Like `PrivateTransferCircuit`, without revealing the spent commitment: the proof
+/// places it in the tree at `merkle_root` instead. Both output notes hold the spent
+/// note's asset, and their amounts add up to its amount, so a transfer never creates
+/// value.
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifier: Hash(commitment || secret)
+/// - merkle_root: Root of the commitment tree holding the spent note
+/// - asset_id: Asset of all three notes
+/// - output_commitments: Hash(output_amount || asset_id || output_randomness), twice
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - amount, tweak: The spent note
+/// - secret: Secret key the note's randomness and nullifier derive from
+/// - merkle_path, path_indices: Position of the spent note in the tree
+/// - output_amounts, output_randomness: The output notes
+#[derive(Clone)]
+pub struct TransferCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The nullifier (prevents double-spend)
+	pub nullifier: Option<Vec<u8>>,
+
+	/// The merkle root
+	pub merkle_root: Option<Vec<u8>>,
+
+	/// Asset ID of the notes
+	pub asset_id: Option<u32>,
+
+	/// Commitments of the two output notes
+	pub output_commitments: Option<[Vec<u8>; 2]>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// The amount (hidden!)
+	pub amount: Option<u128>,
+
+	/// Tweak of the randomness used in commitment (hidden!)
+	pub tweak: Option<[u8; 32]>,
+
+	/// Secret for nullifier generation (hidden!)
+	pub secret: Option<[u8; 32]>,
+
+	/// Merkle path siblings (hidden!)
+	pub merkle_path: Option<Vec<[u8; 32]>>,
+
+	/// Merkle path positions (hidden!)
+	pub path_indices: Option<Vec<bool>>,
+
+	/// Amounts of the output notes (hidden!)
+	pub output_amounts: Option<[u128; 2]>,
+
+	/// Randomness of the output notes (hidden!)
+	pub output_randomness: Option<[[u8; 32]; 2]>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> TransferCircuit<H> {
+	/// Create a new circuit for proof generation
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		nullifier: Vec<u8>,
+		merkle_root: Vec<u8>,
+		asset_id: u32,
+		output_commitments: [Vec<u8>; 2],
+		amount: u128,
+		tweak: [u8; 32],
+		secret: [u8; 32],
+		merkle_path: Vec<[u8; 32]>,
+		path_indices: Vec<bool>,
+		output_amounts: [u128; 2],
+		output_randomness: [[u8; 32]; 2],
+	) -> Self {
+		Self {
+			nullifier: Some(nullifier),
+			merkle_root: Some(merkle_root),
+			asset_id: Some(asset_id),
+			output_commitments: Some(output_commitments),
+			amount: Some(amount),
+			tweak: Some(tweak),
+			secret: Some(secret),
+			merkle_path: Some(merkle_path),
+			path_indices: Some(path_indices),
+			output_amounts: Some(output_amounts),
+			output_randomness: Some(output_randomness),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			nullifier: None,
+			merkle_root: None,
+			asset_id: None,
+			output_commitments: None,
+			amount: None,
+			tweak: None,
+			secret: None,
+			merkle_path: None,
+			path_indices: None,
+			output_amounts: None,
+			output_randomness: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for TransferCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// The circuit shape must not depend on the witness
+		let (merkle_path, path_indices) = match (self.merkle_path, self.path_indices) {
+			(Some(path), Some(indices)) => (path, indices),
+			_ if cs.is_in_setup_mode() => (vec![[0u8; 32]; TREE_DEPTH], vec![false; TREE_DEPTH]),
+			_ => return Err(SynthesisError::AssignmentMissing),
+		};
+		if merkle_path.len() != TREE_DEPTH || path_indices.len() != TREE_DEPTH {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+		let [first_output, second_output] = match self.output_commitments {
+			Some(commitments) => commitments.map(Some),
+			None => [None, None],
+		};
+
+		// === ALLOCATE PUBLIC INPUTS ===
+		let nullifier_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.nullifier, 32)?
+		)?;
+
+		let root_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.merkle_root, 32)?
+		)?;
+
+		let asset_id_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.asset_id.map(|id| id.to_le_bytes().to_vec()), 4)?
+		)?;
+
+		let output_vars = [
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, first_output, 32)?)?,
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, second_output, 32)?)?,
+		];
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+		let amount_var =
+			UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.amount)?.to_le_bytes())?;
+		let tweak_var = UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.tweak)?)?;
+		let secret_var = UInt8::new_witness_vec(cs.clone(), &witness(&cs, self.secret)?)?;
+		let output_amounts = witness(&cs, self.output_amounts)?;
+		let output_randomness = witness(&cs, self.output_randomness)?;
+
+		// === CONSTRAINT 1: commitment = Hash(amount || asset_id || randomness) ===
+		let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+		let commitment_var = H::commitment_gadget(&amount_var, &asset_id_var, &randomness_var)?;
+
+		// === CONSTRAINT 2: nullifier = Hash(commitment || secret) ===
+		H::nullifier_gadget(&commitment_var, &secret_var)?.enforce_equal(&nullifier_var)?;
+
+		// === CONSTRAINT 3: The commitment hashes up to the public root ===
+		let computed_root =
+			merkle_root_gadget::<H>(cs.clone(), commitment_var, &merkle_path, &path_indices)?;
+		computed_root.enforce_equal(&root_var)?;
+
+		// === CONSTRAINT 4: output = Hash(output_amount || asset_id || output_randomness) ===
+		let mut output_total = FpVar::zero();
+		let outputs = output_vars.iter().zip(output_amounts).zip(output_randomness);
+		for ((output_var, output_amount), randomness) in outputs {
+			let output_amount_var =
+				UInt8::new_witness_vec(cs.clone(), &output_amount.to_le_bytes())?;
+			let output_randomness_var = UInt8::new_witness_vec(cs.clone(), &randomness)?;
+
+			H::commitment_gadget(&output_amount_var, &asset_id_var, &output_randomness_var)?
+				.enforce_equal(output_var)?;
+
+			output_total += le_bytes_to_field(&output_amount_var)?;
+		}
+
+		// === CONSTRAINT 5: The output amounts add up to the spent amount ===
+		// Both are below 2^128, so their sum cannot wrap around the field
+		output_total.enforce_equal(&le_bytes_to_field(&amount_var)?)?;
+
+		Ok(())
+	}
+}
+
+/// Circuit for withdrawing two notes of the tree as one amount (joined withdrawals)
+///
+/// Two spends of `PrivateTransferCircuit` against the same root: the public amount is the
+/// sum of both notes' amounts, so small deposits can leave the pool together. The two
+/// commitments must differ, so one note cannot be counted twice.
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifiers: Hash(commitment || secret) of each spent note
+/// - commitments: The two spent notes, which are marked spent
+/// - merkle_root: Root of the commitment tree holding both notes
+/// - asset_id: Asset of both notes
+/// - amount: Sum of the notes' amounts, paid out
+/// - recipient_hash: Hash of who is paid (`primitives::recipient_hash`), so a copied proof
+///   cannot redirect the withdrawal
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - input_amounts, tweaks: The spent notes
+/// - secrets: Secret keys the notes' randomness and nullifiers derive from
+/// - merkle_paths, path_indices: Positions of the spent notes in the tree
+#[derive(Clone)]
+pub struct JoinSplitCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The nullifiers of both notes (prevent double-spend)
+	pub nullifiers: Option<[Vec<u8>; 2]>,
+
+	/// The commitments of both notes, which must differ
+	pub commitments: Option<[Vec<u8>; 2]>,
+
+	/// The merkle root
+	pub merkle_root: Option<Vec<u8>>,
+
+	/// Asset ID of the notes
+	pub asset_id: Option<u32>,
+
+	/// The withdrawn amount
+	pub amount: Option<u128>,
+
+	/// Hash of the withdrawal's recipient
+	pub recipient_hash: Option<Vec<u8>>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// Amounts of the notes (hidden!)
+	pub input_amounts: Option<[u128; 2]>,
+
+	/// Tweaks of the randomness used in the commitments (hidden!)
+	pub tweaks: Option<[[u8; 32]; 2]>,
+
+	/// Secrets for nullifier generation (hidden!)
+	pub secrets: Option<[[u8; 32]; 2]>,
+
+	/// Merkle path siblings of each note (hidden!)
+	pub merkle_paths: Option<[Vec<[u8; 32]>; 2]>,
+
+	/// Merkle path positions of each note (hidden!)
+	pub path_indices: Option<[Vec<bool>; 2]>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> JoinSplitCircuit<H> {
+	/// Create a new circuit for proof generation
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		nullifiers: [Vec<u8>; 2],
+		commitments: [Vec<u8>; 2],
+		merkle_root: Vec<u8>,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: Vec<u8>,
+		input_amounts: [u128; 2],
+		tweaks: [[u8; 32]; 2],
+		secrets: [[u8; 32]; 2],
+		merkle_paths: [Vec<[u8; 32]>; 2],
+		path_indices: [Vec<bool>; 2],
+	) -> Self {
+		Self {
+			nullifiers: Some(nullifiers),
+			commitments: Some(commitments),
+			merkle_root: Some(merkle_root),
+			asset_id: Some(asset_id),
+			amount: Some(amount),
+			recipient_hash: Some(recipient_hash),
+			input_amounts: Some(input_amounts),
+			tweaks: Some(tweaks),
+			secrets: Some(secrets),
+			merkle_paths: Some(merkle_paths),
+			path_indices: Some(path_indices),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			nullifiers: None,
+			commitments: None,
+			merkle_root: None,
+			asset_id: None,
+			amount: None,
+			recipient_hash: None,
+			input_amounts: None,
+			tweaks: None,
+			secrets: None,
+			merkle_paths: None,
+			path_indices: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for JoinSplitCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// The circuit shape must not depend on the witness
+		let (merkle_paths, path_indices) = match (self.merkle_paths, self.path_indices) {
+			(Some(paths), Some(indices)) => (paths, indices),
+			_ if cs.is_in_setup_mode() => (
+				[vec![[0u8; 32]; TREE_DEPTH], vec![[0u8; 32]; TREE_DEPTH]],
+				[vec![false; TREE_DEPTH], vec![false; TREE_DEPTH]],
+			),
+			_ => return Err(SynthesisError::AssignmentMissing),
+		};
+		let mut shapes = merkle_paths.iter().zip(path_indices.iter());
+		if shapes.any(|(path, indices)| path.len() != TREE_DEPTH || indices.len() != TREE_DEPTH) {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+		let [first_nullifier, second_nullifier] = match self.nullifiers {
+			Some(nullifiers) => nullifiers.map(Some),
+			None => [None, None],
+		};
+		let [first_commitment, second_commitment] = match self.commitments {
+			Some(commitments) => commitments.map(Some),
+			None => [None, None],
+		};
+
+		// === ALLOCATE PUBLIC INPUTS ===
+		let nullifier_vars = [
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, first_nullifier, 32)?)?,
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, second_nullifier, 32)?)?,
+		];
+
+		let commitment_vars = [
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, first_commitment, 32)?)?,
+			UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, second_commitment, 32)?)?,
+		];
+		// One note counted twice would pay out twice its value
+		commitment_vars[0].is_eq(&commitment_vars[1])?.enforce_equal(&Boolean::FALSE)?;
+
+		let root_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.merkle_root, 32)?
+		)?;
+
+		let asset_id_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.asset_id.map(|id| id.to_le_bytes().to_vec()), 4)?
+		)?;
+
+		let amount_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.amount.map(|amount| amount.to_le_bytes().to_vec()), 16)?
+		)?;
+
+		// Bound to the proof as in `PrivateTransferCircuit`, by allocation alone
+		UInt8::new_input_vec(cs.clone(), &input_bytes(&cs, self.recipient_hash, 32)?)?;
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+		let input_amounts = witness(&cs, self.input_amounts)?;
+		let tweaks = witness(&cs, self.tweaks)?;
+		let secrets = witness(&cs, self.secrets)?;
+
+		let mut input_total = FpVar::zero();
+		let inputs = nullifier_vars
+			.iter()
+			.zip(commitment_vars.iter())
+			.zip(input_amounts)
+			.zip(tweaks)
+			.zip(secrets)
+			.zip(merkle_paths.iter().zip(path_indices.iter()));
+		for (((((nullifier_var, commitment), input_amount), tweak), secret), (path, indices)) in
+			inputs
+		{
+			let input_amount_var =
+				UInt8::new_witness_vec(cs.clone(), &input_amount.to_le_bytes())?;
+			let tweak_var = UInt8::new_witness_vec(cs.clone(), &tweak)?;
+			let secret_var = UInt8::new_witness_vec(cs.clone(), &secret)?;
+
+			// === CONSTRAINT 1: commitment = Hash(amount || asset_id || randomness) ===
+			let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+			let commitment_var =
+				H::commitment_gadget(&input_amount_var, &asset_id_var, &randomness_var)?;
+			commitment_var.enforce_equal(commitment)?;
+
+			// === CONSTRAINT 2: nullifier = Hash(commitment || secret) ===
+			H::nullifier_gadget(&commitment_var, &secret_var)?.enforce_equal(nullifier_var)?;
+
+			// === CONSTRAINT 3: The commitment hashes up to the public root ===
+			let computed_root = merkle_root_gadget::<H>(cs.clone(), commitment_var, path, indices)?;
+			computed_root.enforce_equal(&root_var)?;
+
+			input_total += le_bytes_to_field(&input_amount_var)?;
+		}
+
+		// === CONSTRAINT 4: The notes' amounts add up to the withdrawn amount ===
+		// Both are below 2^128, so their sum cannot wrap around the field
+		input_total.enforce_equal(&le_bytes_to_field(&amount_var)?)?;
+
+		Ok(())
+	}
+}
+
+/// Circuit proving that a commitment is a leaf of the commitment tree
+///
+/// Used for delegated proving: a proving service proves membership with this circuit
+/// while the client proves ownership with `OwnershipCircuit`. Both proofs expose the same
+/// `link_tag = Hash(commitment || link_blinding)`, which ties them to one note without
+/// putting the commitment itself in the public inputs.
 ///
 /// PUBLIC INPUTS (visible on-chain):
-/// - nullifier: Hash(commitment || secret) - prevents double-spending
-/// - commitment: The commitment being spent
+/// - merkle_root: Root of the commitment tree
+/// - link_tag: Hash(commitment || link_blinding)
+///
+/// PRIVATE INPUTS (witness - never revealed):
+/// - commitment: The leaf being proven
+/// - link_blinding: Blinding used in the link tag
+/// - merkle_path: Sibling hashes from leaf to root (`TREE_DEPTH` entries)
+/// - path_indices: Position at each level (`true` = node is the right child)
+#[derive(Clone)]
+pub struct MembershipCircuit<H> {
+	// === PUBLIC INPUTS ===
+	/// The merkle root
+	pub merkle_root: Option<Vec<u8>>,
+
+	/// Tag linking this proof to an ownership proof
+	pub link_tag: Option<Vec<u8>>,
+
+	// === PRIVATE INPUTS (WITNESS) ===
+	/// The commitment (hidden!)
+	pub commitment: Option<[u8; 32]>,
+
+	/// Link tag blinding (hidden!)
+	pub link_blinding: Option<[u8; 32]>,
+
+	/// Merkle path siblings (hidden!)
+	pub merkle_path: Option<Vec<[u8; 32]>>,
+
+	/// Merkle path positions (hidden!)
+	pub path_indices: Option<Vec<bool>>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
+}
+
+impl<H> MembershipCircuit<H> {
+	/// Create a new circuit for proof generation
+	pub fn new(
+		merkle_root: Vec<u8>,
+		link_tag: Vec<u8>,
+		commitment: [u8; 32],
+		link_blinding: [u8; 32],
+		merkle_path: Vec<[u8; 32]>,
+		path_indices: Vec<bool>,
+	) -> Self {
+		Self {
+			merkle_root: Some(merkle_root),
+			link_tag: Some(link_tag),
+			commitment: Some(commitment),
+			link_blinding: Some(link_blinding),
+			merkle_path: Some(merkle_path),
+			path_indices: Some(path_indices),
+			hasher: PhantomData,
+		}
+	}
+
+	/// Create an empty circuit (for setup)
+	pub fn empty() -> Self {
+		Self {
+			merkle_root: None,
+			link_tag: None,
+			commitment: None,
+			link_blinding: None,
+			merkle_path: None,
+			path_indices: None,
+			hasher: PhantomData,
+		}
+	}
+}
+
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for MembershipCircuit<H> {
+	fn generate_constraints(
+		self,
+		cs: ConstraintSystemRef<ScalarField>,
+	) -> Result<(), SynthesisError> {
+		// The circuit shape must not depend on the witness
+		let (merkle_path, path_indices) = match (self.merkle_path, self.path_indices) {
+			(Some(path), Some(indices)) => (path, indices),
+			_ if cs.is_in_setup_mode() => (vec![[0u8; 32]; TREE_DEPTH], vec![false; TREE_DEPTH]),
+			_ => return Err(SynthesisError::AssignmentMissing),
+		};
+		if merkle_path.len() != TREE_DEPTH || path_indices.len() != TREE_DEPTH {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+
+		// === ALLOCATE PUBLIC INPUTS ===
+		let root_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.merkle_root, 32)?
+		)?;
+
+		let link_tag_var = UInt8::new_input_vec(
+			cs.clone(),
+			&input_bytes(&cs, self.link_tag, 32)?
+		)?;
+
+		// === ALLOCATE PRIVATE WITNESSES ===
+		let commitment_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.commitment)?
+		)?;
+
+		let blinding_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.link_blinding)?
+		)?;
+
+		// === CONSTRAINT 1: link_tag = Hash(commitment || link_blinding) ===
+		let computed_tag = link_tag_gadget::<H>(&commitment_var, &blinding_var)?;
+		computed_tag.enforce_equal(&link_tag_var)?;
+
+		// === CONSTRAINT 2: The commitment hashes up to the public root ===
+		let computed_root =
+			merkle_root_gadget::<H>(cs, commitment_var, &merkle_path, &path_indices)?;
+		computed_root.enforce_equal(&root_var)?;
+
+		Ok(())
+	}
+}
+
+/// Circuit proving ownership of the note behind a link tag
+///
+/// The client-side half of a delegated withdrawal proof (see `MembershipCircuit`).
+///
+/// PUBLIC INPUTS (visible on-chain):
+/// - nullifier: Hash(commitment || secret)
+/// - link_tag: Hash(commitment || link_blinding)
 ///
 /// PRIVATE INPUTS (witness - never revealed):
-/// - amount: The hidden amount
-/// - asset_id: The asset type
-/// - randomness: Secret randomness used in commitment
-/// - secret: Secret key for generating nullifier
+/// - amount, asset_id, tweak: Opening of the commitment
+/// - secret: Secret key the note's randomness and nullifier derive from
+/// - link_blinding: Blinding used in the link tag
 #[derive(Clone)]
-pub struct PrivateTransferCircuit {
+pub struct OwnershipCircuit<H> {
 	// === PUBLIC INPUTS ===
 	/// The nullifier (prevents double-spend)
 	pub nullifier: Option<Vec<u8>>,
 
-	/// The commitment being spent
-	pub commitment: Option<Vec<u8>>,
+	/// Tag linking this proof to a membership proof
+	pub link_tag: Option<Vec<u8>>,
 
 	// === PRIVATE INPUTS (WITNESS) ===
 	/// The amount (hidden!)
@@ -42,30 +1090,39 @@ pub struct PrivateTransferCircuit {
 	/// Asset ID (hidden!)
 	pub asset_id: Option<u32>,
 
-	/// Randomness used in commitment (hidden!)
-	pub randomness: Option<[u8; 32]>,
+	/// Tweak of the randomness used in commitment (hidden!)
+	pub tweak: Option<[u8; 32]>,
 
 	/// Secret for nullifier generation (hidden!)
 	pub secret: Option<[u8; 32]>,
+
+	/// Link tag blinding (hidden!)
+	pub link_blinding: Option<[u8; 32]>,
+
+	/// Hasher of the notes and the tree
+	hasher: PhantomData<H>,
 }
 
-impl PrivateTransferCircuit {
+impl<H> OwnershipCircuit<H> {
 	/// Create a new circuit for proof generation
 	pub fn new(
 		nullifier: Vec<u8>,
-		commitment: Vec<u8>,
+		link_tag: Vec<u8>,
 		amount: u128,
 		asset_id: u32,
-		randomness: [u8; 32],
+		tweak: [u8; 32],
 		secret: [u8; 32],
+		link_blinding: [u8; 32],
 	) -> Self {
 		Self {
 			nullifier: Some(nullifier),
-			commitment: Some(commitment),
+			link_tag: Some(link_tag),
 			amount: Some(amount),
 			asset_id: Some(asset_id),
-			randomness: Some(randomness),
+			tweak: Some(tweak),
 			secret: Some(secret),
+			link_blinding: Some(link_blinding),
+			hasher: PhantomData,
 		}
 	}
 
@@ -73,111 +1130,282 @@ impl PrivateTransferCircuit {
 	pub fn empty() -> Self {
 		Self {
 			nullifier: None,
-			commitment: None,
+			link_tag: None,
 			amount: None,
 			asset_id: None,
-			randomness: None,
+			tweak: None,
 			secret: None,
+			link_blinding: None,
+			hasher: PhantomData,
 		}
 	}
 }
 
-impl ConstraintSynthesizer<ScalarField> for PrivateTransferCircuit {
+impl<H: CircuitHasher> ConstraintSynthesizer<ScalarField> for OwnershipCircuit<H> {
 	fn generate_constraints(
 		self,
 		cs: ConstraintSystemRef<ScalarField>,
 	) -> Result<(), SynthesisError> {
 		// === ALLOCATE PUBLIC INPUTS ===
-		// Week 3: Use 32-byte defaults for empty circuit
 		let nullifier_var = UInt8::new_input_vec(
 			cs.clone(),
-			&self.nullifier.unwrap_or_else(|| vec![0u8; 32])
+			&input_bytes(&cs, self.nullifier, 32)?
 		)?;
 
-		let commitment_var = UInt8::new_input_vec(
+		let link_tag_var = UInt8::new_input_vec(
 			cs.clone(),
-			&self.commitment.unwrap_or_else(|| vec![0u8; 32])
+			&input_bytes(&cs, self.link_tag, 32)?
 		)?;
 
 		// === ALLOCATE PRIVATE WITNESSES ===
-		let amount_bytes = self.amount
-			.map(|a| a.to_le_bytes().to_vec())
-			.unwrap_or_else(|| vec![0u8; 16]); // u128 is 16 bytes
-		let amount_var = UInt8::new_witness_vec(cs.clone(), &amount_bytes)?;
+		let amount_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.amount)?.to_le_bytes()
+		)?;
 
-		let asset_id_bytes = self.asset_id
-			.map(|a| a.to_le_bytes().to_vec())
-			.unwrap_or_else(|| vec![0u8; 4]); // u32 is 4 bytes
-		let asset_id_var = UInt8::new_witness_vec(cs.clone(), &asset_id_bytes)?;
+		let asset_id_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.asset_id)?.to_le_bytes()
+		)?;
 
-		let randomness_var = UInt8::new_witness_vec(
+		let tweak_var = UInt8::new_witness_vec(
 			cs.clone(),
-			&self.randomness.unwrap_or([0u8; 32]).to_vec()
+			&witness(&cs, self.tweak)?
 		)?;
 
 		let secret_var = UInt8::new_witness_vec(
 			cs.clone(),
-			&self.secret.unwrap_or([0u8; 32]).to_vec()
+			&witness(&cs, self.secret)?
 		)?;
 
-		// === CONSTRAINT 1: Verify commitment is correctly formed ===
-		// commitment = Hash(amount || asset_id || randomness)
-		let mut commitment_preimage = Vec::new();
-		commitment_preimage.extend_from_slice(&amount_var);
-		commitment_preimage.extend_from_slice(&asset_id_var);
-		commitment_preimage.extend_from_slice(&randomness_var);
+		let blinding_var = UInt8::new_witness_vec(
+			cs.clone(),
+			&witness(&cs, self.link_blinding)?
+		)?;
 
-		// Use Blake2s for in-circuit hashing (efficient in R1CS)
-		let computed_commitment = blake2s_hash(&commitment_preimage)?;
+		// === CONSTRAINT 1: commitment = Hash(amount || asset_id || randomness) ===
+		let randomness_var = note_randomness_gadget::<H>(&secret_var, &tweak_var)?;
+		let commitment_var = H::commitment_gadget(&amount_var, &asset_id_var, &randomness_var)?;
 
-		// Enforce: computed_commitment == commitment
-		computed_commitment.enforce_equal(&commitment_var)?;
+		// === CONSTRAINT 2: nullifier = Hash(commitment || secret) ===
+		H::nullifier_gadget(&commitment_var, &secret_var)?.enforce_equal(&nullifier_var)?;
 
-		// === CONSTRAINT 2: Verify nullifier is correctly formed ===
-		// nullifier = Hash(commitment || secret)
-		let mut nullifier_preimage = Vec::new();
-		nullifier_preimage.extend_from_slice(&commitment_var);
-		nullifier_preimage.extend_from_slice(&secret_var);
+		// === CONSTRAINT 3: link_tag = Hash(commitment || link_blinding) ===
+		link_tag_gadget::<H>(&commitment_var, &blinding_var)?.enforce_equal(&link_tag_var)?;
 
-		let computed_nullifier = blake2s_hash(&nullifier_preimage)?;
+		Ok(())
+	}
+}
 
-		// Enforce: computed_nullifier == nullifier
-		computed_nullifier.enforce_equal(&nullifier_var)?;
+/// `primitives::note_randomness` in circuit, of the note key of `secret` and `tweak`
+///
+/// A spent note's randomness is derived rather than witnessed: the commitment then binds
+/// the secret its nullifier is made with, and a note has exactly one nullifier.
+fn note_randomness_gadget<H: CircuitHasher>(
+	secret: &[UInt8<ScalarField>],
+	tweak: &[UInt8<ScalarField>],
+) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+	// `primitives::note_key`: the nullifier of the reserved leaf
+	let note_key = H::nullifier_gadget(&UInt8::constant_vec(RESERVED_HASH.as_bytes()), secret)?;
+	H::pair_gadget(&note_key, tweak)
+}
 
-		// === SUCCESS ===
-		// If we reach here, the prover knows:
-		// 1. The amount and randomness that create the commitment
-		// 2. The secret that creates the nullifier
-		// But the verifier learns NOTHING except that the proof is valid!
+/// Link tag in circuit: Hash(commitment || link_blinding), hashed as a pair of tree nodes
+fn link_tag_gadget<H: CircuitHasher>(
+	commitment: &[UInt8<ScalarField>],
+	link_blinding: &[UInt8<ScalarField>],
+) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+	H::pair_gadget(commitment, link_blinding)
+}
 
-		Ok(())
+/// Recompute a merkle root in circuit, matching `H::hash_pair`
+fn merkle_root_gadget<H: CircuitHasher>(
+	cs: ConstraintSystemRef<ScalarField>,
+	leaf: Vec<UInt8<ScalarField>>,
+	merkle_path: &[[u8; 32]],
+	path_indices: &[bool],
+) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+	let mut current = leaf;
+
+	for (sibling, is_right) in merkle_path.iter().zip(path_indices) {
+		let sibling_var = UInt8::new_witness_vec(cs.clone(), sibling)?;
+		let is_right_var = Boolean::new_witness(cs.clone(), || Ok(*is_right))?;
+
+		// Order the pair: a right child has its sibling on the left
+		let mut left = Vec::with_capacity(32);
+		let mut right = Vec::with_capacity(32);
+		for (node, other) in current.iter().zip(&sibling_var) {
+			left.push(UInt8::conditionally_select(&is_right_var, other, node)?);
+			right.push(UInt8::conditionally_select(&is_right_var, node, other)?);
+		}
+
+		current = H::pair_gadget(&left, &right)?;
+	}
+
+	Ok(current)
+}
+
+/// The little-endian integer of `bytes`, as a field element
+fn le_bytes_to_field(bytes: &[UInt8<ScalarField>]) -> Result<FpVar<ScalarField>, SynthesisError> {
+	Boolean::le_bits_to_fp_var(&bytes.to_bits_le()?)
+}
+
+/// Bytes of a public input of `len` bytes: its value when proving, zeros during a setup
+///
+/// Only the empty circuit of a setup may leave an input unset. A missing input when proving,
+/// or one of another length, fails synthesis instead of proving placeholder bytes.
+fn input_bytes(
+	cs: &ConstraintSystemRef<ScalarField>,
+	value: Option<Vec<u8>>,
+	len: usize,
+) -> Result<Vec<u8>, SynthesisError> {
+	match value {
+		Some(bytes) if bytes.len() == len => Ok(bytes),
+		Some(_) => Err(SynthesisError::Unsatisfiable),
+		None if cs.is_in_setup_mode() => Ok(vec![0u8; len]),
+		None => Err(SynthesisError::AssignmentMissing),
+	}
+}
+
+/// A witness value when proving, its default during a setup
+fn witness<V: Default>(
+	cs: &ConstraintSystemRef<ScalarField>,
+	value: Option<V>,
+) -> Result<V, SynthesisError> {
+	match value {
+		Some(value) => Ok(value),
+		None if cs.is_in_setup_mode() => Ok(V::default()),
+		None => Err(SynthesisError::AssignmentMissing),
+	}
+}
+
+/// `poseidon::element` in circuit: the little-endian integer of `bytes`, reduced modulo
+/// the field order
+///
+/// Converted 31 bytes at a time: a chunk below the modulus needs no range check, and the
+/// field arithmetic combining them does the reduction.
+fn element_gadget(bytes: &[UInt8<ScalarField>]) -> Result<FpVar<ScalarField>, SynthesisError> {
+	let chunk_factor = ScalarField::from(2u8).pow([31 * 8]);
+	let mut element = FpVar::zero();
+	let mut factor = ScalarField::one();
+	for chunk in bytes.chunks(31) {
+		element += le_bytes_to_field(chunk)? * factor;
+		factor *= chunk_factor;
 	}
+	Ok(element)
+}
+
+/// Poseidon of the elements of `inputs` in circuit, as `poseidon::to_h256` encodes it
+fn poseidon_bytes_gadget(
+	inputs: &[FpVar<ScalarField>],
+) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+	// The canonical encoding: the bits are checked to be below the modulus
+	poseidon_hash_gadget(inputs)?.to_bytes()
 }
 
-/// Helper function for Blake2s hashing in circuit
-/// Uses ark-r1cs-std's Blake2s gadget
-fn blake2s_hash(input: &[UInt8<ScalarField>]) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
-	use ark_r1cs_std::bits::uint8::UInt8;
+impl CircuitHasher for PoseidonHasher {
+	fn commitment_gadget(
+		amount: &[UInt8<ScalarField>],
+		asset_id: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		poseidon_bytes_gadget(&[
+			element_gadget(amount)?,
+			element_gadget(asset_id)?,
+			element_gadget(randomness)?,
+		])
+	}
+
+	fn bundle_commitment_gadget(
+		assets: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		let mut inputs = vec![FpVar::constant(ScalarField::from(BUNDLE_COMMITMENT_TAG))];
+		// `primitives::bundle_assets`: (asset_id || amount) per entry
+		let asset_id_len = core::mem::size_of::<u32>();
+		for entry in assets.chunks_exact(asset_id_len + core::mem::size_of::<u128>()) {
+			let (asset_id, amount) = entry.split_at(asset_id_len);
+			inputs.extend([element_gadget(asset_id)?, element_gadget(amount)?]);
+		}
+		inputs.push(element_gadget(randomness)?);
+		poseidon_bytes_gadget(&inputs)
+	}
+
+	fn nullifier_gadget(
+		commitment: &[UInt8<ScalarField>],
+		secret: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		poseidon_bytes_gadget(&[element_gadget(commitment)?, element_gadget(secret)?])
+	}
 
-	// For Week 2 MVP, we'll use a simplified hash
-	// In production, use: Blake2sGadget::evaluate()
+	fn pair_gadget(
+		left: &[UInt8<ScalarField>],
+		right: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		poseidon_bytes_gadget(&[element_gadget(left)?, element_gadget(right)?])
+	}
+}
 
-	// Simple placeholder: XOR all bytes (NOT SECURE - just for testing!)
-	// TODO Week 3: Replace with actual Blake2s gadget
+/// `simple_hash` in circuit: the XOR of the input's 32-byte chunks
+///
+/// Trivially invertible, so only built with `SimpleHasher` (tests and the
+/// `insecure-test-hash` feature).
+#[cfg(any(test, feature = "insecure-test-hash"))]
+fn xor_hash_gadget(
+	input: &[UInt8<ScalarField>],
+) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
 	let mut result = vec![UInt8::constant(0u8); 32];
 
-	for (i, byte) in input.iter().enumerate() {
-		let idx = i % 32;
-		result[idx] = result[idx].xor(byte)?;
+	for chunk in input.chunks(32) {
+		for (acc, byte) in result.iter_mut().zip(chunk) {
+			*acc = acc.xor(byte)?;
+		}
 	}
 
 	Ok(result)
 }
 
+#[cfg(any(test, feature = "insecure-test-hash"))]
+impl CircuitHasher for SimpleHasher {
+	fn commitment_gadget(
+		amount: &[UInt8<ScalarField>],
+		asset_id: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		xor_hash_gadget(&[amount, asset_id, randomness].concat())
+	}
+
+	fn bundle_commitment_gadget(
+		assets: &[UInt8<ScalarField>],
+		randomness: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		let tag = [UInt8::constant(BUNDLE_COMMITMENT_TAG)];
+		xor_hash_gadget(&[&tag[..], assets, randomness].concat())
+	}
+
+	fn nullifier_gadget(
+		commitment: &[UInt8<ScalarField>],
+		secret: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		xor_hash_gadget(&[commitment, secret].concat())
+	}
+
+	fn pair_gadget(
+		left: &[UInt8<ScalarField>],
+		right: &[UInt8<ScalarField>],
+	) -> Result<Vec<UInt8<ScalarField>>, SynthesisError> {
+		xor_hash_gadget(&[left, right].concat())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::merkle_tree;
+	use crate::primitives;
+	use crate::test_support::TEST_RECIPIENT_HASH;
 	use ark_relations::r1cs::ConstraintSystem;
+	use sp_core::H256;
 
 	#[test]
 	fn test_circuit_satisfiability() {
@@ -186,8 +1414,9 @@ mod tests {
 		// Create test data
 		let amount = 100u128;
 		let asset_id = 0u32;
-		let randomness = [1u8; 32];
+		let tweak = [0u8; 32];
 		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
 
 		// Week 3: Generate commitment and nullifier using simple_hash
 		let commitment_hash = simple_hash::generate_commitment(amount, asset_id, &randomness);
@@ -196,14 +1425,23 @@ mod tests {
 		let nullifier_hash = simple_hash::generate_nullifier(&commitment_hash, &secret);
 		let nullifier = nullifier_hash.as_bytes().to_vec();
 
+		// A tree of the commitment and two other leaves
+		let leaves = [H256::repeat_byte(7), commitment_hash, H256::repeat_byte(9)];
+		let root = merkle_tree::calculate_root(&leaves).unwrap();
+		let (siblings, path_indices) = merkle_tree::generate_circuit_proof(&leaves, 1).unwrap();
+
 		// Create circuit
-		let circuit = PrivateTransferCircuit::new(
+		let circuit = PrivateTransferCircuit::<SimpleHasher>::new(
 			nullifier,
 			commitment,
+			root.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			siblings.into_iter().map(|sibling| sibling.0).collect(),
+			path_indices,
 		);
 
 		// Test constraint satisfaction
@@ -212,4 +1450,370 @@ mod tests {
 
 		assert!(cs.is_satisfied().unwrap(), "Circuit should be satisfied");
 	}
+
+	#[test]
+	fn test_bundle_circuit_binds_assets() {
+		use crate::simple_hash;
+
+		let assets = vec![(0u32, 100u128), (1, 250)];
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let commitment = simple_hash::generate_bundle_commitment(&assets, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+
+		let circuit = |assets: Vec<(u32, u128)>| {
+			let circuit = BundleWithdrawalCircuit::<SimpleHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				commitment.as_bytes().to_vec(),
+				assets,
+				TEST_RECIPIENT_HASH.to_vec(),
+				tweak,
+				secret,
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit(assets.clone()), "Circuit should be satisfied");
+		// Claiming other amounts fails
+		assert!(!circuit(vec![(0, 100), (1, 251)]));
+	}
+	#[test]
+	fn test_swap_circuit_binds_output_value() {
+		use crate::simple_hash;
+
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let output_randomness = [3u8; 32];
+		let commitment = simple_hash::generate_commitment(100, 1, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+
+		let circuit = |output_amount: u128| {
+			let output = simple_hash::generate_commitment(output_amount, 1, &output_randomness);
+			let circuit = SwapCircuit::<SimpleHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				commitment.as_bytes().to_vec(),
+				output.as_bytes().to_vec(),
+				100,
+				1,
+				tweak,
+				secret,
+				output_randomness,
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit(100), "Circuit should be satisfied");
+		// The output note cannot hold more than the spent one
+		assert!(!circuit(101));
+	}
+
+	#[test]
+	fn test_transfer_circuit_conserves_value() {
+		use crate::simple_hash;
+
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let output_randomness = [[3u8; 32], [4u8; 32]];
+		let commitment = simple_hash::generate_commitment(100, 1, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+		let leaves = [H256::repeat_byte(7), commitment];
+		let root = merkle_tree::calculate_root(&leaves).unwrap();
+		let (siblings, path_indices) = merkle_tree::generate_circuit_proof(&leaves, 1).unwrap();
+		let merkle_path: Vec<[u8; 32]> = siblings.into_iter().map(|sibling| sibling.0).collect();
+
+		let circuit = |output_amounts: [u128; 2]| {
+			let outputs = [0, 1].map(|i| {
+				simple_hash::generate_commitment(output_amounts[i], 1, &output_randomness[i])
+					.as_bytes()
+					.to_vec()
+			});
+			let circuit = TransferCircuit::<SimpleHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				root.as_bytes().to_vec(),
+				1,
+				outputs,
+				100,
+				tweak,
+				secret,
+				merkle_path.clone(),
+				path_indices.clone(),
+				output_amounts,
+				output_randomness,
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit([60, 40]), "Circuit should be satisfied");
+		assert!(circuit([100, 0]));
+		// The outputs cannot hold more, or less, than the spent note
+		assert!(!circuit([60, 41]));
+		assert!(!circuit([60, 39]));
+	}
+
+	#[test]
+	fn test_join_split_circuit_sums_both_notes() {
+		use crate::simple_hash;
+
+		let tweaks = [[0u8; 32], [1u8; 32]];
+		let secrets = [[2u8; 32], [4u8; 32]];
+		let randomness =
+			[0, 1].map(|i| simple_hash::generate_note_randomness(&secrets[i], &tweaks[i]));
+		let notes = [
+			simple_hash::generate_commitment(60, 1, &randomness[0]),
+			simple_hash::generate_commitment(40, 1, &randomness[1]),
+		];
+		let nullifiers = [
+			simple_hash::generate_nullifier(&notes[0], &secrets[0]),
+			simple_hash::generate_nullifier(&notes[1], &secrets[1]),
+		];
+		let leaves = [notes[0], H256::repeat_byte(7), notes[1]];
+		let root = merkle_tree::calculate_root(&leaves).unwrap();
+		let path = |index: usize| {
+			let (siblings, path_indices) =
+				merkle_tree::generate_circuit_proof(&leaves, index).unwrap();
+			(siblings.into_iter().map(|sibling| sibling.0).collect::<Vec<_>>(), path_indices)
+		};
+		let paths = [path(0), path(2)];
+
+		// Join the notes at `inputs` into `amount`
+		let circuit = |inputs: [usize; 2], amount: u128| {
+			let circuit = JoinSplitCircuit::<SimpleHasher>::new(
+				inputs.map(|i| nullifiers[i].as_bytes().to_vec()),
+				inputs.map(|i| notes[i].as_bytes().to_vec()),
+				root.as_bytes().to_vec(),
+				1,
+				amount,
+				TEST_RECIPIENT_HASH.to_vec(),
+				inputs.map(|i| [60, 40][i]),
+				inputs.map(|i| tweaks[i]),
+				inputs.map(|i| secrets[i]),
+				inputs.map(|i| paths[i].0.clone()),
+				inputs.map(|i| paths[i].1.clone()),
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit([0, 1], 100), "Circuit should be satisfied");
+		// The withdrawn amount is exactly the sum of the notes
+		assert!(!circuit([0, 1], 101));
+		assert!(!circuit([0, 1], 60));
+		// One note is not two
+		assert!(!circuit([0, 0], 120));
+	}
+
+	#[test]
+	fn test_missing_or_mis_sized_inputs_fail_synthesis() {
+		let circuit = |nullifier: Vec<u8>| {
+			PrivateTransferCircuit::<SimpleHasher>::new(
+				nullifier,
+				vec![0u8; 32],
+				vec![0u8; 32],
+				TEST_RECIPIENT_HASH.to_vec(),
+				100,
+				0,
+				[1u8; 32],
+				[2u8; 32],
+				vec![[0u8; 32]; TREE_DEPTH],
+				vec![false; TREE_DEPTH],
+			)
+		};
+
+		// Only a setup may leave inputs unset
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		assert!(matches!(
+			PrivateTransferCircuit::<SimpleHasher>::empty().generate_constraints(cs),
+			Err(SynthesisError::AssignmentMissing)
+		));
+
+		// A short nullifier is not padded into a proof of zeros
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		assert!(matches!(
+			circuit(vec![0u8; 31]).generate_constraints(cs),
+			Err(SynthesisError::Unsatisfiable)
+		));
+
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		assert!(circuit(vec![0u8; 32]).generate_constraints(cs).is_ok());
+	}
+
+	#[test]
+	fn test_poseidon_gadgets_match_native_hasher() {
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		let bytes = |value: &[u8]| UInt8::new_witness_vec(cs.clone(), value).unwrap();
+		let value = |hash: Vec<UInt8<ScalarField>>| {
+			H256::from_slice(&hash.value().unwrap())
+		};
+
+		// Randomness above the field order is reduced, as `poseidon::element` does
+		let assets = [(0u32, 100u128), (7, u128::MAX)];
+		for randomness in [[1u8; 32], [0xff; 32]] {
+			let commitment = PoseidonHasher::commitment_gadget(
+				&bytes(&250u128.to_le_bytes()),
+				&bytes(&3u32.to_le_bytes()),
+				&bytes(&randomness),
+			)
+			.unwrap();
+			assert_eq!(value(commitment), PoseidonHasher::hash_commitment(250, 3, &randomness));
+
+			let bundle = PoseidonHasher::bundle_commitment_gadget(
+				&bytes(&bundle_assets(&assets)),
+				&bytes(&randomness),
+			)
+			.unwrap();
+			assert_eq!(value(bundle), PoseidonHasher::hash_bundle_commitment(&assets, &randomness));
+		}
+
+		let commitment = H256::repeat_byte(0xee);
+		let secret = [2u8; 32];
+		let nullifier =
+			PoseidonHasher::nullifier_gadget(&bytes(commitment.as_bytes()), &bytes(&secret));
+		assert_eq!(value(nullifier.unwrap()), PoseidonHasher::hash_nullifier(&commitment, &secret));
+
+		// The note key, and a tweak above the field order
+		let tweak = [0xff; 32];
+		let randomness = note_randomness_gadget::<PoseidonHasher>(&bytes(&secret), &bytes(&tweak));
+		let note_key = primitives::note_key::<PoseidonHasher>(&secret);
+		assert_eq!(
+			value(randomness.unwrap()).0,
+			primitives::note_randomness::<PoseidonHasher>(&note_key, &tweak)
+		);
+
+		let (left, right) = (H256::repeat_byte(1), H256::repeat_byte(0xfe));
+		let parent = PoseidonHasher::pair_gadget(&bytes(left.as_bytes()), &bytes(right.as_bytes()))
+			.unwrap();
+		assert_eq!(value(parent), PoseidonHasher::hash_pair(&left, &right));
+
+		assert!(cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn test_poseidon_circuit_proves_membership_of_the_pallet_tree() {
+		let amount = 100u128;
+		let asset_id = 0u32;
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let note_key = primitives::note_key::<PoseidonHasher>(&secret);
+		let randomness = primitives::note_randomness::<PoseidonHasher>(&note_key, &tweak);
+		let commitment = PoseidonHasher::hash_commitment(amount, asset_id, &randomness);
+		let nullifier = PoseidonHasher::hash_nullifier(&commitment, &secret);
+
+		// The full-depth tree the pallet keeps, hashed by the same hasher
+		let leaves = [H256::repeat_byte(7), commitment, H256::repeat_byte(9)];
+		let root = merkle_tree::root_of::<PoseidonHasher>(&leaves).unwrap();
+		let path = merkle_tree::path_of::<PoseidonHasher>(&leaves, 1).unwrap();
+		// Leaf 1 is a right child, every ancestor a left one
+		let path_indices: Vec<bool> = (0..TREE_DEPTH).map(|level| level == 0).collect();
+
+		let circuit = |root: H256| {
+			let circuit = PrivateTransferCircuit::<PoseidonHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				commitment.as_bytes().to_vec(),
+				root.as_bytes().to_vec(),
+				TEST_RECIPIENT_HASH.to_vec(),
+				amount,
+				asset_id,
+				tweak,
+				secret,
+				path.iter().map(|sibling| sibling.0).collect(),
+				path_indices.clone(),
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit(root));
+		// The root of the same leaves under the XOR hash is not a root of this circuit
+		assert!(!circuit(merkle_tree::root_of::<SimpleHasher>(&leaves).unwrap()));
+	}
+
+	#[test]
+	fn test_withdrawal_circuit_needs_the_secret_not_the_note_key() {
+		// An XCM note, whose tweak is its origin's digest
+		let (secret, tweak) = ([2u8; 32], [0x11u8; 32]);
+		let note_key = primitives::note_key::<PoseidonHasher>(&secret);
+		let randomness = primitives::note_randomness::<PoseidonHasher>(&note_key, &tweak);
+		let commitment = PoseidonHasher::hash_commitment(100, 0, &randomness);
+		let leaves = [commitment];
+		let root = merkle_tree::root_of::<PoseidonHasher>(&leaves).unwrap();
+		let path = merkle_tree::path_of::<PoseidonHasher>(&leaves, 0).unwrap();
+
+		// Withdraw the note to `recipient_hash`, opening it with `secret`
+		let circuit = |secret: [u8; 32], recipient_hash: [u8; 32]| {
+			let nullifier = PoseidonHasher::hash_nullifier(&commitment, &secret);
+			let circuit = PrivateTransferCircuit::<PoseidonHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				commitment.as_bytes().to_vec(),
+				root.as_bytes().to_vec(),
+				recipient_hash.to_vec(),
+				100,
+				0,
+				tweak,
+				secret,
+				path.iter().map(|sibling| sibling.0).collect(),
+				vec![false; TREE_DEPTH],
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit(secret, TEST_RECIPIENT_HASH));
+		// The key a deposit reveals does not let anyone else name the beneficiary
+		assert!(!circuit(note_key, [9u8; 32]));
+	}
+
+	#[test]
+	fn test_transfer_circuit_spends_a_note_under_its_secret_only() {
+		let secret = [2u8; 32];
+		let note_key = primitives::note_key::<PoseidonHasher>(&secret);
+		let randomness = primitives::note_randomness::<PoseidonHasher>(&note_key, &[0u8; 32]);
+		let commitment = PoseidonHasher::hash_commitment(100, 1, &randomness);
+		let output_randomness = [[3u8; 32], [4u8; 32]];
+		let outputs = [0, 1].map(|i| {
+			PoseidonHasher::hash_commitment(50, 1, &output_randomness[i]).as_bytes().to_vec()
+		});
+		let leaves = [H256::repeat_byte(7), commitment];
+		let root = merkle_tree::root_of::<PoseidonHasher>(&leaves).unwrap();
+		let path = merkle_tree::path_of::<PoseidonHasher>(&leaves, 1).unwrap();
+		let path_indices: Vec<bool> = (0..TREE_DEPTH).map(|level| level == 0).collect();
+
+		// Spend the note under `secret` and `tweak`, revealing the nullifier they give it
+		let circuit = |secret: [u8; 32], tweak: [u8; 32]| {
+			let nullifier = PoseidonHasher::hash_nullifier(&commitment, &secret);
+			let circuit = TransferCircuit::<PoseidonHasher>::new(
+				nullifier.as_bytes().to_vec(),
+				root.as_bytes().to_vec(),
+				1,
+				outputs.clone(),
+				100,
+				tweak,
+				secret,
+				path.iter().map(|sibling| sibling.0).collect(),
+				path_indices.clone(),
+				[50, 50],
+				output_randomness,
+			);
+			let cs = ConstraintSystem::<ScalarField>::new_ref();
+			circuit.generate_constraints(cs.clone()).unwrap();
+			cs.is_satisfied().unwrap()
+		};
+
+		assert!(circuit(secret, [0u8; 32]));
+		// No other secret opens the note, so it has no second nullifier
+		assert!(!circuit([5u8; 32], [0u8; 32]));
+		assert!(!circuit([5u8; 32], [1u8; 32]));
+		assert!(!circuit(secret, [1u8; 32]));
+	}
 }
@@ -0,0 +1,352 @@
+//! Delegated proving protocol
+//!
+//! Low-power devices cannot run Groth16 proving for a full withdrawal locally. This module
+//! splits the withdrawal proof so the expensive part can be outsourced without giving the
+//! proving service anything it could spend:
+//!
+//! ```text
+//! Client                                   Proving service
+//!   │                                            │
+//!   ├── MembershipRequest ─────────────────────> │  (leaf index, commitment, link blinding)
+//!   │                                            ├─ builds merkle path over its leaf set
+//!   │                                            ├─ proves MembershipCircuit
+//!   │ <──────────────────── MembershipResponse ──┤  (root, link tag, proof)
+//!   │
+//!   ├─ verify_membership_response()
+//!   ├─ proves OwnershipCircuit locally (secret never leaves the device)
+//!   └─ submits both proofs; verifier runs zksnark::verify_composed_proof()
+//! ```
+//!
+//! The two halves are bound together by the link tag `Hash(commitment || link_blinding)`.
+//! The ownership half needs the note opening and the nullifier secret, which are never part
+//! of a request, so the service alone cannot produce a composed proof that verifies.
+//!
+//! ## MVP Simplifications
+//!
+//! - The service learns which commitment is being spent (it proves its membership), but not
+//!   the amount, asset or nullifier secret.
+//! - Hashing uses the placeholder `simple_hash`; its hiding/binding guarantees only become
+//!   meaningful once it is replaced by a real hash gadget.
+
+use codec::{Decode, Encode};
+use sp_core::H256;
+use alloc::{string::String, vec::Vec};
+use ark_bn254::Bn254;
+use ark_groth16::{ProvingKey, VerifyingKey};
+
+use crate::hasher::SimpleHasher;
+use crate::{merkle_tree, simple_hash, zksnark};
+
+/// Version of the request/response encoding
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Request sent by a client to a proving service
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct MembershipRequest {
+	/// Protocol version (`PROTOCOL_VERSION`)
+	pub version: u8,
+	/// Index of the note's leaf in the commitment tree
+	pub leaf_index: u32,
+	/// The note's commitment
+	pub commitment: H256,
+	/// Blinding for the link tag, chosen fresh by the client for every request
+	pub link_blinding: [u8; 32],
+}
+
+impl MembershipRequest {
+	/// Create a request for the current protocol version
+	pub fn new(leaf_index: u32, commitment: H256, link_blinding: [u8; 32]) -> Self {
+		Self { version: PROTOCOL_VERSION, leaf_index, commitment, link_blinding }
+	}
+
+	/// The link tag both proof halves must expose
+	pub fn link_tag(&self) -> H256 {
+		simple_hash::generate_link_tag(&self.commitment, &self.link_blinding)
+	}
+}
+
+/// Response returned by a proving service
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct MembershipResponse {
+	/// Protocol version (`PROTOCOL_VERSION`)
+	pub version: u8,
+	/// Root of the tree the membership proof was made against
+	pub merkle_root: H256,
+	/// Link tag exposed by the membership proof
+	pub link_tag: H256,
+	/// Serialized Groth16 proof for `MembershipCircuit`
+	pub proof: Vec<u8>,
+}
+
+/// Errors in the delegated proving protocol
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DelegatedProvingError {
+	/// Request or response uses an unknown protocol version
+	UnsupportedVersion,
+	/// The leaf at `leaf_index` is not the requested commitment
+	LeafMismatch,
+	/// The service's leaves do not form a tree
+	Tree(merkle_tree::MerkleError),
+	/// The service failed to generate a proof
+	ProofGeneration(String),
+	/// A message or proof could not be decoded
+	Decoding(zksnark::VerifyError),
+	/// The response's link tag does not belong to the request
+	LinkTagMismatch,
+	/// The membership proof does not verify
+	InvalidProof,
+}
+
+/// Serve a membership request (proving service side)
+///
+/// `leaves` is the service's view of the commitment tree.
+pub fn serve_membership_request(
+	proving_key: &ProvingKey<Bn254>,
+	leaves: &[H256],
+	request: &MembershipRequest,
+) -> Result<MembershipResponse, DelegatedProvingError> {
+	if request.version != PROTOCOL_VERSION {
+		return Err(DelegatedProvingError::UnsupportedVersion);
+	}
+
+	let leaf_index = request.leaf_index as usize;
+	if leaves.get(leaf_index) != Some(&request.commitment) {
+		return Err(DelegatedProvingError::LeafMismatch);
+	}
+
+	let (siblings, path_indices) = merkle_tree::generate_circuit_proof(leaves, leaf_index)
+		.map_err(DelegatedProvingError::Tree)?;
+	let merkle_root = merkle_tree::calculate_root(leaves).map_err(DelegatedProvingError::Tree)?;
+	let link_tag = request.link_tag();
+
+	let proof = zksnark::generate_membership_proof::<SimpleHasher>(
+		proving_key,
+		merkle_root.as_bytes().to_vec(),
+		link_tag.as_bytes().to_vec(),
+		request.commitment.0,
+		request.link_blinding,
+		siblings.into_iter().map(|sibling| sibling.0).collect(),
+		path_indices,
+	).map_err(DelegatedProvingError::ProofGeneration)?;
+
+	Ok(MembershipResponse { version: PROTOCOL_VERSION, merkle_root, link_tag, proof })
+}
+
+/// Check a service's response before building the ownership half (client side)
+pub fn verify_membership_response(
+	verifying_key: &VerifyingKey<Bn254>,
+	request: &MembershipRequest,
+	response: &MembershipResponse,
+) -> Result<(), DelegatedProvingError> {
+	if response.version != PROTOCOL_VERSION {
+		return Err(DelegatedProvingError::UnsupportedVersion);
+	}
+
+	if response.link_tag != request.link_tag() {
+		return Err(DelegatedProvingError::LinkTagMismatch);
+	}
+
+	let valid = zksnark::verify_membership_proof(
+		verifying_key,
+		&response.proof,
+		response.merkle_root.as_bytes(),
+		response.link_tag.as_bytes(),
+	).map_err(DelegatedProvingError::Decoding)?;
+
+	if valid {
+		Ok(())
+	} else {
+		Err(DelegatedProvingError::InvalidProof)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::circuit::OwnershipCircuit;
+	use crate::test_support::{membership_setup, ownership_setup};
+	use alloc::vec;
+	use ark_bn254::Fr as ScalarField;
+	use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+	struct Note {
+		amount: u128,
+		asset_id: u32,
+		tweak: [u8; 32],
+		secret: [u8; 32],
+	}
+
+	impl Note {
+		fn commitment(&self) -> H256 {
+			let randomness = simple_hash::generate_note_randomness(&self.secret, &self.tweak);
+			simple_hash::generate_commitment(self.amount, self.asset_id, &randomness)
+		}
+
+		fn nullifier(&self) -> H256 {
+			simple_hash::generate_nullifier(&self.commitment(), &self.secret)
+		}
+
+		fn ownership_proof(&self, pk: &ProvingKey<Bn254>, link_blinding: [u8; 32]) -> Vec<u8> {
+			let link_tag = simple_hash::generate_link_tag(&self.commitment(), &link_blinding);
+			zksnark::generate_ownership_proof::<SimpleHasher>(
+				pk,
+				self.nullifier().as_bytes().to_vec(),
+				link_tag.as_bytes().to_vec(),
+				self.amount,
+				self.asset_id,
+				self.tweak,
+				self.secret,
+				link_blinding,
+			).unwrap()
+		}
+	}
+
+	fn note(seed: u8) -> Note {
+		Note { amount: 1000 + seed as u128, asset_id: 0, tweak: [seed; 32], secret: [seed.wrapping_add(100); 32] }
+	}
+
+	fn tree(notes: &[Note]) -> Vec<H256> {
+		notes.iter().map(Note::commitment).collect()
+	}
+
+	#[test]
+	fn test_messages_roundtrip_encoding() {
+		let request = MembershipRequest::new(3, H256::from([7u8; 32]), [9u8; 32]);
+		assert_eq!(MembershipRequest::decode(&mut &request.encode()[..]).unwrap(), request);
+
+		let response = MembershipResponse {
+			version: PROTOCOL_VERSION,
+			merkle_root: H256::from([1u8; 32]),
+			link_tag: request.link_tag(),
+			proof: vec![1, 2, 3],
+		};
+		assert_eq!(MembershipResponse::decode(&mut &response.encode()[..]).unwrap(), response);
+	}
+
+	#[test]
+	fn test_service_rejects_mismatched_leaf_and_version() {
+		let (pk, _) = membership_setup();
+		let notes = [note(1), note(2)];
+		let leaves = tree(&notes);
+
+		let wrong_leaf = MembershipRequest::new(1, notes[0].commitment(), [5u8; 32]);
+		assert_eq!(
+			serve_membership_request(pk, &leaves, &wrong_leaf),
+			Err(DelegatedProvingError::LeafMismatch)
+		);
+
+		let mut old_version = MembershipRequest::new(0, notes[0].commitment(), [5u8; 32]);
+		old_version.version = PROTOCOL_VERSION + 1;
+		assert_eq!(
+			serve_membership_request(pk, &leaves, &old_version),
+			Err(DelegatedProvingError::UnsupportedVersion)
+		);
+	}
+
+	#[test]
+	fn test_delegated_withdrawal_composes() {
+		let (membership_pk, membership_vk) = membership_setup();
+		let (ownership_pk, ownership_vk) = ownership_setup();
+		let notes = [note(1), note(2), note(3)];
+		let leaves = tree(&notes);
+		let spent = &notes[2];
+		let link_blinding = [11u8; 32];
+
+		// Service proves membership
+		let request = MembershipRequest::new(2, spent.commitment(), link_blinding);
+		let response = serve_membership_request(membership_pk, &leaves, &request).unwrap();
+		assert_eq!(response.merkle_root, merkle_tree::calculate_root(&leaves).unwrap());
+		assert_eq!(verify_membership_response(membership_vk, &request, &response), Ok(()));
+
+		// Client proves ownership and the composed proof verifies
+		let ownership_proof = spent.ownership_proof(ownership_pk, link_blinding);
+		assert!(zksnark::verify_composed_proof(
+			membership_vk,
+			ownership_vk,
+			&response.proof,
+			&ownership_proof,
+			response.merkle_root.as_bytes(),
+			spent.nullifier().as_bytes(),
+			response.link_tag.as_bytes(),
+		).unwrap());
+	}
+
+	#[test]
+	fn test_service_cannot_prove_ownership_alone() {
+		let notes = [note(1), note(2)];
+		let victim = &notes[0];
+		let link_blinding = [11u8; 32];
+		let request = MembershipRequest::new(0, victim.commitment(), link_blinding);
+
+		// The service knows the request but not the note opening or the nullifier secret,
+		// so its best attempt at the ownership half uses guessed witnesses. No witness it
+		// can build satisfies the circuit, so there is no ownership proof for it to produce.
+		let forged_nullifier = simple_hash::generate_nullifier(&victim.commitment(), &[0u8; 32]);
+		let forged = OwnershipCircuit::<SimpleHasher>::new(
+			forged_nullifier.as_bytes().to_vec(),
+			request.link_tag().as_bytes().to_vec(),
+			0,
+			0,
+			[0u8; 32],
+			[0u8; 32],
+			link_blinding,
+		);
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		forged.generate_constraints(cs.clone()).unwrap();
+		assert!(!cs.is_satisfied().unwrap());
+
+		// Claiming the victim's real nullifier does not help without the secret either
+		let forged = OwnershipCircuit::<SimpleHasher>::new(
+			victim.nullifier().as_bytes().to_vec(),
+			request.link_tag().as_bytes().to_vec(),
+			0,
+			0,
+			[0u8; 32],
+			[0u8; 32],
+			link_blinding,
+		);
+		let cs = ConstraintSystem::<ScalarField>::new_ref();
+		forged.generate_constraints(cs.clone()).unwrap();
+		assert!(!cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn test_proofs_for_different_notes_do_not_compose() {
+		let (membership_pk, membership_vk) = membership_setup();
+		let (ownership_pk, ownership_vk) = ownership_setup();
+		let notes = [note(1), note(2)];
+		let leaves = tree(&notes);
+		let link_blinding = [11u8; 32];
+
+		// Membership for note 0, ownership for note 1
+		let request = MembershipRequest::new(0, notes[0].commitment(), link_blinding);
+		let response = serve_membership_request(membership_pk, &leaves, &request).unwrap();
+		let other_ownership = notes[1].ownership_proof(ownership_pk, link_blinding);
+
+		assert!(!zksnark::verify_composed_proof(
+			membership_vk,
+			ownership_vk,
+			&response.proof,
+			&other_ownership,
+			response.merkle_root.as_bytes(),
+			notes[1].nullifier().as_bytes(),
+			response.link_tag.as_bytes(),
+		).unwrap());
+	}
+
+	#[test]
+	fn test_client_rejects_wrong_link_tag() {
+		let (membership_pk, membership_vk) = membership_setup();
+		let notes = [note(1), note(2)];
+		let leaves = tree(&notes);
+
+		let request = MembershipRequest::new(1, notes[1].commitment(), [11u8; 32]);
+		let mut response = serve_membership_request(membership_pk, &leaves, &request).unwrap();
+		response.link_tag = H256::from([0xAAu8; 32]);
+
+		assert_eq!(
+			verify_membership_response(membership_vk, &request, &response),
+			Err(DelegatedProvingError::LinkTagMismatch)
+		);
+	}
+}
@@ -0,0 +1,155 @@
+//! Error code snapshot
+//!
+//! Support tooling stores the codes of `error_codes::ERROR_CODES`, so a code must keep
+//! meaning the same error across runtime upgrades. The table is compared against the
+//! codes checked in below, and every `Error` variant must have a code. A failing test
+//! means a variant was added without a code (append one with the next free code, here and
+//! in the table) or an existing code changed, which is never allowed.
+
+use crate::error_codes::{error_code, error_description, ERROR_CODES, UNKNOWN_ERROR_CODE};
+use crate::{mock::Test, Error};
+use std::collections::BTreeSet;
+use scale_info::{TypeDef, TypeInfo};
+
+/// Code of every error; new errors are appended with the next free code
+const ERROR_CODE_SNAPSHOT: &[(u16, &str)] = &[
+	(1, "CommitmentAlreadyExists"),
+	(2, "NullifierAlreadyUsed"),
+	(3, "CommitmentNotFound"),
+	(4, "InvalidProof"),
+	(5, "AmountOverflow"),
+	(6, "InvalidRandomness"),
+	(7, "InvalidDecoySchedule"),
+	(8, "DecoyNotFound"),
+	(9, "AssetNotRegistered"),
+	(10, "InvalidAbandonmentPeriod"),
+	(11, "LeafNotFound"),
+	(12, "CommitmentDenied"),
+	(13, "NoteAlreadySpent"),
+	(14, "NoteNotAbandonable"),
+	(15, "NoteNotYetAbandoned"),
+	(16, "ClaimNotFound"),
+	(17, "ClaimNotRedeemable"),
+	(18, "BelowExistentialDeposit"),
+	(19, "InvalidBeneficiary"),
+	(20, "ExportFailed"),
+	(21, "NoTrappedAssets"),
+	(22, "TreeFull"),
+	(23, "NoChannelToDestination"),
+	(24, "DepositBelowMinimum"),
+	(25, "FeeExceedsMaximum"),
+	(26, "FeeExceedsAmount"),
+	(27, "InvalidFeeConfig"),
+	(28, "WithdrawalNotFound"),
+	(29, "WithdrawalNotFailed"),
+	(30, "InsufficientReserveBacking"),
+	(31, "WithdrawalsPaused"),
+	(32, "NoUnattributedBalance"),
+	(33, "ReservedCommitmentValue"),
+	(34, "NoPendingAnnouncement"),
+	(35, "StaleAnnouncement"),
+	(36, "AnnouncementExpired"),
+	(37, "HasherMismatch"),
+	(38, "VerifyingKeyNotSet"),
+	(39, "RecipientCannotBeCreated"),
+	(40, "CircuitVersionMismatch"),
+	(41, "EmptySplit"),
+	(42, "ProofAnchorRequired"),
+	(43, "ProofAnchorExpired"),
+	(44, "InvalidBundle"),
+	(45, "NoteKindMismatch"),
+	(46, "InvalidNoteAgeBounds"),
+	(47, "NoteAgeRootRequired"),
+	(48, "UnknownMerkleRoot"),
+	(49, "NoteTooYoung"),
+	(50, "NoteTooOld"),
+	(51, "NotSponsored"),
+	(52, "SwapExpired"),
+	(53, "InvalidSwap"),
+	(54, "SwapAssetMismatch"),
+	(55, "InvalidBeneficiaryLength"),
+	(56, "NotBatcher"),
+	(57, "EmptyBatch"),
+	(58, "BatchRootMismatch"),
+	(59, "BatchLimitExceeded"),
+	(60, "BackupSizeMismatch"),
+	(61, "NoNoteBackup"),
+	(62, "NoteAssetMismatch"),
+	(63, "VerificationBudgetExhausted"),
+	(64, "DuplicateAlertTag"),
+	(65, "InvalidOriginLocation"),
+	(66, "TreeUnderMaintenance"),
+	(67, "NoTreeRebuild"),
+	(68, "InvalidRebuildChunk"),
+	(69, "RebuiltRootMismatch"),
+	(70, "RandomnessReused"),
+	(71, "TooManyProvingJobs"),
+	(72, "BountyTooLow"),
+	(73, "ProvingJobNotFound"),
+	(74, "ProvingJobAlreadyClaimed"),
+	(75, "NotJobProver"),
+	(76, "ProvingJobLapsed"),
+	(77, "ProvingJobNotLapsed"),
+	(78, "AssetWindingDown"),
+	(79, "WrongWindDownPhase"),
+	(80, "WindDownPhaseRunning"),
+	(81, "ClaimWindowClosed"),
+	(82, "NoteNotRefundable"),
+	(83, "NoPayouts"),
+	(84, "PayoutSumMismatch"),
+	(85, "AdminActionAlreadyProposed"),
+	(86, "AdminActionNotProposed"),
+	(87, "AdminActionNotApproved"),
+	(88, "AdminApprovalExpired"),
+	(89, "InsufficientBalance"),
+	(90, "VerifyingKeyTooLarge"),
+	(91, "AssetInactive"),
+	(92, "AssetAlreadyRegistered"),
+	(93, "SignedXcmDepositsDisabled"),
+	(94, "InvalidDenomination"),
+	(95, "UnsignedWithdrawalsDisabled"),
+	(96, "UnsignedRelayFeeNotPaid"),
+	(97, "MalformedVerifyingKey"),
+	(98, "BridgePaused"),
+];
+
+#[test]
+fn error_codes_match_the_snapshot() {
+	let codes: Vec<(u16, &str)> =
+		ERROR_CODES.iter().map(|(code, name, _)| (*code, *name)).collect();
+	assert_eq!(
+		codes, ERROR_CODE_SNAPSHOT,
+		"error codes changed: append new errors with the next free code, here and in \
+		 `error_codes::ERROR_CODES`; never renumber nor reuse a code"
+	);
+
+	let distinct_codes: BTreeSet<u16> = ERROR_CODES.iter().map(|(code, ..)| *code).collect();
+	let distinct_names: BTreeSet<&str> = ERROR_CODES.iter().map(|(_, name, _)| *name).collect();
+	assert_eq!(distinct_codes.len(), ERROR_CODES.len(), "an error code is used twice");
+	assert_eq!(distinct_names.len(), ERROR_CODES.len(), "an error has two codes");
+	assert!(!distinct_codes.contains(&UNKNOWN_ERROR_CODE));
+}
+
+#[test]
+fn every_error_has_a_code() {
+	let TypeDef::Variant(errors) = Error::<Test>::type_info().type_def else {
+		panic!("`Error` is an enum");
+	};
+	for variant in errors.variants {
+		assert!(
+			error_code(variant.name).is_some(),
+			"`Error::{}` has no code: register it in `error_codes::ERROR_CODES`",
+			variant.name
+		);
+	}
+}
+
+#[test]
+fn errors_convert_to_their_codes() {
+	assert_eq!(u16::from(Error::<Test>::CommitmentAlreadyExists), 1);
+	assert_eq!(u16::from(Error::<Test>::AmountOverflow), 5);
+
+	let code = u16::from(Error::<Test>::NullifierAlreadyUsed);
+	assert_eq!(error_description(code), "Nullifier has already been used (double-spend attempt)");
+	assert_eq!(error_description(UNKNOWN_ERROR_CODE), "Unknown error");
+}
@@ -0,0 +1,253 @@
+//! Stable codes of the pallet's errors
+//!
+//! A `DispatchError::Module` names an error by its position in `Error`, which is only
+//! stable as long as nobody reorders the enum, and carries no description. Support tooling
+//! reads errors through this table instead: every variant has a code that is never
+//! renumbered nor reused, and a human description (see `error_description`). New
+//! variants are appended with the next free code; `error_code_tests` checks the table
+//! against a snapshot and against `Error` itself.
+
+/// Code of an error missing from `ERROR_CODES` (never assigned)
+pub const UNKNOWN_ERROR_CODE: u16 = 0;
+
+/// Code, variant name and description of every `Error` variant
+pub const ERROR_CODES: &[(u16, &str, &str)] = &[
+	(1, "CommitmentAlreadyExists", "Commitment already exists"),
+	(2, "NullifierAlreadyUsed", "Nullifier has already been used (double-spend attempt)"),
+	(3, "CommitmentNotFound", "Commitment does not exist"),
+	(4, "InvalidProof", "Invalid proof (for future zkSNARK verification)"),
+	(5, "AmountOverflow", "Amount overflow"),
+	(6, "InvalidRandomness", "Invalid randomness"),
+	(7, "InvalidDecoySchedule", "Decoy interval must be non-zero"),
+	(8, "DecoyNotFound", "Commitment is not an outstanding decoy note"),
+	(9, "AssetNotRegistered", "Asset is not registered"),
+	(10, "InvalidAbandonmentPeriod", "Abandonment period must be non-zero"),
+	(11, "LeafNotFound", "No leaf exists at the given index"),
+	(12, "CommitmentDenied", "Commitment has been denied and can no longer be withdrawn"),
+	(13, "NoteAlreadySpent", "Note has already been spent"),
+	(
+		14,
+		"NoteNotAbandonable",
+		"Note cannot be swept (its asset had no abandonment period when deposited)",
+	),
+	(15, "NoteNotYetAbandoned", "Note is younger than its asset's abandonment period"),
+	(16, "ClaimNotFound", "Claim does not exist"),
+	(
+		17,
+		"ClaimNotRedeemable",
+		"Claim cannot be paid out yet (the transfer to the beneficiary still fails)",
+	),
+	(
+		18,
+		"BelowExistentialDeposit",
+		"Payout would leave the recipient below the asset's existential deposit",
+	),
+	(19, "InvalidBeneficiary", "The exporter does not accept the beneficiary"),
+	(20, "ExportFailed", "The exporter failed to send the withdrawal"),
+	(21, "NoTrappedAssets", "No assets are trapped for this origin and asset"),
+	(22, "TreeFull", "The commitment tree has no free leaves"),
+	(23, "NoChannelToDestination", "No open channel to the withdrawal's first hop"),
+	(24, "DepositBelowMinimum", "The deposit is below the asset's minimum deposit"),
+	(25, "FeeExceedsMaximum", "The destination fee is above the caller's `max_fee`"),
+	(26, "FeeExceedsAmount", "The destination or relayer fee is above the withdrawn amount"),
+	(27, "InvalidFeeConfig", "The proportional fee is above one million parts per million"),
+	(28, "WithdrawalNotFound", "No queued withdrawal with this ID"),
+	(29, "WithdrawalNotFailed", "Only failed withdrawals can be retried"),
+	(
+		30,
+		"InsufficientReserveBacking",
+		"The withdrawal exceeds what the pool holds on the asset's reserve chain",
+	),
+	(
+		31,
+		"WithdrawalsPaused",
+		"Withdrawals are paused, by the admin or the solvency circuit breaker",
+	),
+	(32, "NoUnattributedBalance", "No unattributed funds for this origin and asset"),
+	(
+		33,
+		"ReservedCommitmentValue",
+		"The commitment or nullifier is a reserved hash value (all zeros)",
+	),
+	(34, "NoPendingAnnouncement", "No verifying key is announced"),
+	(
+		35,
+		"StaleAnnouncement",
+		"The announced verifying key was made for other circuits or another runtime",
+	),
+	(
+		36,
+		"AnnouncementExpired",
+		"The announced verifying key was not applied within `AnnouncementTtl` blocks",
+	),
+	(
+		37,
+		"HasherMismatch",
+		"The verifying key was generated for another note hasher than the runtime's",
+	),
+	(38, "VerifyingKeyNotSet", "No verifying key is set"),
+	(
+		39,
+		"RecipientCannotBeCreated",
+		"The payout cannot create the recipient's account (e.g. an insufficient asset)",
+	),
+	(
+		40,
+		"CircuitVersionMismatch",
+		"The proof was made for other circuits than the verifying key's",
+	),
+	(41, "EmptySplit", "A split deposit lists no commitments"),
+	(
+		42,
+		"ProofAnchorRequired",
+		"Withdrawals must be anchored to a recent block (`withdraw_anchored`)",
+	),
+	(43, "ProofAnchorExpired", "The anchor block hash is not one of the last `MaxProofAge` blocks"),
+	(
+		44,
+		"InvalidBundle",
+		"A bundle is empty, not ordered by strictly increasing asset ID, or has a zero amount",
+	),
+	(
+		45,
+		"NoteKindMismatch",
+		"A bundle note was withdrawn as a single-asset note, or the other way round",
+	),
+	(
+		46,
+		"InvalidNoteAgeBounds",
+		"The minimum note age exceeds the maximum, or the maximum is zero",
+	),
+	(
+		47,
+		"NoteAgeRootRequired",
+		"Notes of the asset have age bounds and must be withdrawn against a recent root \
+		 (`withdraw`, `withdraw_via_relayer`)",
+	),
+	(48, "UnknownMerkleRoot", "The merkle root is not in `RootHistory`"),
+	(49, "NoteTooYoung", "Leaves under the root may be younger than the asset's minimum note age"),
+	(50, "NoteTooOld", "Leaves under the root may be older than the asset's maximum note age"),
+	(
+		51,
+		"NotSponsored",
+		"The deposit is not eligible for sponsoring, or the pot cannot pay its fee",
+	),
+	(52, "SwapExpired", "The swap deadline has passed"),
+	(53, "InvalidSwap", "The legs of a swap are of the same asset or create the same note"),
+	(54, "SwapAssetMismatch", "A swap leg's asset is not the asset of the note it spends"),
+	(
+		55,
+		"InvalidBeneficiaryLength",
+		"The beneficiary is not as long as the destination's accounts",
+	),
+	(56, "NotBatcher", "The account is not a registered batcher"),
+	(57, "EmptyBatch", "A commitment batch lists no commitments"),
+	(58, "BatchRootMismatch", "The batch root is not the root of the listed commitments"),
+	(
+		59,
+		"BatchLimitExceeded",
+		"The block's commitment batches would exceed `MaxBatchLeavesPerBlock`",
+	),
+	(60, "BackupSizeMismatch", "A note backup is not exactly `MaxBackupLen` bytes long"),
+	(61, "NoNoteBackup", "The account has no note backup"),
+	(
+		62,
+		"NoteAssetMismatch",
+		"The spent note was shielded as another asset than the withdrawal claims",
+	),
+	(
+		63,
+		"VerificationBudgetExhausted",
+		"Withdrawals used this block's proof verification weight; retry in a later block",
+	),
+	(64, "DuplicateAlertTag", "The same alert tag is listed twice"),
+	(
+		65,
+		"InvalidOriginLocation",
+		"The origin of an XCM deposit is too deep or holds an oversized key",
+	),
+	(
+		66,
+		"TreeUnderMaintenance",
+		"The tree cache is being rebuilt; no leaf can be inserted until it is done",
+	),
+	(67, "NoTreeRebuild", "No tree cache rebuild is under way"),
+	(
+		68,
+		"InvalidRebuildChunk",
+		"A rebuild chunk must start at the next leaf to rehash, and hold between one and \
+		 `MAX_REBUILD_CHUNK` stored leaves",
+	),
+	(69, "RebuiltRootMismatch", "The rebuilt root differs from the root governance expected"),
+	(
+		70,
+		"RandomnessReused",
+		"The deposit's randomness was used by another deposit within `RandomnessReuseWindow` \
+		 blocks",
+	),
+	(71, "TooManyProvingJobs", "The proving job board holds `MaxProvingJobs` jobs"),
+	(72, "BountyTooLow", "The bounty is below `MinProvingBounty`"),
+	(73, "ProvingJobNotFound", "No proving job with this ID is on the board"),
+	(74, "ProvingJobAlreadyClaimed", "The proving job was already claimed"),
+	(75, "NotJobProver", "Only the prover that claimed the job can fulfil it"),
+	(76, "ProvingJobLapsed", "The proving job can no longer be claimed or fulfilled"),
+	(77, "ProvingJobNotLapsed", "The proving job is still open or its claim still running"),
+	(78, "AssetWindingDown", "The asset is being wound down"),
+	(79, "WrongWindDownPhase", "The asset is not in the phase of its wind-down this needs"),
+	(80, "WindDownPhaseRunning", "The current wind-down phase has not ended yet"),
+	(81, "ClaimWindowClosed", "Notes of the asset can no longer be withdrawn with a proof"),
+	(
+		82,
+		"NoteNotRefundable",
+		"The note has no record of its amount, so it cannot be refunded publicly",
+	),
+	(83, "NoPayouts", "A split withdrawal names no recipient"),
+	(
+		84,
+		"PayoutSumMismatch",
+		"The payouts of a split withdrawal do not add up to the withdrawn amount",
+	),
+	(
+		85,
+		"AdminActionAlreadyProposed",
+		"The admin action is already proposed and its proposal has not expired",
+	),
+	(86, "AdminActionNotProposed", "No admin action was proposed under this hash"),
+	(
+		87,
+		"AdminActionNotApproved",
+		"The admin action was not proposed and approved by the co-signer",
+	),
+	(
+		88,
+		"AdminApprovalExpired",
+		"The admin action's proposal expired before it was approved or executed",
+	),
+	(89, "InsufficientBalance", "The depositor's free balance does not cover the native deposit"),
+	(90, "VerifyingKeyTooLarge", "The verifying key is longer than the pallet stores"),
+	(
+		91,
+		"AssetInactive",
+		"The asset is registered but no longer accepts deposits (e.g. it is wound down)",
+	),
+	(92, "AssetAlreadyRegistered", "The asset is already registered"),
+	(93, "SignedXcmDepositsDisabled", "Signed XCM deposits are disabled on this chain"),
+	(94, "InvalidDenomination", "The amount is not one of the asset's fixed denominations"),
+	(95, "UnsignedWithdrawalsDisabled", "The chain takes no unsigned relayed withdrawals"),
+	(96, "UnsignedRelayFeeNotPaid", "An unsigned withdrawal does not pay the treasury its fee"),
+	(97, "MalformedVerifyingKey", "The verifying key does not decode"),
+	(98, "BridgePaused", "The bridge is paused"),
+];
+
+/// Code of the `Error` variant called `name`
+pub fn error_code(name: &str) -> Option<u16> {
+	ERROR_CODES.iter().find(|(_, variant, _)| *variant == name).map(|(code, ..)| *code)
+}
+
+/// Description of the error of `code`, for display off-chain
+pub fn error_description(code: u16) -> &'static str {
+	ERROR_CODES
+		.iter()
+		.find(|(known, ..)| *known == code)
+		.map_or("Unknown error", |(_, _, description)| *description)
+}
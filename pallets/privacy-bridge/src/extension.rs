@@ -0,0 +1,380 @@
+//! Transaction pool checks of the privacy bridge
+//!
+//! `CheckDuplicateCommitment` rejects deposit transactions whose commitment is already in
+//! the tree, so they fail in the pool instead of taking block space to fail at dispatch:
+//!
+//! ```ignore
+//! pub type TxExtension = (
+//!     ...
+//!     pallet_privacy_bridge::CheckDuplicateCommitment<Runtime>,
+//! );
+//! ```
+//!
+//! `deposit_from_xcm` derives its commitment the way dispatch does, after the registry
+//! lookup. A duplicate submitted as a transaction is rejected even when dispatch would
+//! accept it as a redelivery: a transaction brings no funds to hold for refunding. Deposits
+//! arriving as XCM (`Transact` or the shield executor) never pass the pool and keep the
+//! dispatch-level handling.
+//!
+//! `CheckVerificationBudget` keeps withdrawals out of blocks whose proof verification budget
+//! (`Config::MaxVerificationWeightPerBlock`) they would exceed. They are reported as
+//! exhausting the block's resources, so the pool keeps them for a later block instead of
+//! dropping them.
+//!
+//! `SponsorDeposit` wraps the fee payment extension so the sponsorship pot pays the fees of
+//! small native deposits (see `Pallet::sponsored_amount`). It keeps the identifier and
+//! metadata of the extension it wraps, so wallets sign transactions as before:
+//!
+//! ```ignore
+//! pallet_privacy_bridge::SponsorDeposit<
+//!     Runtime,
+//!     pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+//! >,
+//! ```
+
+use core::marker::PhantomData;
+
+use alloc::vec::Vec;
+use codec::{Decode, Encode};
+use frame::deps::frame_support::{
+	pallet_prelude::Weight,
+	traits::{Get, IsSubType},
+};
+use scale_info::TypeInfo;
+use sp_core::H256;
+use sp_runtime::{
+	impl_tx_ext_default,
+	traits::{
+		AsSystemOriginSigner, DispatchInfoOf, DispatchOriginOf, Dispatchable, Implication,
+		PostDispatchInfoOf, TransactionExtension, TransactionExtensionMetadata, ValidateResult,
+	},
+	transaction_validity::{
+		InvalidTransaction, TransactionSource, TransactionValidity, TransactionValidityError,
+		ValidTransaction,
+	},
+	DispatchResult,
+};
+
+use crate::{Call, Commitments, Config, Pallet};
+
+/// Custom invalid-transaction code of a local deposit (`deposit`, `deposit_with_receipt`,
+/// `deposit_split`, `deposit_bundle` or `submit_commitment_batch`) or a `shielded_swap`
+/// whose commitment exists
+pub const DUPLICATE_COMMITMENT: u8 = 1;
+
+/// Custom invalid-transaction code of a `deposit_from_xcm` whose commitment exists
+pub const DUPLICATE_XCM_COMMITMENT: u8 = 2;
+
+/// Reject deposits of existing commitments before block inclusion
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckDuplicateCommitment<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckDuplicateCommitment<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+
+	/// Invalid-transaction code of a deposit `call` of an existing commitment
+	pub fn duplicate_code(call: &Call<T>) -> Option<u8> {
+		let exists = |commitment: &H256| Commitments::<T>::contains_key(commitment);
+		match call {
+			Call::deposit { amount, asset_id, note_key } |
+			Call::deposit_with_receipt { amount, asset_id, note_key } =>
+				exists(&Pallet::<T>::generate_commitment(*amount, *asset_id, note_key))
+					.then_some(DUPLICATE_COMMITMENT),
+			Call::deposit_split { commitments, .. } =>
+				commitments.iter().any(exists).then_some(DUPLICATE_COMMITMENT),
+			Call::submit_commitment_batch { commitments, .. } =>
+				commitments.iter().any(exists).then_some(DUPLICATE_COMMITMENT),
+			Call::deposit_bundle { assets, note_key } => {
+				let randomness =
+					crate::primitives::note_randomness::<T::Hasher>(note_key, &[0u8; 32]);
+				exists(&crate::primitives::bundle_commitment::<T::Hasher>(assets, &randomness))
+					.then_some(DUPLICATE_COMMITMENT)
+			},
+			Call::shielded_swap { first, second, .. } =>
+				[first, second]
+					.iter()
+					.any(|leg| exists(&leg.output_commitment))
+					.then_some(DUPLICATE_COMMITMENT),
+			Call::deposit_from_xcm { asset_id, amount, origin_location, note_key } => {
+				// Unregistered assets and invalid origins are left for dispatch to reject
+				if !crate::xcm_config::is_valid_origin_location(origin_location) {
+					return None;
+				}
+				let registered = Pallet::<T>::registered_asset(asset_id)?;
+				let commitment = crate::xcm_config::xcm_commitment_data::<T::Hasher>(
+					*amount,
+					registered.local_id,
+					note_key,
+					origin_location,
+				);
+				exists(&commitment).then_some(DUPLICATE_XCM_COMMITMENT)
+			},
+			_ => None,
+		}
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckDuplicateCommitment<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> core::fmt::Debug for CheckDuplicateCommitment<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		write!(f, "CheckDuplicateCommitment")
+	}
+}
+
+impl<T: Config + Send + Sync> TransactionExtension<T::RuntimeCall> for CheckDuplicateCommitment<T>
+where
+	T::RuntimeCall: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckDuplicateCommitment";
+	type Implicit = ();
+	type Val = ();
+	type Pre = ();
+
+	fn weight(&self, call: &T::RuntimeCall) -> Weight {
+		let reads = match call.is_sub_type() {
+			Some(
+				Call::deposit { .. } | Call::deposit_with_receipt { .. } | Call::deposit_bundle { .. },
+			) => 1,
+			Some(Call::deposit_split { commitments, .. }) => commitments.len() as u64,
+			Some(Call::submit_commitment_batch { commitments, .. }) => commitments.len() as u64,
+			Some(Call::shielded_swap { .. }) => 2,
+			// The registry, then the commitment
+			Some(Call::deposit_from_xcm { .. }) => 2,
+			_ => 0,
+		};
+		T::DbWeight::get().reads(reads)
+	}
+
+	fn validate(
+		&self,
+		origin: DispatchOriginOf<T::RuntimeCall>,
+		call: &T::RuntimeCall,
+		_info: &DispatchInfoOf<T::RuntimeCall>,
+		_len: usize,
+		_self_implicit: Self::Implicit,
+		_inherited_implication: &impl Implication,
+		_source: TransactionSource,
+	) -> ValidateResult<Self::Val, T::RuntimeCall> {
+		if let Some(code) = call.is_sub_type().and_then(Self::duplicate_code) {
+			return Err(TransactionValidityError::from(InvalidTransaction::Custom(code)));
+		}
+		Ok((ValidTransaction::default(), (), origin))
+	}
+
+	impl_tx_ext_default!(T::RuntimeCall; prepare);
+}
+
+/// Defer withdrawals past the block's proof verification budget
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckVerificationBudget<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckVerificationBudget<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckVerificationBudget<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> core::fmt::Debug for CheckVerificationBudget<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		write!(f, "CheckVerificationBudget")
+	}
+}
+
+impl<T: Config + Send + Sync> TransactionExtension<T::RuntimeCall> for CheckVerificationBudget<T>
+where
+	T::RuntimeCall: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckVerificationBudget";
+	type Implicit = ();
+	type Val = ();
+	type Pre = ();
+
+	fn weight(&self, call: &T::RuntimeCall) -> Weight {
+		match call.is_sub_type().map(Pallet::<T>::proofs_verified) {
+			Some(proofs) if proofs > 0 => T::DbWeight::get().reads(1),
+			_ => Weight::zero(),
+		}
+	}
+
+	fn validate(
+		&self,
+		origin: DispatchOriginOf<T::RuntimeCall>,
+		call: &T::RuntimeCall,
+		_info: &DispatchInfoOf<T::RuntimeCall>,
+		_len: usize,
+		_self_implicit: Self::Implicit,
+		_inherited_implication: &impl Implication,
+		_source: TransactionSource,
+	) -> ValidateResult<Self::Val, T::RuntimeCall> {
+		let proofs = call.is_sub_type().map_or(0, Pallet::<T>::proofs_verified);
+		if proofs > 0 && Pallet::<T>::verification_budget_after(proofs).is_none() {
+			return Err(TransactionValidityError::from(InvalidTransaction::ExhaustsResources));
+		}
+		Ok((ValidTransaction::default(), (), origin))
+	}
+
+	impl_tx_ext_default!(T::RuntimeCall; prepare);
+}
+
+/// Pay the fees of small deposits from the sponsorship pot, and others with `S`
+///
+/// `S` is the runtime's fee payment extension. Sponsored deposits skip it entirely; the
+/// pot pays `SponsoredFee` for them when the transaction is applied. Once the pot is
+/// exhausted or a rate limit is reached, deposits pay their own fees again.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct SponsorDeposit<T: Config + Send + Sync, S>(pub S, PhantomData<T>);
+
+impl<T: Config + Send + Sync, S> SponsorDeposit<T, S> {
+	pub fn new(inner: S) -> Self {
+		Self(inner, PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync, S> From<S> for SponsorDeposit<T, S> {
+	fn from(inner: S) -> Self {
+		Self::new(inner)
+	}
+}
+
+impl<T: Config + Send + Sync, S: core::fmt::Debug> core::fmt::Debug for SponsorDeposit<T, S> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		write!(f, "SponsorDeposit<{:?}>", self.0)
+	}
+}
+
+/// Validation or preparation state of `SponsorDeposit`
+pub enum Sponsorship<I, A> {
+	/// The fee is paid with the wrapped extension
+	Charged(I),
+	/// The pot pays the fee of the account's deposit of the amount
+	Sponsored(A, u128),
+}
+
+impl<T: Config + Send + Sync, S> TransactionExtension<T::RuntimeCall> for SponsorDeposit<T, S>
+where
+	S: TransactionExtension<T::RuntimeCall>,
+	T::RuntimeCall: IsSubType<Call<T>>,
+	<T::RuntimeCall as Dispatchable>::RuntimeOrigin: AsSystemOriginSigner<T::AccountId> + Clone,
+{
+	const IDENTIFIER: &'static str = S::IDENTIFIER;
+	type Implicit = S::Implicit;
+	type Val = Sponsorship<S::Val, T::AccountId>;
+	type Pre = Sponsorship<S::Pre, T::AccountId>;
+
+	fn implicit(&self) -> Result<Self::Implicit, TransactionValidityError> {
+		self.0.implicit()
+	}
+
+	fn metadata() -> Vec<TransactionExtensionMetadata> {
+		S::metadata()
+	}
+
+	fn weight(&self, call: &T::RuntimeCall) -> Weight {
+		let sponsoring = match call.is_sub_type() {
+			// The pot, both rate limit counters and the pot account
+			Some(Call::deposit { .. }) => T::DbWeight::get().reads_writes(4, 4),
+			_ => Weight::zero(),
+		};
+		self.0.weight(call).saturating_add(sponsoring)
+	}
+
+	fn validate(
+		&self,
+		origin: DispatchOriginOf<T::RuntimeCall>,
+		call: &T::RuntimeCall,
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+		self_implicit: Self::Implicit,
+		inherited_implication: &impl Implication,
+		source: TransactionSource,
+	) -> ValidateResult<Self::Val, T::RuntimeCall> {
+		let sponsored = origin.as_system_origin_signer().and_then(|who| {
+			let amount = Pallet::<T>::sponsored_amount(who, call.is_sub_type()?)?;
+			Some((who.clone(), amount))
+		});
+		if let Some((who, amount)) = sponsored {
+			return Ok((ValidTransaction::default(), Sponsorship::Sponsored(who, amount), origin));
+		}
+
+		let (valid, val, origin) =
+			self.0.validate(origin, call, info, len, self_implicit, inherited_implication, source)?;
+		Ok((valid, Sponsorship::Charged(val), origin))
+	}
+
+	fn prepare(
+		self,
+		val: Self::Val,
+		origin: &DispatchOriginOf<T::RuntimeCall>,
+		call: &T::RuntimeCall,
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		match val {
+			Sponsorship::Charged(val) =>
+				self.0.prepare(val, origin, call, info, len).map(Sponsorship::Charged),
+			Sponsorship::Sponsored(who, amount) => {
+				// Earlier transactions of the block may have drained the pot or used the limits
+				call.is_sub_type()
+					.and_then(|call| Pallet::<T>::sponsored_amount(&who, call))
+					.ok_or(InvalidTransaction::Payment)?;
+				Pallet::<T>::sponsor_deposit(&who, amount)
+					.map_err(|_| InvalidTransaction::Payment)?;
+				Ok(Sponsorship::Sponsored(who, amount))
+			},
+		}
+	}
+
+	fn post_dispatch_details(
+		pre: Self::Pre,
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		post_info: &PostDispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+		result: &DispatchResult,
+	) -> Result<Weight, TransactionValidityError> {
+		match pre {
+			Sponsorship::Charged(pre) => S::post_dispatch_details(pre, info, post_info, len, result),
+			Sponsorship::Sponsored(..) => Ok(Weight::zero()),
+		}
+	}
+
+	fn bare_validate(
+		call: &T::RuntimeCall,
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+	) -> TransactionValidity {
+		S::bare_validate(call, info, len)
+	}
+
+	fn bare_validate_and_prepare(
+		call: &T::RuntimeCall,
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+	) -> Result<(), TransactionValidityError> {
+		S::bare_validate_and_prepare(call, info, len)
+	}
+
+	fn bare_post_dispatch(
+		info: &DispatchInfoOf<T::RuntimeCall>,
+		post_info: &mut PostDispatchInfoOf<T::RuntimeCall>,
+		len: usize,
+		result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		S::bare_post_dispatch(info, post_info, len, result)
+	}
+}
@@ -0,0 +1,487 @@
+//! Fuzzing of the deposit → prove → withdraw pipeline
+//!
+//! Random but valid sequences of operations (deposits of random amounts and
+//! denominations, private transfers through `shielded_swap`, withdrawals and XCM exits) run
+//! against the mock runtime, interleaved with adversarial withdrawals: bit-flipped proofs
+//! and commitments, swapped public inputs, replayed nullifiers, notes spent as another
+//! asset. After every operation the pallet's invariants (`Pallet::do_try_state`, shared
+//! with `try_state`) and the harness's model of the pool are checked:
+//!
+//! - the shielded value of every asset is that of the notes the model holds unspent;
+//! - every nullifier marked used is one of a note the model spent;
+//! - every accepted withdrawal spent an unspent note the model knows, as its asset and for
+//!   its amount, i.e. had a valid proof: tampered withdrawals must all be refused;
+//! - a refused operation leaves storage untouched, and nothing panics.
+//!
+//! Valid deposits, withdrawals and transfers must be accepted. Exits may be refused (the
+//! mock has no route for every asset), as long as they change nothing. Amounts are not
+//! bound on chain until proofs are verified (see `attack_tests`), so no mutation claims
+//! another amount.
+//!
+//! Runs are seeded and reproducible. The harness is ignored by default:
+//!
+//! ```text
+//! cargo test -p pallet-privacy-bridge fuzz -- --ignored
+//! CLOAK_FUZZ_SEED=42 cargo test -p pallet-privacy-bridge fuzz -- --ignored
+//! ```
+//!
+//! A failing run is shrunk to the fewest operations that still fail, and printed as a
+//! script to paste into a test.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::circuit::CIRCUIT_VERSION;
+use crate::shield::{PayoutTarget, ProofEnvelope, UnshieldInputs};
+use crate::test_support::{flip_envelope_bit, flip_hash_bit, swap_public_inputs};
+use crate::xcm_config::CustodyMode;
+use crate::{mock::*, NullifierSet, Pallet, SwapLeg, TotalShielded, NATIVE_ASSET_ID};
+use frame::deps::frame_support::traits::{fungible, fungibles};
+use frame::testing_prelude::*;
+use rand_chacha::{
+	rand_core::{RngCore, SeedableRng},
+	ChaCha20Rng,
+};
+use sp_core::H256;
+use sp_runtime::StateVersion;
+use staging_xcm::v5::{AssetId, Location};
+
+/// Seed of the one run to replay; without it, seeds from 0 are run
+pub const SEED_VAR: &str = "CLOAK_FUZZ_SEED";
+/// Number of seeds to run, `DEFAULT_RUNS` if unset
+pub const RUNS_VAR: &str = "CLOAK_FUZZ_RUNS";
+/// Operations per run, `DEFAULT_STEPS` if unset
+pub const STEPS_VAR: &str = "CLOAK_FUZZ_STEPS";
+
+const DEFAULT_RUNS: u64 = 32;
+const DEFAULT_STEPS: u64 = 200;
+
+/// Local ID of the relay chain's asset, registered in every run
+const RELAY_ASSET: u32 = 1;
+/// Accounts depositing; withdrawals also go to two accounts holding nothing
+const USERS: u64 = 4;
+/// Balance of either asset every user starts with
+const USER_BALANCE: u128 = 1_000_000;
+/// Values of notes deposited as fixed denominations
+const DENOMINATIONS: [u128; 4] = [10, 100, 1_000, 10_000];
+/// Account tampered withdrawals try to pay
+const ATTACKER: u64 = 66;
+
+/// Operation of a run
+///
+/// Notes are picked when the operation runs, as `note` modulo the number of notes it
+/// applies to, so an operation stays meaningful when shrinking drops earlier ones.
+#[derive(Clone, Debug)]
+enum Op {
+	/// Deposit a note of `amount` of `asset_id` from `who`
+	Deposit { who: u64, asset_id: u32, amount: u128, randomness: [u8; 32] },
+	/// Withdraw an unspent note to `recipient`
+	Withdraw { note: usize, recipient: u64 },
+	/// Withdraw an unspent note to the relay chain
+	Exit { note: usize },
+	/// Swap an unspent native note and an unspent relay asset note between their owners
+	Transfer { native: usize, relay: usize, randomness: [u8; 32] },
+	/// Withdraw a note with tampered inputs
+	Attack { note: usize, mutation: Mutation },
+	/// Move to the next block
+	NextBlock,
+}
+
+/// Tampering with a withdrawal
+#[derive(Clone, Copy, Debug)]
+enum Mutation {
+	/// An unspent note, with a bit of its proof flipped
+	FlippedProof { bit: usize },
+	/// An unspent note, with a bit of its commitment flipped
+	FlippedCommitment { bit: usize },
+	/// An unspent note, with its nullifier and commitment swapped
+	SwappedInputs,
+	/// A spent note, withdrawn again
+	Replay,
+	/// An unspent note, spent with the nullifier of a spent one
+	ReusedNullifier { spent: usize },
+	/// An unspent note, spent as the other asset
+	WrongAsset,
+}
+
+/// The model's record of a note
+#[derive(Clone, Debug)]
+struct Note {
+	asset_id: u32,
+	amount: u128,
+	commitment: H256,
+	nullifier: H256,
+	spent: bool,
+}
+
+impl Note {
+	/// Note of `amount` of `asset_id` shielded with `randomness`, whose secret is the
+	/// inverted randomness
+	fn new(asset_id: u32, amount: u128, randomness: &[u8; 32]) -> Self {
+		let commitment = Pallet::<Test>::generate_commitment(amount, asset_id, randomness);
+		let secret = randomness.map(|byte| !byte);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+		Note { asset_id, amount, commitment, nullifier, spent: false }
+	}
+
+	/// Public inputs of a valid withdrawal of the note
+	fn inputs(&self) -> UnshieldInputs {
+		UnshieldInputs {
+			nullifier: self.nullifier,
+			commitment: self.commitment,
+			asset_id: self.asset_id,
+			amount: self.amount,
+			anchor_block_hash: None,
+			merkle_root: None,
+		}
+	}
+}
+
+/// Operation a run failed at, and why
+#[derive(Debug)]
+struct Failure {
+	step: usize,
+	reason: String,
+}
+
+fn envelope() -> ProofEnvelope {
+	ProofEnvelope { circuit_version: CIRCUIT_VERSION, proof: mock_proof().into_inner() }
+}
+
+/// Register the native and the relay chain's asset, and fund the users and the pool
+fn setup() {
+	let assets = [(Location::here(), NATIVE_ASSET_ID), (Location::parent(), RELAY_ASSET)];
+	for (location, asset_id) in assets {
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			AssetId(location),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		assert!(Pallet::<Test>::registered_asset_by_local(asset_id).is_some());
+	}
+	assert_ok!(Assets::force_create(RuntimeOrigin::root(), RELAY_ASSET, 1, true, 1));
+	for who in 1..=USERS {
+		assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(
+			RELAY_ASSET,
+			&who,
+			USER_BALANCE
+		));
+		assert_ok!(<Balances as fungible::Mutate<u64>>::mint_into(&who, USER_BALANCE));
+	}
+	// Swaps move value between assets without moving tokens: the pool pays out either
+	assert_ok!(<Balances as fungible::Mutate<u64>>::mint_into(
+		&Pallet::<Test>::account_id(),
+		u128::from(USERS) * USER_BALANCE
+	));
+}
+
+/// Generate the operations of the run of `seed`
+fn generate(seed: u64, steps: u64) -> Vec<Op> {
+	let mut rng = ChaCha20Rng::seed_from_u64(seed);
+	let randomness = |rng: &mut ChaCha20Rng| {
+		let mut bytes = [0u8; 32];
+		rng.fill_bytes(&mut bytes);
+		bytes
+	};
+
+	(0..steps)
+		.map(|_| {
+			let note = rng.next_u32() as usize;
+			match rng.next_u32() % 100 {
+				0..=34 => {
+					let amount = if rng.next_u32() % 2 == 0 {
+						DENOMINATIONS[rng.next_u32() as usize % DENOMINATIONS.len()]
+					} else {
+						1 + u128::from(rng.next_u32() % 5_000)
+					};
+					Op::Deposit {
+						who: 1 + u64::from(rng.next_u32()) % USERS,
+						asset_id: rng.next_u32() % 2,
+						amount,
+						randomness: randomness(&mut rng),
+					}
+				},
+				35..=54 => {
+					let recipient = 1 + u64::from(rng.next_u32()) % (USERS + 2);
+					Op::Withdraw { note, recipient }
+				},
+				55..=61 => Op::Exit { note },
+				62..=69 => Op::Transfer {
+					native: note,
+					relay: rng.next_u32() as usize,
+					randomness: randomness(&mut rng),
+				},
+				70..=93 => {
+					let bit = rng.next_u32() as usize;
+					let mutation = match rng.next_u32() % 6 {
+						0 => Mutation::FlippedProof { bit },
+						1 => Mutation::FlippedCommitment { bit },
+						2 => Mutation::SwappedInputs,
+						3 => Mutation::Replay,
+						4 => Mutation::ReusedNullifier { spent: rng.next_u32() as usize },
+						_ => Mutation::WrongAsset,
+					};
+					Op::Attack { note, mutation }
+				},
+				_ => Op::NextBlock,
+			}
+		})
+		.collect()
+}
+
+/// Index of the `choice`-th (modulo their number) note matching `filter`
+fn pick(notes: &[Note], choice: usize, filter: impl Fn(&Note) -> bool) -> Option<usize> {
+	let matching: Vec<usize> =
+		notes.iter().enumerate().filter(|(_, note)| filter(note)).map(|(i, _)| i).collect();
+	(!matching.is_empty()).then(|| matching[choice % matching.len()])
+}
+
+fn storage_root() -> Vec<u8> {
+	sp_io::storage::root(StateVersion::V1)
+}
+
+/// Check a refused operation changed nothing
+fn expect_unchanged(before: &[u8], error: DispatchError) -> Result<(), String> {
+	if storage_root() != before {
+		return Err(format!("refused ({error:?}) but changed storage"));
+	}
+	Ok(())
+}
+
+/// Check a valid operation was accepted
+fn expect_accepted(result: DispatchResult) -> Result<(), String> {
+	result.map_err(|error| format!("refused a valid operation: {error:?}"))
+}
+
+/// Envelope and inputs of `mutation` applied to the `note`-th note, if there is one
+fn tamper(
+	notes: &[Note],
+	note: usize,
+	mutation: Mutation,
+) -> Option<(ProofEnvelope, UnshieldInputs)> {
+	let unspent = || {
+		let index = pick(notes, note, |candidate| !candidate.spent)?;
+		Some(notes[index].inputs())
+	};
+	let spent = |choice| pick(notes, choice, |candidate| candidate.spent).map(|i| &notes[i]);
+
+	Some(match mutation {
+		Mutation::FlippedProof { bit } => (flip_envelope_bit(&envelope(), bit), unspent()?),
+		Mutation::FlippedCommitment { bit } => {
+			let inputs = unspent()?;
+			let commitment = flip_hash_bit(inputs.commitment, bit);
+			(envelope(), UnshieldInputs { commitment, ..inputs })
+		},
+		Mutation::SwappedInputs => (envelope(), swap_public_inputs(unspent()?)),
+		Mutation::Replay => (envelope(), spent(note)?.inputs()),
+		Mutation::ReusedNullifier { spent: choice } => {
+			let nullifier = spent(choice)?.nullifier;
+			(envelope(), UnshieldInputs { nullifier, ..unspent()? })
+		},
+		Mutation::WrongAsset => {
+			let inputs = unspent()?;
+			let asset_id =
+				if inputs.asset_id == NATIVE_ASSET_ID { RELAY_ASSET } else { NATIVE_ASSET_ID };
+			(envelope(), UnshieldInputs { asset_id, ..inputs })
+		},
+	})
+}
+
+/// Run `op`, updating the model
+fn apply(op: &Op, notes: &mut Vec<Note>) -> Result<(), String> {
+	match *op {
+		Op::Deposit { who, asset_id, amount, randomness } => {
+			expect_accepted(PrivacyBridge::deposit(
+				RuntimeOrigin::signed(who),
+				amount,
+				asset_id,
+				randomness,
+			))?;
+			notes.push(Note::new(asset_id, amount, &randomness));
+		},
+		Op::Withdraw { note, recipient } => {
+			let Some(index) = pick(notes, note, |candidate| !candidate.spent) else {
+				return Ok(());
+			};
+			let Note { asset_id, amount, commitment, nullifier, .. } = notes[index];
+			expect_accepted(PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(recipient),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				amount,
+				asset_id,
+				mock_proof(),
+				None,
+			))?;
+			notes[index].spent = true;
+		},
+		Op::Exit { note } => {
+			let Some(index) = pick(notes, note, |candidate| !candidate.spent) else {
+				return Ok(());
+			};
+			let before = storage_root();
+			let beneficiary = Location::new(0, []);
+			let payout = PayoutTarget::Remote(Location::parent(), beneficiary);
+			match Pallet::<Test>::do_unshield(envelope(), notes[index].inputs(), payout) {
+				Ok(()) => notes[index].spent = true,
+				Err(error) => expect_unchanged(&before, error)?,
+			}
+		},
+		Op::Transfer { native, relay, randomness } => {
+			let pick_asset = |choice, asset_id| {
+				pick(notes, choice, |candidate| !candidate.spent && candidate.asset_id == asset_id)
+			};
+			let (Some(first), Some(second)) =
+				(pick_asset(native, NATIVE_ASSET_ID), pick_asset(relay, RELAY_ASSET))
+			else {
+				return Ok(());
+			};
+
+			// Each party gets a note of the same asset and value, of the other's randomness
+			let outputs = [first, second].map(|index| {
+				let mut randomness = randomness;
+				randomness[0] ^= index as u8;
+				randomness[1] ^= (index >> 8) as u8;
+				Note::new(notes[index].asset_id, notes[index].amount, &randomness)
+			});
+			let leg = |index: usize, output: &Note| SwapLeg {
+				proof_envelope: envelope(),
+				nullifier: notes[index].nullifier,
+				commitment: notes[index].commitment,
+				asset_id: notes[index].asset_id,
+				output_commitment: output.commitment,
+			};
+			expect_accepted(PrivacyBridge::shielded_swap(
+				RuntimeOrigin::signed(1),
+				leg(first, &outputs[0]),
+				leg(second, &outputs[1]),
+				System::block_number(),
+			))?;
+			notes[first].spent = true;
+			notes[second].spent = true;
+			notes.extend(outputs);
+		},
+		Op::Attack { note, mutation } => {
+			let Some((envelope, inputs)) = tamper(notes, note, mutation) else { return Ok(()) };
+			let before = storage_root();
+			match Pallet::<Test>::do_unshield(envelope, inputs, PayoutTarget::Local(ATTACKER)) {
+				Ok(()) => return Err(format!("accepted a withdrawal with {mutation:?}")),
+				Err(error) => expect_unchanged(&before, error)?,
+			}
+		},
+		Op::NextBlock => run_to_block(System::block_number() + 1),
+	}
+
+	Ok(())
+}
+
+/// Check the pallet's invariants, and that the pool matches the model
+fn check_invariants(notes: &[Note]) -> Result<(), String> {
+	Pallet::<Test>::do_try_state().map_err(|error| format!("invariant broken: {error:?}"))?;
+
+	for asset_id in [NATIVE_ASSET_ID, RELAY_ASSET] {
+		let unspent: u128 = notes
+			.iter()
+			.filter(|candidate| !candidate.spent && candidate.asset_id == asset_id)
+			.map(|note| note.amount)
+			.sum();
+		let shielded = TotalShielded::<Test>::get(asset_id);
+		if shielded != unspent {
+			return Err(format!("asset {asset_id}: {shielded} shielded, notes hold {unspent}"));
+		}
+	}
+
+	let used = NullifierSet::<Test>::iter().filter(|(_, used)| *used).count();
+	let spent = notes.iter().filter(|candidate| candidate.spent).count();
+	if used != spent {
+		return Err(format!("{used} nullifiers used for {spent} notes spent"));
+	}
+
+	Ok(())
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+	payload
+		.downcast_ref::<&str>()
+		.map(|message| message.to_string())
+		.or_else(|| payload.downcast_ref::<String>().cloned())
+		.unwrap_or_else(|| "panic".into())
+}
+
+/// Run `ops` on a fresh chain, checking the invariants after each
+fn run(ops: &[Op]) -> Result<(), Failure> {
+	new_test_ext().execute_with(|| {
+		setup();
+		let mut notes = Vec::new();
+		for (step, op) in ops.iter().enumerate() {
+			let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+				apply(op, &mut notes)?;
+				check_invariants(&notes)
+			}));
+			let reason = match outcome {
+				Ok(Ok(())) => continue,
+				Ok(Err(reason)) => reason,
+				Err(payload) => format!("panicked: {}", panic_message(&*payload)),
+			};
+			return Err(Failure { step, reason });
+		}
+		Ok(())
+	})
+}
+
+/// Drop the operations a failing run does not need to fail
+fn shrink(mut ops: Vec<Op>, mut failure: Failure) -> (Vec<Op>, Failure) {
+	ops.truncate(failure.step + 1);
+	for index in (0..ops.len()).rev() {
+		let mut candidate = ops.clone();
+		candidate.remove(index);
+		if let Err(smaller) = run(&candidate) {
+			ops = candidate;
+			failure = smaller;
+		}
+	}
+	(ops, failure)
+}
+
+/// Script reproducing a failure, to paste into a test of this module
+fn reproduction(seed: u64, ops: &[Op], failure: &Failure) -> String {
+	let mut script = format!(
+		"seed {seed} fails: {}\n\
+		 Replay it with {SEED_VAR}={seed}, or the {} operations it shrank to:\n\n\
+		 let ops = vec![\n",
+		failure.reason,
+		ops.len()
+	);
+	for op in ops {
+		let op = format!("{op:?}").replace("mutation: ", "mutation: Mutation::");
+		script.push_str(&format!("\tOp::{op},\n"));
+	}
+	script.push_str("];\nlet failure = run(&ops).unwrap_err();\n");
+	script.push_str(&format!("// fails at operation {}\n", failure.step));
+	script
+}
+
+fn env_number(var: &str) -> Option<u64> {
+	let value = std::env::var(var).ok()?;
+	Some(value.parse().unwrap_or_else(|_| panic!("{var} must be a number, not {value:?}")))
+}
+
+#[test]
+#[ignore = "fuzz harness: run with `-- --ignored`"]
+fn fuzz_deposit_prove_withdraw_pipeline() {
+	let seeds = match env_number(SEED_VAR) {
+		Some(seed) => seed..seed + 1,
+		None => 0..env_number(RUNS_VAR).unwrap_or(DEFAULT_RUNS),
+	};
+	let steps = env_number(STEPS_VAR).unwrap_or(DEFAULT_STEPS);
+
+	for seed in seeds {
+		let ops = generate(seed, steps);
+		if let Err(failure) = run(&ops) {
+			let (ops, failure) = shrink(ops, failure);
+			panic!("{}", reproduction(seed, &ops, &failure));
+		}
+	}
+}
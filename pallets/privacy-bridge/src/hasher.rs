@@ -0,0 +1,233 @@
+//! Note hashing backends
+//!
+//! Commitments, nullifiers and the commitment tree are hashed by the runtime's
+//! `Config::Hasher`, so deployments can pick the hash that suits them:
+//!
+//! - `Blake2Hasher`: Substrate's native hash, cheapest to compute in the runtime
+//! - `Keccak256Hasher`: matches Ethereum tooling, for bridges verifying notes there
+//! - `PoseidonHasher`: arithmetic-friendly, for the cheapest proving
+//!
+//! `SimpleHasher` wraps the XOR-based `simple_hash` and only exists for tests and with the
+//! `insecure-test-hash` feature, which `production` builds refuse. The withdrawal circuits
+//! hash with `circuit::CircuitHasher`, which only `PoseidonHasher` and `SimpleHasher`
+//! implement, so runtimes verifying proofs hash with Poseidon. The verifying key records
+//! which hasher its setup was generated for (`SetupMetadata`), so a key built for another
+//! hash is refused.
+
+use frame::prelude::*;
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use alloc::vec::Vec;
+use ark_bn254::Fr;
+
+use crate::poseidon;
+use crate::primitives::{bundle_preimage, BUNDLE_COMMITMENT_TAG, MAX_BUNDLE_SIZE};
+
+/// Identifies a note hasher, e.g. in the metadata of a trusted setup
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum HasherId {
+	Blake2,
+	Keccak256,
+	Poseidon,
+	/// The XOR-based test hash
+	Simple,
+}
+
+/// Hash functions of the shielded pool
+pub trait NoteHasher {
+	/// Which hasher this is
+	fn id() -> HasherId;
+
+	/// Commitment = Hash(amount || asset_id || randomness)
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256;
+
+	/// BundleCommitment = Hash(primitives::bundle_preimage(assets, randomness))
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256;
+
+	/// Nullifier = Hash(commitment || secret)
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256;
+
+	/// Parent node of the commitment tree
+	fn hash_pair(left: &H256, right: &H256) -> H256;
+}
+
+/// Input bytes of a commitment
+fn commitment_preimage(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> Vec<u8> {
+	let mut data = Vec::new();
+	data.extend_from_slice(&amount.to_le_bytes());
+	data.extend_from_slice(&asset_id.to_le_bytes());
+	data.extend_from_slice(randomness);
+	data
+}
+
+/// Concatenation of two 32-byte values
+fn concat(left: &[u8; 32], right: &[u8; 32]) -> [u8; 64] {
+	let mut data = [0u8; 64];
+	data[..32].copy_from_slice(left);
+	data[32..].copy_from_slice(right);
+	data
+}
+
+/// Blake2-256, Substrate's native hash
+pub struct Blake2Hasher;
+
+impl NoteHasher for Blake2Hasher {
+	fn id() -> HasherId {
+		HasherId::Blake2
+	}
+
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
+		BlakeTwo256::hash(&commitment_preimage(amount, asset_id, randomness))
+	}
+
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+		BlakeTwo256::hash(&bundle_preimage(assets, randomness))
+	}
+
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
+		BlakeTwo256::hash(&concat(commitment.as_fixed_bytes(), secret))
+	}
+
+	fn hash_pair(left: &H256, right: &H256) -> H256 {
+		BlakeTwo256::hash(&concat(left.as_fixed_bytes(), right.as_fixed_bytes()))
+	}
+}
+
+/// Keccak-256, for compatibility with Ethereum
+pub struct Keccak256Hasher;
+
+impl NoteHasher for Keccak256Hasher {
+	fn id() -> HasherId {
+		HasherId::Keccak256
+	}
+
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
+		sp_io::hashing::keccak_256(&commitment_preimage(amount, asset_id, randomness)).into()
+	}
+
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+		sp_io::hashing::keccak_256(&bundle_preimage(assets, randomness)).into()
+	}
+
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
+		sp_io::hashing::keccak_256(&concat(commitment.as_fixed_bytes(), secret)).into()
+	}
+
+	fn hash_pair(left: &H256, right: &H256) -> H256 {
+		sp_io::hashing::keccak_256(&concat(left.as_fixed_bytes(), right.as_fixed_bytes())).into()
+	}
+}
+
+/// Poseidon over the BN254 scalar field, with circomlib's parameters (see `poseidon`)
+///
+/// Byte inputs are reduced into field elements, and the output element is encoded
+/// little-endian.
+pub struct PoseidonHasher;
+
+impl NoteHasher for PoseidonHasher {
+	fn id() -> HasherId {
+		HasherId::Poseidon
+	}
+
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
+		poseidon::poseidon_commitment(amount, asset_id, randomness)
+	}
+
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+		let mut inputs = Vec::from([Fr::from(BUNDLE_COMMITMENT_TAG)]);
+		for index in 0..MAX_BUNDLE_SIZE as usize {
+			let (asset_id, amount) = assets.get(index).copied().unwrap_or_default();
+			inputs.extend([Fr::from(asset_id), Fr::from(amount)]);
+		}
+		inputs.push(poseidon::element(randomness));
+		poseidon::to_h256(poseidon::poseidon_hash(&inputs))
+	}
+
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
+		let inputs = [poseidon::element(commitment.as_bytes()), poseidon::element(secret)];
+		poseidon::to_h256(poseidon::poseidon_hash(&inputs))
+	}
+
+	fn hash_pair(left: &H256, right: &H256) -> H256 {
+		poseidon::poseidon_hash_pair(left, right)
+	}
+}
+
+/// The XOR-based `simple_hash` (tests only: it is trivially invertible)
+#[cfg(any(test, feature = "insecure-test-hash"))]
+pub struct SimpleHasher;
+
+#[cfg(any(test, feature = "insecure-test-hash"))]
+impl NoteHasher for SimpleHasher {
+	fn id() -> HasherId {
+		HasherId::Simple
+	}
+
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
+		crate::simple_hash::generate_commitment(amount, asset_id, randomness)
+	}
+
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+		crate::simple_hash::generate_bundle_commitment(assets, randomness)
+	}
+
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
+		crate::simple_hash::generate_nullifier(commitment, secret)
+	}
+
+	fn hash_pair(left: &H256, right: &H256) -> H256 {
+		crate::simple_hash::hash_pair(left, right)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Every hasher is deterministic and separates its inputs
+	fn check_hasher<H: NoteHasher>() {
+		let commitment = H::hash_commitment(100, 1, &[7u8; 32]);
+		assert_eq!(commitment, H::hash_commitment(100, 1, &[7u8; 32]));
+		assert_ne!(commitment, H::hash_commitment(101, 1, &[7u8; 32]));
+		assert_ne!(commitment, H::hash_commitment(100, 2, &[7u8; 32]));
+
+		let bundle = H::hash_bundle_commitment(&[(1, 100), (2, 5)], &[7u8; 32]);
+		assert_eq!(bundle, H::hash_bundle_commitment(&[(1, 100), (2, 5)], &[7u8; 32]));
+		assert_ne!(bundle, H::hash_bundle_commitment(&[(1, 100), (2, 6)], &[7u8; 32]));
+		assert_ne!(commitment, H::hash_bundle_commitment(&[(1, 100)], &[7u8; 32]));
+
+		let nullifier = H::hash_nullifier(&commitment, &[9u8; 32]);
+		assert_ne!(nullifier, H::hash_nullifier(&commitment, &[8u8; 32]));
+		assert_ne!(H::hash_pair(&commitment, &nullifier), H::hash_pair(&nullifier, &nullifier));
+	}
+
+	#[test]
+	fn test_blake2_hasher() {
+		check_hasher::<Blake2Hasher>();
+		assert_eq!(
+			Blake2Hasher::hash_commitment(100, 1, &[7u8; 32]),
+			BlakeTwo256::hash(&commitment_preimage(100, 1, &[7u8; 32]))
+		);
+	}
+
+	#[test]
+	fn test_keccak_hasher() {
+		check_hasher::<Keccak256Hasher>();
+	}
+
+	#[test]
+	fn test_poseidon_hasher() {
+		check_hasher::<PoseidonHasher>();
+		// Unlike the XOR hash, the order of children matters
+		let (left, right) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		assert_ne!(PoseidonHasher::hash_pair(&left, &right), PoseidonHasher::hash_pair(&right, &left));
+	}
+
+	#[test]
+	fn test_hashers_disagree() {
+		let blake2 = Blake2Hasher::hash_commitment(100, 1, &[7u8; 32]);
+		assert_ne!(blake2, Keccak256Hasher::hash_commitment(100, 1, &[7u8; 32]));
+		assert_ne!(blake2, PoseidonHasher::hash_commitment(100, 1, &[7u8; 32]));
+		assert_ne!(blake2, SimpleHasher::hash_commitment(100, 1, &[7u8; 32]));
+	}
+}
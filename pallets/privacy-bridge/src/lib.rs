@@ -27,9 +27,16 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(all(feature = "insecure-test-hash", feature = "production"))]
+compile_error!(
+	"`insecure-test-hash` enables the XOR-based `simple_hash`, which must never reach a \
+	 `production` runtime"
+);
+
 extern crate alloc;
 
 pub use pallet::*;
+pub use extension::{CheckDuplicateCommitment, CheckVerificationBudget, SponsorDeposit};
 
 #[cfg(test)]
 mod mock;
@@ -37,6 +44,9 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(test)]
+mod test_support;
+
 pub mod weights;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -46,31 +56,122 @@ mod benchmarking;
 pub mod circuit;
 pub mod zksnark;
 
-// Week 3: Simple hash for zkSNARK compatibility
+// Week 3: Simple hash for zkSNARK compatibility (insecure: tests and tooling only)
+#[cfg(any(test, feature = "insecure-test-hash"))]
 pub mod simple_hash;
 
 // Week 3: Merkle tree for commitment anonymity
 pub mod merkle_tree;
 
+// Configurable commitment, nullifier and tree hashing
+pub mod hasher;
+
+// Poseidon over BN254 with circomlib's parameters, natively and in circuit
+pub mod poseidon;
+
+// Stable note derivations for wallets and downstream pallets
+pub mod primitives;
+
+// Delegated proving: outsourced merkle membership proofs (over `simple_hash` for now)
+#[cfg(any(test, feature = "insecure-test-hash"))]
+pub mod delegated_proving;
+
 // Week 4: XCM cross-chain integration
 pub mod xcm_config;
 
+// Outbound bridge adapters (XCM and non-XCM exits)
+pub mod bridge_adapter;
+
+// Runtime API for wallets (withdrawal dry runs)
+pub mod runtime_api;
+
+// Shielding and unshielding on behalf of other pallets of the runtime
+pub mod shield;
+
+// Transaction pool checks (duplicate commitments)
+pub mod extension;
+
+// Operator metrics (deposits, withdrawals, queues)
+pub mod metrics;
+
+// Randomness of protocol-generated values (decoys, payout order)
+pub mod randomness;
+
+// Stable codes and descriptions of the pallet's errors
+pub mod error_codes;
+
+// Storage migrations between pallet versions
+pub mod migrations;
+
 #[cfg(test)]
 mod zksnark_integration_test;
 
 #[cfg(test)]
 mod xcm_tests;
 
+#[cfg(test)]
+mod attack_tests;
+
+#[cfg(test)]
+mod call_encoding_tests;
+
+#[cfg(test)]
+mod error_code_tests;
+
+#[cfg(test)]
+mod fuzz_tests;
+
+#[cfg(all(test, feature = "runtime-benchmarks"))]
+mod weight_budgets;
+
+#[cfg(all(test, feature = "runtime-benchmarks"))]
+mod weight_regression_tests;
+
+#[cfg(test)]
+mod xcm_simulator_tests;
+
 #[frame::pallet]
 pub mod pallet {
 	use frame::prelude::*;
+	use frame::deps::frame_support::{
+		storage::with_storage_layer,
+		traits::{
+			fungible, fungibles,
+			tokens::{DepositConsequence, Fortitude, Precision, Preservation, Provenance},
+			Randomness,
+		},
+		PalletId,
+	};
 	use sp_core::H256;
-	use sp_runtime::traits::{BlakeTwo256, Hash};
-	use alloc::vec::Vec;
+	use sp_runtime::{
+		traits::{AccountIdConversion, BlakeTwo256, Hash, One, Saturating, Zero},
+		helpers_128bit::multiply_by_rational_with_rounding,
+		PerThing, Permill, Rounding, SaturatedConversion,
+	};
+	use alloc::{
+		collections::{BTreeMap, BTreeSet},
+		vec,
+		vec::Vec,
+	};
 
 	// Week 4: XCM imports
-	use staging_xcm::v5::{AssetId as XcmAssetId, Location};
-	use crate::xcm_config::RegisteredAsset;
+	use staging_xcm::v5::{AssetId as XcmAssetId, InteriorLocation, Location};
+	use crate::xcm_config::{
+		beneficiary_from_encoded, sibling_para_id, AccountFormat, CustodyMode, Denominations,
+		DepositReceipt, RegisteredAsset, WithdrawalRoute, MAX_OPEN_CHANNELS,
+	};
+	use crate::randomness;
+	use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+	use crate::bridge_adapter::{
+		ExportError, ExportWithdrawal, MessageId, SendDepositReceipt, VersionedDestination,
+		MAX_BENEFICIARY_LEN,
+	};
+	use crate::merkle_tree::{self, TREE_DEPTH};
+	use crate::primitives::{self, NoteBundle, MAX_BUNDLE_SIZE};
+	use crate::hasher::{HasherId, NoteHasher};
+	use crate::metrics::BridgeMetrics;
+	use crate::shield::{PayoutTarget, ProofEnvelope, UnshieldInputs};
+	use crate::zksnark::{VerifyError, WithdrawalVerifier};
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
 	#[pallet::config]
@@ -79,9 +180,289 @@ pub mod pallet {
 
 		/// A type representing the weights required by the dispatchables of this pallet.
 		type WeightInfo: crate::weights::WeightInfo;
+
+		/// Hash of commitments, nullifiers and the commitment tree
+		///
+		/// Withdrawal proofs only verify when the circuits hash with it too
+		/// (`circuit::CircuitHasher`), as `PoseidonHasher` does.
+		type Hasher: NoteHasher;
+
+		/// Origin allowed to perform privileged bridge administration (e.g. governance)
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Treasury account that owns protocol-funded (decoy) notes
+		#[pallet::constant]
+		type TreasuryAccount: Get<Self::AccountId>;
+
+		/// Value committed in each decoy note (zero for pure anonymity-set padding)
+		#[pallet::constant]
+		type DecoyValue: Get<u128>;
+
+		/// Maximum number of decoy notes created in a single block
+		#[pallet::constant]
+		type MaxDecoysPerBlock: Get<u32>;
+
+		/// Native currency held by the pool and paid out by withdrawals
+		type Currency: fungible::Mutate<Self::AccountId, Balance = u128>;
+
+		/// Fungible assets backing registered non-native assets, keyed by local asset ID
+		type Fungibles: fungibles::Mutate<Self::AccountId, AssetId = u32, Balance = u128>;
+
+		/// Pallet ID, used to derive the pool account holding shielded value
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// Whether local payouts that would leave the recipient below the existential
+		/// deposit become claims (`true`) or are rejected before the note is spent (`false`)
+		#[pallet::constant]
+		type ClaimDustPayouts: Get<bool>;
+
+		/// Outbound leg of cross-chain withdrawals (XCM or another bridge)
+		type Exporter: ExportWithdrawal;
+
+		/// Origin of a chain resolving its trapped shield deposits (e.g. `EnsureXcm`)
+		type TrapOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = Location>;
+
+		/// Maximum number of queued withdrawals paid out by one outbound message
+		#[pallet::constant]
+		type MaxDepositsPerMessage: Get<u32>;
+
+		/// Location of this chain in the consensus universe, used to bring asset
+		/// locations into their canonical form before registry lookups
+		type UniversalLocation: Get<InteriorLocation>;
+
+		/// Outbound leg of deposit receipts to the chains cross-chain deposits came from
+		type ReceiptSender: SendDepositReceipt;
+
+		/// Reserve backing of one asset moving by more than this within a block
+		/// (in the asset's smallest unit) raises `ReserveBackingAnomaly`
+		#[pallet::constant]
+		type ReserveBackingAlertThreshold: Get<u128>;
+
+		/// Blocks between two solvency checks in `on_finalize` (zero disables them)
+		#[pallet::constant]
+		type SolvencyCheckInterval: Get<BlockNumberFor<Self>>;
+
+		/// Maximum number of assets one solvency check covers; larger registries are
+		/// checked over several runs
+		#[pallet::constant]
+		type MaxSolvencyChecks: Get<u32>;
+
+		/// Whether deposits only append their leaf, leaving `on_finalize` to hash the
+		/// block's leaves into the tree in one pass (otherwise each deposit does it)
+		#[pallet::constant]
+		type BatchTreeInsertion: Get<bool>;
+
+		/// Blocks after which an announced verifying key can no longer be applied
+		#[pallet::constant]
+		type AnnouncementTtl: Get<BlockNumberFor<Self>>;
+
+		/// Maximum number of notes one `deposit_split` creates
+		#[pallet::constant]
+		type MaxSplit: Get<u32>;
+
+		/// Number of recent merkle roots kept in `RootHistory`
+		#[pallet::constant]
+		type RootHistorySize: Get<u32>;
+
+		/// Number of recent blocks a withdrawal proof may be anchored to, or `None` to not
+		/// anchor proofs
+		///
+		/// When set, withdrawals must carry the hash of one of the last `MaxProofAge` blocks
+		/// (the proof's `anchor_block_hash` input), so a proof expires that many blocks after
+		/// it was made. Keep it within `frame_system`'s `BlockHashCount`.
+		#[pallet::constant]
+		type MaxProofAge: Get<Option<u32>>;
+
+		/// Largest native `deposit` whose transaction fee the sponsorship pot pays (zero
+		/// disables sponsoring, see `SponsorDeposit`)
+		#[pallet::constant]
+		type SponsorThreshold: Get<u128>;
+
+		/// Fee drawn from the sponsorship pot for each sponsored deposit
+		#[pallet::constant]
+		type SponsoredFee: Get<u128>;
+
+		/// Blocks per period of the sponsorship rate limits
+		#[pallet::constant]
+		type SponsorPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Sponsored deposits of one account per period
+		#[pallet::constant]
+		type MaxSponsoredPerAccount: Get<u32>;
+
+		/// Sponsored deposits of all accounts together per period
+		#[pallet::constant]
+		type MaxSponsoredPerPeriod: Get<u32>;
+
+		/// Operator metrics hook (`()` reports nothing)
+		type Metrics: BridgeMetrics;
+
+		/// Maximum number of commitments in one `submit_commitment_batch`
+		#[pallet::constant]
+		type MaxBatch: Get<u32>;
+
+		/// Commitments all batchers together may submit per block
+		#[pallet::constant]
+		type MaxBatchLeavesPerBlock: Get<u32>;
+
+		/// Size of a note backup: `set_note_backup` only stores blobs of exactly this many
+		/// bytes, so a backup's length says nothing about the notes in it
+		#[pallet::constant]
+		type MaxBackupLen: Get<u32>;
+
+		/// Native deposit per byte of a stored note backup, returned when it is cleared
+		#[pallet::constant]
+		type BackupDepositPerByte: Get<u128>;
+
+		/// Proof verification weight all withdrawals together may use per block
+		///
+		/// Each note spent with a proof counts `proof_verification_weight`. Withdrawals past
+		/// the limit fail with `VerificationBudgetExhausted`, and `CheckVerificationBudget`
+		/// leaves them in the pool for a later block.
+		#[pallet::constant]
+		type MaxVerificationWeightPerBlock: Get<Weight>;
+
+		/// Source of the randomness of values the pallet generates (see `derive_randomness`)
+		type Randomness: Randomness<Self::Hash, BlockNumberFor<Self>>;
+
+		/// Most alert tags one account may register (see `register_alert_tags`)
+		#[pallet::constant]
+		type MaxAlertTags: Get<u32>;
+
+		/// Length in blocks of the window recent deposits are counted over (see
+		/// `anonymity_score`)
+		#[pallet::constant]
+		type ScoreWindow: Get<u32>;
+
+		/// Anonymity score below which `LowAnonymityWarning` is emitted
+		#[pallet::constant]
+		type MinAnonymityScore: Get<u32>;
+
+		/// Number of blocks a local deposit's note key may not be used again for, 0 to
+		/// not check (see `ensure_fresh_randomness`)
+		#[pallet::constant]
+		type RandomnessReuseWindow: Get<u32>;
+
+		/// Largest encrypted request of a proving job, in bytes
+		#[pallet::constant]
+		type MaxProvingJobLen: Get<u32>;
+
+		/// Most proving jobs on the board at once
+		#[pallet::constant]
+		type MaxProvingJobs: Get<u32>;
+
+		/// Blocks a posted proving job stays open for claims
+		#[pallet::constant]
+		type ProvingJobTtl: Get<u32>;
+
+		/// Blocks a prover has to fulfil a job it claimed
+		#[pallet::constant]
+		type ProvingClaimTtl: Get<u32>;
+
+		/// Native bond a prover puts up to claim a job, slashed if it lets the claim lapse
+		#[pallet::constant]
+		type ProverBond: Get<u128>;
+
+		/// Smallest native bounty of a proving job
+		#[pallet::constant]
+		type MinProvingBounty: Get<u128>;
+
+		/// Length in blocks of each phase of an asset's wind-down: the claim window, then
+		/// the refund window (see `begin_asset_winddown`)
+		#[pallet::constant]
+		type WindDownPeriod: Get<u32>;
+
+		/// Maximum number of recipients one `withdraw_split` pays
+		#[pallet::constant]
+		type MaxPayouts: Get<u32>;
+
+		/// Origin that approves the admin actions `AdminOrigin` proposes (see
+		/// `propose_admin_action`); must not be controlled by the same key
+		type CoSignerOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Number of blocks a proposed admin action can be approved and executed in
+		#[pallet::constant]
+		type AdminApprovalTtl: Get<BlockNumberFor<Self>>;
+
+		/// Verifier of withdrawal proofs against the verifying key
+		type ProofVerifier: WithdrawalVerifier;
+
+		/// Whether `deposit_from_xcm` may be called: it takes the signed caller's word that
+		/// the assets arrived, so only test chains enable it. Cross-chain deposits arrive
+		/// through `xcm_config::ShieldDepositTransactor` regardless.
+		#[pallet::constant]
+		type AllowSignedXcmDeposits: Get<bool>;
+
+		/// Smallest fee an unsigned `withdraw_via_relayer` pays the treasury out of its
+		/// note, or `None` to only take signed relayed withdrawals
+		///
+		/// Unsigned transactions pay no transaction fee: this fee, in the smallest unit of
+		/// the note's asset, is what covers the block space and proof verification.
+		#[pallet::constant]
+		type UnsignedRelayFee: Get<Option<u128>>;
+
+		/// Longest proof a call carries, in bytes (see `ProofOf`)
+		///
+		/// A call with a longer proof fails to decode, before its weight is even charged.
+		/// Must be at least `zksnark::PROOF_LEN`, the length of a Groth16 proof.
+		#[pallet::constant]
+		type MaxProofSize: Get<u32>;
 	}
 
+	/// Local asset ID of the native currency
+	pub const NATIVE_ASSET_ID: u32 = 0;
+
+	/// Pool priority of unsigned withdrawals, below most signed transactions
+	pub const UNSIGNED_WITHDRAWAL_PRIORITY: TransactionPriority = TransactionPriority::MAX / 4;
+
+	/// Log target of the pallet: debug lines for deposits, withdrawals and XCM sends, warn
+	/// lines for failures and anomalies
+	pub const LOG_TARGET: &str = "runtime::privacy-bridge";
+
+	/// Decimals of the relay chain token (DOT)
+	pub const RELAY_ASSET_DECIMALS: u32 = 10;
+
+	/// Default minimum deposit of the relay asset registered at genesis (0.1 DOT)
+	pub const DEFAULT_RELAY_MIN_DEPOSIT: u128 = 10u128.pow(RELAY_ASSET_DECIMALS - 1);
+
+	/// Format version of `GenesisConfig`
+	pub const GENESIS_CONFIG_VERSION: u32 = 2;
+
+	/// Preset for development and open testnets (see `GenesisConfig::preset`)
+	pub const DEVELOPMENT_PRESET: &str = "development";
+
+	/// Preset for a cautious production launch (see `GenesisConfig::preset`)
+	pub const CONSERVATIVE_PRESET: &str = "conservative";
+
+	/// Number of actions `RecentActions` keeps
+	pub const MAX_RECENT_ACTIONS: u32 = 256;
+
+	/// Most leaves one `rebuild_tree` call rehashes
+	pub const MAX_REBUILD_CHUNK: u32 = 1024;
+
+	/// Identifier of a payout claim
+	pub type ClaimId = u64;
+
+	/// Identifier of a queued cross-chain withdrawal
+	pub type WithdrawalId = u64;
+
+	/// Serialized Groth16 verifying key
+	pub type VerifyingKeyBytes = BoundedVec<u8, ConstU32<8192>>;
+
+	/// Serialized proof, as calls carry it
+	pub type ProofOf<T> = BoundedVec<u8, <T as Config>::MaxProofSize>;
+
+	/// Serialized prepared verifying key (`zksnark::serialize_prepared_vk`)
+	///
+	/// A key's points take twice their compressed size, plus 384 bytes for `e(alpha, beta)`.
+	pub type PreparedKeyBytes = BoundedVec<u8, ConstU32<{ 2 * 8192 + 384 }>>;
+
+	/// Version of the pallet's storage layout
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Stores metadata about each commitment
@@ -96,6 +477,493 @@ pub mod pallet {
 		pub asset_id: u32,
 	}
 
+	/// What the trusted setup of a verifying key was generated for
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct SetupMetadata {
+		/// Hasher the circuits of the setup use
+		pub hasher: HasherId,
+		/// `circuit::CIRCUIT_VERSION` of the circuits
+		pub circuit_version: u32,
+	}
+
+	/// Circuit a verifying key checks proofs of
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug,
+		serde::Serialize, serde::Deserialize,
+	)]
+	pub enum CircuitId {
+		/// `circuit::PrivateTransferCircuit`, checking withdrawals
+		Withdraw,
+		/// `circuit::TransferCircuit`, checking `private_transfer`
+		Transfer,
+		/// `circuit::JoinSplitCircuit`, checking `withdraw_join`
+		JoinSplit,
+		/// `circuit::SwapCircuit`, checking the legs of `shielded_swap`
+		Swap,
+		/// `circuit::BundleWithdrawalCircuit`, checking `withdraw_bundle`
+		Bundle,
+	}
+
+	/// A merkle root kept in `RootHistory`
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct RootRecord {
+		/// The root
+		pub root: H256,
+		/// Its position in the sequence of roots (`RootSequence` when it was recorded)
+		pub sequence: u32,
+		/// Block the first leaf of the tree was deposited in, so no leaf under the root is
+		/// older
+		pub oldest_leaf_block: u32,
+		/// Block the root was recorded in, so no leaf under the root is newer
+		pub newest_leaf_block: u32,
+	}
+
+	/// Merkle tree of the notes of one denomination of an asset (see `set_denominations`)
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, Default, RuntimeDebug,
+	)]
+	pub struct DenominationTree {
+		/// Number of notes in the pool, so the index of the next one
+		pub leaves: u32,
+		/// Root withdrawals of the pool's notes are proven against
+		pub root: H256,
+		/// Frontier of the tree, as `FilledSubtrees` is for the main tree
+		pub filled_subtrees: [H256; TREE_DEPTH],
+	}
+
+	/// State of a tree cache rebuild (see `start_tree_rebuild`)
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct TreeRebuildProgress {
+		/// Root governance expects the stored leaves to hash to
+		pub expected_root: H256,
+		/// Index of the next leaf to rehash
+		pub next_leaf: u32,
+		/// Root over the leaves rehashed so far
+		pub root: H256,
+	}
+
+	/// A privacy-relevant change recorded in `RecentActions`
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum Action {
+		/// A commitment was inserted into the tree
+		LeafInserted { leaf_index: u32, commitment: H256 },
+		/// The merkle root changed
+		RootUpdated { root: H256 },
+		/// A nullifier was spent
+		NullifierSpent { nullifier: H256 },
+	}
+
+	/// An entry of `RecentActions`
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct ActionRecord<BlockNumber> {
+		/// Position in the sequence of all actions, from 0
+		pub sequence: u64,
+		/// Block the action happened in
+		pub block_number: BlockNumber,
+		/// What happened
+		pub action: Action,
+	}
+
+	/// Coarse grade of an anonymity score
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default,
+	)]
+	pub enum AnonymityBucket {
+		/// Fewer than 10
+		#[default]
+		Minimal,
+		/// 10 to 99
+		Low,
+		/// 100 to 999
+		Moderate,
+		/// 1000 or more
+		High,
+	}
+
+	/// How much of the bridge is paused
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default,
+	)]
+	pub enum PauseLevel {
+		/// Fully operational
+		#[default]
+		None,
+		/// New deposits are refused; withdrawals continue so users can exit
+		DepositsPaused,
+		/// Both deposits and withdrawals are refused
+		AllPaused,
+		/// Withdrawals, claims and queued exports are refused; deposits continue. Tripped by
+		/// a failed solvency check (the withdrawal circuit breaker)
+		WithdrawalsPaused,
+	}
+
+	impl PauseLevel {
+		/// Whether deposits are refused at this level
+		pub fn deposits_paused(self) -> bool {
+			matches!(self, Self::DepositsPaused | Self::AllPaused)
+		}
+
+		/// Whether withdrawals are refused at this level
+		pub fn withdrawals_paused(self) -> bool {
+			matches!(self, Self::WithdrawalsPaused | Self::AllPaused)
+		}
+
+		/// This level with withdrawals refused as well
+		pub fn with_withdrawals_paused(self) -> Self {
+			if self.deposits_paused() {
+				Self::AllPaused
+			} else {
+				Self::WithdrawalsPaused
+			}
+		}
+	}
+
+	impl AnonymityBucket {
+		/// Bucket of `score`
+		pub fn of(score: u32) -> Self {
+			match score {
+				0..=9 => Self::Minimal,
+				10..=99 => Self::Low,
+				100..=999 => Self::Moderate,
+				_ => Self::High,
+			}
+		}
+	}
+
+	/// Running counts behind an asset's anonymity score, updated on every deposit and spend
+	///
+	/// Recent deposits are counted in two fixed windows of `Config::ScoreWindow` blocks, the
+	/// current one and the one before, which is all a sliding-window estimate needs.
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default,
+	)]
+	pub struct AnonymityTally {
+		/// Notes of the asset not yet spent, decoys included
+		pub unspent_notes: u32,
+		/// Index of the window `window_deposits` counts (block number / `ScoreWindow`)
+		pub window: u32,
+		/// Deposits in window `window`
+		pub window_deposits: u32,
+		/// Deposits in the window before `window`
+		pub previous_window_deposits: u32,
+		/// Whether the score was at or above `MinAnonymityScore` when last updated
+		pub above_floor: bool,
+	}
+
+	/// Anonymity score of one asset
+	#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default)]
+	pub struct AnonymityScore {
+		/// Estimate of the asset's unspent notes
+		pub unspent_notes: u32,
+		/// Deposits of the last `ScoreWindow` blocks, older ones weighted by their overlap
+		/// with the window
+		pub recent_deposits: u32,
+		/// `unspent_notes + recent_deposits`
+		pub score: u32,
+		/// Coarse grade of `score`
+		pub bucket: AnonymityBucket,
+	}
+
+	/// One party's side of a `shielded_swap`
+	#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct SwapLeg {
+		/// Proof of `circuit::SwapCircuit` over the inputs below
+		pub proof_envelope: ProofEnvelope,
+		/// Nullifier of the note spent
+		pub nullifier: H256,
+		/// Commitment of the note spent
+		pub commitment: H256,
+		/// Local asset ID of the note spent, and of the note created
+		pub asset_id: u32,
+		/// Commitment of the note created for the counterparty
+		pub output_commitment: H256,
+	}
+
+	/// Verifying key announced for rotation, bound to the circuits and runtime it was made for
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	#[scale_info(skip_type_params(T))]
+	pub struct PendingKeyRotation<T: Config> {
+		/// Serialized verifying key, for the circuit it is announced under
+		pub vk: VerifyingKeyBytes,
+		/// Hasher of the key's setup
+		pub hasher: HasherId,
+		/// `circuit::CIRCUIT_VERSION` when announced
+		pub circuit_version: u32,
+		/// Runtime `spec_version` when announced
+		pub spec_version: u32,
+		/// Block of the announcement
+		pub announced_at: BlockNumberFor<T>,
+	}
+
+	/// Admin operation that needs the co-signer's approval to execute
+	///
+	/// Approvals are kept under `AdminAction::hash`, which tooling computes the same way
+	/// before proposing.
+	#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub enum AdminAction {
+		/// `apply_verifying_key` of the key announced for `circuit` with this hash
+		/// (`BlakeTwo256` of the serialized key)
+		ApplyVerifyingKey { circuit: CircuitId, vk_hash: H256 },
+		/// `begin_asset_winddown` of this asset (in its canonical form)
+		WindDownAsset { asset_id: XcmAssetId },
+	}
+
+	impl AdminAction {
+		/// Hash the action is proposed and approved under
+		pub fn hash(&self) -> H256 {
+			BlakeTwo256::hash_of(&(b"cloak/admin-action", self))
+		}
+	}
+
+	/// Proposed admin action, see `propose_admin_action`
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct AdminApproval<BlockNumber> {
+		/// Block from which the proposal can no longer be approved nor executed
+		pub expires_at: BlockNumber,
+		/// Whether `CoSignerOrigin` approved it
+		pub approved: bool,
+	}
+
+	/// Pending decoy deposits for an under-populated asset
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct DecoySchedule<BlockNumber> {
+		/// Decoys still to be created
+		pub remaining: u32,
+		/// Blocks between two decoys
+		pub interval: BlockNumber,
+		/// Block at which the next decoy is created
+		pub next_at: BlockNumber,
+	}
+
+	/// Protocol-owned decoy note, kept so the treasury can reclaim it later
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct DecoyNote {
+		/// Asset the decoy was created for
+		pub asset_id: u32,
+		/// Value committed in the decoy
+		pub value: u128,
+		/// Nullifier consumed when the decoy is reclaimed
+		pub nullifier: H256,
+	}
+
+	/// Data retained for notes of assets with an abandonment period
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct AbandonmentRecord<BlockNumber> {
+		/// Local asset ID of the note
+		pub asset_id: u32,
+		/// Deposited amount (public in the deposit call)
+		pub amount: u128,
+		/// Block at which the note was deposited
+		pub deposited_at: BlockNumber,
+	}
+
+	/// Value owed to a beneficiary whose payout failed after the note was spent
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct Claim<AccountId> {
+		/// Local asset ID of the owed value
+		pub asset_id: u32,
+		/// Amount owed
+		pub amount: u128,
+		/// Account the value is paid to
+		pub beneficiary: AccountId,
+	}
+
+	/// Bridge fee charged on withdrawals to a destination
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default)]
+	pub struct FeeConfig {
+		/// Fixed part of the fee
+		pub flat: u128,
+		/// Proportional part of the fee, in parts per million of the withdrawn amount
+		pub ppm: u32,
+	}
+
+	impl FeeConfig {
+		/// Fee charged on a withdrawal of `amount`
+		pub fn fee_for(&self, amount: u128) -> u128 {
+			self.flat.saturating_add(Permill::from_parts(self.ppm).mul_floor(amount))
+		}
+	}
+
+	/// Why a cross-chain withdrawal would be rejected
+	#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum RejectionReason {
+		/// The asset is not registered
+		AssetNotRegistered,
+		/// No open channel to the first hop of the withdrawal
+		NoChannelToDestination,
+		/// The destination fee is above the withdrawn amount
+		FeeExceedsAmount,
+		/// The amount sent is below the asset's minimum balance on the destination
+		BelowRemoteMinBalance,
+		/// The beneficiary is longer than `MAX_BENEFICIARY_LEN` bytes
+		BeneficiaryTooLong,
+	}
+
+	impl<T> From<RejectionReason> for Error<T> {
+		fn from(reason: RejectionReason) -> Self {
+			match reason {
+				RejectionReason::AssetNotRegistered => Error::<T>::AssetNotRegistered,
+				RejectionReason::NoChannelToDestination => Error::<T>::NoChannelToDestination,
+				RejectionReason::FeeExceedsAmount => Error::<T>::FeeExceedsAmount,
+				RejectionReason::BelowRemoteMinBalance => Error::<T>::BelowExistentialDeposit,
+				RejectionReason::BeneficiaryTooLong => Error::<T>::InvalidBeneficiary,
+			}
+		}
+	}
+
+	/// Outcome of a cross-chain withdrawal dry run
+	#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct DryRunResult {
+		/// Whether the withdrawal would be accepted
+		pub ok: bool,
+		/// Destination fee taken out of the withdrawn amount
+		pub estimated_fee: u128,
+		/// Every check the withdrawal fails, empty if `ok`
+		pub reasons: Vec<RejectionReason>,
+	}
+
+	/// Pool accounting of one asset
+	#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default)]
+	pub struct PoolStats {
+		/// Total value shielded, decoys included
+		pub total_shielded: u128,
+		/// Portion of `total_shielded` owned by the protocol (decoys)
+		pub protocol_owned: u128,
+		/// Value held for the pool on the asset's reserve chain
+		pub reserve_backing: u128,
+		/// How well the asset's notes hide among each other
+		pub anonymity: AnonymityScore,
+	}
+
+	/// What the runtime API tells about a commitment
+	///
+	/// Every API response describes commitments with this view, never `CommitmentData`:
+	/// the depositor stays in raw storage, which is public, but our own APIs do not
+	/// serve the linkage of deposits to accounts.
+	#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct PublicCommitmentInfo<BlockNumber> {
+		/// Block the commitment was inserted in
+		pub block_number: BlockNumber,
+		/// Local asset ID of the note
+		pub asset_id: u32,
+		/// Index of the commitment's leaf in the tree
+		pub leaf_index: u32,
+	}
+
+	/// Alert tags of one watched user, with the salt they were derived with
+	///
+	/// A tag is `primitives::alert_tag(nullifier, salt)`: the watcher holding it can
+	/// recognise the nullifier once it is spent, but cannot derive it beforehand.
+	#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct AlertTagQuery {
+		/// Salt the user derived the tags with
+		pub salt: [u8; 32],
+		/// Tags to look for
+		pub tags: Vec<H256>,
+	}
+
+	/// A registered alert tag whose nullifier was spent
+	#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct AlertMatch<BlockNumber> {
+		/// The matching tag
+		pub tag: H256,
+		/// Block the nullifier was spent in
+		pub block_number: BlockNumber,
+	}
+
+	/// State of a queued cross-chain withdrawal
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum WithdrawalStatus {
+		/// Waiting to be exported in `on_idle`
+		Queued,
+		/// The export failed; the value stays in the pool until retried
+		Failed,
+	}
+
+	/// Cross-chain withdrawal whose note is spent but which is not exported yet
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct QueuedWithdrawal {
+		pub nullifier: H256,
+		pub asset_id: u32,
+		pub amount: u128,
+		pub destination: VersionedDestination,
+		pub beneficiary: BoundedVec<u8, ConstU32<MAX_BENEFICIARY_LEN>>,
+		pub status: WithdrawalStatus,
+	}
+
+	/// Identifier of a proving job
+	pub type ProvingJobId = u64;
+
+	/// Where a proving job stands
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum ProvingJobStatus<AccountId, BlockNumber> {
+		/// Waiting for a prover
+		Open,
+		/// Claimed by `prover`, who has to fulfil it before `deadline`
+		Claimed { prover: AccountId, deadline: BlockNumber },
+	}
+
+	/// A proving job on the board (see `post_proving_job`)
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct ProvingJob<AccountId, BlockNumber> {
+		/// Account that posted the job, paid the bounty and receives the withdrawal
+		pub owner: AccountId,
+		/// Native bounty held for the prover
+		pub bounty: u128,
+		/// Block from which an unclaimed job can no longer be claimed
+		pub expires_at: BlockNumber,
+		pub status: ProvingJobStatus<AccountId, BlockNumber>,
+	}
+
+	/// Phase of an asset being delisted (see `begin_asset_winddown`)
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum WindDownPhase<BlockNumber> {
+		/// Deposits are closed; notes are still withdrawn with a proof until `ends_at`
+		ClaimWindow { ends_at: BlockNumber },
+		/// Withdrawals are closed; recorded notes are refunded publicly, each for its
+		/// amount scaled by `held / owed`, until `ends_at`
+		Refunding {
+			/// User-owned shielded value when the claim window closed
+			owed: u128,
+			/// Value the pool held for it then, at most `owed`
+			held: u128,
+			/// Value refunded so far
+			refunded: u128,
+			ends_at: BlockNumber,
+		},
+		/// What was left went to the treasury; the asset's notes are worthless
+		Finalized,
+	}
+
+	/// Record of a successful withdrawal, kept under its receipt ID (see `withdrawal_by_id`)
+	#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, RuntimeDebug)]
+	pub struct WithdrawalRecord<BlockNumber> {
+		/// Nullifier of the note spent
+		pub nullifier: H256,
+		pub asset_id: u32,
+		/// Value of the note spent, the fee included
+		pub amount: u128,
+		/// Destination fee paid to the treasury, zero for local withdrawals
+		pub fee: u128,
+		pub block_number: BlockNumber,
+		/// Index of the withdrawal's extrinsic in its block
+		pub extrinsic_index: u32,
+		/// Chain the value was sent to, `None` when paid out locally
+		pub destination: Option<VersionedDestination>,
+	}
+
+	/// How trapped shield deposits are resolved
+	#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum TrapResolution {
+		/// Send the assets back to `beneficiary` on the originating chain
+		Return { beneficiary: Location },
+		/// Retry shielding the assets into the XCM note of `note_key`
+		///
+		/// The commitment is `xcm_config::xcm_commitment_data` over the trapped amount and
+		/// the chain resolving the trap, as for a shield deposit that went through.
+		Shield { note_key: [u8; 32] },
+	}
+
 	/// Stores the shielded note data (kept off-chain by user)
 	/// This is what the user will keep secret to later spend their commitment
 	#[derive(Encode, Decode, Clone, PartialEq, RuntimeDebug)]
@@ -106,7 +974,7 @@ pub mod pallet {
 		pub amount: u128,
 		/// Asset ID
 		pub asset_id: u32,
-		/// Secret randomness used in commitment
+		/// Randomness used in commitment, derived from the secret (`primitives::note_randomness`)
 		pub randomness: [u8; 32],
 		/// Nullifier for spending (derived from commitment + secret)
 		pub nullifier: H256,
@@ -114,8 +982,10 @@ pub mod pallet {
 
 	/// Storage: Maps commitment hash -> commitment metadata
 	/// The commitment itself is a hash that hides the amount and randomness
+	///
+	/// No getter: `CommitmentData` names the depositor, so outside the pallet it is only
+	/// read as raw storage. Use `commitment_info` for the public view.
 	#[pallet::storage]
-	#[pallet::getter(fn commitments)]
 	pub type Commitments<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
@@ -136,18 +1006,120 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Storage: Nullifiers by the block they were spent in (block, nullifier) -> ()
+	///
+	/// Lets watchers export recent spends without scanning `NullifierSet`.
+	#[pallet::storage]
+	pub type SpentNullifiers<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		Blake2_128Concat,
+		H256,
+		(),
+		OptionQuery,
+	>;
+
+	/// Storage: Successful withdrawals by receipt ID
+	/// (see `primitives::withdrawal_receipt_id`)
+	#[pallet::storage]
+	pub type WithdrawalRecords<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, WithdrawalRecord<BlockNumberFor<T>>, OptionQuery>;
+
+	/// Storage: Randomness commitments of recent local deposits (commitment -> block seen)
+	///
+	/// Only kept with a `RandomnessReuseWindow`; pruned in `on_initialize` once the window
+	/// has passed.
+	#[pallet::storage]
+	pub type RecentRandomness<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, BlockNumberFor<T>, OptionQuery>;
+
+	/// Storage: `RecentRandomness` entries by the block they were seen in, for pruning
+	/// (block, commitment) -> ()
+	#[pallet::storage]
+	pub type RandomnessSeenAt<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		Blake2_128Concat,
+		H256,
+		(),
+		OptionQuery,
+	>;
+
+	/// Storage: Proving jobs on the board
+	#[pallet::storage]
+	#[pallet::getter(fn proving_job)]
+	pub type ProvingJobs<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ProvingJobId,
+		ProvingJob<T::AccountId, BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Storage: Encrypted request of each proving job, for provers to read
+	#[pallet::storage]
+	#[pallet::getter(fn proving_job_request)]
+	pub type ProvingJobRequests<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ProvingJobId,
+		BoundedVec<u8, T::MaxProvingJobLen>,
+		OptionQuery,
+	>;
+
+	/// Storage: Number of proving jobs on the board, at most `MaxProvingJobs`
+	#[pallet::storage]
+	pub type ProvingJobCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Storage: ID of the next proving job
+	#[pallet::storage]
+	pub type NextProvingJobId<T: Config> = StorageValue<_, ProvingJobId, ValueQuery>;
+
+	/// Storage: Wind-down phase of delisted local assets
+	#[pallet::storage]
+	#[pallet::getter(fn wind_down)]
+	pub type WindDowns<T: Config> =
+		StorageMap<_, Blake2_128Concat, u32, WindDownPhase<BlockNumberFor<T>>, OptionQuery>;
+
+	/// Storage: Admin actions proposed for dual control, by `AdminAction::hash`
+	#[pallet::storage]
+	#[pallet::getter(fn admin_approval)]
+	pub type AdminApprovals<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, AdminApproval<BlockNumberFor<T>>, OptionQuery>;
+
 	/// Storage: Counter for total commitments (useful for merkle tree indexing later)
 	#[pallet::storage]
 	#[pallet::getter(fn commitment_count)]
 	pub type CommitmentCount<T: Config> = StorageValue<_, u32, ValueQuery>;
 
-	/// Week 3: Storage for zkSNARK verifying key (serialized)
-	/// This key is used to verify withdrawal proofs on-chain
+	/// Storage: zkSNARK verifying key (serialized) of each circuit
 	/// Generated off-chain during trusted setup, stored on-chain for verification
 	#[pallet::storage]
-	#[pallet::getter(fn verifying_key)]
-	pub type VerifyingKey<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<4096>>, OptionQuery>;
-	// Note: 4096 bytes should be enough for Groth16 verifying key
+	#[pallet::getter(fn verifying_key_for)]
+	pub type VerifyingKeys<T: Config> =
+		StorageMap<_, Twox64Concat, CircuitId, VerifyingKeyBytes, OptionQuery>;
+
+	/// Storage: `VerifyingKeys`, prepared for verification (`WithdrawalVerifier::prepare_key`)
+	///
+	/// Written with the key, so proofs skip preparing it. A key stored before this map
+	/// existed has no entry and is prepared for each proof.
+	#[pallet::storage]
+	pub type PreparedVerifyingKeys<T: Config> =
+		StorageMap<_, Twox64Concat, CircuitId, PreparedKeyBytes, OptionQuery>;
+
+	/// Storage: Setup the active withdrawal verifying key was generated for
+	#[pallet::storage]
+	#[pallet::getter(fn verifying_key_setup)]
+	pub type VerifyingKeySetup<T: Config> = StorageValue<_, SetupMetadata, OptionQuery>;
+
+	/// Storage: Verifying keys announced for rotation, per circuit, waiting for
+	/// `apply_verifying_key`
+	#[pallet::storage]
+	#[pallet::getter(fn pending_verifying_key)]
+	pub type PendingVerifyingKeys<T: Config> =
+		StorageMap<_, Twox64Concat, CircuitId, PendingKeyRotation<T>, OptionQuery>;
 
 	/// Week 4: Asset registry - maps XCM AssetId to local asset ID
 	/// This allows the bridge to support multiple assets from different parachains
@@ -161,352 +1133,6691 @@ pub mod pallet {
 		OptionQuery,
 	>;
 
+	/// First local asset ID assigned to XCM assets (lower IDs are reserved)
+	#[pallet::type_value]
+	pub fn FirstXcmAssetId() -> u32 {
+		NATIVE_ASSET_ID + 1
+	}
+
 	/// Week 4: Counter for assigning local asset IDs
+	///
+	/// Starts after `NATIVE_ASSET_ID`, which is never assigned to an XCM asset.
 	#[pallet::storage]
 	#[pallet::getter(fn next_asset_id)]
-	pub type NextAssetId<T: Config> = StorageValue<_, u32, ValueQuery>;
+	pub type NextAssetId<T: Config> = StorageValue<_, u32, ValueQuery, FirstXcmAssetId>;
+
+	/// Storage: Reverse index from local asset ID to XCM asset ID
+	#[pallet::storage]
+	#[pallet::getter(fn local_asset_index)]
+	pub type LocalAssetIndex<T: Config> = StorageMap<_, Blake2_128Concat, u32, XcmAssetId, OptionQuery>;
+
+	/// Storage: Leaves in insertion order (leaf index -> commitment)
+	#[pallet::storage]
+	#[pallet::getter(fn merkle_leaves)]
+	pub type MerkleLeaves<T: Config> = StorageMap<_, Blake2_128Concat, u32, H256, OptionQuery>;
+
+	/// Storage: Frontier of the incremental merkle tree (level -> last left node)
+	#[pallet::storage]
+	#[pallet::getter(fn filled_subtrees)]
+	pub type FilledSubtrees<T: Config> = StorageMap<_, Blake2_128Concat, u8, H256, ValueQuery>;
+
+	/// Storage: Inner nodes of the merkle tree (level, index -> hash)
+	///
+	/// Level 1 holds the parents of `MerkleLeaves`, up to the children of `MerkleRoot`.
+	/// Nodes are rewritten as leaves are hashed in, so they always describe the tree of
+	/// `MerkleRoot`; missing ones are empty subtrees. At most one node per leaf and level,
+	/// so bounded by `TREE_DEPTH` levels of the tree. Trees grown before this cache existed
+	/// fill it with a tree rebuild (see `start_tree_rebuild`).
+	#[pallet::storage]
+	pub type MerkleNodes<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, u8, Twox64Concat, u32, H256, OptionQuery>;
+
+	/// Storage: Tree of each denomination pool (local asset ID, denomination -> tree)
+	///
+	/// Notes of a denominated asset are leaves of the main tree and of their pool's tree;
+	/// withdrawals prove them against the pool's root, so the pool is their anonymity set.
+	#[pallet::storage]
+	#[pallet::getter(fn denomination_tree)]
+	pub type DenominationTrees<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		u32,
+		Blake2_128Concat,
+		u128,
+		DenominationTree,
+		ValueQuery,
+	>;
+
+	/// Storage: Leaves of each denomination pool ((local asset ID, denomination), index ->
+	/// commitment), for wallets building paths in the pool's tree
+	#[pallet::storage]
+	pub type DenominationLeaves<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, (u32, u128), Twox64Concat, u32, H256, OptionQuery>;
+
+	/// Storage: Denomination pool of each note in one (commitment -> denomination)
+	#[pallet::storage]
+	#[pallet::getter(fn denomination_of)]
+	pub type DenominationOf<T: Config> = StorageMap<_, Blake2_128Concat, H256, u128, OptionQuery>;
+
+	/// Storage: Root of the incremental merkle tree
+	#[pallet::storage]
+	#[pallet::getter(fn merkle_root)]
+	pub type MerkleRoot<T: Config> = StorageValue<_, H256, ValueQuery>;
+
+	/// Storage: Number of leaves hashed into `MerkleRoot`
+	///
+	/// Trails `CommitmentCount` within a block when `BatchTreeInsertion` is set.
+	#[pallet::storage]
+	#[pallet::getter(fn tree_size)]
+	pub type TreeSize<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Storage: The last `RootHistorySize` merkle roots, as a ring buffer
+	/// (slot -> root record)
+	///
+	/// A root is written to slot `sequence % RootHistorySize`, evicting the root recorded
+	/// `RootHistorySize` updates before it.
+	#[pallet::storage]
+	#[pallet::getter(fn root_history)]
+	pub type RootHistory<T: Config> = StorageMap<_, Blake2_128Concat, u32, RootRecord, OptionQuery>;
+
+	/// Storage: Number of merkle root updates so far (sequence number of the current root)
+	#[pallet::storage]
+	#[pallet::getter(fn root_sequence)]
+	pub type RootSequence<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Storage: Leaf index of each commitment
+	#[pallet::storage]
+	#[pallet::getter(fn leaf_of)]
+	pub type CommitmentIndex<T: Config> = StorageMap<_, Blake2_128Concat, H256, u32, OptionQuery>;
+
+	/// Storage: Commitments that have been spent
+	#[pallet::storage]
+	#[pallet::getter(fn spent_commitments)]
+	pub type SpentCommitments<T: Config> = StorageMap<_, Blake2_128Concat, H256, bool, ValueQuery>;
+
+	/// Storage: Commitments of multi-asset (bundle) notes
+	#[pallet::storage]
+	#[pallet::getter(fn bundle_notes)]
+	pub type BundleNotes<T: Config> = StorageMap<_, Blake2_128Concat, H256, bool, ValueQuery>;
+
+	/// Storage: Denied (filtered) commitments that can no longer be withdrawn
+	#[pallet::storage]
+	#[pallet::getter(fn denied_commitments)]
+	pub type DeniedCommitments<T: Config> = StorageMap<_, Blake2_128Concat, H256, bool, ValueQuery>;
+
+	/// Storage: Sweep data for notes of assets with an abandonment period
+	#[pallet::storage]
+	#[pallet::getter(fn abandonment_records)]
+	pub type AbandonmentRecords<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		AbandonmentRecord<BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Storage: Public claims for swept notes (claimant, local asset ID -> amount)
+	#[pallet::storage]
+	#[pallet::getter(fn abandoned_claims)]
+	pub type AbandonedClaims<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		u32,
+		u128,
+		ValueQuery,
+	>;
+
+	/// Storage: Total value currently shielded per local asset ID
+	/// Includes protocol-owned value (see `ProtocolOwnedShielded`)
+	#[pallet::storage]
+	#[pallet::getter(fn total_shielded)]
+	pub type TotalShielded<T: Config> = StorageMap<_, Blake2_128Concat, u32, u128, ValueQuery>;
+
+	/// Storage: Portion of `TotalShielded` owned by the protocol (decoy notes)
+	#[pallet::storage]
+	#[pallet::getter(fn protocol_owned_shielded)]
+	pub type ProtocolOwnedShielded<T: Config> = StorageMap<_, Blake2_128Concat, u32, u128, ValueQuery>;
+
+	/// Storage: Decoy schedules per local asset ID
+	#[pallet::storage]
+	#[pallet::getter(fn decoy_schedules)]
+	pub type DecoySchedules<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32,
+		DecoySchedule<BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Storage: Outstanding decoy notes (commitment -> decoy details)
+	#[pallet::storage]
+	#[pallet::getter(fn decoy_notes)]
+	pub type DecoyNotes<T: Config> = StorageMap<_, Blake2_128Concat, H256, DecoyNote, OptionQuery>;
+
+	/// Storage: Outstanding claims for failed payouts
+	#[pallet::storage]
+	#[pallet::getter(fn claims)]
+	pub type Claims<T: Config> = StorageMap<_, Blake2_128Concat, ClaimId, Claim<T::AccountId>, OptionQuery>;
+
+	/// Storage: Counter for assigning claim IDs
+	#[pallet::storage]
+	#[pallet::getter(fn next_claim_id)]
+	pub type NextClaimId<T: Config> = StorageValue<_, ClaimId, ValueQuery>;
+
+	/// Storage: Cross-chain withdrawals waiting for export, or whose export failed
+	#[pallet::storage]
+	#[pallet::getter(fn queued_withdrawals)]
+	pub type QueuedWithdrawals<T: Config> =
+		StorageMap<_, Blake2_128Concat, WithdrawalId, QueuedWithdrawal, OptionQuery>;
+
+	/// Storage: ID of the next queued withdrawal
+	#[pallet::storage]
+	#[pallet::getter(fn next_withdrawal_id)]
+	pub type NextWithdrawalId<T: Config> = StorageValue<_, WithdrawalId, ValueQuery>;
+
+	/// Storage: Bridge fee schedule per withdrawal destination (none means no fee)
+	#[pallet::storage]
+	#[pallet::getter(fn destination_fees)]
+	pub type DestinationFees<T: Config> =
+		StorageMap<_, Blake2_128Concat, Location, FeeConfig, OptionQuery>;
+
+	/// Storage: Sibling parachains we have an open outbound channel to (sorted)
+	///
+	/// Maintained by governance; withdrawals whose first hop is a sibling outside
+	/// this set are rejected before the note is spent.
+	#[pallet::storage]
+	#[pallet::getter(fn open_channels)]
+	pub type OpenChannels<T: Config> =
+		StorageValue<_, BoundedVec<u32, ConstU32<MAX_OPEN_CHANNELS>>, ValueQuery>;
+
+	/// Storage: Account format of beneficiaries per withdrawal destination (none means
+	/// `AccountId32`)
+	#[pallet::storage]
+	#[pallet::getter(fn destination_account_format)]
+	pub type DestinationAccountFormat<T: Config> =
+		StorageMap<_, Blake2_128Concat, Location, AccountFormat, OptionQuery>;
+
+	/// Storage: Shield deposits trapped by the XCM executor (origin, XCM asset -> amount)
+	#[pallet::storage]
+	#[pallet::getter(fn trapped_assets)]
+	pub type TrappedAssets<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		Location,
+		Blake2_128Concat,
+		XcmAssetId,
+		u128,
+		ValueQuery,
+	>;
+
+	/// Storage: Value of reserve-backed assets held for the pool on their reserve chain
+	///
+	/// For assets with a remote reserve, the pool only holds derivatives; the backing
+	/// sits in this chain's sovereign account on the reserve. Increased by XCM deposits,
+	/// decreased by cross-chain withdrawals (local ID -> amount).
+	#[pallet::storage]
+	#[pallet::getter(fn reserve_backing)]
+	pub type ReserveBacking<T: Config> = StorageMap<_, Blake2_128Concat, u32, u128, ValueQuery>;
+
+	/// Storage: Reserve backing moved in or out per asset within a block
+	/// (local ID -> (block, amount moved)), for `ReserveBackingAnomaly`
+	#[pallet::storage]
+	pub type ReserveBackingMoved<T: Config> =
+		StorageMap<_, Blake2_128Concat, u32, (BlockNumberFor<T>, u128), OptionQuery>;
+
+	/// Storage: How much of the bridge is paused
+	///
+	/// Set by the admin; a failed solvency check also pauses withdrawals (see
+	/// `PauseLevel::with_withdrawals_paused`) until the admin unpauses.
+	#[pallet::storage]
+	#[pallet::getter(fn pause_level)]
+	pub type Paused<T: Config> = StorageValue<_, PauseLevel, ValueQuery>;
+
+	/// Storage: Whether `BridgeNotFullyConfigured` was raised already
+	#[pallet::storage]
+	pub type NotConfiguredWarned<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+	/// Storage: Local asset ID the next solvency check starts from
+	#[pallet::storage]
+	pub type SolvencyCursor<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Storage: Origin of commitments inserted by XCM deposits (commitment -> origin)
+	///
+	/// Lets a redelivered deposit be recognised as a duplicate of the first one.
+	#[pallet::storage]
+	#[pallet::getter(fn xcm_deposit_origins)]
+	pub type XcmDepositOrigins<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, Location, OptionQuery>;
+
+	/// Storage: Funds of duplicate XCM deposits held by the pool but owned by no note
+	/// (origin, XCM asset -> amount), to be refunded by the admin
+	#[pallet::storage]
+	#[pallet::getter(fn unattributed_balances)]
+	pub type UnattributedBalances<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		Location,
+		Blake2_128Concat,
+		XcmAssetId,
+		u128,
+		ValueQuery,
+	>;
+
+	/// Storage: Participation receipts per account (a count of opted-in deposits)
+	///
+	/// Soul-bound: receipts cannot be transferred, and hold no amount, asset or
+	/// commitment, so they do not link deposits to withdrawals.
+	#[pallet::storage]
+	#[pallet::getter(fn receipt_count)]
+	pub type ParticipationReceipts<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Storage: Native balance of the sponsorship pot, paying the fees of small deposits
+	///
+	/// Held by `sponsorship_account` and topped up by governance from the treasury.
+	#[pallet::storage]
+	#[pallet::getter(fn sponsorship_pot)]
+	pub type SponsorshipPot<T: Config> = StorageValue<_, u128, ValueQuery>;
+
+	/// Storage: Sponsored deposits of each account, with the period they were counted in
+	#[pallet::storage]
+	pub type SponsoredDeposits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (BlockNumberFor<T>, u32), ValueQuery>;
+
+	/// Storage: Sponsored deposits of all accounts, with the period they were counted in
+	#[pallet::storage]
+	pub type SponsoredInPeriod<T: Config> = StorageValue<_, (BlockNumberFor<T>, u32), ValueQuery>;
+
+	/// Storage: Accounts allowed to submit commitment batches
+	#[pallet::storage]
+	#[pallet::getter(fn is_batcher)]
+	pub type Batchers<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, bool, ValueQuery>;
+
+	/// Storage: Commitments submitted in batches, with the block they were counted in
+	#[pallet::storage]
+	pub type BatchLeavesInBlock<T: Config> = StorageValue<_, (BlockNumberFor<T>, u32), ValueQuery>;
+
+	/// Storage: Encrypted note backup of each account that opted in
+	///
+	/// Opaque to the chain: wallets encrypt their note metadata to the account's seed, so
+	/// the seed and chain access are enough to recover. Storing a backup links the account
+	/// to the pool, which is why it is opt-in and every blob has the same size.
+	#[pallet::storage]
+	#[pallet::getter(fn note_backup)]
+	pub type NoteBackups<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BoundedVec<u8, T::MaxBackupLen>, OptionQuery>;
+
+	/// Storage: Deposit paid for each account's note backup, held by `backup_deposit_account`
+	#[pallet::storage]
+	pub type NoteBackupDeposits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u128, ValueQuery>;
+
+	/// Storage: The last `MAX_RECENT_ACTIONS` leaf insertions, root updates and spends, as
+	/// a ring buffer (slot -> action record)
+	///
+	/// Action `sequence` is written to slot `sequence % MAX_RECENT_ACTIONS`, evicting the
+	/// action recorded `MAX_RECENT_ACTIONS` before it. For wallets catching up after a
+	/// short time offline (see `recent_actions`); older actions are only found in the
+	/// events of their blocks.
+	#[pallet::storage]
+	pub type RecentActions<T: Config> =
+		StorageMap<_, Blake2_128Concat, u32, ActionRecord<BlockNumberFor<T>>, OptionQuery>;
+
+	/// Storage: Number of actions recorded so far (sequence number of the next one)
+	#[pallet::storage]
+	pub type ActionSequence<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	/// Storage: Alert tags registered by each account that opted in
+	#[pallet::storage]
+	#[pallet::getter(fn alert_tags)]
+	pub type AlertTags<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BoundedVec<H256, T::MaxAlertTags>, ValueQuery>;
+
+	/// Storage: Number of accounts that registered each alert tag
+	///
+	/// `match_alert_tags` only reports tags counted here.
+	#[pallet::storage]
+	pub type AlertTagRegistrations<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, u32, ValueQuery>;
+
+	/// Storage: Counts behind each local asset's anonymity score (see `anonymity_score`)
+	#[pallet::storage]
+	pub type AnonymityTallies<T: Config> =
+		StorageMap<_, Blake2_128Concat, u32, AnonymityTally, ValueQuery>;
+
+	/// Storage: Progress of the tree cache rebuild, if one is under way
+	///
+	/// While set, the pallet is in maintenance mode: no leaf enters the tree until
+	/// `rebuild_tree` has rehashed every leaf to the expected root.
+	#[pallet::storage]
+	#[pallet::getter(fn tree_rebuild)]
+	pub type TreeRebuild<T: Config> = StorageValue<_, TreeRebuildProgress, OptionQuery>;
+
+	/// Storage: Proof verification weight used by withdrawals, with the block it was used in
+	///
+	/// Cleared in `on_initialize`; see `verification_weight_used` for why the block is kept.
+	#[pallet::storage]
+	pub type VerificationWeightUsed<T: Config> =
+		StorageValue<_, (BlockNumberFor<T>, Weight), ValueQuery>;
 
 	/// Events emitted by the privacy bridge pallet
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		/// Asset was shielded (deposited into privacy pool)
+		///
+		/// `leaf_index` is the commitment's position in the merkle tree and `merkle_root` the
+		/// root right after its insertion, which is what withdrawal proofs need. With
+		/// `BatchTreeInsertion` the leaf is folded in at the end of the block, so the root is
+		/// the one before it and the root including it is read from `merkle_root` afterwards.
 		AssetShielded {
 			commitment: H256,
 			asset_id: u32,
 			depositor: T::AccountId,
 			block_number: BlockNumberFor<T>,
+			leaf_index: u32,
+			merkle_root: H256,
 		},
 		/// Asset was unshielded (withdrawn from privacy pool)
 		AssetUnshielded {
 			nullifier: H256,
 			asset_id: u32,
 			block_number: BlockNumberFor<T>,
+			/// Receipt ID of the withdrawal (see `withdrawal_by_id`)
+			receipt_id: H256,
 		},
-		/// Private transfer occurred (Week 2+ feature)
+		/// A note was spent into two new notes of the same asset
 		PrivateTransfer {
 			nullifier: H256,
+			output_commitments: [H256; 2],
+			leaf_indices: [u32; 2],
 		},
-	}
-
-	/// Errors that can occur in the privacy bridge pallet
-	#[pallet::error]
-	pub enum Error<T> {
-		/// Commitment already exists
-		CommitmentAlreadyExists,
-		/// Nullifier has already been used (double-spend attempt)
-		NullifierAlreadyUsed,
-		/// Commitment does not exist
-		CommitmentNotFound,
-		/// Invalid proof (for future zkSNARK verification)
-		InvalidProof,
-		/// Amount overflow
-		AmountOverflow,
-		/// Invalid randomness
-		InvalidRandomness,
-	}
-
-	#[pallet::hooks]
-	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
-
-	/// Dispatchable functions (extrinsics)
-	#[pallet::call]
-	impl<T: Config> Pallet<T> {
-		/// Deposit (shield) an asset into the privacy pool
-		///
-		/// This creates a commitment that hides the amount and returns a shielded note
-		/// The user must keep the shielded note data off-chain to later spend it
-		///
-		/// Parameters:
-		/// - `amount`: Amount to shield (in smallest unit)
-		/// - `asset_id`: Asset identifier (0 for native token)
-		/// - `randomness`: 32 bytes of randomness for commitment
-		///
-		/// Emits: `AssetShielded` event
+		/// Decoy deposits were scheduled (or cancelled when `count` is zero)
+		DecoysScheduled {
+			asset_id: u32,
+			count: u32,
+			interval: BlockNumberFor<T>,
+		},
+		/// A decoy note was reclaimed by the treasury
+		DecoyReclaimed {
+			commitment: H256,
+			asset_id: u32,
+			value: u128,
+		},
+		/// The abandonment period of an asset was changed
+		AbandonmentPeriodSet {
+			asset_id: XcmAssetId,
+			period: Option<u32>,
+		},
+		/// The open channel set was replaced
+		OpenChannelsSynced {
+			channels: BoundedVec<u32, ConstU32<MAX_OPEN_CHANNELS>>,
+		},
+		/// An asset's reserve chain was set or cleared
+		ReserveLocationSet {
+			asset_id: XcmAssetId,
+			reserve_location: Option<Location>,
+		},
+		/// An abandoned note was swept into the public claims pool
+		AbandonedNoteSwept {
+			leaf_index: u32,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+			claimant: T::AccountId,
+		},
+		/// A payout failed and its value was recorded as a claim
+		ClaimCreated {
+			claim_id: ClaimId,
+			asset_id: u32,
+			amount: u128,
+			beneficiary: T::AccountId,
+		},
+		/// A claim was paid out to its beneficiary
+		ClaimRedeemed {
+			claim_id: ClaimId,
+			asset_id: u32,
+			amount: u128,
+			beneficiary: T::AccountId,
+		},
+		/// A cross-chain withdrawal was queued for batched export
+		WithdrawalQueued {
+			withdrawal_id: WithdrawalId,
+			nullifier: H256,
+			asset_id: u32,
+			/// Amount to be sent, after the destination fee
+			amount: u128,
+			fee: u128,
+			destination: VersionedDestination,
+		},
+		/// A queued withdrawal was exported as part of a batch message
+		QueuedWithdrawalExecuted {
+			withdrawal_id: WithdrawalId,
+			message_id: MessageId,
+		},
+		/// The batch message carrying a queued withdrawal could not be exported
+		QueuedWithdrawalFailed {
+			withdrawal_id: WithdrawalId,
+		},
+		/// A shield deposit failed during XCM execution and its assets were trapped
+		ShieldDepositTrapped {
+			origin: Location,
+			asset_id: XcmAssetId,
+			amount: u128,
+		},
+		/// Trapped shield deposits were returned or shielded
+		TrappedAssetsReclaimed {
+			origin: Location,
+			asset_id: XcmAssetId,
+			amount: u128,
+			resolution: TrapResolution,
+		},
+		/// A cross-chain withdrawal was handed to the exporter
+		WithdrawalExported {
+			nullifier: H256,
+			asset_id: u32,
+			/// Amount sent, after the destination fee
+			amount: u128,
+			fee: u128,
+			destination: VersionedDestination,
+			message_id: MessageId,
+			/// Receipt ID of the withdrawal (see `withdrawal_by_id`)
+			receipt_id: H256,
+		},
+		/// The fee schedule for a destination was set or cleared
+		DestinationFeeSet {
+			destination: Location,
+			fee: Option<FeeConfig>,
+		},
+		/// Reserve backing of an asset moved by more than `ReserveBackingAlertThreshold`
+		/// within the current block
+		ReserveBackingAnomaly {
+			asset_id: u32,
+			/// Value moved in and out of the backing in this block so far
+			moved: u128,
+		},
+		/// An asset holds less than its shielded value; withdrawals were halted
+		SolvencyAlert {
+			asset_id: u32,
+			/// Value the pool owes holders of the asset's notes
+			expected: u128,
+			/// Value the pool actually holds
+			actual: u128,
+		},
+		/// A deposit was made while no verifying key is set, so nothing can be withdrawn yet
 		///
-		/// Week 1 MVP: Simple deposit without actual token transfer
-		/// Week 4+: Will integrate with XCM to receive assets from other chains
-		#[pallet::call_index(0)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(2))]
-		pub fn deposit(
-			origin: OriginFor<T>,
+		/// Raised once.
+		BridgeNotFullyConfigured,
+		/// An XCM deposit was delivered again; its funds are held as unattributed
+		DuplicateDeposit {
+			commitment: H256,
+			origin: Location,
+			asset_id: XcmAssetId,
+			amount: u128,
+		},
+		/// Unattributed funds were returned to their origin
+		UnattributedRefunded {
+			origin: Location,
+			asset_id: XcmAssetId,
 			amount: u128,
+			message_id: MessageId,
+		},
+		/// A verifying key was announced for rotation
+		VerifyingKeyAnnounced {
+			circuit: CircuitId,
+			circuit_version: u32,
+			spec_version: u32,
+			expires_at: BlockNumberFor<T>,
+		},
+		/// The announced verifying key became the active one
+		VerifyingKeyApplied {
+			circuit: CircuitId,
+			circuit_version: u32,
+		},
+		/// A deposit was split into `count` notes of `amount_each`, at leaf indices
+		/// `first_leaf_index..first_leaf_index + count` (each also has an `AssetShielded`)
+		DepositSplit {
+			depositor: T::AccountId,
 			asset_id: u32,
-			randomness: [u8; 32],
-		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
-
-			// Generate commitment: commitment = Hash(amount || asset_id || randomness)
-			let commitment = Self::generate_commitment(amount, asset_id, &randomness);
+			amount_each: u128,
+			count: u32,
+			first_leaf_index: u32,
+		},
+		/// A note holding several assets was shielded (amounts stay hidden, as in
+		/// `AssetShielded`)
+		BundleShielded {
+			commitment: H256,
+			asset_ids: BoundedVec<u32, ConstU32<MAX_BUNDLE_SIZE>>,
+			depositor: T::AccountId,
+			block_number: BlockNumberFor<T>,
+			leaf_index: u32,
+		},
+		/// A note holding several assets was unshielded
+		BundleUnshielded {
+			nullifier: H256,
+			asset_ids: BoundedVec<u32, ConstU32<MAX_BUNDLE_SIZE>>,
+			block_number: BlockNumberFor<T>,
+		},
+		/// An account was issued a participation receipt, holding `receipts` in total
+		ParticipationReceiptIssued {
+			account: T::AccountId,
+			receipts: u32,
+		},
+		/// The note age bounds of an asset were set or cleared
+		NoteAgeBoundsSet {
+			asset_id: XcmAssetId,
+			min_note_age: Option<u32>,
+			max_note_age: Option<u32>,
+		},
+		/// The transaction fee of a deposit was paid by the sponsorship pot
+		DepositSponsored {
+			account: T::AccountId,
+			amount: u128,
+			fee: u128,
+		},
+		/// Governance topped up the sponsorship pot, now holding `pot`
+		SponsorshipPotToppedUp {
+			amount: u128,
+			pot: u128,
+		},
+		/// Two notes were spent into notes for each other's owners
+		NotesSwapped {
+			nullifiers: [H256; 2],
+			output_commitments: [H256; 2],
+			leaf_indices: [u32; 2],
+			block_number: BlockNumberFor<T>,
+		},
+		/// The beneficiary account format of a destination was set or cleared
+		DestinationAccountFormatSet {
+			destination: Location,
+			format: Option<AccountFormat>,
+		},
+		/// An account was allowed to submit commitment batches
+		BatcherRegistered {
+			batcher: T::AccountId,
+		},
+		/// An account may no longer submit commitment batches
+		BatcherRemoved {
+			batcher: T::AccountId,
+		},
+		/// A batch of `count` commitments was shielded, at leaf indices
+		/// `first_leaf_index..first_leaf_index + count` (each also has an `AssetShielded`)
+		CommitmentBatchSubmitted {
+			batcher: T::AccountId,
+			asset_id: u32,
+			batch_root: H256,
+			total_amount: u128,
+			count: u32,
+			first_leaf_index: u32,
+		},
+		/// An account's encrypted note backup was stored or replaced, `deposit` now held
+		NoteBackupSet {
+			account: T::AccountId,
+			deposit: u128,
+		},
+		/// An account's note backup was removed and its deposit returned
+		NoteBackupCleared {
+			account: T::AccountId,
+			refunded: u128,
+		},
+		/// An account replaced its alert tags (cleared them when `count` is zero)
+		AlertTagsRegistered {
+			account: T::AccountId,
+			count: u32,
+		},
+		/// A rebuild of the tree cache started: the pallet is in maintenance mode
+		TreeRebuildStarted {
+			expected_root: H256,
+			leaves: u32,
+		},
+		/// A chunk of leaves was rehashed, `remaining` are left
+		TreeRebuildProgressed {
+			next_leaf: u32,
+			remaining: u32,
+		},
+		/// The tree cache was rebuilt to `root` over `leaves` leaves; maintenance mode ended
+		TreeRebuilt {
+			root: H256,
+			leaves: u32,
+		},
+		/// A proving job was posted with `bounty` held for its prover
+		ProvingJobPosted {
+			job_id: ProvingJobId,
+			owner: T::AccountId,
+			bounty: u128,
+			expires_at: BlockNumberFor<T>,
+		},
+		/// A prover bonded to fulfil a proving job before `deadline`
+		ProvingJobClaimed {
+			job_id: ProvingJobId,
+			prover: T::AccountId,
+			deadline: BlockNumberFor<T>,
+		},
+		/// A proving job's withdrawal went through; the prover got the bounty and its bond
+		ProvingJobFulfilled {
+			job_id: ProvingJobId,
+			prover: T::AccountId,
+			bounty: u128,
+		},
+		/// A proving job lapsed: the bounty went back to the owner, and the bond of a
+		/// prover that let its claim lapse to the treasury
+		ProvingJobExpired {
+			job_id: ProvingJobId,
+			refunded: u128,
+			slashed: u128,
+		},
+		/// An asset stopped taking deposits; its notes can be withdrawn until
+		/// `claim_window_ends`
+		WindDownStarted {
+			asset_id: u32,
+			claim_window_ends: BlockNumberFor<T>,
+		},
+		/// An asset's claim window closed; recorded notes are refunded `held / owed` of
+		/// their amount until `refunds_end`
+		WindDownRefundsOpened {
+			asset_id: u32,
+			owed: u128,
+			held: u128,
+			refunds_end: BlockNumberFor<T>,
+		},
+		/// A note of an asset winding down was refunded to a public claim
+		WindDownNoteRefunded {
+			leaf_index: u32,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+			claimant: T::AccountId,
+		},
+		/// An asset's wind-down completed; the value not refunded went to the treasury
+		WindDownFinalized {
+			asset_id: u32,
+			swept: u128,
+		},
+		/// One recipient of a split withdrawal was paid, or got a claim (`claim_id`) when
+		/// the transfer failed
+		SplitPayout {
+			receipt_id: H256,
+			recipient: T::AccountId,
+			amount: u128,
+			claim_id: Option<ClaimId>,
+		},
+		/// A withdrawal was split between `recipients` accounts
+		WithdrawalSplit {
+			receipt_id: H256,
+			asset_id: u32,
+			amount: u128,
+			recipients: u32,
+		},
+		/// An admin action was proposed, to be approved by the co-signer before
+		/// `expires_at`
+		AdminActionProposed {
+			action_hash: H256,
+			expires_at: BlockNumberFor<T>,
+		},
+		/// The co-signer approved a proposed admin action
+		AdminActionApproved {
+			action_hash: H256,
+		},
+		/// An approved admin action was executed, consuming its approval
+		AdminActionExecuted {
+			action_hash: H256,
+		},
+		/// Two notes were withdrawn together as one amount
+		NotesJoined {
+			nullifiers: [H256; 2],
+			asset_id: u32,
+			block_number: BlockNumberFor<T>,
+			/// Receipt ID of the withdrawal (see `withdrawal_by_id`)
+			receipt_id: H256,
+		},
+		/// An XCM asset was registered under a local asset ID
+		AssetRegistered {
+			xcm_asset_id: XcmAssetId,
+			local_id: u32,
+			min_deposit: u128,
+		},
+		/// An asset stopped or resumed taking deposits
+		AssetStatusChanged {
+			asset_id: XcmAssetId,
+			is_active: bool,
+		},
+		/// The minimum deposit of an asset was changed
+		AssetMinDepositUpdated {
+			asset_id: XcmAssetId,
+			min_deposit: u128,
+		},
+		/// The amounts an asset takes deposits of were changed (empty for any amount)
+		DenominationsSet {
+			asset_id: XcmAssetId,
+			denominations: Denominations,
+		},
+		/// A relayer submitted the withdrawal of `receipt_id` and was paid `fee` out of it
+		WithdrawalRelayed {
+			receipt_id: H256,
+			relayer: T::AccountId,
+			fee: u128,
+		},
+		/// The admin paused or unpaused the bridge, or a failed solvency check paused
+		/// withdrawals
+		PauseLevelSet {
+			level: PauseLevel,
+		},
+		/// An asset's anonymity score fell below `MinAnonymityScore` while it still takes
+		/// deposits
+		LowAnonymityWarning {
+			asset_id: u32,
+			score: u32,
+		},
+		/// Deposit receipts of an asset were enabled or disabled
+		ReceiptCallSet {
+			asset_id: XcmAssetId,
+			receipt_call: Option<[u8; 2]>,
+		},
+		/// A deposit receipt was sent to the chain the deposit came from
+		DepositReceiptSent {
+			commitment: H256,
+			origin: Location,
+			message_id: MessageId,
+		},
+		/// A deposit receipt could not be sent; the deposit itself stands
+		DepositReceiptFailed {
+			commitment: H256,
+			origin: Location,
+		},
+	}
+
+	/// Errors that can occur in the privacy bridge pallet
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Commitment already exists
+		CommitmentAlreadyExists,
+		/// Nullifier has already been used (double-spend attempt)
+		NullifierAlreadyUsed,
+		/// Commitment does not exist
+		CommitmentNotFound,
+		/// Invalid proof (for future zkSNARK verification)
+		InvalidProof,
+		/// Amount overflow
+		AmountOverflow,
+		/// Invalid randomness
+		InvalidRandomness,
+		/// Decoy interval must be non-zero
+		InvalidDecoySchedule,
+		/// Commitment is not an outstanding decoy note
+		DecoyNotFound,
+		/// Asset is not registered
+		AssetNotRegistered,
+		/// Abandonment period must be non-zero
+		InvalidAbandonmentPeriod,
+		/// No leaf exists at the given index
+		LeafNotFound,
+		/// Commitment has been denied and can no longer be withdrawn
+		CommitmentDenied,
+		/// Note has already been spent
+		NoteAlreadySpent,
+		/// Note cannot be swept (its asset had no abandonment period when deposited)
+		NoteNotAbandonable,
+		/// Note is younger than its asset's abandonment period
+		NoteNotYetAbandoned,
+		/// Claim does not exist
+		ClaimNotFound,
+		/// Claim cannot be paid out yet (the transfer to the beneficiary still fails)
+		ClaimNotRedeemable,
+		/// Payout would leave the recipient below the asset's existential deposit
+		BelowExistentialDeposit,
+		/// The exporter does not accept the beneficiary
+		InvalidBeneficiary,
+		/// The exporter failed to send the withdrawal
+		ExportFailed,
+		/// No assets are trapped for this origin and asset
+		NoTrappedAssets,
+		/// The commitment tree has no free leaves
+		TreeFull,
+		/// No open channel to the withdrawal's first hop
+		NoChannelToDestination,
+		/// The deposit is below the asset's minimum deposit
+		DepositBelowMinimum,
+		/// The destination fee is above the caller's `max_fee`
+		FeeExceedsMaximum,
+		/// The destination or relayer fee is above the withdrawn amount
+		FeeExceedsAmount,
+		/// The proportional fee is above one million parts per million
+		InvalidFeeConfig,
+		/// No queued withdrawal with this ID
+		WithdrawalNotFound,
+		/// Only failed withdrawals can be retried
+		WithdrawalNotFailed,
+		/// The withdrawal exceeds what the pool holds on the asset's reserve chain
+		InsufficientReserveBacking,
+		/// Withdrawals are paused, by the admin or the solvency circuit breaker
+		WithdrawalsPaused,
+		/// No unattributed funds for this origin and asset
+		NoUnattributedBalance,
+		/// The commitment or nullifier is a reserved hash value (all zeros)
+		ReservedCommitmentValue,
+		/// No verifying key is announced
+		NoPendingAnnouncement,
+		/// The announced verifying key was made for other circuits or another runtime
+		StaleAnnouncement,
+		/// The announced verifying key was not applied within `AnnouncementTtl` blocks
+		AnnouncementExpired,
+		/// The verifying key was generated for another note hasher than the runtime's
+		HasherMismatch,
+		/// No verifying key is set
+		VerifyingKeyNotSet,
+		/// The payout cannot create the recipient's account (e.g. an insufficient asset)
+		RecipientCannotBeCreated,
+		/// The proof was made for other circuits than the verifying key's
+		CircuitVersionMismatch,
+		/// A split deposit lists no commitments
+		EmptySplit,
+		/// Withdrawals must be anchored to a recent block (`withdraw_anchored`)
+		ProofAnchorRequired,
+		/// The anchor block hash is not one of the last `MaxProofAge` blocks
+		ProofAnchorExpired,
+		/// A bundle is empty, not ordered by strictly increasing asset ID, or has a zero
+		/// amount
+		InvalidBundle,
+		/// A bundle note was withdrawn as a single-asset note, or the other way round
+		NoteKindMismatch,
+		/// The minimum note age exceeds the maximum, or the maximum is zero
+		InvalidNoteAgeBounds,
+		/// Notes of the asset have age bounds and must be withdrawn against a recent root
+		/// (`withdraw`, `withdraw_via_relayer`)
+		NoteAgeRootRequired,
+		/// The merkle root is not in `RootHistory`
+		UnknownMerkleRoot,
+		/// Leaves under the root may be younger than the asset's minimum note age
+		NoteTooYoung,
+		/// Leaves under the root may be older than the asset's maximum note age
+		NoteTooOld,
+		/// The deposit is not eligible for sponsoring, or the pot cannot pay its fee
+		NotSponsored,
+		/// The swap deadline has passed
+		SwapExpired,
+		/// The legs of a swap are of the same asset or create the same note
+		InvalidSwap,
+		/// A swap leg's asset is not the asset of the note it spends
+		SwapAssetMismatch,
+		/// The beneficiary is not as long as the destination's accounts
+		InvalidBeneficiaryLength,
+		/// The account is not a registered batcher
+		NotBatcher,
+		/// A commitment batch lists no commitments
+		EmptyBatch,
+		/// The batch root is not the root of the listed commitments
+		BatchRootMismatch,
+		/// The block's commitment batches would exceed `MaxBatchLeavesPerBlock`
+		BatchLimitExceeded,
+		/// A note backup is not exactly `MaxBackupLen` bytes long
+		BackupSizeMismatch,
+		/// The account has no note backup
+		NoNoteBackup,
+		/// The spent note was shielded as another asset than the withdrawal claims
+		NoteAssetMismatch,
+		/// Withdrawals used this block's proof verification weight; retry in a later block
+		VerificationBudgetExhausted,
+		/// The same alert tag is listed twice
+		DuplicateAlertTag,
+		/// The origin of an XCM deposit is too deep or holds an oversized key
+		InvalidOriginLocation,
+		/// The tree cache is being rebuilt; no leaf can be inserted until it is done
+		TreeUnderMaintenance,
+		/// No tree cache rebuild is under way
+		NoTreeRebuild,
+		/// A rebuild chunk must start at the next leaf to rehash, and hold between one and
+		/// `MAX_REBUILD_CHUNK` stored leaves
+		InvalidRebuildChunk,
+		/// The rebuilt root differs from the root governance expected
+		RebuiltRootMismatch,
+		/// The deposit's note key was used by another deposit within
+		/// `RandomnessReuseWindow` blocks
+		RandomnessReused,
+		/// The proving job board holds `MaxProvingJobs` jobs
+		TooManyProvingJobs,
+		/// The bounty is below `MinProvingBounty`
+		BountyTooLow,
+		/// No proving job with this ID is on the board
+		ProvingJobNotFound,
+		/// The proving job was already claimed
+		ProvingJobAlreadyClaimed,
+		/// Only the prover that claimed the job can fulfil it
+		NotJobProver,
+		/// The proving job can no longer be claimed or fulfilled
+		ProvingJobLapsed,
+		/// The proving job is still open or its claim still running
+		ProvingJobNotLapsed,
+		/// The asset is being wound down
+		AssetWindingDown,
+		/// The asset is not in the phase of its wind-down this needs
+		WrongWindDownPhase,
+		/// The current wind-down phase has not ended yet
+		WindDownPhaseRunning,
+		/// Notes of the asset can no longer be withdrawn with a proof
+		ClaimWindowClosed,
+		/// The note has no record of its amount, so it cannot be refunded publicly
+		NoteNotRefundable,
+		/// A split withdrawal names no recipient
+		NoPayouts,
+		/// The payouts of a split withdrawal do not add up to the withdrawn amount
+		PayoutSumMismatch,
+		/// The admin action is already proposed and its proposal has not expired
+		AdminActionAlreadyProposed,
+		/// No admin action was proposed under this hash
+		AdminActionNotProposed,
+		/// The admin action was not proposed and approved by the co-signer
+		AdminActionNotApproved,
+		/// The admin action's proposal expired before it was approved or executed
+		AdminApprovalExpired,
+		/// The depositor's free balance does not cover the native deposit
+		InsufficientBalance,
+		/// The verifying key is longer than `VerifyingKeyBytes` holds
+		///
+		/// No longer returned: calls now take the key bounded, and fail to decode instead.
+		VerifyingKeyTooLarge,
+		/// The asset is registered but no longer accepts deposits (e.g. it is wound down)
+		AssetInactive,
+		/// The asset is already registered
+		AssetAlreadyRegistered,
+		/// `deposit_from_xcm` is disabled (see `Config::AllowSignedXcmDeposits`)
+		SignedXcmDepositsDisabled,
+		/// The amount is not one of the asset's denominations, or the denominations are
+		/// not increasing non-zero amounts
+		InvalidDenomination,
+		/// The chain takes no unsigned relayed withdrawals (`UnsignedRelayFee` is `None`)
+		UnsignedWithdrawalsDisabled,
+		/// An unsigned relayed withdrawal does not pay the treasury `UnsignedRelayFee`
+		UnsignedRelayFeeNotPaid,
+		/// The verifying key does not decode, so it cannot be prepared for verification
+		MalformedVerifyingKey,
+		/// The admin has paused the bridge for this operation
+		BridgePaused,
+	}
+
+	/// Stable code of the error, see `error_codes`
+	impl<T: Config> From<Error<T>> for u16 {
+		fn from(error: Error<T>) -> u16 {
+			crate::error_codes::error_code(error.as_str())
+				.unwrap_or(crate::error_codes::UNKNOWN_ERROR_CODE)
+		}
+	}
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Format version the config was written for, `GENESIS_CONFIG_VERSION`
+		///
+		/// Genesis fails for any other version, so a config written for another format is
+		/// never applied with fields it does not know about.
+		pub version: u32,
+		/// Register the relay chain token (`Location::parent()`) as the first XCM asset
+		pub register_relay_asset: bool,
+		/// Minimum deposit of the relay asset
+		pub relay_asset_min_deposit: u128,
+		/// Route withdrawals of the relay asset through the relay chain, its reserve.
+		/// With `false` it is treated as teleported and sent straight to destinations.
+		pub relay_asset_via_reserve: bool,
+		/// Further assets to register, in order: `(location, min_deposit, reserve)`
+		pub assets: Vec<(Location, u128, Option<Location>)>,
+		/// Note age bounds of registered assets: `(location, min_note_age, max_note_age)`
+		pub note_age_bounds: Vec<(Location, Option<u32>, Option<u32>)>,
+		/// Start at `PauseLevel::WithdrawalsPaused`, until governance unpauses
+		pub withdrawals_paused: bool,
+		/// Verifying keys to start with: `(circuit, vk_bytes)`, each set up for the runtime's
+		/// `Config::Hasher`, so withdrawals work from the first block
+		pub verifying_keys: Vec<(CircuitId, Vec<u8>)>,
+		#[serde(skip)]
+		pub _config: PhantomData<T>,
+	}
+
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self {
+				version: GENESIS_CONFIG_VERSION,
+				register_relay_asset: false,
+				relay_asset_min_deposit: DEFAULT_RELAY_MIN_DEPOSIT,
+				relay_asset_via_reserve: true,
+				assets: Vec::new(),
+				note_age_bounds: Vec::new(),
+				withdrawals_paused: false,
+				verifying_keys: Vec::new(),
+				_config: PhantomData,
+			}
+		}
+	}
+
+	impl<T: Config> GenesisConfig<T> {
+		/// Config of the preset `name`, if there is one
+		///
+		/// - `DEVELOPMENT_PRESET`: the relay asset routed via its reserve, with the default
+		///   minimum deposit and no note age bounds
+		/// - `CONSERVATIVE_PRESET`: a relay asset minimum deposit of 1 DOT, notes withdrawable
+		///   only after 600 blocks (an hour of 6 s blocks), and withdrawals paused until
+		///   governance has set a verifying key and unpauses
+		pub fn preset(name: &str) -> Option<Self> {
+			let relay_via_reserve = Self { register_relay_asset: true, ..Default::default() };
+			match name {
+				DEVELOPMENT_PRESET => Some(relay_via_reserve),
+				CONSERVATIVE_PRESET => Some(Self {
+					relay_asset_min_deposit: 10u128.pow(RELAY_ASSET_DECIMALS),
+					note_age_bounds: vec![(Location::parent(), Some(600), None)],
+					withdrawals_paused: true,
+					..relay_via_reserve
+				}),
+				_ => None,
+			}
+		}
+
+		/// Register `location` in the pool's custody, routed via `reserve` if set
+		fn register_genesis_asset(
+			location: Location,
+			min_deposit: u128,
+			reserve: Option<Location>,
+		) {
+			let asset_id = XcmAssetId(location);
+			Pallet::<T>::do_register_asset(asset_id.clone(), min_deposit, CustodyMode::Pot, 0)
+				.expect("genesis assets are distinct");
+			AssetRegistry::<T>::mutate(asset_id, |asset| {
+				if let Some(asset) = asset {
+					asset.reserve_location = reserve;
+				}
+			});
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			assert_eq!(
+				self.version, GENESIS_CONFIG_VERSION,
+				"genesis config written for another format version"
+			);
+
+			if self.register_relay_asset {
+				let reserve = self.relay_asset_via_reserve.then(Location::parent);
+				let min_deposit = self.relay_asset_min_deposit;
+				Self::register_genesis_asset(Location::parent(), min_deposit, reserve);
+			}
+			for (location, min_deposit, reserve) in self.assets.iter() {
+				Self::register_genesis_asset(location.clone(), *min_deposit, reserve.clone());
+			}
+
+			for (location, min_note_age, max_note_age) in self.note_age_bounds.iter() {
+				assert!(*max_note_age != Some(0), "genesis maximum note age of zero");
+				if let (Some(min), Some(max)) = (min_note_age, max_note_age) {
+					assert!(min <= max, "genesis minimum note age above the maximum");
+				}
+				AssetRegistry::<T>::mutate(XcmAssetId(location.clone()), |asset| {
+					let asset = asset.as_mut().expect("note age bounds of an unregistered asset");
+					asset.min_note_age = *min_note_age;
+					asset.max_note_age = *max_note_age;
+				});
+			}
+
+			for (circuit, vk_bytes) in self.verifying_keys.iter() {
+				let vk: VerifyingKeyBytes =
+					vk_bytes.clone().try_into().expect("genesis verifying key too long");
+				Pallet::<T>::put_verifying_key(*circuit, vk)
+					.expect("genesis verifying key does not decode");
+				if *circuit == CircuitId::Withdraw {
+					VerifyingKeySetup::<T>::put(SetupMetadata {
+						hasher: T::Hasher::id(),
+						circuit_version: crate::circuit::CIRCUIT_VERSION,
+					});
+				}
+			}
+
+			if self.withdrawals_paused {
+				Paused::<T>::put(PauseLevel::WithdrawalsPaused);
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			VerificationWeightUsed::<T>::kill();
+			let mut weight = Self::create_due_decoys(n).saturating_add(T::DbWeight::get().writes(1));
+			weight.saturating_accrue(Self::prune_recent_randomness(n));
+			if Self::solvency_check_due(n) {
+				weight.saturating_accrue(Self::solvency_check_weight());
+			}
+			if T::BatchTreeInsertion::get() {
+				weight.saturating_accrue(Self::tree_fold_weight());
+			}
+			weight
+		}
+
+		fn on_finalize(n: BlockNumberFor<T>) {
+			if T::BatchTreeInsertion::get() {
+				Self::fold_pending_leaves();
+			}
+			if Self::solvency_check_due(n) {
+				Self::check_solvency();
+			}
+		}
+
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			if Paused::<T>::get().withdrawals_paused() {
+				return T::DbWeight::get().reads(1);
+			}
+			Self::drain_withdrawal_queue(remaining_weight)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::do_try_state()
+		}
+	}
+
+	/// Pool checks of unsigned `withdraw` and `withdraw_via_relayer` transactions
+	///
+	/// No fee is paid up front, so the pool only takes a withdrawal that would go through:
+	/// it pays the treasury its `UnsignedRelayFee`, its nullifier is unused and its proof
+	/// verifies. The nullifier is the tag it provides, so the pool keeps one withdrawal per
+	/// note, for as long as its root may still be accepted (see `validate_unsigned_withdrawal`).
+	/// Once in a block, only the fee and the nullifier are checked again before dispatch,
+	/// which verifies the proof itself.
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			match call {
+				Call::withdraw {
+					nullifier,
+					commitment,
+					merkle_root,
+					amount,
+					asset_id,
+					proof,
+					recipient,
+				} => {
+					let Some(recipient) = recipient else {
+						return InvalidTransaction::Call.into();
+					};
+					if Self::unsigned_withdrawal_fee(*amount).is_err() {
+						return InvalidTransaction::Payment.into();
+					}
+					Self::validate_unsigned_withdrawal(
+						proof,
+						nullifier,
+						commitment,
+						merkle_root,
+						*asset_id,
+						*amount,
+						&primitives::recipient_hash(recipient),
+					)
+				},
+				Call::withdraw_via_relayer {
+					proof,
+					nullifier,
+					commitment,
+					merkle_root,
+					amount,
+					recipient,
+					relayer,
+					fee,
+					asset_id,
+				} => {
+					if *fee > *amount || Self::ensure_unsigned_relay_fee(relayer, *fee).is_err() {
+						return InvalidTransaction::Payment.into();
+					}
+					Self::validate_unsigned_withdrawal(
+						proof,
+						nullifier,
+						commitment,
+						merkle_root,
+						*asset_id,
+						*amount,
+						&primitives::relayed_recipient_hash(recipient, relayer, *fee),
+					)
+				},
+				_ => InvalidTransaction::Call.into(),
+			}
+		}
+
+		fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+			let (nullifier, fee_paid) = match call {
+				Call::withdraw { nullifier, amount, .. } =>
+					(nullifier, Self::unsigned_withdrawal_fee(*amount).is_ok()),
+				Call::withdraw_via_relayer { nullifier, relayer, fee, .. } =>
+					(nullifier, Self::ensure_unsigned_relay_fee(relayer, *fee).is_ok()),
+				_ => return Err(InvalidTransaction::Call.into()),
+			};
+			if !fee_paid {
+				return Err(InvalidTransaction::Payment.into());
+			}
+			if NullifierSet::<T>::get(nullifier) {
+				return Err(InvalidTransaction::Stale.into());
+			}
+			Ok(())
+		}
+	}
+
+	/// Dispatchable functions (extrinsics)
+	///
+	/// Every call is pinned to an explicit `call_index`; new calls take the next free one.
+	/// Encodings are checked against fixtures in `call_encoding_tests`.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Deposit (shield) an asset into the privacy pool
+		///
+		/// This creates a commitment that hides the amount and returns a shielded note
+		/// The user must keep the shielded note data off-chain to later spend it
+		///
+		/// Parameters:
+		/// - `amount`: Amount to shield (in smallest unit)
+		/// - `asset_id`: Asset identifier (0 for native token)
+		/// - `note_key`: Key of the depositor's note secret (`primitives::note_key`)
+		///
+		/// Emits: `AssetShielded` event
+		///
+		/// The chain derives the note's randomness from `note_key`, so only the holder of
+		/// the secret can open the note. A bare commitment is not taken: the chain would
+		/// have no way to check that it commits to the amount transferred.
+		///
+		/// Native deposits are transferred from the caller to the pool account
+		/// (`Pallet::account_id`), failing with `InsufficientBalance` if the caller cannot
+		/// pay them. Non-native assets (`asset_id != 0`) are locked in the pool account or
+		/// burned, depending on their custody mode. Deposits of a registered asset fail with
+		/// `AssetInactive` once it is deactivated, and with `DepositBelowMinimum` below its
+		/// minimum deposit, as over XCM.
+		///
+		/// The first deposit made before a verifying key is set also emits
+		/// `BridgeNotFullyConfigured`.
+		///
+		/// The commitment is hashed into the merkle tree through all `TREE_DEPTH` levels,
+		/// right away or at the end of the block with `BatchTreeInsertion`.
+		///
+		/// Leaf indices are assigned consecutively in the order deposits execute, so the
+		/// deposits of a block take their indices in extrinsic order. A deposit that fails
+		/// is rolled back and consumes no index. The assigned index is reported as
+		/// `leaf_index` in `AssetShielded`; `PostDispatchInfo` only carries weight and fee
+		/// information, so it cannot return it.
+		#[pallet::call_index(0)]
+		#[pallet::weight(Pallet::<T>::deposit_weight())]
+		pub fn deposit(
+			origin: OriginFor<T>,
+			amount: u128,
+			asset_id: u32,
+			note_key: [u8; 32],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_can_pay_native(asset_id, &who, amount)?;
+			Self::ensure_fresh_randomness(&note_key)?;
+
+			// Generate commitment: commitment = Hash(amount || asset_id || randomness)
+			let commitment = Self::generate_commitment(amount, asset_id, &note_key);
+
+			Self::do_shield(who, commitment, asset_id, amount)?;
+
+			// NOTE: In a real implementation, the shielded note would be returned to the user
+			// or encrypted and stored. For now, the user would reconstruct it from the event data
+			// and their secret.
+
+			Ok(())
+		}
+
+		/// Withdraw (unshield) an asset from the privacy pool
+		///
+		/// `proof` must prove the caller knows the note of `commitment` and its secret, and
+		/// that the commitment is a leaf of the tree at `merkle_root`, with `nullifier`,
+		/// `commitment`, `merkle_root`, `asset_id`, `amount` and the hash of the recipient
+		/// (`recipient`, or else the caller), who is paid, as public inputs; it is verified
+		/// before the nullifier is marked used.
+		///
+		/// `merkle_root` must still be in `RootHistory`, so a proof made before later deposits
+		/// moved the root stays valid for `RootHistorySize` root updates. For assets with note
+		/// age bounds the pool does not learn which leaf is spent, so the bounds are checked
+		/// against the block range of all leaves under the root (see `set_note_age_bounds`).
+		/// Notes of a denomination pool are proven against the current root of their pool
+		/// instead (see `set_denominations`).
+		///
+		/// Fails with `UnknownMerkleRoot` for any other root, with `VerifyingKeyNotSet` until
+		/// governance sets a verifying key, with `InvalidProof` if the proof does not verify,
+		/// and with `ProofAnchorRequired` when `MaxProofAge` is set (see `withdraw_anchored`).
+		///
+		/// Parameters:
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The commitment being spent (public input of the withdrawal proof)
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `amount`: Amount of the note, all of it withdrawn
+		/// - `asset_id`: Asset identifier
+		/// - `proof`: Serialized Groth16 proof (`zksnark::generate_proof`), at most
+		///   `MaxProofSize` bytes
+		/// - `recipient`: Account paid, the caller if `None`; required when unsigned
+		///
+		/// The value is paid to the recipient from the pool account. A payout that would leave
+		/// the recipient below the existential deposit is rejected up front, unless
+		/// `ClaimDustPayouts` is set; any other failed transfer is recorded as a claim.
+		///
+		/// The call may also be submitted unsigned, as `withdraw_via_relayer` can: the
+		/// treasury is then paid `UnsignedRelayFee` out of the note, and the pool only takes
+		/// the transaction once its proof verifies (see `validate_unsigned`).
+		///
+		/// Emits: `AssetUnshielded` event, `ClaimCreated` if the payout failed, and
+		/// `WithdrawalRelayed` when unsigned
+		#[pallet::call_index(1)]
+		#[pallet::weight(
+			T::WeightInfo::withdraw()
+				.saturating_add(T::DbWeight::get().reads(T::RootHistorySize::get() as u64))
+				.saturating_add(T::DbWeight::get().reads_writes(3, 4))
+		)]
+		pub fn withdraw(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			amount: u128,
+			asset_id: u32,
+			proof: ProofOf<T>,
+			recipient: Option<T::AccountId>,
+		) -> DispatchResult {
+			let submitter = frame_system::ensure_signed_or_none(origin)?;
+			let fee = match submitter {
+				Some(_) => None,
+				None => Some(Self::unsigned_withdrawal_fee(amount)?),
+			};
+			let who = recipient.or(submitter).ok_or(DispatchError::BadOrigin)?;
+			let recipient_hash = primitives::recipient_hash(&who);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			match fee {
+				Some(fee) => Self::withdraw_relayed(
+					nullifier,
+					commitment,
+					amount,
+					asset_id,
+					who,
+					T::TreasuryAccount::get(),
+					fee,
+				),
+				None => Self::do_withdraw(who, nullifier, commitment, amount, asset_id),
+			}
+		}
+
+		/// Week 4: Register an XCM asset for cross-chain deposits
+		///
+		/// Allows governance to register assets from other parachains. Local IDs are
+		/// assigned from 1; this chain's own token (`Location::here()`) is always
+		/// registered as `NATIVE_ASSET_ID`. An asset is registered once, under any of its
+		/// equivalent locations: registering it again fails with `AssetAlreadyRegistered`.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId to register
+		/// - `min_deposit`: Minimum deposit amount
+		/// - `custody_mode`: How shielded value is held; cannot be changed later
+		/// - `remote_min_balance`: Existential deposit of the asset on destination chains
+		///
+		/// Emits: `AssetRegistered` event
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::register_asset())]
+		pub fn register_asset(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			min_deposit: u128,
+			custody_mode: CustodyMode,
+			remote_min_balance: u128,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+
+			Self::do_register_asset(asset_id, min_deposit, custody_mode, remote_min_balance)?;
+
+			Ok(())
+		}
+
+		/// Week 4: Cross-chain deposit via XCM
+		///
+		/// Creates a commitment for assets the caller says were received from another
+		/// parachain. Nothing checks that claim, so the call fails with
+		/// `SignedXcmDepositsDisabled` unless `Config::AllowSignedXcmDeposits` is set (test
+		/// chains); real deposits are shielded by `xcm_config::ShieldDepositTransactor`
+		/// when the XCM executor delivers them.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId being deposited
+		/// - `amount`: Amount received
+		/// - `origin`: Location of sender parachain
+		/// - `note_key`: Key of the depositor's note secret (`primitives::note_key`)
+		///
+		/// Delivering the same deposit again succeeds without inserting a second leaf;
+		/// the duplicate amount is recorded in `UnattributedBalances`. Origins beyond the
+		/// bounds of `xcm_config::is_valid_origin_location` fail with `InvalidOriginLocation`
+		/// before anything is hashed or stored. Deposits of an asset deactivated with
+		/// `set_asset_status` fail with `AssetInactive`.
+		///
+		/// The commitment is domain-separated from local deposits (see
+		/// `xcm_config::xcm_commitment_data`), so it never collides with a `deposit` made
+		/// with the same parameters.
+		///
+		/// Emits: `AssetShielded` event, `DepositReceiptSent` if the asset sends receipts,
+		/// `DuplicateDeposit` for a redelivered deposit
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::deposit_from_xcm())]
+		pub fn deposit_from_xcm(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			amount: u128,
+			origin_location: Location,
+			note_key: [u8; 32],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(T::AllowSignedXcmDeposits::get(), Error::<T>::SignedXcmDepositsDisabled);
+			Self::ensure_deposits_enabled()?;
+			ensure!(
+				crate::xcm_config::is_valid_origin_location(&origin_location),
+				Error::<T>::InvalidOriginLocation
+			);
+
+			// Look up registered asset
+			let registered = Self::registered_asset(&asset_id)
+				.ok_or(Error::<T>::AssetNotRegistered)
+				.inspect_err(|_| {
+					log::warn!(target: LOG_TARGET, "XCM deposit of unregistered asset {asset_id:?}")
+				})?;
+			ensure!(registered.is_active, Error::<T>::AssetInactive);
+
+			// Check minimum deposit
+			if amount < registered.min_deposit {
+				log::warn!(
+					target: LOG_TARGET,
+					"XCM deposit of asset {} below its minimum deposit",
+					registered.local_id
+				);
+				return Err(Error::<T>::DepositBelowMinimum.into());
+			}
+			let denomination = Self::denomination_pool(&registered.denominations, amount)?;
+
+			// Generate commitment using local asset ID
+			let commitment = crate::xcm_config::xcm_commitment_data::<T::Hasher>(
+				amount,
+				registered.local_id,
+				&note_key,
+				&origin_location,
+			);
+
+			// A redelivered message succeeds without a second leaf
+			if Self::is_duplicate_xcm_deposit(commitment, &registered, &origin_location) {
+				Self::note_unattributed(commitment, &origin_location, &registered, amount);
+				return Ok(());
+			}
+
+			// Store commitment metadata
+			let leaf_index = Self::insert_commitment(commitment, &who, registered.local_id)
+				.and_then(|index| {
+					Self::insert_denominated(registered.local_id, denomination, commitment)
+						.map(|_| index)
+				})
+				.inspect_err(Self::warn_failure("XCM deposit", commitment))?;
+			XcmDepositOrigins::<T>::insert(commitment, &origin_location);
+			Self::note_shielded(registered.local_id, amount)
+				.and_then(|_| Self::note_backing_in(&registered, amount))
+				.inspect_err(Self::warn_failure("XCM deposit", commitment))?;
+			Self::record_for_abandonment(commitment, registered.local_id, amount);
+
+			// Emit event
+			Self::deposit_event(Event::AssetShielded {
+				commitment,
+				asset_id: registered.local_id,
+				depositor: who,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				leaf_index,
+				merkle_root: MerkleRoot::<T>::get(),
+			});
+			T::Metrics::deposit(registered.local_id);
+			log::debug!(
+				target: LOG_TARGET,
+				"shielded {commitment:?} of asset {} from {origin_location:?} at leaf {leaf_index}",
+				registered.local_id
+			);
+
+			Self::send_deposit_receipt(&registered, &origin_location, commitment, leaf_index);
+
+			Ok(())
+		}
+
+		/// Week 4: Withdraw to another parachain via XCM
+		///
+		/// Withdraw assets and send them to a destination parachain. Thin wrapper over
+		/// `withdraw_to_remote` with an XCM destination and beneficiary.
+		///
+		/// The proof is checked as for `withdraw`, paying the `primitives::recipient_hash` of
+		/// `beneficiary` instead of the caller, so anyone may submit it. A note shielded over
+		/// XCM is proven with `xcm_withdrawal_tweak` and the amount that was delivered.
+		/// Deposits only reveal the note key, so only the holder of the note's secret can
+		/// prove a withdrawal, and choose its beneficiary.
+		///
+		/// Parameters:
+		/// - `nullifier`: Nullifier hash
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `asset_id`: Local asset ID
+		/// - `amount`: Amount to withdraw
+		/// - `destination`: Destination parachain location
+		/// - `beneficiary`: Recipient account on destination chain
+		/// - `max_fee`: Highest destination fee accepted; `amount` less the fee is sent
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, `WithdrawalExported` events
+		#[pallet::call_index(5)]
+		#[pallet::weight(
+			T::WeightInfo::withdraw_to_parachain()
+				.saturating_add(T::DbWeight::get().reads(T::RootHistorySize::get() as u64))
+		)]
+		pub fn withdraw_to_parachain(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: Location,
+			beneficiary: Location,
+			max_fee: u128,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let recipient_hash = primitives::recipient_hash(&beneficiary);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			Self::do_withdraw_to_remote(
+				nullifier,
+				commitment,
+				asset_id,
+				amount,
+				destination.into(),
+				&beneficiary.encode(),
+				max_fee,
+			)
+		}
+
+		/// Schedule protocol-funded decoy deposits for an under-populated asset
+		///
+		/// Newly registered assets have tiny anonymity sets. Decoys are notes owned by
+		/// the treasury, created by `on_initialize` every `interval` blocks until `count`
+		/// decoys exist. Scheduling again replaces the previous schedule; a `count` of
+		/// zero cancels it.
+		///
+		/// Parameters:
+		/// - `asset_id`: Local asset ID to pad
+		/// - `count`: Number of decoys to create
+		/// - `interval`: Blocks between two decoys
+		///
+		/// Emits: `DecoysScheduled` event
+		#[pallet::call_index(6)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn schedule_decoys(
+			origin: OriginFor<T>,
+			asset_id: u32,
+			count: u32,
+			interval: BlockNumberFor<T>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			if count == 0 {
+				DecoySchedules::<T>::remove(asset_id);
+			} else {
+				ensure!(!interval.is_zero(), Error::<T>::InvalidDecoySchedule);
+
+				let now = <frame_system::Pallet<T>>::block_number();
+				DecoySchedules::<T>::insert(asset_id, DecoySchedule {
+					remaining: count,
+					interval,
+					next_at: now.saturating_add(interval),
+				});
+			}
+
+			Self::deposit_event(Event::DecoysScheduled { asset_id, count, interval });
+
+			Ok(())
+		}
+
+		/// Reclaim a decoy note back to the treasury
+		///
+		/// Consumes the decoy's nullifier so the note can never be spent again and
+		/// removes its value from the protocol-owned accounting.
+		///
+		/// Parameters:
+		/// - `commitment`: Commitment of the decoy note
+		///
+		/// Emits: `DecoyReclaimed` event
+		#[pallet::call_index(7)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(6, 6))]
+		pub fn reclaim_decoy(
+			origin: OriginFor<T>,
+			commitment: H256,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let note = DecoyNotes::<T>::take(&commitment).ok_or(Error::<T>::DecoyNotFound)?;
+
+			Self::spend_note(note.nullifier, commitment)?;
+
+			Self::note_unshielded(note.asset_id, note.value);
+			ProtocolOwnedShielded::<T>::mutate(note.asset_id, |total| {
+				*total = total.saturating_sub(note.value)
+			});
+
+			Self::deposit_event(Event::DecoyReclaimed {
+				commitment,
+				asset_id: note.asset_id,
+				value: note.value,
+			});
+
+			Ok(())
+		}
+
+		/// Set or clear the abandonment period of a registered asset
+		///
+		/// Notes deposited while a period is set can be swept into the public claims
+		/// pool once they are older than the period and still unspent.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `period`: Abandonment period in blocks, `None` to disable
+		///
+		/// Emits: `AbandonmentPeriodSet` event
+		#[pallet::call_index(8)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_abandonment_period(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			period: Option<u32>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(period != Some(0), Error::<T>::InvalidAbandonmentPeriod);
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.abandonment_period = period;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::AbandonmentPeriodSet { asset_id, period });
+
+			Ok(())
+		}
+
+		/// Sweep an abandoned note into the public claims pool (permissionless)
+		///
+		/// The note must be unspent and older than its asset's abandonment period. Its
+		/// commitment is denied first, so late withdrawal proofs fail. The value is
+		/// credited to the recorded depositor, or to the treasury when the commitment
+		/// metadata has been pruned. Notes without a sweep record cannot be swept.
+		///
+		/// Parameters:
+		/// - `leaf_index`: Index of the abandoned leaf
+		///
+		/// Emits: `AbandonedNoteSwept` event
+		#[pallet::call_index(9)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(7, 5))]
+		pub fn sweep_abandoned(
+			origin: OriginFor<T>,
+			leaf_index: u32,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+
+			let commitment = MerkleLeaves::<T>::get(leaf_index).ok_or(Error::<T>::LeafNotFound)?;
+
+			ensure!(
+				!SpentCommitments::<T>::get(&commitment),
+				Error::<T>::NoteAlreadySpent
+			);
+			ensure!(
+				!DeniedCommitments::<T>::get(&commitment),
+				Error::<T>::CommitmentDenied
+			);
+
+			let record = AbandonmentRecords::<T>::get(&commitment)
+				.ok_or(Error::<T>::NoteNotAbandonable)?;
+			let period = Self::registered_asset_by_local(record.asset_id)
+				.and_then(|asset| asset.abandonment_period)
+				.ok_or(Error::<T>::NoteNotAbandonable)?;
+			// Past an asset's claim window, notes are refunded by its wind-down
+			Self::ensure_claim_window_open(record.asset_id)?;
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				now >= record.deposited_at.saturating_add(period.into()),
+				Error::<T>::NoteNotYetAbandoned
+			);
+
+			// Deny the leaf first so any late withdrawal proof for it fails
+			DeniedCommitments::<T>::insert(&commitment, true);
+			AbandonmentRecords::<T>::remove(&commitment);
+			Self::note_left_anonymity_set(record.asset_id);
+
+			let claimant = Commitments::<T>::get(&commitment)
+				.map(|data| data.depositor)
+				.unwrap_or_else(T::TreasuryAccount::get);
+
+			AbandonedClaims::<T>::mutate(&claimant, record.asset_id, |claim| {
+				*claim = claim.saturating_add(record.amount)
+			});
+			Self::note_unshielded(record.asset_id, record.amount);
+
+			Self::deposit_event(Event::AbandonedNoteSwept {
+				leaf_index,
+				commitment,
+				asset_id: record.asset_id,
+				amount: record.amount,
+				claimant,
+			});
+
+			Ok(())
+		}
+
+		/// Withdraw to a remote chain through the configured exporter
+		///
+		/// Works for any destination the exporter can reach, including non-XCM bridges.
+		/// Amounts below the asset's `remote_min_balance` are rejected before the note is
+		/// spent, and a failed export reverts the whole withdrawal.
+		///
+		/// The proof is checked as for `withdraw`, paying the hash of `destination` and
+		/// `beneficiary` (`primitives::remote_recipient_hash`) instead of the caller.
+		///
+		/// Parameters:
+		/// - `nullifier`: Nullifier hash
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `asset_id`: Local asset ID of a registered asset
+		/// - `amount`: Amount to withdraw
+		/// - `destination`: Destination chain
+		/// - `beneficiary`: Recipient, encoded as the exporter expects
+		/// - `max_fee`: Highest destination fee accepted; `amount` less the fee is sent
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, `WithdrawalExported` events
+		#[pallet::call_index(11)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(8 + T::RootHistorySize::get() as u64, 4)
+		)]
+		pub fn withdraw_to_remote(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: VersionedDestination,
+			beneficiary: BoundedVec<u8, ConstU32<MAX_BENEFICIARY_LEN>>,
+			max_fee: u128,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let recipient_hash = primitives::remote_recipient_hash(&destination, &beneficiary);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			Self::do_withdraw_to_remote(
+				nullifier,
+				commitment,
+				asset_id,
+				amount,
+				destination,
+				&beneficiary,
+				max_fee,
+			)
+		}
+
+		/// Resolve shield deposits trapped for the calling chain
+		///
+		/// Called by the originating chain (via XCM origin) once the blocking condition
+		/// is resolved: either sends the assets back, or retries shielding them into an
+		/// XCM note for the trapped amount and the calling chain (the asset must be
+		/// registered by then). XCM deposits are held by the pool, so retried notes list
+		/// the pool account as depositor.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the trapped assets
+		/// - `resolution`: Return them or shield them
+		///
+		/// Emits: `TrappedAssetsReclaimed` event (plus `AssetShielded` when shielding)
+		#[pallet::call_index(12)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(6, 7))]
+		pub fn reclaim_trapped(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			resolution: TrapResolution,
+		) -> DispatchResult {
+			let location = T::TrapOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			let amount = TrappedAssets::<T>::take(&location, &asset_id);
+			ensure!(!amount.is_zero(), Error::<T>::NoTrappedAssets);
+
+			match &resolution {
+				TrapResolution::Return { beneficiary } => {
+					T::Exporter::export(
+						&asset_id,
+						amount,
+						&location.clone().into(),
+						&beneficiary.encode(),
+					).map_err(Self::export_error)?;
+				},
+				TrapResolution::Shield { note_key } => {
+					Self::ensure_deposits_enabled()?;
+					let registered = AssetRegistry::<T>::get(&asset_id)
+						.ok_or(Error::<T>::AssetNotRegistered)?;
+					ensure!(registered.is_active, Error::<T>::AssetInactive);
+					let denomination = Self::denomination_pool(&registered.denominations, amount)?;
+					let commitment = crate::xcm_config::xcm_commitment_data::<T::Hasher>(
+						amount,
+						registered.local_id,
+						note_key,
+						&location,
+					);
+					let pool = Self::account_id();
+
+					let leaf_index =
+						Self::insert_commitment(commitment, &pool, registered.local_id)?;
+					Self::insert_denominated(registered.local_id, denomination, commitment)?;
+					Self::note_shielded(registered.local_id, amount)?;
+					Self::note_backing_in(&registered, amount)?;
+					Self::record_for_abandonment(commitment, registered.local_id, amount);
+
+					Self::deposit_event(Event::AssetShielded {
+						commitment,
+						asset_id: registered.local_id,
+						depositor: pool,
+						block_number: <frame_system::Pallet<T>>::block_number(),
+						leaf_index,
+						merkle_root: MerkleRoot::<T>::get(),
+					});
+					T::Metrics::deposit(registered.local_id);
+				},
+			}
+
+			Self::deposit_event(Event::TrappedAssetsReclaimed {
+				origin: location,
+				asset_id,
+				amount,
+				resolution,
+			});
+
+			Ok(())
+		}
+
+		/// Redeem a claim for a failed payout (permissionless)
+		///
+		/// Retries the transfer to the claim's beneficiary; anyone can call this once the
+		/// transfer can succeed (e.g. the beneficiary account now exists).
+		///
+		/// Parameters:
+		/// - `claim_id`: ID of the claim
+		///
+		/// Emits: `ClaimRedeemed` event
+		#[pallet::call_index(10)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn claim(
+			origin: OriginFor<T>,
+			claim_id: ClaimId,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			Self::ensure_withdrawals_enabled()?;
+
+			let claim = Claims::<T>::get(claim_id).ok_or(Error::<T>::ClaimNotFound)?;
+
+			Self::transfer_from_pool(claim.asset_id, &claim.beneficiary, claim.amount)
+				.map_err(|_| Error::<T>::ClaimNotRedeemable)?;
+			Claims::<T>::remove(claim_id);
+
+			Self::deposit_event(Event::ClaimRedeemed {
+				claim_id,
+				asset_id: claim.asset_id,
+				amount: claim.amount,
+				beneficiary: claim.beneficiary,
+			});
+
+			Ok(())
+		}
+
+		/// Set the reserve chain of a registered asset (admin only)
+		///
+		/// Cross-chain withdrawals of assets with a remote reserve are routed through
+		/// that reserve, unless the destination is the reserve itself.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `reserve_location`: Reserve chain, or `None` if this chain is the reserve
+		///
+		/// Emits: `ReserveLocationSet` event
+		#[pallet::call_index(13)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_reserve_location(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			reserve_location: Option<Location>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.reserve_location = reserve_location.clone();
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ReserveLocationSet { asset_id, reserve_location });
+
+			Ok(())
+		}
+
+		/// Withdraw to a remote chain in the next batched export
+		///
+		/// Like `withdraw_to_remote`, but the note is spent now and the payout is queued;
+		/// `on_idle` exports queued withdrawals with one message per destination and
+		/// asset. A failed export leaves the withdrawal `Failed` for `retry_withdrawal`.
+		/// The proof is checked as for `withdraw_to_remote`.
+		///
+		/// Parameters:
+		/// - `nullifier`: Nullifier hash
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `asset_id`: Local asset ID of a registered asset
+		/// - `amount`: Amount to withdraw
+		/// - `destination`: Destination chain
+		/// - `beneficiary`: Recipient, encoded as the exporter expects
+		/// - `max_fee`: Highest destination fee accepted; `amount` less the fee is sent
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, `WithdrawalQueued` events
+		#[pallet::call_index(14)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(8 + T::RootHistorySize::get() as u64, 6)
+		)]
+		pub fn queue_withdrawal_to_remote(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: VersionedDestination,
+			beneficiary: BoundedVec<u8, ConstU32<MAX_BENEFICIARY_LEN>>,
+			max_fee: u128,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let recipient_hash = primitives::remote_recipient_hash(&destination, &beneficiary);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			let (_, payout, fee) =
+				Self::spend_for_remote(nullifier, commitment, asset_id, amount, &destination, max_fee)
+					.inspect_err(Self::warn_failure("queued withdrawal", nullifier))?;
+
+			let withdrawal_id = NextWithdrawalId::<T>::get();
+			NextWithdrawalId::<T>::put(
+				withdrawal_id.checked_add(1).ok_or(Error::<T>::AmountOverflow)?
+			);
+
+			QueuedWithdrawals::<T>::insert(withdrawal_id, QueuedWithdrawal {
+				nullifier,
+				asset_id,
+				amount: payout,
+				destination: destination.clone(),
+				beneficiary,
+				status: WithdrawalStatus::Queued,
+			});
+			T::Metrics::withdrawal_queued();
+
+			Self::deposit_event(Event::WithdrawalQueued {
+				withdrawal_id,
+				nullifier,
+				asset_id,
+				amount: payout,
+				fee,
+				destination,
+			});
+
+			Ok(())
+		}
+
+		/// Queue a failed withdrawal for export again (permissionless)
+		///
+		/// Parameters:
+		/// - `withdrawal_id`: ID of the failed withdrawal
+		#[pallet::call_index(15)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn retry_withdrawal(
+			origin: OriginFor<T>,
+			withdrawal_id: WithdrawalId,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+
+			QueuedWithdrawals::<T>::try_mutate(withdrawal_id, |maybe_withdrawal| -> DispatchResult {
+				let withdrawal = maybe_withdrawal.as_mut().ok_or(Error::<T>::WithdrawalNotFound)?;
+				ensure!(
+					withdrawal.status == WithdrawalStatus::Failed,
+					Error::<T>::WithdrawalNotFailed
+				);
+				withdrawal.status = WithdrawalStatus::Queued;
+				T::Metrics::xcm_retry();
+				Ok(())
+			})
+		}
+
+		/// Replace the set of sibling parachains with an open channel (admin only)
+		///
+		/// Parameters:
+		/// - `channels`: Para IDs of all siblings with an open outbound channel
+		///
+		/// Emits: `OpenChannelsSynced` event
+		#[pallet::call_index(16)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn sync_open_channels(
+			origin: OriginFor<T>,
+			mut channels: BoundedVec<u32, ConstU32<MAX_OPEN_CHANNELS>>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			channels.sort_unstable();
+			channels.dedup();
+			OpenChannels::<T>::put(&channels);
+
+			Self::deposit_event(Event::OpenChannelsSynced { channels });
+
+			Ok(())
+		}
+
+		/// Set or clear the bridge fee for withdrawals to a destination (admin only)
+		///
+		/// The fee is `flat + ppm * amount / 1_000_000`, paid to the treasury out of
+		/// the withdrawn amount.
+		///
+		/// Parameters:
+		/// - `destination`: Withdrawal destination the fee applies to
+		/// - `fee`: Fee schedule, or `None` for no fee
+		///
+		/// Emits: `DestinationFeeSet` event
+		#[pallet::call_index(17)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn set_destination_fee(
+			origin: OriginFor<T>,
+			destination: Location,
+			fee: Option<FeeConfig>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			match fee {
+				Some(config) => {
+					ensure!(
+						config.ppm <= Permill::ACCURACY,
+						Error::<T>::InvalidFeeConfig
+					);
+					DestinationFees::<T>::insert(&destination, config);
+				},
+				None => DestinationFees::<T>::remove(&destination),
+			}
+
+			Self::deposit_event(Event::DestinationFeeSet { destination, fee });
+
+			Ok(())
+		}
+
+		/// Enable or disable deposit receipts for a registered asset (admin only)
+		///
+		/// Cross-chain deposits of the asset are confirmed to their origin chain with a
+		/// message carrying the commitment and its leaf index. Receipts cost an extra
+		/// message per deposit, so they are off unless enabled here.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `receipt_call`: Call (pallet index, call index) receiving receipts on the
+		///   origin chain, or `None` to stop sending receipts
+		///
+		/// Emits: `ReceiptCallSet` event
+		#[pallet::call_index(18)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_receipt_call(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			receipt_call: Option<[u8; 2]>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.receipt_call = receipt_call;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ReceiptCallSet { asset_id, receipt_call });
+
+			Ok(())
+		}
+
+		/// Pause deposits, withdrawals, or the whole bridge (admin only)
+		///
+		/// `DepositsPaused` refuses new deposits but lets withdrawals continue so users can
+		/// exit; `WithdrawalsPaused` halts withdrawals, claims and queued exports but takes
+		/// deposits; `AllPaused` refuses both. The level replaces the current one, except that
+		/// paused withdrawals stay paused: only `unpause` resumes them, so pausing deposits
+		/// never resets a tripped withdrawal circuit breaker.
+		///
+		/// Parameters:
+		/// - `level`: How much of the bridge to pause
+		///
+		/// Emits: `PauseLevelSet` event
+		#[pallet::call_index(61)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn pause(origin: OriginFor<T>, level: PauseLevel) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let level = if Paused::<T>::get().withdrawals_paused() {
+				level.with_withdrawals_paused()
+			} else {
+				level
+			};
+			Paused::<T>::put(level);
+			Self::deposit_event(Event::PauseLevelSet { level });
+
+			Ok(())
+		}
+
+		/// Resume deposits and withdrawals (admin only)
+		///
+		/// Also resets the withdrawal circuit breaker a failed solvency check tripped.
+		///
+		/// Emits: `PauseLevelSet` event
+		#[pallet::call_index(62)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn unpause(origin: OriginFor<T>) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Paused::<T>::put(PauseLevel::None);
+			Self::deposit_event(Event::PauseLevelSet { level: PauseLevel::None });
+
+			Ok(())
+		}
+
+		/// Return the unattributed funds of duplicate deposits to their origin (admin only)
+		///
+		/// Parameters:
+		/// - `origin_location`: Chain the duplicate deposits came from
+		/// - `asset_id`: XCM AssetId of the deposited asset
+		/// - `beneficiary`: Recipient on the origin chain
+		///
+		/// Emits: `UnattributedRefunded` event
+		#[pallet::call_index(20)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn refund_unattributed(
+			origin: OriginFor<T>,
+			origin_location: Location,
+			asset_id: XcmAssetId,
+			beneficiary: Location,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			let amount = UnattributedBalances::<T>::take(&origin_location, &asset_id);
+			ensure!(!amount.is_zero(), Error::<T>::NoUnattributedBalance);
+
+			let message_id = T::Exporter::export(
+				&asset_id,
+				amount,
+				&origin_location.clone().into(),
+				&beneficiary.encode(),
+			)
+			.map_err(Self::export_error)?;
+
+			Self::deposit_event(Event::UnattributedRefunded {
+				origin: origin_location,
+				asset_id,
+				amount,
+				message_id,
+			});
+
+			Ok(())
+		}
+
+		/// Announce a verifying key to replace the active one of a circuit (governance/sudo only)
+		///
+		/// This is the only way to change a verifying key after genesis: the key becomes
+		/// active once applied with `apply_verifying_key`. The announcement records the
+		/// current circuit version and runtime `spec_version`, and replaces any earlier
+		/// announcement for the same circuit.
+		///
+		/// Parameters:
+		/// - `circuit`: Circuit whose proofs the key checks
+		/// - `vk_bytes`: Serialized verifying key
+		/// - `hasher`: Hasher the key's setup was generated for; must be the runtime's
+		///
+		/// Emits: `VerifyingKeyAnnounced` event
+		#[pallet::call_index(21)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn announce_verifying_key(
+			origin: OriginFor<T>,
+			circuit: CircuitId,
+			vk_bytes: VerifyingKeyBytes,
+			hasher: HasherId,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(hasher == T::Hasher::id(), Error::<T>::HasherMismatch);
+
+			let announced_at = <frame_system::Pallet<T>>::block_number();
+			let spec_version = Self::spec_version();
+
+			PendingVerifyingKeys::<T>::insert(circuit, PendingKeyRotation {
+				vk: vk_bytes,
+				hasher,
+				circuit_version: crate::circuit::CIRCUIT_VERSION,
+				spec_version,
+				announced_at,
+			});
+			Self::deposit_event(Event::VerifyingKeyAnnounced {
+				circuit,
+				circuit_version: crate::circuit::CIRCUIT_VERSION,
+				spec_version,
+				expires_at: announced_at.saturating_add(T::AnnouncementTtl::get()),
+			});
+
+			Ok(())
+		}
+
+		/// Activate the verifying key announced for a circuit
+		///
+		/// Anyone may apply it, but only within `AnnouncementTtl` blocks of the announcement
+		/// and only while the circuit version and runtime `spec_version` are those it was
+		/// announced under: a runtime upgrade in between invalidates the announcement. The
+		/// key must also have been approved under dual control, as
+		/// `AdminAction::ApplyVerifyingKey` (see `propose_admin_action`). The key is prepared
+		/// for verification once, here, and stored prepared in `PreparedVerifyingKeys` as
+		/// well; applying the `Withdraw` key also records its setup in `VerifyingKeySetup`.
+		///
+		/// Parameters:
+		/// - `circuit`: Circuit whose announced key to apply
+		///
+		/// Emits: `AdminActionExecuted` and `VerifyingKeyApplied` events
+		#[pallet::call_index(22)]
+		#[pallet::weight(T::WeightInfo::apply_verifying_key())]
+		pub fn apply_verifying_key(origin: OriginFor<T>, circuit: CircuitId) -> DispatchResult {
+			ensure_signed(origin)?;
+
+			let pending = PendingVerifyingKeys::<T>::get(circuit)
+				.ok_or(Error::<T>::NoPendingAnnouncement)?;
+			let expires_at = pending.announced_at.saturating_add(T::AnnouncementTtl::get());
+			ensure!(
+				<frame_system::Pallet<T>>::block_number() < expires_at,
+				Error::<T>::AnnouncementExpired
+			);
+			ensure!(
+				pending.circuit_version == crate::circuit::CIRCUIT_VERSION &&
+					pending.spec_version == Self::spec_version(),
+				Error::<T>::StaleAnnouncement
+			);
+			let vk_hash = BlakeTwo256::hash(&pending.vk);
+			Self::consume_admin_approval(AdminAction::ApplyVerifyingKey { circuit, vk_hash })?;
+
+			PendingVerifyingKeys::<T>::remove(circuit);
+			Self::put_verifying_key(circuit, pending.vk)?;
+			if circuit == CircuitId::Withdraw {
+				VerifyingKeySetup::<T>::put(SetupMetadata {
+					hasher: pending.hasher,
+					circuit_version: pending.circuit_version,
+				});
+			}
+			Self::deposit_event(Event::VerifyingKeyApplied {
+				circuit,
+				circuit_version: pending.circuit_version,
+			});
+
+			Ok(())
+		}
+
+		/// Shield one deposit into several notes of the same value
+		///
+		/// Takes `amount_each` times the number of commitments from the caller in one
+		/// transfer and inserts every commitment as a leaf, in order. Each note must meet
+		/// the asset's minimum deposit. A duplicate commitment (in the tree or in the list)
+		/// fails the whole deposit.
+		///
+		/// The weight is benchmarked with each leaf hashed into the tree right away, which
+		/// bounds the cost with `BatchTreeInsertion` as well.
+		///
+		/// Parameters:
+		/// - `asset_id`: Asset identifier (0 for native token)
+		/// - `commitments`: Commitments of the notes, computed off-chain
+		/// - `amount_each`: Value of every note
+		///
+		/// Emits: `AssetShielded` event per note, then `DepositSplit`
+		#[pallet::call_index(23)]
+		#[pallet::weight(T::WeightInfo::deposit_split(commitments.len() as u32))]
+		pub fn deposit_split(
+			origin: OriginFor<T>,
+			asset_id: u32,
+			commitments: BoundedVec<H256, T::MaxSplit>,
+			amount_each: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_deposits_enabled()?;
+			ensure!(!commitments.is_empty(), Error::<T>::EmptySplit);
+			Self::ensure_asset_takes_deposit(asset_id, amount_each)?;
+			let denomination =
+				Self::denomination_pool(&Self::local_denominations(asset_id), amount_each)?;
+			let count = commitments.len() as u32;
+			let total = amount_each
+				.checked_mul(count as u128)
+				.ok_or(Error::<T>::AmountOverflow)?;
+
+			Self::transfer_to_pool(asset_id, &who, total)?;
+
+			let mut first_leaf_index = None;
+			for commitment in commitments {
+				let leaf_index = Self::insert_commitment(commitment, &who, asset_id)?;
+				Self::insert_denominated(asset_id, denomination, commitment)?;
+				first_leaf_index.get_or_insert(leaf_index);
+				Self::record_for_abandonment(commitment, asset_id, amount_each);
+
+				Self::deposit_event(Event::AssetShielded {
+					commitment,
+					asset_id,
+					depositor: who.clone(),
+					block_number: <frame_system::Pallet<T>>::block_number(),
+					leaf_index,
+					merkle_root: MerkleRoot::<T>::get(),
+				});
+				T::Metrics::deposit(asset_id);
+			}
+			Self::note_shielded(asset_id, total)?;
+			Self::warn_if_not_configured();
+
+			Self::deposit_event(Event::DepositSplit {
+				depositor: who,
+				asset_id,
+				amount_each,
+				count,
+				first_leaf_index: first_leaf_index.unwrap_or_default(),
+			});
+
+			Ok(())
+		}
+
+		/// Withdraw with a proof anchored to a recent block
+		///
+		/// `withdraw` for runtimes setting `MaxProofAge`: the proof also commits to
+		/// `anchor_block_hash` (`primitives::anchored_recipient_hash`), which must be the hash
+		/// of one of the last `MaxProofAge` blocks. A proof not submitted in time has to be
+		/// generated again. Without `MaxProofAge` the anchor is not checked.
+		///
+		/// Parameters:
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `amount`: Amount to withdraw
+		/// - `asset_id`: Asset identifier
+		/// - `anchor_block_hash`: Hash of the recent block the proof was made against
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded` event, `ClaimCreated` if the payout failed
+		#[pallet::call_index(24)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() + T::DbWeight::get().reads_writes(
+				7 + T::MaxProofAge::get().unwrap_or(0) as u64 + T::RootHistorySize::get() as u64,
+				7,
+			)
+		)]
+		pub fn withdraw_anchored(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			amount: u128,
+			asset_id: u32,
+			anchor_block_hash: H256,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let recipient_hash = primitives::anchored_recipient_hash(&who, &anchor_block_hash);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				Some(anchor_block_hash),
+			)?;
+
+			Self::do_withdraw(who, nullifier, commitment, amount, asset_id)
+		}
+
+		/// Deposit several assets as one note
+		///
+		/// Each asset is taken into custody like a `deposit` of it; the note commits to all
+		/// of them (see `primitives::bundle_commitment`) and is withdrawn as a whole with
+		/// `withdraw_bundle`. If any transfer fails, nothing is deposited. Bundle notes do
+		/// not take part in abandonment sweeping.
+		///
+		/// Parameters:
+		/// - `assets`: `(asset_id, amount)` pairs, by strictly increasing asset ID; each
+		///   amount must meet the asset's minimum deposit
+		/// - `note_key`: Key of the depositor's note secret (`primitives::note_key`)
+		///
+		/// Emits: `BundleShielded` event
+		#[pallet::call_index(25)]
+		#[pallet::weight(
+			Pallet::<T>::deposit_weight() +
+				T::DbWeight::get().reads_writes(4 * assets.len() as u64, 3 * assets.len() as u64 + 1)
+		)]
+		pub fn deposit_bundle(
+			origin: OriginFor<T>,
+			assets: NoteBundle,
+			note_key: [u8; 32],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_deposits_enabled()?;
+			Self::ensure_valid_bundle(&assets)?;
+			Self::ensure_fresh_randomness(&note_key)?;
+			for &(asset_id, amount) in assets.iter() {
+				Self::ensure_asset_takes_deposit(asset_id, amount)?;
+			}
+
+			let randomness = crate::primitives::note_randomness::<T::Hasher>(&note_key, &[0u8; 32]);
+			let commitment = crate::primitives::bundle_commitment::<T::Hasher>(&assets, &randomness);
+			for &(asset_id, amount) in assets.iter() {
+				Self::transfer_to_pool(asset_id, &who, amount)?;
+				Self::note_shielded(asset_id, amount)?;
+			}
+			let leaf_index = Self::insert_commitment(commitment, &who, assets[0].0)?;
+			BundleNotes::<T>::insert(commitment, true);
+			Self::warn_if_not_configured();
+
+			Self::deposit_event(Event::BundleShielded {
+				commitment,
+				asset_ids: Self::bundle_asset_ids(&assets),
+				depositor: who,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				leaf_index,
+			});
+			for &(asset_id, _) in assets.iter() {
+				T::Metrics::deposit(asset_id);
+			}
+
+			Ok(())
+		}
+
+		/// Withdraw a note holding several assets
+		///
+		/// The bundle withdrawal proof exposes every `(asset_id, amount)` pair of the note
+		/// (`circuit::BundleWithdrawalCircuit`), and each is paid to the caller like a
+		/// `withdraw` of it. The proof is verified against the `CircuitId::Bundle` key, with
+		/// the nullifier, the pairs and the caller's `primitives::recipient_hash` as public
+		/// inputs. Payout checks run for all assets before the note is spent.
+		///
+		/// Parameters:
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The bundle commitment being spent
+		/// - `assets`: The note's `(asset_id, amount)` pairs, in the order deposited
+		/// - `proof`: Serialized Groth16 proof (`zksnark::generate_bundle_proof`), at most
+		///   `MaxProofSize` bytes
+		///
+		/// Emits: `BundleUnshielded` event, `ClaimCreated` for each failed payout
+		#[pallet::call_index(26)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(
+					8 + 3 * assets.len() as u64,
+					5 + 3 * assets.len() as u64,
+				)
+		)]
+		pub fn withdraw_bundle(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			assets: NoteBundle,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_proof_anchor(None)?;
+			Self::ensure_withdrawals_enabled()?;
+			Self::verifying_key_for_proofs()?;
+			Self::charge_verification(1)?;
+			Self::ensure_valid_bundle(&assets)?;
+			ensure!(BundleNotes::<T>::get(&commitment), Error::<T>::NoteKindMismatch);
+			for &(asset_id, _) in assets.iter() {
+				Self::ensure_note_age(asset_id, None)?;
+			}
+			let recipient_hash = primitives::recipient_hash(&who);
+			Self::verify_bundle_proof(&proof, &nullifier, &commitment, &assets, &recipient_hash)?;
+
+			if !T::ClaimDustPayouts::get() {
+				for &(asset_id, amount) in assets.iter() {
+					Self::ensure_can_receive_payout(asset_id, &who, amount)?;
+				}
+			}
+
+			Self::spend_note(nullifier, commitment)
+				.inspect_err(Self::warn_failure("bundle withdrawal", nullifier))?;
+			for &(asset_id, amount) in assets.iter() {
+				Self::note_unshielded(asset_id, amount);
+			}
+			log::debug!(target: LOG_TARGET, "bundle withdrawal of {nullifier:?}: note spent");
+
+			Self::deposit_event(Event::BundleUnshielded {
+				nullifier,
+				asset_ids: Self::bundle_asset_ids(&assets),
+				block_number: <frame_system::Pallet<T>>::block_number(),
+			});
+			for &(asset_id, _) in assets.iter() {
+				T::Metrics::withdrawal(asset_id);
+			}
+
+			// The nullifier is consumed: every asset must end up paid or claimable
+			for &(asset_id, amount) in assets.iter() {
+				Self::pay_out(asset_id, &who, amount)?;
+			}
+
+			Ok(())
+		}
+
+		/// Deposit and receive a participation receipt
+		///
+		/// A `deposit` that also increments the caller's `ParticipationReceipts`, e.g. to
+		/// qualify for rewards of pool users. The receipt is a per-account count only: it
+		/// records neither the amount, the asset nor the commitment, and cannot be
+		/// transferred. Opting in publicly marks the account as a pool user, so plain
+		/// `deposit` issues no receipt.
+		///
+		/// Parameters:
+		/// - `amount`: Amount to shield (in smallest unit)
+		/// - `asset_id`: Asset identifier (0 for native token)
+		/// - `note_key`: Key of the depositor's note secret (`primitives::note_key`)
+		///
+		/// Emits: `AssetShielded`, `ParticipationReceiptIssued` events
+		#[pallet::call_index(27)]
+		#[pallet::weight(
+			Pallet::<T>::deposit_weight().saturating_add(T::DbWeight::get().reads_writes(1, 1))
+		)]
+		pub fn deposit_with_receipt(
+			origin: OriginFor<T>,
+			amount: u128,
+			asset_id: u32,
+			note_key: [u8; 32],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_fresh_randomness(&note_key)?;
+
+			let commitment = Self::generate_commitment(amount, asset_id, &note_key);
+			Self::do_shield(who.clone(), commitment, asset_id, amount)?;
+
+			let receipts = ParticipationReceipts::<T>::mutate(&who, |count| {
+				*count = count.saturating_add(1);
+				*count
+			});
+			Self::deposit_event(Event::ParticipationReceiptIssued { account: who, receipts });
+
+			Ok(())
+		}
+
+		/// Set or clear the note age bounds of a registered asset (governance only)
+		///
+		/// Notes of an asset with bounds can only be withdrawn against a recent root
+		/// (`withdraw`, `withdraw_via_relayer`), and only while the root's leaf range
+		/// satisfies them:
+		/// - the root must have been recorded at least `min_note_age` blocks ago, so the
+		///   minimum only holds for notes proven against a root that old. Roots leave
+		///   `RootHistory` after `RootHistorySize` updates, which bounds usable minimums.
+		/// - the tree's first leaf must be at most `max_note_age` blocks old. Every root
+		///   contains it, so the maximum bounds the lifetime of the pool for the asset.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `min_note_age`: Minimum note age in blocks, `None` for no minimum
+		/// - `max_note_age`: Maximum note age in blocks, `None` for no maximum
+		///
+		/// Emits: `NoteAgeBoundsSet` event
+		#[pallet::call_index(29)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_note_age_bounds(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			min_note_age: Option<u32>,
+			max_note_age: Option<u32>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(max_note_age != Some(0), Error::<T>::InvalidNoteAgeBounds);
+			if let (Some(min), Some(max)) = (min_note_age, max_note_age) {
+				ensure!(min <= max, Error::<T>::InvalidNoteAgeBounds);
+			}
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.min_note_age = min_note_age;
+				asset.max_note_age = max_note_age;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::NoteAgeBoundsSet { asset_id, min_note_age, max_note_age });
+
+			Ok(())
+		}
+
+		/// Move treasury funds into the sponsorship pot (governance only)
+		///
+		/// The pot pays the transaction fees of native deposits of at most
+		/// `SponsorThreshold`, `SponsoredFee` each, until it runs dry (see `SponsorDeposit`).
+		///
+		/// Parameters:
+		/// - `amount`: Native amount taken from `TreasuryAccount`
+		///
+		/// Emits: `SponsorshipPotToppedUp` event
+		#[pallet::call_index(30)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn top_up_sponsorship_pot(origin: OriginFor<T>, amount: u128) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&T::TreasuryAccount::get(),
+				&Self::sponsorship_account(),
+				amount,
+				Preservation::Preserve,
+			)?;
+			let pot = SponsorshipPot::<T>::mutate(|pot| {
+				*pot = pot.saturating_add(amount);
+				*pot
+			});
+
+			Self::deposit_event(Event::SponsorshipPotToppedUp { amount, pot });
+
+			Ok(())
+		}
+
+		/// Swap two shielded notes of different assets between their owners
+		///
+		/// Each leg spends one party's note into a note of the same asset and amount for
+		/// the counterparty, of randomness derived from the counterparty's note key
+		/// (`circuit::SwapCircuit` binds the output commitment). Both notes are spent and
+		/// both outputs inserted, or nothing happens. Amounts stay hidden and no value
+		/// leaves the pool. Each leg's proof is verified against the `CircuitId::Swap` key
+		/// before either note is spent, and its envelope must be for the circuits of the
+		/// current verifying key. Output notes do not take part in abandonment sweeping.
+		///
+		/// Parameters:
+		/// - `first`, `second`: The legs of the two parties, of different assets
+		/// - `deadline`: Last block the swap can be executed in
+		///
+		/// Emits: `NotesSwapped` event
+		#[pallet::call_index(31)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight().saturating_mul(2) +
+				Pallet::<T>::deposit_weight().saturating_mul(2) +
+				T::DbWeight::get().reads_writes(18, 10)
+		)]
+		pub fn shielded_swap(
+			origin: OriginFor<T>,
+			first: SwapLeg,
+			second: SwapLeg,
+			deadline: BlockNumberFor<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(now <= deadline, Error::<T>::SwapExpired);
+			ensure!(first.asset_id != second.asset_id, Error::<T>::InvalidSwap);
+			ensure!(first.output_commitment != second.output_commitment, Error::<T>::InvalidSwap);
+			Self::ensure_proof_anchor(None)?;
+			Self::ensure_withdrawals_enabled()?;
+			Self::verifying_key_for_proofs()?;
+			Self::charge_verification(2)?;
+
+			let legs = [first, second];
+			for leg in legs.iter() {
+				Self::ensure_valid_swap_leg(leg)?;
+				Self::verify_swap_proof(leg)?;
+			}
+			for leg in legs.iter() {
+				Self::spend_note(leg.nullifier, leg.commitment)
+					.inspect_err(Self::warn_failure("swap", leg.nullifier))?;
+			}
+			let mut leaf_indices = [0u32; 2];
+			for (leaf_index, leg) in leaf_indices.iter_mut().zip(legs.iter()) {
+				*leaf_index = Self::insert_commitment(leg.output_commitment, &who, leg.asset_id)?;
+			}
+			Self::warn_if_not_configured();
+
+			let [first, second] = legs;
+			Self::deposit_event(Event::NotesSwapped {
+				nullifiers: [first.nullifier, second.nullifier],
+				output_commitments: [first.output_commitment, second.output_commitment],
+				leaf_indices,
+				block_number: now,
+			});
+
+			Ok(())
+		}
+
+		/// Set or clear the account format of a destination's beneficiaries (admin only)
+		///
+		/// Used by `withdraw_to_parachain_encoded` to turn raw accounts into beneficiary
+		/// locations.
+		///
+		/// Parameters:
+		/// - `destination`: Withdrawal destination the format applies to
+		/// - `format`: Account format, or `None` for `AccountId32`
+		///
+		/// Emits: `DestinationAccountFormatSet` event
+		#[pallet::call_index(32)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn set_destination_account_format(
+			origin: OriginFor<T>,
+			destination: Location,
+			format: Option<AccountFormat>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			match format {
+				Some(format) => DestinationAccountFormat::<T>::insert(&destination, format),
+				None => DestinationAccountFormat::<T>::remove(&destination),
+			}
+
+			Self::deposit_event(Event::DestinationAccountFormatSet { destination, format });
+
+			Ok(())
+		}
+
+		/// Withdraw to another parachain, naming the beneficiary by its raw account
+		///
+		/// `withdraw_to_parachain` with the beneficiary built by `beneficiary_from_encoded`:
+		/// a 32-byte account (an SS58 address, decoded) or a 20-byte key, as the
+		/// destination's account format requires. The proof pays the built beneficiary's
+		/// `primitives::recipient_hash`, the one `withdraw_to_parachain` checks.
+		///
+		/// Parameters:
+		/// - `nullifier`: Nullifier hash
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `asset_id`: Local asset ID
+		/// - `amount`: Amount to withdraw
+		/// - `destination`: Destination parachain location
+		/// - `beneficiary`: Raw account of the recipient on the destination chain
+		/// - `max_fee`: Highest destination fee accepted; `amount` less the fee is sent
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, `WithdrawalExported` events
+		#[pallet::call_index(33)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(9 + T::RootHistorySize::get() as u64, 4)
+		)]
+		pub fn withdraw_to_parachain_encoded(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: Location,
+			beneficiary: BoundedVec<u8, ConstU32<MAX_BENEFICIARY_LEN>>,
+			max_fee: u128,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let beneficiary = beneficiary_from_encoded::<T>(&destination, &beneficiary)?;
+			let recipient_hash = primitives::recipient_hash(&beneficiary);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			Self::do_withdraw_to_remote(
+				nullifier,
+				commitment,
+				asset_id,
+				amount,
+				destination.into(),
+				&beneficiary.encode(),
+				max_fee,
+			)
+		}
+
+		/// Allow an account to submit commitment batches (admin only)
+		///
+		/// Parameters:
+		/// - `batcher`: Account of the high-frequency depositor
+		///
+		/// Emits: `BatcherRegistered` event
+		#[pallet::call_index(34)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
+		pub fn register_batcher(origin: OriginFor<T>, batcher: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Batchers::<T>::insert(&batcher, true);
+			Self::deposit_event(Event::BatcherRegistered { batcher });
+
+			Ok(())
+		}
+
+		/// Revoke an account's permission to submit commitment batches (admin only)
+		///
+		/// Parameters:
+		/// - `batcher`: Account registered with `register_batcher`
+		///
+		/// Emits: `BatcherRemoved` event
+		#[pallet::call_index(35)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn remove_batcher(origin: OriginFor<T>, batcher: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(Batchers::<T>::get(&batcher), Error::<T>::NotBatcher);
+
+			Batchers::<T>::remove(&batcher);
+			Self::deposit_event(Event::BatcherRemoved { batcher });
+
+			Ok(())
+		}
+
+		/// Shield a batch of notes prepared off-chain (registered batchers only)
+		///
+		/// For high-frequency depositors such as exchanges: `total_amount` is taken from
+		/// the batcher in one transfer and every commitment is inserted as a leaf, in
+		/// order. `batch_root` is the root of the commitments as a tree of their own
+		/// (`merkle_tree::root_of`), binding the signed transaction to exactly this list.
+		/// The value of each note stays with the batcher's off-chain records: the total
+		/// must meet the asset's minimum deposit per note, and batch notes are not
+		/// recorded for abandonment sweeps. A duplicate commitment fails the whole batch.
+		///
+		/// Parameters:
+		/// - `commitments`: Commitments of the notes, computed off-chain
+		/// - `batch_root`: Root of the commitments
+		/// - `total_amount`: Value of all notes together
+		/// - `asset_id`: Asset identifier (0 for native token)
+		///
+		/// Emits: `AssetShielded` event per note, then `CommitmentBatchSubmitted`
+		#[pallet::call_index(36)]
+		#[pallet::weight(
+			T::WeightInfo::deposit_split(commitments.len() as u32)
+				.saturating_add(T::DbWeight::get().reads_writes(2, 1))
+		)]
+		pub fn submit_commitment_batch(
+			origin: OriginFor<T>,
+			commitments: BoundedVec<H256, T::MaxBatch>,
+			batch_root: H256,
+			total_amount: u128,
+			asset_id: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Batchers::<T>::get(&who), Error::<T>::NotBatcher);
+			Self::ensure_deposits_enabled()?;
+			ensure!(!commitments.is_empty(), Error::<T>::EmptyBatch);
+			ensure!(
+				merkle_tree::root_of::<T::Hasher>(&commitments).is_ok_and(|root| root == batch_root),
+				Error::<T>::BatchRootMismatch
+			);
+			let count = commitments.len() as u32;
+			if let Some(asset) = Self::registered_asset_by_local(asset_id) {
+				// The value of each note is unknown, so it cannot join a denomination pool
+				ensure!(asset.denominations.is_empty(), Error::<T>::InvalidDenomination);
+				let minimum = asset
+					.min_deposit
+					.checked_mul(count as u128)
+					.ok_or(Error::<T>::AmountOverflow)?;
+				ensure!(total_amount >= minimum, Error::<T>::DepositBelowMinimum);
+			}
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let (block, submitted) = BatchLeavesInBlock::<T>::get();
+			let submitted = if block == now { submitted } else { 0 };
+			let submitted = submitted.saturating_add(count);
+			ensure!(submitted <= T::MaxBatchLeavesPerBlock::get(), Error::<T>::BatchLimitExceeded);
+			BatchLeavesInBlock::<T>::put((now, submitted));
+
+			Self::transfer_to_pool(asset_id, &who, total_amount)?;
+
+			let mut first_leaf_index = None;
+			for commitment in commitments {
+				let leaf_index = Self::insert_commitment(commitment, &who, asset_id)?;
+				first_leaf_index.get_or_insert(leaf_index);
+
+				Self::deposit_event(Event::AssetShielded {
+					commitment,
+					asset_id,
+					depositor: who.clone(),
+					block_number: now,
+					leaf_index,
+					merkle_root: MerkleRoot::<T>::get(),
+				});
+				T::Metrics::deposit(asset_id);
+			}
+			Self::note_shielded(asset_id, total_amount)?;
+			Self::warn_if_not_configured();
+
+			log::debug!(
+				target: LOG_TARGET,
+				"batch {batch_root:?}: {count} commitments of asset {asset_id} from leaf {}",
+				first_leaf_index.unwrap_or_default()
+			);
+			Self::deposit_event(Event::CommitmentBatchSubmitted {
+				batcher: who,
+				asset_id,
+				batch_root,
+				total_amount,
+				count,
+				first_leaf_index: first_leaf_index.unwrap_or_default(),
+			});
+
+			Ok(())
+		}
+
+		/// Store or replace the caller's encrypted note backup (opt-in)
+		///
+		/// The blob is opaque to the chain. Wallets pad it to exactly `MaxBackupLen` bytes
+		/// before encrypting, so every backup on chain has the same size; other lengths are
+		/// refused. A deposit of `BackupDepositPerByte` per byte is taken from the caller;
+		/// replacing a backup settles the difference to the deposit already held.
+		///
+		/// Parameters:
+		/// - `backup`: Encrypted note metadata, `MaxBackupLen` bytes
+		///
+		/// Emits: `NoteBackupSet` event
+		#[pallet::call_index(37)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 4))]
+		pub fn set_note_backup(
+			origin: OriginFor<T>,
+			backup: BoundedVec<u8, T::MaxBackupLen>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(backup.len() as u32 == T::MaxBackupLen::get(), Error::<T>::BackupSizeMismatch);
+
+			let deposit = T::BackupDepositPerByte::get().saturating_mul(backup.len() as u128);
+			let held = NoteBackupDeposits::<T>::get(&who);
+			if deposit > held {
+				<T::Currency as fungible::Mutate<_>>::transfer(
+					&who,
+					&Self::backup_deposit_account(),
+					deposit - held,
+					Preservation::Preserve,
+				)?;
+			} else if held > deposit {
+				Self::refund_backup_deposit(&who, held - deposit)?;
+			}
+
+			NoteBackups::<T>::insert(&who, backup);
+			NoteBackupDeposits::<T>::insert(&who, deposit);
+			Self::deposit_event(Event::NoteBackupSet { account: who, deposit });
+
+			Ok(())
+		}
+
+		/// Remove the caller's note backup and return its deposit
+		///
+		/// Emits: `NoteBackupCleared` event
+		#[pallet::call_index(38)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 4))]
+		pub fn clear_note_backup(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(NoteBackups::<T>::contains_key(&who), Error::<T>::NoNoteBackup);
+
+			let refunded = NoteBackupDeposits::<T>::take(&who);
+			Self::refund_backup_deposit(&who, refunded)?;
+			NoteBackups::<T>::remove(&who);
+			Self::deposit_event(Event::NoteBackupCleared { account: who, refunded });
+
+			Ok(())
+		}
+
+		/// Replace the caller's alert tags (opt-in)
+		///
+		/// Each tag is `primitives::alert_tag(nullifier, salt)` for a note the caller wants a
+		/// watch-only service to look after. The service is given the tags and the salt,
+		/// never the nullifiers, and finds spends of them with the `match_alert_tags`
+		/// runtime API. Spending computes nothing extra. An empty list clears the tags.
+		///
+		/// Parameters:
+		/// - `tags`: Alert tags, replacing any registered before
+		///
+		/// Emits: `AlertTagsRegistered` event
+		#[pallet::call_index(39)]
+		#[pallet::weight({
+			let tags = 2 * T::MaxAlertTags::get() as u64;
+			Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1 + tags, 1 + tags)
+		})]
+		pub fn register_alert_tags(
+			origin: OriginFor<T>,
+			tags: BoundedVec<H256, T::MaxAlertTags>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let mut sorted = tags.to_vec();
+			sorted.sort();
+			sorted.dedup();
+			ensure!(sorted.len() == tags.len(), Error::<T>::DuplicateAlertTag);
+
+			for tag in AlertTags::<T>::take(&who) {
+				AlertTagRegistrations::<T>::mutate_exists(tag, |count| {
+					*count = count.map(|n| n.saturating_sub(1)).filter(|n| *n > 0);
+				});
+			}
+			for tag in tags.iter() {
+				AlertTagRegistrations::<T>::mutate(tag, |count| *count = count.saturating_add(1));
+			}
+
+			let count = tags.len() as u32;
+			if !tags.is_empty() {
+				AlertTags::<T>::insert(&who, tags);
+			}
+			Self::deposit_event(Event::AlertTagsRegistered { account: who, count });
+
+			Ok(())
+		}
+
+		/// Put the pallet in maintenance mode to rebuild the tree cache (governance only)
+		///
+		/// For when `FilledSubtrees` or `MerkleRoot` no longer match the stored leaves, e.g.
+		/// after a faulty migration: every new leaf would build on the wrong root and its
+		/// note could never be proven. Until `rebuild_tree` has rehashed all leaves,
+		/// deposits fail with `TreeUnderMaintenance`; spends against earlier roots go on.
+		/// Calling it again restarts the rebuild from the first leaf. An empty tree has no
+		/// cache to rebuild.
+		///
+		/// Parameters:
+		/// - `expected_root`: Root the stored leaves must hash to, computed off-chain
+		///
+		/// Emits: `TreeRebuildStarted` event
+		#[pallet::call_index(40)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn start_tree_rebuild(origin: OriginFor<T>, expected_root: H256) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			let leaves = CommitmentCount::<T>::get();
+			ensure!(leaves > 0, Error::<T>::LeafNotFound);
+
+			TreeRebuild::<T>::put(TreeRebuildProgress {
+				expected_root,
+				next_leaf: 0,
+				root: H256::zero(),
+			});
+			log::warn!(target: LOG_TARGET, "tree cache rebuild of {leaves} leaves started");
+			Self::deposit_event(Event::TreeRebuildStarted { expected_root, leaves });
+
+			Ok(())
+		}
+
+		/// Rehash the next `count` stored leaves into the tree cache (governance only)
+		///
+		/// Chunks go in leaf order, one or more per block, starting where the previous one
+		/// stopped. The chunk holding the last leaf checks the root against the expected
+		/// one, records it and ends maintenance mode; on a mismatch it fails and the
+		/// rebuild has to be restarted with the right root.
+		///
+		/// Parameters:
+		/// - `start_leaf`: Index of the first leaf of the chunk, the next leaf to rehash
+		/// - `count`: Number of leaves to rehash, at most `MAX_REBUILD_CHUNK`
+		///
+		/// Emits: `TreeRebuildProgressed` event, `TreeRebuilt` after the last leaf
+		#[pallet::call_index(41)]
+		#[pallet::weight(Pallet::<T>::tree_rebuild_weight(*count))]
+		pub fn rebuild_tree(origin: OriginFor<T>, start_leaf: u32, count: u32) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let mut progress = TreeRebuild::<T>::get().ok_or(Error::<T>::NoTreeRebuild)?;
+			let leaves = CommitmentCount::<T>::get();
+			let end = start_leaf.saturating_add(count);
+			ensure!(
+				start_leaf == progress.next_leaf &&
+					(1..=MAX_REBUILD_CHUNK).contains(&count) &&
+					end <= leaves,
+				Error::<T>::InvalidRebuildChunk
+			);
+
+			// The frontier is rewritten level by level as leaves are appended, so a corrupted
+			// one is fully replaced from the first leaf on
+			let mut filled_subtrees: [H256; TREE_DEPTH] =
+				core::array::from_fn(|level| FilledSubtrees::<T>::get(level as u8));
+			let mut nodes = BTreeMap::new();
+			for index in start_leaf..end {
+				let leaf = MerkleLeaves::<T>::get(index).unwrap_or_default();
+				progress.root = Self::append_cached(&mut filled_subtrees, index, leaf, &mut nodes);
+			}
+			progress.next_leaf = end;
+			Self::store_nodes(nodes);
+
+			if end < leaves {
+				for (level, node) in filled_subtrees.iter().enumerate() {
+					FilledSubtrees::<T>::insert(level as u8, node);
+				}
+				TreeRebuild::<T>::put(progress);
+				Self::deposit_event(Event::TreeRebuildProgressed {
+					next_leaf: end,
+					remaining: leaves - end,
+				});
+				return Ok(());
+			}
+
+			ensure!(progress.root == progress.expected_root, Error::<T>::RebuiltRootMismatch);
+			for (level, node) in filled_subtrees.iter().enumerate() {
+				FilledSubtrees::<T>::insert(level as u8, node);
+			}
+			MerkleRoot::<T>::put(progress.root);
+			TreeSize::<T>::put(leaves);
+			Self::record_root(progress.root);
+			TreeRebuild::<T>::kill();
+
+			Self::deposit_event(Event::TreeRebuildProgressed { next_leaf: end, remaining: 0 });
+			Self::deposit_event(Event::TreeRebuilt { root: progress.root, leaves });
+
+			Ok(())
+		}
+
+		/// Post a proving job to the board, holding its bounty
+		///
+		/// For users who cannot prove a withdrawal themselves: the request, encrypted to
+		/// the provers the user trusts, carries what they need (see `delegated_proving`).
+		/// The bounty is taken from the caller and paid to the prover that fulfils the job,
+		/// whose withdrawal then pays out to the caller. A job nobody claims within
+		/// `ProvingJobTtl` blocks can be expired for a refund (see `expire_proving_job`).
+		///
+		/// Parameters:
+		/// - `request`: Encrypted proving request, opaque to the chain
+		/// - `bounty`: Native amount paid to the prover, at least `MinProvingBounty`
+		///
+		/// Emits: `ProvingJobPosted` event
+		#[pallet::call_index(42)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(4, 6))]
+		pub fn post_proving_job(
+			origin: OriginFor<T>,
+			request: BoundedVec<u8, T::MaxProvingJobLen>,
+			bounty: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(bounty >= T::MinProvingBounty::get(), Error::<T>::BountyTooLow);
+			let count = ProvingJobCount::<T>::get();
+			ensure!(count < T::MaxProvingJobs::get(), Error::<T>::TooManyProvingJobs);
+
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&who,
+				&Self::proving_jobs_account(),
+				bounty,
+				Preservation::Preserve,
+			)?;
+
+			let job_id = NextProvingJobId::<T>::get();
+			NextProvingJobId::<T>::put(job_id.checked_add(1).ok_or(Error::<T>::AmountOverflow)?);
+			let expires_at = frame_system::Pallet::<T>::block_number()
+				.saturating_add(T::ProvingJobTtl::get().into());
+			ProvingJobs::<T>::insert(job_id, ProvingJob {
+				owner: who.clone(),
+				bounty,
+				expires_at,
+				status: ProvingJobStatus::Open,
+			});
+			ProvingJobRequests::<T>::insert(job_id, request);
+			ProvingJobCount::<T>::put(count + 1);
+
+			Self::deposit_event(Event::ProvingJobPosted { job_id, owner: who, bounty, expires_at });
+
+			Ok(())
+		}
+
+		/// Claim an open proving job, bonding `ProverBond`
+		///
+		/// The caller has `ProvingClaimTtl` blocks to fulfil the job; a claim left to lapse
+		/// loses the bond to the treasury, so a prover cannot sit on jobs it will not do.
+		///
+		/// Parameters:
+		/// - `job_id`: ID of the job
+		///
+		/// Emits: `ProvingJobClaimed` event
+		#[pallet::call_index(43)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn claim_proving_job(origin: OriginFor<T>, job_id: ProvingJobId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let mut job = ProvingJobs::<T>::get(job_id).ok_or(Error::<T>::ProvingJobNotFound)?;
+			ensure!(job.status == ProvingJobStatus::Open, Error::<T>::ProvingJobAlreadyClaimed);
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(now < job.expires_at, Error::<T>::ProvingJobLapsed);
+
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&who,
+				&Self::proving_jobs_account(),
+				T::ProverBond::get(),
+				Preservation::Preserve,
+			)?;
+
+			let deadline = now.saturating_add(T::ProvingClaimTtl::get().into());
+			job.status = ProvingJobStatus::Claimed { prover: who.clone(), deadline };
+			ProvingJobs::<T>::insert(job_id, job);
+
+			Self::deposit_event(Event::ProvingJobClaimed { job_id, prover: who, deadline });
+
+			Ok(())
+		}
+
+		/// Fulfil a claimed proving job with the withdrawal it asked for
+		///
+		/// Runs the checks of `withdraw`, but pays the note's value to the job's owner: the
+		/// proof must pay the owner's `primitives::recipient_hash`, so a prover cannot keep
+		/// the withdrawal for itself. Only once it verifies is the prover that claimed the
+		/// job paid the bounty and given its bond back.
+		///
+		/// Parameters:
+		/// - `job_id`: ID of the job, claimed by the caller
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `amount`: Amount to withdraw
+		/// - `asset_id`: Asset identifier
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, `ProvingJobFulfilled` events
+		#[pallet::call_index(44)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(11 + T::RootHistorySize::get() as u64, 12)
+		)]
+		pub fn fulfill_proving_job(
+			origin: OriginFor<T>,
+			job_id: ProvingJobId,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			amount: u128,
+			asset_id: u32,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let job = ProvingJobs::<T>::get(job_id).ok_or(Error::<T>::ProvingJobNotFound)?;
+			let ProvingJobStatus::Claimed { prover, deadline } = job.status else {
+				return Err(Error::<T>::NotJobProver.into());
+			};
+			ensure!(prover == who, Error::<T>::NotJobProver);
+			ensure!(
+				frame_system::Pallet::<T>::block_number() < deadline,
+				Error::<T>::ProvingJobLapsed
+			);
+			let recipient_hash = primitives::recipient_hash(&job.owner);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			Self::do_withdraw(job.owner, nullifier, commitment, amount, asset_id)?;
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&Self::proving_jobs_account(),
+				&prover,
+				job.bounty.saturating_add(T::ProverBond::get()),
+				Preservation::Expendable,
+			)?;
+			Self::remove_proving_job(job_id);
+
+			Self::deposit_event(Event::ProvingJobFulfilled { job_id, prover, bounty: job.bounty });
+
+			Ok(())
+		}
+
+		/// Take a lapsed proving job off the board, refunding its bounty
+		///
+		/// Anyone may call it once an open job is past its `ProvingJobTtl`, or a claimed
+		/// job past its deadline; the claiming prover's bond then goes to the treasury.
+		///
+		/// Parameters:
+		/// - `job_id`: ID of the job
+		///
+		/// Emits: `ProvingJobExpired` event
+		#[pallet::call_index(45)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(4, 6))]
+		pub fn expire_proving_job(origin: OriginFor<T>, job_id: ProvingJobId) -> DispatchResult {
+			ensure_signed(origin)?;
+			let job = ProvingJobs::<T>::get(job_id).ok_or(Error::<T>::ProvingJobNotFound)?;
+			let now = frame_system::Pallet::<T>::block_number();
+			let (lapses_at, slashed) = match job.status {
+				ProvingJobStatus::Open => (job.expires_at, 0),
+				ProvingJobStatus::Claimed { deadline, .. } => (deadline, T::ProverBond::get()),
+			};
+			ensure!(now >= lapses_at, Error::<T>::ProvingJobNotLapsed);
+
+			let account = Self::proving_jobs_account();
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&account,
+				&job.owner,
+				job.bounty,
+				Preservation::Expendable,
+			)?;
+			if !slashed.is_zero() {
+				<T::Currency as fungible::Mutate<_>>::transfer(
+					&account,
+					&T::TreasuryAccount::get(),
+					slashed,
+					Preservation::Expendable,
+				)?;
+			}
+			Self::remove_proving_job(job_id);
+
+			Self::deposit_event(Event::ProvingJobExpired { job_id, refunded: job.bounty, slashed });
+
+			Ok(())
+		}
+
+		/// Start delisting a registered asset, e.g. after its issuer defaulted
+		///
+		/// Deposits of the asset stop at once. Its notes can still be withdrawn with a
+		/// proof during a claim window of `WindDownPeriod` blocks; then, after
+		/// `open_winddown_refunds`, the notes left are refunded publicly (see
+		/// `refund_winddown_note`) until `finalize_winddown` sweeps the rest to the
+		/// treasury.
+		///
+		/// Needs the co-signer's approval of `AdminAction::WindDownAsset` (see
+		/// `propose_admin_action`).
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		///
+		/// Emits: `AdminActionExecuted` and `WindDownStarted` events
+		#[pallet::call_index(46)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn begin_asset_winddown(origin: OriginFor<T>, asset_id: XcmAssetId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			let local_id = AssetRegistry::<T>::try_mutate(
+				&asset_id,
+				|maybe_asset| -> Result<u32, DispatchError> {
+					let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+					ensure!(
+						!WindDowns::<T>::contains_key(asset.local_id),
+						Error::<T>::AssetWindingDown
+					);
+					asset.is_active = false;
+					Ok(asset.local_id)
+				},
+			)?;
+			Self::consume_admin_approval(AdminAction::WindDownAsset { asset_id })?;
+
+			let claim_window_ends = frame_system::Pallet::<T>::block_number()
+				.saturating_add(T::WindDownPeriod::get().into());
+			let phase = WindDownPhase::ClaimWindow { ends_at: claim_window_ends };
+			WindDowns::<T>::insert(local_id, phase);
+
+			Self::deposit_event(Event::WindDownStarted { asset_id: local_id, claim_window_ends });
+
+			Ok(())
+		}
+
+		/// Close the claim window of an asset winding down, and open its refunds
+		/// (permissionless)
+		///
+		/// Snapshots what the pool owes to note holders and what it holds for the asset:
+		/// after a default the pool may hold less, and every refund is cut by the same
+		/// ratio. Withdrawals with a proof are refused from here on.
+		///
+		/// Parameters:
+		/// - `asset_id`: Local asset ID
+		///
+		/// Emits: `WindDownRefundsOpened` event
+		#[pallet::call_index(47)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(5, 1))]
+		pub fn open_winddown_refunds(origin: OriginFor<T>, asset_id: u32) -> DispatchResult {
+			ensure_signed(origin)?;
+			let Some(WindDownPhase::ClaimWindow { ends_at }) = WindDowns::<T>::get(asset_id) else {
+				return Err(Error::<T>::WrongWindDownPhase.into());
+			};
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(now >= ends_at, Error::<T>::WindDownPhaseRunning);
+
+			let owed = TotalShielded::<T>::get(asset_id)
+				.saturating_sub(ProtocolOwnedShielded::<T>::get(asset_id));
+			// Burned custody without a reserve holds nothing and mints its payouts
+			let held = Self::solvency_of(asset_id).map_or(owed, |(_, actual)| actual.min(owed));
+			let refunds_end = now.saturating_add(T::WindDownPeriod::get().into());
+			WindDowns::<T>::insert(asset_id, WindDownPhase::Refunding {
+				owed,
+				held,
+				refunded: 0,
+				ends_at: refunds_end,
+			});
+
+			Self::deposit_event(Event::WindDownRefundsOpened { asset_id, owed, held, refunds_end });
+
+			Ok(())
+		}
+
+		/// Refund an unspent note of an asset winding down to a public claim
+		/// (permissionless)
+		///
+		/// Like `sweep_abandoned`, this needs the note's sweep record, kept for assets with
+		/// an abandonment period: the claim goes to the recorded depositor, or to the
+		/// treasury when the commitment metadata has been pruned. It is for the note's
+		/// amount scaled by `held / owed`. Notes without a record are left to the final
+		/// sweep.
+		///
+		/// Parameters:
+		/// - `leaf_index`: Index of the note's leaf
+		///
+		/// Emits: `WindDownNoteRefunded` event
+		#[pallet::call_index(48)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(7, 6))]
+		pub fn refund_winddown_note(origin: OriginFor<T>, leaf_index: u32) -> DispatchResult {
+			ensure_signed(origin)?;
+
+			let commitment = MerkleLeaves::<T>::get(leaf_index).ok_or(Error::<T>::LeafNotFound)?;
+			ensure!(!SpentCommitments::<T>::get(&commitment), Error::<T>::NoteAlreadySpent);
+			ensure!(!DeniedCommitments::<T>::get(&commitment), Error::<T>::CommitmentDenied);
+			let record = AbandonmentRecords::<T>::get(&commitment)
+				.ok_or(Error::<T>::NoteNotRefundable)?;
+
+			let amount = WindDowns::<T>::try_mutate(
+				record.asset_id,
+				|phase| -> Result<u128, DispatchError> {
+					let Some(WindDownPhase::Refunding { owed, held, refunded, .. }) = phase else {
+						return Err(Error::<T>::WrongWindDownPhase.into());
+					};
+					let amount = multiply_by_rational_with_rounding(
+						record.amount,
+						*held,
+						*owed,
+						Rounding::Down,
+					)
+					.unwrap_or_default();
+					*refunded = refunded.saturating_add(amount);
+					Ok(amount)
+				},
+			)?;
+
+			DeniedCommitments::<T>::insert(&commitment, true);
+			AbandonmentRecords::<T>::remove(&commitment);
+			Self::note_left_anonymity_set(record.asset_id);
+
+			let claimant = Commitments::<T>::get(&commitment)
+				.map(|data| data.depositor)
+				.unwrap_or_else(T::TreasuryAccount::get);
+			AbandonedClaims::<T>::mutate(&claimant, record.asset_id, |claim| {
+				*claim = claim.saturating_add(amount)
+			});
+			Self::note_unshielded(record.asset_id, record.amount);
+
+			Self::deposit_event(Event::WindDownNoteRefunded {
+				leaf_index,
+				commitment,
+				asset_id: record.asset_id,
+				amount,
+				claimant,
+			});
+
+			Ok(())
+		}
+
+		/// End the wind-down of an asset once its refund window is over (permissionless)
+		///
+		/// What the pool held and did not refund, including the value of decoy notes, is
+		/// credited to the treasury's public claims. The asset's notes left are worthless.
+		///
+		/// Parameters:
+		/// - `asset_id`: Local asset ID
+		///
+		/// Emits: `WindDownFinalized` event
+		#[pallet::call_index(49)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(2, 5))]
+		pub fn finalize_winddown(origin: OriginFor<T>, asset_id: u32) -> DispatchResult {
+			ensure_signed(origin)?;
+			let Some(WindDownPhase::Refunding { held, refunded, ends_at, .. }) =
+				WindDowns::<T>::get(asset_id)
+			else {
+				return Err(Error::<T>::WrongWindDownPhase.into());
+			};
+			ensure!(
+				frame_system::Pallet::<T>::block_number() >= ends_at,
+				Error::<T>::WindDownPhaseRunning
+			);
+
+			let swept = held
+				.saturating_sub(refunded)
+				.saturating_add(ProtocolOwnedShielded::<T>::take(asset_id));
+			AbandonedClaims::<T>::mutate(&T::TreasuryAccount::get(), asset_id, |claim| {
+				*claim = claim.saturating_add(swept)
+			});
+			TotalShielded::<T>::remove(asset_id);
+			AnonymityTallies::<T>::remove(asset_id);
+			WindDowns::<T>::insert(asset_id, WindDownPhase::Finalized);
+
+			Self::deposit_event(Event::WindDownFinalized { asset_id, swept });
+
+			Ok(())
+		}
+
+		/// Withdraw a note to several recipients, e.g. a DAO paying out of its treasury
+		///
+		/// Like `withdraw`, with the value split between `payouts`, which must add up to
+		/// `amount`. The proof pays `primitives::split_recipient_hash` of `payouts`, so no
+		/// recipient or share can be changed. The proof is verified first, then the payouts
+		/// are checked before the note is spent; the note is then spent, and each payout
+		/// that fails is recorded as a claim instead of reverting the others.
+		///
+		/// Parameters:
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Recent root of the tree the proof was made against
+		/// - `amount`: Amount of the note
+		/// - `asset_id`: Asset identifier
+		/// - `payouts`: Recipients and the amount each is paid
+		/// - `proof`: Serialized Groth16 proof, at most `MaxProofSize` bytes
+		///
+		/// Emits: `AssetUnshielded`, a `SplitPayout` per recipient and `WithdrawalSplit`
+		/// events, `ClaimCreated` for every failed payout
+		#[pallet::call_index(50)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				T::DbWeight::get().reads_writes(7 + T::RootHistorySize::get() as u64, 7) +
+				T::DbWeight::get().reads_writes(3, 4).saturating_mul(payouts.len() as u64)
+		)]
+		pub fn withdraw_split(
+			origin: OriginFor<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			amount: u128,
+			asset_id: u32,
+			payouts: BoundedVec<(T::AccountId, u128), T::MaxPayouts>,
+			proof: ProofOf<T>,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let recipient_hash = primitives::split_recipient_hash(&payouts);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			let receipt_id = Self::spend_locally(nullifier, commitment, amount, asset_id, &payouts)
+				.inspect_err(Self::warn_failure("withdrawal", nullifier))?;
+
+			// The nullifier is consumed: every payout must end up paid or claimable
+			for (recipient, value) in payouts.iter() {
+				let claim_id = Self::pay_out(asset_id, recipient, *value)?;
+				Self::deposit_event(Event::SplitPayout {
+					receipt_id,
+					recipient: recipient.clone(),
+					amount: *value,
+					claim_id,
+				});
+			}
+
+			Self::deposit_event(Event::WithdrawalSplit {
+				receipt_id,
+				asset_id,
+				amount,
+				recipients: payouts.len() as u32,
+			});
+
+			Ok(())
+		}
+
+		/// Propose an admin action for dual control
+		///
+		/// The most dangerous admin operations (`apply_verifying_key`,
+		/// `begin_asset_winddown`) only execute once `AdminOrigin` proposed them and
+		/// `CoSignerOrigin` approved them, both within `AdminApprovalTtl` blocks of the
+		/// proposal. Executing the operation consumes the approval.
+		///
+		/// Parameters:
+		/// - `action_hash`: `AdminAction::hash` of the operation
+		///
+		/// Emits: `AdminActionProposed` event
+		#[pallet::call_index(51)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn propose_admin_action(origin: OriginFor<T>, action_hash: H256) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let now = frame_system::Pallet::<T>::block_number();
+			if let Some(approval) = AdminApprovals::<T>::get(action_hash) {
+				ensure!(now >= approval.expires_at, Error::<T>::AdminActionAlreadyProposed);
+			}
+
+			let expires_at = now.saturating_add(T::AdminApprovalTtl::get());
+			AdminApprovals::<T>::insert(action_hash, AdminApproval { expires_at, approved: false });
+			Self::deposit_event(Event::AdminActionProposed { action_hash, expires_at });
+
+			Ok(())
+		}
+
+		/// Approve a proposed admin action, as the co-signer
+		///
+		/// Parameters:
+		/// - `action_hash`: Hash the action was proposed under
+		///
+		/// Emits: `AdminActionApproved` event
+		#[pallet::call_index(52)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn approve_admin_action(origin: OriginFor<T>, action_hash: H256) -> DispatchResult {
+			T::CoSignerOrigin::ensure_origin(origin)?;
+
+			AdminApprovals::<T>::try_mutate(action_hash, |maybe_approval| -> DispatchResult {
+				let approval = maybe_approval.as_mut().ok_or(Error::<T>::AdminActionNotProposed)?;
+				ensure!(
+					frame_system::Pallet::<T>::block_number() < approval.expires_at,
+					Error::<T>::AdminApprovalExpired
+				);
+				approval.approved = true;
+				Ok(())
+			})?;
+			Self::deposit_event(Event::AdminActionApproved { action_hash });
+
+			Ok(())
+		}
+
+		/// Split a shielded note into two new notes, without it leaving the pool
+		///
+		/// The proof (`circuit::TransferCircuit`) shows the spent note is a leaf of the tree
+		/// at `merkle_root`, which must still be in the root history, and that both outputs
+		/// are notes of its asset whose amounts add up to its own. The spent commitment and
+		/// all three amounts stay hidden; only the nullifier is marked. Either output may be
+		/// for another owner, who learns its amount off-chain and whose note key its
+		/// randomness derives from.
+		///
+		/// Parameters:
+		/// - `proof`: Proof against the `CircuitId::Transfer` verifying key
+		/// - `input_nullifier`: Nullifier of the spent note
+		/// - `output_commitment_1`, `output_commitment_2`: Commitments of the new notes
+		/// - `merkle_root`: Root of the tree the proof places the spent note in
+		/// - `asset_id`: Asset of the spent note and of both outputs
+		///
+		/// Emits: `PrivateTransfer` event
+		#[pallet::call_index(53)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() +
+				Pallet::<T>::deposit_weight().saturating_mul(2) +
+				T::DbWeight::get().reads_writes(10, 4)
+		)]
+		pub fn private_transfer(
+			origin: OriginFor<T>,
+			proof: ProofOf<T>,
+			input_nullifier: H256,
+			output_commitment_1: H256,
+			output_commitment_2: H256,
+			merkle_root: H256,
+			asset_id: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let outputs = [output_commitment_1, output_commitment_2];
+			ensure!(
+				output_commitment_1 != output_commitment_2,
+				Error::<T>::CommitmentAlreadyExists
+			);
+			for output in outputs.iter() {
+				ensure!(
+					!Commitments::<T>::contains_key(output),
+					Error::<T>::CommitmentAlreadyExists
+				);
+			}
+			Self::ensure_proof_anchor(None)?;
+			Self::ensure_withdrawals_enabled()?;
+			Self::ensure_note_age(asset_id, Some(merkle_root))?;
+			Self::ensure_claim_window_open(asset_id)?;
+			Self::charge_verification(1)?;
+			Self::verify_transfer_proof(
+				&proof,
+				&input_nullifier,
+				&merkle_root,
+				asset_id,
+				&outputs,
+			)?;
+
+			Self::spend_nullifier(input_nullifier)
+				.inspect_err(Self::warn_failure("transfer", input_nullifier))?;
+			Self::note_left_anonymity_set(asset_id);
+			let mut leaf_indices = [0u32; 2];
+			for (leaf_index, output) in leaf_indices.iter_mut().zip(outputs) {
+				*leaf_index = Self::insert_commitment(output, &who, asset_id)?;
+			}
+			Self::warn_if_not_configured();
+
+			Self::deposit_event(Event::PrivateTransfer {
+				nullifier: input_nullifier,
+				output_commitments: outputs,
+				leaf_indices,
+			});
+
+			Ok(())
+		}
+
+		/// Withdraw two notes of one asset as a single amount
+		///
+		/// The proof (`circuit::JoinSplitCircuit`) shows both notes are distinct leaves of the
+		/// tree at the current `MerkleRoot` and that their amounts add up to `amount`, paid
+		/// to `recipient`; how the amount splits between them stays hidden. Both notes are
+		/// spent, nullifier and commitment, or neither is: if either is already spent the
+		/// call fails.
+		///
+		/// Parameters:
+		/// - `proof`: Proof against the `CircuitId::JoinSplit` verifying key
+		/// - `nullifier_a`, `nullifier_b`: Nullifiers of the two notes
+		/// - `commitment_a`, `commitment_b`: Commitments of the two notes
+		/// - `amount`: Sum of the notes' amounts, paid out
+		/// - `asset_id`: Asset of both notes
+		/// - `recipient`: Account receiving `amount`
+		///
+		/// Emits: `NotesJoined` event
+		#[pallet::call_index(55)]
+		#[pallet::weight(
+			Pallet::<T>::proof_verification_weight() + T::DbWeight::get().reads_writes(14, 8)
+		)]
+		pub fn withdraw_join(
+			origin: OriginFor<T>,
+			proof: ProofOf<T>,
+			nullifier_a: H256,
+			nullifier_b: H256,
+			commitment_a: H256,
+			commitment_b: H256,
+			amount: u128,
+			asset_id: u32,
+			recipient: T::AccountId,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			// The same note cannot be counted twice
+			ensure!(nullifier_a != nullifier_b, Error::<T>::NullifierAlreadyUsed);
+			ensure!(commitment_a != commitment_b, Error::<T>::NoteAlreadySpent);
+			Self::ensure_proof_anchor(None)?;
+			Self::ensure_withdrawals_enabled()?;
+			Self::ensure_note_age(asset_id, None)?;
+			Self::ensure_claim_window_open(asset_id)?;
+			Self::charge_verification(1)?;
+			let nullifiers = [nullifier_a, nullifier_b];
+			let commitments = [commitment_a, commitment_b];
+			let recipient_hash = primitives::recipient_hash(&recipient);
+			Self::verify_join_proof(
+				&proof,
+				&nullifiers,
+				&commitments,
+				asset_id,
+				amount,
+				&recipient_hash,
+			)?;
+			if !T::ClaimDustPayouts::get() {
+				Self::ensure_can_receive_payout(asset_id, &recipient, amount)?;
+			}
+
+			// A spent second note fails the call, undoing the first
+			for (nullifier, commitment) in nullifiers.into_iter().zip(commitments) {
+				Self::spend_note(nullifier, commitment)
+					.inspect_err(Self::warn_failure("joined withdrawal", nullifier))?;
+			}
+			Self::note_unshielded(asset_id, amount);
+			let receipt_id = Self::record_withdrawal(nullifier_a, asset_id, amount, 0, None);
+			Self::pay_out(asset_id, &recipient, amount)?;
+
+			Self::deposit_event(Event::NotesJoined {
+				nullifiers,
+				asset_id,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				receipt_id,
+			});
+			T::Metrics::withdrawal(asset_id);
+
+			Ok(())
+		}
+
+		/// Stop or resume deposits of a registered asset (admin only)
+		///
+		/// An inactive asset takes no deposits; notes already shielded in it can still be
+		/// withdrawn. An asset being wound down cannot be reactivated.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `is_active`: Whether the asset takes deposits
+		///
+		/// Emits: `AssetStatusChanged` event
+		#[pallet::call_index(57)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(2, 1))]
+		pub fn set_asset_status(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			is_active: bool,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				ensure!(
+					!is_active || !WindDowns::<T>::contains_key(asset.local_id),
+					Error::<T>::AssetWindingDown
+				);
+				asset.is_active = is_active;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::AssetStatusChanged { asset_id, is_active });
+
+			Ok(())
+		}
+
+		/// Change the minimum deposit of a registered asset (admin only)
+		///
+		/// Applies to deposits from now on; notes already shielded are unaffected.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `min_deposit`: New minimum deposit amount
+		///
+		/// Emits: `AssetMinDepositUpdated` event
+		#[pallet::call_index(58)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn update_asset_min_deposit(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			min_deposit: u128,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.min_deposit = min_deposit;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::AssetMinDepositUpdated { asset_id, min_deposit });
+
+			Ok(())
+		}
+
+		/// Restrict the deposits of a registered asset to fixed amounts (admin only)
+		///
+		/// Withdrawals are linkable to deposits of the same odd amount; with denominations,
+		/// every deposit must match one of them exactly and its note joins the pool of that
+		/// denomination, a tree of its own that withdrawals are proven against. Empty
+		/// `denominations` take any amount again. Notes already in a pool stay spendable
+		/// against it when its denomination is dropped. Commitment batches, whose notes'
+		/// values are unknown, are refused for denominated assets.
+		///
+		/// Parameters:
+		/// - `asset_id`: XCM AssetId of the registered asset
+		/// - `denominations`: Non-zero amounts in increasing order, or none
+		///
+		/// Emits: `DenominationsSet` event
+		#[pallet::call_index(59)]
+		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
+		pub fn set_denominations(
+			origin: OriginFor<T>,
+			asset_id: XcmAssetId,
+			denominations: Denominations,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(
+				denominations.first().map_or(true, |smallest| *smallest > 0) &&
+					denominations.windows(2).all(|pair| pair[0] < pair[1]),
+				Error::<T>::InvalidDenomination
+			);
+
+			let asset_id = Self::canonical_asset_id(asset_id);
+			AssetRegistry::<T>::try_mutate(&asset_id, |maybe_asset| -> DispatchResult {
+				let asset = maybe_asset.as_mut().ok_or(Error::<T>::AssetNotRegistered)?;
+				asset.denominations = denominations.clone();
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::DenominationsSet { asset_id, denominations });
+
+			Ok(())
+		}
+
+		/// Withdraw through a relayer, which is paid its fee out of the note
+		///
+		/// Lets a withdrawer with no funded account have anyone submit the withdrawal, so
+		/// nothing links them to the transaction. Like `withdraw`, with the proof made
+		/// against `merkle_root`, one of the roots in `RootHistory` (or the current root of
+		/// the note's denomination pool), and committing to the recipient, the relayer and
+		/// the fee (`primitives::relayed_recipient_hash`) instead of the caller: the proof
+		/// fails if the recipient or the fee split is changed. `recipient` is paid `amount`
+		/// less `fee` and `relayer` the fee, a failed payout being recorded as a claim. Like
+		/// `withdraw`, fails with `ProofAnchorRequired` when `MaxProofAge` is set.
+		///
+		/// The call may also be submitted unsigned, so no account at all is involved. The
+		/// relayer must then be the treasury, paid at least `UnsignedRelayFee`, and the pool
+		/// only takes the transaction once its proof verifies (see `validate_unsigned`).
+		///
+		/// Parameters:
+		/// - `proof`: Serialized Groth16 proof (`zksnark::generate_proof`), at most
+		///   `MaxProofSize` bytes
+		/// - `nullifier`: The nullifier hash (prevents double-spending)
+		/// - `commitment`: The commitment being spent
+		/// - `merkle_root`: Root the proof was made against
+		/// - `amount`: Amount of the note, the fee included
+		/// - `recipient`: Account paid the amount less the fee
+		/// - `relayer`: Account paid the fee, the treasury when unsigned
+		/// - `fee`: Part of the amount paid to the relayer, at most `amount`
+		/// - `asset_id`: Asset identifier
+		///
+		/// Emits: `AssetUnshielded` and `WithdrawalRelayed` events, `ClaimCreated` for every
+		/// failed payout
+		#[pallet::call_index(60)]
+		#[pallet::weight(
+			Weight::from_parts(10_000, 0) +
+				T::DbWeight::get().reads_writes(7 + T::RootHistorySize::get() as u64, 7) +
+				T::DbWeight::get().reads_writes(3, 4).saturating_mul(2)
+		)]
+		pub fn withdraw_via_relayer(
+			origin: OriginFor<T>,
+			proof: ProofOf<T>,
+			nullifier: H256,
+			commitment: H256,
+			merkle_root: H256,
+			amount: u128,
+			recipient: T::AccountId,
+			relayer: T::AccountId,
+			fee: u128,
+			asset_id: u32,
+		) -> DispatchResult {
+			let submitter = frame_system::ensure_signed_or_none(origin)?;
+			ensure!(fee <= amount, Error::<T>::FeeExceedsAmount);
+			if submitter.is_none() {
+				Self::ensure_unsigned_relay_fee(&relayer, fee)?;
+			}
+			let recipient_hash = primitives::relayed_recipient_hash(&recipient, &relayer, fee);
+			Self::check_withdrawal_proof(
+				&proof,
+				&nullifier,
+				&commitment,
+				&merkle_root,
+				asset_id,
+				amount,
+				&recipient_hash,
+				None,
+			)?;
+
+			Self::withdraw_relayed(nullifier, commitment, amount, asset_id, recipient, relayer, fee)
+		}
+	}
+
+	/// Helper functions (not callable by users)
+	impl<T: Config> Pallet<T> {
+		/// Shield `amount` of local asset `asset_id` from `source` into `commitment`
+		///
+		/// The internals of `deposit` without origin handling, for other pallets of the
+		/// runtime (see `ShieldInterface`): the caller is responsible for `source` having
+		/// authorised the deposit, and for charging `Pallet::deposit_weight()`. Either the
+		/// whole shield happens or nothing does, even if the caller goes on after an error.
+		pub fn do_shield(
+			source: T::AccountId,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+		) -> DispatchResult {
+			Self::ensure_deposits_enabled()?;
+			Self::ensure_asset_takes_deposit(asset_id, amount)?;
+			with_storage_layer(|| {
+				let denomination =
+					Self::denomination_pool(&Self::local_denominations(asset_id), amount)?;
+
+				// Take the deposited value into the pool
+				Self::transfer_to_pool(asset_id, &source, amount)?;
+
+				// Store commitment metadata (note: amount is NOT stored!)
+				let leaf_index = Self::insert_commitment(commitment, &source, asset_id)?;
+				Self::insert_denominated(asset_id, denomination, commitment)?;
+				Self::note_shielded(asset_id, amount)?;
+				Self::record_for_abandonment(commitment, asset_id, amount);
+				Self::warn_if_not_configured();
+
+				Self::deposit_event(Event::AssetShielded {
+					commitment,
+					asset_id,
+					depositor: source,
+					block_number: <frame_system::Pallet<T>>::block_number(),
+					leaf_index,
+					merkle_root: MerkleRoot::<T>::get(),
+				});
+				T::Metrics::deposit(asset_id);
+				log::debug!(
+					target: LOG_TARGET,
+					"shielded {commitment:?} of asset {asset_id} at leaf {leaf_index}"
+				);
+
+				Ok(())
+			})
+			.inspect_err(Self::warn_failure("deposit", commitment))
+		}
+
+		/// `spec_version` of the running runtime
+		fn spec_version() -> u32 {
+			<T as frame_system::Config>::Version::get().spec_version
+		}
+
+		/// The verifying key to check withdrawal proofs against
+		///
+		/// Refuses a key whose setup used another hasher than the runtime's (e.g. after
+		/// `Config::Hasher` changed), as no proof against it can match on-chain hashes.
+		pub fn verifying_key_for_proofs() -> Result<VerifyingKeyBytes, DispatchError> {
+			let vk = Self::verifying_key_for(CircuitId::Withdraw)
+				.ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			let setup = VerifyingKeySetup::<T>::get().ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			ensure!(setup.hasher == T::Hasher::id(), Error::<T>::HasherMismatch);
+
+			Ok(vk)
+		}
+
+		/// Store `vk` as the verifying key of `circuit`, with its prepared form
+		fn put_verifying_key(circuit: CircuitId, vk: VerifyingKeyBytes) -> DispatchResult {
+			let prepared: PreparedKeyBytes = T::ProofVerifier::prepare_key(&vk)
+				.ok()
+				.and_then(|prepared| prepared.try_into().ok())
+				.ok_or(Error::<T>::MalformedVerifyingKey)?;
+			VerifyingKeys::<T>::insert(circuit, vk);
+			PreparedVerifyingKeys::<T>::insert(circuit, prepared);
+			Ok(())
+		}
+
+		/// `vk`, the verifying key of `circuit`, prepared for `Config::ProofVerifier`
+		///
+		/// Read from `PreparedVerifyingKeys`, or prepared on the spot for a key stored
+		/// before it.
+		fn prepared_key_for(circuit: CircuitId, vk: &[u8]) -> Result<Vec<u8>, VerifyError> {
+			match PreparedVerifyingKeys::<T>::get(circuit) {
+				Some(prepared) => Ok(prepared.into_inner()),
+				None => T::ProofVerifier::prepare_key(vk),
+			}
+		}
+
+		/// Account holding the shielded value
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account_truncating()
+		}
+
+		/// Account holding the sponsorship pot, kept apart from the shielded value
+		pub fn sponsorship_account() -> T::AccountId {
+			T::PalletId::get().into_sub_account_truncating(b"sponsor")
+		}
+
+		/// Account holding the bounties of proving jobs and the bonds of their provers
+		pub fn proving_jobs_account() -> T::AccountId {
+			T::PalletId::get().into_sub_account_truncating(b"provers")
+		}
+
+		/// Take a proving job and its request off the board
+		fn remove_proving_job(job_id: ProvingJobId) {
+			ProvingJobs::<T>::remove(job_id);
+			ProvingJobRequests::<T>::remove(job_id);
+			ProvingJobCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+		}
+
+		/// Account holding the deposits of note backups
+		pub fn backup_deposit_account() -> T::AccountId {
+			T::PalletId::get().into_sub_account_truncating(b"backups")
+		}
+
+		/// Return `amount` of `who`'s note backup deposit
+		fn refund_backup_deposit(who: &T::AccountId, amount: u128) -> DispatchResult {
+			if amount == 0 {
+				return Ok(());
+			}
+
+			<T::Currency as fungible::Mutate<_>>::transfer(
+				&Self::backup_deposit_account(),
+				who,
+				amount,
+				Preservation::Expendable,
+			)?;
+
+			Ok(())
+		}
+
+		/// Amount of a `deposit` whose transaction fee the sponsorship pot pays for `who`
+		///
+		/// Native deposits of at most `SponsorThreshold` are sponsored while the pot holds
+		/// `SponsoredFee` and neither `who` nor all accounts together have used up their
+		/// sponsored deposits of the current period. Other calls are never sponsored.
+		pub fn sponsored_amount(who: &T::AccountId, call: &Call<T>) -> Option<u128> {
+			let Call::deposit { amount, asset_id, .. } = call else {
+				return None;
+			};
+			let threshold = T::SponsorThreshold::get();
+			if *asset_id != NATIVE_ASSET_ID || threshold.is_zero() || *amount > threshold {
+				return None;
+			}
+			if SponsorshipPot::<T>::get() < T::SponsoredFee::get() {
+				return None;
+			}
+
+			let period = Self::sponsor_period();
+			let used = |(counted_in, count): (BlockNumberFor<T>, u32)| {
+				if counted_in == period { count } else { 0 }
+			};
+			let within_limits = used(SponsoredDeposits::<T>::get(who)) < T::MaxSponsoredPerAccount::get() &&
+				used(SponsoredInPeriod::<T>::get()) < T::MaxSponsoredPerPeriod::get();
+			within_limits.then_some(*amount)
+		}
+
+		/// Pay the transaction fee of `who`'s deposit of `amount` from the sponsorship pot
+		///
+		/// The fee is burned, as the runtime does with transaction fees it charges.
+		pub(crate) fn sponsor_deposit(who: &T::AccountId, amount: u128) -> DispatchResult {
+			let fee = T::SponsoredFee::get();
+			let pot = SponsorshipPot::<T>::get().checked_sub(fee).ok_or(Error::<T>::NotSponsored)?;
+			<T::Currency as fungible::Mutate<_>>::burn_from(
+				&Self::sponsorship_account(),
+				fee,
+				Preservation::Expendable,
+				Precision::Exact,
+				Fortitude::Polite,
+			)?;
+			SponsorshipPot::<T>::put(pot);
+
+			let period = Self::sponsor_period();
+			let count = |entry: &mut (BlockNumberFor<T>, u32)| {
+				let count = if entry.0 == period { entry.1 } else { 0 };
+				*entry = (period, count.saturating_add(1));
+			};
+			SponsoredDeposits::<T>::mutate(who, count);
+			SponsoredInPeriod::<T>::mutate(count);
+
+			Self::deposit_event(Event::DepositSponsored { account: who.clone(), amount, fee });
+
+			Ok(())
+		}
+
+		/// Sponsorship rate limit period of the current block
+		fn sponsor_period() -> BlockNumberFor<T> {
+			let length = T::SponsorPeriod::get().max(One::one());
+			frame_system::Pallet::<T>::block_number() / length
+		}
+
+		/// Pay out value of a spent note, recording a claim if the transfer fails
+		///
+		/// Only fails if the claim itself cannot be recorded, so the caller's spend is
+		/// never committed without the value being paid or claimable. Returns the ID of
+		/// the claim, if one was recorded.
+		pub(crate) fn pay_out(
+			asset_id: u32,
+			beneficiary: &T::AccountId,
+			amount: u128,
+		) -> Result<Option<ClaimId>, DispatchError> {
+			if Self::transfer_from_pool(asset_id, beneficiary, amount).is_ok() {
+				return Ok(None);
+			}
+
+			let claim_id = NextClaimId::<T>::get();
+			NextClaimId::<T>::put(claim_id.checked_add(1).ok_or(Error::<T>::AmountOverflow)?);
+
+			Claims::<T>::insert(claim_id, Claim {
+				asset_id,
+				amount,
+				beneficiary: beneficiary.clone(),
+			});
+
+			Self::deposit_event(Event::ClaimCreated {
+				claim_id,
+				asset_id,
+				amount,
+				beneficiary: beneficiary.clone(),
+			});
+
+			Ok(Some(claim_id))
+		}
+
+		/// Custody mode of a local asset (`Pot` unless registered otherwise)
+		pub fn custody_mode(asset_id: u32) -> CustodyMode {
+			Self::registered_asset_by_local(asset_id)
+				.map(|asset| asset.custody_mode)
+				.unwrap_or_default()
+		}
+
+		/// Spend a note and pay its value out locally to `who`
+		pub(crate) fn do_withdraw(
+			who: T::AccountId,
+			nullifier: H256,
+			commitment: H256,
+			amount: u128,
+			asset_id: u32,
+		) -> DispatchResult {
+			Self::withdraw_locally(who, nullifier, commitment, amount, asset_id)
+				.inspect_err(Self::warn_failure("withdrawal", nullifier))
+		}
+
+		/// Spend a note for a relayed withdrawal, paying `recipient` the `amount` less `fee`
+		/// and `relayer` the fee
+		fn withdraw_relayed(
+			nullifier: H256,
+			commitment: H256,
+			amount: u128,
+			asset_id: u32,
+			recipient: T::AccountId,
+			relayer: T::AccountId,
+			fee: u128,
+		) -> DispatchResult {
+			let mut payouts = vec![(recipient, amount - fee)];
+			if fee > 0 {
+				payouts.push((relayer.clone(), fee));
+			}
+			let receipt_id = Self::spend_locally(nullifier, commitment, amount, asset_id, &payouts)
+				.inspect_err(Self::warn_failure("withdrawal", nullifier))?;
+
+			// The nullifier is consumed: both payouts must end up paid or claimable
+			for (account, value) in payouts.iter() {
+				Self::pay_out(asset_id, account, *value)?;
+			}
+			Self::deposit_event(Event::WithdrawalRelayed { receipt_id, relayer, fee });
+
+			Ok(())
+		}
+
+		/// `do_withdraw`, without its logging
+		fn withdraw_locally(
+			who: T::AccountId,
+			nullifier: H256,
+			commitment: H256,
+			amount: u128,
+			asset_id: u32,
+		) -> DispatchResult {
+			Self::spend_locally(nullifier, commitment, amount, asset_id, &[(who.clone(), amount)])?;
+
+			// The nullifier is consumed: the value must end up paid or claimable
+			Self::pay_out(asset_id, &who, amount)?;
+			log::debug!(target: LOG_TARGET, "withdrawal of {nullifier:?}: paid in asset {asset_id}");
+
+			// Week 4+: Send tokens via XCM to destination parachain
+
+			Ok(())
+		}
+
+		/// Consume the co-signer's approval of `action`, before executing it
+		fn consume_admin_approval(action: AdminAction) -> DispatchResult {
+			let action_hash = action.hash();
+			let approval =
+				AdminApprovals::<T>::get(action_hash).ok_or(Error::<T>::AdminActionNotApproved)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() < approval.expires_at,
+				Error::<T>::AdminApprovalExpired
+			);
+			ensure!(approval.approved, Error::<T>::AdminActionNotApproved);
+
+			AdminApprovals::<T>::remove(action_hash);
+			Self::deposit_event(Event::AdminActionExecuted { action_hash });
+
+			Ok(())
+		}
+
+		/// Spend a note for a local withdrawal to `payouts`, returning its receipt ID
+		///
+		/// Runs the proof checks, then checks the payouts add up to `amount` and can be
+		/// received, before the note is spent. Paying out is left to the caller.
+		fn spend_locally(
+			nullifier: H256,
+			commitment: H256,
+			amount: u128,
+			asset_id: u32,
+			payouts: &[(T::AccountId, u128)],
+		) -> Result<H256, DispatchError> {
+			Self::ensure_withdrawals_enabled()?;
+			Self::verifying_key_for_proofs()?;
+			Self::charge_verification(1)?;
+			log::debug!(
+				target: LOG_TARGET,
+				"withdrawal of {nullifier:?}: proof checks passed, {:?} of verification weight \
+				 used in the block",
+				Self::verification_weight_used()
+			);
+
+			ensure!(!payouts.is_empty(), Error::<T>::NoPayouts);
+			let total = payouts
+				.iter()
+				.try_fold(0u128, |total, (_, value)| total.checked_add(*value))
+				.ok_or(Error::<T>::AmountOverflow)?;
+			ensure!(total == amount, Error::<T>::PayoutSumMismatch);
+			if !T::ClaimDustPayouts::get() {
+				for (recipient, value) in payouts {
+					Self::ensure_can_receive_payout(asset_id, recipient, *value)?;
+				}
+			}
+
+			// Check the note can be spent and mark it as used
+			ensure!(!BundleNotes::<T>::get(&commitment), Error::<T>::NoteKindMismatch);
+			Self::ensure_note_asset(&commitment, asset_id)?;
+			Self::spend_note(nullifier, commitment)?;
+			Self::note_unshielded(asset_id, amount);
+			let receipt_id = Self::record_withdrawal(nullifier, asset_id, amount, 0, None);
+
+			// Emit event
+			Self::deposit_event(Event::AssetUnshielded {
+				nullifier,
+				asset_id,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				receipt_id,
+			});
+			T::Metrics::withdrawal(asset_id);
+
+			Ok(receipt_id)
+		}
+
+		/// Spend a note on behalf of another pallet of the runtime (see `UnshieldInterface`)
+		///
+		/// Runs the checks of `withdraw` for a `Local` payout and of `withdraw_to_parachain`
+		/// for a `Remote` one, which accepts any destination fee up to the amount. The
+		/// envelope must be for the circuits of the current verifying key, and its proof
+		/// must verify against the public inputs, with the current root when they name none,
+		/// paying the payout's account or beneficiary (see `unshield_recipient_hash`).
+		/// Either the whole unshield happens or nothing does.
+		pub fn do_unshield(
+			proof_envelope: ProofEnvelope,
+			public_inputs: UnshieldInputs,
+			payout: PayoutTarget<T::AccountId>,
+		) -> DispatchResult {
+			with_storage_layer(|| {
+				Self::validate_unshield(&proof_envelope, &public_inputs)
+					.inspect_err(Self::warn_failure("unshield", public_inputs.nullifier))?;
+
+				let UnshieldInputs { nullifier, commitment, asset_id, amount, .. } = public_inputs;
+				let merkle_root = public_inputs.merkle_root.unwrap_or_else(MerkleRoot::<T>::get);
+				let recipient_hash =
+					Self::unshield_recipient_hash(&payout, public_inputs.anchor_block_hash);
+				Self::verify_withdrawal_proof_against(
+					&proof_envelope.proof,
+					&nullifier,
+					&commitment,
+					&merkle_root,
+					asset_id,
+					amount,
+					&recipient_hash,
+				)?;
+
+				match payout {
+					PayoutTarget::Local(who) =>
+						Self::do_withdraw(who, nullifier, commitment, amount, asset_id),
+					PayoutTarget::Remote(destination, beneficiary) => Self::do_withdraw_to_remote(
+						nullifier,
+						commitment,
+						asset_id,
+						amount,
+						destination.into(),
+						&beneficiary.encode(),
+						amount,
+					),
+				}
+			})
+		}
+
+		/// Recipient hash the proof of an unshield to `payout` pays
+		///
+		/// That of the account for a `Local` payout, and of the beneficiary's location for a
+		/// `Remote` one, as `withdraw` and `withdraw_to_parachain` take it; an anchored proof
+		/// binds its anchor as well, as in `withdraw_anchored`.
+		pub fn unshield_recipient_hash(
+			payout: &PayoutTarget<T::AccountId>,
+			anchor_block_hash: Option<H256>,
+		) -> H256 {
+			match (payout, anchor_block_hash) {
+				(PayoutTarget::Local(who), None) => primitives::recipient_hash(who),
+				(PayoutTarget::Local(who), Some(anchor)) =>
+					primitives::anchored_recipient_hash(who, &anchor),
+				(PayoutTarget::Remote(_, beneficiary), None) =>
+					primitives::recipient_hash(beneficiary),
+				(PayoutTarget::Remote(_, beneficiary), Some(anchor)) =>
+					primitives::anchored_recipient_hash(beneficiary, &anchor),
+			}
+		}
+
+		/// Check a proof's envelope and public inputs would be accepted, without spending
+		///
+		/// The checks of `do_unshield` that do not depend on the payout, in the order it runs
+		/// them: circuit version, proof anchor, note age, then the note itself (kind, asset,
+		/// nullifier and commitment). Nothing is written.
+		pub fn validate_unshield(
+			proof_envelope: &ProofEnvelope,
+			public_inputs: &UnshieldInputs,
+		) -> DispatchResult {
+			let setup = VerifyingKeySetup::<T>::get().ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			ensure!(
+				proof_envelope.circuit_version == setup.circuit_version,
+				Self::proof_failure(Error::<T>::CircuitVersionMismatch)
+			);
+			Self::ensure_proof_anchor(public_inputs.anchor_block_hash)?;
+			Self::ensure_note_age(public_inputs.asset_id, public_inputs.merkle_root)?;
+
+			ensure!(!BundleNotes::<T>::get(&public_inputs.commitment), Error::<T>::NoteKindMismatch);
+			Self::ensure_note_asset(&public_inputs.commitment, public_inputs.asset_id)?;
+			Self::ensure_spendable(public_inputs.nullifier, public_inputs.commitment)
+		}
+
+		/// Spend a note and export its value to a remote chain
+		pub(crate) fn do_withdraw_to_remote(
+			nullifier: H256,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: VersionedDestination,
+			beneficiary: &[u8],
+			max_fee: u128,
+		) -> DispatchResult {
+			let (asset, payout, fee) =
+				Self::spend_for_remote(nullifier, commitment, asset_id, amount, &destination, max_fee)
+					.inspect_err(Self::warn_failure("remote withdrawal", nullifier))?;
+
+			let message_id = T::Exporter::export(&asset.asset_id, payout, &destination, beneficiary)
+				.map_err(|error| DispatchError::from(Self::export_error(error)))
+				.inspect_err(Self::warn_failure("export of withdrawal", nullifier))?;
+			log::debug!(
+				target: LOG_TARGET,
+				"withdrawal of {nullifier:?}: sent message {message_id:?} to {destination:?}"
+			);
+
+			Self::deposit_event(Event::WithdrawalExported {
+				nullifier,
+				asset_id,
+				amount: payout,
+				fee,
+				destination,
+				message_id,
+				receipt_id: Self::withdrawal_receipt_id(nullifier),
+			});
+
+			Ok(())
+		}
+
+		/// Checks of a cross-chain withdrawal that do not depend on the note spent
+		///
+		/// Shared by the dispatch path and `dry_run_cross_chain_withdrawal`. Returns the
+		/// registered asset, the destination fee and every check that fails, in the
+		/// order the dispatch path reports them.
+		pub(crate) fn check_remote_withdrawal(
+			asset_id: u32,
+			amount: u128,
+			destination: &VersionedDestination,
+		) -> (Option<RegisteredAsset>, u128, Vec<RejectionReason>) {
+			let VersionedDestination::V5(location) = destination;
+			let fee = DestinationFees::<T>::get(location).map_or(0, |config| config.fee_for(amount));
+
+			let Some(asset) = Self::registered_asset_by_local(asset_id) else {
+				return (None, fee, vec![RejectionReason::AssetNotRegistered]);
+			};
+
+			let mut reasons = Vec::new();
+			if Self::ensure_channel_open(&asset, destination).is_err() {
+				reasons.push(RejectionReason::NoChannelToDestination);
+			}
+			match amount.checked_sub(fee) {
+				None => reasons.push(RejectionReason::FeeExceedsAmount),
+				Some(payout) if payout < asset.remote_min_balance =>
+					reasons.push(RejectionReason::BelowRemoteMinBalance),
+				Some(_) => {},
+			}
+
+			(Some(asset), fee, reasons)
+		}
+
+		/// Check whether a cross-chain withdrawal would be accepted, without spending
+		///
+		/// Runs the same checks as the dispatch path, except those on the note itself
+		/// (nullifier, commitment, proof). Backs the `PrivacyBridgeApi` runtime API.
+		pub fn dry_run_cross_chain_withdrawal(
+			asset_id: u32,
+			amount: u128,
+			destination: Location,
+			beneficiary: Vec<u8>,
+		) -> DryRunResult {
+			let (_, estimated_fee, mut reasons) =
+				Self::check_remote_withdrawal(asset_id, amount, &destination.into());
+			if beneficiary.len() > MAX_BENEFICIARY_LEN as usize {
+				reasons.push(RejectionReason::BeneficiaryTooLong);
+			}
+
+			DryRunResult { ok: reasons.is_empty(), estimated_fee, reasons }
+		}
+
+		/// Spend a note for a cross-chain withdrawal
+		///
+		/// The destination fee is taken out of `amount` and paid to the treasury.
+		/// Unreachable destinations, fees above `max_fee` and payouts below the asset's
+		/// `remote_min_balance` are rejected before spending.
+		///
+		/// Returns the registered asset, the amount to send and the fee.
+		fn spend_for_remote(
+			nullifier: H256,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+			destination: &VersionedDestination,
+			max_fee: u128,
+		) -> Result<(RegisteredAsset, u128, u128), DispatchError> {
+			Self::ensure_withdrawals_enabled()?;
+			Self::verifying_key_for_proofs()?;
+			Self::charge_verification(1)?;
+			let (asset, fee, reasons) = Self::check_remote_withdrawal(asset_id, amount, destination);
+			if let Some(reason) = reasons.first() {
+				return Err(Error::<T>::from(*reason).into());
+			}
+			let asset = asset.ok_or(Error::<T>::AssetNotRegistered)?;
+			ensure!(fee <= max_fee, Error::<T>::FeeExceedsMaximum);
+			let payout = amount.saturating_sub(fee);
+
+			// Check the note can be spent and mark it as used
+			ensure!(!BundleNotes::<T>::get(&commitment), Error::<T>::NoteKindMismatch);
+			Self::ensure_note_asset(&commitment, asset_id)?;
+			Self::spend_note(nullifier, commitment)?;
+			Self::note_unshielded(asset_id, amount);
+			Self::note_backing_out(&asset, amount)?;
+			let destination = Some(destination.clone());
+			let receipt_id = Self::record_withdrawal(nullifier, asset_id, amount, fee, destination);
+
+			Self::deposit_event(Event::AssetUnshielded {
+				nullifier,
+				asset_id,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				receipt_id,
+			});
+			T::Metrics::withdrawal(asset_id);
+
+			if !fee.is_zero() {
+				Self::pay_out(asset_id, &T::TreasuryAccount::get(), fee)?;
+			}
+
+			Ok((asset, payout, fee))
+		}
+
+		/// Export queued withdrawals within `limit`, one message per (destination, asset)
+		///
+		/// Each message pays out at most `MaxDepositsPerMessage` withdrawals. Every
+		/// withdrawal in a message is marked by the message's result: removed on success,
+		/// `Failed` otherwise. Returns the weight used.
+		///
+		/// Messages are sent, and each pays out, in an order drawn from `Config::Randomness`,
+		/// so payouts do not reveal the order withdrawals were queued in.
+		pub(crate) fn drain_withdrawal_queue(limit: Weight) -> Weight {
+			let db = T::DbWeight::get();
+			let per_withdrawal = db.reads_writes(1, 1);
+			let per_message = Weight::from_parts(10_000, 0) + db.reads(1);
+			let max_deposits = T::MaxDepositsPerMessage::get().max(1) as usize;
+
+			// The queue, then the randomness source
+			let mut used = db.reads(2);
+			if used.any_gt(limit) {
+				return Weight::zero();
+			}
+			let mut batches: Vec<(VersionedDestination, u32, Vec<(WithdrawalId, QueuedWithdrawal)>)> =
+				Vec::new();
+
+			for (withdrawal_id, withdrawal) in QueuedWithdrawals::<T>::iter() {
+				// Leave room for a new message in case this withdrawal starts one
+				if used.saturating_add(per_withdrawal).saturating_add(per_message).any_gt(limit) {
+					break;
+				}
+				used.saturating_accrue(per_withdrawal);
+
+				if withdrawal.status != WithdrawalStatus::Queued {
+					continue;
+				}
+
+				let batch = batches.iter_mut().find(|(destination, asset_id, withdrawals)| {
+					*destination == withdrawal.destination &&
+						*asset_id == withdrawal.asset_id &&
+						withdrawals.len() < max_deposits
+				});
+				match batch {
+					Some((_, _, withdrawals)) => withdrawals.push((withdrawal_id, withdrawal)),
+					None => {
+						used.saturating_accrue(per_message);
+						batches.push((
+							withdrawal.destination.clone(),
+							withdrawal.asset_id,
+							vec![(withdrawal_id, withdrawal)],
+						));
+					},
+				}
+			}
+
+			let mut rng = ChaCha20Rng::from_seed(Self::derive_randomness(b"withdrawal-queue").0);
+			randomness::shuffle(&mut batches, &mut rng);
+			for (destination, asset_id, mut withdrawals) in batches {
+				randomness::shuffle(&mut withdrawals, &mut rng);
+				let payouts: Vec<(u128, Vec<u8>)> = withdrawals
+					.iter()
+					.map(|(_, withdrawal)| (withdrawal.amount, withdrawal.beneficiary.to_vec()))
+					.collect();
+				let result = Self::registered_asset_by_local(asset_id)
+					.ok_or(ExportError::Unroutable)
+					.and_then(|asset| T::Exporter::export_batch(&asset.asset_id, &destination, &payouts));
+
+				for (withdrawal_id, mut withdrawal) in withdrawals {
+					match result {
+						Ok(message_id) => {
+							log::debug!(
+								target: LOG_TARGET,
+								"queued withdrawal {withdrawal_id}: sent message {message_id:?}"
+							);
+							QueuedWithdrawals::<T>::remove(withdrawal_id);
+							Self::deposit_event(Event::QueuedWithdrawalExecuted {
+								withdrawal_id,
+								message_id,
+							});
+							T::Metrics::withdrawal_exported();
+						},
+						Err(error) => {
+							log::warn!(
+								target: LOG_TARGET,
+								"queued withdrawal {withdrawal_id} failed: {error:?}"
+							);
+							withdrawal.status = WithdrawalStatus::Failed;
+							QueuedWithdrawals::<T>::insert(withdrawal_id, withdrawal);
+							T::Metrics::export_failed();
+							Self::deposit_event(Event::QueuedWithdrawalFailed { withdrawal_id });
+						},
+					}
+				}
+			}
+
+			used
+		}
+
+		/// Ensure the first hop of a withdrawal is reachable
+		///
+		/// The first hop is the asset's reserve when routing through it, else the
+		/// destination. Only sibling parachains need an open channel; the relay and
+		/// non-XCM destinations are always accepted.
+		fn ensure_channel_open(
+			asset: &RegisteredAsset,
+			destination: &VersionedDestination,
+		) -> DispatchResult {
+			let VersionedDestination::V5(destination) = destination;
+			let first_hop = match WithdrawalRoute::select(asset.reserve_location.as_ref(), destination) {
+				WithdrawalRoute::ViaReserve(reserve) => reserve,
+				WithdrawalRoute::Direct => destination.clone(),
+			};
+
+			if let Some(para_id) = sibling_para_id(&first_hop) {
+				ensure!(
+					OpenChannels::<T>::get().binary_search(&para_id).is_ok(),
+					Error::<T>::NoChannelToDestination
+				);
+			}
+
+			Ok(())
+		}
+
+		/// Register an asset, returning its local ID
+		pub(crate) fn do_register_asset(
+			asset_id: XcmAssetId,
+			min_deposit: u128,
+			custody_mode: CustodyMode,
+			remote_min_balance: u128,
+		) -> Result<u32, DispatchError> {
+			// Registry keys are canonical, so every equivalent location finds the asset
+			let asset_id = Self::canonical_asset_id(asset_id);
+			// Registering again would orphan the notes shielded under the old local ID
+			ensure!(
+				!AssetRegistry::<T>::contains_key(&asset_id),
+				Error::<T>::AssetAlreadyRegistered
+			);
+
+			// The native token keeps its reserved ID; XCM assets take the next free one
+			let local_id = if asset_id.0 == Location::here() {
+				NATIVE_ASSET_ID
+			} else {
+				let local_id = NextAssetId::<T>::get();
+				NextAssetId::<T>::put(local_id.checked_add(1).ok_or(Error::<T>::AmountOverflow)?);
+				local_id
+			};
+
+			// Create registration
+			let mut registration = RegisteredAsset::new(asset_id.clone(), local_id);
+			registration.min_deposit = min_deposit;
+			registration.custody_mode = custody_mode;
+			registration.remote_min_balance = remote_min_balance;
+
+			// Store registration and the reverse index
+			LocalAssetIndex::<T>::insert(local_id, asset_id.clone());
+			AssetRegistry::<T>::insert(&asset_id, registration);
+			Self::deposit_event(Event::AssetRegistered {
+				xcm_asset_id: asset_id,
+				local_id,
+				min_deposit,
+			});
+
+			Ok(local_id)
+		}
+
+		/// Reserve chain of a registered asset, if it is not this chain
+		pub fn reserve_location(asset_id: &XcmAssetId) -> Option<Location> {
+			Self::registered_asset(asset_id).and_then(|asset| asset.reserve_location)
+		}
+
+		/// Canonical form of an asset ID as seen from this chain
+		///
+		/// See `xcm_config::canonical_location`.
+		pub fn canonical_asset_id(asset_id: XcmAssetId) -> XcmAssetId {
+			XcmAssetId(crate::xcm_config::canonical_location(
+				&asset_id.0,
+				&T::UniversalLocation::get(),
+			))
+		}
+
+		/// Look up a registered asset by any equivalent form of its XCM AssetId
+		pub fn registered_asset(asset_id: &XcmAssetId) -> Option<RegisteredAsset> {
+			AssetRegistry::<T>::get(Self::canonical_asset_id(asset_id.clone()))
+		}
+
+		/// Map an exporter failure to a pallet error
+		fn export_error(error: ExportError) -> Error<T> {
+			match error {
+				ExportError::InvalidBeneficiary => Error::<T>::InvalidBeneficiary,
+				ExportError::Unroutable |
+				ExportError::SendFailed |
+				ExportError::FeesExceedAmount => Error::<T>::ExportFailed,
+			}
+		}
+
+		/// Shield assets deposited by an XCM program from `origin`
+		///
+		/// Called by `ShieldDepositTransactor` with the message topic as `note_key`. The
+		/// commitment is computed here over the deposited amount and `origin` (see
+		/// `xcm_config::xcm_commitment_data`), so a message can only shield into a note of
+		/// its own chain for the amount it delivered. `origin` is also where a deposit
+		/// receipt goes, if the asset sends receipts, and identifies redelivered deposits.
+		pub(crate) fn shield_from_xcm(
+			asset_id: &XcmAssetId,
+			amount: u128,
+			note_key: &[u8; 32],
+			origin: &Location,
+		) -> DispatchResult {
+			Self::ensure_deposits_enabled()?;
+			ensure!(
+				crate::xcm_config::is_valid_origin_location(origin),
+				Error::<T>::InvalidOriginLocation
+			);
+			let registered =
+				Self::registered_asset(asset_id).ok_or(Error::<T>::AssetNotRegistered)?;
+			let commitment = crate::xcm_config::xcm_commitment_data::<T::Hasher>(
+				amount,
+				registered.local_id,
+				note_key,
+				origin,
+			);
+
+			Self::shield_xcm_commitment(&registered, amount, commitment, origin)
+		}
+
+		/// Shield `amount` of `registered` deposited by `origin` into `commitment`
+		///
+		/// The assets are credited to the pool account, which is recorded as the depositor.
+		pub(crate) fn shield_xcm_commitment(
+			registered: &RegisteredAsset,
+			amount: u128,
+			commitment: H256,
+			origin: &Location,
+		) -> DispatchResult {
+			ensure!(registered.is_active, Error::<T>::AssetInactive);
+			ensure!(amount >= registered.min_deposit, Error::<T>::DepositBelowMinimum);
+			let denomination = Self::denomination_pool(&registered.denominations, amount)?;
+
+			// A redelivered message succeeds without a second leaf
+			if Self::is_duplicate_xcm_deposit(commitment, registered, origin) {
+				Self::note_unattributed(commitment, origin, registered, amount);
+				return Ok(());
+			}
+
+			let pool = Self::account_id();
+			let leaf_index = Self::insert_commitment(commitment, &pool, registered.local_id)?;
+			Self::insert_denominated(registered.local_id, denomination, commitment)?;
+			XcmDepositOrigins::<T>::insert(commitment, origin);
+			Self::note_shielded(registered.local_id, amount)?;
+			Self::note_backing_in(registered, amount)?;
+			Self::record_for_abandonment(commitment, registered.local_id, amount);
+
+			Self::deposit_event(Event::AssetShielded {
+				commitment,
+				asset_id: registered.local_id,
+				depositor: pool,
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				leaf_index,
+				merkle_root: MerkleRoot::<T>::get(),
+			});
+			T::Metrics::deposit(registered.local_id);
+			Self::send_deposit_receipt(registered, origin, commitment, leaf_index);
+
+			Ok(())
+		}
+
+		/// Whether an XCM deposit into `commitment` was already made by `origin` for `asset`
+		pub(crate) fn is_duplicate_xcm_deposit(
+			commitment: H256,
+			asset: &RegisteredAsset,
+			origin: &Location,
+		) -> bool {
+			Commitments::<T>::get(&commitment)
+				.map_or(false, |data| data.asset_id == asset.local_id) &&
+				XcmDepositOrigins::<T>::get(&commitment).as_ref() == Some(origin)
+		}
+
+		/// Hold the funds of a duplicate deposit for refunding to `origin`
+		pub(crate) fn note_unattributed(
+			commitment: H256,
+			origin: &Location,
+			asset: &RegisteredAsset,
+			amount: u128,
+		) {
+			UnattributedBalances::<T>::mutate(origin, &asset.asset_id, |balance| {
+				*balance = balance.saturating_add(amount)
+			});
+
+			log::warn!(
+				target: LOG_TARGET,
+				"duplicate XCM deposit into {commitment:?} from {origin:?}, held for refunding"
+			);
+			Self::deposit_event(Event::DuplicateDeposit {
+				commitment,
+				origin: origin.clone(),
+				asset_id: asset.asset_id.clone(),
+				amount,
+			});
+		}
+
+		/// Confirm a cross-chain deposit to `origin`, if `asset` sends receipts
+		///
+		/// Best effort: a receipt that cannot be sent does not fail the deposit.
+		pub(crate) fn send_deposit_receipt(
+			asset: &RegisteredAsset,
+			origin: &Location,
+			commitment: H256,
+			leaf_index: u32,
+		) {
+			let Some(receipt_call) = asset.receipt_call else {
+				return;
+			};
+
+			let receipt = DepositReceipt { commitment, leaf_index };
+			match T::ReceiptSender::send_receipt(origin, receipt_call, &receipt) {
+				Ok(message_id) => {
+					log::debug!(
+						target: LOG_TARGET,
+						"receipt of {commitment:?}: sent message {message_id:?} to {origin:?}"
+					);
+					Self::deposit_event(Event::DepositReceiptSent {
+						commitment,
+						origin: origin.clone(),
+						message_id,
+					})
+				},
+				Err(error) => {
+					log::warn!(
+						target: LOG_TARGET,
+						"receipt of {commitment:?} to {origin:?} failed: {error:?}"
+					);
+					Self::deposit_event(Event::DepositReceiptFailed {
+						commitment,
+						origin: origin.clone(),
+					})
+				},
+			}
+		}
+
+		/// Record a shield deposit trapped by the XCM executor
+		pub(crate) fn note_trapped(origin: &Location, asset_id: XcmAssetId, amount: u128) {
+			let asset_id = Self::canonical_asset_id(asset_id);
+			TrappedAssets::<T>::mutate(origin, &asset_id, |trapped| {
+				*trapped = trapped.saturating_add(amount)
+			});
+
+			log::warn!(
+				target: LOG_TARGET,
+				"shield deposit from {origin:?} trapped: {amount} of {asset_id:?}"
+			);
+			Self::deposit_event(Event::ShieldDepositTrapped {
+				origin: origin.clone(),
+				asset_id,
+				amount,
+			});
+		}
+
+		/// Smallest local payout of `asset_id` that can create a fresh recipient account
+		///
+		/// A recipient that holds none of the asset only comes into existence once paid at
+		/// least the asset's minimum balance (the existential deposit, for the native
+		/// asset). Smaller payouts only reach accounts that already exist.
+		pub fn minimum_payout(asset_id: u32) -> u128 {
+			if asset_id == NATIVE_ASSET_ID {
+				<T::Currency as fungible::Inspect<_>>::minimum_balance()
+			} else {
+				<T::Fungibles as fungibles::Inspect<_>>::minimum_balance(asset_id)
+			}
+		}
+
+		/// Ensure a local payout can be credited to the recipient, creating its account
+		///
+		/// Fails if the payout would leave the recipient below the existential deposit,
+		/// or if the asset cannot create the recipient's account at all (e.g. an asset
+		/// that is not sufficient, paid to an account without providers).
+		pub(crate) fn ensure_can_receive_payout(
+			asset_id: u32,
+			recipient: &T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			let consequence = if asset_id == NATIVE_ASSET_ID {
+				<T::Currency as fungible::Inspect<_>>::can_deposit(
+					recipient,
+					amount,
+					Provenance::Minted,
+				)
+			} else {
+				<T::Fungibles as fungibles::Inspect<_>>::can_deposit(
+					asset_id,
+					recipient,
+					amount,
+					Provenance::Minted,
+				)
+			};
+
+			match consequence {
+				DepositConsequence::BelowMinimum => Err(Error::<T>::BelowExistentialDeposit.into()),
+				DepositConsequence::CannotCreate => Err(Error::<T>::RecipientCannotBeCreated.into()),
+				_ => Ok(()),
+			}
+		}
+
+		/// Fail with `InsufficientBalance` if `depositor` cannot pay `amount` of `asset_id`,
+		/// when it is the native asset
+		fn ensure_can_pay_native(
+			asset_id: u32,
+			depositor: &T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			if asset_id == NATIVE_ASSET_ID {
+				let spendable = <T::Currency as fungible::Inspect<_>>::reducible_balance(
+					depositor,
+					Preservation::Expendable,
+					Fortitude::Polite,
+				);
+				ensure!(spendable >= amount, Error::<T>::InsufficientBalance);
+			}
+			Ok(())
+		}
+
+		/// Take deposited value into custody: lock it in the pool account or burn it
+		///
+		/// Native deposits are always transferred to the pool account.
+		fn transfer_to_pool(
+			asset_id: u32,
+			depositor: &T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			if asset_id == NATIVE_ASSET_ID {
+				Self::ensure_can_pay_native(asset_id, depositor, amount)?;
+				<T::Currency as fungible::Mutate<_>>::transfer(
+					depositor,
+					&Self::account_id(),
+					amount,
+					Preservation::Expendable,
+				)?;
+				return Ok(());
+			}
+
+			match Self::custody_mode(asset_id) {
+				CustodyMode::Pot => {
+					<T::Fungibles as fungibles::Mutate<_>>::transfer(
+						asset_id,
+						depositor,
+						&Self::account_id(),
+						amount,
+						Preservation::Expendable,
+					)?;
+				},
+				CustodyMode::BurnMint => {
+					<T::Fungibles as fungibles::Mutate<_>>::burn_from(
+						asset_id,
+						depositor,
+						amount,
+						Preservation::Expendable,
+						Precision::Exact,
+						Fortitude::Polite,
+					)?;
+				},
+			}
+
+			Ok(())
+		}
+
+		/// Release value from custody to a beneficiary
+		///
+		/// The native asset is paid from `Currency`. Other local assets are paid from
+		/// the pool account or minted, depending on their custody mode.
+		///
+		/// The beneficiary is always credited with `mint_into`, which creates its account
+		/// (taking a provider reference) if it does not exist yet; pool-held value is
+		/// burned from the pool first, so issuance is unchanged.
+		fn transfer_from_pool(
+			asset_id: u32,
+			beneficiary: &T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			let pool = Self::account_id();
+
+			// Isolate the transfer so a failure leaves no partial changes behind
+			with_storage_layer(|| {
+				if asset_id == NATIVE_ASSET_ID {
+					<T::Currency as fungible::Mutate<_>>::burn_from(
+						&pool,
+						amount,
+						Preservation::Expendable,
+						Precision::Exact,
+						Fortitude::Polite,
+					)?;
+					<T::Currency as fungible::Mutate<_>>::mint_into(beneficiary, amount)?;
+				} else {
+					if Self::custody_mode(asset_id) == CustodyMode::Pot {
+						<T::Fungibles as fungibles::Mutate<_>>::burn_from(
+							asset_id,
+							&pool,
+							amount,
+							Preservation::Expendable,
+							Precision::Exact,
+							Fortitude::Polite,
+						)?;
+					}
+					<T::Fungibles as fungibles::Mutate<_>>::mint_into(asset_id, beneficiary, amount)?;
+				}
+
+				Ok(())
+			})
+		}
+
+		/// Warn, once, that deposits are accumulating while withdrawals are impossible
+		fn warn_if_not_configured() {
+			if !VerifyingKeys::<T>::contains_key(CircuitId::Withdraw) &&
+				!NotConfiguredWarned::<T>::get()
+			{
+				NotConfiguredWarned::<T>::put(true);
+				log::warn!(target: LOG_TARGET, "deposits accepted without a verifying key");
+				Self::deposit_event(Event::BridgeNotFullyConfigured);
+			}
+		}
+
+		/// Store a new commitment and its metadata
+		///
+		/// Returns the index assigned to the commitment
+		pub(crate) fn insert_commitment(
+			commitment: H256,
+			depositor: &T::AccountId,
+			asset_id: u32,
+		) -> Result<u32, DispatchError> {
+			ensure!(!TreeRebuild::<T>::exists(), Error::<T>::TreeUnderMaintenance);
+			ensure!(!WindDowns::<T>::contains_key(asset_id), Error::<T>::AssetWindingDown);
+			// The zero hash pads empty tree slots
+			ensure!(
+				!primitives::is_reserved(&commitment),
+				Error::<T>::ReservedCommitmentValue
+			);
+			// Ensure commitment doesn't already exist
+			ensure!(
+				!Commitments::<T>::contains_key(&commitment),
+				Error::<T>::CommitmentAlreadyExists
+			);
+
+			let index = CommitmentCount::<T>::get();
+			ensure!((index as u64) < 1u64 << TREE_DEPTH, Error::<T>::TreeFull);
+			CommitmentCount::<T>::put(
+				index.checked_add(1).ok_or(Error::<T>::AmountOverflow)?
+			);
+
+			Commitments::<T>::insert(&commitment, CommitmentData {
+				block_number: <frame_system::Pallet<T>>::block_number(),
+				depositor: depositor.clone(),
+				asset_id,
+			});
+			MerkleLeaves::<T>::insert(index, commitment);
+			CommitmentIndex::<T>::insert(&commitment, index);
+			Self::journal(Action::LeafInserted { leaf_index: index, commitment });
+			Self::note_joined_anonymity_set(asset_id, 1);
+			if !T::BatchTreeInsertion::get() {
+				Self::fold_pending_leaves();
+			} else {
+				T::Metrics::pending_leaves(index + 1 - TreeSize::<T>::get());
+			}
+
+			Ok(index)
+		}
+
+		/// Hash the leaves not yet in the tree into `FilledSubtrees` and `MerkleRoot`
+		///
+		/// The frontier is read and written once however many leaves are pending, which is
+		/// what batching a block's deposits saves.
+		pub(crate) fn fold_pending_leaves() {
+			let count = CommitmentCount::<T>::get();
+			let mut next = TreeSize::<T>::get();
+			// A rebuild owns the frontier until it is done
+			if next >= count || TreeRebuild::<T>::exists() {
+				return;
+			}
+
+			let mut filled_subtrees: [H256; TREE_DEPTH] =
+				core::array::from_fn(|level| FilledSubtrees::<T>::get(level as u8));
+			let mut root = MerkleRoot::<T>::get();
+			let mut nodes = BTreeMap::new();
+			while next < count {
+				let leaf = MerkleLeaves::<T>::get(next).unwrap_or_default();
+				root = Self::append_cached(&mut filled_subtrees, next, leaf, &mut nodes);
+				next += 1;
+			}
+
+			for (level, node) in filled_subtrees.iter().enumerate() {
+				FilledSubtrees::<T>::insert(level as u8, node);
+			}
+			Self::store_nodes(nodes);
+			MerkleRoot::<T>::put(root);
+			TreeSize::<T>::put(next);
+			Self::record_root(root);
+			T::Metrics::pending_leaves(0);
+		}
+
+		/// Hash `leaf` into the tree at `leaf_index`, collecting the inner nodes it rehashes
+		/// into `nodes` so each is stored once however many leaves pass through it
+		fn append_cached(
+			filled_subtrees: &mut [H256; TREE_DEPTH],
+			leaf_index: u32,
+			leaf: H256,
+			nodes: &mut BTreeMap<(u8, u32), H256>,
+		) -> H256 {
+			merkle_tree::append_leaf_with_nodes::<T::Hasher>(
+				filled_subtrees,
+				leaf_index,
+				leaf,
+				|level, index, node| {
+					nodes.insert((level, index), node);
+				},
+			)
+		}
+
+		/// Write the inner nodes collected by `append_cached` to `MerkleNodes`
+		fn store_nodes(nodes: BTreeMap<(u8, u32), H256>) {
+			for ((level, index), node) in nodes {
+				MerkleNodes::<T>::insert(level, index, node);
+			}
+		}
+
+		/// Record a new merkle root in `RootHistory`, evicting the oldest one
+		pub(crate) fn record_root(root: H256) {
+			let sequence = RootSequence::<T>::get().wrapping_add(1);
+			let slot = sequence % T::RootHistorySize::get().max(1);
+			RootHistory::<T>::insert(slot, RootRecord {
+				root,
+				sequence,
+				oldest_leaf_block: Self::first_leaf_block(),
+				newest_leaf_block: frame_system::Pallet::<T>::block_number().saturated_into(),
+			});
+			RootSequence::<T>::put(sequence);
+			Self::journal(Action::RootUpdated { root });
+		}
+
+		/// Record `action` in `RecentActions`, in place of the action `MAX_RECENT_ACTIONS`
+		/// before it
+		fn journal(action: Action) {
+			let sequence = ActionSequence::<T>::get();
+			RecentActions::<T>::insert(Self::action_slot(sequence), ActionRecord {
+				sequence,
+				block_number: frame_system::Pallet::<T>::block_number(),
+				action,
+			});
+			ActionSequence::<T>::put(sequence.wrapping_add(1));
+		}
+
+		/// Slot of `RecentActions` action `sequence` is recorded in
+		fn action_slot(sequence: u64) -> u32 {
+			(sequence % u64::from(MAX_RECENT_ACTIONS)) as u32
+		}
+
+		/// Actions from sequence number `since` on, oldest first
+		///
+		/// Empty when `since` is past the last action. `None` when the journal no longer
+		/// holds action `since`: the caller missed more than `MAX_RECENT_ACTIONS` actions and
+		/// has to resync from events. Backs the `PrivacyBridgeApi` runtime API.
+		pub fn recent_actions(since: u64) -> Option<Vec<ActionRecord<BlockNumberFor<T>>>> {
+			let next = ActionSequence::<T>::get();
+			if since < next.saturating_sub(u64::from(MAX_RECENT_ACTIONS)) {
+				return None;
+			}
+			Some(
+				(since..next)
+					.filter_map(|sequence| RecentActions::<T>::get(Self::action_slot(sequence)))
+					.collect(),
+			)
+		}
+
+		/// Block the first leaf of the tree was deposited in
+		///
+		/// Block 0 once its commitment metadata is pruned, which only makes the maximum
+		/// note age check stricter.
+		fn first_leaf_block() -> u32 {
+			MerkleLeaves::<T>::get(0)
+				.and_then(Commitments::<T>::get)
+				.map_or(0, |data| data.block_number.saturated_into())
+		}
+
+		/// Record of `root` in `RootHistory`, if it is one of the last `RootHistorySize` roots
+		fn known_root(root: H256) -> Option<RootRecord> {
+			(0..T::RootHistorySize::get().max(1))
+				.filter_map(RootHistory::<T>::get)
+				.find(|record| record.root == root)
+		}
+
+		/// Whether `root` is still in `RootHistory`, so proofs against it are accepted
+		pub fn is_known_root(root: H256) -> bool {
+			Self::known_root(root).is_some()
+		}
+
+		/// Number of further root updates `root` stays in `RootHistory` for
+		///
+		/// `Some(0)` means the next update evicts it; `None` that it is not (or no longer)
+		/// a recent root. Backs the `PrivacyBridgeApi` runtime API, so wallets can tell how
+		/// long a proof against `root` remains usable.
+		pub fn root_ttl(root: H256) -> Option<u32> {
+			let size = T::RootHistorySize::get().max(1);
+			let current = RootSequence::<T>::get();
+			(0..size)
+				.filter_map(RootHistory::<T>::get)
+				.filter(|record| record.root == root)
+				.map(|record| record.sequence.wrapping_add(size - 1).wrapping_sub(current))
+				.max()
+		}
+
+		/// Roots in `RootHistory`, newest first
+		///
+		/// Withdrawal proofs are accepted against any of them. Backs the `PrivacyBridgeApi`
+		/// runtime API.
+		pub fn known_roots() -> Vec<H256> {
+			let mut records: Vec<RootRecord> =
+				(0..T::RootHistorySize::get().max(1)).filter_map(RootHistory::<T>::get).collect();
+			records.sort_by(|a, b| b.sequence.cmp(&a.sequence));
+			records.into_iter().map(|record| record.root).collect()
+		}
+
+		/// Siblings of leaf `leaf_index` from the leaf up, leading to `merkle_root`
+		///
+		/// Reads one sibling per level from `MerkleLeaves` and `MerkleNodes`, so the cost
+		/// depends on `TREE_DEPTH` only. `None` if the leaf is not hashed into the tree yet,
+		/// as with `BatchTreeInsertion` until the end of its block, or while the node cache
+		/// is incomplete: during a tree rebuild, or before one filled it. Backs the
+		/// `PrivacyBridgeApi` runtime API.
+		pub fn merkle_path(leaf_index: u32) -> Option<Vec<H256>> {
+			let size = TreeSize::<T>::get();
+			if leaf_index >= size ||
+				TreeRebuild::<T>::exists() ||
+				!MerkleNodes::<T>::contains_key(1, 0)
+			{
+				return None;
+			}
+
+			let mut path = Vec::with_capacity(TREE_DEPTH);
+			let mut index = leaf_index;
+			let mut zero = primitives::RESERVED_HASH;
+			for level in 0..TREE_DEPTH {
+				let sibling = index ^ 1;
+				let node = if level == 0 {
+					(sibling < size).then(|| MerkleLeaves::<T>::get(sibling)).flatten()
+				} else {
+					MerkleNodes::<T>::get(level as u8, sibling)
+				};
+				path.push(node.unwrap_or(zero));
+				zero = T::Hasher::hash_pair(&zero, &zero);
+				index /= 2;
+			}
+			Some(path)
+		}
+
+		/// Nullifiers spent from block `block` on, with the block each was spent in
+		///
+		/// Returned in block order. A block's spends are never split across pages: blocks
+		/// are added until at least `limit` entries are collected, so the next page starts
+		/// at the block after the last one returned. Backs the `PrivacyBridgeApi` runtime
+		/// API for watchers comparing spends across deployments.
+		pub fn nullifiers_spent_since(
+			block: BlockNumberFor<T>,
+			limit: u32,
+		) -> Vec<(H256, BlockNumberFor<T>)> {
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut spent = Vec::new();
+			let mut at = block;
+			while at <= now && spent.len() < limit as usize {
+				spent.extend(SpentNullifiers::<T>::iter_key_prefix(at).map(|nullifier| (nullifier, at)));
+				if at == now {
+					break;
+				}
+				at += One::one();
+			}
+			spent
+		}
+
+		/// Receipt ID of a withdrawal of `nullifier` in the current extrinsic
+		pub(crate) fn withdrawal_receipt_id(nullifier: H256) -> H256 {
+			crate::primitives::withdrawal_receipt_id(
+				&nullifier,
+				frame_system::Pallet::<T>::block_number().saturated_into(),
+				frame_system::Pallet::<T>::extrinsic_index().unwrap_or_default(),
+			)
+		}
+
+		/// Keep the record of a successful withdrawal, returning its receipt ID
+		fn record_withdrawal(
+			nullifier: H256,
+			asset_id: u32,
+			amount: u128,
+			fee: u128,
+			destination: Option<VersionedDestination>,
+		) -> H256 {
+			let receipt_id = Self::withdrawal_receipt_id(nullifier);
+			WithdrawalRecords::<T>::insert(receipt_id, WithdrawalRecord {
+				nullifier,
+				asset_id,
+				amount,
+				fee,
+				block_number: frame_system::Pallet::<T>::block_number(),
+				extrinsic_index: frame_system::Pallet::<T>::extrinsic_index().unwrap_or_default(),
+				destination,
+			});
+			receipt_id
+		}
+
+		/// Record of the withdrawal with receipt ID `receipt_id`
+		///
+		/// Receipt IDs are in the `AssetUnshielded` and `WithdrawalExported` events. Backs
+		/// the `PrivacyBridgeApi` runtime API for integrators reconciling their ledgers.
+		pub fn withdrawal_by_id(receipt_id: H256) -> Option<WithdrawalRecord<BlockNumberFor<T>>> {
+			WithdrawalRecords::<T>::get(receipt_id)
+		}
+
+		/// Registered alert tags of `queries` whose nullifiers were spent from block
+		/// `spent_since` on, in block order
+		///
+		/// Walks the spend-block index once, hashing each spent nullifier with every query's
+		/// salt, so the cost grows with the spends and the number of salts, not the tags.
+		/// Tags nobody registered are never reported. Backs the `PrivacyBridgeApi` runtime
+		/// API for watch-only services.
+		pub fn match_alert_tags(
+			spent_since: BlockNumberFor<T>,
+			queries: Vec<AlertTagQuery>,
+		) -> Vec<AlertMatch<BlockNumberFor<T>>> {
+			let mut watched: BTreeMap<[u8; 32], BTreeSet<H256>> = BTreeMap::new();
+			for query in queries {
+				let registered = query
+					.tags
+					.into_iter()
+					.filter(|tag| AlertTagRegistrations::<T>::contains_key(tag));
+				watched.entry(query.salt).or_default().extend(registered);
+			}
+			watched.retain(|_, tags| !tags.is_empty());
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut matches = Vec::new();
+			let mut at = spent_since;
+			while at <= now && !watched.is_empty() {
+				for nullifier in SpentNullifiers::<T>::iter_key_prefix(at) {
+					for (salt, tags) in watched.iter() {
+						let tag = crate::primitives::alert_tag(&nullifier, salt);
+						if tags.contains(&tag) {
+							matches.push(AlertMatch { tag, block_number: at });
+						}
+					}
+				}
+				if at == now {
+					break;
+				}
+				at += One::one();
+			}
+			matches
+		}
+
+		/// Public view of a commitment in the tree, without its depositor
+		///
+		/// Backs the `PrivacyBridgeApi` runtime API.
+		pub fn commitment_info(commitment: H256) -> Option<PublicCommitmentInfo<BlockNumberFor<T>>> {
+			let data = Commitments::<T>::get(commitment)?;
+			Some(PublicCommitmentInfo {
+				block_number: data.block_number,
+				asset_id: data.asset_id,
+				leaf_index: CommitmentIndex::<T>::get(commitment)?,
+			})
+		}
+
+		/// Amount and note key of the `index`-th synthetic note of `force_populate_tree`
+		#[cfg(any(feature = "runtime-benchmarks", test))]
+		pub fn synthetic_note(index: u32) -> (u128, [u8; 32]) {
+			let mut note_key = [0xffu8; 32];
+			note_key[..4].copy_from_slice(&index.to_le_bytes());
+			(1, note_key)
+		}
+
+		/// Append `leaves` synthetic native notes to the tree, bypassing dispatch
+		///
+		/// Leaf `i` is the note of `synthetic_note(i)`, deposited by the pool account. The
+		/// resulting storage is that of as many `deposit`s with immediate tree insertion,
+		/// but the frontier is loaded and stored once, so benchmarks and tests can build
+		/// trees of 100k+ leaves.
+		#[cfg(any(feature = "runtime-benchmarks", test))]
+		pub fn force_populate_tree(leaves: u32) -> DispatchResult {
+			Self::fold_pending_leaves();
+			let start = CommitmentCount::<T>::get();
+			let end = start.checked_add(leaves).ok_or(Error::<T>::TreeFull)?;
+			ensure!((end as u64) <= 1u64 << TREE_DEPTH, Error::<T>::TreeFull);
+			if leaves == 0 {
+				return Ok(());
+			}
+
+			let depositor = Self::account_id();
+			let block_number = <frame_system::Pallet<T>>::block_number();
+			let history = T::RootHistorySize::get().max(1);
+			let mut sequence = RootSequence::<T>::get();
+			let newest_leaf_block: u32 = block_number.saturated_into();
+			let mut filled_subtrees: [H256; TREE_DEPTH] =
+				core::array::from_fn(|level| FilledSubtrees::<T>::get(level as u8));
+			let mut root = MerkleRoot::<T>::get();
+			let mut nodes = BTreeMap::new();
+			let mut total = 0u128;
+
+			for index in start..end {
+				let (amount, note_key) = Self::synthetic_note(index);
+				let commitment = Self::generate_commitment(amount, NATIVE_ASSET_ID, &note_key);
+				Commitments::<T>::insert(&commitment, CommitmentData {
+					block_number,
+					depositor: depositor.clone(),
+					asset_id: NATIVE_ASSET_ID,
+				});
+				MerkleLeaves::<T>::insert(index, commitment);
+				CommitmentIndex::<T>::insert(&commitment, index);
+				Self::record_for_abandonment(commitment, NATIVE_ASSET_ID, amount);
+				total = total.saturating_add(amount);
+
+				// Every deposit records its root; only the last `history` survive
+				root = Self::append_cached(&mut filled_subtrees, index, commitment, &mut nodes);
+				sequence = sequence.wrapping_add(1);
+				if end - index <= history {
+					RootHistory::<T>::insert(sequence % history, RootRecord {
+						root,
+						sequence,
+						oldest_leaf_block: Self::first_leaf_block(),
+						newest_leaf_block,
+					});
+				}
+			}
+
+			for (level, node) in filled_subtrees.iter().enumerate() {
+				FilledSubtrees::<T>::insert(level as u8, node);
+			}
+			Self::store_nodes(nodes);
+			MerkleRoot::<T>::put(root);
+			RootSequence::<T>::put(sequence);
+			TreeSize::<T>::put(end);
+			CommitmentCount::<T>::put(end);
+			Self::note_shielded(NATIVE_ASSET_ID, total)?;
+			Self::note_joined_anonymity_set(NATIVE_ASSET_ID, leaves);
+			Self::warn_if_not_configured();
+
+			Ok(())
+		}
+
+		/// Weight of `deposit` under the configured tree insertion mode
+		pub fn deposit_weight() -> Weight {
+			if T::BatchTreeInsertion::get() {
+				T::WeightInfo::deposit_batched()
+			} else {
+				T::WeightInfo::deposit(TREE_DEPTH as u32)
+			}
+		}
+
+		/// Weight of `rebuild_tree` rehashing `count` leaves
+		///
+		/// Every leaf is read and hashed through all levels; the frontier is loaded and
+		/// stored once.
+		pub fn tree_rebuild_weight(count: u32) -> Weight {
+			// Hashing cost per level, the slope of the `deposit` weight
+			let per_leaf = Weight::from_parts(2_500_000 * TREE_DEPTH as u64, 0)
+				.saturating_add(T::DbWeight::get().reads_writes(1, TREE_DEPTH as u64));
+			Weight::from_parts(10_000, 0)
+				.saturating_add(per_leaf.saturating_mul(count as u64))
+				.saturating_add(Self::tree_fold_weight())
+		}
+
+		/// Weight of folding a block's leaves in `on_finalize`, on top of the per-leaf
+		/// hashing charged by `deposit_batched`
+		fn tree_fold_weight() -> Weight {
+			let depth = TREE_DEPTH as u64;
+			T::DbWeight::get().reads_writes(depth + 4, depth + 4)
+		}
+
+		/// Check a bundle is non-empty, ordered by strictly increasing asset ID and holds no
+		/// zero amount (which would read as padding, see `primitives::bundle_assets`)
+		pub(crate) fn ensure_valid_bundle(assets: &NoteBundle) -> DispatchResult {
+			ensure!(!assets.is_empty(), Error::<T>::InvalidBundle);
+			ensure!(assets.iter().all(|(_, amount)| *amount > 0), Error::<T>::InvalidBundle);
+			ensure!(
+				assets.windows(2).all(|pair| pair[0].0 < pair[1].0),
+				Error::<T>::InvalidBundle
+			);
+			Ok(())
+		}
+
+		/// Check a swap leg before its note is spent
+		///
+		/// The envelope must be for the current circuits, and the spent note a single-asset
+		/// note of the leg's asset, so the output note is of the asset the proof spent.
+		fn ensure_valid_swap_leg(leg: &SwapLeg) -> DispatchResult {
+			let setup = VerifyingKeySetup::<T>::get().ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			ensure!(
+				leg.proof_envelope.circuit_version == setup.circuit_version,
+				Self::proof_failure(Error::<T>::CircuitVersionMismatch)
+			);
+			ensure!(!BundleNotes::<T>::get(&leg.commitment), Error::<T>::NoteKindMismatch);
+			if let Some(data) = Commitments::<T>::get(&leg.commitment) {
+				ensure!(data.asset_id == leg.asset_id, Error::<T>::SwapAssetMismatch);
+			}
+			Self::ensure_note_age(leg.asset_id, None)
+		}
+
+		/// Asset IDs of a bundle, for its events
+		fn bundle_asset_ids(assets: &NoteBundle) -> BoundedVec<u32, ConstU32<MAX_BUNDLE_SIZE>> {
+			BoundedVec::truncate_from(assets.iter().map(|(asset_id, _)| *asset_id).collect())
+		}
+
+		/// Check the anchor block hash of a withdrawal proof against `MaxProofAge`
+		///
+		/// Without `MaxProofAge` any anchor (or none) is accepted. With it, the anchor must
+		/// be the hash of one of the last `MaxProofAge` blocks; the window starts at the
+		/// parent block, whose hash is the latest known.
+		pub(crate) fn ensure_proof_anchor(anchor_block_hash: Option<H256>) -> DispatchResult {
+			let Some(max_age) = T::MaxProofAge::get() else {
+				return Ok(());
+			};
+			let anchor = anchor_block_hash
+				.ok_or_else(|| Self::proof_failure(Error::<T>::ProofAnchorRequired))?;
+			// Unknown and pruned blocks read as the zero hash
+			ensure!(!anchor.is_zero(), Self::proof_failure(Error::<T>::ProofAnchorExpired));
+
+			let mut block = frame_system::Pallet::<T>::block_number();
+			for _ in 0..max_age {
+				if block.is_zero() {
+					break;
+				}
+				block -= One::one();
+				if frame_system::Pallet::<T>::block_hash(block).as_ref() == anchor.as_bytes() {
+					return Ok(());
+				}
+			}
+			Err(Self::proof_failure(Error::<T>::ProofAnchorExpired))
+		}
+
+		/// Check the proof of a withdrawal of one note, made against `merkle_root`
+		///
+		/// The anchor must be recent (see `ensure_proof_anchor`). The root must be in
+		/// `RootHistory`, or be the current root of the note's denomination pool, and meet
+		/// the asset's note age bounds.
+		#[allow(clippy::too_many_arguments)]
+		fn check_withdrawal_proof(
+			proof: &[u8],
+			nullifier: &H256,
+			commitment: &H256,
+			merkle_root: &H256,
+			asset_id: u32,
+			amount: u128,
+			recipient_hash: &H256,
+			anchor_block_hash: Option<H256>,
+		) -> DispatchResult {
+			Self::ensure_proof_anchor(anchor_block_hash)?;
+			match DenominationOf::<T>::get(commitment) {
+				Some(denomination) => {
+					let pool = DenominationTrees::<T>::get(asset_id, denomination);
+					ensure!(*merkle_root == pool.root, Error::<T>::UnknownMerkleRoot);
+					Self::ensure_note_age(asset_id, None)?;
+				},
+				None => Self::ensure_note_age(asset_id, Some(*merkle_root))?,
+			}
+			Self::verify_withdrawal_proof_against(
+				proof,
+				nullifier,
+				commitment,
+				merkle_root,
+				asset_id,
+				amount,
+				recipient_hash,
+			)
+		}
+
+		/// Pool validity of an unsigned withdrawal whose fee was checked: its nullifier is
+		/// unused and its proof, paying whoever `recipient_hash` commits to, verifies
+		///
+		/// The transaction lives for one block more than the root updates `merkle_root` has
+		/// left in `RootHistory` (`root_ttl`), one block for the root of a denomination pool,
+		/// which the pool's next deposit replaces. The pool revalidates it on every block, so
+		/// it is dropped sooner if several updates in a block evict the root.
+		fn validate_unsigned_withdrawal(
+			proof: &[u8],
+			nullifier: &H256,
+			commitment: &H256,
+			merkle_root: &H256,
+			asset_id: u32,
+			amount: u128,
+			recipient_hash: &H256,
+		) -> TransactionValidity {
+			if let Err(error) = Self::ensure_spendable(*nullifier, *commitment) {
+				return if error == Error::<T>::NullifierAlreadyUsed.into() {
+					InvalidTransaction::Stale.into()
+				} else {
+					InvalidTransaction::Call.into()
+				};
+			}
+			if let Err(error) = Self::check_withdrawal_proof(
+				proof,
+				nullifier,
+				commitment,
+				merkle_root,
+				asset_id,
+				amount,
+				recipient_hash,
+				None,
+			) {
+				return if error == Error::<T>::UnknownMerkleRoot.into() {
+					InvalidTransaction::Stale.into()
+				} else {
+					InvalidTransaction::BadProof.into()
+				};
+			}
+
+			let root_ttl = Self::root_ttl(*merkle_root).unwrap_or_default();
+			ValidTransaction::with_tag_prefix("PrivacyBridgeWithdrawal")
+				.priority(UNSIGNED_WITHDRAWAL_PRIORITY)
+				.and_provides(nullifier)
+				.longevity(u64::from(root_ttl) + 1)
+				.propagate(true)
+				.build()
+		}
+
+		/// Fee an unsigned `withdraw` of `amount` pays the treasury: its `UnsignedRelayFee`
+		fn unsigned_withdrawal_fee(amount: u128) -> Result<u128, DispatchError> {
+			let fee = T::UnsignedRelayFee::get().ok_or(Error::<T>::UnsignedWithdrawalsDisabled)?;
+			ensure!(fee <= amount, Error::<T>::FeeExceedsAmount);
+			Ok(fee)
+		}
+
+		/// Check an unsigned relayed withdrawal pays the treasury its `UnsignedRelayFee`
+		fn ensure_unsigned_relay_fee(relayer: &T::AccountId, fee: u128) -> DispatchResult {
+			let minimum =
+				T::UnsignedRelayFee::get().ok_or(Error::<T>::UnsignedWithdrawalsDisabled)?;
+			ensure!(
+				*relayer == T::TreasuryAccount::get() && fee >= minimum,
+				Error::<T>::UnsignedRelayFeeNotPaid
+			);
+			Ok(())
+		}
+
+		/// Verify the proof of spending `commitment`, a leaf of the tree at `merkle_root`
+		/// holding `amount` of `asset_id`, with `nullifier`, paying whoever `recipient_hash`
+		/// commits to
+		///
+		/// A note pays out only what it was shielded with, in its asset, and a proof copied
+		/// from a pending transaction does not pay whoever resubmits it.
+		#[allow(clippy::too_many_arguments)]
+		fn verify_withdrawal_proof_against(
+			proof: &[u8],
+			nullifier: &H256,
+			commitment: &H256,
+			merkle_root: &H256,
+			asset_id: u32,
+			amount: u128,
+			recipient_hash: &H256,
+		) -> DispatchResult {
+			let vk = Self::verifying_key_for_proofs()?;
+			let verified = Self::prepared_key_for(CircuitId::Withdraw, &vk).and_then(|key| {
+				T::ProofVerifier::verify(
+					&key,
+					proof,
+					nullifier,
+					commitment,
+					merkle_root,
+					asset_id,
+					amount,
+					recipient_hash,
+				)
+			});
+			match verified {
+				Ok(true) => Ok(()),
+				Ok(false) => Err(Self::proof_failure(Error::<T>::InvalidProof)),
+				Err(error) => {
+					log::debug!(target: LOG_TARGET, "proof of {nullifier:?} malformed: {error:?}");
+					Err(Self::proof_failure(Error::<T>::InvalidProof))
+				},
+			}
+		}
+
+		/// Verify the proof of splitting the note of `nullifier`, a leaf of the tree at
+		/// `merkle_root`, into the notes of `outputs`
+		fn verify_transfer_proof(
+			proof: &[u8],
+			nullifier: &H256,
+			merkle_root: &H256,
+			asset_id: u32,
+			outputs: &[H256; 2],
+		) -> DispatchResult {
+			let vk = Self::verifying_key_for(CircuitId::Transfer)
+				.ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			let verified = Self::prepared_key_for(CircuitId::Transfer, &vk).and_then(|key| {
+				T::ProofVerifier::verify_transfer(
+					&key,
+					proof,
+					nullifier,
+					merkle_root,
+					asset_id,
+					outputs,
+				)
+			});
+			match verified {
+				Ok(true) => Ok(()),
+				Ok(false) => Err(Self::proof_failure(Error::<T>::InvalidProof)),
+				Err(error) => {
+					log::debug!(target: LOG_TARGET, "proof of {nullifier:?} malformed: {error:?}");
+					Err(Self::proof_failure(Error::<T>::InvalidProof))
+				},
+			}
+		}
+
+		/// Verify the proof of spending the notes `commitments` with `nullifiers`, leaves of
+		/// the tree at the current `MerkleRoot`, holding `amount` together for the recipient
+		/// of `recipient_hash`
+		fn verify_join_proof(
+			proof: &[u8],
+			nullifiers: &[H256; 2],
+			commitments: &[H256; 2],
+			asset_id: u32,
+			amount: u128,
+			recipient_hash: &H256,
+		) -> DispatchResult {
+			let vk = Self::verifying_key_for(CircuitId::JoinSplit)
+				.ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			let root = MerkleRoot::<T>::get();
+			let verified = Self::prepared_key_for(CircuitId::JoinSplit, &vk).and_then(|key| {
+				T::ProofVerifier::verify_join(
+					&key,
+					proof,
+					nullifiers,
+					commitments,
+					&root,
+					asset_id,
+					amount,
+					recipient_hash,
+				)
+			});
+			match verified {
+				Ok(true) => Ok(()),
+				Ok(false) => Err(Self::proof_failure(Error::<T>::InvalidProof)),
+				Err(error) => {
+					log::debug!(
+						target: LOG_TARGET,
+						"proof of {nullifiers:?} malformed: {error:?}"
+					);
+					Err(Self::proof_failure(Error::<T>::InvalidProof))
+				},
+			}
+		}
+
+		/// Verify the proof of spending the bundle note `commitment`, holding `assets`, with
+		/// `nullifier`, paying whoever `recipient_hash` commits to
+		fn verify_bundle_proof(
+			proof: &[u8],
+			nullifier: &H256,
+			commitment: &H256,
+			assets: &NoteBundle,
+			recipient_hash: &H256,
+		) -> DispatchResult {
+			let vk =
+				Self::verifying_key_for(CircuitId::Bundle).ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			let verified = Self::prepared_key_for(CircuitId::Bundle, &vk).and_then(|key| {
+				T::ProofVerifier::verify_bundle(
+					&key,
+					proof,
+					nullifier,
+					commitment,
+					assets,
+					recipient_hash,
+				)
+			});
+			match verified {
+				Ok(true) => Ok(()),
+				Ok(false) => Err(Self::proof_failure(Error::<T>::InvalidProof)),
+				Err(error) => {
+					log::debug!(target: LOG_TARGET, "proof of {nullifier:?} malformed: {error:?}");
+					Err(Self::proof_failure(Error::<T>::InvalidProof))
+				},
+			}
+		}
+
+		/// Verify the proof of a `shielded_swap` leg: its note, spent with its nullifier,
+		/// becomes its output note
+		fn verify_swap_proof(leg: &SwapLeg) -> DispatchResult {
+			let vk =
+				Self::verifying_key_for(CircuitId::Swap).ok_or(Error::<T>::VerifyingKeyNotSet)?;
+			let verified = Self::prepared_key_for(CircuitId::Swap, &vk).and_then(|key| {
+				T::ProofVerifier::verify_swap(
+					&key,
+					&leg.proof_envelope.proof,
+					&leg.nullifier,
+					&leg.commitment,
+					&leg.output_commitment,
+				)
+			});
+			match verified {
+				Ok(true) => Ok(()),
+				Ok(false) => Err(Self::proof_failure(Error::<T>::InvalidProof)),
+				Err(error) => {
+					let nullifier = leg.nullifier;
+					log::debug!(target: LOG_TARGET, "proof of {nullifier:?} malformed: {error:?}");
+					Err(Self::proof_failure(Error::<T>::InvalidProof))
+				},
+			}
+		}
+
+		/// Report a withdrawal refused for its proof and return the error
+		fn proof_failure(error: Error<T>) -> DispatchError {
+			log::warn!(target: LOG_TARGET, "proof refused: {error:?}");
+			T::Metrics::proof_failure();
+			error.into()
+		}
 
-			// Ensure commitment doesn't already exist
-			ensure!(
-				!Commitments::<T>::contains_key(&commitment),
-				Error::<T>::CommitmentAlreadyExists
-			);
+		/// Log a failed `operation` on the note of `subject` (nullifier or commitment)
+		fn warn_failure(operation: &'static str, subject: H256) -> impl FnOnce(&DispatchError) {
+			move |error| {
+				log::warn!(target: LOG_TARGET, "{operation} of {subject:?} failed: {error:?}")
+			}
+		}
 
-			// Store commitment metadata (note: amount is NOT stored!)
-			let commitment_data = CommitmentData {
-				block_number: <frame_system::Pallet<T>>::block_number(),
-				depositor: who.clone(),
-				asset_id,
+		/// Check the note age bounds of `asset_id` against the leaves under `merkle_root`
+		///
+		/// A given root must be in `RootHistory`, whatever the asset. Assets without bounds
+		/// also accept no root; for the others the bounds must hold for both ends of the
+		/// root's leaf block range, so they hold for the spent leaf whichever it is.
+		pub(crate) fn ensure_note_age(asset_id: u32, merkle_root: Option<H256>) -> DispatchResult {
+			let record = match merkle_root {
+				Some(root) => Some(Self::known_root(root).ok_or(Error::<T>::UnknownMerkleRoot)?),
+				None => None,
 			};
+			let Some(asset) = Self::registered_asset_by_local(asset_id) else {
+				return Ok(());
+			};
+			if asset.min_note_age.is_none() && asset.max_note_age.is_none() {
+				return Ok(());
+			}
+			let record = record.ok_or(Error::<T>::NoteAgeRootRequired)?;
 
-			Commitments::<T>::insert(&commitment, commitment_data);
+			let now: u32 = frame_system::Pallet::<T>::block_number().saturated_into();
+			if let Some(min_age) = asset.min_note_age {
+				ensure!(
+					now.saturating_sub(record.newest_leaf_block) >= min_age,
+					Error::<T>::NoteTooYoung
+				);
+			}
+			if let Some(max_age) = asset.max_note_age {
+				ensure!(
+					now.saturating_sub(record.oldest_leaf_block) <= max_age,
+					Error::<T>::NoteTooOld
+				);
+			}
+			Ok(())
+		}
 
-			// Increment commitment counter
-			let count = CommitmentCount::<T>::get();
-			CommitmentCount::<T>::put(
-				count.checked_add(1).ok_or(Error::<T>::AmountOverflow)?
-			);
+		/// Weight of verifying one withdrawal proof
+		pub fn proof_verification_weight() -> Weight {
+			T::WeightInfo::withdraw()
+		}
 
-			// Emit event
-			Self::deposit_event(Event::AssetShielded {
-				commitment,
-				asset_id,
-				depositor: who,
-				block_number: <frame_system::Pallet<T>>::block_number(),
-			});
+		/// Number of proofs `call` has verified: one per note it spends
+		pub fn proofs_verified(call: &Call<T>) -> u32 {
+			match call {
+				Call::withdraw { .. } |
+				Call::withdraw_to_parachain { .. } |
+				Call::withdraw_to_remote { .. } |
+				Call::queue_withdrawal_to_remote { .. } |
+				Call::withdraw_anchored { .. } |
+				Call::withdraw_bundle { .. } |
+				Call::withdraw_to_parachain_encoded { .. } |
+				Call::fulfill_proving_job { .. } |
+				Call::withdraw_split { .. } |
+				Call::private_transfer { .. } |
+				Call::withdraw_join { .. } => 1,
+				Call::shielded_swap { .. } => 2,
+				_ => 0,
+			}
+		}
 
-			// NOTE: In a real implementation, the shielded note would be returned to the user
-			// or encrypted and stored. For now, the user would reconstruct it from the event data
-			// and their secret randomness.
+		/// Proof verification weight used so far in this block
+		///
+		/// Only counts what `VerificationWeightUsed` holds for the current block. The pool
+		/// validates transactions as of the next block without running `on_initialize`, so
+		/// the weight left from the last block must not count against it.
+		pub fn verification_weight_used() -> Weight {
+			let (block, used) = VerificationWeightUsed::<T>::get();
+			if block == frame_system::Pallet::<T>::block_number() {
+				used
+			} else {
+				Weight::zero()
+			}
+		}
+
+		/// Verification weight used in this block once `proofs` more proofs are verified, if
+		/// it stays within `MaxVerificationWeightPerBlock`
+		pub fn verification_budget_after(proofs: u32) -> Option<Weight> {
+			let used = Self::verification_weight_used()
+				.saturating_add(Self::proof_verification_weight().saturating_mul(proofs.into()));
+			used.all_lte(T::MaxVerificationWeightPerBlock::get()).then_some(used)
+		}
 
+		/// Count the verification of `proofs` proofs against this block's budget
+		fn charge_verification(proofs: u32) -> DispatchResult {
+			let used =
+				Self::verification_budget_after(proofs).ok_or(Error::<T>::VerificationBudgetExhausted)?;
+			VerificationWeightUsed::<T>::put((frame_system::Pallet::<T>::block_number(), used));
 			Ok(())
 		}
 
-		/// Withdraw (unshield) an asset from the privacy pool
+		/// Check the note of `commitment` was shielded as `asset_id`
 		///
-		/// Week 1 MVP: Simple nullifier check (no zkSNARK proof yet)
-		/// Week 2+: Will require zkSNARK proof of commitment ownership
+		/// A note's asset is public from its deposit. A withdrawal claiming another asset
+		/// would pay out value of an asset the note never held.
+		pub(crate) fn ensure_note_asset(commitment: &H256, asset_id: u32) -> DispatchResult {
+			if let Some(data) = Commitments::<T>::get(commitment) {
+				ensure!(data.asset_id == asset_id, Error::<T>::NoteAssetMismatch);
+			}
+			Self::ensure_claim_window_open(asset_id)
+		}
+
+		/// Fail if notes of `asset_id` are no longer withdrawn with a proof: its wind-down
+		/// is past the claim window
+		pub(crate) fn ensure_claim_window_open(asset_id: u32) -> DispatchResult {
+			match WindDowns::<T>::get(asset_id) {
+				None | Some(WindDownPhase::ClaimWindow { .. }) => Ok(()),
+				Some(_) => Err(Error::<T>::ClaimWindowClosed.into()),
+			}
+		}
+
+		/// Consume a note: check and mark both its nullifier and its commitment
 		///
-		/// Parameters:
-		/// - `nullifier`: The nullifier hash (prevents double-spending)
-		/// - `amount`: Amount to withdraw (for Week 1 testing)
-		/// - `asset_id`: Asset identifier
+		/// Tracking spent commitments stops a note from being spent twice under two
+		/// different nullifiers, and lets abandoned-note sweeping skip spent notes.
+		pub(crate) fn spend_note(nullifier: H256, commitment: H256) -> DispatchResult {
+			Self::ensure_spendable(nullifier, commitment)?;
+
+			// Mark nullifier and commitment as used
+			Self::mark_nullifier_spent(nullifier);
+			SpentCommitments::<T>::insert(&commitment, true);
+			AbandonmentRecords::<T>::remove(&commitment);
+			if let Some(data) = Commitments::<T>::get(&commitment) {
+				Self::note_left_anonymity_set(data.asset_id);
+			}
+
+			Ok(())
+		}
+
+		/// Consume a note whose commitment stays hidden: check and mark its nullifier
 		///
-		/// Emits: `AssetUnshielded` event
-		#[pallet::call_index(1)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().reads_writes(1, 1))]
-		pub fn withdraw(
-			origin: OriginFor<T>,
-			nullifier: H256,
-			_amount: u128,
-			asset_id: u32,
-		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+		/// For spends proven against a root rather than a commitment (`private_transfer`),
+		/// the nullifier alone keeps the note from being spent twice: the circuits derive
+		/// the note's randomness from the secret of its nullifier, so a note has exactly one.
+		pub(crate) fn spend_nullifier(nullifier: H256) -> DispatchResult {
+			ensure!(!primitives::is_reserved(&nullifier), Error::<T>::ReservedCommitmentValue);
+			ensure!(!NullifierSet::<T>::get(&nullifier), Error::<T>::NullifierAlreadyUsed);
+			Self::mark_nullifier_spent(nullifier);
+
+			Ok(())
+		}
+
+		/// Mark `nullifier` as used, recording the block it was spent in
+		fn mark_nullifier_spent(nullifier: H256) {
+			NullifierSet::<T>::insert(&nullifier, true);
+			SpentNullifiers::<T>::insert(frame_system::Pallet::<T>::block_number(), &nullifier, ());
+			Self::journal(Action::NullifierSpent { nullifier });
+		}
 
+		/// Check a note can be consumed by `spend_note`, without marking it
+		pub(crate) fn ensure_spendable(nullifier: H256, commitment: H256) -> DispatchResult {
+			ensure!(!primitives::is_reserved(&nullifier), Error::<T>::ReservedCommitmentValue);
 			// Check that nullifier hasn't been used
 			ensure!(
 				!NullifierSet::<T>::get(&nullifier),
 				Error::<T>::NullifierAlreadyUsed
 			);
+			ensure!(
+				CommitmentIndex::<T>::contains_key(&commitment),
+				Error::<T>::CommitmentNotFound
+			);
+			ensure!(
+				!DeniedCommitments::<T>::get(&commitment),
+				Error::<T>::CommitmentDenied
+			);
+			ensure!(
+				!SpentCommitments::<T>::get(&commitment),
+				Error::<T>::NoteAlreadySpent
+			);
+			if let Some(data) = Commitments::<T>::get(&commitment) {
+				Self::ensure_claim_window_open(data.asset_id)?;
+			}
 
-			// Mark nullifier as used
-			NullifierSet::<T>::insert(&nullifier, true);
+			Ok(())
+		}
 
-			// Emit event
-			Self::deposit_event(Event::AssetUnshielded {
-				nullifier,
-				asset_id,
-				block_number: <frame_system::Pallet<T>>::block_number(),
-			});
+		/// Look up a registered asset by its local asset ID
+		pub fn registered_asset_by_local(local_id: u32) -> Option<RegisteredAsset> {
+			LocalAssetIndex::<T>::get(local_id).and_then(AssetRegistry::<T>::get)
+		}
 
-			// Week 1: No actual token transfer
-			// Week 2+: Verify zkSNARK proof
-			// Week 4+: Send tokens via XCM to destination parachain
+		/// Denominations of local asset `asset_id`, none for unregistered assets
+		fn local_denominations(asset_id: u32) -> Denominations {
+			Self::registered_asset_by_local(asset_id)
+				.map(|asset| asset.denominations)
+				.unwrap_or_default()
+		}
+
+		/// Denomination pool a deposit of `amount` joins, `None` when `denominations` is
+		/// empty and any amount is taken
+		fn denomination_pool(
+			denominations: &[u128],
+			amount: u128,
+		) -> Result<Option<u128>, DispatchError> {
+			if denominations.is_empty() {
+				return Ok(None);
+			}
+			ensure!(denominations.contains(&amount), Error::<T>::InvalidDenomination);
+			Ok(Some(amount))
+		}
 
+		/// Append `commitment` to the tree of its `denomination` pool of local asset
+		/// `asset_id`, if it joins one
+		fn insert_denominated(
+			asset_id: u32,
+			denomination: Option<u128>,
+			commitment: H256,
+		) -> DispatchResult {
+			let Some(denomination) = denomination else { return Ok(()) };
+			DenominationTrees::<T>::try_mutate(asset_id, denomination, |tree| -> DispatchResult {
+				ensure!((tree.leaves as u64) < 1u64 << TREE_DEPTH, Error::<T>::TreeFull);
+				tree.root = merkle_tree::append_leaf::<T::Hasher>(
+					&mut tree.filled_subtrees,
+					tree.leaves,
+					commitment,
+				);
+				DenominationLeaves::<T>::insert((asset_id, denomination), tree.leaves, commitment);
+				tree.leaves += 1;
+				Ok(())
+			})?;
+			DenominationOf::<T>::insert(commitment, denomination);
 			Ok(())
 		}
 
-		/// Week 3: Set the zkSNARK verifying key (governance/sudo only)
-		///
-		/// This should be called once during initialization with the verifying key
-		/// from the trusted setup ceremony.
+		/// Keep what is needed to sweep a note later if its asset has an abandonment period
 		///
-		/// Parameters:
-		/// - `vk_bytes`: Serialized verifying key
-		#[pallet::call_index(2)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
-		pub fn set_verifying_key(
-			origin: OriginFor<T>,
-			vk_bytes: Vec<u8>,
-		) -> DispatchResult {
-			ensure_root(origin)?;
+		/// The deposited amount is already public in the deposit call; it is only retained
+		/// for assets that opted into abandonment.
+		pub(crate) fn record_for_abandonment(commitment: H256, asset_id: u32, amount: u128) {
+			let opted_in = Self::registered_asset_by_local(asset_id)
+				.map_or(false, |asset| asset.abandonment_period.is_some());
 
-			let bounded_vk: BoundedVec<u8, ConstU32<4096>> = vk_bytes.try_into()
-				.map_err(|_| Error::<T>::InvalidProof)?; // Reuse error type
+			if opted_in {
+				AbandonmentRecords::<T>::insert(&commitment, AbandonmentRecord {
+					asset_id,
+					amount,
+					deposited_at: <frame_system::Pallet<T>>::block_number(),
+				});
+			}
+		}
+
+		/// Add shielded value to the per-asset total
+		pub(crate) fn note_shielded(asset_id: u32, amount: u128) -> DispatchResult {
+			TotalShielded::<T>::try_mutate(asset_id, |total| {
+				*total = total.checked_add(amount).ok_or(Error::<T>::AmountOverflow)?;
+				Ok(())
+			})
+		}
+
+		/// Remove unshielded value from the per-asset total
+		pub(crate) fn note_unshielded(asset_id: u32, amount: u128) {
+			TotalShielded::<T>::mutate(asset_id, |total| *total = total.saturating_sub(amount));
+		}
+
+		/// Add an XCM deposit of a reserve-backed asset to its reserve backing
+		pub(crate) fn note_backing_in(asset: &RegisteredAsset, amount: u128) -> DispatchResult {
+			if asset.reserve_location.is_none() {
+				return Ok(());
+			}
 
-			VerifyingKey::<T>::put(bounded_vk);
+			ReserveBacking::<T>::try_mutate(asset.local_id, |backing| {
+				*backing = backing.checked_add(amount).ok_or(Error::<T>::AmountOverflow)?;
+				Ok::<_, DispatchError>(())
+			})?;
+			Self::note_backing_moved(asset.local_id, amount);
 
 			Ok(())
 		}
 
-		/// Week 4: Register an XCM asset for cross-chain deposits
-		///
-		/// Allows governance to register assets from other parachains
+		/// Remove a cross-chain withdrawal of a reserve-backed asset from its reserve backing
 		///
-		/// Parameters:
-		/// - `asset_id`: XCM AssetId to register
-		/// - `min_deposit`: Minimum deposit amount
-		#[pallet::call_index(3)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(2))]
-		pub fn register_asset(
-			origin: OriginFor<T>,
-			asset_id: XcmAssetId,
-			min_deposit: u128,
-		) -> DispatchResult {
-			ensure_root(origin)?;
+		/// A withdrawal above the recorded backing is rejected: the pool cannot send
+		/// more than it holds on the reserve chain.
+		pub(crate) fn note_backing_out(asset: &RegisteredAsset, amount: u128) -> DispatchResult {
+			if asset.reserve_location.is_none() {
+				return Ok(());
+			}
 
-			// Get next local asset ID
-			let local_id = NextAssetId::<T>::get();
-			NextAssetId::<T>::put(local_id.checked_add(1).ok_or(Error::<T>::AmountOverflow)?);
+			ReserveBacking::<T>::try_mutate(asset.local_id, |backing| {
+				*backing = backing
+					.checked_sub(amount)
+					.ok_or(Error::<T>::InsufficientReserveBacking)?;
+				Ok::<_, DispatchError>(())
+			})?;
+			Self::note_backing_moved(asset.local_id, amount);
 
-			// Create registration
-			let mut registration = RegisteredAsset::new(asset_id.clone(), local_id);
-			registration.min_deposit = min_deposit;
+			Ok(())
+		}
+
+		/// Track backing moved within the block, raising an anomaly once above the threshold
+		fn note_backing_moved(asset_id: u32, amount: u128) {
+			let now = <frame_system::Pallet<T>>::block_number();
+			let before = match ReserveBackingMoved::<T>::get(asset_id) {
+				Some((block, moved)) if block == now => moved,
+				_ => 0,
+			};
+			let moved = before.saturating_add(amount);
+			ReserveBackingMoved::<T>::insert(asset_id, (now, moved));
+
+			// Raised once per block, when the threshold is first crossed
+			let threshold = T::ReserveBackingAlertThreshold::get();
+			if before <= threshold && moved > threshold {
+				log::warn!(
+					target: LOG_TARGET,
+					"reserve backing of asset {asset_id} moved by {moved} in the block"
+				);
+				Self::deposit_event(Event::ReserveBackingAnomaly { asset_id, moved });
+			}
+		}
 
-			// Store registration
-			AssetRegistry::<T>::insert(asset_id, registration);
+		/// Fail unless the bridge pays out withdrawals
+		///
+		/// `BridgePaused` when the whole bridge is paused, `WithdrawalsPaused` when only
+		/// withdrawals are.
+		pub(crate) fn ensure_withdrawals_enabled() -> DispatchResult {
+			match Paused::<T>::get() {
+				PauseLevel::AllPaused => Err(Error::<T>::BridgePaused.into()),
+				PauseLevel::WithdrawalsPaused => Err(Error::<T>::WithdrawalsPaused.into()),
+				PauseLevel::None | PauseLevel::DepositsPaused => Ok(()),
+			}
+		}
 
+		/// Fail with `BridgePaused` unless the bridge takes deposits
+		pub(crate) fn ensure_deposits_enabled() -> DispatchResult {
+			ensure!(!Paused::<T>::get().deposits_paused(), Error::<T>::BridgePaused);
 			Ok(())
 		}
 
-		/// Week 4: Cross-chain deposit via XCM
-		///
-		/// Called when assets are received from another parachain via XCM
-		/// Creates a commitment for the received assets
+		/// Whether a solvency check runs at the end of block `n`
+		fn solvency_check_due(n: BlockNumberFor<T>) -> bool {
+			let interval = T::SolvencyCheckInterval::get();
+			!interval.is_zero() && (n % interval).is_zero()
+		}
+
+		/// Upper bound of the weight of `check_solvency`
+		fn solvency_check_weight() -> Weight {
+			let per_asset = T::DbWeight::get().reads(5);
+			T::DbWeight::get()
+				.reads_writes(2, 2)
+				.saturating_add(per_asset.saturating_mul(T::MaxSolvencyChecks::get() as u64))
+		}
+
+		/// Compare what the pool owes with what it holds, for up to `MaxSolvencyChecks`
+		/// assets starting at `SolvencyCursor`
 		///
-		/// Parameters:
-		/// - `asset_id`: XCM AssetId being deposited
-		/// - `amount`: Amount received
-		/// - `origin`: Location of sender parachain
-		/// - `randomness`: Randomness for commitment
-		#[pallet::call_index(4)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(3))]
-		pub fn deposit_from_xcm(
-			origin: OriginFor<T>,
-			asset_id: XcmAssetId,
-			amount: u128,
-			origin_location: Location,
-			randomness: [u8; 32],
-		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+		/// An asset holding less than its user-owned shielded value raises
+		/// `SolvencyAlert` and trips the withdrawal circuit breaker. Holdings are:
+		/// - the native currency: the pool's reducible balance;
+		/// - `Pot` custody: the pool's balance of the asset;
+		/// - `BurnMint` custody: the reserve backing ledger, if the asset has a remote
+		///   reserve (otherwise shielded value is burned and nothing is held).
+		pub(crate) fn check_solvency() {
+			let next_asset_id = NextAssetId::<T>::get();
+			let mut asset_id = SolvencyCursor::<T>::get();
+			if asset_id >= next_asset_id {
+				asset_id = NATIVE_ASSET_ID;
+			}
 
-			// Look up registered asset
-			let registered = AssetRegistry::<T>::get(&asset_id)
-				.ok_or(Error::<T>::InvalidProof)?; // Reuse error
+			let checks = T::MaxSolvencyChecks::get().min(next_asset_id);
+			for _ in 0..checks {
+				if let Some((expected, actual)) = Self::solvency_of(asset_id) {
+					if actual < expected {
+						log::warn!(
+							target: LOG_TARGET,
+							"asset {asset_id} is insolvent: {actual} held for {expected} shielded"
+						);
+						Self::deposit_event(Event::SolvencyAlert { asset_id, expected, actual });
+						let level = Paused::<T>::get();
+						if !level.withdrawals_paused() {
+							let level = level.with_withdrawals_paused();
+							Paused::<T>::put(level);
+							Self::deposit_event(Event::PauseLevelSet { level });
+						}
+					}
+				}
 
-			// Check minimum deposit
-			ensure!(amount >= registered.min_deposit, Error::<T>::InvalidProof);
+				asset_id = asset_id.saturating_add(1);
+				if asset_id >= next_asset_id {
+					asset_id = NATIVE_ASSET_ID;
+				}
+			}
 
-			// Generate commitment using local asset ID
-			let commitment = crate::xcm_config::xcm_commitment_data(
-				amount,
-				registered.local_id,
-				&randomness,
-				&origin_location,
-			);
+			SolvencyCursor::<T>::put(asset_id);
+		}
 
-			// Ensure commitment doesn't already exist
-			ensure!(
-				!Commitments::<T>::contains_key(&commitment),
-				Error::<T>::CommitmentAlreadyExists
-			);
+		/// Value owed to note holders and value held for a local asset, if it is checked
+		fn solvency_of(asset_id: u32) -> Option<(u128, u128)> {
+			let expected = TotalShielded::<T>::get(asset_id)
+				.saturating_sub(ProtocolOwnedShielded::<T>::get(asset_id));
+			let pool = Self::account_id();
 
-			// Store commitment metadata
-			let commitment_data = CommitmentData {
-				block_number: <frame_system::Pallet<T>>::block_number(),
-				depositor: who.clone(),
-				asset_id: registered.local_id,
+			if asset_id == NATIVE_ASSET_ID {
+				let actual = <T::Currency as fungible::Inspect<_>>::reducible_balance(
+					&pool,
+					Preservation::Expendable,
+					Fortitude::Polite,
+				);
+				return Some((expected, actual));
+			}
+
+			let asset = Self::registered_asset_by_local(asset_id)?;
+			let actual = match asset.custody_mode {
+				CustodyMode::Pot => <T::Fungibles as fungibles::Inspect<_>>::balance(asset_id, &pool),
+				CustodyMode::BurnMint => {
+					asset.reserve_location.as_ref()?;
+					ReserveBacking::<T>::get(asset_id)
+				},
 			};
 
-			Commitments::<T>::insert(&commitment, commitment_data);
+			Some((expected, actual))
+		}
 
-			// Increment commitment counter
-			let count = CommitmentCount::<T>::get();
-			CommitmentCount::<T>::put(
-				count.checked_add(1).ok_or(Error::<T>::AmountOverflow)?
-			);
+		/// Pool accounting of a local asset
+		pub fn pool_stats(asset_id: u32) -> PoolStats {
+			PoolStats {
+				total_shielded: TotalShielded::<T>::get(asset_id),
+				protocol_owned: ProtocolOwnedShielded::<T>::get(asset_id),
+				reserve_backing: ReserveBacking::<T>::get(asset_id),
+				anonymity: Self::anonymity_score(asset_id),
+			}
+		}
 
-			// Emit event
-			Self::deposit_event(Event::AssetShielded {
-				commitment,
-				asset_id: registered.local_id,
-				depositor: who,
-				block_number: <frame_system::Pallet<T>>::block_number(),
+		/// Anonymity score of a local asset
+		///
+		/// Counts the asset's unspent notes and its deposits of the last `ScoreWindow`
+		/// blocks: the notes a withdrawal hides among, and how much new activity covers its
+		/// timing. Deposits of the previous window are weighted by how much of it the
+		/// sliding window still covers. Notes whose commitment metadata was pruned before
+		/// they were spent are never subtracted, so `unspent_notes` is an upper estimate.
+		pub fn anonymity_score(asset_id: u32) -> AnonymityScore {
+			Self::score_of(&Self::rolled_tally(asset_id))
+		}
+
+		/// Anonymity score of a tally already rolled to the current block's window
+		fn score_of(tally: &AnonymityTally) -> AnonymityScore {
+			let window = T::ScoreWindow::get().max(1);
+			let now: u32 = frame_system::Pallet::<T>::block_number().saturated_into();
+			// The sliding window still covers this much of the previous fixed one
+			let overlap = window - now % window;
+			let carried =
+				tally.previous_window_deposits as u64 * overlap as u64 / window as u64;
+			let recent_deposits =
+				tally.window_deposits.saturating_add(carried.saturated_into::<u32>());
+			let score = tally.unspent_notes.saturating_add(recent_deposits);
+
+			AnonymityScore {
+				unspent_notes: tally.unspent_notes,
+				recent_deposits,
+				score,
+				bucket: AnonymityBucket::of(score),
+			}
+		}
+
+		/// `AnonymityTallies` of `asset_id`, with its windows moved to the current block's
+		fn rolled_tally(asset_id: u32) -> AnonymityTally {
+			let mut tally = AnonymityTallies::<T>::get(asset_id);
+			let now: u32 = frame_system::Pallet::<T>::block_number().saturated_into();
+			let window = now / T::ScoreWindow::get().max(1);
+			if tally.window != window {
+				tally.previous_window_deposits = if tally.window.saturating_add(1) == window {
+					tally.window_deposits
+				} else {
+					0
+				};
+				tally.window_deposits = 0;
+				tally.window = window;
+			}
+			tally
+		}
+
+		/// Apply `update` to the anonymity tally of `asset_id`
+		///
+		/// Emits `LowAnonymityWarning` when the score falls below `MinAnonymityScore` from
+		/// at or above it while the asset takes deposits. A score that decays between two
+		/// updates is caught at the next one.
+		fn update_anonymity(asset_id: u32, update: impl FnOnce(&mut AnonymityTally)) {
+			let mut tally = Self::rolled_tally(asset_id);
+			update(&mut tally);
+
+			let score = Self::score_of(&tally).score;
+			let was_above_floor = tally.above_floor;
+			tally.above_floor = score >= T::MinAnonymityScore::get();
+			AnonymityTallies::<T>::insert(asset_id, tally);
+
+			if was_above_floor && !tally.above_floor && Self::takes_deposits(asset_id) {
+				Self::deposit_event(Event::LowAnonymityWarning { asset_id, score });
+			}
+		}
+
+		/// Add `count` newly deposited notes of `asset_id` to its anonymity set
+		fn note_joined_anonymity_set(asset_id: u32, count: u32) {
+			Self::update_anonymity(asset_id, |tally| {
+				tally.unspent_notes = tally.unspent_notes.saturating_add(count);
+				tally.window_deposits = tally.window_deposits.saturating_add(count);
 			});
+		}
+
+		/// Remove a spent or swept note of `asset_id` from its anonymity set
+		fn note_left_anonymity_set(asset_id: u32) {
+			Self::update_anonymity(asset_id, |tally| {
+				tally.unspent_notes = tally.unspent_notes.saturating_sub(1);
+			});
+		}
 
+		/// Fail unless local asset `asset_id` takes a deposit of `amount`: not being wound
+		/// down, and if registered, active with `amount` at least its minimum deposit
+		///
+		/// The checks `deposit_from_xcm` runs on the registered asset, for deposits naming
+		/// the local asset ID.
+		pub(crate) fn ensure_asset_takes_deposit(asset_id: u32, amount: u128) -> DispatchResult {
+			ensure!(!WindDowns::<T>::contains_key(asset_id), Error::<T>::AssetWindingDown);
+			if let Some(asset) = Self::registered_asset_by_local(asset_id) {
+				ensure!(asset.is_active, Error::<T>::AssetInactive);
+				ensure!(amount >= asset.min_deposit, Error::<T>::DepositBelowMinimum);
+			}
 			Ok(())
 		}
 
-		/// Week 4: Withdraw to another parachain via XCM
+		/// Whether new notes of a local asset can still be deposited
+		fn takes_deposits(asset_id: u32) -> bool {
+			asset_id == NATIVE_ASSET_ID ||
+				Self::registered_asset_by_local(asset_id).map_or(false, |asset| asset.is_active)
+		}
+
+		/// Reject a note key another local deposit used within `RandomnessReuseWindow`
+		/// blocks, and remember this use
 		///
-		/// Withdraw assets and send them to a destination parachain
+		/// A heuristic against wallets reusing secrets: identical notes are caught as
+		/// duplicate commitments anyway, but notes of different amounts under one known
+		/// note key can be linked by trying small amounts. Only calls that take the note
+		/// key itself are checked; commitments built off-chain (`deposit_split`, commitment
+		/// batches, XCM shields) cannot be.
+		pub(crate) fn ensure_fresh_randomness(note_key: &[u8; 32]) -> DispatchResult {
+			let window = T::RandomnessReuseWindow::get();
+			if window == 0 {
+				return Ok(());
+			}
+
+			let key = crate::primitives::randomness_commitment(note_key);
+			let now = frame_system::Pallet::<T>::block_number();
+			if let Some(seen_at) = RecentRandomness::<T>::get(key) {
+				ensure!(
+					now >= seen_at.saturating_add(window.into()),
+					Error::<T>::RandomnessReused
+				);
+			}
+			RecentRandomness::<T>::insert(key, now);
+			RandomnessSeenAt::<T>::insert(now, key, ());
+			Ok(())
+		}
+
+		/// Forget the randomness seen `RandomnessReuseWindow` blocks before block `n`
 		///
-		/// Parameters:
-		/// - `nullifier`: Nullifier hash
-		/// - `asset_id`: Local asset ID
-		/// - `amount`: Amount to withdraw
-		/// - `destination`: Destination parachain location
-		/// - `beneficiary`: Recipient account on destination chain
-		#[pallet::call_index(5)]
-		#[pallet::weight(Weight::from_parts(10_000, 0) + T::DbWeight::get().writes(1))]
-		pub fn withdraw_to_parachain(
-			origin: OriginFor<T>,
-			nullifier: H256,
-			asset_id: u32,
-			amount: u128,
-			destination: Location,
-			beneficiary: Location,
-		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+		/// Returns the weight used: one block's local deposits at most.
+		pub(crate) fn prune_recent_randomness(n: BlockNumberFor<T>) -> Weight {
+			let db = T::DbWeight::get();
+			let window = T::RandomnessReuseWindow::get();
+			if window == 0 || n < window.into() {
+				return Weight::zero();
+			}
 
-			// Check that nullifier hasn't been used
+			let expired = n - window.into();
+			let mut weight = db.reads(1);
+			for (key, ()) in RandomnessSeenAt::<T>::drain_prefix(expired) {
+				// Reused since: the newer use stays until its own window has passed
+				if RecentRandomness::<T>::get(key) == Some(expired) {
+					RecentRandomness::<T>::remove(key);
+				}
+				weight.saturating_accrue(db.reads_writes(2, 2));
+			}
+			weight
+		}
+
+		/// Create every decoy that is due at block `n`
+		///
+		/// Decoy randomness is drawn from `Config::Randomness` (see `decoy_seed`).
+		pub(crate) fn create_due_decoys(n: BlockNumberFor<T>) -> Weight {
+			let db = T::DbWeight::get();
+			let mut weight = db.reads(1);
+
+			let due: Vec<(u32, DecoySchedule<BlockNumberFor<T>>)> = DecoySchedules::<T>::iter()
+				.filter(|(_, schedule)| schedule.next_at <= n)
+				.take(T::MaxDecoysPerBlock::get() as usize)
+				.collect();
+
+			let treasury = T::TreasuryAccount::get();
+			let value = T::DecoyValue::get();
+
+			for (asset_id, mut schedule) in due {
+				weight = weight.saturating_add(db.reads_writes(2, 6));
+
+				let seed = Self::decoy_seed(n, asset_id, schedule.remaining);
+				let commitment = Self::generate_commitment(value, asset_id, &seed);
+				let nullifier = Self::generate_nullifier(&commitment, &seed);
+
+				// Hooks are not transactional: roll back a partially created decoy
+				let created = with_storage_layer(|| -> Result<u32, DispatchError> {
+					let leaf_index = Self::insert_commitment(commitment, &treasury, asset_id)?;
+					Self::note_shielded(asset_id, value)?;
+					ProtocolOwnedShielded::<T>::mutate(asset_id, |total| {
+						*total = total.saturating_add(value)
+					});
+					DecoyNotes::<T>::insert(&commitment, DecoyNote { asset_id, value, nullifier });
+					Ok(leaf_index)
+				});
+
+				if let Ok(leaf_index) = created {
+					Self::deposit_event(Event::AssetShielded {
+						commitment,
+						asset_id,
+						depositor: treasury.clone(),
+						block_number: n,
+						leaf_index,
+						merkle_root: MerkleRoot::<T>::get(),
+					});
+				}
+
+				schedule.remaining = schedule.remaining.saturating_sub(1);
+				if schedule.remaining == 0 {
+					DecoySchedules::<T>::remove(asset_id);
+				} else {
+					schedule.next_at = n.saturating_add(schedule.interval);
+					DecoySchedules::<T>::insert(asset_id, schedule);
+				}
+			}
+
+			weight
+		}
+
+		/// Check the pallet's invariants: `check_solvency`, then `check_counters`
+		///
+		/// Run by `try_state`, and after every step of the fuzz harness.
+		#[cfg(any(feature = "try-runtime", test))]
+		pub(crate) fn do_try_state() -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::check_solvency()?;
+			Self::check_counters()
+		}
+
+		/// Check that registered non-native assets are backed by what custody holds
+		///
+		/// - `Pot`: the pool account holds at least the user-owned shielded value
+		///   (decoys are accounting-only and carry no tokens).
+		/// - `BurnMint`: shielded value is burned, so the pool account holds none of it;
+		///   the issuance delta is `TotalShielded` itself.
+		/// - Reserve backing never exceeds the derivatives it backs. It cannot go negative:
+		///   withdrawals above it are rejected.
+		#[cfg(any(feature = "try-runtime", test))]
+		pub fn check_solvency() -> Result<(), sp_runtime::TryRuntimeError> {
+			use fungibles::Inspect;
+
+			let pool = Self::account_id();
+			for asset in AssetRegistry::<T>::iter_values() {
+				let asset_id = asset.local_id;
+				if asset_id == NATIVE_ASSET_ID {
+					continue;
+				}
+
+				ensure!(
+					ReserveBacking::<T>::get(asset_id) <=
+						T::Fungibles::total_issuance(asset_id)
+							.saturating_add(TotalShielded::<T>::get(asset_id)),
+					"Reserve backing exceeds the asset's derivatives"
+				);
+
+				let held = T::Fungibles::balance(asset_id, &pool);
+				match asset.custody_mode {
+					CustodyMode::Pot => {
+						let owed = TotalShielded::<T>::get(asset_id)
+							.saturating_sub(ProtocolOwnedShielded::<T>::get(asset_id));
+						ensure!(held >= owed, "Pot-custody asset is under-collateralised");
+					},
+					CustodyMode::BurnMint => {
+						ensure!(held.is_zero(), "Burn-mint asset has value locked in the pool");
+					},
+				}
+			}
+
+			Ok(())
+		}
+
+		/// Check that the tree's counters agree with its leaves and spent notes
+		///
+		/// - Every leaf is stored below `CommitmentCount` and indexed back to its position,
+		///   and no other commitment is indexed; `TreeSize` never passes `CommitmentCount`.
+		/// - Every spent commitment is a leaf.
+		/// - Protocol-owned value never exceeds the shielded value of its asset.
+		#[cfg(any(feature = "try-runtime", test))]
+		pub fn check_counters() -> Result<(), sp_runtime::TryRuntimeError> {
+			let count = CommitmentCount::<T>::get();
+			ensure!(TreeSize::<T>::get() <= count, "Tree holds leaves that were never inserted");
+
+			let mut leaves = 0u32;
+			for (index, commitment) in MerkleLeaves::<T>::iter() {
+				ensure!(index < count, "Leaf stored beyond `CommitmentCount`");
+				ensure!(
+					CommitmentIndex::<T>::get(commitment) == Some(index),
+					"Leaf not indexed at its position"
+				);
+				leaves += 1;
+			}
+			ensure!(leaves == count, "Leaf count differs from `CommitmentCount`");
 			ensure!(
-				!NullifierSet::<T>::get(&nullifier),
-				Error::<T>::NullifierAlreadyUsed
+				CommitmentIndex::<T>::iter_keys().count() == count as usize,
+				"Commitment indexed without a leaf"
 			);
 
-			// Mark nullifier as used
-			NullifierSet::<T>::insert(&nullifier, true);
+			for (commitment, spent) in SpentCommitments::<T>::iter() {
+				ensure!(
+					!spent || CommitmentIndex::<T>::contains_key(commitment),
+					"Spent note is no leaf"
+				);
+			}
+			for (asset_id, protocol_owned) in ProtocolOwnedShielded::<T>::iter() {
+				ensure!(
+					protocol_owned <= TotalShielded::<T>::get(asset_id),
+					"Protocol-owned value exceeds the shielded value"
+				);
+			}
 
-			// Emit event (actual XCM sending would happen here in production)
-			Self::deposit_event(Event::AssetUnshielded {
-				nullifier,
-				asset_id,
-				block_number: <frame_system::Pallet<T>>::block_number(),
-			});
+			Ok(())
+		}
 
-			// Week 4 MVP: Log the cross-chain transfer intent
-			// Production: Actually send XCM message to destination
-			// Example: pallet_xcm::Pallet::<T>::send_xcm(destination, beneficiary, assets)
+		/// Derive decoy randomness from the randomness source, block, asset and sequence
+		fn decoy_seed(n: BlockNumberFor<T>, asset_id: u32, sequence: u32) -> [u8; 32] {
+			let mut data = Vec::new();
+			data.extend_from_slice(b"cloak/decoy");
+			data.extend_from_slice(Self::derive_randomness(b"decoy").as_bytes());
+			data.extend_from_slice(&n.encode());
+			data.extend_from_slice(&asset_id.to_le_bytes());
+			data.extend_from_slice(&sequence.to_le_bytes());
 
-			// Store the destination and beneficiary for future reference
-			let _ = (destination, beneficiary, amount);
+			BlakeTwo256::hash(&data).0
+		}
 
-			Ok(())
+		/// Randomness for `context`, drawn from `Config::Randomness`
+		///
+		/// Domain separated: distinct contexts get unrelated values from the same source.
+		pub fn derive_randomness(context: &[u8]) -> H256 {
+			let (seed, _) = T::Randomness::random(context);
+			BlakeTwo256::hash_of(&(b"cloak/randomness", context, seed))
 		}
-	}
 
-	/// Helper functions (not callable by users)
-	impl<T: Config> Pallet<T> {
-		/// Generate a commitment hash from amount, asset_id, and randomness
+		/// Generate the commitment of a local note from amount, asset_id, and note key
 		///
-		/// Commitment = Hash(amount || asset_id || randomness)
+		/// Commitment = Hash(amount || asset_id || randomness), with the randomness derived
+		/// from the note key (see `primitives::note_commitment`)
 		///
 		/// This hides the amount and randomness while creating a unique identifier
 		pub fn generate_commitment(
 			amount: u128,
 			asset_id: u32,
-			randomness: &[u8; 32],
+			note_key: &[u8; 32],
 		) -> H256 {
-			let mut data = Vec::new();
-			data.extend_from_slice(&amount.to_le_bytes());
-			data.extend_from_slice(&asset_id.to_le_bytes());
-			data.extend_from_slice(randomness);
-
-			BlakeTwo256::hash(&data)
+			crate::primitives::note_commitment::<T::Hasher>(amount, asset_id, note_key)
 		}
 
 		/// Generate a nullifier from commitment and secret
@@ -520,11 +7831,16 @@ pub mod pallet {
 			commitment: &H256,
 			secret: &[u8; 32],
 		) -> H256 {
-			let mut data = Vec::new();
-			data.extend_from_slice(commitment.as_bytes());
-			data.extend_from_slice(secret);
+			crate::primitives::nullifier::<T::Hasher>(commitment, secret)
+		}
 
-			BlakeTwo256::hash(&data)
+		/// Tweak a withdrawal proof opens an XCM note from `origin` with
+		///
+		/// An XCM note's randomness is derived from the note key and the origin (see
+		/// `xcm_config::xcm_commitment_data`), so its withdrawal is proven with this value,
+		/// the note's secret and the amount that was delivered.
+		pub fn xcm_withdrawal_tweak(origin: &Location) -> [u8; 32] {
+			crate::xcm_config::origin_digest(origin)
 		}
 
 		/// Verify a shielded note matches a commitment
@@ -533,7 +7849,7 @@ pub mod pallet {
 			note: &ShieldedNote,
 			commitment: &H256,
 		) -> bool {
-			let computed_commitment = Self::generate_commitment(
+			let computed_commitment = crate::primitives::commitment::<T::Hasher>(
 				note.amount,
 				note.asset_id,
 				&note.randomness,
@@ -7,78 +7,218 @@
 //! ## Design (Week 3 - Hackathon MVP)
 //!
 //! - **Tree Depth**: 20 (supports 2^20 = ~1 million commitments)
-//! - **Hash Function**: simple_hash (matches circuit implementation)
+//! - **Hash Function**: the runtime's `NoteHasher` (`append_leaf`, `root_of`)
 //! - **Construction**: Incremental (append-only, no deletions)
-//! - **Storage**: Only store leaf commitments + computed root
+//! - **Storage**: Leaf commitments, the inner nodes of the tree and its root, so merkle
+//!   paths are read rather than rebuilt (`append_leaf_with_nodes`)
 //!
 //! ## Production Improvements Needed
 //!
-//! - Use Poseidon hash for better zkSNARK efficiency
 //! - Implement full sparse merkle tree for better privacy
 //! - Add merkle proof caching/optimization
 //! - Consider using existing libraries like `rs-merkle`
+//!
+//! The variable-depth tree of the withdrawal circuits (`calculate_root`, `generate_proof`,
+//! `verify_proof`) hashes with `simple_hash`, like the circuits' `SimpleHasher`, so it
+//! only exists for tests and with the `insecure-test-hash` feature.
+//!
+//! Roots are computed by dispatchables, so nothing here may panic: malformed input is an
+//! error, never an out-of-bounds index.
+
+#![cfg_attr(
+	not(test),
+	deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
 
 use sp_core::H256;
 use alloc::vec::Vec;
-use crate::simple_hash::simple_hash_bytes;
+use crate::hasher::NoteHasher;
+use crate::primitives::RESERVED_HASH;
+#[cfg(any(test, feature = "insecure-test-hash"))]
+use crate::simple_hash::hash_pair;
 
 /// Tree depth (20 levels = 2^20 = ~1 million leaves)
 pub const TREE_DEPTH: usize = 20;
 
-/// Calculate parent hash from two children
-pub fn hash_pair(left: &H256, right: &H256) -> H256 {
-	let mut data = Vec::new();
-	data.extend_from_slice(left.as_bytes());
-	data.extend_from_slice(right.as_bytes());
+/// Most leaves a tree of `TREE_DEPTH` levels holds
+pub const MAX_LEAVES: usize = 1 << TREE_DEPTH;
+
+/// Why a root or proof could not be computed over a set of leaves
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MerkleError {
+	/// There are no leaves to build the tree from
+	NoLeaves,
+	/// More than `MAX_LEAVES` leaves
+	TooManyLeaves,
+	/// There is no leaf at the requested index
+	LeafIndexOutOfBounds,
+}
 
-	let hash = simple_hash_bytes(&data);
-	H256::from(hash)
+/// Parents of the nodes of a level, pairing an odd last node with a zero sibling
+#[cfg(any(test, feature = "insecure-test-hash"))]
+fn parent_level(level: &[H256]) -> Vec<H256> {
+	level
+		.chunks(2)
+		.filter_map(|pair| match pair {
+			[left, right] => Some(hash_pair(left, right)),
+			[left] => Some(hash_pair(left, &H256::zero())),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Check `leaves` form a non-empty tree of at most `TREE_DEPTH` levels
+#[cfg(any(test, feature = "insecure-test-hash"))]
+fn ensure_tree(leaves: &[H256]) -> Result<(), MerkleError> {
+	if leaves.is_empty() {
+		return Err(MerkleError::NoLeaves);
+	}
+	if leaves.len() > MAX_LEAVES {
+		return Err(MerkleError::TooManyLeaves);
+	}
+	Ok(())
 }
 
 /// Calculate the merkle root from a list of leaf commitments
 ///
-/// Uses incremental construction: fills remaining slots with zero hashes
-pub fn calculate_root(leaves: &[H256]) -> H256 {
-	if leaves.is_empty() {
-		return H256::zero();
+/// Uses incremental construction: fills remaining slots with zero hashes. Levels are
+/// hashed until a single node is left, at most `TREE_DEPTH` of them given the leaf bound.
+#[cfg(any(test, feature = "insecure-test-hash"))]
+pub fn calculate_root(leaves: &[H256]) -> Result<H256, MerkleError> {
+	ensure_tree(leaves)?;
+
+	let mut current_level = leaves.to_vec();
+	while current_level.len() > 1 {
+		current_level = parent_level(&current_level);
+	}
+
+	current_level.first().copied().ok_or(MerkleError::NoLeaves)
+}
+
+/// Append a leaf to an incremental tree hashed by `H`, returning the new root
+///
+/// `filled_subtrees` is the tree's frontier: for every level, the last left node whose
+/// right sibling is still empty. The leaf at `leaf_index` is hashed up through all
+/// `TREE_DEPTH` levels, so the cost depends on the depth only, never on the number of
+/// leaves already in the tree.
+///
+/// Empty leaves are `RESERVED_HASH`, and an empty subtree hashes its two empty children.
+/// With `simple_hash` every empty subtree is zero, so the tree agrees with
+/// `calculate_root`.
+pub fn append_leaf<H: NoteHasher>(
+	filled_subtrees: &mut [H256; TREE_DEPTH],
+	leaf_index: u32,
+	leaf: H256,
+) -> H256 {
+	append_leaf_with_nodes::<H>(filled_subtrees, leaf_index, leaf, |_, _, _| {})
+}
+
+/// `append_leaf`, passing every inner node it rehashes to `on_node`
+///
+/// Nodes are reported as `(level, index, hash)`, from level 1 (the parents of leaves) up
+/// to the children of the root, which is returned instead. Caching them is what lets
+/// `Pallet::merkle_path` read a path instead of rebuilding the tree.
+pub fn append_leaf_with_nodes<H: NoteHasher>(
+	filled_subtrees: &mut [H256; TREE_DEPTH],
+	leaf_index: u32,
+	leaf: H256,
+	mut on_node: impl FnMut(u8, u32, H256),
+) -> H256 {
+	let mut current_hash = leaf;
+	let mut current_index = leaf_index;
+	let mut zero = RESERVED_HASH;
+
+	for (level, filled) in filled_subtrees.iter_mut().enumerate() {
+		current_hash = if current_index % 2 == 0 {
+			// Left child: its right sibling is still empty
+			*filled = current_hash;
+			H::hash_pair(&current_hash, &zero)
+		} else {
+			H::hash_pair(filled, &current_hash)
+		};
+
+		zero = H::hash_pair(&zero, &zero);
+		current_index /= 2;
+		if level + 1 < TREE_DEPTH {
+			on_node(level as u8 + 1, current_index, current_hash);
+		}
+	}
+
+	current_hash
+}
+
+/// Root of the full-depth tree hashed by `H` over `leaves`, padded with empty leaves
+///
+/// Reference for `append_leaf`: rebuilds every level, so only use it on small trees. No
+/// leaves is the empty tree.
+pub fn root_of<H: NoteHasher>(leaves: &[H256]) -> Result<H256, MerkleError> {
+	if leaves.len() > MAX_LEAVES {
+		return Err(MerkleError::TooManyLeaves);
 	}
 
-	// Start with the leaves
 	let mut current_level = leaves.to_vec();
+	let mut zero = RESERVED_HASH;
 
-	// Build tree level by level
 	for _level in 0..TREE_DEPTH {
-		if current_level.len() == 1 {
-			return current_level[0];
-		}
+		current_level = hashed_parent_level::<H>(&current_level, &zero);
+		zero = H::hash_pair(&zero, &zero);
+	}
 
-		let mut next_level = Vec::new();
+	Ok(current_level.first().copied().unwrap_or(zero))
+}
 
-		// Process pairs
-		for i in (0..current_level.len()).step_by(2) {
-			let left = current_level[i];
-			let right = if i + 1 < current_level.len() {
-				current_level[i + 1]
-			} else {
-				H256::zero() // Pad with zero if odd number
-			};
+/// Siblings of the leaf at `leaf_index` in the full-depth tree hashed by `H` over `leaves`
+///
+/// One sibling per level, from the leaf up, so hashing the leaf with them leads to
+/// `root_of(leaves)`. Rebuilds every level like `root_of`: meant for off-chain queries,
+/// not dispatchables.
+pub fn path_of<H: NoteHasher>(
+	leaves: &[H256],
+	leaf_index: usize,
+) -> Result<Vec<H256>, MerkleError> {
+	if leaves.len() > MAX_LEAVES {
+		return Err(MerkleError::TooManyLeaves);
+	}
+	if leaf_index >= leaves.len() {
+		return Err(MerkleError::LeafIndexOutOfBounds);
+	}
 
-			next_level.push(hash_pair(&left, &right));
-		}
+	let mut path = Vec::with_capacity(TREE_DEPTH);
+	let mut current_level = leaves.to_vec();
+	let mut current_index = leaf_index;
+	let mut zero = RESERVED_HASH;
 
-		current_level = next_level;
+	for _level in 0..TREE_DEPTH {
+		path.push(current_level.get(current_index ^ 1).copied().unwrap_or(zero));
+		current_level = hashed_parent_level::<H>(&current_level, &zero);
+		current_index /= 2;
+		zero = H::hash_pair(&zero, &zero);
 	}
 
-	// Should have single root
-	current_level[0]
+	Ok(path)
+}
+
+/// Parents of the nodes of a level hashed by `H`, pairing an odd last node with `zero`,
+/// the empty subtree of that level
+fn hashed_parent_level<H: NoteHasher>(level: &[H256], zero: &H256) -> Vec<H256> {
+	level
+		.chunks(2)
+		.filter_map(|pair| match pair {
+			[left, right] => Some(H::hash_pair(left, right)),
+			[left] => Some(H::hash_pair(left, zero)),
+			_ => None,
+		})
+		.collect()
 }
 
 /// Generate a merkle proof for a specific leaf
 ///
 /// Returns the sibling hashes needed to recompute the root
-pub fn generate_proof(leaves: &[H256], leaf_index: usize) -> Result<Vec<H256>, &'static str> {
+#[cfg(any(test, feature = "insecure-test-hash"))]
+pub fn generate_proof(leaves: &[H256], leaf_index: usize) -> Result<Vec<H256>, MerkleError> {
+	ensure_tree(leaves)?;
 	if leaf_index >= leaves.len() {
-		return Err("Leaf index out of bounds");
+		return Err(MerkleError::LeafIndexOutOfBounds);
 	}
 
 	let mut proof = Vec::new();
@@ -86,55 +226,56 @@ pub fn generate_proof(leaves: &[H256], leaf_index: usize) -> Result<Vec<H256>, &
 	let mut current_index = leaf_index;
 
 	// Build proof by collecting siblings at each level
-	for _level in 0..TREE_DEPTH {
-		if current_level.len() == 1 {
-			break;
-		}
-
-		// Get sibling index
-		let sibling_index = if current_index % 2 == 0 {
-			current_index + 1
-		} else {
-			current_index - 1
-		};
-
-		// Get sibling value (or zero if doesn't exist)
-		let sibling = if sibling_index < current_level.len() {
-			current_level[sibling_index]
-		} else {
-			H256::zero()
-		};
-
+	while current_level.len() > 1 {
+		// Sibling is the other child of the same parent (or zero if doesn't exist)
+		let sibling = current_level.get(current_index ^ 1).copied().unwrap_or_else(H256::zero);
 		proof.push(sibling);
 
 		// Move to next level
-		let mut next_level = Vec::new();
-		for i in (0..current_level.len()).step_by(2) {
-			let left = current_level[i];
-			let right = if i + 1 < current_level.len() {
-				current_level[i + 1]
-			} else {
-				H256::zero()
-			};
-			next_level.push(hash_pair(&left, &right));
-		}
-
-		current_level = next_level;
+		current_level = parent_level(&current_level);
 		current_index /= 2;
 	}
 
 	Ok(proof)
 }
 
+/// Generate a fixed-depth merkle proof, as consumed by the zkSNARK circuits
+///
+/// Circuits always hash `TREE_DEPTH` levels, so the proof from `generate_proof` is padded
+/// with zero siblings. With `simple_hash`, hashing with a zero sibling is the identity,
+/// so the padded path still leads to `calculate_root(leaves)`.
+///
+/// Returns the siblings and, for every level, whether the node is the right child.
+#[cfg(any(test, feature = "insecure-test-hash"))]
+pub fn generate_circuit_proof(
+	leaves: &[H256],
+	leaf_index: usize,
+) -> Result<(Vec<H256>, Vec<bool>), MerkleError> {
+	let mut siblings = generate_proof(leaves, leaf_index)?;
+	siblings.resize(TREE_DEPTH, H256::zero());
+
+	let path_indices = (0..TREE_DEPTH)
+		.map(|level| (leaf_index >> level) & 1 == 1)
+		.collect();
+
+	Ok((siblings, path_indices))
+}
+
 /// Verify a merkle proof
 ///
-/// Recomputes the root using the leaf and proof, returns true if it matches expected_root
+/// Recomputes the root using the leaf and proof, returns true if it matches expected_root.
+/// A proof longer than `TREE_DEPTH` is rejected without hashing it.
+#[cfg(any(test, feature = "insecure-test-hash"))]
 pub fn verify_proof(
 	leaf: &H256,
 	proof: &[H256],
 	leaf_index: usize,
 	expected_root: &H256,
 ) -> bool {
+	if proof.len() > TREE_DEPTH {
+		return false;
+	}
+
 	let mut current_hash = *leaf;
 	let mut current_index = leaf_index;
 
@@ -157,6 +298,7 @@ pub fn verify_proof(
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::hasher::{Blake2Hasher, SimpleHasher};
 
 	#[test]
 	fn test_hash_pair() {
@@ -176,7 +318,7 @@ mod tests {
 	#[test]
 	fn test_calculate_root_single_leaf() {
 		let leaves = vec![H256::from([1u8; 32])];
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 		assert_eq!(root, leaves[0]);
 	}
 
@@ -186,7 +328,7 @@ mod tests {
 			H256::from([1u8; 32]),
 			H256::from([2u8; 32]),
 		];
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 		let expected = hash_pair(&leaves[0], &leaves[1]);
 		assert_eq!(root, expected);
 	}
@@ -199,7 +341,7 @@ mod tests {
 			H256::from([3u8; 32]),
 			H256::from([4u8; 32]),
 		];
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 
 		// Manually compute expected root
 		let h01 = hash_pair(&leaves[0], &leaves[1]);
@@ -218,7 +360,7 @@ mod tests {
 			H256::from([4u8; 32]),
 		];
 
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 
 		// Generate proof for each leaf and verify
 		for (i, leaf) in leaves.iter().enumerate() {
@@ -234,7 +376,7 @@ mod tests {
 			H256::from([2u8; 32]),
 		];
 
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 		let proof = generate_proof(&leaves, 0).unwrap();
 
 		// Try to verify with wrong leaf
@@ -249,7 +391,7 @@ mod tests {
 			H256::from([2u8; 32]),
 		];
 
-		let root = calculate_root(&leaves);
+		let root = calculate_root(&leaves).unwrap();
 		let proof = generate_proof(&leaves, 0).unwrap();
 
 		// Try to verify with wrong root
@@ -257,6 +399,71 @@ mod tests {
 		assert!(!verify_proof(&leaves[0], &proof, 0, &wrong_root), "Proof should fail for wrong root");
 	}
 
+	#[test]
+	fn test_circuit_proof_is_padded_to_tree_depth() {
+		let leaves = vec![
+			H256::from([1u8; 32]),
+			H256::from([2u8; 32]),
+			H256::from([3u8; 32]),
+		];
+		let root = calculate_root(&leaves).unwrap();
+
+		let (siblings, path_indices) = generate_circuit_proof(&leaves, 2).unwrap();
+		assert_eq!(siblings.len(), TREE_DEPTH);
+		assert_eq!(path_indices.len(), TREE_DEPTH);
+		assert_eq!(&path_indices[..2], &[false, true]);
+
+		// The padded path still verifies against the tree root
+		assert!(verify_proof(&leaves[2], &siblings, 2, &root));
+	}
+
+	#[test]
+	fn test_append_leaf_matches_calculate_root() {
+		let leaves: Vec<H256> = (1..=9u8).map(|i| H256::from([i; 32])).collect();
+		let mut filled_subtrees = [RESERVED_HASH; TREE_DEPTH];
+
+		for (index, leaf) in leaves.iter().enumerate() {
+			let root = append_leaf::<SimpleHasher>(&mut filled_subtrees, index as u32, *leaf);
+			assert_eq!(root, calculate_root(&leaves[..=index]).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_append_leaf_matches_root_of() {
+		let leaves: Vec<H256> = (1..=9u8).map(|i| H256::from([i; 32])).collect();
+		let mut filled_subtrees = [RESERVED_HASH; TREE_DEPTH];
+
+		assert_ne!(root_of::<Blake2Hasher>(&[]).unwrap(), H256::zero());
+		for (index, leaf) in leaves.iter().enumerate() {
+			let root = append_leaf::<Blake2Hasher>(&mut filled_subtrees, index as u32, *leaf);
+			assert_eq!(root, root_of::<Blake2Hasher>(&leaves[..=index]).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_path_of_leads_to_root_of() {
+		let leaves: Vec<H256> = (1..=9u8).map(|i| H256::from([i; 32])).collect();
+		let root = root_of::<Blake2Hasher>(&leaves).unwrap();
+
+		for (index, leaf) in leaves.iter().enumerate() {
+			let path = path_of::<Blake2Hasher>(&leaves, index).unwrap();
+			assert_eq!(path.len(), TREE_DEPTH);
+
+			let mut node = *leaf;
+			for (level, sibling) in path.iter().enumerate() {
+				node = if (index >> level) & 1 == 0 {
+					Blake2Hasher::hash_pair(&node, sibling)
+				} else {
+					Blake2Hasher::hash_pair(sibling, &node)
+				};
+			}
+			assert_eq!(node, root);
+		}
+
+		assert_eq!(path_of::<Blake2Hasher>(&leaves, 9), Err(MerkleError::LeafIndexOutOfBounds));
+		assert_eq!(path_of::<Blake2Hasher>(&[], 0), Err(MerkleError::LeafIndexOutOfBounds));
+	}
+
 	#[test]
 	fn test_incremental_root_updates() {
 		// Test that adding leaves incrementally works correctly
@@ -264,9 +471,9 @@ mod tests {
 		let leaf2 = H256::from([2u8; 32]);
 		let leaf3 = H256::from([3u8; 32]);
 
-		let root1 = calculate_root(&[leaf1]);
-		let root2 = calculate_root(&[leaf1, leaf2]);
-		let root3 = calculate_root(&[leaf1, leaf2, leaf3]);
+		let root1 = calculate_root(&[leaf1]).unwrap();
+		let root2 = calculate_root(&[leaf1, leaf2]).unwrap();
+		let root3 = calculate_root(&[leaf1, leaf2, leaf3]).unwrap();
 
 		// Roots should be different
 		assert_ne!(root1, root2);
@@ -277,4 +484,31 @@ mod tests {
 		let proof_leaf1_in_tree3 = generate_proof(&[leaf1, leaf2, leaf3], 0).unwrap();
 		assert!(verify_proof(&leaf1, &proof_leaf1_in_tree3, 0, &root3));
 	}
+
+	#[test]
+	fn test_degenerate_leaf_sets_are_errors() {
+		assert_eq!(calculate_root(&[]), Err(MerkleError::NoLeaves));
+		assert_eq!(generate_proof(&[], 0), Err(MerkleError::NoLeaves));
+		assert_eq!(generate_circuit_proof(&[], 0), Err(MerkleError::NoLeaves));
+
+		let leaves = vec![H256::from([1u8; 32]); 2];
+		assert_eq!(generate_proof(&leaves, 2), Err(MerkleError::LeafIndexOutOfBounds));
+
+		let too_many = vec![H256::from([1u8; 32]); MAX_LEAVES + 1];
+		assert_eq!(calculate_root(&too_many), Err(MerkleError::TooManyLeaves));
+		assert_eq!(generate_proof(&too_many, 0), Err(MerkleError::TooManyLeaves));
+		assert_eq!(root_of::<Blake2Hasher>(&too_many), Err(MerkleError::TooManyLeaves));
+	}
+
+	#[test]
+	fn test_verify_proof_rejects_over_long_proofs() {
+		let leaves = vec![H256::from([1u8; 32]), H256::from([2u8; 32])];
+		let root = calculate_root(&leaves).unwrap();
+		let mut proof = generate_proof(&leaves, 0).unwrap();
+		assert!(verify_proof(&leaves[0], &proof, 0, &root));
+
+		// Zero siblings leave a `simple_hash` path unchanged, only the length is wrong
+		proof.resize(TREE_DEPTH + 1, H256::zero());
+		assert!(!verify_proof(&leaves[0], &proof, 0, &root));
+	}
 }
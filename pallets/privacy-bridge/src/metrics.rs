@@ -0,0 +1,134 @@
+//! Operator metrics of the privacy bridge
+//!
+//! The pallet reports deposits, withdrawals, refused proofs, queue depths and export
+//! retries through `Config::Metrics` as they happen, so node operators can chart them
+//! without decoding events:
+//!
+//! ```ignore
+//! impl pallet_privacy_bridge::Config for Runtime {
+//!     type Metrics = pallet_privacy_bridge::metrics::AtomicMetrics;
+//! }
+//!
+//! // Node side, e.g. in a prometheus collector
+//! let deposits = pallet_privacy_bridge::metrics::COUNTERS.deposits.load(Ordering::Relaxed);
+//! ```
+//!
+//! Reports are made where things happen, including in extrinsics that fail afterwards and
+//! are rolled back, so the numbers are for monitoring and never for accounting. Atomics
+//! are only readable by the node when the runtime executes natively.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Instrumentation points of the pallet
+///
+/// Every method does nothing by default, and `()` reports nothing.
+pub trait BridgeMetrics {
+	/// Value of `asset_id` was shielded into a new note
+	fn deposit(_asset_id: u32) {}
+
+	/// A note of `asset_id` was spent and its value unshielded
+	fn withdrawal(_asset_id: u32) {}
+
+	/// A withdrawal was refused for its proof (stale circuits or an expired anchor)
+	fn proof_failure() {}
+
+	/// Number of leaves waiting to be hashed into the tree
+	fn pending_leaves(_depth: u32) {}
+
+	/// A cross-chain withdrawal joined the withdrawal queue
+	fn withdrawal_queued() {}
+
+	/// A queued withdrawal was exported and left the queue
+	fn withdrawal_exported() {}
+
+	/// Exporting a queued withdrawal failed; it stays queued as `Failed`
+	fn export_failed() {}
+
+	/// A failed withdrawal was queued for export again
+	fn xcm_retry() {}
+}
+
+impl BridgeMetrics for () {}
+
+/// Counters of `AtomicMetrics`
+pub struct BridgeCounters {
+	pub deposits: AtomicU64,
+	pub withdrawals: AtomicU64,
+	pub proof_failures: AtomicU64,
+	pub pending_leaves: AtomicU64,
+	/// Withdrawals queued and not yet exported, failed ones included
+	pub withdrawal_queue_depth: AtomicU64,
+	pub export_failures: AtomicU64,
+	pub xcm_retries: AtomicU64,
+}
+
+impl BridgeCounters {
+	pub const fn new() -> Self {
+		Self {
+			deposits: AtomicU64::new(0),
+			withdrawals: AtomicU64::new(0),
+			proof_failures: AtomicU64::new(0),
+			pending_leaves: AtomicU64::new(0),
+			withdrawal_queue_depth: AtomicU64::new(0),
+			export_failures: AtomicU64::new(0),
+			xcm_retries: AtomicU64::new(0),
+		}
+	}
+}
+
+impl Default for BridgeCounters {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Counters updated by `AtomicMetrics`, for a node-side exporter to read
+pub static COUNTERS: BridgeCounters = BridgeCounters::new();
+
+/// Example `BridgeMetrics` incrementing `COUNTERS`
+pub struct AtomicMetrics;
+
+impl AtomicMetrics {
+	fn increment(counter: &AtomicU64) {
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl BridgeMetrics for AtomicMetrics {
+	fn deposit(_asset_id: u32) {
+		Self::increment(&COUNTERS.deposits);
+	}
+
+	fn withdrawal(_asset_id: u32) {
+		Self::increment(&COUNTERS.withdrawals);
+	}
+
+	fn proof_failure() {
+		Self::increment(&COUNTERS.proof_failures);
+	}
+
+	fn pending_leaves(depth: u32) {
+		COUNTERS.pending_leaves.store(depth.into(), Ordering::Relaxed);
+	}
+
+	fn withdrawal_queued() {
+		Self::increment(&COUNTERS.withdrawal_queue_depth);
+	}
+
+	fn withdrawal_exported() {
+		// Withdrawals queued before the node started were never counted
+		let _ = COUNTERS.withdrawal_queue_depth.fetch_update(
+			Ordering::Relaxed,
+			Ordering::Relaxed,
+			|depth| Some(depth.saturating_sub(1)),
+		);
+	}
+
+	fn export_failed() {
+		Self::increment(&COUNTERS.export_failures);
+	}
+
+	fn xcm_retry() {
+		Self::increment(&COUNTERS.xcm_retries);
+	}
+}
@@ -0,0 +1,98 @@
+//! Storage migrations
+//!
+//! Each migration moves the pallet's storage from one `StorageVersion` to the next and is
+//! wrapped in a `VersionedMigration`, so it runs once, only on the version it expects, and
+//! bumps the on-chain version itself. The runtime lists them in its `Migrations`.
+
+use crate::xcm_config::RegisteredAsset;
+use crate::{AssetRegistry, CircuitId, Config, Pallet, VerifyingKeyBytes, VerifyingKeys};
+use codec::{Decode, Encode};
+use frame::deps::frame_support::{
+	migrations::VersionedMigration,
+	storage_alias,
+	traits::{Get, UncheckedOnRuntimeUpgrade},
+	weights::Weight,
+	BoundedVec,
+};
+use sp_runtime::traits::ConstU32;
+
+/// v0 to v1: verifying keys per circuit
+pub mod v1 {
+	use super::*;
+
+	/// Verifying key of `withdraw` and the calls sharing its circuit
+	#[storage_alias]
+	pub(crate) type VerifyingKey<T: Config> =
+		StorageValue<Pallet<T>, BoundedVec<u8, ConstU32<4096>>, OptionQuery>;
+
+	/// Move the single verifying key into `VerifyingKeys`, under the `Withdraw` circuit
+	pub struct UncheckedMigrateToV1<T>(core::marker::PhantomData<T>);
+
+	impl<T: Config> UncheckedOnRuntimeUpgrade for UncheckedMigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let Some(vk) = VerifyingKey::<T>::take() else {
+				return T::DbWeight::get().reads_writes(1, 1);
+			};
+			// Every key of the old 4096-byte bound fits the new one
+			let vk: VerifyingKeyBytes = BoundedVec::truncate_from(vk.into_inner());
+			VerifyingKeys::<T>::insert(CircuitId::Withdraw, vk);
+
+			T::DbWeight::get().reads_writes(1, 2)
+		}
+	}
+
+	/// `UncheckedMigrateToV1`, run only on storage version 0
+	pub type MigrateToV1<T> = VersionedMigration<
+		0,
+		1,
+		UncheckedMigrateToV1<T>,
+		Pallet<T>,
+		<T as frame::deps::frame_system::Config>::DbWeight,
+	>;
+}
+
+/// v1 to v2: per-asset settings in the asset registry
+pub mod v2 {
+	use super::*;
+	use staging_xcm::v5::AssetId;
+
+	/// `RegisteredAsset` as released, before its per-asset settings
+	#[derive(Encode, Decode)]
+	pub(crate) struct RegisteredAssetV1 {
+		pub asset_id: AssetId,
+		pub local_id: u32,
+		pub min_deposit: u128,
+		pub is_active: bool,
+	}
+
+	/// Keep each registered asset's ID, minimum deposit and status, and give it the
+	/// defaults of a new registration for the rest: custody in the pool account, no
+	/// abandonment, note age or receipt settings, and no denominations, so it takes any
+	/// amount
+	pub struct UncheckedMigrateToV2<T>(core::marker::PhantomData<T>);
+
+	impl<T: Config> UncheckedOnRuntimeUpgrade for UncheckedMigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut assets = 0;
+			AssetRegistry::<T>::translate_values::<RegisteredAssetV1, _>(|old| {
+				assets += 1;
+				Some(RegisteredAsset {
+					min_deposit: old.min_deposit,
+					is_active: old.is_active,
+					..RegisteredAsset::new(old.asset_id, old.local_id)
+				})
+			});
+
+			T::DbWeight::get().reads_writes(assets, assets)
+		}
+	}
+
+	/// `UncheckedMigrateToV2`, run only on storage version 1
+	pub type MigrateToV2<T> = VersionedMigration<
+		1,
+		2,
+		UncheckedMigrateToV2<T>,
+		Pallet<T>,
+		<T as frame::deps::frame_system::Config>::DbWeight,
+	>;
+}
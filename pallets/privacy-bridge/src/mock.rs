@@ -1,9 +1,40 @@
+use crate::bridge_adapter::{
+	ExportError, ExportWithdrawal, MessageId, SendDepositReceipt, VersionedDestination,
+};
+use crate::hasher::{Blake2Hasher, HasherId, NoteHasher, SimpleHasher};
+use crate::metrics::BridgeMetrics;
+use crate::xcm_config::DepositReceipt;
+use crate::zksnark::{self, Groth16Verifier, ProofBytes, VerifyError, WithdrawalVerifier};
 use frame::{
-	deps::{frame_support::weights::constants::RocksDbWeight, frame_system::GenesisConfig},
+	deps::{
+		frame_support::{
+			traits::{
+				fungible,
+				tokens::{Fortitude, Precision, Preservation},
+				AsEnsureOriginWithArg, ConstU128, ConstU32,
+			},
+			weights::constants::RocksDbWeight,
+			ord_parameter_types, PalletId,
+		},
+		frame_system::{EnsureRoot, EnsureSigned, EnsureSignedBy, GenesisConfig},
+	},
 	prelude::*,
 	runtime::prelude::*,
 	testing_prelude::*,
 };
+use sp_core::H256;
+use sp_runtime::{
+	traits::{AsSystemOriginSigner, DispatchInfoOf, Implication, TransactionExtension, ValidateResult},
+	transaction_validity::{
+		InvalidTransaction, TransactionSource, TransactionValidityError, ValidTransaction,
+	},
+};
+use staging_xcm::v5::{
+	AssetId as XcmAssetId,
+	InteriorLocation,
+	Junction::{GlobalConsensus, PalletInstance, Parachain},
+	Location, NetworkId,
+};
 
 // Configure a mock runtime to test the pallet.
 #[frame_construct_runtime]
@@ -26,6 +57,89 @@ mod test_runtime {
 	pub type System = frame_system;
 	#[runtime::pallet_index(1)]
 	pub type PrivacyBridge = crate;
+	#[runtime::pallet_index(2)]
+	pub type Balances = pallet_balances;
+	#[runtime::pallet_index(3)]
+	pub type Assets = pallet_assets;
+	#[runtime::pallet_index(4)]
+	pub type ShieldCaller = shield_caller;
+	#[runtime::pallet_index(5)]
+	pub type UnshieldCaller = unshield_caller;
+}
+
+/// A sibling pallet shielding inside its own extrinsic, as a DEX shields a swap's output
+#[frame::pallet(dev_mode)]
+pub mod shield_caller {
+	use crate::shield::ShieldInterface;
+	use frame::prelude::*;
+	use sp_core::H256;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type Shield: ShieldInterface<Self::AccountId>;
+	}
+
+	/// Number of completed swaps
+	#[pallet::storage]
+	pub type Swaps<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// "Swap" into `amount` of `asset_id` and shield the output into `commitment`
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::Shield::shield_weight())]
+		pub fn swap_and_shield(
+			origin: OriginFor<T>,
+			commitment: H256,
+			asset_id: u32,
+			amount: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Swaps::<T>::mutate(|swaps| *swaps += 1);
+			T::Shield::shield(who, commitment, asset_id, amount)
+		}
+	}
+}
+
+impl shield_caller::Config for Test {
+	type Shield = PrivacyBridge;
+}
+
+/// A sibling pallet spending notes whose proofs it received, as a recovery pallet would
+#[frame::pallet(dev_mode)]
+pub mod unshield_caller {
+	use crate::shield::{PayoutTarget, ProofEnvelope, UnshieldInputs, UnshieldInterface};
+	use frame::prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type Unshield: UnshieldInterface<Self::AccountId>;
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Spend a note on governance's behalf
+		#[pallet::call_index(0)]
+		pub fn recover(
+			origin: OriginFor<T>,
+			proof_envelope: ProofEnvelope,
+			public_inputs: UnshieldInputs,
+			payout: PayoutTarget<T::AccountId>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			T::Unshield::unshield(proof_envelope, public_inputs, payout)
+		}
+	}
+}
+
+impl unshield_caller::Config for Test {
+	type Unshield = PrivacyBridge;
 }
 
 #[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
@@ -34,14 +148,629 @@ impl frame_system::Config for Test {
 	type Block = MockBlock<Test>;
 	type BlockHashCount = ConstU64<250>;
 	type DbWeight = RocksDbWeight;
+	type AccountData = pallet_balances::AccountData<u128>;
+}
+
+/// Existential deposit of the native currency
+pub const EXISTENTIAL_DEPOSIT: u128 = 5;
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
+impl pallet_balances::Config for Test {
+	type Balance = u128;
+	type ExistentialDeposit = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type AccountStore = System;
+}
+
+#[derive_impl(pallet_assets::config_preludes::TestDefaultConfig)]
+impl pallet_assets::Config for Test {
+	type Balance = u128;
+	type Currency = Balances;
+	type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<u64>>;
+	type ForceOrigin = EnsureRoot<u64>;
+	type Freezer = ();
+}
+
+parameter_types! {
+	pub const PrivacyBridgePalletId: PalletId = PalletId(*b"py/cloak");
+	pub static ClaimDustPayouts: bool = true;
+	/// Withdrawals handed to `MockExporter`: (asset, amount, destination, beneficiary)
+	pub static ExportedWithdrawals: Vec<(XcmAssetId, u128, VersionedDestination, Vec<u8>)> = vec![];
+	/// Batches handed to `MockExporter`, one per message: (asset, destination, payouts)
+	pub static ExportedBatches: Vec<(XcmAssetId, VersionedDestination, Vec<(u128, Vec<u8>)>)> = vec![];
+	/// Makes every `MockExporter` export and `MockReceiptSender` receipt fail
+	pub static ExportFails: bool = false;
+	/// Reserve backing moved within a block that raises `ReserveBackingAnomaly`
+	pub static ReserveBackingAlertThreshold: u128 = 5_000;
+	/// Receipts handed to `MockReceiptSender`: (origin, receipt call, receipt)
+	pub static SentReceipts: Vec<(Location, [u8; 2], DepositReceipt)> = vec![];
+	/// Leaves deposits' tree insertion to `on_finalize`
+	pub static BatchTreeInsertion: bool = false;
+	/// Number of recent blocks withdrawals may be anchored to
+	pub static MaxProofAge: Option<u32> = None;
+	/// Hasher `MockHasher` delegates to
+	pub static MockHasherId: HasherId = HasherId::Blake2;
+	/// Verify withdrawal proofs with Groth16 rather than accept `mock_proof`
+	pub static VerifyProofs: bool = false;
+	/// Lets tests call `deposit_from_xcm` directly
+	pub static AllowSignedXcmDeposits: bool = true;
+	/// Smallest treasury fee of an unsigned relayed withdrawal
+	pub static UnsignedRelayFee: Option<u128> = Some(10);
+	/// Reports handed to `RecordingMetrics`
+	pub static RecordedMetrics: MetricCounts = MetricCounts::default();
+	/// Native deposit per byte of a note backup
+	pub static BackupDepositPerByte: u128 = 2;
+	/// Proof verification weight withdrawals may use per block
+	pub static MaxVerificationWeightPerBlock: Weight = Weight::MAX;
+	/// Seed of `TestRandomness`
+	pub static RandomnessSeed: u64 = 0;
+	/// Blocks a deposit's randomness may not be reused for, not checked at 0
+	pub static RandomnessReuseWindow: u32 = 0;
+	/// Where shield deposits are addressed to (the privacy bridge pallet)
+	pub ShieldLocation: Location = Location::new(0, [PalletInstance(1)]);
+	/// The mock chain is parachain `MOCK_PARA_ID` on Polkadot
+	pub UniversalLocation: InteriorLocation =
+		[GlobalConsensus(NetworkId::Polkadot), Parachain(MOCK_PARA_ID)].into();
+}
+
+/// Parachain ID of the mock chain
+pub const MOCK_PARA_ID: u32 = 100;
+
+/// Exporter recording withdrawals instead of sending them
+pub struct MockExporter;
+
+impl ExportWithdrawal for MockExporter {
+	fn export(
+		asset: &XcmAssetId,
+		amount: u128,
+		destination: &VersionedDestination,
+		beneficiary: &[u8],
+	) -> Result<MessageId, ExportError> {
+		if ExportFails::get() {
+			return Err(ExportError::SendFailed);
+		}
+
+		let record = (asset.clone(), amount, destination.clone(), beneficiary.to_vec());
+		let message_id = sp_io::hashing::blake2_256(&record.encode());
+		ExportedWithdrawals::mutate(|exported| exported.push(record));
+
+		Ok(message_id)
+	}
+
+	fn export_batch(
+		asset: &XcmAssetId,
+		destination: &VersionedDestination,
+		payouts: &[(u128, Vec<u8>)],
+	) -> Result<MessageId, ExportError> {
+		if ExportFails::get() {
+			return Err(ExportError::SendFailed);
+		}
+
+		let batch = (asset.clone(), destination.clone(), payouts.to_vec());
+		let message_id = sp_io::hashing::blake2_256(&batch.encode());
+		ExportedBatches::mutate(|exported| exported.push(batch));
+
+		Ok(message_id)
+	}
+}
+
+/// Receipt sender recording receipts instead of sending them
+pub struct MockReceiptSender;
+
+impl SendDepositReceipt for MockReceiptSender {
+	fn send_receipt(
+		origin: &Location,
+		receipt_call: [u8; 2],
+		receipt: &DepositReceipt,
+	) -> Result<MessageId, ExportError> {
+		if ExportFails::get() {
+			return Err(ExportError::SendFailed);
+		}
+
+		let record = (origin.clone(), receipt_call, *receipt);
+		let message_id = sp_io::hashing::blake2_256(&record.encode());
+		SentReceipts::mutate(|sent| sent.push(record));
+
+		Ok(message_id)
+	}
+}
+
+/// Counts of the reports of `RecordingMetrics`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetricCounts {
+	pub deposits: Vec<u32>,
+	pub withdrawals: Vec<u32>,
+	pub proof_failures: u32,
+	pub pending_leaves: u32,
+	pub queued: u32,
+	pub exported: u32,
+	pub export_failures: u32,
+	pub retries: u32,
+}
+
+/// Metrics recording every report in `RecordedMetrics`
+pub struct RecordingMetrics;
+
+impl BridgeMetrics for RecordingMetrics {
+	fn deposit(asset_id: u32) {
+		RecordedMetrics::mutate(|counts| counts.deposits.push(asset_id));
+	}
+
+	fn withdrawal(asset_id: u32) {
+		RecordedMetrics::mutate(|counts| counts.withdrawals.push(asset_id));
+	}
+
+	fn proof_failure() {
+		RecordedMetrics::mutate(|counts| counts.proof_failures += 1);
+	}
+
+	fn pending_leaves(depth: u32) {
+		RecordedMetrics::mutate(|counts| counts.pending_leaves = depth);
+	}
+
+	fn withdrawal_queued() {
+		RecordedMetrics::mutate(|counts| counts.queued += 1);
+	}
+
+	fn withdrawal_exported() {
+		RecordedMetrics::mutate(|counts| counts.exported += 1);
+	}
+
+	fn export_failed() {
+		RecordedMetrics::mutate(|counts| counts.export_failures += 1);
+	}
+
+	fn xcm_retry() {
+		RecordedMetrics::mutate(|counts| counts.retries += 1);
+	}
+}
+
+/// Collective-flip-style randomness: a hash of `RandomnessSeed` and the subject
+///
+/// Tests change the seed where collective flip would see new block hashes.
+pub struct TestRandomness;
+
+impl frame::deps::frame_support::traits::Randomness<H256, u64> for TestRandomness {
+	fn random(subject: &[u8]) -> (H256, u64) {
+		let seed = RandomnessSeed::get();
+		(sp_io::hashing::blake2_256(&(seed, subject).encode()).into(), System::block_number())
+	}
+}
+
+/// Hasher switching between Blake2 and the XOR test hash on `MockHasherId`
+pub struct MockHasher;
+
+impl NoteHasher for MockHasher {
+	fn id() -> HasherId {
+		MockHasherId::get()
+	}
+
+	fn hash_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
+		match Self::id() {
+			HasherId::Simple => SimpleHasher::hash_commitment(amount, asset_id, randomness),
+			_ => Blake2Hasher::hash_commitment(amount, asset_id, randomness),
+		}
+	}
+
+	fn hash_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+		match Self::id() {
+			HasherId::Simple => SimpleHasher::hash_bundle_commitment(assets, randomness),
+			_ => Blake2Hasher::hash_bundle_commitment(assets, randomness),
+		}
+	}
+
+	fn hash_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
+		match Self::id() {
+			HasherId::Simple => SimpleHasher::hash_nullifier(commitment, secret),
+			_ => Blake2Hasher::hash_nullifier(commitment, secret),
+		}
+	}
+
+	fn hash_pair(left: &H256, right: &H256) -> H256 {
+		match Self::id() {
+			HasherId::Simple => SimpleHasher::hash_pair(left, right),
+			_ => Blake2Hasher::hash_pair(left, right),
+		}
+	}
+}
+
+/// Verifier accepting `mock_proof`, or verifying with Groth16 when `VerifyProofs` is set
+pub struct MockVerifier;
+
+impl WithdrawalVerifier for MockVerifier {
+	/// Prepares keys Groth16 decodes, and keeps any other key as it is, so tests may set
+	/// placeholder keys
+	fn prepare_key(verifying_key: &[u8]) -> Result<Vec<u8>, VerifyError> {
+		Ok(Groth16Verifier::prepare_key(verifying_key).unwrap_or_else(|_| verifying_key.to_vec()))
+	}
+
+	fn verify(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		if VerifyProofs::get() {
+			return Groth16Verifier::verify(
+				prepared_key,
+				proof,
+				nullifier,
+				commitment,
+				merkle_root,
+				asset_id,
+				amount,
+				recipient_hash,
+			);
+		}
+		Ok(proof == &mock_proof()[..])
+	}
+
+	fn verify_transfer(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		output_commitments: &[H256; 2],
+	) -> Result<bool, VerifyError> {
+		if VerifyProofs::get() {
+			return Groth16Verifier::verify_transfer(
+				prepared_key,
+				proof,
+				nullifier,
+				merkle_root,
+				asset_id,
+				output_commitments,
+			);
+		}
+		Ok(proof == &mock_proof()[..])
+	}
+
+	fn verify_join(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifiers: &[H256; 2],
+		commitments: &[H256; 2],
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		if VerifyProofs::get() {
+			return Groth16Verifier::verify_join(
+				prepared_key,
+				proof,
+				nullifiers,
+				commitments,
+				merkle_root,
+				asset_id,
+				amount,
+				recipient_hash,
+			);
+		}
+		Ok(proof == &mock_proof()[..])
+	}
+
+	fn verify_bundle(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		assets: &[(u32, u128)],
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		if VerifyProofs::get() {
+			return Groth16Verifier::verify_bundle(
+				prepared_key,
+				proof,
+				nullifier,
+				commitment,
+				assets,
+				recipient_hash,
+			);
+		}
+		Ok(proof == &mock_proof()[..])
+	}
+
+	fn verify_swap(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		output_commitment: &H256,
+	) -> Result<bool, VerifyError> {
+		if VerifyProofs::get() {
+			return Groth16Verifier::verify_swap(
+				prepared_key,
+				proof,
+				nullifier,
+				commitment,
+				output_commitment,
+			);
+		}
+		Ok(proof == &mock_proof()[..])
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_proof(
+		asset_id: u32,
+		_recipient_hash: &H256,
+	) -> Result<zksnark::BenchmarkProof, String> {
+		Ok(zksnark::BenchmarkProof {
+			verifying_key: TEST_VERIFYING_KEY.to_vec(),
+			nullifier: H256::repeat_byte(1),
+			commitment: H256::repeat_byte(2),
+			merkle_root: H256::repeat_byte(2),
+			asset_id,
+			amount: 100,
+			proof: mock_proof().into_inner(),
+		})
+	}
+}
+
+/// Proof `MockVerifier` accepts for any note, unless `VerifyProofs` is set
+pub fn mock_proof() -> ProofBytes {
+	BoundedVec::truncate_from(vec![0x5a; zksnark::PROOF_LEN])
+}
+
+/// Treats a signed account `id` as the XCM origin of sibling parachain `id`
+pub struct EnsureSiblingParachain;
+
+impl EnsureOrigin<RuntimeOrigin> for EnsureSiblingParachain {
+	type Success = Location;
+
+	fn try_origin(o: RuntimeOrigin) -> Result<Location, RuntimeOrigin> {
+		match o.clone().into() {
+			Ok(frame_system::RawOrigin::Signed(id)) => {
+				Ok(Location::new(1, [Parachain(id as u32)]))
+			},
+			_ => Err(o),
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn try_successful_origin() -> Result<RuntimeOrigin, ()> {
+		Ok(RuntimeOrigin::signed(2000))
+	}
 }
 
+/// Account owning protocol-funded decoy notes
+pub const TREASURY: u64 = 999;
+
+/// Value committed in each decoy note
+pub const DECOY_VALUE: u128 = 10;
+
+ord_parameter_types! {
+	/// Account approving admin actions (`CoSignerOrigin`), besides root proposing them
+	pub const CoSigner: u64 = 77;
+}
+
+/// Size of every note backup
+pub const BACKUP_LEN: u32 = 32;
+
 impl crate::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type Hasher = MockHasher;
+	type AdminOrigin = EnsureRoot<u64>;
+	type TreasuryAccount = ConstU64<TREASURY>;
+	type DecoyValue = ConstU128<DECOY_VALUE>;
+	type MaxDecoysPerBlock = ConstU32<4>;
+	type Currency = Balances;
+	type Fungibles = Assets;
+	type PalletId = PrivacyBridgePalletId;
+	type ClaimDustPayouts = ClaimDustPayouts;
+	type Exporter = MockExporter;
+	type TrapOrigin = EnsureSiblingParachain;
+	type MaxDepositsPerMessage = ConstU32<4>;
+	type UniversalLocation = UniversalLocation;
+	type ReceiptSender = MockReceiptSender;
+	type ReserveBackingAlertThreshold = ReserveBackingAlertThreshold;
+	type SolvencyCheckInterval = ConstU64<10>;
+	type MaxSolvencyChecks = ConstU32<2>;
+	type BatchTreeInsertion = BatchTreeInsertion;
+	type AnnouncementTtl = ConstU64<100>;
+	type MaxSplit = ConstU32<16>;
+	type RootHistorySize = ConstU32<4>;
+	type MaxProofAge = MaxProofAge;
+	type SponsorThreshold = ConstU128<100>;
+	type SponsoredFee = ConstU128<SPONSORED_FEE>;
+	type SponsorPeriod = ConstU64<10>;
+	type MaxSponsoredPerAccount = ConstU32<2>;
+	type MaxSponsoredPerPeriod = ConstU32<3>;
+	type Metrics = RecordingMetrics;
+	type MaxBatch = ConstU32<4>;
+	type MaxBatchLeavesPerBlock = ConstU32<6>;
+	type MaxBackupLen = ConstU32<BACKUP_LEN>;
+	type BackupDepositPerByte = BackupDepositPerByte;
+	type MaxVerificationWeightPerBlock = MaxVerificationWeightPerBlock;
+	type Randomness = TestRandomness;
+	type MaxAlertTags = ConstU32<4>;
+	type ScoreWindow = ConstU32<10>;
+	type MinAnonymityScore = ConstU32<5>;
+	type RandomnessReuseWindow = RandomnessReuseWindow;
+	type MaxProvingJobLen = ConstU32<64>;
+	type MaxProvingJobs = ConstU32<2>;
+	type ProvingJobTtl = ConstU32<10>;
+	type ProvingClaimTtl = ConstU32<5>;
+	type ProverBond = ConstU128<PROVER_BOND>;
+	type MinProvingBounty = ConstU128<10>;
+	type WindDownPeriod = ConstU32<10>;
+	type MaxPayouts = ConstU32<3>;
+	type CoSignerOrigin = EnsureSignedBy<CoSigner, u64>;
+	type AdminApprovalTtl = ConstU64<10>;
+	type ProofVerifier = MockVerifier;
+	type AllowSignedXcmDeposits = AllowSignedXcmDeposits;
+	type UnsignedRelayFee = UnsignedRelayFee;
+	type MaxProofSize = ConstU32<{ zksnark::PROOF_LEN as u32 }>;
+}
+
+/// Bond a prover puts up to claim a proving job
+pub const PROVER_BOND: u128 = 50;
+
+/// Fee the sponsorship pot pays per sponsored deposit
+pub const SPONSORED_FEE: u128 = 10;
+
+/// Fee `ChargeFlatFee` takes from every signed transaction
+pub const FLAT_FEE: u128 = 20;
+
+/// Fee payment extension standing in for `ChargeTransactionPayment`, burning `FLAT_FEE`
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo, RuntimeDebug)]
+pub struct ChargeFlatFee;
+
+impl TransactionExtension<RuntimeCall> for ChargeFlatFee {
+	const IDENTIFIER: &'static str = "ChargeFlatFee";
+	type Implicit = ();
+	type Val = ();
+	type Pre = ();
+
+	fn weight(&self, _call: &RuntimeCall) -> Weight {
+		Weight::zero()
+	}
+
+	fn validate(
+		&self,
+		origin: RuntimeOrigin,
+		_call: &RuntimeCall,
+		_info: &DispatchInfoOf<RuntimeCall>,
+		_len: usize,
+		_self_implicit: (),
+		_inherited_implication: &impl Implication,
+		_source: TransactionSource,
+	) -> ValidateResult<(), RuntimeCall> {
+		Ok((ValidTransaction::default(), (), origin))
+	}
+
+	fn prepare(
+		self,
+		_val: (),
+		origin: &RuntimeOrigin,
+		_call: &RuntimeCall,
+		_info: &DispatchInfoOf<RuntimeCall>,
+		_len: usize,
+	) -> Result<(), TransactionValidityError> {
+		let who = origin.as_system_origin_signer().ok_or(InvalidTransaction::BadSigner)?;
+		<Balances as fungible::Mutate<u64>>::burn_from(
+			who,
+			FLAT_FEE,
+			Preservation::Preserve,
+			Precision::Exact,
+			Fortitude::Polite,
+		)
+		.map(|_| ())
+		.map_err(|_| InvalidTransaction::Payment.into())
+	}
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> TestState {
-	GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut storage = GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(DEPOSITOR, DEPOSITOR_BALANCE)],
+		..Default::default()
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	crate::GenesisConfig::<Test> {
+		// Withdrawals, bundle withdrawals and swaps need a verifying key
+		verifying_keys: vec![
+			(crate::CircuitId::Withdraw, TEST_VERIFYING_KEY.to_vec()),
+			(crate::CircuitId::Bundle, TEST_VERIFYING_KEY.to_vec()),
+			(crate::CircuitId::Swap, TEST_VERIFYING_KEY.to_vec()),
+		],
+		..Default::default()
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+	let mut ext: TestState = storage.into();
+	ext.execute_with(|| {
+		// Events are not recorded in block 0
+		System::set_block_number(1);
+		// Channels to the siblings used in tests
+		crate::OpenChannels::<Test>::put(BoundedVec::truncate_from(vec![2000, 3000]));
+	});
+	ext
+}
+
+/// Account making most deposits of the tests, funded in `new_test_ext`
+pub const DEPOSITOR: u64 = 1;
+
+/// Native balance `DEPOSITOR` starts with
+pub const DEPOSITOR_BALANCE: u128 = 1_000_000;
+
+/// Verifying key set in `new_test_ext`
+pub const TEST_VERIFYING_KEY: [u8; 4] = [0xab; 4];
+
+/// Advance to block `n`, running `on_initialize` for every block on the way
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		let next = System::block_number() + 1;
+		System::set_block_number(next);
+		PrivacyBridge::on_initialize(next);
+	}
+}
+
+/// Note key making the `SimpleHasher` commitment of a local note the reserved all-zero hash
+///
+/// `simple_hash` XORs every input byte into position `index % 32`, and the randomness
+/// follows the 20 bytes of amount and asset ID. Under XOR a note's zero-tweak randomness
+/// is its key, and an XCM note's is its key XOR the origin digest.
+pub fn zero_commitment_randomness(amount: u128, asset_id: u32) -> [u8; 32] {
+	let partial = crate::simple_hash::generate_commitment(amount, asset_id, &[0u8; 32]);
+	core::array::from_fn(|i| partial[(20 + i) % 32])
+}
+
+/// A log line captured by `capture_logs`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogLine {
+	pub level: log::Level,
+	pub target: String,
+	pub message: String,
+}
+
+std::thread_local! {
+	/// Lines logged on this thread while `capture_logs` runs
+	static CAPTURED_LOGS: core::cell::RefCell<Option<Vec<LogLine>>> = const {
+		core::cell::RefCell::new(None)
+	};
+}
+
+/// Logger keeping the lines of threads inside `capture_logs`
+struct CapturingLogger;
+
+impl log::Log for CapturingLogger {
+	fn enabled(&self, _metadata: &log::Metadata) -> bool {
+		CAPTURED_LOGS.with(|lines| lines.borrow().is_some())
+	}
+
+	fn log(&self, record: &log::Record) {
+		CAPTURED_LOGS.with(|lines| {
+			if let Some(lines) = lines.borrow_mut().as_mut() {
+				lines.push(LogLine {
+					level: record.level(),
+					target: record.target().into(),
+					message: record.args().to_string(),
+				});
+			}
+		});
+	}
+
+	fn flush(&self) {}
+}
+
+/// Run `f`, returning what it logged at debug level and above on this thread
+pub fn capture_logs<R>(f: impl FnOnce() -> R) -> (R, Vec<LogLine>) {
+	static INSTALL: std::sync::Once = std::sync::Once::new();
+	INSTALL.call_once(|| {
+		log::set_logger(&CapturingLogger).expect("no other logger in the tests");
+		log::set_max_level(log::LevelFilter::Debug);
+	});
+
+	CAPTURED_LOGS.with(|lines| *lines.borrow_mut() = Some(Vec::new()));
+	let result = f();
+	let lines = CAPTURED_LOGS.with(|lines| lines.borrow_mut().take()).unwrap_or_default();
+	(result, lines)
 }
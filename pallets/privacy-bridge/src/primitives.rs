@@ -0,0 +1,343 @@
+//! Public API of the shielded pool's notes
+//!
+//! The canonical derivation of commitments and nullifiers, and the encoding of proof
+//! public inputs, for wallets and downstream pallets. The pallet, the XCM deposit path and
+//! the proof verifier all derive through these functions, so code depending on this module
+//! computes exactly what the chain does.
+//!
+//! Everything here is re-exported or defined once and only changes with a breaking
+//! release; the older entry points (`Pallet::generate_commitment`,
+//! `xcm_config::xcm_commitment_data`, ...) remain as wrappers.
+//!
+//! ```ignore
+//! use pallet_privacy_bridge::primitives::{self, Blake2Hasher};
+//!
+//! let commitment = primitives::commitment::<Blake2Hasher>(amount, asset_id, &randomness);
+//! let nullifier = primitives::nullifier::<Blake2Hasher>(&commitment, &secret);
+//! ```
+
+use alloc::vec::Vec;
+use ark_bn254::Fr;
+use codec::Encode;
+use ark_ff::PrimeField;
+use sp_core::{ConstU32, H256};
+use sp_runtime::BoundedVec;
+
+pub use crate::error_codes::{error_description, UNKNOWN_ERROR_CODE};
+pub use crate::hasher::{Blake2Hasher, HasherId, Keccak256Hasher, NoteHasher, PoseidonHasher};
+
+/// Hash value reserved for empty tree nodes, never accepted as a commitment or nullifier
+///
+/// The all-zero hash is the commitment tree's empty-leaf padding: a commitment equal to it
+/// could be "proven" to sit in any empty slot.
+pub const RESERVED_HASH: H256 = H256([0u8; 32]);
+
+/// Whether `hash` is a reserved value
+pub fn is_reserved(hash: &H256) -> bool {
+	*hash == RESERVED_HASH
+}
+
+/// Commitment of a shielded note, a leaf of the commitment tree
+pub type Commitment = H256;
+
+/// Nullifier revealed when a note is spent
+pub type Nullifier = H256;
+
+/// Version of the XCM note derivation
+///
+/// 1: the randomness did not depend on the origin
+/// 2: the randomness bound the origin chain through Blake2-256
+/// 3: the randomness is `note_randomness` of the depositor's note key and the origin
+///    digest, which the circuits evaluate
+pub const XCM_COMMITMENT_VERSION: u8 = 3;
+
+/// Domain tag of multi-asset (bundle) notes
+pub const BUNDLE_COMMITMENT_TAG: u8 = 2;
+
+/// Most assets one bundle note holds
+pub const MAX_BUNDLE_SIZE: u32 = 4;
+
+/// Assets of a bundle note, `(asset_id, amount)` ordered by strictly increasing asset ID
+pub type NoteBundle = BoundedVec<(u32, u128), ConstU32<MAX_BUNDLE_SIZE>>;
+
+/// Commitment of a note shielded on this chain
+///
+/// Commitment = Hash(amount || asset_id || randomness)
+pub fn commitment<H: NoteHasher>(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> Commitment {
+	H::hash_commitment(amount, asset_id, randomness)
+}
+
+/// Key of the notes of `secret`, which a depositor shares instead of the secret
+///
+/// NoteKey = Hash(RESERVED_HASH || secret): the nullifier the secret would give the
+/// reserved empty leaf, which is never a commitment. A note's randomness derives from its
+/// key (`note_randomness`), and the circuits derive the key from the secret, so a note
+/// opens with exactly one secret, and has exactly one nullifier. Knowing the key, as
+/// anyone reading a deposit does, is not enough to spend the note.
+pub fn note_key<H: NoteHasher>(secret: &[u8; 32]) -> [u8; 32] {
+	H::hash_nullifier(&RESERVED_HASH, secret).0
+}
+
+/// Randomness of a note of `note_key`
+///
+/// NoteRandomness = Hash(note_key || tweak), a tree node's hash, the tweak being zero for a
+/// note shielded on this chain and the origin digest of an XCM deposit's note
+/// (`xcm_note_randomness`). Change outputs of transfers and swaps are committed to the
+/// randomness their recipient derives this way, so the recipient can spend them.
+pub fn note_randomness<H: NoteHasher>(note_key: &[u8; 32], tweak: &[u8; 32]) -> [u8; 32] {
+	H::hash_pair(&H256(*note_key), &H256(*tweak)).0
+}
+
+/// Commitment of a note shielded on this chain under `note_key`
+///
+/// NoteCommitment = commitment(amount, asset_id, note_randomness(note_key, 0))
+pub fn note_commitment<H: NoteHasher>(
+	amount: u128,
+	asset_id: u32,
+	note_key: &[u8; 32],
+) -> Commitment {
+	commitment::<H>(amount, asset_id, &note_randomness::<H>(note_key, &[0u8; 32]))
+}
+
+/// Randomness an XCM deposit's note is committed to and spent with
+///
+/// XcmRandomness = note_randomness(note_key, origin_digest), `note_key` being the deposit's
+/// message topic and `origin_digest` the Blake2-256 of the SCALE-encoded origin location
+/// (see `xcm_config::origin_digest`). The origin enters through its fixed-size digest, so
+/// the derivation costs the same whatever the origin.
+pub fn xcm_note_randomness<H: NoteHasher>(
+	note_key: &[u8; 32],
+	origin_digest: &[u8; 32],
+) -> [u8; 32] {
+	note_randomness::<H>(note_key, origin_digest)
+}
+
+/// Commitment of a note shielded by an XCM deposit from the origin of `origin_digest`
+///
+/// A local commitment over `xcm_note_randomness`, so a local and an XCM deposit with the
+/// same parameters create two distinct notes, as do deposits from two chains. The
+/// withdrawal circuit is the same for all of them; an XCM note is proven with the origin
+/// digest as its tweak.
+pub fn xcm_commitment<H: NoteHasher>(
+	amount: u128,
+	asset_id: u32,
+	note_key: &[u8; 32],
+	origin_digest: &[u8; 32],
+) -> Commitment {
+	commitment::<H>(amount, asset_id, &xcm_note_randomness::<H>(note_key, origin_digest))
+}
+
+/// Encoding of a bundle's assets, padded with `(0, 0)` to `MAX_BUNDLE_SIZE` entries
+///
+/// BundleAssets = (asset_id || amount) * MAX_BUNDLE_SIZE, little-endian. Also the public
+/// input a bundle withdrawal proof exposes its assets as.
+pub fn bundle_assets(assets: &[(u32, u128)]) -> Vec<u8> {
+	let mut data = Vec::with_capacity(MAX_BUNDLE_SIZE as usize * 20);
+	for index in 0..MAX_BUNDLE_SIZE as usize {
+		let (asset_id, amount) = assets.get(index).copied().unwrap_or_default();
+		data.extend_from_slice(&asset_id.to_le_bytes());
+		data.extend_from_slice(&amount.to_le_bytes());
+	}
+	data
+}
+
+/// Input bytes of a bundle commitment
+///
+/// BundlePreimage = BUNDLE_COMMITMENT_TAG || BundleAssets || randomness. Its length differs
+/// from a single note's preimage, so the two never share a commitment.
+pub fn bundle_preimage(assets: &[(u32, u128)], randomness: &[u8; 32]) -> Vec<u8> {
+	let mut data = Vec::new();
+	data.push(BUNDLE_COMMITMENT_TAG);
+	data.extend_from_slice(&bundle_assets(assets));
+	data.extend_from_slice(randomness);
+	data
+}
+
+/// Commitment of a note holding several assets
+///
+/// Only the first `MAX_BUNDLE_SIZE` entries of `assets` are committed to.
+pub fn bundle_commitment<H: NoteHasher>(assets: &[(u32, u128)], randomness: &[u8; 32]) -> Commitment {
+	H::hash_bundle_commitment(assets, randomness)
+}
+
+/// Nullifier of a note
+///
+/// Nullifier = Hash(commitment || secret)
+pub fn nullifier<H: NoteHasher>(commitment: &Commitment, secret: &[u8; 32]) -> Nullifier {
+	H::hash_nullifier(commitment, secret)
+}
+
+/// Tag a watch-only service can recognise a spent nullifier by
+///
+/// AlertTag = Blake2-256(nullifier || salt)
+///
+/// Registered with `register_alert_tags`; without the nullifier the tag cannot be spent.
+pub fn alert_tag(nullifier: &Nullifier, salt: &[u8; 32]) -> H256 {
+	let mut data = [0u8; 64];
+	data[..32].copy_from_slice(nullifier.as_bytes());
+	data[32..].copy_from_slice(salt);
+	H256(sp_io::hashing::blake2_256(&data))
+}
+
+/// What `RecentRandomness` keeps of a deposit's randomness
+///
+/// RandomnessCommitment = Blake2-256(randomness)
+pub fn randomness_commitment(randomness: &[u8; 32]) -> H256 {
+	H256(sp_io::hashing::blake2_256(randomness))
+}
+
+/// Identifier of a successful withdrawal, for reconciliation by integrators
+///
+/// WithdrawalReceiptId = Blake2-256(nullifier || block_number || extrinsic_index), the
+/// numbers little-endian. Ledgers can key withdrawals by it without handling nullifiers.
+pub fn withdrawal_receipt_id(
+	nullifier: &Nullifier,
+	block_number: u32,
+	extrinsic_index: u32,
+) -> H256 {
+	let mut data = [0u8; 40];
+	data[..32].copy_from_slice(nullifier.as_bytes());
+	data[32..36].copy_from_slice(&block_number.to_le_bytes());
+	data[36..].copy_from_slice(&extrinsic_index.to_le_bytes());
+	H256(sp_io::hashing::blake2_256(&data))
+}
+
+/// Public input binding a withdrawal proof to who is paid
+///
+/// RecipientHash = Blake2-256(SCALE(recipient)), `recipient` being the account a local
+/// withdrawal pays. A proof made for one recipient does not verify for another, so one
+/// copied from a pending transaction cannot redirect the payout.
+pub fn recipient_hash<R: Encode>(recipient: &R) -> H256 {
+	H256(sp_io::hashing::blake2_256(&recipient.encode()))
+}
+
+/// Public input binding a relayed withdrawal proof to who is paid, and how
+///
+/// RelayedRecipientHash = Blake2-256(SCALE((recipient, relayer, fee))), taking the place
+/// of `recipient_hash` in the proof of `withdraw_via_relayer`. The relayer cannot change
+/// the recipient, nor raise its own fee, without the proof failing.
+pub fn relayed_recipient_hash<R: Encode>(recipient: &R, relayer: &R, fee: u128) -> H256 {
+	H256(sp_io::hashing::blake2_256(&(recipient, relayer, fee).encode()))
+}
+
+/// Public input binding an anchored withdrawal proof to who is paid, and its anchor
+///
+/// AnchoredRecipientHash = Blake2-256(SCALE((recipient, anchor_block_hash))), taking the
+/// place of `recipient_hash` in the proof of `withdraw_anchored`, so the proof only holds
+/// while its anchor block is recent.
+pub fn anchored_recipient_hash<R: Encode>(recipient: &R, anchor_block_hash: &H256) -> H256 {
+	H256(sp_io::hashing::blake2_256(&(recipient, anchor_block_hash).encode()))
+}
+
+/// Public input binding a cross-chain withdrawal proof to where it pays out
+///
+/// RemoteRecipientHash = Blake2-256(SCALE((destination, beneficiary))), `beneficiary` being
+/// the recipient as the exporter takes it. Taking the place of `recipient_hash` in the
+/// proofs of `withdraw_to_remote` and `queue_withdrawal_to_remote`, it keeps a copied proof
+/// from sending the value to another chain or account.
+pub fn remote_recipient_hash<D: Encode>(destination: &D, beneficiary: &[u8]) -> H256 {
+	H256(sp_io::hashing::blake2_256(&(destination, beneficiary).encode()))
+}
+
+/// Public input binding a split withdrawal proof to its payouts
+///
+/// SplitRecipientHash = Blake2-256(SCALE(payouts)), taking the place of `recipient_hash` in
+/// the proof of `withdraw_split`: neither a recipient nor its share can change.
+pub fn split_recipient_hash<R: Encode>(payouts: &[(R, u128)]) -> H256 {
+	H256(sp_io::hashing::blake2_256(&payouts.encode()))
+}
+
+/// Append the field elements of a public input, the way `UInt8::new_input_vec` packs it
+///
+/// Bytes are packed little-endian, 31 to a field element.
+pub fn push_public_input(public_inputs: &mut Vec<Fr>, input: &[u8]) {
+	for chunk in input.chunks(31) {
+		let mut bytes = [0u8; 32];
+		bytes[..chunk.len()].copy_from_slice(chunk);
+		public_inputs.push(Fr::from_le_bytes_mod_order(&bytes));
+	}
+}
+
+/// Field elements of a proof's public inputs, in order
+pub fn public_inputs(inputs: &[&[u8]]) -> Vec<Fr> {
+	let mut public_inputs = Vec::new();
+	for input in inputs {
+		push_public_input(&mut public_inputs, input);
+	}
+	public_inputs
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_commitment_and_nullifier_use_the_hasher() {
+		let commitment = commitment::<Blake2Hasher>(1000, 1, &[42u8; 32]);
+		assert_eq!(commitment, Blake2Hasher::hash_commitment(1000, 1, &[42u8; 32]));
+		assert_eq!(
+			nullifier::<Blake2Hasher>(&commitment, &[7u8; 32]),
+			Blake2Hasher::hash_nullifier(&commitment, &[7u8; 32])
+		);
+	}
+
+	#[test]
+	fn test_xcm_commitment_is_domain_separated() {
+		let note_key = [42u8; 32];
+		let origin = [1u8; 32];
+
+		// Same parameters, different note than a local deposit
+		let xcm = xcm_commitment::<Blake2Hasher>(1000, 1, &note_key, &origin);
+		assert_ne!(xcm, note_commitment::<Blake2Hasher>(1000, 1, &note_key));
+
+		// It is the local commitment over the derived randomness
+		let derived = xcm_note_randomness::<Blake2Hasher>(&note_key, &origin);
+		assert_eq!(xcm, commitment::<Blake2Hasher>(1000, 1, &derived));
+
+		// ...which depends on the origin
+		assert_ne!(xcm, xcm_commitment::<Blake2Hasher>(1000, 1, &note_key, &[2u8; 32]));
+	}
+
+	#[test]
+	fn test_notes_derive_from_the_secret() {
+		let secret = [7u8; 32];
+		let note_key = note_key::<Blake2Hasher>(&secret);
+
+		// The key is the reserved leaf's nullifier, not the secret itself
+		assert_eq!(note_key, nullifier::<Blake2Hasher>(&RESERVED_HASH, &secret).0);
+		assert_ne!(note_key, secret);
+		assert_ne!(note_key, note_key::<Blake2Hasher>(&[8u8; 32]));
+
+		let randomness = note_randomness::<Blake2Hasher>(&note_key, &[0u8; 32]);
+		assert_eq!(
+			note_commitment::<Blake2Hasher>(1000, 1, &note_key),
+			commitment::<Blake2Hasher>(1000, 1, &randomness)
+		);
+	}
+
+	#[test]
+	fn test_bundle_commitment_is_domain_separated() {
+		let randomness = [42u8; 32];
+		let bundle = bundle_commitment::<Blake2Hasher>(&[(1, 1000)], &randomness);
+		assert_ne!(bundle, commitment::<Blake2Hasher>(1000, 1, &randomness));
+
+		// Padding entries are implicit, order is not
+		assert_eq!(bundle, bundle_commitment::<Blake2Hasher>(&[(1, 1000), (0, 0)], &randomness));
+		assert_ne!(
+			bundle_commitment::<Blake2Hasher>(&[(1, 10), (2, 20)], &randomness),
+			bundle_commitment::<Blake2Hasher>(&[(2, 20), (1, 10)], &randomness)
+		);
+		assert_eq!(bundle_assets(&[(1, 1000)]).len(), 80);
+	}
+
+	#[test]
+	fn test_public_inputs_pack_31_bytes_per_element() {
+		let input = [1u8; 32];
+		let elements = public_inputs(&[&input, &input]);
+
+		// 32 bytes take two elements each, the second holding the last byte
+		assert_eq!(elements.len(), 4);
+		assert_eq!(elements[1], Fr::from(1u8));
+		assert_eq!(elements[0], elements[2]);
+	}
+}
@@ -0,0 +1,38 @@
+//! Randomness of protocol-generated values
+//!
+//! Values the pallet generates itself (decoy notes, the order queued withdrawals are paid
+//! out in) draw on `Config::Randomness` through `Pallet::derive_randomness`, which
+//! separates every use by its context.
+//!
+//! `ParentHashRandomness` is a source for chains without a randomness beacon. It returns
+//! the parent block's hash, which the block's author knows in advance: enough for decoys
+//! to look like regular leaves and for the payout order not to follow the queue, not to
+//! keep anything from the collator.
+
+use core::marker::PhantomData;
+
+use frame::deps::{
+	frame_support::traits::Randomness,
+	frame_system::{self, pallet_prelude::BlockNumberFor},
+};
+use rand_chacha::{rand_core::RngCore, ChaCha20Rng};
+use sp_runtime::traits::{Hash, One, Saturating};
+
+/// Randomness of the parent block's hash and the subject
+pub struct ParentHashRandomness<T>(PhantomData<T>);
+
+impl<T: frame_system::Config> Randomness<T::Hash, BlockNumberFor<T>> for ParentHashRandomness<T> {
+	fn random(subject: &[u8]) -> (T::Hash, BlockNumberFor<T>) {
+		let parent_hash = frame_system::Pallet::<T>::parent_hash();
+		let known_since = frame_system::Pallet::<T>::block_number().saturating_sub(One::one());
+		(T::Hashing::hash_of(&(subject, parent_hash)), known_since)
+	}
+}
+
+/// Shuffle `items` in an order drawn from `rng` (Fisher-Yates)
+pub fn shuffle<I>(items: &mut [I], rng: &mut ChaCha20Rng) {
+	for i in (1..items.len()).rev() {
+		let j = rng.next_u64() % (i as u64 + 1);
+		items.swap(i, j as usize);
+	}
+}
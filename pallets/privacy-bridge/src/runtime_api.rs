@@ -0,0 +1,172 @@
+//! Runtime API of the privacy bridge
+//!
+//! Lets wallets check a cross-chain withdrawal before asking the user to sign it, read
+//! the pool's accounting and how well an asset's notes hide among each other, see how
+//! long a merkle root stays accepted and count their participation receipts, catch up on
+//! recent tree changes and look up withdrawals by receipt ID, read the tree's roots and
+//! the merkle path of their leaf to build proofs, and lets watchers export recently spent
+//! nullifiers and look for spends of the alert tags users registered:
+//!
+//! ```ignore
+//! impl pallet_privacy_bridge::runtime_api::PrivacyBridgeApi<Block, BlockNumber, AccountId>
+//!     for Runtime
+//! {
+//!     fn dry_run_cross_chain_withdrawal(
+//!         asset_id: u32,
+//!         amount: u128,
+//!         destination: Location,
+//!         beneficiary: Vec<u8>,
+//!     ) -> DryRunResult {
+//!         PrivacyBridge::dry_run_cross_chain_withdrawal(asset_id, amount, destination, beneficiary)
+//!     }
+//!
+//!     fn pool_stats(asset_id: u32) -> PoolStats {
+//!         PrivacyBridge::pool_stats(asset_id)
+//!     }
+//!
+//!     fn anonymity_score(asset_id: u32) -> AnonymityScore {
+//!         PrivacyBridge::anonymity_score(asset_id)
+//!     }
+//!
+//!     fn root_ttl(root: H256) -> Option<u32> {
+//!         PrivacyBridge::root_ttl(root)
+//!     }
+//!
+//!     fn nullifiers_spent_since(block: BlockNumber, limit: u32) -> Vec<(H256, BlockNumber)> {
+//!         PrivacyBridge::nullifiers_spent_since(block, limit)
+//!     }
+//!
+//!     fn receipt_count(account: AccountId) -> u32 {
+//!         PrivacyBridge::receipt_count(account)
+//!     }
+//!
+//!     fn commitment_info(commitment: H256) -> Option<PublicCommitmentInfo<BlockNumber>> {
+//!         PrivacyBridge::commitment_info(commitment)
+//!     }
+//!
+//!     fn match_alert_tags(
+//!         spent_since: BlockNumber,
+//!         queries: Vec<AlertTagQuery>,
+//!     ) -> Vec<AlertMatch<BlockNumber>> {
+//!         PrivacyBridge::match_alert_tags(spent_since, queries)
+//!     }
+//!
+//!     fn recent_actions(since_seq: u64) -> Option<Vec<ActionRecord<BlockNumber>>> {
+//!         PrivacyBridge::recent_actions(since_seq)
+//!     }
+//!
+//!     fn withdrawal_by_id(receipt_id: H256) -> Option<WithdrawalRecord<BlockNumber>> {
+//!         PrivacyBridge::withdrawal_by_id(receipt_id)
+//!     }
+//!
+//!     fn merkle_root() -> H256 {
+//!         PrivacyBridge::merkle_root()
+//!     }
+//!
+//!     fn known_roots() -> Vec<H256> {
+//!         PrivacyBridge::known_roots()
+//!     }
+//!
+//!     fn merkle_path(leaf_index: u32) -> Option<Vec<H256>> {
+//!         PrivacyBridge::merkle_path(leaf_index)
+//!     }
+//!
+//!     fn commitment_count() -> u32 {
+//!         PrivacyBridge::commitment_count()
+//!     }
+//! }
+//! ```
+//!
+//! Clients without the runtime's types call it through the `state_call` RPC, with the
+//! method named `PrivacyBridgeApi_<fn>` and the SCALE-encoded arguments. The path of leaf
+//! 5 (`5u32` is `0x05000000`) comes back as an encoded `Option<Vec<H256>>`:
+//!
+//! ```text
+//! curl -H 'Content-Type: application/json' http://localhost:9944 -d '{
+//!     "id": 1, "jsonrpc": "2.0", "method": "state_call",
+//!     "params": ["PrivacyBridgeApi_merkle_path", "0x05000000"]
+//! }'
+//! ```
+//!
+//! Responses never name the depositor of a commitment: commitments are described by
+//! `PublicCommitmentInfo`, and `AccountId` only appears as an input.
+
+use alloc::vec::Vec;
+use codec::Codec;
+use sp_core::H256;
+use staging_xcm::v5::Location;
+
+use crate::{
+	ActionRecord, AlertMatch, AlertTagQuery, AnonymityScore, DryRunResult, PoolStats,
+	PublicCommitmentInfo, WithdrawalRecord,
+};
+
+frame::deps::sp_api::decl_runtime_apis! {
+	/// Queries for wallets of the privacy bridge
+	pub trait PrivacyBridgeApi<BlockNumber, AccountId>
+	where
+		BlockNumber: Codec,
+		AccountId: Codec,
+	{
+		/// Check a cross-chain withdrawal of `amount` of local asset `asset_id` to
+		/// `beneficiary` on `destination`, returning the fee it would be charged and
+		/// every reason it would be rejected
+		fn dry_run_cross_chain_withdrawal(
+			asset_id: u32,
+			amount: u128,
+			destination: Location,
+			beneficiary: Vec<u8>,
+		) -> DryRunResult;
+
+		/// Pool accounting of local asset `asset_id`, including its reserve backing
+		fn pool_stats(asset_id: u32) -> PoolStats;
+
+		/// Anonymity score of local asset `asset_id`: its unspent notes and recent deposits
+		fn anonymity_score(asset_id: u32) -> AnonymityScore;
+
+		/// Number of further merkle root updates `root` remains accepted for, or `None`
+		/// if it is not a recent root
+		fn root_ttl(root: H256) -> Option<u32>;
+
+		/// Nullifiers spent from `block` on, with their spend blocks, in block order
+		///
+		/// Stops after the block that brings the result to `limit` entries; continue from
+		/// the block after the last one returned.
+		fn nullifiers_spent_since(block: BlockNumber, limit: u32) -> Vec<(H256, BlockNumber)>;
+
+		/// Number of participation receipts of `account` (see `deposit_with_receipt`)
+		fn receipt_count(account: AccountId) -> u32;
+
+		/// Block, asset and leaf index of `commitment`, if it is in the tree
+		fn commitment_info(commitment: H256) -> Option<PublicCommitmentInfo<BlockNumber>>;
+
+		/// Registered alert tags of `queries` whose nullifiers were spent from block
+		/// `spent_since` on, with their spend blocks, in block order
+		fn match_alert_tags(
+			spent_since: BlockNumber,
+			queries: Vec<AlertTagQuery>,
+		) -> Vec<AlertMatch<BlockNumber>>;
+
+		/// Leaf insertions, root updates and spends from sequence number `since_seq` on
+		///
+		/// `None` when they are no longer all kept: the caller has to resync from events.
+		fn recent_actions(since_seq: u64) -> Option<Vec<ActionRecord<BlockNumber>>>;
+
+		/// Record of the withdrawal with receipt ID `receipt_id`, as found in its
+		/// `AssetUnshielded` event
+		fn withdrawal_by_id(receipt_id: H256) -> Option<WithdrawalRecord<BlockNumber>>;
+
+		/// Current merkle root of the commitment tree
+		fn merkle_root() -> H256;
+
+		/// Roots withdrawal proofs are accepted against, newest first
+		fn known_roots() -> Vec<H256>;
+
+		/// Siblings of leaf `leaf_index` from the leaf up, leading to the current root, or
+		/// `None` if the leaf is not in the tree yet
+		fn merkle_path(leaf_index: u32) -> Option<Vec<H256>>;
+
+		/// Number of commitments ever inserted, so the index of the next leaf
+		fn commitment_count() -> u32;
+	}
+}
@@ -0,0 +1,117 @@
+//! Shielding and unshielding on behalf of other pallets
+//!
+//! Pallets of the same runtime (e.g. a DEX shielding a swap's output) shield value through
+//! `ShieldInterface`, without depending on this pallet's `Config`:
+//!
+//! ```ignore
+//! #[pallet::config]
+//! pub trait Config: frame_system::Config {
+//!     type Shield: ShieldInterface<Self::AccountId>;
+//! }
+//!
+//! #[pallet::weight(T::WeightInfo::swap().saturating_add(T::Shield::shield_weight()))]
+//! pub fn swap_and_shield(origin: OriginFor<T>, commitment: H256, ...) -> DispatchResult {
+//!     let who = ensure_signed(origin)?;
+//!     let (asset_id, amount) = Self::do_swap(&who, ...)?;
+//!     T::Shield::shield(who, commitment, asset_id, amount)
+//! }
+//! ```
+//!
+//! The caller charges `shield_weight()` on top of its own weight. Shielding only calls
+//! into the runtime's `Currency`/`Fungibles`, never back into the caller, so it is safe to
+//! call from within another pallet's extrinsic.
+//!
+//! Symmetrically, pallets that received a withdrawal proof (e.g. a governance recovery or
+//! fee rebate pallet) spend the note through `UnshieldInterface`, paying out locally or to
+//! a remote chain.
+
+use frame::prelude::*;
+use sp_core::H256;
+use alloc::vec::Vec;
+use staging_xcm::v5::Location;
+
+use crate::{Config, Pallet};
+
+/// Shield value into the privacy pool
+pub trait ShieldInterface<AccountId> {
+	/// Shield `amount` of local asset `asset_id` from `source` into `commitment`
+	///
+	/// `source` must have authorised the deposit; the commitment is computed off-chain
+	/// (see `primitives::commitment`).
+	fn shield(source: AccountId, commitment: H256, asset_id: u32, amount: u128) -> DispatchResult;
+
+	/// Weight to charge for one `shield`
+	fn shield_weight() -> Weight;
+}
+
+/// Proof submitted for a withdrawal, with the circuits it was made for
+#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ProofEnvelope {
+	/// `circuit::CIRCUIT_VERSION` of the circuits the proof was made with
+	pub circuit_version: u32,
+	/// Serialized proof
+	pub proof: Vec<u8>,
+}
+
+/// Public inputs of a withdrawal proof
+#[derive(Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct UnshieldInputs {
+	/// Nullifier of the note spent
+	pub nullifier: H256,
+	/// Commitment of the note spent
+	pub commitment: H256,
+	/// Local asset ID of the note
+	pub asset_id: u32,
+	/// Value of the note
+	pub amount: u128,
+	/// Hash of the recent block the proof is anchored to (see `Config::MaxProofAge`)
+	pub anchor_block_hash: Option<H256>,
+	/// Recent merkle root the proof is made against (see `RegisteredAsset::min_note_age`)
+	pub merkle_root: Option<H256>,
+}
+
+/// Where the value of an unshielded note goes
+#[derive(Encode, Decode, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum PayoutTarget<AccountId> {
+	/// An account of this chain
+	Local(AccountId),
+	/// A beneficiary (second) on a remote chain (first)
+	Remote(Location, Location),
+}
+
+/// Spend notes of the privacy pool
+pub trait UnshieldInterface<AccountId> {
+	/// Spend the note of `public_inputs` and pay its value to `payout`
+	///
+	/// Runs the same checks as the withdrawal extrinsics.
+	fn unshield(
+		proof_envelope: ProofEnvelope,
+		public_inputs: UnshieldInputs,
+		payout: PayoutTarget<AccountId>,
+	) -> DispatchResult;
+}
+
+impl<T: Config> ShieldInterface<T::AccountId> for Pallet<T> {
+	fn shield(
+		source: T::AccountId,
+		commitment: H256,
+		asset_id: u32,
+		amount: u128,
+	) -> DispatchResult {
+		Pallet::<T>::do_shield(source, commitment, asset_id, amount)
+	}
+
+	fn shield_weight() -> Weight {
+		Pallet::<T>::deposit_weight()
+	}
+}
+
+impl<T: Config> UnshieldInterface<T::AccountId> for Pallet<T> {
+	fn unshield(
+		proof_envelope: ProofEnvelope,
+		public_inputs: UnshieldInputs,
+		payout: PayoutTarget<T::AccountId>,
+	) -> DispatchResult {
+		Pallet::<T>::do_unshield(proof_envelope, public_inputs, payout)
+	}
+}
@@ -18,17 +18,28 @@
 //! ❌ NOT cryptographically secure
 //! ❌ Vulnerable to collision attacks
 //! ❌ Should NOT be used in production
+//!
+//! Only built for unit tests and with the `insecure-test-hash` feature, which a
+//! `production` build refuses. Runtimes hash through `Config::Hasher` (see `hasher`).
+//!
+//! ## Reserved values
+//!
+//! The all-zero hash (`RESERVED_HASH`) is the merkle tree's empty-leaf padding. A
+//! commitment equal to it could be "proven" to sit in any empty slot, and with XOR finding
+//! a preimage is trivial, so the pallet rejects zero commitments and zero nullifiers.
 
 use sp_core::H256;
 use alloc::vec::Vec;
 
+pub use crate::primitives::{is_reserved, RESERVED_HASH};
+
 /// Simple hash function using XOR
 ///
 /// This creates a deterministic 32-byte hash by XORing input bytes.
 /// Each input byte is XORed into the corresponding position (mod 32).
 ///
-/// Example:
-/// ```
+/// Example (with the `insecure-test-hash` feature):
+/// ```ignore
 /// use pallet_privacy_bridge::simple_hash::simple_hash_bytes;
 ///
 /// let data = b"hello world";
@@ -50,6 +61,15 @@ pub fn simple_hash(data: &[u8]) -> H256 {
 	H256::from(simple_hash_bytes(data))
 }
 
+/// Parent node of the circuits' merkle tree: the XOR of both children
+pub fn hash_pair(left: &H256, right: &H256) -> H256 {
+	let mut data = Vec::new();
+	data.extend_from_slice(left.as_bytes());
+	data.extend_from_slice(right.as_bytes());
+
+	simple_hash(&data)
+}
+
 /// Generate commitment using simple hash
 ///
 /// Commitment = Hash(amount || asset_id || randomness)
@@ -62,6 +82,14 @@ pub fn generate_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -
 	simple_hash(&data)
 }
 
+/// Generate the commitment of a multi-asset note using simple hash
+///
+/// BundleCommitment = Hash(BUNDLE_COMMITMENT_TAG || BundleAssets || randomness), see
+/// `primitives::bundle_preimage`
+pub fn generate_bundle_commitment(assets: &[(u32, u128)], randomness: &[u8; 32]) -> H256 {
+	simple_hash(&crate::primitives::bundle_preimage(assets, randomness))
+}
+
 /// Generate nullifier using simple hash
 ///
 /// Nullifier = Hash(commitment || secret)
@@ -73,6 +101,26 @@ pub fn generate_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
 	simple_hash(&data)
 }
 
+/// Generate the randomness of a note of `secret` using simple hash
+///
+/// NoteRandomness = Hash(Hash(RESERVED_HASH || secret) || tweak), see
+/// `primitives::note_randomness`
+pub fn generate_note_randomness(secret: &[u8; 32], tweak: &[u8; 32]) -> [u8; 32] {
+	let note_key = generate_nullifier(&RESERVED_HASH, secret);
+	hash_pair(&note_key, &H256(*tweak)).0
+}
+
+/// Generate the tag linking a delegated membership proof to an ownership proof
+///
+/// LinkTag = Hash(commitment || link_blinding)
+pub fn generate_link_tag(commitment: &H256, link_blinding: &[u8; 32]) -> H256 {
+	let mut data = Vec::new();
+	data.extend_from_slice(commitment.as_bytes());
+	data.extend_from_slice(link_blinding);
+
+	simple_hash(&data)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
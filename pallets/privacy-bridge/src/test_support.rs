@@ -0,0 +1,231 @@
+//! Shared fixtures of the pallet's tests
+//!
+//! A Groth16 setup takes seconds per circuit, and far longer once the circuits hash and
+//! check merkle paths in-circuit. Tests take their keys from `test_setup` and the other
+//! fixtures here, which run each setup at most once per process and keep the proving key
+//! on disk between runs, under `<temp dir>/cloak-test-keys/<circuit>-v<version>.bin`.
+//!
+//! Setups are deterministic, so a cached key only goes stale when a circuit changes.
+//! Bumping `CIRCUIT_VERSION` moves to new files; while a circuit is being reworked
+//! without a bump, run the tests with `CLOAK_REGENERATE_TEST_KEYS=1` to redo every setup
+//! (the fresh keys replace the cached ones).
+//!
+//! The mutations at the end tamper with withdrawal inputs the way an attacker would, for
+//! the fuzz harness and adversarial tests.
+
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::PathBuf,
+	sync::{Mutex, OnceLock},
+};
+
+use ark_bn254::Bn254;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use sp_core::H256;
+
+use crate::circuit::CIRCUIT_VERSION;
+use crate::hasher::SimpleHasher;
+use crate::merkle_tree::TREE_DEPTH;
+use crate::shield::{ProofEnvelope, UnshieldInputs};
+use crate::zksnark;
+
+/// Proving and verifying key of a circuit
+pub type Setup = (ProvingKey<Bn254>, VerifyingKey<Bn254>);
+
+/// Setups of this process, each run once
+type SetupCache = BTreeMap<(TestCircuit, u32), &'static OnceLock<Setup>>;
+
+/// Set to anything but `0` to ignore cached keys and run every setup again
+pub const REGENERATE_KEYS_VAR: &str = "CLOAK_REGENERATE_TEST_KEYS";
+
+/// Circuits with a test setup
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum TestCircuit {
+	Withdrawal,
+	Membership,
+	Ownership,
+	Bundle,
+	Swap,
+	Transfer,
+	Join,
+}
+
+impl TestCircuit {
+	fn name(self) -> &'static str {
+		match self {
+			TestCircuit::Withdrawal => "withdrawal",
+			TestCircuit::Membership => "membership",
+			TestCircuit::Ownership => "ownership",
+			TestCircuit::Bundle => "bundle",
+			TestCircuit::Swap => "swap",
+			TestCircuit::Transfer => "transfer",
+			TestCircuit::Join => "join",
+		}
+	}
+
+	fn generate(self) -> Result<Setup, String> {
+		match self {
+			TestCircuit::Withdrawal => zksnark::generate_setup_parameters::<SimpleHasher>(),
+			TestCircuit::Membership => zksnark::generate_membership_setup::<SimpleHasher>(),
+			TestCircuit::Ownership => zksnark::generate_ownership_setup::<SimpleHasher>(),
+			TestCircuit::Bundle => zksnark::generate_bundle_setup::<SimpleHasher>(),
+			TestCircuit::Swap => zksnark::generate_swap_setup::<SimpleHasher>(),
+			TestCircuit::Transfer => zksnark::generate_transfer_setup::<SimpleHasher>(),
+			TestCircuit::Join => zksnark::generate_join_setup::<SimpleHasher>(),
+		}
+	}
+}
+
+/// Keys of the withdrawal circuit (`generate_setup_parameters`)
+pub fn test_setup() -> &'static Setup {
+	setup_for(TestCircuit::Withdrawal, CIRCUIT_VERSION)
+}
+
+/// Keys of the delegated membership circuit (`generate_membership_setup`)
+pub fn membership_setup() -> &'static Setup {
+	setup_for(TestCircuit::Membership, CIRCUIT_VERSION)
+}
+
+/// Keys of the delegated ownership circuit (`generate_ownership_setup`)
+pub fn ownership_setup() -> &'static Setup {
+	setup_for(TestCircuit::Ownership, CIRCUIT_VERSION)
+}
+
+/// Keys of the bundle withdrawal circuit (`generate_bundle_setup`)
+pub fn bundle_setup() -> &'static Setup {
+	setup_for(TestCircuit::Bundle, CIRCUIT_VERSION)
+}
+
+/// Keys of the swap circuit (`generate_swap_setup`)
+pub fn swap_setup() -> &'static Setup {
+	setup_for(TestCircuit::Swap, CIRCUIT_VERSION)
+}
+
+/// Keys of the transfer circuit (`generate_transfer_setup`)
+pub fn transfer_setup() -> &'static Setup {
+	setup_for(TestCircuit::Transfer, CIRCUIT_VERSION)
+}
+
+/// Keys of the joined withdrawal circuit (`generate_join_setup`)
+pub fn join_setup() -> &'static Setup {
+	setup_for(TestCircuit::Join, CIRCUIT_VERSION)
+}
+
+/// Keys of `circuit`, cached under `circuit_version`
+///
+/// Setups always run the current circuits; the version only names the cache entry, so
+/// keys of an older version are never mistaken for the current ones. Concurrent tests
+/// asking for the same keys wait for one setup.
+pub fn setup_for(circuit: TestCircuit, circuit_version: u32) -> &'static Setup {
+	static SETUPS: OnceLock<Mutex<SetupCache>> = OnceLock::new();
+
+	let cell = *SETUPS
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.entry((circuit, circuit_version))
+		.or_insert_with(|| Box::leak(Box::new(OnceLock::new())));
+	cell.get_or_init(|| load_or_generate(circuit, circuit_version))
+}
+
+/// Whether `REGENERATE_KEYS_VAR` asks for fresh setups
+fn regenerate_keys() -> bool {
+	std::env::var_os(REGENERATE_KEYS_VAR).is_some_and(|value| value != "0")
+}
+
+fn key_path(circuit: TestCircuit, circuit_version: u32) -> PathBuf {
+	std::env::temp_dir()
+		.join("cloak-test-keys")
+		.join(format!("{}-v{}.bin", circuit.name(), circuit_version))
+}
+
+fn load_or_generate(circuit: TestCircuit, circuit_version: u32) -> Setup {
+	let path = key_path(circuit, circuit_version);
+	if !regenerate_keys() {
+		let cached = fs::read(&path).ok().and_then(|bytes| {
+			ProvingKey::<Bn254>::deserialize_uncompressed_unchecked(&bytes[..]).ok()
+		});
+		if let Some(pk) = cached {
+			let vk = pk.vk.clone();
+			return (pk, vk);
+		}
+	}
+
+	let (pk, vk) = circuit
+		.generate()
+		.unwrap_or_else(|error| panic!("setup of the {} circuit: {error}", circuit.name()));
+	store(&path, &pk);
+	(pk, vk)
+}
+
+/// Cache `pk` at `path`, best effort
+///
+/// Written to a temporary file first, so another test binary never reads half a key.
+fn store(path: &PathBuf, pk: &ProvingKey<Bn254>) {
+	let mut bytes = Vec::new();
+	if pk.serialize_uncompressed(&mut bytes).is_err() {
+		return;
+	}
+	let Some(dir) = path.parent() else {
+		return;
+	};
+	let partial = path.with_extension(format!("{}.partial", std::process::id()));
+	let stored = fs::create_dir_all(dir)
+		.and_then(|_| fs::write(&partial, &bytes))
+		.and_then(|_| fs::rename(&partial, path));
+	if stored.is_err() {
+		let _ = fs::remove_file(&partial);
+	}
+}
+
+/// Recipient hash test withdrawal proofs are made for
+pub const TEST_RECIPIENT_HASH: [u8; 32] = [0x42; 32];
+
+/// Circuit path of the only leaf of a tree (`merkle_path`, `path_indices`)
+///
+/// Hashing with a zero sibling is the identity, so the tree's root is the leaf itself.
+pub fn lone_leaf_path() -> (Vec<[u8; 32]>, Vec<bool>) {
+	(vec![[0u8; 32]; TREE_DEPTH], vec![false; TREE_DEPTH])
+}
+
+/// `hash` with its bit `bit` (modulo 256) flipped
+pub fn flip_hash_bit(hash: H256, bit: usize) -> H256 {
+	let mut bytes = hash.0;
+	bytes[bit / 8 % 32] ^= 1 << (bit % 8);
+	H256(bytes)
+}
+
+/// `envelope` with the bit `bit` (modulo the proof's length in bits) of its proof flipped
+pub fn flip_envelope_bit(envelope: &ProofEnvelope, bit: usize) -> ProofEnvelope {
+	let mut proof = envelope.proof.clone();
+	let bit = bit % (proof.len() * 8);
+	proof[bit / 8] ^= 1 << (bit % 8);
+	ProofEnvelope { circuit_version: envelope.circuit_version, proof }
+}
+
+/// `inputs` with the nullifier and the commitment swapped
+pub fn swap_public_inputs(inputs: UnshieldInputs) -> UnshieldInputs {
+	UnshieldInputs { nullifier: inputs.commitment, commitment: inputs.nullifier, ..inputs }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cached_keys_match_a_fresh_setup() {
+		let (pk, vk) = swap_setup();
+		assert!(core::ptr::eq(swap_setup(), setup_for(TestCircuit::Swap, CIRCUIT_VERSION)));
+
+		// What a later run loads is what the setup generates
+		let (fresh_pk, fresh_vk) = zksnark::generate_swap_setup::<SimpleHasher>().unwrap();
+		assert_eq!(*vk, fresh_vk);
+		assert_eq!(pk.vk, fresh_pk.vk);
+		let bytes = fs::read(key_path(TestCircuit::Swap, CIRCUIT_VERSION)).unwrap();
+		let loaded = ProvingKey::<Bn254>::deserialize_uncompressed_unchecked(&bytes[..]).unwrap();
+		assert!(loaded == fresh_pk);
+	}
+}
@@ -1,6 +1,33 @@
-use crate::{mock::*, Error, Pallet, CommitmentCount, Commitments, NullifierSet};
+use crate::{
+	mock::*, AbandonedClaims, AbandonmentRecords, Action, ActionRecord, AdminAction, AdminApprovals,
+	AlertMatch, AlertTagQuery, CircuitId, PauseLevel,
+	AlertTagRegistrations, AlertTags, AnonymityBucket, AnonymityScore, AssetRegistry, BundleNotes,
+	Claim, Claims, Error, Event, Pallet, CommitmentCount, CommitmentIndex, Commitments, DecoyNotes,
+	DecoySchedules, DenominationLeaves, DeniedCommitments, FilledSubtrees, MerkleLeaves,
+	NoteBackupDeposits, NoteBackups, NullifierSet, ParticipationReceipts, ProtocolOwnedShielded,
+	PublicCommitmentInfo, SpentNullifiers, SwapLeg, TotalShielded, WindDownPhase, WithdrawalRecord,
+};
+use frame::deps::frame_support::traits::{fungible::{Inspect, Mutate}, fungibles};
 use frame::testing_prelude::*;
 use sp_core::H256;
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	TokenError,
+};
+use crate::xcm_config::CustodyMode;
+use crate::hasher::{HasherId, NoteHasher, SimpleHasher};
+use crate::shield::{PayoutTarget, ProofEnvelope, UnshieldInputs};
+use crate::primitives::NoteBundle;
+use crate::extension::{
+	CheckDuplicateCommitment, CheckVerificationBudget, SponsorDeposit, DUPLICATE_COMMITMENT,
+};
+use frame::deps::frame_support::dispatch::DispatchInfo;
+use sp_runtime::traits::{DispatchTransaction, ValidateUnsigned};
+use frame::deps::frame_support::traits::UnfilteredDispatchable;
+use sp_runtime::transaction_validity::{
+	InvalidTransaction, TransactionSource, TransactionValidityError,
+};
+use staging_xcm::v5::{AssetId, Junction::{AccountId32, Parachain}, Location};
 
 #[test]
 fn deposit_creates_commitment() {
@@ -62,6 +89,190 @@ fn deposit_fails_for_duplicate_commitment() {
 	});
 }
 
+/// Validate `call` as a transaction of `who` with the duplicate commitment check
+fn validate_in_pool(who: u64, call: RuntimeCall) -> Result<(), TransactionValidityError> {
+	CheckDuplicateCommitment::<Test>::new()
+		.validate_only(
+			RuntimeOrigin::signed(who),
+			&call,
+			&DispatchInfo::default(),
+			0,
+			TransactionSource::External,
+		)
+		.map(|_| ())
+}
+
+#[test]
+fn duplicate_deposit_is_rejected_in_pool() {
+	new_test_ext().execute_with(|| {
+		let call = RuntimeCall::PrivacyBridge(crate::Call::deposit {
+			amount: 100,
+			asset_id: 0,
+			note_key: [1u8; 32],
+		});
+
+		// Valid until the commitment exists
+		assert_ok!(validate_in_pool(1, call.clone()));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_eq!(
+			validate_in_pool(1, call),
+			Err(InvalidTransaction::Custom(DUPLICATE_COMMITMENT).into())
+		);
+
+		// A split carrying an existing commitment is rejected as well
+		let existing = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let mut commitments = split_commitments(2);
+		commitments.push(existing);
+		let split = RuntimeCall::PrivacyBridge(crate::Call::deposit_split {
+			asset_id: 0,
+			commitments: commitments.try_into().unwrap(),
+			amount_each: 100,
+		});
+		assert_eq!(
+			validate_in_pool(1, split),
+			Err(InvalidTransaction::Custom(DUPLICATE_COMMITMENT).into())
+		);
+
+		// Other calls are not affected
+		let other = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+		assert_ok!(validate_in_pool(1, other));
+	});
+}
+
+#[test]
+fn participation_receipts_count_opted_in_deposits() {
+	new_test_ext().execute_with(|| {
+		// Plain deposits issue no receipt
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_eq!(PrivacyBridge::receipt_count(1), 0);
+
+		assert_ok!(PrivacyBridge::deposit_with_receipt(RuntimeOrigin::signed(1), 100, 0, [2u8; 32]));
+		assert_ok!(PrivacyBridge::deposit_with_receipt(RuntimeOrigin::signed(1), 700, 0, [3u8; 32]));
+		System::assert_last_event(Event::ParticipationReceiptIssued { account: 1, receipts: 2 }.into());
+		assert_eq!(PrivacyBridge::receipt_count(1), 2);
+		assert_eq!(PrivacyBridge::receipt_count(2), 0);
+		assert_eq!(CommitmentCount::<Test>::get(), 3);
+
+		// A failed deposit issues no receipt
+		assert_noop!(
+			PrivacyBridge::deposit_with_receipt(RuntimeOrigin::signed(1), 100, 0, [2u8; 32]),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+
+		// The receipt is a bare count: no amount, asset or commitment
+		assert_eq!(ParticipationReceipts::<Test>::iter().collect::<Vec<_>>(), vec![(1, 2)]);
+	});
+}
+
+/// Apply a native `deposit` of `who` as a transaction paying fees through `SponsorDeposit`
+fn apply_deposit(who: u64, amount: u128, key: u8) -> Result<(), TransactionValidityError> {
+	let call = RuntimeCall::PrivacyBridge(crate::Call::deposit {
+		amount,
+		asset_id: 0,
+		note_key: [key; 32],
+	});
+	SponsorDeposit::<Test, ChargeFlatFee>::new(ChargeFlatFee)
+		.dispatch_transaction(RuntimeOrigin::signed(who), call, &DispatchInfo::default(), 0)
+		.map(|result| assert_ok!(result))
+}
+
+/// Fund the sponsorship pot with `amount` from the treasury, and give users 1 to 4 a
+/// balance of 1000
+fn sponsorship_setup(amount: u128) {
+	assert_ok!(Balances::mint_into(&TREASURY, amount + EXISTENTIAL_DEPOSIT));
+	assert_noop!(
+		PrivacyBridge::top_up_sponsorship_pot(RuntimeOrigin::signed(1), amount),
+		DispatchError::BadOrigin
+	);
+	assert_ok!(PrivacyBridge::top_up_sponsorship_pot(RuntimeOrigin::root(), amount));
+	for who in 1..=4 {
+		Balances::set_balance(&who, 1000);
+	}
+}
+
+#[test]
+fn small_deposit_fee_is_sponsored() {
+	new_test_ext().execute_with(|| {
+		sponsorship_setup(50);
+		assert_eq!(Balances::balance(&Pallet::<Test>::sponsorship_account()), 50);
+
+		// At the threshold, the pot pays the fee
+		assert_ok!(apply_deposit(1, 100, 1));
+		System::assert_has_event(
+			Event::DepositSponsored { account: 1, amount: 100, fee: SPONSORED_FEE }.into(),
+		);
+		assert_eq!(Balances::balance(&1), 1000 - 100);
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 40);
+		assert_eq!(Balances::balance(&Pallet::<Test>::sponsorship_account()), 40);
+		assert_eq!(CommitmentCount::<Test>::get(), 1);
+	});
+}
+
+#[test]
+fn large_deposit_pays_own_fee() {
+	new_test_ext().execute_with(|| {
+		sponsorship_setup(50);
+
+		assert_ok!(apply_deposit(1, 101, 1));
+		assert_eq!(Balances::balance(&1), 1000 - 101 - FLAT_FEE);
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 50);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PrivacyBridge(Event::DepositSponsored { .. })
+		)));
+	});
+}
+
+#[test]
+fn sponsoring_stops_when_pot_is_exhausted() {
+	new_test_ext().execute_with(|| {
+		sponsorship_setup(15);
+
+		assert_ok!(apply_deposit(1, 10, 1));
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 5);
+
+		// The pot cannot cover another fee: the depositor pays
+		assert_ok!(apply_deposit(2, 10, 2));
+		assert_eq!(Balances::balance(&2), 1000 - 10 - FLAT_FEE);
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 5);
+
+		// Topping up resumes sponsoring
+		assert_ok!(Balances::mint_into(&TREASURY, 10));
+		assert_ok!(PrivacyBridge::top_up_sponsorship_pot(RuntimeOrigin::root(), 10));
+		System::assert_last_event(Event::SponsorshipPotToppedUp { amount: 10, pot: 15 }.into());
+		assert_ok!(apply_deposit(2, 10, 3));
+		assert_eq!(Balances::balance(&2), 1000 - 20 - FLAT_FEE);
+	});
+}
+
+#[test]
+fn sponsoring_is_rate_limited() {
+	new_test_ext().execute_with(|| {
+		sponsorship_setup(100);
+
+		// Two sponsored deposits per account and period
+		assert_ok!(apply_deposit(1, 10, 1));
+		assert_ok!(apply_deposit(1, 10, 2));
+		assert_ok!(apply_deposit(1, 10, 3));
+		assert_eq!(Balances::balance(&1), 1000 - 30 - FLAT_FEE);
+
+		// Three for all accounts together
+		assert_ok!(apply_deposit(2, 10, 4));
+		assert_ok!(apply_deposit(3, 10, 5));
+		assert_eq!(Balances::balance(&2), 1000 - 10);
+		assert_eq!(Balances::balance(&3), 1000 - 10 - FLAT_FEE);
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 70);
+
+		// Both limits reset with the next period
+		run_to_block(10);
+		assert_ok!(apply_deposit(1, 10, 6));
+		assert_ok!(apply_deposit(3, 10, 7));
+		assert_eq!(Balances::balance(&1), 1000 - 40 - FLAT_FEE);
+		assert_eq!(Balances::balance(&3), 1000 - 20 - FLAT_FEE);
+		assert_eq!(PrivacyBridge::sponsorship_pot(), 50);
+	});
+}
+
 #[test]
 fn commitment_hides_amount() {
 	new_test_ext().execute_with(|| {
@@ -109,14 +320,22 @@ fn withdraw_marks_nullifier_as_used() {
 		let user = 1u64;
 		let amount = 100u128;
 		let asset_id = 0u32;
+		let randomness = [3u8; 32];
 		let nullifier = H256::from([3u8; 32]);
 
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(user), amount, asset_id, randomness));
+		let commitment = Pallet::<Test>::generate_commitment(amount, asset_id, &randomness);
+
 		// Withdraw should succeed
 		assert_ok!(PrivacyBridge::withdraw(
 			RuntimeOrigin::signed(user),
 			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
 			amount,
-			asset_id
+			asset_id,
+			mock_proof(),
+			None
 		));
 
 		// Verify nullifier was marked as used
@@ -130,14 +349,22 @@ fn withdraw_fails_for_used_nullifier() {
 		let user = 1u64;
 		let amount = 100u128;
 		let asset_id = 0u32;
+		let randomness = [3u8; 32];
 		let nullifier = H256::from([3u8; 32]);
 
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(user), amount, asset_id, randomness));
+		let commitment = Pallet::<Test>::generate_commitment(amount, asset_id, &randomness);
+
 		// First withdraw succeeds
 		assert_ok!(PrivacyBridge::withdraw(
 			RuntimeOrigin::signed(user),
 			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
 			amount,
-			asset_id
+			asset_id,
+			mock_proof(),
+			None
 		));
 
 		// Second withdraw with same nullifier should fail (double-spend prevention)
@@ -145,14 +372,65 @@ fn withdraw_fails_for_used_nullifier() {
 			PrivacyBridge::withdraw(
 				RuntimeOrigin::signed(user),
 				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
 				amount,
-				asset_id
+				asset_id,
+				mock_proof(),
+				None
 			),
 			Error::<Test>::NullifierAlreadyUsed
 		);
 	});
 }
 
+#[test]
+fn spent_nullifiers_are_exported_by_block() {
+	new_test_ext().execute_with(|| {
+		fund_pool(10_000);
+		let spend = |seed: u8| {
+			let randomness = [seed; 32];
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, randomness));
+			let commitment = Pallet::<Test>::generate_commitment(100, 0, &randomness);
+			let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &randomness);
+			assert_ok!(PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			));
+			nullifier
+		};
+
+		// Two spends in block 1, none in block 2, one in block 3
+		let (first, second) = (spend(1), spend(2));
+		run_to_block(3);
+		let third = spend(3);
+
+		assert!(SpentNullifiers::<Test>::contains_key(1, first));
+		assert!(SpentNullifiers::<Test>::contains_key(1, second));
+		assert_eq!(SpentNullifiers::<Test>::iter_prefix(2).count(), 0);
+		assert!(SpentNullifiers::<Test>::contains_key(3, third));
+
+		let mut all = PrivacyBridge::nullifiers_spent_since(0, 10);
+		assert_eq!(all.pop(), Some((third, 3)));
+		all.sort();
+		let mut block_one = vec![(first, 1), (second, 1)];
+		block_one.sort();
+		assert_eq!(all, block_one);
+
+		// Pages end on a block boundary; the next page starts after it
+		assert_eq!(PrivacyBridge::nullifiers_spent_since(1, 1).len(), 2);
+		assert_eq!(PrivacyBridge::nullifiers_spent_since(2, 1), vec![(third, 3)]);
+		assert!(PrivacyBridge::nullifiers_spent_since(4, 10).is_empty());
+		assert!(PrivacyBridge::nullifiers_spent_since(0, 0).is_empty());
+	});
+}
+
 #[test]
 fn generate_commitment_is_deterministic() {
 	new_test_ext().execute_with(|| {
@@ -250,8 +528,12 @@ fn full_deposit_withdraw_cycle() {
 		assert_ok!(PrivacyBridge::withdraw(
 			RuntimeOrigin::signed(user),
 			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
 			amount,
-			asset_id
+			asset_id,
+			mock_proof(),
+			None
 		));
 
 		// Verify nullifier is used
@@ -261,3 +543,5697 @@ fn full_deposit_withdraw_cycle() {
 		assert!(Commitments::<Test>::contains_key(&commitment));
 	});
 }
+
+#[test]
+fn deposit_and_withdraw_update_total_shielded() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::mint_into(&2, 1000));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(2), 50, 0, [2u8; 32]));
+		assert_eq!(TotalShielded::<Test>::get(0), 150);
+
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(3),
+			H256::from([9u8; 32]),
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None
+		));
+		assert_eq!(TotalShielded::<Test>::get(0), 50);
+	});
+}
+
+#[test]
+fn schedule_decoys_requires_admin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrivacyBridge::schedule_decoys(RuntimeOrigin::signed(1), 0, 3, 2),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn schedule_decoys_rejects_zero_interval() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 0, 3, 0),
+			Error::<Test>::InvalidDecoySchedule
+		);
+	});
+}
+
+#[test]
+fn schedule_decoys_stores_schedule() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 5, 3, 2));
+
+		let schedule = DecoySchedules::<Test>::get(5).unwrap();
+		assert_eq!(schedule.remaining, 3);
+		assert_eq!(schedule.interval, 2);
+		assert_eq!(schedule.next_at, 3);
+		System::assert_last_event(Event::DecoysScheduled { asset_id: 5, count: 3, interval: 2 }.into());
+
+		// A zero count cancels the schedule
+		assert_ok!(PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 5, 0, 0));
+		assert!(DecoySchedules::<Test>::get(5).is_none());
+	});
+}
+
+#[test]
+fn decoys_are_created_at_the_scheduled_cadence() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 0, 3, 2));
+
+		run_to_block(2);
+		assert_eq!(CommitmentCount::<Test>::get(), 0);
+
+		run_to_block(3);
+		assert_eq!(CommitmentCount::<Test>::get(), 1);
+
+		run_to_block(4);
+		assert_eq!(CommitmentCount::<Test>::get(), 1);
+
+		run_to_block(5);
+		assert_eq!(CommitmentCount::<Test>::get(), 2);
+
+		run_to_block(7);
+		assert_eq!(CommitmentCount::<Test>::get(), 3);
+		assert!(DecoySchedules::<Test>::get(0).is_none());
+
+		// Schedule exhausted: no further decoys
+		run_to_block(12);
+		assert_eq!(CommitmentCount::<Test>::get(), 3);
+
+		// Decoys are owned by the treasury and accounted as protocol-owned value
+		assert_eq!(DecoyNotes::<Test>::iter().count(), 3);
+		for (commitment, note) in DecoyNotes::<Test>::iter() {
+			assert_eq!(Commitments::<Test>::get(&commitment).unwrap().depositor, TREASURY);
+			assert_eq!(note.value, DECOY_VALUE);
+		}
+		assert_eq!(TotalShielded::<Test>::get(0), 3 * DECOY_VALUE);
+		assert_eq!(ProtocolOwnedShielded::<Test>::get(0), 3 * DECOY_VALUE);
+	});
+}
+
+#[test]
+fn decoys_do_not_count_user_deposits_as_protocol_owned() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 0, 1, 1));
+
+		run_to_block(2);
+
+		assert_eq!(TotalShielded::<Test>::get(0), 100 + DECOY_VALUE);
+		assert_eq!(ProtocolOwnedShielded::<Test>::get(0), DECOY_VALUE);
+	});
+}
+
+#[test]
+fn treasury_reclaims_decoy() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::schedule_decoys(RuntimeOrigin::root(), 0, 1, 1));
+		run_to_block(2);
+
+		let (commitment, note) = DecoyNotes::<Test>::iter().next().unwrap();
+
+		// Only the admin origin may reclaim
+		assert_noop!(
+			PrivacyBridge::reclaim_decoy(RuntimeOrigin::signed(1), commitment),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(PrivacyBridge::reclaim_decoy(RuntimeOrigin::root(), commitment));
+
+		assert!(NullifierSet::<Test>::get(&note.nullifier));
+		assert!(DecoyNotes::<Test>::get(&commitment).is_none());
+		assert_eq!(TotalShielded::<Test>::get(0), 0);
+		assert_eq!(ProtocolOwnedShielded::<Test>::get(0), 0);
+		System::assert_last_event(
+			Event::DecoyReclaimed { commitment, asset_id: 0, value: DECOY_VALUE }.into()
+		);
+
+		// A decoy can only be reclaimed once
+		assert_noop!(
+			PrivacyBridge::reclaim_decoy(RuntimeOrigin::root(), commitment),
+			Error::<Test>::DecoyNotFound
+		);
+	});
+}
+
+#[test]
+fn reclaim_rejects_user_commitment() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::reclaim_decoy(RuntimeOrigin::root(), commitment),
+			Error::<Test>::DecoyNotFound
+		);
+	});
+}
+
+/// Register the native asset (local ID 0) with an abandonment period
+fn register_with_abandonment(period: u32) -> AssetId {
+	let asset_id = AssetId(Location::here());
+	assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 0));
+	assert_ok!(PrivacyBridge::set_abandonment_period(
+		RuntimeOrigin::root(),
+		asset_id.clone(),
+		Some(period)
+	));
+	asset_id
+}
+
+#[test]
+fn set_abandonment_period_validates_input() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+
+		assert_noop!(
+			PrivacyBridge::set_abandonment_period(RuntimeOrigin::signed(1), asset_id.clone(), Some(10)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			PrivacyBridge::set_abandonment_period(RuntimeOrigin::root(), asset_id.clone(), Some(10)),
+			Error::<Test>::AssetNotRegistered
+		);
+
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 0));
+		assert_noop!(
+			PrivacyBridge::set_abandonment_period(RuntimeOrigin::root(), asset_id.clone(), Some(0)),
+			Error::<Test>::InvalidAbandonmentPeriod
+		);
+
+		assert_ok!(PrivacyBridge::set_abandonment_period(RuntimeOrigin::root(), asset_id.clone(), Some(10)));
+		assert_eq!(AssetRegistry::<Test>::get(&asset_id).unwrap().abandonment_period, Some(10));
+		System::assert_last_event(Event::AbandonmentPeriodSet { asset_id, period: Some(10) }.into());
+	});
+}
+
+#[test]
+fn sweep_abandoned_fails_before_period() {
+	new_test_ext().execute_with(|| {
+		register_with_abandonment(100);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+
+		run_to_block(100);
+		assert_noop!(
+			PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NoteNotYetAbandoned
+		);
+	});
+}
+
+#[test]
+fn sweep_abandoned_credits_depositor_after_period() {
+	new_test_ext().execute_with(|| {
+		register_with_abandonment(100);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+
+		run_to_block(101);
+
+		// Anyone may sweep
+		assert_ok!(PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0));
+
+		assert!(DeniedCommitments::<Test>::get(&commitment));
+		assert!(AbandonmentRecords::<Test>::get(&commitment).is_none());
+		assert_eq!(AbandonedClaims::<Test>::get(1, 0), 500);
+		assert_eq!(TotalShielded::<Test>::get(0), 0);
+		System::assert_last_event(Event::AbandonedNoteSwept {
+			leaf_index: 0,
+			commitment,
+			asset_id: 0,
+			amount: 500,
+			claimant: 1,
+		}.into());
+
+		// A note can only be swept once
+		assert_noop!(
+			PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::CommitmentDenied
+		);
+	});
+}
+
+#[test]
+fn swept_note_cannot_be_withdrawn() {
+	new_test_ext().execute_with(|| {
+		register_with_abandonment(100);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		run_to_block(101);
+		assert_ok!(PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0));
+
+		// A late withdrawal for the swept leaf is blocked
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(1),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::CommitmentDenied
+		);
+	});
+}
+
+#[test]
+fn spent_note_cannot_be_swept() {
+	new_test_ext().execute_with(|| {
+		register_with_abandonment(100);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(1),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		run_to_block(101);
+		assert_noop!(
+			PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NoteAlreadySpent
+		);
+	});
+}
+
+#[test]
+fn spent_note_cannot_be_spent_with_another_nullifier() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(1),
+			Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]),
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None
+		));
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(1),
+				Pallet::<Test>::generate_nullifier(&commitment, &[3u8; 32]),
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None
+			),
+			Error::<Test>::NoteAlreadySpent
+		);
+	});
+}
+
+#[test]
+fn sweep_falls_back_to_treasury_when_metadata_pruned() {
+	new_test_ext().execute_with(|| {
+		register_with_abandonment(100);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+
+		// Simulate metadata pruning: the depositor is no longer known
+		Commitments::<Test>::remove(&commitment);
+
+		run_to_block(101);
+		assert_ok!(PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0));
+
+		assert_eq!(AbandonedClaims::<Test>::get(TREASURY, 0), 500);
+		assert_eq!(AbandonedClaims::<Test>::get(1, 0), 0);
+	});
+}
+
+#[test]
+fn sweep_requires_abandonment_record() {
+	new_test_ext().execute_with(|| {
+		// Deposited before the asset opted into abandonment: nothing retained
+		let asset_id = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 0));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::set_abandonment_period(RuntimeOrigin::root(), asset_id, Some(100)));
+
+		run_to_block(101);
+		assert_noop!(
+			PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NoteNotAbandonable
+		);
+		assert_noop!(
+			PrivacyBridge::sweep_abandoned(RuntimeOrigin::signed(2), 1),
+			Error::<Test>::LeafNotFound
+		);
+	});
+}
+
+/// Give the pool account enough native balance to pay out withdrawals
+fn fund_pool(amount: u128) {
+	assert_ok!(Balances::mint_into(&Pallet::<Test>::account_id(), amount));
+}
+
+#[test]
+fn withdraw_pays_out_from_pool() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		assert_eq!(Balances::balance(&2), 500);
+		assert_eq!(Balances::balance(&Pallet::<Test>::account_id()), 0);
+		assert_eq!(Claims::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn native_deposit_moves_balance_into_pool() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		assert_eq!(Balances::balance(&pool), 0);
+
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		assert_eq!(Balances::balance(&1), DEPOSITOR_BALANCE - 500);
+		assert_eq!(Balances::balance(&pool), 500);
+
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(Balances::balance(&2), 500);
+		assert_eq!(Balances::balance(&pool), 0);
+	});
+}
+
+#[test]
+fn native_deposit_requires_balance() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::mint_into(&2, 100));
+
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(2), 101, 0, [1u8; 32]),
+			Error::<Test>::InsufficientBalance
+		);
+		assert_eq!(CommitmentCount::<Test>::get(), 0);
+		assert_eq!(Balances::balance(&2), 100);
+
+		// The whole balance can be shielded
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(2), 100, 0, [1u8; 32]));
+		assert_eq!(Balances::balance(&2), 0);
+	});
+}
+
+#[test]
+fn failed_payout_creates_claim() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		// Below the existential deposit: paying a fresh account fails
+		let amount = EXISTENTIAL_DEPOSIT - 1;
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), amount, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(amount, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(7),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			amount,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		// The nullifier is consumed and the value is claimable instead of lost
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_eq!(Balances::balance(&7), 0);
+		assert_eq!(
+			Claims::<Test>::get(0),
+			Some(Claim { asset_id: 0, amount, beneficiary: 7 })
+		);
+		System::assert_last_event(
+			Event::ClaimCreated { claim_id: 0, asset_id: 0, amount, beneficiary: 7 }.into()
+		);
+	});
+}
+
+#[test]
+fn claim_redeems_once_payout_succeeds() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let amount = EXISTENTIAL_DEPOSIT - 1;
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), amount, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(amount, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(7),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			amount,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		// Still below the existential deposit
+		assert_noop!(
+			PrivacyBridge::claim(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::ClaimNotRedeemable
+		);
+
+		// Once the beneficiary exists, anyone can redeem the claim
+		assert_ok!(Balances::mint_into(&7, EXISTENTIAL_DEPOSIT));
+		assert_ok!(PrivacyBridge::claim(RuntimeOrigin::signed(3), 0));
+
+		assert_eq!(Balances::balance(&7), EXISTENTIAL_DEPOSIT + amount);
+		assert_eq!(Claims::<Test>::get(0), None);
+		System::assert_last_event(
+			Event::ClaimRedeemed { claim_id: 0, asset_id: 0, amount, beneficiary: 7 }.into()
+		);
+
+		assert_noop!(
+			PrivacyBridge::claim(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::ClaimNotFound
+		);
+	});
+}
+
+/// Local ID of the non-native test asset
+const TEST_ASSET: u32 = 1;
+
+/// Create the non-native test asset and give `who` a balance of it
+fn create_test_asset(who: u64, balance: u128) {
+	assert_ok!(Assets::force_create(RuntimeOrigin::root(), TEST_ASSET, who, true, 1));
+	assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(TEST_ASSET, &who, balance));
+}
+
+#[test]
+fn non_native_shield_unshield_cycle() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 0);
+		assert_eq!(Claims::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn non_native_deposit_requires_balance() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 100);
+
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 101, TEST_ASSET, [1u8; 32]),
+			TokenError::FundsUnavailable
+		);
+		assert_eq!(CommitmentCount::<Test>::get(), 0);
+	});
+}
+
+#[test]
+fn non_native_payout_shortfall_becomes_claim() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		// The pool only holds 400 of the asset
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 0);
+		assert_eq!(
+			Claims::<Test>::get(0),
+			Some(Claim { asset_id: TEST_ASSET, amount: 500, beneficiary: 2 })
+		);
+	});
+}
+
+/// Register an XCM asset whose local ID is `TEST_ASSET`
+///
+/// Local ID 0 is shared with the native asset, so a placeholder registration takes it.
+fn register_test_asset(custody_mode: CustodyMode) {
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		AssetId(Location::here()),
+		0,
+		CustodyMode::Pot,
+		0
+	));
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		AssetId(Location::parent()),
+		0,
+		custody_mode,
+		0
+	));
+	assert_eq!(Pallet::<Test>::custody_mode(TEST_ASSET), custody_mode);
+}
+
+#[test]
+fn sibling_pallet_shields_inside_its_extrinsic() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+
+		assert_ok!(ShieldCaller::swap_and_shield(RuntimeOrigin::signed(1), commitment, TEST_ASSET, 400));
+
+		assert_eq!(crate::mock::shield_caller::Swaps::<Test>::get(), 1);
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+		System::assert_last_event(
+			Event::AssetShielded {
+				commitment,
+				asset_id: TEST_ASSET,
+				depositor: 1,
+				block_number: 1,
+				leaf_index: 0,
+				merkle_root: PrivacyBridge::merkle_root(),
+			}
+			.into(),
+		);
+
+		// A failed shield fails the caller's extrinsic as a whole
+		assert_noop!(
+			ShieldCaller::swap_and_shield(RuntimeOrigin::signed(1), commitment, TEST_ASSET, 400),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn failed_do_shield_leaves_no_changes() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		assert_ok!(Pallet::<Test>::do_shield(1, commitment, TEST_ASSET, 400));
+
+		// The duplicate fails after its transfer; a caller carrying on keeps no part of it
+		assert_eq!(
+			Pallet::<Test>::do_shield(1, commitment, TEST_ASSET, 400),
+			Err(Error::<Test>::CommitmentAlreadyExists.into())
+		);
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+	});
+}
+
+#[test]
+fn do_shield_refuses_deactivated_asset_and_small_deposits() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		register_test_asset(CustodyMode::Pot);
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::update_asset_min_deposit(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			100,
+		));
+		assert_ok!(PrivacyBridge::set_asset_status(RuntimeOrigin::root(), asset_id.clone(), false));
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+
+		// Other pallets shielding through `ShieldInterface` are held to `deposit`'s checks
+		assert_noop!(
+			Pallet::<Test>::do_shield(1, commitment, TEST_ASSET, 400),
+			Error::<Test>::AssetInactive
+		);
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]),
+			Error::<Test>::AssetInactive
+		);
+
+		assert_ok!(PrivacyBridge::set_asset_status(RuntimeOrigin::root(), asset_id, true));
+		assert_noop!(
+			Pallet::<Test>::do_shield(1, commitment, TEST_ASSET, 99),
+			Error::<Test>::DepositBelowMinimum
+		);
+		assert_ok!(Pallet::<Test>::do_shield(1, commitment, TEST_ASSET, 400));
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+	});
+}
+
+/// Commitments of `count` notes of 100 units of the test asset
+fn split_commitments(count: u8) -> Vec<H256> {
+	(1..=count)
+		.map(|seed| Pallet::<Test>::generate_commitment(100, TEST_ASSET, &[seed; 32]))
+		.collect()
+}
+
+#[test]
+fn deposit_split_shields_each_note() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		let commitments = split_commitments(4);
+
+		assert_ok!(PrivacyBridge::deposit_split(
+			RuntimeOrigin::signed(1),
+			TEST_ASSET,
+			commitments.clone().try_into().unwrap(),
+			100
+		));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+		let shielded: Vec<(H256, u32, H256)> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::PrivacyBridge(Event::AssetShielded {
+					commitment,
+					asset_id: TEST_ASSET,
+					depositor: 1,
+					block_number: 1,
+					leaf_index,
+					merkle_root,
+				}) => Some((commitment, leaf_index, merkle_root)),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(shielded.len(), commitments.len());
+		for (index, commitment) in commitments.iter().enumerate() {
+			assert_eq!(CommitmentIndex::<Test>::get(commitment), Some(index as u32));
+			assert_eq!((shielded[index].0, shielded[index].1), (*commitment, index as u32));
+		}
+		// Each note carries the root it was inserted under, the last one the current root
+		assert_eq!(shielded[3].2, PrivacyBridge::merkle_root());
+		assert_ne!(shielded[2].2, shielded[3].2);
+		System::assert_last_event(
+			Event::DepositSplit {
+				depositor: 1,
+				asset_id: TEST_ASSET,
+				amount_each: 100,
+				count: 4,
+				first_leaf_index: 0,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn deposit_split_is_atomic() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		let mut commitments = split_commitments(3);
+
+		// A commitment listed twice
+		commitments.push(commitments[0]);
+		assert_noop!(
+			PrivacyBridge::deposit_split(
+				RuntimeOrigin::signed(1),
+				TEST_ASSET,
+				commitments.clone().try_into().unwrap(),
+				100
+			),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+
+		// A commitment already in the tree
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, TEST_ASSET, [3u8; 32]));
+		commitments.pop();
+		assert_noop!(
+			PrivacyBridge::deposit_split(
+				RuntimeOrigin::signed(1),
+				TEST_ASSET,
+				commitments.try_into().unwrap(),
+				100
+			),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn deposit_split_enforces_minimum_per_note() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			AssetId(Location::parent()),
+			150,
+			CustodyMode::Pot,
+			0
+		));
+
+		// 4 x 100 is above the minimum in total, but not per note
+		assert_noop!(
+			PrivacyBridge::deposit_split(
+				RuntimeOrigin::signed(1),
+				TEST_ASSET,
+				split_commitments(4).try_into().unwrap(),
+				100
+			),
+			Error::<Test>::DepositBelowMinimum
+		);
+		assert_noop!(
+			PrivacyBridge::deposit_split(RuntimeOrigin::signed(1), TEST_ASSET, Default::default(), 100),
+			Error::<Test>::EmptySplit
+		);
+	});
+}
+
+/// Submit `commitments` as a batch of account 1 worth `total_amount` of the test asset
+fn submit_batch(commitments: &[H256], total_amount: u128) -> DispatchResult {
+	PrivacyBridge::submit_commitment_batch(
+		RuntimeOrigin::signed(1),
+		commitments.to_vec().try_into().unwrap(),
+		crate::merkle_tree::root_of::<MockHasher>(commitments).unwrap(),
+		total_amount,
+		TEST_ASSET,
+	)
+}
+
+#[test]
+fn commitment_batches_require_a_registered_batcher() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		let commitments = split_commitments(4);
+
+		assert_noop!(submit_batch(&commitments[..2], 200), Error::<Test>::NotBatcher);
+		assert_noop!(
+			PrivacyBridge::register_batcher(RuntimeOrigin::signed(1), 1),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(PrivacyBridge::register_batcher(RuntimeOrigin::root(), 1));
+		System::assert_last_event(Event::BatcherRegistered { batcher: 1 }.into());
+		assert_ok!(submit_batch(&commitments[..2], 200));
+
+		assert_ok!(PrivacyBridge::remove_batcher(RuntimeOrigin::root(), 1));
+		System::assert_last_event(Event::BatcherRemoved { batcher: 1 }.into());
+		assert_noop!(submit_batch(&commitments[2..], 200), Error::<Test>::NotBatcher);
+		assert_noop!(
+			PrivacyBridge::remove_batcher(RuntimeOrigin::root(), 1),
+			Error::<Test>::NotBatcher
+		);
+	});
+}
+
+#[test]
+fn commitment_batches_are_checked_and_limited() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		assert_ok!(PrivacyBridge::register_batcher(RuntimeOrigin::root(), 1));
+		let commitments = split_commitments(9);
+
+		// The root must be of exactly the listed commitments
+		assert_noop!(
+			PrivacyBridge::submit_commitment_batch(
+				RuntimeOrigin::signed(1),
+				commitments[..3].to_vec().try_into().unwrap(),
+				crate::merkle_tree::root_of::<MockHasher>(&commitments[..2]).unwrap(),
+				300,
+				TEST_ASSET,
+			),
+			Error::<Test>::BatchRootMismatch
+		);
+		assert_noop!(submit_batch(&[], 0), Error::<Test>::EmptyBatch);
+
+		// At most `MaxBatch` (4) per batch, enforced by decoding
+		assert!(BoundedVec::<H256, <Test as crate::Config>::MaxBatch>::try_from(
+			commitments[..5].to_vec()
+		)
+		.is_err());
+
+		// At most `MaxBatchLeavesPerBlock` (6) per block, across batches
+		assert_ok!(submit_batch(&commitments[..4], 400));
+		assert_noop!(submit_batch(&commitments[4..7], 300), Error::<Test>::BatchLimitExceeded);
+		assert_ok!(submit_batch(&commitments[4..6], 200));
+
+		run_to_block(2);
+		assert_ok!(submit_batch(&commitments[6..], 300));
+		assert_eq!(CommitmentCount::<Test>::get(), 9);
+	});
+}
+
+#[test]
+fn commitment_batch_pulls_total_in_one_transfer() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		assert_ok!(PrivacyBridge::register_batcher(RuntimeOrigin::root(), 1));
+		let commitments = split_commitments(4);
+		System::reset_events();
+
+		assert_ok!(submit_batch(&commitments, 400));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+		let transfers = System::events()
+			.iter()
+			.filter(|record| {
+				matches!(record.event, RuntimeEvent::Assets(pallet_assets::Event::Transferred { .. }))
+			})
+			.count();
+		assert_eq!(transfers, 1);
+
+		for (index, commitment) in commitments.iter().enumerate() {
+			assert_eq!(CommitmentIndex::<Test>::get(commitment), Some(index as u32));
+		}
+		System::assert_last_event(
+			Event::CommitmentBatchSubmitted {
+				batcher: 1,
+				asset_id: TEST_ASSET,
+				batch_root: crate::merkle_tree::root_of::<MockHasher>(&commitments).unwrap(),
+				total_amount: 400,
+				count: 4,
+				first_leaf_index: 0,
+			}
+			.into(),
+		);
+	});
+}
+
+/// Note backup of `BACKUP_LEN` bytes filled with `byte`
+fn backup(byte: u8) -> BoundedVec<u8, <Test as crate::Config>::MaxBackupLen> {
+	vec![byte; BACKUP_LEN as usize].try_into().unwrap()
+}
+
+#[test]
+fn note_backup_holds_a_deposit_per_byte() {
+	new_test_ext().execute_with(|| {
+		let deposits = Pallet::<Test>::backup_deposit_account();
+		Balances::set_balance(&1, 1000);
+
+		assert_ok!(PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), backup(7)));
+		assert_eq!(PrivacyBridge::note_backup(1), Some(backup(7)));
+		assert_eq!(NoteBackupDeposits::<Test>::get(1), 64);
+		assert_eq!(Balances::balance(&1), 1000 - 64);
+		assert_eq!(Balances::balance(&deposits), 64);
+		System::assert_last_event(Event::NoteBackupSet { account: 1, deposit: 64 }.into());
+
+		assert_ok!(PrivacyBridge::clear_note_backup(RuntimeOrigin::signed(1)));
+		assert!(!NoteBackups::<Test>::contains_key(1));
+		assert!(!NoteBackupDeposits::<Test>::contains_key(1));
+		assert_eq!(Balances::balance(&1), 1000);
+		System::assert_last_event(Event::NoteBackupCleared { account: 1, refunded: 64 }.into());
+		assert_noop!(
+			PrivacyBridge::clear_note_backup(RuntimeOrigin::signed(1)),
+			Error::<Test>::NoNoteBackup
+		);
+
+		// The deposit must be affordable
+		assert_noop!(
+			PrivacyBridge::set_note_backup(RuntimeOrigin::signed(2), backup(7)),
+			TokenError::FundsUnavailable
+		);
+	});
+}
+
+#[test]
+fn note_backups_must_be_exactly_the_backup_size() {
+	new_test_ext().execute_with(|| {
+		Balances::set_balance(&1, 1000);
+
+		let short = vec![7u8; BACKUP_LEN as usize - 1];
+		assert_noop!(
+			PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), short.try_into().unwrap()),
+			Error::<Test>::BackupSizeMismatch
+		);
+		assert_noop!(
+			PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), BoundedVec::new()),
+			Error::<Test>::BackupSizeMismatch
+		);
+
+		// Longer blobs do not decode
+		assert!(BoundedVec::<u8, <Test as crate::Config>::MaxBackupLen>::try_from(vec![
+			7u8;
+			BACKUP_LEN as usize + 1
+		])
+		.is_err());
+	});
+}
+
+#[test]
+fn replacing_a_note_backup_settles_the_deposit_difference() {
+	new_test_ext().execute_with(|| {
+		Balances::set_balance(&1, 1000);
+		assert_ok!(PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), backup(1)));
+
+		// Same rate: the held deposit covers the new blob
+		assert_ok!(PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), backup(2)));
+		assert_eq!(PrivacyBridge::note_backup(1), Some(backup(2)));
+		assert_eq!(Balances::balance(&1), 1000 - 64);
+
+		// A higher rate takes only the difference, a lower one refunds it
+		BackupDepositPerByte::set(3);
+		assert_ok!(PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), backup(3)));
+		assert_eq!(NoteBackupDeposits::<Test>::get(1), 96);
+		assert_eq!(Balances::balance(&1), 1000 - 96);
+
+		BackupDepositPerByte::set(1);
+		assert_ok!(PrivacyBridge::set_note_backup(RuntimeOrigin::signed(1), backup(4)));
+		assert_eq!(NoteBackupDeposits::<Test>::get(1), 32);
+		assert_eq!(Balances::balance(&1), 1000 - 32);
+		System::assert_last_event(Event::NoteBackupSet { account: 1, deposit: 32 }.into());
+
+		// Clearing returns what is held, whatever the current rate
+		BackupDepositPerByte::set(5);
+		assert_ok!(PrivacyBridge::clear_note_backup(RuntimeOrigin::signed(1)));
+		assert_eq!(Balances::balance(&1), 1000);
+		assert_eq!(Balances::balance(&Pallet::<Test>::backup_deposit_account()), 0);
+	});
+}
+
+/// Second local asset for bundle tests
+const SECOND_ASSET: u32 = 2;
+
+#[test]
+fn bundle_shield_unshield_cycle() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		fund_pool(1000);
+		create_test_asset(1, 1000);
+		let bundle: NoteBundle = vec![(0, 300), (TEST_ASSET, 400)].try_into().unwrap();
+
+		// Bundles must be ordered by asset ID
+		let unordered: NoteBundle = vec![(TEST_ASSET, 400), (0, 300)].try_into().unwrap();
+		assert_noop!(
+			PrivacyBridge::deposit_bundle(RuntimeOrigin::signed(1), unordered, [5u8; 32]),
+			Error::<Test>::InvalidBundle
+		);
+
+		assert_ok!(PrivacyBridge::deposit_bundle(RuntimeOrigin::signed(1), bundle.clone(), [5u8; 32]));
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(TotalShielded::<Test>::get(0), 300);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+
+		let randomness = crate::primitives::note_randomness::<MockHasher>(&[5u8; 32], &[0u8; 32]);
+		let commitment = crate::primitives::bundle_commitment::<MockHasher>(&bundle, &randomness);
+		assert!(BundleNotes::<Test>::get(commitment));
+		System::assert_last_event(
+			Event::BundleShielded {
+				commitment,
+				asset_ids: vec![0, TEST_ASSET].try_into().unwrap(),
+				depositor: 1,
+				block_number: 1,
+				leaf_index: 0,
+			}
+			.into(),
+		);
+
+		// The note is only spent as a whole
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				300,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteKindMismatch
+		);
+
+		assert_ok!(PrivacyBridge::withdraw_bundle(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			bundle.clone(),
+			mock_proof()
+		));
+		assert_eq!(Balances::balance(&2), 300);
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 0);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 0);
+		assert_eq!(Claims::<Test>::iter().count(), 0);
+
+		assert_noop!(
+			PrivacyBridge::withdraw_bundle(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				bundle,
+				mock_proof()
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+	});
+}
+
+/// Verify proofs with Groth16 from now on, against the withdrawal key and the keys of
+/// `circuits` of the test setups, all hashing with `SimpleHasher`
+fn verify_with_test_keys(circuits: &[(CircuitId, &crate::test_support::Setup)]) {
+	MockHasherId::set(HasherId::Simple);
+	VerifyProofs::set(true);
+	let withdraw = (CircuitId::Withdraw, crate::test_support::test_setup());
+	for (circuit, (_, vk)) in core::iter::once(&withdraw).chain(circuits) {
+		rotate_verifying_key(*circuit, crate::zksnark::serialize_vk(vk).unwrap(), HasherId::Simple);
+	}
+}
+
+#[test]
+fn withdraw_bundle_pays_only_the_proven_recipient() {
+	new_test_ext().execute_with(|| {
+		let (pk, _) = crate::test_support::bundle_setup();
+		verify_with_test_keys(&[(CircuitId::Bundle, crate::test_support::bundle_setup())]);
+		fund_pool(1000);
+		create_test_asset(1, 1000);
+		let bundle: NoteBundle = vec![(0, 300), (TEST_ASSET, 400)].try_into().unwrap();
+		let secret = [2u8; 32];
+		let note_key = crate::primitives::note_key::<MockHasher>(&secret);
+		assert_ok!(PrivacyBridge::deposit_bundle(
+			RuntimeOrigin::signed(1),
+			bundle.clone(),
+			note_key
+		));
+		let randomness = crate::primitives::note_randomness::<MockHasher>(&note_key, &[0u8; 32]);
+		let commitment = crate::primitives::bundle_commitment::<MockHasher>(&bundle, &randomness);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+		let proof = crate::zksnark::generate_bundle_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			bundle.to_vec(),
+			crate::primitives::recipient_hash(&2u64).as_bytes().to_vec(),
+			[0u8; 32],
+			secret,
+		)
+		.unwrap();
+		let withdraw_bundle = |who| {
+			PrivacyBridge::withdraw_bundle(
+				RuntimeOrigin::signed(who),
+				nullifier,
+				commitment,
+				bundle.clone(),
+				BoundedVec::truncate_from(proof.clone()),
+			)
+		};
+
+		// Submitted by anyone else, the proof pays no one
+		assert_noop!(withdraw_bundle(3), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw_bundle(2));
+		assert_eq!(Balances::balance(&2), 300);
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+	});
+}
+
+#[test]
+fn bundle_deposit_with_partial_balance_rolls_back() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), SECOND_ASSET, 1, true, 1));
+		assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(SECOND_ASSET, &1, 100));
+
+		// The first asset transfers, the second cannot
+		let bundle: NoteBundle = vec![(TEST_ASSET, 400), (SECOND_ASSET, 500)].try_into().unwrap();
+		assert_noop!(
+			PrivacyBridge::deposit_bundle(RuntimeOrigin::signed(1), bundle, [5u8; 32]),
+			TokenError::FundsUnavailable
+		);
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 1000);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 0);
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 0);
+		assert_eq!(CommitmentCount::<Test>::get(), 0);
+	});
+}
+
+/// Key of the note `swap_leg` spends for `seed`, whose secret is `[seed + 100; 32]`
+fn swap_note_key(seed: u8) -> [u8; 32] {
+	crate::primitives::note_key::<MockHasher>(&[seed + 100; 32])
+}
+
+/// Leg of a `shielded_swap` spending the note `(amount, asset_id)` of `seed` into the note
+/// of the key `[output_key; 32]`
+fn swap_leg(amount: u128, asset_id: u32, seed: u8, output_key: u8) -> SwapLeg {
+	let commitment = Pallet::<Test>::generate_commitment(amount, asset_id, &swap_note_key(seed));
+	SwapLeg {
+		proof_envelope: current_envelope(),
+		nullifier: Pallet::<Test>::generate_nullifier(&commitment, &[seed + 100; 32]),
+		commitment,
+		asset_id,
+		output_commitment: Pallet::<Test>::generate_commitment(
+			amount,
+			asset_id,
+			&[output_key; 32],
+		),
+	}
+}
+
+/// Notes of 400 `TEST_ASSET` of account 1 and 300 native of account 2 to swap
+fn swap_setup() -> (SwapLeg, SwapLeg) {
+	create_test_asset(1, 1000);
+	fund_pool(1000);
+	assert_ok!(Balances::mint_into(&2, 1000));
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, swap_note_key(1)));
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(2), 300, 0, swap_note_key(2)));
+	(swap_leg(400, TEST_ASSET, 1, 3), swap_leg(300, 0, 2, 4))
+}
+
+#[test]
+fn shielded_swap_exchanges_notes() {
+	new_test_ext().execute_with(|| {
+		let (first, second) = swap_setup();
+
+		assert_ok!(PrivacyBridge::shielded_swap(
+			RuntimeOrigin::signed(3),
+			first.clone(),
+			second.clone(),
+			5
+		));
+		System::assert_last_event(
+			Event::NotesSwapped {
+				nullifiers: [first.nullifier, second.nullifier],
+				output_commitments: [first.output_commitment, second.output_commitment],
+				leaf_indices: [2, 3],
+				block_number: 1,
+			}
+			.into(),
+		);
+		assert!(NullifierSet::<Test>::get(first.nullifier));
+		assert!(NullifierSet::<Test>::get(second.nullifier));
+		assert_eq!(Commitments::<Test>::get(first.output_commitment).unwrap().asset_id, TEST_ASSET);
+		assert_eq!(Commitments::<Test>::get(second.output_commitment).unwrap().asset_id, 0);
+
+		// No value left the pool
+		assert_eq!(TotalShielded::<Test>::get(TEST_ASSET), 400);
+		assert_eq!(TotalShielded::<Test>::get(0), 300);
+
+		// Account 2 now owns the `TEST_ASSET` note, and the spent note is gone
+		let nullifier = Pallet::<Test>::generate_nullifier(&first.output_commitment, &[9u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			first.output_commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(1),
+				first.nullifier,
+				first.commitment,
+				PrivacyBridge::merkle_root(),
+				400,
+				TEST_ASSET,
+				mock_proof(),
+				None
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+	});
+}
+
+#[test]
+fn shielded_swap_with_one_bad_leg_rolls_back() {
+	new_test_ext().execute_with(|| {
+		let (first, second) = swap_setup();
+		let swap = |second: SwapLeg| {
+			PrivacyBridge::shielded_swap(RuntimeOrigin::signed(3), first.clone(), second, 5)
+		};
+
+		// A proof for other circuits
+		let mut stale = second.clone();
+		stale.proof_envelope.circuit_version += 1;
+		assert_noop!(swap(stale), Error::<Test>::CircuitVersionMismatch);
+
+		// A note that does not exist: the first leg's spend is undone
+		assert_noop!(swap(swap_leg(300, 0, 7, 4)), Error::<Test>::CommitmentNotFound);
+
+		// A leg claiming another asset than its note's
+		let mut relabelled = second.clone();
+		relabelled.asset_id = 7;
+		assert_noop!(swap(relabelled), Error::<Test>::SwapAssetMismatch);
+
+		// Both legs of one asset
+		assert_noop!(swap(swap_leg(400, TEST_ASSET, 1, 4)), Error::<Test>::InvalidSwap);
+
+		run_to_block(6);
+		assert_noop!(swap(second.clone()), Error::<Test>::SwapExpired);
+		assert!(!NullifierSet::<Test>::get(first.nullifier));
+		assert_eq!(CommitmentCount::<Test>::get(), 2);
+	});
+}
+
+/// `swap_leg`, proven with the swap setup
+fn proven_swap_leg(amount: u128, asset_id: u32, seed: u8, output_key: u8) -> SwapLeg {
+	let (pk, _) = crate::test_support::swap_setup();
+	let leg = swap_leg(amount, asset_id, seed, output_key);
+	let proof = crate::zksnark::generate_swap_proof::<SimpleHasher>(
+		pk,
+		leg.nullifier.as_bytes().to_vec(),
+		leg.commitment.as_bytes().to_vec(),
+		leg.output_commitment.as_bytes().to_vec(),
+		amount,
+		asset_id,
+		[0u8; 32],
+		[seed + 100; 32],
+		crate::primitives::note_randomness::<MockHasher>(&[output_key; 32], &[0u8; 32]),
+	)
+	.unwrap();
+	SwapLeg { proof_envelope: ProofEnvelope { proof, ..current_envelope() }, ..leg }
+}
+
+#[test]
+fn shielded_swap_verifies_both_legs() {
+	new_test_ext().execute_with(|| {
+		verify_with_test_keys(&[(CircuitId::Swap, crate::test_support::swap_setup())]);
+		swap_setup();
+		let first = proven_swap_leg(400, TEST_ASSET, 1, 3);
+		let second = proven_swap_leg(300, 0, 2, 4);
+		let swap = |second: SwapLeg| {
+			PrivacyBridge::shielded_swap(RuntimeOrigin::signed(3), first.clone(), second, 5)
+		};
+
+		// The second note paid into a note its proof does not create
+		let redirected = SwapLeg {
+			output_commitment: Pallet::<Test>::generate_commitment(300, 0, &[5u8; 32]),
+			..second.clone()
+		};
+		assert_noop!(swap(redirected), Error::<Test>::InvalidProof);
+		// The first leg's proof does not spend the second note
+		let borrowed = SwapLeg { proof_envelope: first.proof_envelope.clone(), ..second.clone() };
+		assert_noop!(swap(borrowed), Error::<Test>::InvalidProof);
+
+		assert_ok!(swap(second.clone()));
+		assert!(NullifierSet::<Test>::get(first.nullifier));
+		assert!(NullifierSet::<Test>::get(second.nullifier));
+	});
+}
+
+/// Deposit a native note of 100 and prove its split into notes of 60 and 40 with the
+/// transfer setup, verifying proofs with Groth16 from now on:
+/// (nullifier, outputs, merkle root, proof)
+fn proven_transfer() -> (H256, [H256; 2], H256, crate::zksnark::ProofBytes) {
+	let (pk, vk) = crate::test_support::transfer_setup();
+	MockHasherId::set(HasherId::Simple);
+	VerifyProofs::set(true);
+	rotate_verifying_key(
+		CircuitId::Transfer,
+		crate::zksnark::serialize_vk(vk).unwrap(),
+		HasherId::Simple,
+	);
+
+	let secret = [2u8; 32];
+	let note_key = crate::primitives::note_key::<MockHasher>(&secret);
+	let output_keys = [[3u8; 32], [4u8; 32]];
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, note_key));
+	let commitment = Pallet::<Test>::generate_commitment(100, 0, &note_key);
+	let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+	let outputs = [
+		Pallet::<Test>::generate_commitment(60, 0, &output_keys[0]),
+		Pallet::<Test>::generate_commitment(40, 0, &output_keys[1]),
+	];
+	let leaves: Vec<H256> =
+		(0..CommitmentCount::<Test>::get()).filter_map(MerkleLeaves::<Test>::get).collect();
+	let leaf_index = CommitmentIndex::<Test>::get(commitment).unwrap() as usize;
+	let (siblings, path_indices) =
+		crate::merkle_tree::generate_circuit_proof(&leaves, leaf_index).unwrap();
+	let merkle_root = PrivacyBridge::merkle_root();
+	let proof = crate::zksnark::generate_transfer_proof::<SimpleHasher>(
+		pk,
+		nullifier.as_bytes().to_vec(),
+		merkle_root.as_bytes().to_vec(),
+		0,
+		outputs.map(|output| output.as_bytes().to_vec()),
+		100,
+		[0u8; 32],
+		secret,
+		siblings.into_iter().map(|sibling| sibling.0).collect(),
+		path_indices,
+		[60, 40],
+		output_keys.map(|key| crate::primitives::note_randomness::<MockHasher>(&key, &[0u8; 32])),
+	)
+	.unwrap();
+	(nullifier, outputs, merkle_root, BoundedVec::truncate_from(proof))
+}
+
+#[test]
+fn private_transfer_splits_a_note() {
+	new_test_ext().execute_with(|| {
+		let (nullifier, outputs, merkle_root, proof) = proven_transfer();
+		let transfer = |proof, outputs: [H256; 2]| {
+			PrivacyBridge::private_transfer(
+				RuntimeOrigin::signed(2),
+				proof,
+				nullifier,
+				outputs[0],
+				outputs[1],
+				merkle_root,
+				0,
+			)
+		};
+
+		// The proof covers neither other outputs nor another asset
+		let other_outputs = [outputs[0], H256::repeat_byte(9)];
+		assert_noop!(transfer(proof.clone(), other_outputs), Error::<Test>::InvalidProof);
+		assert_noop!(
+			PrivacyBridge::private_transfer(
+				RuntimeOrigin::signed(2),
+				proof.clone(),
+				nullifier,
+				outputs[0],
+				outputs[1],
+				merkle_root,
+				TEST_ASSET,
+			),
+			Error::<Test>::InvalidProof
+		);
+
+		assert_ok!(transfer(proof.clone(), outputs));
+		System::assert_last_event(
+			Event::PrivateTransfer {
+				nullifier,
+				output_commitments: outputs,
+				leaf_indices: [1, 2],
+			}
+			.into(),
+		);
+		assert!(NullifierSet::<Test>::get(nullifier));
+		for output in outputs {
+			assert_eq!(Commitments::<Test>::get(output).unwrap().asset_id, 0);
+		}
+		// No value left the pool
+		assert_eq!(TotalShielded::<Test>::get(0), 100);
+	});
+}
+
+#[test]
+fn private_transfer_rejects_spent_notes_and_existing_outputs() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let existing = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let merkle_root = PrivacyBridge::merkle_root();
+		let transfer = |nullifier: H256, outputs: [H256; 2]| {
+			PrivacyBridge::private_transfer(
+				RuntimeOrigin::signed(2),
+				mock_proof(),
+				nullifier,
+				outputs[0],
+				outputs[1],
+				merkle_root,
+				0,
+			)
+		};
+		let outputs = [H256::repeat_byte(3), H256::repeat_byte(4)];
+
+		// Outputs already in the tree, or twice the same output
+		assert_noop!(
+			transfer(H256::repeat_byte(1), [outputs[0], existing]),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+		assert_noop!(
+			transfer(H256::repeat_byte(1), [outputs[0], outputs[0]]),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+		// A root the tree never had
+		assert_noop!(
+			PrivacyBridge::private_transfer(
+				RuntimeOrigin::signed(2),
+				mock_proof(),
+				H256::repeat_byte(1),
+				outputs[0],
+				outputs[1],
+				H256::repeat_byte(8),
+				0,
+			),
+			Error::<Test>::UnknownMerkleRoot
+		);
+
+		rotate_verifying_key(CircuitId::Transfer, TEST_VERIFYING_KEY.to_vec(), HasherId::Blake2);
+		assert_ok!(transfer(H256::repeat_byte(1), outputs));
+
+		// The nullifier is spent, whatever the outputs
+		assert_noop!(
+			transfer(H256::repeat_byte(1), [H256::repeat_byte(5), H256::repeat_byte(6)]),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+	});
+}
+
+/// Deposit native notes of 60 and 40 and prove their joined withdrawal of `amount` to
+/// account 3 with the join setup, verifying proofs with Groth16 from now on:
+/// (nullifiers, commitments, proof)
+fn proven_join(amount: u128) -> ([H256; 2], [H256; 2], crate::zksnark::ProofBytes) {
+	let (pk, vk) = crate::test_support::join_setup();
+	MockHasherId::set(HasherId::Simple);
+	VerifyProofs::set(true);
+	rotate_verifying_key(
+		CircuitId::JoinSplit,
+		crate::zksnark::serialize_vk(vk).unwrap(),
+		HasherId::Simple,
+	);
+
+	let secrets = [[2u8; 32], [4u8; 32]];
+	let keys = secrets.map(|secret| crate::primitives::note_key::<MockHasher>(&secret));
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 60, 0, keys[0]));
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 40, 0, keys[1]));
+	let notes = [
+		Pallet::<Test>::generate_commitment(60, 0, &keys[0]),
+		Pallet::<Test>::generate_commitment(40, 0, &keys[1]),
+	];
+	let nullifiers = [
+		Pallet::<Test>::generate_nullifier(&notes[0], &secrets[0]),
+		Pallet::<Test>::generate_nullifier(&notes[1], &secrets[1]),
+	];
+	let leaves: Vec<H256> =
+		(0..CommitmentCount::<Test>::get()).filter_map(MerkleLeaves::<Test>::get).collect();
+	let path = |note: H256| {
+		let leaf_index = CommitmentIndex::<Test>::get(note).unwrap() as usize;
+		let (siblings, path_indices) =
+			crate::merkle_tree::generate_circuit_proof(&leaves, leaf_index).unwrap();
+		(siblings.into_iter().map(|sibling| sibling.0).collect::<Vec<_>>(), path_indices)
+	};
+	let (first_path, first_indices) = path(notes[0]);
+	let (second_path, second_indices) = path(notes[1]);
+	let proof = crate::zksnark::generate_join_proof::<SimpleHasher>(
+		pk,
+		nullifiers.map(|nullifier| nullifier.as_bytes().to_vec()),
+		notes.map(|note| note.as_bytes().to_vec()),
+		PrivacyBridge::merkle_root().as_bytes().to_vec(),
+		0,
+		amount,
+		crate::primitives::recipient_hash(&3u64).as_bytes().to_vec(),
+		[60, 40],
+		[[0u8; 32]; 2],
+		secrets,
+		[first_path, second_path],
+		[first_indices, second_indices],
+	)
+	.unwrap();
+	(nullifiers, notes, BoundedVec::truncate_from(proof))
+}
+
+#[test]
+fn withdraw_join_pays_out_both_notes() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let ([nullifier_a, nullifier_b], [commitment_a, commitment_b], proof) = proven_join(100);
+		let join = |amount: u128, recipient: u64| {
+			PrivacyBridge::withdraw_join(
+				RuntimeOrigin::signed(2),
+				proof.clone(),
+				nullifier_a,
+				nullifier_b,
+				commitment_a,
+				commitment_b,
+				amount,
+				0,
+				recipient,
+			)
+		};
+
+		// The proof covers the sum of the notes, paid to its recipient, nothing more
+		assert_noop!(join(101, 3), Error::<Test>::InvalidProof);
+		assert_noop!(join(100, 2), Error::<Test>::InvalidProof);
+
+		assert_ok!(join(100, 3));
+		assert!(NullifierSet::<Test>::get(nullifier_a));
+		assert!(NullifierSet::<Test>::get(nullifier_b));
+		assert!(crate::SpentCommitments::<Test>::get(commitment_a));
+		assert!(crate::SpentCommitments::<Test>::get(commitment_b));
+		assert_eq!(Balances::balance(&3), 100);
+		assert_eq!(TotalShielded::<Test>::get(0), 0);
+		System::assert_last_event(
+			Event::NotesJoined {
+				nullifiers: [nullifier_a, nullifier_b],
+				asset_id: 0,
+				block_number: 1,
+				receipt_id: Pallet::<Test>::withdrawal_receipt_id(nullifier_a),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn withdraw_join_with_one_spent_note_marks_neither() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [3u8; 32]));
+		let withdrawn = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let unspent = Pallet::<Test>::generate_commitment(100, 0, &[3u8; 32]);
+		let spent = Pallet::<Test>::generate_nullifier(&withdrawn, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(1),
+			spent,
+			withdrawn,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None
+		));
+		rotate_verifying_key(CircuitId::JoinSplit, TEST_VERIFYING_KEY.to_vec(), HasherId::Blake2);
+		let (fresh, other) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		let join = |nullifiers: [H256; 2], commitments: [H256; 2]| {
+			PrivacyBridge::withdraw_join(
+				RuntimeOrigin::signed(2),
+				mock_proof(),
+				nullifiers[0],
+				nullifiers[1],
+				commitments[0],
+				commitments[1],
+				200,
+				0,
+				3,
+			)
+		};
+
+		// Whichever nullifier is spent, the other one stays unused
+		assert_noop!(
+			join([fresh, spent], [unspent, withdrawn]),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert_noop!(
+			join([spent, fresh], [withdrawn, unspent]),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		// A spent note under a nullifier never seen is still spent
+		assert_noop!(join([fresh, other], [unspent, withdrawn]), Error::<Test>::NoteAlreadySpent);
+		// Nor can one note be counted twice
+		assert_noop!(
+			join([fresh, fresh], [unspent, withdrawn]),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+		assert_noop!(join([fresh, other], [unspent, unspent]), Error::<Test>::NoteAlreadySpent);
+		assert!(!NullifierSet::<Test>::get(fresh));
+		assert!(!crate::SpentCommitments::<Test>::get(unspent));
+	});
+}
+
+#[test]
+fn pot_custody_locks_value_in_pool() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		register_test_asset(CustodyMode::Pot);
+
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 400);
+		assert_eq!(<Assets as fungibles::Inspect<u64>>::total_issuance(TEST_ASSET), 1000);
+		assert_ok!(Pallet::<Test>::do_try_state());
+
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 0);
+		assert_ok!(Pallet::<Test>::do_try_state());
+	});
+}
+
+#[test]
+fn burn_mint_custody_keeps_supply_honest() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		create_test_asset(1, 1000);
+		register_test_asset(CustodyMode::BurnMint);
+
+		// Shielding burns the deposit
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		assert_eq!(Assets::balance(TEST_ASSET, 1), 600);
+		assert_eq!(Assets::balance(TEST_ASSET, pool), 0);
+		assert_eq!(<Assets as fungibles::Inspect<u64>>::total_issuance(TEST_ASSET), 600);
+		assert_ok!(Pallet::<Test>::do_try_state());
+
+		// Unshielding mints it back to the beneficiary
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 400);
+		assert_eq!(<Assets as fungibles::Inspect<u64>>::total_issuance(TEST_ASSET), 1000);
+		assert_ok!(Pallet::<Test>::do_try_state());
+	});
+}
+
+#[test]
+fn try_state_detects_custody_mismatch() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		register_test_asset(CustodyMode::Pot);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+
+		// Shielded value the pool does not hold
+		TotalShielded::<Test>::insert(TEST_ASSET, 500);
+		assert!(Pallet::<Test>::do_try_state().is_err());
+	});
+}
+
+#[test]
+fn dust_payout_to_fresh_account_rejected_before_spend() {
+	new_test_ext().execute_with(|| {
+		ClaimDustPayouts::set(false);
+		fund_pool(1000);
+		let amount = EXISTENTIAL_DEPOSIT - 1;
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), amount, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(amount, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(7),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				amount,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::BelowExistentialDeposit
+		);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+
+		// An existing account can receive the same amount
+		assert_ok!(Balances::mint_into(&7, EXISTENTIAL_DEPOSIT));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(7),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			amount,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(Balances::balance(&7), EXISTENTIAL_DEPOSIT + amount);
+	});
+}
+
+#[test]
+fn payout_above_existential_deposit_to_fresh_account() {
+	new_test_ext().execute_with(|| {
+		ClaimDustPayouts::set(false);
+		fund_pool(1000);
+		let amount = EXISTENTIAL_DEPOSIT;
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), amount, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(amount, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(7),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			amount,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(Balances::balance(&7), amount);
+		assert_eq!(Claims::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn withdrawal_creates_fresh_native_account() {
+	new_test_ext().execute_with(|| {
+		let pool = Pallet::<Test>::account_id();
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let issuance = Balances::total_issuance();
+		assert!(!System::account_exists(&42));
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(42),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		assert!(System::account_exists(&42));
+		assert_eq!(System::providers(&42), 1);
+		assert_eq!(Balances::balance(&42), 100);
+		assert_eq!(Balances::balance(&pool), 1000);
+		assert_eq!(Balances::total_issuance(), issuance);
+	});
+}
+
+#[test]
+fn withdrawal_creates_fresh_account_for_sufficient_asset() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert!(!System::account_exists(&42));
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(42),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+
+		// A sufficient asset keeps the account alive on its own
+		assert!(System::account_exists(&42));
+		assert_eq!(System::providers(&42), 0);
+		assert_eq!(System::sufficients(&42), 1);
+		assert_eq!(Assets::balance(TEST_ASSET, 42), 400);
+		assert_eq!(<Assets as fungibles::Inspect<u64>>::total_issuance(TEST_ASSET), 1000);
+	});
+}
+
+#[test]
+fn insufficient_asset_payout_to_fresh_account_rejected_before_spend() {
+	new_test_ext().execute_with(|| {
+		ClaimDustPayouts::set(false);
+		// The pool needs a provider to hold an insufficient asset
+		fund_pool(1000);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), TEST_ASSET, 1, false, 1));
+		Balances::set_balance(&1, 1000);
+		assert_ok!(<Assets as fungibles::Mutate<u64>>::mint_into(TEST_ASSET, &1, 1000));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, TEST_ASSET, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(400, TEST_ASSET, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(42),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				400,
+				TEST_ASSET,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::RecipientCannotBeCreated
+		);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+
+		// Once the recipient exists, it can hold the asset
+		assert_ok!(Balances::mint_into(&42, EXISTENTIAL_DEPOSIT));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(42),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			TEST_ASSET,
+			mock_proof(),
+			None
+		));
+		assert_eq!(System::providers(&42), 1);
+		assert_eq!(Assets::balance(TEST_ASSET, 42), 400);
+	});
+}
+
+#[test]
+fn minimum_payout_is_the_minimum_balance() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		assert_eq!(Pallet::<Test>::minimum_payout(0), EXISTENTIAL_DEPOSIT);
+		assert_eq!(Pallet::<Test>::minimum_payout(TEST_ASSET), 1);
+	});
+}
+
+#[test]
+fn cross_chain_withdrawal_respects_remote_min_balance() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 50));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id,
+			100,
+			Location::parent(),
+			[1u8; 32]
+		));
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(100, 1, &[1u8; 32], &Location::parent());
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(1),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				1,
+				49,
+				Location::new(1, [Parachain(2000)]),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::BelowExistentialDeposit
+		);
+
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(1),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			1,
+			50,
+			Location::new(1, [Parachain(2000)]),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+	});
+}
+
+/// Envelope of a proof made for the current circuits
+fn current_envelope() -> crate::shield::ProofEnvelope {
+	crate::shield::ProofEnvelope {
+		circuit_version: crate::circuit::CIRCUIT_VERSION,
+		proof: mock_proof().into_inner(),
+	}
+}
+
+#[test]
+fn sibling_pallet_unshields_locally() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let inputs = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 0,
+			amount: 500,
+			anchor_block_hash: None,
+			merkle_root: None,
+		};
+
+		// Proofs for other circuits are refused
+		let stale = ProofEnvelope { circuit_version: crate::circuit::CIRCUIT_VERSION + 1, proof: vec![] };
+		assert_noop!(
+			UnshieldCaller::recover(RuntimeOrigin::root(), stale, inputs, PayoutTarget::Local(7)),
+			Error::<Test>::CircuitVersionMismatch
+		);
+
+		assert_ok!(UnshieldCaller::recover(
+			RuntimeOrigin::root(),
+			current_envelope(),
+			inputs,
+			PayoutTarget::Local(7)
+		));
+		assert_eq!(Balances::balance(&7), 500);
+		assert!(NullifierSet::<Test>::get(&nullifier));
+
+		// The same checks as `withdraw` apply
+		assert_noop!(
+			UnshieldCaller::recover(RuntimeOrigin::root(), current_envelope(), inputs, PayoutTarget::Local(7)),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+	});
+}
+
+#[test]
+fn sibling_pallet_unshields_to_remote() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 50));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id.clone(),
+			100,
+			Location::parent(),
+			[1u8; 32]
+		));
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(100, 1, &[1u8; 32], &Location::parent());
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let destination = Location::new(1, [Parachain(2000)]);
+		let beneficiary = Location::new(0, []);
+
+		// Remote payouts run the checks of `withdraw_to_parachain`
+		let below_minimum = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 1,
+			amount: 49,
+			anchor_block_hash: None,
+			merkle_root: None,
+		};
+		assert_noop!(
+			Pallet::<Test>::do_unshield(
+				current_envelope(),
+				below_minimum,
+				PayoutTarget::Remote(destination.clone(), beneficiary.clone())
+			),
+			Error::<Test>::BelowExistentialDeposit
+		);
+
+		let inputs = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 1,
+			amount: 100,
+			anchor_block_hash: None,
+			merkle_root: None,
+		};
+		assert_ok!(UnshieldCaller::recover(
+			RuntimeOrigin::root(),
+			current_envelope(),
+			inputs,
+			PayoutTarget::Remote(destination.clone(), beneficiary.clone())
+		));
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_eq!(
+			ExportedWithdrawals::get(),
+			vec![(asset_id, 100, destination.into(), beneficiary.encode())]
+		);
+	});
+}
+
+#[test]
+fn sibling_pallet_unshield_verifies_the_proof_for_its_payout() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::recipient_hash(&7u64));
+		let envelope = ProofEnvelope { proof: proof.into_inner(), ..current_envelope() };
+		let inputs = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 0,
+			amount: 100,
+			anchor_block_hash: None,
+			merkle_root: Some(PrivacyBridge::merkle_root()),
+		};
+		let recover = |inputs, payout| {
+			UnshieldCaller::recover(RuntimeOrigin::root(), envelope.clone(), inputs, payout)
+		};
+
+		// The proof pays 100 to account 7: not another account, beneficiary or amount
+		assert_noop!(recover(inputs, PayoutTarget::Local(8)), Error::<Test>::InvalidProof);
+		assert_noop!(
+			recover(
+				inputs,
+				PayoutTarget::Remote(Location::new(1, [Parachain(2000)]), Location::new(0, []))
+			),
+			Error::<Test>::InvalidProof
+		);
+		assert_noop!(
+			recover(UnshieldInputs { amount: 99, ..inputs }, PayoutTarget::Local(7)),
+			Error::<Test>::InvalidProof
+		);
+
+		assert_ok!(recover(inputs, PayoutTarget::Local(7)));
+		assert_eq!(Balances::balance(&7), 100);
+	});
+}
+
+/// Deposit a native note in block 1, run to block 6 and give blocks 1 to 5 known hashes
+fn anchored_note_setup() -> (H256, H256) {
+	MaxProofAge::set(Some(3));
+	fund_pool(1000);
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+	run_to_block(6);
+	for block in 1..6u64 {
+		frame_system::BlockHash::<Test>::insert(block, H256::repeat_byte(block as u8));
+	}
+	let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+	(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]))
+}
+
+#[test]
+fn anchored_withdrawal_accepted_within_window() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = anchored_note_setup();
+
+		// Unanchored proofs are refused once anchoring is required
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::ProofAnchorRequired
+		);
+
+		// Block 3 is the oldest of the last three blocks
+		assert_ok!(PrivacyBridge::withdraw_anchored(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			H256::repeat_byte(3),
+			mock_proof(),
+		));
+		assert_eq!(Balances::balance(&2), 500);
+		assert!(NullifierSet::<Test>::get(&nullifier));
+	});
+}
+
+#[test]
+fn anchored_withdrawal_rejected_outside_window() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = anchored_note_setup();
+
+		// Too old, unknown, and the zero hash of pruned blocks
+		for anchor in [H256::repeat_byte(2), H256::repeat_byte(9), H256::zero()] {
+			assert_noop!(
+				PrivacyBridge::withdraw_anchored(
+					RuntimeOrigin::signed(2),
+					nullifier,
+					commitment,
+					PrivacyBridge::merkle_root(),
+					500,
+					0,
+					anchor,
+					mock_proof(),
+				),
+				Error::<Test>::ProofAnchorExpired
+			);
+		}
+
+		// The same applies to sibling pallets unshielding
+		let inputs = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 0,
+			amount: 500,
+			anchor_block_hash: Some(H256::repeat_byte(2)),
+			merkle_root: None,
+		};
+		assert_noop!(
+			UnshieldCaller::recover(RuntimeOrigin::root(), current_envelope(), inputs, PayoutTarget::Local(7)),
+			Error::<Test>::ProofAnchorExpired
+		);
+
+		// Without `MaxProofAge` the anchor is not checked
+		MaxProofAge::set(None);
+		assert_ok!(PrivacyBridge::withdraw_anchored(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			H256::repeat_byte(2),
+			mock_proof(),
+		));
+	});
+}
+
+/// Register the native asset with note age bounds and deposit a note at block 1
+fn aged_note_setup(min_note_age: Option<u32>, max_note_age: Option<u32>) -> (H256, H256) {
+	let asset_id = AssetId(Location::here());
+	assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 0));
+	assert_ok!(PrivacyBridge::set_note_age_bounds(
+		RuntimeOrigin::root(),
+		asset_id,
+		min_note_age,
+		max_note_age
+	));
+	fund_pool(2000);
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+	let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+	(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]))
+}
+
+#[test]
+fn set_note_age_bounds_validates_input() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::here());
+
+		assert_noop!(
+			PrivacyBridge::set_note_age_bounds(RuntimeOrigin::signed(1), asset_id.clone(), None, Some(10)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			PrivacyBridge::set_note_age_bounds(RuntimeOrigin::root(), asset_id.clone(), None, Some(10)),
+			Error::<Test>::AssetNotRegistered
+		);
+
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), asset_id.clone(), 0, CustodyMode::Pot, 0));
+		for (min, max) in [(Some(11), Some(10)), (None, Some(0))] {
+			assert_noop!(
+				PrivacyBridge::set_note_age_bounds(RuntimeOrigin::root(), asset_id.clone(), min, max),
+				Error::<Test>::InvalidNoteAgeBounds
+			);
+		}
+		assert_ok!(PrivacyBridge::set_note_age_bounds(RuntimeOrigin::root(), asset_id.clone(), Some(10), Some(10)));
+		let asset = AssetRegistry::<Test>::get(&asset_id).unwrap();
+		assert_eq!((asset.min_note_age, asset.max_note_age), (Some(10), Some(10)));
+	});
+}
+
+#[test]
+fn minimum_note_age_checks_newest_leaf_of_root() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = aged_note_setup(Some(3), None);
+		let early_root = PrivacyBridge::merkle_root();
+
+		// Bounded assets are withdrawn against a known recent root
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				H256::repeat_byte(9),
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::UnknownMerkleRoot
+		);
+
+		run_to_block(3);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				early_root,
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteTooYoung
+		);
+
+		// At block 4 the note is old enough, but a root recorded now also holds a new leaf
+		run_to_block(4);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [3u8; 32]));
+		let late_root = PrivacyBridge::merkle_root();
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				late_root,
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteTooYoung
+		);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			early_root,
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(Balances::balance(&2), 500);
+	});
+}
+
+#[test]
+fn maximum_note_age_checks_oldest_leaf_of_root() {
+	new_test_ext().execute_with(|| {
+		let _ = aged_note_setup(None, Some(10));
+
+		// A note deposited at block 8 is under a root whose range starts at block 1
+		run_to_block(8);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [3u8; 32]));
+		let root = PrivacyBridge::merkle_root();
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[3u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[4u8; 32]);
+
+		// Four blocks old, but the root's oldest leaf is past the limit
+		run_to_block(12);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				root,
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NoteTooOld
+		);
+
+		// Remote pallets are held to the same bounds
+		let inputs = UnshieldInputs {
+			nullifier,
+			commitment,
+			asset_id: 0,
+			amount: 500,
+			anchor_block_hash: None,
+			merkle_root: Some(root),
+		};
+		assert_noop!(
+			UnshieldCaller::recover(RuntimeOrigin::root(), current_envelope(), inputs, PayoutTarget::Local(7)),
+			Error::<Test>::NoteTooOld
+		);
+
+		// Lifting the bound lets the note be spent
+		assert_ok!(PrivacyBridge::set_note_age_bounds(
+			RuntimeOrigin::root(),
+			AssetId(Location::here()),
+			None,
+			None
+		));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+	});
+}
+
+#[test]
+fn native_asset_keeps_id_zero() {
+	new_test_ext().execute_with(|| {
+		// XCM assets are numbered from 1, whatever the registration order
+		let relay = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), relay.clone(), 0, CustodyMode::Pot, 0));
+		assert_eq!(AssetRegistry::<Test>::get(&relay).unwrap().local_id, 1);
+
+		let native = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(RuntimeOrigin::root(), native.clone(), 0, CustodyMode::Pot, 0));
+		assert_eq!(AssetRegistry::<Test>::get(&native).unwrap().local_id, crate::NATIVE_ASSET_ID);
+		assert_eq!(PrivacyBridge::next_asset_id(), 2);
+
+		// A native deposit no longer lands on the relay asset's ID
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_eq!(TotalShielded::<Test>::get(0), 100);
+		assert_eq!(TotalShielded::<Test>::get(1), 0);
+		assert_eq!(Pallet::<Test>::registered_asset_by_local(0).map(|asset| asset.asset_id), Some(native));
+	});
+}
+
+/// Externalities with the relay asset registered at genesis
+fn relay_asset_genesis_ext(via_reserve: bool) -> TestState {
+	RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig {
+			register_relay_asset: true,
+			relay_asset_via_reserve: via_reserve,
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into()
+}
+
+#[test]
+fn genesis_registers_relay_asset() {
+	relay_asset_genesis_ext(true).execute_with(|| {
+		let relay = AssetRegistry::<Test>::get(AssetId(Location::parent())).unwrap();
+		assert_eq!(relay.local_id, 1);
+		assert_eq!(relay.min_deposit, crate::DEFAULT_RELAY_MIN_DEPOSIT);
+		assert_eq!(relay.custody_mode, CustodyMode::Pot);
+		assert_eq!(relay.reserve_location, Some(Location::parent()));
+		assert!(relay.is_active);
+		assert_eq!(PrivacyBridge::local_asset_index(1), Some(AssetId(Location::parent())));
+		assert_eq!(PrivacyBridge::next_asset_id(), 2);
+	});
+
+	relay_asset_genesis_ext(false).execute_with(|| {
+		let relay = AssetRegistry::<Test>::get(AssetId(Location::parent())).unwrap();
+		assert_eq!(relay.reserve_location, None);
+	});
+}
+
+#[test]
+fn genesis_skips_relay_asset_by_default() {
+	new_test_ext().execute_with(|| {
+		assert!(AssetRegistry::<Test>::get(AssetId(Location::parent())).is_none());
+		assert_eq!(PrivacyBridge::next_asset_id(), 1);
+	});
+}
+
+/// Externalities built from the genesis preset `name`
+fn preset_ext(name: &str) -> TestState {
+	RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig::preset(name).unwrap(),
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into()
+}
+
+#[test]
+fn development_preset_registers_open_relay_asset() {
+	preset_ext(crate::DEVELOPMENT_PRESET).execute_with(|| {
+		let relay = AssetRegistry::<Test>::get(AssetId(Location::parent())).unwrap();
+		assert_eq!(relay.local_id, 1);
+		assert_eq!(relay.min_deposit, crate::DEFAULT_RELAY_MIN_DEPOSIT);
+		assert_eq!(relay.reserve_location, Some(Location::parent()));
+		assert_eq!((relay.min_note_age, relay.max_note_age), (None, None));
+		assert_eq!(PrivacyBridge::next_asset_id(), 2);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::None);
+	});
+}
+
+#[test]
+fn conservative_preset_starts_in_maintenance_mode() {
+	preset_ext(crate::CONSERVATIVE_PRESET).execute_with(|| {
+		let relay = AssetRegistry::<Test>::get(AssetId(Location::parent())).unwrap();
+		assert_eq!(relay.local_id, 1);
+		assert_eq!(relay.min_deposit, 10u128.pow(crate::RELAY_ASSET_DECIMALS));
+		assert_eq!(relay.reserve_location, Some(Location::parent()));
+		assert_eq!((relay.min_note_age, relay.max_note_age), (Some(600), None));
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::WithdrawalsPaused);
+	});
+	assert!(crate::GenesisConfig::<Test>::preset("mainnet").is_none());
+}
+
+#[test]
+fn genesis_registers_listed_assets_with_bounds() {
+	let sibling = Location::new(1, [Parachain(2000)]);
+	let mut ext: TestState = RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig {
+			register_relay_asset: true,
+			assets: vec![(sibling.clone(), 500, Some(sibling.clone()))],
+			note_age_bounds: vec![(sibling.clone(), Some(10), Some(100))],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into();
+
+	ext.execute_with(|| {
+		let asset = AssetRegistry::<Test>::get(AssetId(sibling.clone())).unwrap();
+		assert_eq!(asset.local_id, 2);
+		assert_eq!(asset.min_deposit, 500);
+		assert_eq!(asset.reserve_location, Some(sibling));
+		assert_eq!((asset.min_note_age, asset.max_note_age), (Some(10), Some(100)));
+		assert_eq!(PrivacyBridge::next_asset_id(), 3);
+	});
+}
+
+#[test]
+fn genesis_sets_verifying_keys() {
+	let mut ext: TestState = RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig {
+			verifying_keys: vec![
+				(CircuitId::Withdraw, vec![1, 2, 3]),
+				(CircuitId::JoinSplit, vec![4, 5]),
+			],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into();
+
+	ext.execute_with(|| {
+		assert_eq!(Pallet::<Test>::verifying_key_for_proofs().unwrap().into_inner(), vec![1, 2, 3]);
+		let setup = PrivacyBridge::verifying_key_setup().unwrap();
+		assert_eq!(setup.hasher, MockHasherId::get());
+		assert_eq!(setup.circuit_version, crate::circuit::CIRCUIT_VERSION);
+		let key_of = |circuit| PrivacyBridge::verifying_key_for(circuit).map(|vk| vk.into_inner());
+		assert_eq!(key_of(CircuitId::Transfer), None);
+		assert_eq!(key_of(CircuitId::JoinSplit), Some(vec![4, 5]));
+		assert!(crate::PreparedVerifyingKeys::<Test>::contains_key(CircuitId::JoinSplit));
+	});
+}
+
+#[test]
+fn genesis_assets_take_xcm_deposits_without_root_calls() {
+	let sibling = Location::new(1, [Parachain(2000)]);
+	let mut ext: TestState = RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig {
+			assets: vec![(sibling.clone(), 100, None)],
+			verifying_keys: vec![(CircuitId::Withdraw, TEST_VERIFYING_KEY.to_vec())],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into();
+
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			AssetId(sibling.clone()),
+			1000,
+			sibling.clone(),
+			[42u8; 32],
+		));
+
+		let commitment =
+			crate::xcm_config::xcm_commitment_data::<MockHasher>(1000, 1, &[42u8; 32], &sibling);
+		assert!(crate::Commitments::<Test>::contains_key(&commitment));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 1);
+	});
+}
+
+#[test]
+#[should_panic(expected = "genesis config written for another format version")]
+fn genesis_refuses_config_of_another_version() {
+	let _ = RuntimeGenesisConfig {
+		privacy_bridge: crate::GenesisConfig {
+			version: crate::GENESIS_CONFIG_VERSION + 1,
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage();
+}
+
+#[test]
+fn solvency_check_passes_for_backed_pool() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [1u8; 32]));
+
+		PrivacyBridge::on_finalize(10);
+
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::None);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PrivacyBridge(Event::SolvencyAlert { .. })
+		)));
+	});
+}
+
+#[test]
+fn solvency_alert_trips_withdrawal_breaker() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [1u8; 32]));
+
+		// Shielded value the pool does not hold
+		TotalShielded::<Test>::insert(0, 5000);
+
+		// Only checked every `SolvencyCheckInterval` (10) blocks
+		PrivacyBridge::on_finalize(9);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::None);
+
+		PrivacyBridge::on_finalize(10);
+		System::assert_has_event(Event::SolvencyAlert { asset_id: 0, expected: 5000, actual: 1400 }.into());
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::WithdrawalsPaused);
+		System::assert_has_event(
+			Event::PauseLevelSet { level: PauseLevel::WithdrawalsPaused }.into(),
+		);
+
+		let commitment = Pallet::<Test>::generate_commitment(400, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				400,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::WithdrawalsPaused
+		);
+
+		// Deposits continue
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [3u8; 32]));
+
+		// Only the admin resets the breaker
+		assert_noop!(PrivacyBridge::unpause(RuntimeOrigin::signed(1)), DispatchError::BadOrigin);
+		TotalShielded::<Test>::insert(0, 500);
+		assert_ok!(PrivacyBridge::unpause(RuntimeOrigin::root()));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			0,
+			mock_proof(),
+			None,
+		));
+	});
+}
+
+#[test]
+fn solvency_alert_pauses_withdrawals_on_top_of_paused_deposits() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::DepositsPaused));
+		TotalShielded::<Test>::insert(0, 5000);
+
+		PrivacyBridge::on_finalize(10);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::AllPaused);
+		System::assert_has_event(Event::PauseLevelSet { level: PauseLevel::AllPaused }.into());
+
+		// Already paused: a further failed check changes nothing
+		System::reset_events();
+		PrivacyBridge::on_finalize(20);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::AllPaused);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PrivacyBridge(Event::PauseLevelSet { .. })
+		)));
+	});
+}
+
+#[test]
+fn pausing_deposits_keeps_the_withdrawal_breaker_tripped() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		TotalShielded::<Test>::insert(0, 5000);
+		PrivacyBridge::on_finalize(10);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::WithdrawalsPaused);
+
+		// Pausing deposits adds to the breaker instead of replacing it
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::DepositsPaused));
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::AllPaused);
+		System::assert_last_event(Event::PauseLevelSet { level: PauseLevel::AllPaused }.into());
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::None));
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::WithdrawalsPaused);
+
+		// Only `unpause` resets it
+		assert_ok!(PrivacyBridge::unpause(RuntimeOrigin::root()));
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::DepositsPaused));
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::DepositsPaused);
+	});
+}
+
+#[test]
+fn pause_requires_admin_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrivacyBridge::pause(RuntimeOrigin::signed(1), PauseLevel::AllPaused),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(PrivacyBridge::unpause(RuntimeOrigin::signed(1)), DispatchError::BadOrigin);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::None);
+	});
+}
+
+#[test]
+fn deposits_paused_still_allows_withdrawals() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [1u8; 32]));
+
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::DepositsPaused));
+		System::assert_last_event(
+			Event::PauseLevelSet { level: PauseLevel::DepositsPaused }.into(),
+		);
+
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [3u8; 32]),
+			Error::<Test>::BridgePaused
+		);
+		assert_noop!(
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id,
+				100,
+				Location::parent(),
+				[4u8; 32]
+			),
+			Error::<Test>::BridgePaused
+		);
+
+		// Users can still exit
+		let commitment = Pallet::<Test>::generate_commitment(400, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			0,
+			mock_proof(),
+			None,
+		));
+	});
+}
+
+#[test]
+fn all_paused_halts_withdrawals_until_unpaused() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id.clone(),
+			100,
+			Location::parent(),
+			[5u8; 32]
+		));
+
+		assert_ok!(PrivacyBridge::pause(RuntimeOrigin::root(), PauseLevel::AllPaused));
+
+		let commitment = Pallet::<Test>::generate_commitment(400, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				400,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::BridgePaused
+		);
+		let xcm_commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			100,
+			1,
+			&[5u8; 32],
+			&Location::parent(),
+		);
+		let xcm_nullifier = Pallet::<Test>::generate_nullifier(&xcm_commitment, &[6u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(1),
+				xcm_nullifier,
+				xcm_commitment,
+				PrivacyBridge::merkle_root(),
+				1,
+				100,
+				Location::new(1, [Parachain(2000)]),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::BridgePaused
+		);
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [3u8; 32]),
+			Error::<Test>::BridgePaused
+		);
+
+		assert_ok!(PrivacyBridge::unpause(RuntimeOrigin::root()));
+		System::assert_last_event(Event::PauseLevelSet { level: PauseLevel::None }.into());
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			400,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(1),
+			xcm_nullifier,
+			xcm_commitment,
+			PrivacyBridge::merkle_root(),
+			1,
+			100,
+			Location::new(1, [Parachain(2000)]),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+	});
+}
+
+#[test]
+fn solvency_check_covers_assets_over_several_runs() {
+	new_test_ext().execute_with(|| {
+		// Local IDs 0 to 3; the mock checks two assets per run
+		for location in [
+			Location::here(),
+			Location::parent(),
+			Location::new(1, [Parachain(2000)]),
+			Location::new(1, [Parachain(3000)]),
+		] {
+			assert_ok!(PrivacyBridge::register_asset(
+				RuntimeOrigin::root(),
+				AssetId(location),
+				0,
+				CustodyMode::Pot,
+				0
+			));
+		}
+		TotalShielded::<Test>::insert(3, 100);
+
+		// IDs 0 and 1
+		PrivacyBridge::on_finalize(10);
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::None);
+
+		// IDs 2 and 3
+		PrivacyBridge::on_finalize(20);
+		System::assert_has_event(Event::SolvencyAlert { asset_id: 3, expected: 100, actual: 0 }.into());
+		assert_eq!(PrivacyBridge::pause_level(), PauseLevel::WithdrawalsPaused);
+	});
+}
+
+#[test]
+fn deposits_in_one_block_take_leaf_indices_in_order() {
+	new_test_ext().execute_with(|| {
+		for who in [2, 3, 4, 5, 9] {
+			assert_ok!(Balances::mint_into(&who, 1000));
+		}
+		let mut commitments = Vec::new();
+		for seed in 1..=5u8 {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(seed as u64), 100, 0, [seed; 32]));
+			commitments.push(Pallet::<Test>::generate_commitment(100, 0, &[seed; 32]));
+
+			// A deposit failing after its commitment was inserted consumes no index
+			if seed == 2 {
+				let shielded = TotalShielded::<Test>::get(0);
+				TotalShielded::<Test>::insert(0, u128::MAX);
+				assert_noop!(
+					PrivacyBridge::deposit(RuntimeOrigin::signed(9), 100, 0, [9u8; 32]),
+					Error::<Test>::AmountOverflow
+				);
+				TotalShielded::<Test>::insert(0, shielded);
+			}
+		}
+
+		let shielded: Vec<(H256, u32)> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::PrivacyBridge(Event::AssetShielded { commitment, leaf_index, .. }) =>
+					Some((commitment, leaf_index)),
+				_ => None,
+			})
+			.collect();
+		let expected: Vec<(H256, u32)> = commitments.iter().copied().zip(0..5).collect();
+		assert_eq!(shielded, expected);
+
+		for (commitment, index) in expected {
+			assert_eq!(CommitmentIndex::<Test>::get(commitment), Some(index));
+			assert_eq!(MerkleLeaves::<Test>::get(index), Some(commitment));
+		}
+		assert_eq!(CommitmentCount::<Test>::get(), 5);
+	});
+}
+
+/// Deposit three native notes and return their commitments
+fn deposit_three_notes() -> Vec<H256> {
+	(1..=3u8)
+		.map(|seed| {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+			Pallet::<Test>::generate_commitment(100, 0, &[seed; 32])
+		})
+		.collect()
+}
+
+#[test]
+fn deposit_updates_merkle_root() {
+	new_test_ext().execute_with(|| {
+		let leaves = deposit_three_notes();
+
+		assert_eq!(PrivacyBridge::tree_size(), 3);
+		assert_eq!(
+			PrivacyBridge::merkle_root(),
+			crate::merkle_tree::root_of::<MockHasher>(&leaves).unwrap()
+		);
+		assert_eq!(PrivacyBridge::filled_subtrees(0), leaves[2]);
+	});
+}
+
+#[test]
+fn batched_tree_insertion_folds_leaves_on_finalize() {
+	new_test_ext().execute_with(|| {
+		BatchTreeInsertion::set(true);
+		let leaves = deposit_three_notes();
+
+		// Leaves are stored, but the tree is only updated at the end of the block
+		assert_eq!(CommitmentCount::<Test>::get(), 3);
+		assert_eq!(PrivacyBridge::tree_size(), 0);
+		assert_eq!(PrivacyBridge::merkle_root(), H256::zero());
+
+		PrivacyBridge::on_finalize(1);
+		assert_eq!(PrivacyBridge::tree_size(), 3);
+		assert_eq!(
+			PrivacyBridge::merkle_root(),
+			crate::merkle_tree::root_of::<MockHasher>(&leaves).unwrap()
+		);
+	});
+}
+
+#[test]
+fn root_ttl_counts_down_to_eviction() {
+	new_test_ext().execute_with(|| {
+		// The mock keeps four roots
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let root = PrivacyBridge::merkle_root();
+		assert_eq!(Pallet::<Test>::root_ttl(root), Some(3));
+
+		for (seed, ttl) in [(2u8, Some(2)), (3, Some(1)), (4, Some(0)), (5, None)] {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+			assert_eq!(Pallet::<Test>::root_ttl(root), ttl);
+		}
+
+		assert_eq!(Pallet::<Test>::root_ttl(PrivacyBridge::merkle_root()), Some(3));
+		assert_eq!(PrivacyBridge::root_sequence(), 5);
+		assert_eq!(Pallet::<Test>::root_ttl(H256::repeat_byte(9)), None);
+	});
+}
+
+#[test]
+fn known_roots_lists_kept_roots_newest_first() {
+	new_test_ext().execute_with(|| {
+		// The mock keeps four roots
+		let mut roots = Vec::new();
+		for seed in 1u8..=6 {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+			roots.push(PrivacyBridge::merkle_root());
+		}
+
+		let expected: Vec<H256> = roots.iter().rev().take(4).copied().collect();
+		assert_eq!(Pallet::<Test>::known_roots(), expected);
+		assert_eq!(PrivacyBridge::commitment_count(), 6);
+	});
+}
+
+#[test]
+fn merkle_path_leads_to_current_root() {
+	new_test_ext().execute_with(|| {
+		let leaves = deposit_three_notes();
+		let root = PrivacyBridge::merkle_root();
+
+		for (index, leaf) in leaves.iter().enumerate() {
+			let path = Pallet::<Test>::merkle_path(index as u32).unwrap();
+			let folded = path.iter().enumerate().fold(*leaf, |node, (level, sibling)| {
+				if (index >> level) & 1 == 0 {
+					MockHasher::hash_pair(&node, sibling)
+				} else {
+					MockHasher::hash_pair(sibling, &node)
+				}
+			});
+			assert_eq!(folded, root);
+		}
+		assert_eq!(Pallet::<Test>::merkle_path(3), None);
+
+		// A batched leaf has no path until it is folded into the tree
+		BatchTreeInsertion::set(true);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [9u8; 32]));
+		assert_eq!(PrivacyBridge::commitment_count(), 4);
+		assert_eq!(Pallet::<Test>::merkle_path(3), None);
+
+		PrivacyBridge::on_finalize(1);
+		assert!(Pallet::<Test>::merkle_path(3).is_some());
+	});
+}
+
+#[test]
+fn merkle_path_matches_slice_based_proof() {
+	new_test_ext().execute_with(|| {
+		// `generate_proof` hashes with `simple_hash`
+		MockHasherId::set(HasherId::Simple);
+		let leaves: Vec<H256> = (1..=5u8)
+			.map(|seed| {
+				assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+				Pallet::<Test>::generate_commitment(100, 0, &[seed; 32])
+			})
+			.collect();
+
+		for index in 0..leaves.len() {
+			let (siblings, _) =
+				crate::merkle_tree::generate_circuit_proof(&leaves, index).unwrap();
+			assert_eq!(Pallet::<Test>::merkle_path(index as u32), Some(siblings));
+		}
+	});
+}
+
+#[test]
+fn merkle_path_waits_for_the_node_cache() {
+	new_test_ext().execute_with(|| {
+		let leaves = deposit_three_notes();
+		let root = PrivacyBridge::merkle_root();
+		let path = Pallet::<Test>::merkle_path(2);
+		assert!(path.is_some());
+
+		// A tree grown before the cache has no path until a rebuild fills it
+		let _ = crate::MerkleNodes::<Test>::clear(u32::MAX, None);
+		assert_eq!(Pallet::<Test>::merkle_path(2), None);
+
+		assert_ok!(PrivacyBridge::start_tree_rebuild(RuntimeOrigin::root(), root));
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 0, 2));
+		assert_eq!(Pallet::<Test>::merkle_path(2), None);
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 2, 1));
+		assert_eq!(Pallet::<Test>::merkle_path(2), path);
+		assert_eq!(crate::merkle_tree::path_of::<MockHasher>(&leaves, 2).ok(), path);
+	});
+}
+
+#[test]
+fn commitment_info_never_names_the_depositor() {
+	new_test_ext().execute_with(|| {
+		run_to_block(3);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+
+		let info = PrivacyBridge::commitment_info(commitment).unwrap();
+		assert_eq!(info, PublicCommitmentInfo { block_number: 3, asset_id: 0, leaf_index: 0 });
+		assert_eq!(PrivacyBridge::commitment_info(H256::repeat_byte(9)), None);
+
+		// Exhaustive: a new field fails to compile here
+		let PublicCommitmentInfo { block_number: _, asset_id: _, leaf_index: _ } = info;
+
+		// Served as block number, asset and leaf index only
+		assert_eq!(info.encode(), (3u64, 0u32, 0u32).encode());
+		let type_info = <PublicCommitmentInfo<u64> as scale_info::TypeInfo>::type_info();
+		let scale_info::TypeDef::Composite(composite) = type_info.type_def else {
+			panic!("PublicCommitmentInfo is a struct");
+		};
+		let type_names: Vec<_> = composite.fields.iter().filter_map(|field| field.type_name).collect();
+		assert_eq!(type_names, vec!["BlockNumber", "u32", "u32"]);
+	});
+}
+
+/// State root after three user deposits followed by `leaves` synthetic notes, appended by
+/// deposits or by `force_populate_tree`
+fn populated_state_root(leaves: u32, force: bool) -> Vec<u8> {
+	new_test_ext().execute_with(|| {
+		// Synthetic notes are deposited by the pool account, which must hold their value
+		fund_pool(1000);
+		deposit_three_notes();
+		if force {
+			assert_ok!(Pallet::<Test>::force_populate_tree(leaves));
+		} else {
+			let pool = Pallet::<Test>::account_id();
+			for index in 3..3 + leaves {
+				let (amount, randomness) = Pallet::<Test>::synthetic_note(index);
+				assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(pool), amount, 0, randomness));
+			}
+		}
+		System::reset_events();
+		sp_io::storage::root(sp_runtime::StateVersion::V1)
+	})
+}
+
+#[test]
+fn force_populated_tree_matches_deposits() {
+	// Fewer leaves than, exactly as many as, and more than the root history holds
+	for leaves in [1, 4, 9] {
+		assert_eq!(populated_state_root(leaves, true), populated_state_root(leaves, false));
+	}
+	assert_ne!(populated_state_root(4, true), populated_state_root(5, false));
+}
+
+#[test]
+fn force_populate_tree_respects_capacity() {
+	new_test_ext().execute_with(|| {
+		let last_slot = (1u32 << crate::merkle_tree::TREE_DEPTH) - 1;
+		CommitmentCount::<Test>::put(last_slot);
+		crate::TreeSize::<Test>::put(last_slot);
+		assert_noop!(Pallet::<Test>::force_populate_tree(2), Error::<Test>::TreeFull);
+	});
+}
+
+#[test]
+fn deposit_weight_follows_tree_insertion_mode() {
+	use crate::weights::WeightInfo;
+
+	new_test_ext().execute_with(|| {
+		let depth = crate::merkle_tree::TREE_DEPTH as u32;
+		assert_eq!(Pallet::<Test>::deposit_weight(), <() as WeightInfo>::deposit(depth));
+		assert!(<() as WeightInfo>::deposit(depth).all_gt(<() as WeightInfo>::deposit(depth - 1)));
+
+		BatchTreeInsertion::set(true);
+		assert_eq!(Pallet::<Test>::deposit_weight(), <() as WeightInfo>::deposit_batched());
+	});
+}
+
+#[test]
+fn deposit_rejects_reserved_commitment() {
+	new_test_ext().execute_with(|| {
+		// The XOR test hash makes a preimage of zero easy to find
+		MockHasherId::set(HasherId::Simple);
+		let randomness = zero_commitment_randomness(100, 0);
+		assert_eq!(Pallet::<Test>::generate_commitment(100, 0, &randomness), H256::zero());
+
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, randomness),
+			Error::<Test>::ReservedCommitmentValue
+		);
+	});
+}
+
+#[test]
+fn withdraw_rejects_reserved_nullifier() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				H256::zero(),
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::ReservedCommitmentValue
+		);
+	});
+}
+
+/// Propose `action` as root and approve it as the co-signer
+fn approve_admin_action(action: AdminAction) {
+	assert_ok!(PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action.hash()));
+	assert_ok!(PrivacyBridge::approve_admin_action(co_signer(), action.hash()));
+}
+
+/// Origin of the account approving admin actions
+fn co_signer() -> RuntimeOrigin {
+	RuntimeOrigin::signed(CoSigner::get())
+}
+
+/// Dual-control approval of applying the verifying key `vk` of `circuit`
+fn apply_key_action(circuit: CircuitId, vk: &[u8]) -> AdminAction {
+	AdminAction::ApplyVerifyingKey { circuit, vk_hash: BlakeTwo256::hash(vk) }
+}
+
+/// Make `vk` the verifying key of `circuit` as governance does: announced, approved under
+/// dual control, then applied
+fn rotate_verifying_key(circuit: CircuitId, vk: Vec<u8>, hasher: HasherId) {
+	assert_ok!(PrivacyBridge::announce_verifying_key(
+		RuntimeOrigin::root(),
+		circuit,
+		BoundedVec::truncate_from(vk.clone()),
+		hasher,
+	));
+	approve_admin_action(apply_key_action(circuit, &vk));
+	assert_ok!(PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), circuit));
+}
+
+#[test]
+fn announced_verifying_key_applies_within_ttl() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::NoPendingAnnouncement
+		);
+		assert_noop!(
+			PrivacyBridge::announce_verifying_key(
+				RuntimeOrigin::signed(1),
+				CircuitId::Withdraw,
+				BoundedVec::truncate_from(vec![1, 2, 3]),
+				HasherId::Blake2,
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		System::assert_last_event(
+			Event::VerifyingKeyAnnounced {
+				circuit: CircuitId::Withdraw,
+				circuit_version: crate::circuit::CIRCUIT_VERSION,
+				spec_version: 0,
+				expires_at: 101,
+			}
+			.into(),
+		);
+
+		run_to_block(100);
+		approve_admin_action(apply_key_action(CircuitId::Withdraw, &[1, 2, 3]));
+		assert_ok!(PrivacyBridge::apply_verifying_key(
+			RuntimeOrigin::signed(1),
+			CircuitId::Withdraw
+		));
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap().into_inner(),
+			vec![1, 2, 3]
+		);
+		// The mock verifier keeps a key Groth16 cannot decode as it is
+		assert_eq!(
+			crate::PreparedVerifyingKeys::<Test>::get(CircuitId::Withdraw).unwrap().into_inner(),
+			vec![1, 2, 3]
+		);
+		assert!(PrivacyBridge::pending_verifying_key(CircuitId::Withdraw).is_none());
+	});
+}
+
+#[test]
+fn stale_verifying_key_announcement_rejected() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+
+		// Announced before a runtime upgrade
+		crate::PendingVerifyingKeys::<Test>::mutate(CircuitId::Withdraw, |pending| {
+			pending.as_mut().unwrap().spec_version += 1;
+		});
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::StaleAnnouncement
+		);
+
+		// Announced for an older circuit
+		crate::PendingVerifyingKeys::<Test>::mutate(CircuitId::Withdraw, |pending| {
+			let pending = pending.as_mut().unwrap();
+			pending.spec_version -= 1;
+			pending.circuit_version -= 1;
+		});
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::StaleAnnouncement
+		);
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap().into_inner(),
+			TEST_VERIFYING_KEY.to_vec()
+		);
+	});
+}
+
+#[test]
+fn expired_verifying_key_announcement_rejected() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+
+		// `AnnouncementTtl` is 100 blocks
+		run_to_block(101);
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AnnouncementExpired
+		);
+
+		// A fresh announcement can be applied
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![4, 5]),
+			HasherId::Blake2,
+		));
+		approve_admin_action(apply_key_action(CircuitId::Withdraw, &[4, 5]));
+		assert_ok!(PrivacyBridge::apply_verifying_key(
+			RuntimeOrigin::signed(1),
+			CircuitId::Withdraw
+		));
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap().into_inner(),
+			vec![4, 5]
+		);
+	});
+}
+
+#[test]
+fn announced_verifying_key_applies_to_its_circuit_only() {
+	new_test_ext().execute_with(|| {
+		let setup = PrivacyBridge::verifying_key_setup();
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Transfer,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		assert!(PrivacyBridge::pending_verifying_key(CircuitId::Withdraw).is_none());
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::NoPendingAnnouncement
+		);
+
+		// An approval of the same key for another circuit does not apply it
+		approve_admin_action(apply_key_action(CircuitId::Withdraw, &[1, 2, 3]));
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Transfer),
+			Error::<Test>::AdminActionNotApproved
+		);
+
+		approve_admin_action(apply_key_action(CircuitId::Transfer, &[1, 2, 3]));
+		assert_ok!(PrivacyBridge::apply_verifying_key(
+			RuntimeOrigin::signed(1),
+			CircuitId::Transfer
+		));
+		System::assert_last_event(
+			Event::VerifyingKeyApplied {
+				circuit: CircuitId::Transfer,
+				circuit_version: crate::circuit::CIRCUIT_VERSION,
+			}
+			.into(),
+		);
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Transfer).unwrap().into_inner(),
+			vec![1, 2, 3]
+		);
+		// The withdrawal key and its setup are untouched
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap().into_inner(),
+			TEST_VERIFYING_KEY.to_vec()
+		);
+		assert_eq!(PrivacyBridge::verifying_key_setup(), setup);
+	});
+}
+
+/// Deposit and withdraw a note with `H` as the runtime's hasher
+fn deposit_withdraw_suite<H: NoteHasher>() {
+	MockHasherId::set(H::id());
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+
+		let leaves = deposit_three_notes();
+		assert_eq!(leaves[0], H::hash_commitment(100, 0, &[1u8; 32]));
+		assert_eq!(
+			PrivacyBridge::merkle_root(),
+			crate::merkle_tree::root_of::<H>(&leaves).unwrap()
+		);
+
+		let nullifier = Pallet::<Test>::generate_nullifier(&leaves[0], &[2u8; 32]);
+		assert_eq!(nullifier, H::hash_nullifier(&leaves[0], &[2u8; 32]));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			leaves[0],
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				leaves[0],
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::NullifierAlreadyUsed
+		);
+	});
+}
+
+#[test]
+fn deposit_withdraw_with_blake2_hasher() {
+	deposit_withdraw_suite::<crate::hasher::Blake2Hasher>();
+}
+
+#[test]
+fn deposit_withdraw_with_simple_hasher() {
+	deposit_withdraw_suite::<crate::hasher::SimpleHasher>();
+}
+
+#[test]
+fn verifying_key_must_match_hasher() {
+	new_test_ext().execute_with(|| {
+		crate::VerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		crate::VerifyingKeySetup::<Test>::kill();
+		assert_noop!(
+			PrivacyBridge::announce_verifying_key(
+				RuntimeOrigin::root(),
+				CircuitId::Withdraw,
+				BoundedVec::truncate_from(vec![1, 2, 3]),
+				HasherId::Poseidon,
+			),
+			Error::<Test>::HasherMismatch
+		);
+		assert_noop!(
+			Pallet::<Test>::verifying_key_for_proofs(),
+			Error::<Test>::VerifyingKeyNotSet
+		);
+
+		rotate_verifying_key(CircuitId::Withdraw, vec![1, 2, 3], HasherId::Blake2);
+		assert_eq!(
+			PrivacyBridge::verifying_key_setup(),
+			Some(crate::SetupMetadata {
+				hasher: HasherId::Blake2,
+				circuit_version: crate::circuit::CIRCUIT_VERSION,
+			})
+		);
+		assert_eq!(Pallet::<Test>::verifying_key_for_proofs().unwrap().into_inner(), vec![1, 2, 3]);
+
+		// After the runtime switches hashers, the old key is refused
+		MockHasherId::set(HasherId::Simple);
+		assert_noop!(Pallet::<Test>::verifying_key_for_proofs(), Error::<Test>::HasherMismatch);
+	});
+}
+
+#[test]
+fn migration_to_v1_keys_verifying_keys_by_circuit() {
+	use crate::migrations::v1;
+	use frame::deps::frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		crate::VerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		StorageVersion::new(0).put::<Pallet<Test>>();
+		v1::VerifyingKey::<Test>::put(BoundedVec::truncate_from(vec![1, 2, 3]));
+
+		v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		let key_of = |circuit| PrivacyBridge::verifying_key_for(circuit).map(|vk| vk.into_inner());
+		assert_eq!(key_of(CircuitId::Withdraw), Some(vec![1, 2, 3]));
+		assert_eq!(key_of(CircuitId::Transfer), None);
+		assert_eq!(key_of(CircuitId::JoinSplit), None);
+		assert!(!v1::VerifyingKey::<Test>::exists());
+		assert_eq!(Pallet::<Test>::on_chain_storage_version(), 1);
+
+		// Once at version 1, the migration no longer runs
+		v1::VerifyingKey::<Test>::put(BoundedVec::truncate_from(vec![9]));
+		v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(key_of(CircuitId::Withdraw), Some(vec![1, 2, 3]));
+	});
+}
+
+#[test]
+fn migration_to_v2_keeps_released_assets_with_default_settings() {
+	use crate::migrations::v2;
+	use frame::deps::frame_support::{
+		storage::unhashed,
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	};
+
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		StorageVersion::new(1).put::<Pallet<Test>>();
+		let old = v2::RegisteredAssetV1 {
+			asset_id: asset_id.clone(),
+			local_id: 1,
+			min_deposit: 50,
+			is_active: false,
+		};
+		unhashed::put_raw(&AssetRegistry::<Test>::hashed_key_for(&asset_id), &old.encode());
+
+		v2::MigrateToV2::<Test>::on_runtime_upgrade();
+
+		let migrated = AssetRegistry::<Test>::get(&asset_id).unwrap();
+		assert_eq!((migrated.local_id, migrated.min_deposit, migrated.is_active), (1, 50, false));
+		assert_eq!(migrated.custody_mode, CustodyMode::Pot);
+		assert_eq!((migrated.min_note_age, migrated.max_note_age), (None, None));
+		assert!(migrated.denominations.is_empty());
+		assert_eq!(Pallet::<Test>::on_chain_storage_version(), 2);
+	});
+}
+
+#[test]
+fn withdraw_requires_verifying_key() {
+	new_test_ext().execute_with(|| {
+		crate::VerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::VerifyingKeyNotSet
+		);
+	});
+}
+
+/// Deposit a native note of 100 and prove its withdrawal to account 2 with the test setup,
+/// verifying proofs with Groth16 from now on: (nullifier, commitment, proof)
+fn proven_note() -> (H256, H256, crate::zksnark::ProofBytes) {
+	proven_note_of(0)
+}
+
+/// `proven_note`, for a note of `asset_id`
+fn proven_note_of(asset_id: u32) -> (H256, H256, crate::zksnark::ProofBytes) {
+	proven_note_paying(asset_id, crate::primitives::recipient_hash(&2u64))
+}
+
+/// `proven_note_of`, with the proof paying whoever `recipient_hash` commits to
+fn proven_note_paying(
+	asset_id: u32,
+	recipient_hash: H256,
+) -> (H256, H256, crate::zksnark::ProofBytes) {
+	let (pk, vk) = crate::test_support::test_setup();
+	MockHasherId::set(HasherId::Simple);
+	VerifyProofs::set(true);
+	rotate_verifying_key(
+		CircuitId::Withdraw,
+		crate::zksnark::serialize_vk(vk).unwrap(),
+		HasherId::Simple,
+	);
+
+	let secret = [2u8; 32];
+	let note_key = crate::primitives::note_key::<MockHasher>(&secret);
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, asset_id, note_key));
+	let commitment = Pallet::<Test>::generate_commitment(100, asset_id, &note_key);
+	let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+	(nullifier, commitment, prove_withdrawal(asset_id, recipient_hash))
+}
+
+/// Another proof of withdrawing the note `proven_note_paying` deposited, against the
+/// current root
+fn prove_withdrawal(asset_id: u32, recipient_hash: H256) -> crate::zksnark::ProofBytes {
+	let (pk, _) = crate::test_support::test_setup();
+	let secret = [2u8; 32];
+	let note_key = crate::primitives::note_key::<MockHasher>(&secret);
+	let commitment = Pallet::<Test>::generate_commitment(100, asset_id, &note_key);
+	let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+	let leaves: Vec<H256> =
+		(0..CommitmentCount::<Test>::get()).filter_map(MerkleLeaves::<Test>::get).collect();
+	let leaf_index = CommitmentIndex::<Test>::get(commitment).unwrap() as usize;
+	let (siblings, path_indices) =
+		crate::merkle_tree::generate_circuit_proof(&leaves, leaf_index).unwrap();
+	let proof = crate::zksnark::generate_proof::<SimpleHasher>(
+		pk,
+		nullifier.as_bytes().to_vec(),
+		commitment.as_bytes().to_vec(),
+		PrivacyBridge::merkle_root().as_bytes().to_vec(),
+		recipient_hash.as_bytes().to_vec(),
+		100,
+		asset_id,
+		[0u8; 32],
+		secret,
+		siblings.into_iter().map(|sibling| sibling.0).collect(),
+		path_indices,
+	)
+	.unwrap();
+	BoundedVec::truncate_from(proof)
+}
+
+#[test]
+fn withdraw_accepts_valid_proof() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) = proven_note();
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			proof,
+			None
+		));
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_eq!(Balances::balance(&2), 100);
+	});
+}
+
+#[test]
+fn withdraw_verifies_against_prepared_key() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) = proven_note();
+
+		// Setting the key stored it prepared
+		let (_, vk) = crate::test_support::test_setup();
+		let prepared = crate::zksnark::prepare_vk(vk);
+		assert_eq!(
+			crate::PreparedVerifyingKeys::<Test>::get(CircuitId::Withdraw).unwrap().into_inner(),
+			crate::zksnark::serialize_prepared_vk(&prepared).unwrap()
+		);
+
+		// A key set before prepared keys were stored is prepared for the proof
+		crate::PreparedVerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			proof,
+			None
+		));
+		assert_eq!(Balances::balance(&2), 100);
+	});
+}
+
+#[test]
+fn withdraw_of_denominated_note_is_proven_against_its_pool() {
+	new_test_ext().execute_with(|| {
+		let (pk, vk) = crate::test_support::test_setup();
+		MockHasherId::set(HasherId::Simple);
+		VerifyProofs::set(true);
+		rotate_verifying_key(
+			CircuitId::Withdraw,
+			crate::zksnark::serialize_vk(vk).unwrap(),
+			HasherId::Simple,
+		);
+		fund_pool(1000);
+
+		// A note from before the denominations is in the main tree only
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [9u8; 32]));
+		let asset_id = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0,
+		));
+		assert_ok!(PrivacyBridge::set_denominations(
+			RuntimeOrigin::root(),
+			asset_id,
+			BoundedVec::truncate_from(vec![100, 1000]),
+		));
+
+		let secret = [2u8; 32];
+		let note_key = crate::primitives::note_key::<MockHasher>(&secret);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, note_key));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &note_key);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &secret);
+		let pool = PrivacyBridge::denomination_tree(0, 100u128);
+		assert_eq!(pool.leaves, 1);
+		assert_eq!(PrivacyBridge::denomination_of(commitment), Some(100));
+		assert_ne!(pool.root, PrivacyBridge::merkle_root());
+
+		// The path is the one of the pool's tree, not of the main tree
+		let leaves: Vec<H256> = (0..pool.leaves)
+			.filter_map(|index| DenominationLeaves::<Test>::get((0u32, 100u128), index))
+			.collect();
+		assert_eq!(leaves, vec![commitment]);
+		let (siblings, path_indices) =
+			crate::merkle_tree::generate_circuit_proof(&leaves, 0).unwrap();
+		let proof = crate::zksnark::generate_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			pool.root.as_bytes().to_vec(),
+			crate::primitives::recipient_hash(&2u64).as_bytes().to_vec(),
+			100,
+			0,
+			[0u8; 32],
+			secret,
+			siblings.into_iter().map(|sibling| sibling.0).collect(),
+			path_indices,
+		)
+		.unwrap();
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			pool.root,
+			100,
+			0,
+			BoundedVec::truncate_from(proof),
+			None
+		));
+		assert_eq!(Balances::balance(&2), 100);
+	});
+}
+
+#[test]
+fn withdraw_refuses_proof_replayed_for_another_recipient() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) = proven_note();
+		let withdraw = |who: u64| {
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(who),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof.clone(),
+				None,
+			)
+		};
+
+		// A front-runner resubmitting the pending withdrawal is not paid
+		assert_noop!(withdraw(3), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw(2));
+		assert_eq!(Balances::balance(&2), 100);
+		assert_eq!(Balances::balance(&3), 0);
+	});
+}
+
+#[test]
+fn withdraw_via_relayer_splits_the_note_between_recipient_and_relayer() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		// Account 2 is fresh: it neither signs nor pays anything
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &3u64, 10));
+		let merkle_root = PrivacyBridge::merkle_root();
+
+		// The relayer submits once the root is no longer the current one
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [7u8; 32]));
+		assert_ok!(PrivacyBridge::withdraw_via_relayer(
+			RuntimeOrigin::signed(3),
+			proof,
+			nullifier,
+			commitment,
+			merkle_root,
+			100,
+			2,
+			3,
+			10,
+			0,
+		));
+
+		assert_eq!(Balances::balance(&2), 90);
+		assert_eq!(Balances::balance(&3), 10);
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		let receipt_id = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				RuntimeEvent::PrivacyBridge(Event::AssetUnshielded { receipt_id, .. }) =>
+					Some(receipt_id),
+				_ => None,
+			})
+			.unwrap();
+		System::assert_last_event(
+			Event::WithdrawalRelayed { receipt_id, relayer: 3, fee: 10 }.into(),
+		);
+	});
+}
+
+#[test]
+fn withdraw_via_relayer_refuses_a_changed_recipient_or_fee() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &3u64, 10));
+		let merkle_root = PrivacyBridge::merkle_root();
+		let relay = |recipient: u64, relayer: u64, fee: u128| {
+			PrivacyBridge::withdraw_via_relayer(
+				RuntimeOrigin::signed(relayer),
+				proof.clone(),
+				nullifier,
+				commitment,
+				merkle_root,
+				100,
+				recipient,
+				relayer,
+				fee,
+				0,
+			)
+		};
+
+		// The relayer cannot pay itself the note, nor raise its fee, nor be replaced
+		assert_noop!(relay(3, 3, 10), Error::<Test>::InvalidProof);
+		assert_noop!(relay(2, 3, 50), Error::<Test>::InvalidProof);
+		assert_noop!(relay(2, 4, 10), Error::<Test>::InvalidProof);
+		assert_noop!(relay(2, 3, 101), Error::<Test>::FeeExceedsAmount);
+
+		assert_ok!(relay(2, 3, 10));
+		assert_eq!(Balances::balance(&2), 90);
+		assert_eq!(Balances::balance(&3), 10);
+	});
+}
+
+#[test]
+fn withdraw_via_relayer_refuses_an_unknown_root() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &3u64, 10));
+
+		assert_noop!(
+			PrivacyBridge::withdraw_via_relayer(
+				RuntimeOrigin::signed(3),
+				proof,
+				nullifier,
+				commitment,
+				H256::repeat_byte(1),
+				100,
+				2,
+				3,
+				10,
+				0,
+			),
+			Error::<Test>::UnknownMerkleRoot
+		);
+	});
+}
+
+/// Unsigned `withdraw_via_relayer` of the note of `proven_note_paying`, paying 2 and the
+/// treasury `fee`
+fn unsigned_relay(
+	nullifier: H256,
+	commitment: H256,
+	proof: crate::zksnark::ProofBytes,
+	fee: u128,
+) -> crate::Call<Test> {
+	crate::Call::withdraw_via_relayer {
+		proof,
+		nullifier,
+		commitment,
+		merkle_root: PrivacyBridge::merkle_root(),
+		amount: 100,
+		recipient: 2,
+		relayer: TREASURY,
+		fee,
+		asset_id: 0,
+	}
+}
+
+#[test]
+fn validate_unsigned_accepts_a_withdrawal_paying_the_treasury() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 10));
+		let call = unsigned_relay(nullifier, commitment, proof, 10);
+
+		let valid = PrivacyBridge::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(valid.provides, vec![("PrivacyBridgeWithdrawal", nullifier).encode()]);
+		assert_eq!(valid.priority, crate::UNSIGNED_WITHDRAWAL_PRIORITY);
+		// The mock keeps four roots: three more updates, then the current block
+		assert_eq!(valid.longevity, 4);
+
+		assert_ok!(PrivacyBridge::pre_dispatch(&call));
+		assert_ok!(call.dispatch_bypass_filter(RuntimeOrigin::none()));
+		assert_eq!(Balances::balance(&2), 90);
+	});
+}
+
+#[test]
+fn validate_unsigned_refuses_a_spent_note() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 10));
+		let call = unsigned_relay(nullifier, commitment, proof, 10);
+		assert_ok!(call.clone().dispatch_bypass_filter(RuntimeOrigin::none()));
+
+		assert_eq!(
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+		assert_eq!(PrivacyBridge::pre_dispatch(&call), Err(InvalidTransaction::Stale.into()));
+	});
+}
+
+#[test]
+fn validate_unsigned_refuses_a_bad_proof_or_fee() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 10));
+		let validate = |call: crate::Call<Test>| {
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &call)
+		};
+
+		// The proof commits to a fee of 10
+		assert_eq!(
+			validate(unsigned_relay(nullifier, commitment, proof.clone(), 20)),
+			InvalidTransaction::BadProof.into()
+		);
+		// Below `UnsignedRelayFee`, or not paid to the treasury
+		assert_eq!(
+			validate(unsigned_relay(nullifier, commitment, proof.clone(), 5)),
+			InvalidTransaction::Payment.into()
+		);
+		let mut call = unsigned_relay(nullifier, commitment, proof.clone(), 10);
+		if let crate::Call::withdraw_via_relayer { relayer, .. } = &mut call {
+			*relayer = 3;
+		}
+		assert_eq!(validate(call.clone()), InvalidTransaction::Payment.into());
+		assert_noop!(
+			call.dispatch_bypass_filter(RuntimeOrigin::none()),
+			Error::<Test>::UnsignedRelayFeeNotPaid
+		);
+
+		UnsignedRelayFee::set(None);
+		assert_eq!(
+			validate(unsigned_relay(nullifier, commitment, proof.clone(), 10)),
+			InvalidTransaction::Payment.into()
+		);
+		assert_noop!(
+			unsigned_relay(nullifier, commitment, proof, 10)
+				.dispatch_bypass_filter(RuntimeOrigin::none()),
+			Error::<Test>::UnsignedWithdrawalsDisabled
+		);
+	});
+}
+
+#[test]
+fn unsigned_withdrawal_lives_as_long_as_its_root() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 10));
+		let call = unsigned_relay(nullifier, commitment, proof, 10);
+		let longevity = || {
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &call)
+				.map(|valid| valid.longevity)
+		};
+		assert_eq!(longevity(), Ok(4));
+
+		// Each deposit moves the root the proof was made against towards eviction
+		for (seed, expected) in [(5u8, 3), (6, 2), (7, 1)] {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+			assert_eq!(longevity(), Ok(expected));
+		}
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [8u8; 32]));
+		assert_eq!(longevity(), Err(InvalidTransaction::Stale.into()));
+	});
+}
+
+#[test]
+fn unsigned_withdrawals_of_one_note_share_the_nullifier_tag() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, first) =
+			proven_note_paying(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 10));
+		let second =
+			prove_withdrawal(0, crate::primitives::relayed_recipient_hash(&2u64, &TREASURY, 20));
+		let first = unsigned_relay(nullifier, commitment, first, 10);
+		let second = unsigned_relay(nullifier, commitment, second, 20);
+		assert_ne!(first, second);
+
+		// Both are valid on their own, but the pool keeps only one of them
+		let first = PrivacyBridge::validate_unsigned(TransactionSource::External, &first).unwrap();
+		let second =
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &second).unwrap();
+		assert_eq!(first.provides, second.provides);
+		assert!(first.requires.is_empty() && second.requires.is_empty());
+	});
+}
+
+/// Unsigned `withdraw` of the note of `proven_note_paying`, to `recipient`
+fn unsigned_withdraw(
+	nullifier: H256,
+	commitment: H256,
+	proof: crate::zksnark::ProofBytes,
+	recipient: Option<u64>,
+) -> crate::Call<Test> {
+	crate::Call::withdraw {
+		nullifier,
+		commitment,
+		merkle_root: PrivacyBridge::merkle_root(),
+		amount: 100,
+		asset_id: 0,
+		proof,
+		recipient,
+	}
+}
+
+#[test]
+fn unsigned_withdraw_pays_the_treasury_its_fee() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::recipient_hash(&2u64));
+		let call = unsigned_withdraw(nullifier, commitment, proof, Some(2));
+
+		let valid = PrivacyBridge::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(valid.provides, vec![("PrivacyBridgeWithdrawal", nullifier).encode()]);
+		assert_eq!(valid.priority, crate::UNSIGNED_WITHDRAWAL_PRIORITY);
+
+		let treasury = Balances::balance(&TREASURY);
+		assert_ok!(PrivacyBridge::pre_dispatch(&call));
+		assert_ok!(call.clone().dispatch_bypass_filter(RuntimeOrigin::none()));
+		// `UnsignedRelayFee` is 10
+		assert_eq!(Balances::balance(&2), 90);
+		assert_eq!(Balances::balance(&TREASURY), treasury + 10);
+
+		assert_eq!(
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+		assert_eq!(PrivacyBridge::pre_dispatch(&call), Err(InvalidTransaction::Stale.into()));
+	});
+}
+
+#[test]
+fn unsigned_withdraw_refuses_a_bad_proof_or_no_recipient() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::recipient_hash(&2u64));
+		let validate = |recipient: Option<u64>| {
+			let call = unsigned_withdraw(nullifier, commitment, proof.clone(), recipient);
+			PrivacyBridge::validate_unsigned(TransactionSource::External, &call)
+		};
+
+		// The proof pays 2, and an unsigned withdrawal has no caller to pay instead
+		assert_eq!(validate(Some(3)), InvalidTransaction::BadProof.into());
+		assert_eq!(validate(None), InvalidTransaction::Call.into());
+		assert_noop!(
+			unsigned_withdraw(nullifier, commitment, proof.clone(), None)
+				.dispatch_bypass_filter(RuntimeOrigin::none()),
+			DispatchError::BadOrigin
+		);
+
+		UnsignedRelayFee::set(None);
+		assert_eq!(validate(Some(2)), InvalidTransaction::Payment.into());
+		assert_noop!(
+			unsigned_withdraw(nullifier, commitment, proof, Some(2))
+				.dispatch_bypass_filter(RuntimeOrigin::none()),
+			Error::<Test>::UnsignedWithdrawalsDisabled
+		);
+	});
+}
+
+#[test]
+fn signed_withdraw_pays_the_proven_recipient() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::recipient_hash(&2u64));
+		let withdraw = |recipient: Option<u64>| {
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(3),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof.clone(),
+				recipient,
+			)
+		};
+
+		// The caller is paid by default, but the proof pays 2
+		assert_noop!(withdraw(None), Error::<Test>::InvalidProof);
+		assert_ok!(withdraw(Some(2)));
+		assert_eq!(Balances::balance(&2), 100);
+		assert_eq!(Balances::balance(&3), 0);
+	});
+}
+
+#[test]
+fn withdraw_refuses_proof_for_another_asset() {
+	new_test_ext().execute_with(|| {
+		create_test_asset(1, 1000);
+		let (nullifier, commitment, proof) = proven_note_of(TEST_ASSET);
+		let withdraw = |asset_id: u32| {
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				asset_id,
+				proof.clone(),
+				None,
+			)
+		};
+
+		// A note of `TEST_ASSET` cannot be paid out in the native asset
+		assert_noop!(withdraw(0), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw(TEST_ASSET));
+		assert_eq!(Assets::balance(TEST_ASSET, 2), 100);
+		assert_eq!(Balances::balance(&2), 0);
+	});
+}
+
+#[test]
+fn withdraw_refuses_proof_for_another_amount() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) = proven_note();
+		let withdraw = |amount: u128| {
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				amount,
+				0,
+				proof.clone(),
+				None,
+			)
+		};
+
+		// The note holds 100: its proof pays out neither more nor less
+		assert_noop!(withdraw(500), Error::<Test>::InvalidProof);
+		assert_noop!(withdraw(99), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw(100));
+		assert_eq!(Balances::balance(&2), 100);
+	});
+}
+
+#[test]
+fn withdraw_to_remote_refuses_proof_for_another_destination() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let native = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			native,
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		let relay = crate::bridge_adapter::VersionedDestination::V5(Location::parent());
+		let beneficiary: BoundedVec<u8, _> = vec![7u8; 32].try_into().unwrap();
+		let recipient_hash = crate::primitives::remote_recipient_hash(&relay, &beneficiary);
+		let (nullifier, commitment, proof) = proven_note_paying(0, recipient_hash);
+		let withdraw = |destination: Location, beneficiary: &[u8]| {
+			PrivacyBridge::withdraw_to_remote(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				0,
+				100,
+				destination.into(),
+				beneficiary.to_vec().try_into().unwrap(),
+				0,
+				proof.clone(),
+			)
+		};
+
+		// Neither the chain nor the account paid can change
+		let sibling = Location::new(1, [Parachain(2000)]);
+		assert_noop!(withdraw(sibling, &beneficiary), Error::<Test>::InvalidProof);
+		assert_noop!(withdraw(Location::parent(), &[8u8; 32]), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw(Location::parent(), &beneficiary));
+		let exported: Vec<_> = ExportedWithdrawals::get()
+			.into_iter()
+			.map(|(_, amount, destination, beneficiary)| (amount, destination, beneficiary))
+			.collect();
+		assert_eq!(exported, vec![(100, relay, beneficiary.to_vec())]);
+	});
+}
+
+#[test]
+fn withdraw_to_parachain_pays_only_the_proven_beneficiary() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let native = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			native,
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		let bob = Location::new(0, [AccountId32 { network: None, id: [7u8; 32] }]);
+		let recipient_hash = crate::primitives::recipient_hash(&bob);
+		let (nullifier, commitment, proof) = proven_note_paying(0, recipient_hash);
+		let withdraw = |beneficiary: Location| {
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				0,
+				100,
+				Location::parent(),
+				beneficiary,
+				0,
+				proof.clone(),
+			)
+		};
+
+		// Whoever submits the proof, it only pays its beneficiary
+		let mallory = Location::new(0, [AccountId32 { network: None, id: [8u8; 32] }]);
+		assert_noop!(withdraw(mallory), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw(bob.clone()));
+		let beneficiaries: Vec<_> = ExportedWithdrawals::get()
+			.into_iter()
+			.map(|(_, _, _, beneficiary)| beneficiary)
+			.collect();
+		assert_eq!(beneficiaries, vec![bob.encode()]);
+	});
+}
+
+#[test]
+fn withdraw_refuses_proof_that_does_not_verify() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (nullifier, commitment, proof) = proven_note();
+
+		// A flipped bit breaks the proof, or its decoding
+		let mut tampered = proof.clone();
+		tampered[40] ^= 1;
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				tampered,
+				None
+			),
+			Error::<Test>::InvalidProof
+		);
+
+		// The proof does not prove another nullifier of the note
+		let other = Pallet::<Test>::generate_nullifier(&commitment, &[3u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				other,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof.clone(),
+				None
+			),
+			Error::<Test>::InvalidProof
+		);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+		assert!(!NullifierSet::<Test>::get(&other));
+
+		// It places the note under the root it was made against, not a later one
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [5u8; 32]));
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof.clone(),
+				None
+			),
+			Error::<Test>::InvalidProof
+		);
+
+		// Nor without a key to verify it against
+		crate::VerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof,
+				None,
+			),
+			Error::<Test>::VerifyingKeyNotSet
+		);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+	});
+}
+
+#[test]
+fn deposit_without_verifying_key_warns_once() {
+	new_test_ext().execute_with(|| {
+		// Configured: no warning
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert!(!System::events()
+			.iter()
+			.any(|record| record.event == Event::BridgeNotFullyConfigured.into()));
+
+		crate::VerifyingKeys::<Test>::remove(CircuitId::Withdraw);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [2u8; 32]));
+		System::assert_has_event(Event::BridgeNotFullyConfigured.into());
+
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [3u8; 32]));
+		let warnings = System::events()
+			.iter()
+			.filter(|record| record.event == Event::BridgeNotFullyConfigured.into())
+			.count();
+		assert_eq!(warnings, 1);
+	});
+}
+
+#[test]
+fn withdrawals_past_the_verification_budget_wait_for_the_next_block() {
+	new_test_ext().execute_with(|| {
+		MaxVerificationWeightPerBlock::set(Pallet::<Test>::proof_verification_weight().saturating_mul(2));
+		fund_pool(2000);
+		let notes: Vec<(H256, H256)> = (1..=3u8)
+			.map(|seed| {
+				assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [seed; 32]));
+				let commitment = Pallet::<Test>::generate_commitment(100, 0, &[seed; 32]);
+				(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[seed + 10; 32]))
+			})
+			.collect();
+		let validate = |(commitment, nullifier): (H256, H256)| {
+			let call = RuntimeCall::PrivacyBridge(crate::Call::withdraw {
+				nullifier,
+				commitment,
+				merkle_root: PrivacyBridge::merkle_root(),
+				amount: 100,
+				asset_id: 0,
+				proof: mock_proof(),
+				recipient: None,
+			});
+			CheckVerificationBudget::<Test>::new()
+				.validate_only(
+					RuntimeOrigin::signed(2),
+					&call,
+					&DispatchInfo::default(),
+					0,
+					TransactionSource::External,
+				)
+				.map(|_| ())
+		};
+
+		// Two proofs fit in a block
+		for &(commitment, nullifier) in &notes[..2] {
+			assert_ok!(validate((commitment, nullifier)));
+			assert_ok!(PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			));
+		}
+
+		// The third is deferred rather than dropped, and deposits are not limited
+		let (commitment, nullifier) = notes[2];
+		assert_eq!(validate(notes[2]), Err(InvalidTransaction::ExhaustsResources.into()));
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::VerificationBudgetExhausted
+		);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [9u8; 32]));
+
+		// Next block
+		run_to_block(2);
+		assert_eq!(Pallet::<Test>::verification_weight_used(), Weight::zero());
+		assert_ok!(validate(notes[2]));
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(Balances::balance(&2), 300);
+	});
+}
+
+#[test]
+fn verification_budget_of_a_past_block_does_not_defer_pool_transactions() {
+	new_test_ext().execute_with(|| {
+		MaxVerificationWeightPerBlock::set(Pallet::<Test>::proof_verification_weight());
+		fund_pool(2000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		// The pool validates as of the next block, before its `on_initialize` clears the
+		// weight left in storage
+		System::set_block_number(2);
+		assert_eq!(Pallet::<Test>::verification_weight_used(), Weight::zero());
+		assert!(Pallet::<Test>::verification_budget_after(1).is_some());
+		assert!(Pallet::<Test>::verification_budget_after(2).is_none());
+	});
+}
+
+#[test]
+fn deposit_and_withdrawal_are_logged() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		let ((), lines) = capture_logs(|| {
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+			assert_ok!(PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				mock_proof(),
+				None,
+			));
+			assert_noop!(
+				PrivacyBridge::withdraw(
+					RuntimeOrigin::signed(2),
+					nullifier,
+					commitment,
+					PrivacyBridge::merkle_root(),
+					500,
+					0,
+					mock_proof(),
+					None,
+				),
+				Error::<Test>::NullifierAlreadyUsed
+			);
+		});
+		let logged = |level: log::Level, text: String| {
+			lines.iter().any(|line| {
+				line.level == level && line.target == crate::LOG_TARGET && line.message.contains(&text)
+			})
+		};
+
+		assert!(logged(log::Level::Debug, format!("shielded {commitment:?} of asset 0 at leaf 0")));
+		assert!(logged(log::Level::Debug, format!("withdrawal of {nullifier:?}: proof checks passed")));
+		assert!(logged(log::Level::Debug, format!("withdrawal of {nullifier:?}: paid in asset 0")));
+		// The replay
+		let replay = DispatchError::from(Error::<Test>::NullifierAlreadyUsed);
+		assert!(logged(log::Level::Warn, format!("withdrawal of {nullifier:?} failed: {replay:?}")));
+	});
+}
+
+/// Bounded list of alert tags
+fn alert_tags(tags: &[H256]) -> BoundedVec<H256, <Test as crate::Config>::MaxAlertTags> {
+	tags.to_vec().try_into().unwrap()
+}
+
+#[test]
+fn watcher_detects_spend_of_registered_alert_tag() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let salt = [7u8; 32];
+		let notes: Vec<(H256, H256)> = (1..=2u8)
+			.map(|byte| {
+				assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [byte; 32]));
+				let commitment = Pallet::<Test>::generate_commitment(100, 0, &[byte; 32]);
+				(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[byte + 10; 32]))
+			})
+			.collect();
+		let tags: Vec<H256> =
+			notes.iter().map(|(_, nullifier)| crate::primitives::alert_tag(nullifier, &salt)).collect();
+
+		assert_ok!(PrivacyBridge::register_alert_tags(RuntimeOrigin::signed(1), alert_tags(&tags)));
+		System::assert_last_event(Event::AlertTagsRegistered { account: 1, count: 2 }.into());
+		assert_eq!(AlertTags::<Test>::get(1).to_vec(), tags);
+
+		// The watcher only holds the tags and the salt
+		let query = vec![AlertTagQuery { salt, tags: tags.clone() }];
+		assert!(PrivacyBridge::match_alert_tags(0, query.clone()).is_empty());
+
+		run_to_block(3);
+		let (commitment, nullifier) = notes[1];
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		assert_eq!(
+			PrivacyBridge::match_alert_tags(0, query.clone()),
+			vec![AlertMatch { tag: tags[1], block_number: 3 }]
+		);
+		// Spends before the watcher's cursor are not reported again
+		assert!(PrivacyBridge::match_alert_tags(4, query).is_empty());
+		// The tags mean nothing under another salt
+		let wrong_salt = vec![AlertTagQuery { salt: [8u8; 32], tags: tags.clone() }];
+		assert!(PrivacyBridge::match_alert_tags(0, wrong_salt).is_empty());
+	});
+}
+
+#[test]
+fn unregistered_alert_tags_are_not_matched() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let salt = [7u8; 32];
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let tag = crate::primitives::alert_tag(&nullifier, &salt);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(1),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		let query = vec![AlertTagQuery { salt, tags: vec![tag] }];
+
+		// Never registered
+		assert!(PrivacyBridge::match_alert_tags(0, query.clone()).is_empty());
+
+		// Registered by two accounts, then dropped by both
+		for account in [1, 2] {
+			assert_ok!(PrivacyBridge::register_alert_tags(
+				RuntimeOrigin::signed(account),
+				alert_tags(&[tag])
+			));
+		}
+		assert_eq!(AlertTagRegistrations::<Test>::get(tag), 2);
+		assert_eq!(PrivacyBridge::match_alert_tags(0, query.clone()).len(), 1);
+		assert_ok!(PrivacyBridge::register_alert_tags(RuntimeOrigin::signed(1), alert_tags(&[])));
+		assert_eq!(PrivacyBridge::match_alert_tags(0, query.clone()).len(), 1);
+		assert_ok!(PrivacyBridge::register_alert_tags(RuntimeOrigin::signed(2), alert_tags(&[])));
+		assert!(!AlertTagRegistrations::<Test>::contains_key(tag));
+		assert!(!AlertTags::<Test>::contains_key(2));
+		assert!(PrivacyBridge::match_alert_tags(0, query).is_empty());
+
+		assert_noop!(
+			PrivacyBridge::register_alert_tags(RuntimeOrigin::signed(1), alert_tags(&[tag, tag])),
+			Error::<Test>::DuplicateAlertTag
+		);
+	});
+}
+
+#[test]
+fn alert_tags_do_not_let_the_watcher_spend() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let salt = [7u8; 32];
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let tag = crate::primitives::alert_tag(&nullifier, &salt);
+		assert_ok!(PrivacyBridge::register_alert_tags(RuntimeOrigin::signed(1), alert_tags(&[tag])));
+
+		// Registering is not spending, and the tag is not the nullifier
+		assert_ne!(tag, nullifier);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+		assert!(SpentNullifiers::<Test>::iter().next().is_none());
+
+		// All the watcher can try is spending with what it holds
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(3),
+				tag,
+				tag,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::CommitmentNotFound
+		);
+		assert!(PrivacyBridge::match_alert_tags(0, vec![AlertTagQuery { salt, tags: vec![tag] }])
+			.is_empty());
+
+		// The owner's spend still goes through, and is what the watcher sees
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(1),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		assert_eq!(
+			PrivacyBridge::match_alert_tags(0, vec![AlertTagQuery { salt, tags: vec![tag] }]),
+			vec![AlertMatch { tag, block_number: 1 }]
+		);
+	});
+}
+
+#[test]
+fn recent_actions_record_tree_changes_and_spends() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_eq!(PrivacyBridge::recent_actions(0), Some(vec![]));
+
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(100, 0, &[1u8; 32]);
+		let root = PrivacyBridge::merkle_root();
+		run_to_block(2);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+
+		let record = |sequence, block_number, action| ActionRecord { sequence, block_number, action };
+		assert_eq!(
+			PrivacyBridge::recent_actions(0),
+			Some(vec![
+				record(0, 1, Action::LeafInserted { leaf_index: 0, commitment }),
+				record(1, 1, Action::RootUpdated { root }),
+				record(2, 2, Action::NullifierSpent { nullifier }),
+			])
+		);
+		// A wallet that saw the deposit only fetches the spend
+		assert_eq!(
+			PrivacyBridge::recent_actions(2),
+			Some(vec![record(2, 2, Action::NullifierSpent { nullifier })])
+		);
+		assert_eq!(PrivacyBridge::recent_actions(3), Some(vec![]));
+	});
+}
+
+#[test]
+fn recent_actions_wrap_around_and_signal_gaps() {
+	new_test_ext().execute_with(|| {
+		// Every insertion records its leaf and the new root
+		for i in 0..200u64 {
+			assert_ok!(Pallet::<Test>::insert_commitment(H256::from_low_u64_be(i + 1), &1, 0));
+		}
+
+		// The journal never holds more than its slots
+		assert_eq!(crate::RecentActions::<Test>::iter().count() as u32, crate::MAX_RECENT_ACTIONS);
+		assert_eq!(crate::ActionSequence::<Test>::get(), 400);
+		let oldest = crate::RecentActions::<Test>::get(400 % 256).unwrap();
+		assert_eq!(oldest.sequence, 400 - 256);
+		assert_eq!(
+			oldest.action,
+			Action::LeafInserted { leaf_index: 72, commitment: H256::from_low_u64_be(73) }
+		);
+		assert_eq!(crate::RecentActions::<Test>::get(399 % 256).unwrap().sequence, 399);
+
+		// Evicted actions cannot be served: the wallet has to resync
+		assert_eq!(PrivacyBridge::recent_actions(0), None);
+		assert_eq!(PrivacyBridge::recent_actions(143), None);
+		assert_eq!(PrivacyBridge::recent_actions(144).unwrap().len(), 256);
+		let suffix = PrivacyBridge::recent_actions(398).unwrap();
+		assert_eq!(suffix.iter().map(|record| record.sequence).collect::<Vec<_>>(), vec![398, 399]);
+	});
+}
+
+/// Deposit a native note of 100, returning what spends it
+fn deposit_scored_note(seed: u8) -> (H256, H256) {
+	let randomness = [seed; 32];
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, randomness));
+	let commitment = Pallet::<Test>::generate_commitment(100, 0, &randomness);
+	(Pallet::<Test>::generate_nullifier(&commitment, &randomness), commitment)
+}
+
+fn spend_scored_note((nullifier, commitment): (H256, H256)) {
+	assert_ok!(PrivacyBridge::withdraw(
+		RuntimeOrigin::signed(2),
+		nullifier,
+		commitment,
+		PrivacyBridge::merkle_root(),
+		100,
+		0,
+		mock_proof(),
+		None,
+	));
+}
+
+fn low_anonymity_warnings() -> Vec<(u32, u32)> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			RuntimeEvent::PrivacyBridge(Event::LowAnonymityWarning { asset_id, score }) =>
+				Some((asset_id, score)),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn anonymity_score_follows_notes_and_recent_deposits() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_eq!(PrivacyBridge::anonymity_score(0), AnonymityScore::default());
+
+		// Window 0 (blocks 0 to 9 with a `ScoreWindow` of 10)
+		let notes: Vec<_> = (1..=3).map(deposit_scored_note).collect();
+		let score = |unspent_notes, recent_deposits| AnonymityScore {
+			unspent_notes,
+			recent_deposits,
+			score: unspent_notes + recent_deposits,
+			bucket: AnonymityBucket::Minimal,
+		};
+		assert_eq!(PrivacyBridge::anonymity_score(0), score(3, 3));
+		assert_eq!(PrivacyBridge::pool_stats(0).anonymity, score(3, 3));
+
+		// Halfway through window 1, half of window 0 is still covered
+		run_to_block(15);
+		assert_eq!(PrivacyBridge::anonymity_score(0), score(3, 1));
+
+		// Window 2 covers none of window 0
+		run_to_block(25);
+		assert_eq!(PrivacyBridge::anonymity_score(0), score(3, 0));
+
+		spend_scored_note(notes[0]);
+		assert_eq!(PrivacyBridge::anonymity_score(0), score(2, 0));
+		deposit_scored_note(4);
+		assert_eq!(PrivacyBridge::anonymity_score(0), score(3, 1));
+		assert_eq!(crate::AnonymityTallies::<Test>::get(0).window, 2);
+
+		// Other assets keep their own score
+		assert_eq!(PrivacyBridge::anonymity_score(1), AnonymityScore::default());
+	});
+}
+
+#[test]
+fn low_anonymity_warning_fires_once_per_drop_below_the_floor() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+
+		// A pool that never reached the floor of 5 does not warn
+		let first = deposit_scored_note(1);
+		let second = deposit_scored_note(2);
+		spend_scored_note(first);
+		assert!(low_anonymity_warnings().is_empty());
+
+		let notes: Vec<_> = (3..=4).map(deposit_scored_note).collect();
+		assert_eq!(PrivacyBridge::anonymity_score(0).score, 3 + 4);
+
+		// Once the deposits age out, the next spend reports the drop
+		run_to_block(25);
+		spend_scored_note(notes[0]);
+		assert_eq!(low_anonymity_warnings(), vec![(0, 2)]);
+
+		// Staying below the floor does not repeat it
+		spend_scored_note(notes[1]);
+		assert_eq!(low_anonymity_warnings(), vec![(0, 2)]);
+
+		// Climbing back over the floor re-arms the warning
+		let refill: Vec<_> = (5..=7).map(deposit_scored_note).collect();
+		assert_eq!(PrivacyBridge::anonymity_score(0).score, 4 + 3);
+		spend_scored_note(refill[0]);
+		assert_eq!(low_anonymity_warnings(), vec![(0, 2)]);
+		run_to_block(45);
+		spend_scored_note(second);
+		assert_eq!(low_anonymity_warnings(), vec![(0, 2), (0, 2)]);
+	});
+}
+
+#[test]
+fn anonymity_buckets_grade_scores() {
+	assert_eq!(AnonymityBucket::of(0), AnonymityBucket::Minimal);
+	assert_eq!(AnonymityBucket::of(9), AnonymityBucket::Minimal);
+	assert_eq!(AnonymityBucket::of(10), AnonymityBucket::Low);
+	assert_eq!(AnonymityBucket::of(999), AnonymityBucket::Moderate);
+	assert_eq!(AnonymityBucket::of(1000), AnonymityBucket::High);
+	assert_eq!(AnonymityBucket::of(u32::MAX), AnonymityBucket::High);
+}
+
+/// Deposit a note of the aged native asset, returning its commitment and nullifier
+fn deposit_aged_note(seed: u8) -> (H256, H256) {
+	assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [seed; 32]));
+	let commitment = Pallet::<Test>::generate_commitment(500, 0, &[seed; 32]);
+	(commitment, Pallet::<Test>::generate_nullifier(&commitment, &[seed + 1; 32]))
+}
+
+/// Corrupt the tree frontier, deposit a leaf on top of it and return the root the stored
+/// leaves actually hash to
+fn corrupt_tree_cache() -> H256 {
+	FilledSubtrees::<Test>::insert(0, H256::repeat_byte(0xee));
+	deposit_aged_note(20);
+	let leaves: Vec<H256> =
+		(0..CommitmentCount::<Test>::get()).filter_map(MerkleLeaves::<Test>::get).collect();
+	let root = crate::merkle_tree::root_of::<MockHasher>(&leaves).unwrap();
+	assert_ne!(PrivacyBridge::merkle_root(), root);
+	root
+}
+
+#[test]
+fn rebuild_tree_recovers_a_corrupted_cache() {
+	new_test_ext().execute_with(|| {
+		let _ = aged_note_setup(None, Some(100));
+		deposit_aged_note(3);
+		deposit_aged_note(5);
+		let _ = corrupt_tree_cache();
+
+		// The newest note is only provable against the true root, which was never recorded
+		let (commitment, nullifier) = deposit_aged_note(7);
+		let leaves: Vec<H256> = (0..5).filter_map(MerkleLeaves::<Test>::get).collect();
+		let root = crate::merkle_tree::root_of::<MockHasher>(&leaves).unwrap();
+		assert_ne!(PrivacyBridge::merkle_root(), root);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				root,
+				500,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::UnknownMerkleRoot
+		);
+
+		assert_noop!(
+			PrivacyBridge::start_tree_rebuild(RuntimeOrigin::signed(1), root),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 0, 2),
+			Error::<Test>::NoTreeRebuild
+		);
+		assert_ok!(PrivacyBridge::start_tree_rebuild(RuntimeOrigin::root(), root));
+		System::assert_last_event(Event::TreeRebuildStarted { expected_root: root, leaves: 5 }.into());
+
+		// Maintenance mode: no leaf joins the tree mid-rebuild
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [9u8; 32]),
+			Error::<Test>::TreeUnderMaintenance
+		);
+
+		// One chunk per block, in leaf order
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 0, 2));
+		System::assert_last_event(Event::TreeRebuildProgressed { next_leaf: 2, remaining: 3 }.into());
+		run_to_block(2);
+		let chunks = [(0, 2), (3, 2), (2, 0), (2, 4), (2, crate::MAX_REBUILD_CHUNK + 1)];
+		for (start_leaf, count) in chunks {
+			assert_noop!(
+				PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), start_leaf, count),
+				Error::<Test>::InvalidRebuildChunk
+			);
+		}
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 2, 2));
+		System::assert_last_event(Event::TreeRebuildProgressed { next_leaf: 4, remaining: 1 }.into());
+		run_to_block(3);
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 4, 1));
+		System::assert_last_event(Event::TreeRebuilt { root, leaves: 5 }.into());
+		assert_eq!(PrivacyBridge::merkle_root(), root);
+		assert_eq!(PrivacyBridge::tree_rebuild(), None);
+
+		// Withdrawals against the true root work again, and new leaves extend it
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			root,
+			500,
+			0,
+			mock_proof(),
+			None,
+		));
+		deposit_aged_note(11);
+		let leaves: Vec<H256> = (0..6).filter_map(MerkleLeaves::<Test>::get).collect();
+		assert_eq!(
+			PrivacyBridge::merkle_root(),
+			crate::merkle_tree::root_of::<MockHasher>(&leaves).unwrap()
+		);
+	});
+}
+
+#[test]
+fn rebuild_tree_rejects_an_unexpected_root() {
+	new_test_ext().execute_with(|| {
+		let _ = aged_note_setup(None, Some(100));
+		let root = corrupt_tree_cache();
+
+		assert_ok!(PrivacyBridge::start_tree_rebuild(RuntimeOrigin::root(), H256::repeat_byte(1)));
+		assert_noop!(
+			PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 0, 2),
+			Error::<Test>::RebuiltRootMismatch
+		);
+		assert!(PrivacyBridge::tree_rebuild().is_some());
+
+		// Restarting with the right root completes the rebuild
+		assert_ok!(PrivacyBridge::start_tree_rebuild(RuntimeOrigin::root(), root));
+		assert_ok!(PrivacyBridge::rebuild_tree(RuntimeOrigin::root(), 0, 2));
+		assert_eq!(PrivacyBridge::merkle_root(), root);
+	});
+}
+
+#[test]
+fn withdrawals_get_stable_receipt_ids() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let (first, first_commitment) = deposit_scored_note(1);
+		let (second, second_commitment) = deposit_scored_note(2);
+
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			first,
+			first_commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		// Hash(nullifier || block || extrinsic index)
+		let first_id = crate::primitives::withdrawal_receipt_id(&first, 1, 0);
+		System::assert_last_event(
+			Event::AssetUnshielded {
+				nullifier: first,
+				asset_id: 0,
+				block_number: 1,
+				receipt_id: first_id,
+			}
+			.into(),
+		);
+		assert_eq!(
+			PrivacyBridge::withdrawal_by_id(first_id),
+			Some(WithdrawalRecord {
+				nullifier: first,
+				asset_id: 0,
+				amount: 100,
+				fee: 0,
+				block_number: 1,
+				extrinsic_index: 0,
+				destination: None,
+			})
+		);
+
+		// Another block and extrinsic give another ID
+		run_to_block(2);
+		System::set_extrinsic_index(3);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(2),
+			second,
+			second_commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof(),
+			None,
+		));
+		let second_id = crate::primitives::withdrawal_receipt_id(&second, 2, 3);
+		assert_ne!(second_id, first_id);
+		let record = PrivacyBridge::withdrawal_by_id(second_id).unwrap();
+		assert_eq!((record.nullifier, record.block_number, record.extrinsic_index), (second, 2, 3));
+
+		// The first receipt still resolves; unknown IDs do not
+		assert_eq!(PrivacyBridge::withdrawal_by_id(first_id).unwrap().nullifier, first);
+		assert_eq!(PrivacyBridge::withdrawal_by_id(H256::repeat_byte(1)), None);
+	});
+}
+
+#[test]
+fn reused_randomness_is_rejected_within_the_window() {
+	new_test_ext().execute_with(|| {
+		// Not checked without a window
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [1u8; 32]));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 200, 0, [1u8; 32]));
+
+		RandomnessReuseWindow::set(5);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [2u8; 32]));
+		let key = crate::primitives::randomness_commitment(&[2u8; 32]);
+		assert_eq!(crate::RecentRandomness::<Test>::get(key), Some(1));
+
+		// Another amount under the same randomness, by any deposit call
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 300, 0, [2u8; 32]),
+			Error::<Test>::RandomnessReused
+		);
+		assert_noop!(
+			PrivacyBridge::deposit_with_receipt(RuntimeOrigin::signed(1), 300, 0, [2u8; 32]),
+			Error::<Test>::RandomnessReused
+		);
+		run_to_block(5);
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 300, 0, [2u8; 32]),
+			Error::<Test>::RandomnessReused
+		);
+	});
+}
+
+#[test]
+fn recent_randomness_is_pruned_after_the_window() {
+	new_test_ext().execute_with(|| {
+		RandomnessReuseWindow::set(5);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [2u8; 32]));
+
+		// Block 6 forgets the randomness seen at block 1
+		run_to_block(6);
+		let key = crate::primitives::randomness_commitment(&[2u8; 32]);
+		assert_eq!(crate::RecentRandomness::<Test>::get(key), None);
+		assert_eq!(crate::RandomnessSeenAt::<Test>::iter_prefix(1).count(), 0);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 300, 0, [2u8; 32]));
+
+		// The new use starts a new window
+		assert_eq!(crate::RecentRandomness::<Test>::get(key), Some(6));
+		run_to_block(10);
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [2u8; 32]),
+			Error::<Test>::RandomnessReused
+		);
+		run_to_block(11);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 400, 0, [2u8; 32]));
+	});
+}
+
+/// Post a proving job of account 1 with `bounty`, returning its ID
+fn post_proving_job(bounty: u128) -> u64 {
+	let job_id = crate::NextProvingJobId::<Test>::get();
+	let request = BoundedVec::truncate_from(vec![0xab; 16]);
+	assert_ok!(PrivacyBridge::post_proving_job(RuntimeOrigin::signed(1), request, bounty));
+	job_id
+}
+
+#[test]
+fn proving_job_is_posted_claimed_and_fulfilled() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		Balances::set_balance(&1, 1000);
+		assert_ok!(Balances::mint_into(&3, 1000));
+		let (nullifier, commitment) = deposit_scored_note(1);
+
+		let job_id = post_proving_job(30);
+		System::assert_last_event(
+			Event::ProvingJobPosted { job_id, owner: 1, bounty: 30, expires_at: 11 }.into(),
+		);
+		assert_eq!(PrivacyBridge::proving_job_request(job_id).unwrap().to_vec(), vec![0xab; 16]);
+		assert_eq!(Balances::balance(&1), 1000 - 100 - 30);
+		assert_eq!(Balances::balance(&Pallet::<Test>::proving_jobs_account()), 30);
+
+		// Only the prover that claimed the job can fulfil it
+		assert_noop!(
+			PrivacyBridge::fulfill_proving_job(
+				RuntimeOrigin::signed(3),
+				job_id,
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof()
+			),
+			Error::<Test>::NotJobProver
+		);
+		assert_ok!(PrivacyBridge::claim_proving_job(RuntimeOrigin::signed(3), job_id));
+		System::assert_last_event(
+			Event::ProvingJobClaimed { job_id, prover: 3, deadline: 6 }.into(),
+		);
+		assert_eq!(Balances::balance(&3), 1000 - PROVER_BOND);
+		assert_noop!(
+			PrivacyBridge::claim_proving_job(RuntimeOrigin::signed(4), job_id),
+			Error::<Test>::ProvingJobAlreadyClaimed
+		);
+		assert_noop!(
+			PrivacyBridge::fulfill_proving_job(
+				RuntimeOrigin::signed(4),
+				job_id,
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof()
+			),
+			Error::<Test>::NotJobProver
+		);
+
+		// The owner gets the withdrawal, the prover the bounty and its bond
+		assert_ok!(PrivacyBridge::fulfill_proving_job(
+			RuntimeOrigin::signed(3),
+			job_id,
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			100,
+			0,
+			mock_proof()
+		));
+		System::assert_last_event(
+			Event::ProvingJobFulfilled { job_id, prover: 3, bounty: 30 }.into(),
+		);
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_eq!(Balances::balance(&1), 1000 - 30);
+		assert_eq!(Balances::balance(&3), 1000 + 30);
+		assert_eq!(Balances::balance(&Pallet::<Test>::proving_jobs_account()), 0);
+		assert_eq!(PrivacyBridge::proving_job(job_id), None);
+		assert_eq!(PrivacyBridge::proving_job_request(job_id), None);
+		assert_eq!(crate::ProvingJobCount::<Test>::get(), 0);
+	});
+}
+
+#[test]
+fn proving_job_is_fulfilled_only_by_a_proof_paying_its_owner() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		Balances::set_balance(&1, 1000);
+		assert_ok!(Balances::mint_into(&3, 1000));
+		let job_id = post_proving_job(30);
+		assert_ok!(PrivacyBridge::claim_proving_job(RuntimeOrigin::signed(3), job_id));
+		let (nullifier, commitment, prover_proof) =
+			proven_note_paying(0, crate::primitives::recipient_hash(&3u64));
+		let fulfill = |proof| {
+			PrivacyBridge::fulfill_proving_job(
+				RuntimeOrigin::signed(3),
+				job_id,
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				proof,
+			)
+		};
+
+		// A proof paying the prover neither withdraws nor releases the bounty
+		assert_noop!(fulfill(prover_proof), Error::<Test>::InvalidProof);
+		assert_eq!(Balances::balance(&Pallet::<Test>::proving_jobs_account()), 30 + PROVER_BOND);
+
+		assert_ok!(fulfill(prove_withdrawal(0, crate::primitives::recipient_hash(&1u64))));
+		assert_eq!(Balances::balance(&1), 1000 - 30);
+		assert_eq!(Balances::balance(&3), 1000 + 30);
+	});
+}
+
+#[test]
+fn unclaimed_proving_job_expires_with_a_refund() {
+	new_test_ext().execute_with(|| {
+		Balances::set_balance(&1, 1000);
+		assert_ok!(Balances::mint_into(&3, 1000));
+		let request = || BoundedVec::truncate_from(vec![0xab; 16]);
+		assert_noop!(
+			PrivacyBridge::post_proving_job(RuntimeOrigin::signed(1), request(), 9),
+			Error::<Test>::BountyTooLow
+		);
+
+		// The board holds `MaxProvingJobs` jobs
+		let job_id = post_proving_job(30);
+		let _ = post_proving_job(30);
+		assert_noop!(
+			PrivacyBridge::post_proving_job(RuntimeOrigin::signed(1), request(), 30),
+			Error::<Test>::TooManyProvingJobs
+		);
+
+		run_to_block(10);
+		assert_noop!(
+			PrivacyBridge::expire_proving_job(RuntimeOrigin::signed(5), job_id),
+			Error::<Test>::ProvingJobNotLapsed
+		);
+
+		// Past its TTL the job can no longer be claimed, only expired
+		run_to_block(11);
+		assert_noop!(
+			PrivacyBridge::claim_proving_job(RuntimeOrigin::signed(3), job_id),
+			Error::<Test>::ProvingJobLapsed
+		);
+		assert_ok!(PrivacyBridge::expire_proving_job(RuntimeOrigin::signed(5), job_id));
+		System::assert_last_event(
+			Event::ProvingJobExpired { job_id, refunded: 30, slashed: 0 }.into(),
+		);
+		assert_eq!(Balances::balance(&1), 1000 - 30);
+		assert_eq!(PrivacyBridge::proving_job(job_id), None);
+
+		// Which frees a slot on the board
+		let _ = post_proving_job(30);
+	});
+}
+
+#[test]
+fn lapsed_claim_slashes_the_prover_bond() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		Balances::set_balance(&1, 1000);
+		assert_ok!(Balances::mint_into(&3, 1000));
+		let (nullifier, commitment) = deposit_scored_note(1);
+		let treasury = Balances::balance(&TREASURY);
+
+		let job_id = post_proving_job(30);
+		assert_ok!(PrivacyBridge::claim_proving_job(RuntimeOrigin::signed(3), job_id));
+		assert_noop!(
+			PrivacyBridge::expire_proving_job(RuntimeOrigin::signed(5), job_id),
+			Error::<Test>::ProvingJobNotLapsed
+		);
+
+		// The claim lapses at block 6: too late to fulfil
+		run_to_block(6);
+		assert_noop!(
+			PrivacyBridge::fulfill_proving_job(
+				RuntimeOrigin::signed(3),
+				job_id,
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				mock_proof()
+			),
+			Error::<Test>::ProvingJobLapsed
+		);
+		assert_ok!(PrivacyBridge::expire_proving_job(RuntimeOrigin::signed(5), job_id));
+		System::assert_last_event(
+			Event::ProvingJobExpired { job_id, refunded: 30, slashed: PROVER_BOND }.into(),
+		);
+		assert_eq!(Balances::balance(&1), 1000 - 100);
+		assert_eq!(Balances::balance(&3), 1000 - PROVER_BOND);
+		assert_eq!(Balances::balance(&TREASURY), treasury + PROVER_BOND);
+		assert_eq!(Balances::balance(&Pallet::<Test>::proving_jobs_account()), 0);
+
+		// The note was never spent
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+	});
+}
+
+#[test]
+fn asset_winddown_runs_through_its_phases() {
+	new_test_ext().execute_with(|| {
+		let asset_id = register_with_abandonment(1_000);
+		fund_pool(1000);
+		let notes = [(1, 500, [1u8; 32]), (2, 300, [3u8; 32]), (3, 200, [5u8; 32])];
+		for (who, amount, randomness) in notes {
+			assert_ok!(Balances::mint_into(&who, amount));
+			assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(who), amount, 0, randomness));
+		}
+		let note = |amount, randomness: [u8; 32]| {
+			let commitment = Pallet::<Test>::generate_commitment(amount, 0, &randomness);
+			(Pallet::<Test>::generate_nullifier(&commitment, &[randomness[0] + 1; 32]), commitment)
+		};
+
+		assert_noop!(
+			PrivacyBridge::begin_asset_winddown(RuntimeOrigin::signed(1), asset_id.clone()),
+			DispatchError::BadOrigin
+		);
+		approve_admin_action(AdminAction::WindDownAsset { asset_id: asset_id.clone() });
+		assert_ok!(PrivacyBridge::begin_asset_winddown(RuntimeOrigin::root(), asset_id.clone()));
+		System::assert_last_event(
+			Event::WindDownStarted { asset_id: 0, claim_window_ends: 11 }.into(),
+		);
+		assert!(!AssetRegistry::<Test>::get(&asset_id).unwrap().is_active);
+		assert_noop!(
+			PrivacyBridge::begin_asset_winddown(RuntimeOrigin::root(), asset_id),
+			Error::<Test>::AssetWindingDown
+		);
+
+		// Claim window: no deposits, withdrawals as usual, refunds not open yet
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, 0, [7u8; 32]),
+			Error::<Test>::AssetWindingDown
+		);
+		run_to_block(5);
+		let (nullifier, commitment) = note(200, [5u8; 32]);
+		assert_ok!(PrivacyBridge::withdraw(
+			RuntimeOrigin::signed(3),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			200,
+			0,
+			mock_proof(),
+			None
+		));
+		assert_noop!(
+			PrivacyBridge::open_winddown_refunds(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::WindDownPhaseRunning
+		);
+		assert_noop!(
+			PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::WrongWindDownPhase
+		);
+
+		// The issuer defaults: the pool holds half of the 800 still shielded
+		let pool = Pallet::<Test>::account_id();
+		Balances::set_balance(&pool, 400);
+
+		run_to_block(11);
+		assert_ok!(PrivacyBridge::open_winddown_refunds(RuntimeOrigin::signed(4), 0));
+		System::assert_last_event(
+			Event::WindDownRefundsOpened { asset_id: 0, owed: 800, held: 400, refunds_end: 21 }
+				.into(),
+		);
+
+		// Refunds: proofs are refused, recorded notes refunded at half their amount
+		let (nullifier, commitment) = note(300, [3u8; 32]);
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				300,
+				0,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::ClaimWindowClosed
+		);
+		assert_ok!(PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 0));
+		let (_, commitment) = note(500, [1u8; 32]);
+		System::assert_last_event(
+			Event::WindDownNoteRefunded {
+				leaf_index: 0,
+				commitment,
+				asset_id: 0,
+				amount: 250,
+				claimant: 1,
+			}
+			.into(),
+		);
+		assert_eq!(AbandonedClaims::<Test>::get(1, 0), 250);
+		assert_noop!(
+			PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::CommitmentDenied
+		);
+		assert_noop!(
+			PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 2),
+			Error::<Test>::NoteAlreadySpent
+		);
+
+		run_to_block(20);
+		assert_noop!(
+			PrivacyBridge::finalize_winddown(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::WindDownPhaseRunning
+		);
+
+		// The note never refunded is swept with the rest to the treasury
+		run_to_block(21);
+		assert_ok!(PrivacyBridge::finalize_winddown(RuntimeOrigin::signed(4), 0));
+		System::assert_last_event(Event::WindDownFinalized { asset_id: 0, swept: 150 }.into());
+		assert_eq!(AbandonedClaims::<Test>::get(TREASURY, 0), 150);
+		assert_eq!(TotalShielded::<Test>::get(0), 0);
+		assert_eq!(PrivacyBridge::wind_down(0), Some(WindDownPhase::Finalized));
+		assert_noop!(
+			PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 1),
+			Error::<Test>::WrongWindDownPhase
+		);
+		assert_noop!(
+			PrivacyBridge::finalize_winddown(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::WrongWindDownPhase
+		);
+	});
+}
+
+#[test]
+fn unrecorded_notes_are_left_to_the_final_sweep() {
+	new_test_ext().execute_with(|| {
+		// No abandonment period: the deposited amount is not retained
+		let asset_id = AssetId(Location::here());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0
+		));
+		fund_pool(500);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+
+		approve_admin_action(AdminAction::WindDownAsset { asset_id: asset_id.clone() });
+		assert_ok!(PrivacyBridge::begin_asset_winddown(RuntimeOrigin::root(), asset_id));
+		run_to_block(11);
+		assert_ok!(PrivacyBridge::open_winddown_refunds(RuntimeOrigin::signed(4), 0));
+		assert_noop!(
+			PrivacyBridge::refund_winddown_note(RuntimeOrigin::signed(4), 0),
+			Error::<Test>::NoteNotRefundable
+		);
+
+		run_to_block(21);
+		assert_ok!(PrivacyBridge::finalize_winddown(RuntimeOrigin::signed(4), 0));
+		assert_eq!(AbandonedClaims::<Test>::get(1, 0), 0);
+		assert_eq!(AbandonedClaims::<Test>::get(TREASURY, 0), 500);
+	});
+}
+
+/// Payouts of a split withdrawal
+fn payouts(
+	entries: &[(u64, u128)],
+) -> BoundedVec<(u64, u128), <Test as crate::Config>::MaxPayouts> {
+	BoundedVec::truncate_from(entries.to_vec())
+}
+
+#[test]
+fn withdraw_split_rejects_payouts_not_adding_up() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+		let withdraw_split = |entries: &[(u64, u128)]| {
+			PrivacyBridge::withdraw_split(
+				RuntimeOrigin::signed(1),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				500,
+				0,
+				payouts(entries),
+				mock_proof(),
+			)
+		};
+
+		assert_noop!(withdraw_split(&[]), Error::<Test>::NoPayouts);
+		assert_noop!(withdraw_split(&[(2, 200), (3, 200)]), Error::<Test>::PayoutSumMismatch);
+		assert_noop!(
+			withdraw_split(&[(2, 300), (3, u128::MAX)]),
+			Error::<Test>::AmountOverflow
+		);
+		assert!(!NullifierSet::<Test>::get(&nullifier));
+
+		assert_ok!(withdraw_split(&[(2, 200), (3, 300)]));
+		let receipt_id = Pallet::<Test>::withdrawal_receipt_id(nullifier);
+		assert_eq!(Balances::balance(&2), 200);
+		assert_eq!(Balances::balance(&3), 300);
+		assert_eq!(Balances::balance(&Pallet::<Test>::account_id()), 1000);
+		System::assert_has_event(
+			Event::SplitPayout { receipt_id, recipient: 3, amount: 300, claim_id: None }.into(),
+		);
+		System::assert_last_event(
+			Event::WithdrawalSplit { receipt_id, asset_id: 0, amount: 500, recipients: 2 }.into(),
+		);
+	});
+}
+
+#[test]
+fn withdraw_split_pays_only_the_proven_payouts() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		let proven = payouts(&[(2, 60), (3, 40)]);
+		let (nullifier, commitment, proof) =
+			proven_note_paying(0, crate::primitives::split_recipient_hash(&proven));
+		let withdraw_split = |split| {
+			PrivacyBridge::withdraw_split(
+				RuntimeOrigin::signed(1),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				100,
+				0,
+				split,
+				proof.clone(),
+			)
+		};
+
+		// The shares swapped, and another recipient
+		assert_noop!(withdraw_split(payouts(&[(2, 40), (3, 60)])), Error::<Test>::InvalidProof);
+		assert_noop!(withdraw_split(payouts(&[(2, 60), (4, 40)])), Error::<Test>::InvalidProof);
+
+		assert_ok!(withdraw_split(proven));
+		assert_eq!(Balances::balance(&2), 60);
+		assert_eq!(Balances::balance(&3), 40);
+	});
+}
+
+#[test]
+fn withdraw_split_records_failed_payouts_as_claims() {
+	new_test_ext().execute_with(|| {
+		fund_pool(1000);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, 0, [1u8; 32]));
+		let commitment = Pallet::<Test>::generate_commitment(500, 0, &[1u8; 32]);
+		let nullifier = Pallet::<Test>::generate_nullifier(&commitment, &[2u8; 32]);
+
+		// Below the existential deposit: paying fresh account 7 fails, the others succeed
+		let dust = EXISTENTIAL_DEPOSIT - 1;
+		assert_ok!(PrivacyBridge::withdraw_split(
+			RuntimeOrigin::signed(1),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			500,
+			0,
+			payouts(&[(2, 200), (7, dust), (3, 300 - dust)]),
+			mock_proof(),
+		));
+
+		assert!(NullifierSet::<Test>::get(&nullifier));
+		assert_eq!(Balances::balance(&2), 200);
+		assert_eq!(Balances::balance(&3), 300 - dust);
+		assert_eq!(Balances::balance(&7), 0);
+		assert_eq!(
+			Claims::<Test>::get(0),
+			Some(Claim { asset_id: 0, amount: dust, beneficiary: 7 })
+		);
+		let receipt_id = Pallet::<Test>::withdrawal_receipt_id(nullifier);
+		System::assert_has_event(
+			Event::SplitPayout { receipt_id, recipient: 7, amount: dust, claim_id: Some(0) }.into(),
+		);
+		System::assert_has_event(
+			Event::SplitPayout { receipt_id, recipient: 3, amount: 300 - dust, claim_id: None }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn admin_action_without_approval_is_refused() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		let action = apply_key_action(CircuitId::Withdraw, &[1, 2, 3]);
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AdminActionNotApproved
+		);
+
+		// Only the admin proposes, only the co-signer approves
+		assert_noop!(
+			PrivacyBridge::propose_admin_action(co_signer(), action.hash()),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			PrivacyBridge::approve_admin_action(co_signer(), action.hash()),
+			Error::<Test>::AdminActionNotProposed
+		);
+		assert_ok!(PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action.hash()));
+		assert_noop!(
+			PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action.hash()),
+			Error::<Test>::AdminActionAlreadyProposed
+		);
+		assert_noop!(
+			PrivacyBridge::approve_admin_action(RuntimeOrigin::root(), action.hash()),
+			DispatchError::BadOrigin
+		);
+
+		// Proposed but not approved
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AdminActionNotApproved
+		);
+
+		// An approval of another key does not apply this one
+		approve_admin_action(apply_key_action(CircuitId::Withdraw, &[4, 5]));
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AdminActionNotApproved
+		);
+		let key = PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap();
+		assert_eq!(key.into_inner(), TEST_VERIFYING_KEY.to_vec());
+	});
+}
+
+#[test]
+fn expired_admin_approval_is_refused() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		let action = apply_key_action(CircuitId::Withdraw, &[1, 2, 3]);
+
+		// `AdminApprovalTtl` is 10 blocks: too late to approve
+		assert_ok!(PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action.hash()));
+		run_to_block(11);
+		assert_noop!(
+			PrivacyBridge::approve_admin_action(co_signer(), action.hash()),
+			Error::<Test>::AdminApprovalExpired
+		);
+
+		// Approved in time, but executed too late
+		approve_admin_action(action.clone());
+		run_to_block(21);
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AdminApprovalExpired
+		);
+		let key = PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap();
+		assert_eq!(key.into_inner(), TEST_VERIFYING_KEY.to_vec());
+	});
+}
+
+#[test]
+fn admin_action_executes_once_with_both_approvals() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		let action_hash = apply_key_action(CircuitId::Withdraw, &[1, 2, 3]).hash();
+
+		assert_ok!(PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action_hash));
+		System::assert_last_event(
+			Event::AdminActionProposed { action_hash, expires_at: 11 }.into(),
+		);
+		run_to_block(5);
+		assert_ok!(PrivacyBridge::approve_admin_action(co_signer(), action_hash));
+		System::assert_last_event(Event::AdminActionApproved { action_hash }.into());
+
+		assert_ok!(PrivacyBridge::apply_verifying_key(
+			RuntimeOrigin::signed(1),
+			CircuitId::Withdraw
+		));
+		System::assert_has_event(Event::AdminActionExecuted { action_hash }.into());
+		assert_eq!(
+			PrivacyBridge::verifying_key_for(CircuitId::Withdraw).unwrap().into_inner(),
+			vec![1, 2, 3]
+		);
+
+		// The approval is consumed: the same key announced again needs a new one
+		assert!(AdminApprovals::<Test>::get(action_hash).is_none());
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			CircuitId::Withdraw,
+			BoundedVec::truncate_from(vec![1, 2, 3]),
+			HasherId::Blake2,
+		));
+		assert_noop!(
+			PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(1), CircuitId::Withdraw),
+			Error::<Test>::AdminActionNotApproved
+		);
+	});
+}
@@ -0,0 +1,68 @@
+//! Weight and proof size budgets of the benchmarked calls
+//!
+//! Each budget is what the benchmark measured when it was last reviewed. The regression
+//! tests in `weight_regression_tests` fail once a measurement exceeds its budget by more
+//! than `TOLERANCE_PERCENT`: a change that makes a call heavier has to raise the budget
+//! here, in the same diff, so the growth is seen in review (and the weights re-run).
+//!
+//! Reads, writes and proof size only depend on the code and the storage layout. Times
+//! depend on the machine and build, so `ref_time` is only checked in optimised builds;
+//! measure with `cargo test --release --features runtime-benchmarks`.
+
+/// How far above its budget a measurement may go
+pub const TOLERANCE_PERCENT: u64 = 10;
+
+/// Expected cost of a benchmark at given components
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+	/// Name of the benchmark
+	pub benchmark: &'static str,
+	/// Number of leaves already in the tree (the `n` component)
+	pub leaves: u32,
+	/// Execution time, in picoseconds
+	pub ref_time: u64,
+	/// Estimated proof size, in bytes (see `benchmarking::measure`)
+	pub proof_size: u64,
+	pub reads: u32,
+	pub writes: u32,
+}
+
+/// Budgets of a deposit hashing its leaf right away: the tree's fill must not change them
+pub const DEPOSIT: &[Budget] = &[
+	Budget {
+		benchmark: "deposit",
+		leaves: 0,
+		ref_time: 2_000_000_000,
+		proof_size: 12_200,
+		reads: 46,
+		writes: 65,
+	},
+	Budget {
+		benchmark: "deposit",
+		leaves: 1_000,
+		ref_time: 2_000_000_000,
+		proof_size: 12_200,
+		reads: 46,
+		writes: 65,
+	},
+];
+
+/// Budgets of a withdrawal
+pub const WITHDRAW: &[Budget] = &[
+	Budget {
+		benchmark: "withdraw",
+		leaves: 0,
+		ref_time: 1_000_000_000,
+		proof_size: 8_000,
+		reads: 30,
+		writes: 25,
+	},
+	Budget {
+		benchmark: "withdraw",
+		leaves: 1_000,
+		ref_time: 1_000_000_000,
+		proof_size: 8_000,
+		reads: 30,
+		writes: 25,
+	},
+];
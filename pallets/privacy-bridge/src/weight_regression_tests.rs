@@ -0,0 +1,82 @@
+//! Weight and proof size regression tests
+//!
+//! Run the `deposit` and `withdraw` benchmarks on the mock runtime and compare what they
+//! measure against the budgets checked in at `weight_budgets`.
+
+use crate::benchmarking::{measure, Measurement};
+use crate::weight_budgets::{Budget, DEPOSIT, TOLERANCE_PERCENT, WITHDRAW};
+use frame::deps::frame_benchmarking::v2::BenchmarkParameter;
+use crate::mock::new_test_ext;
+use frame::testing_prelude::*;
+
+/// Largest value `budget` lets a measurement reach
+fn allowed(budget: u64) -> u64 {
+	budget.saturating_add(budget.saturating_mul(TOLERANCE_PERCENT) / 100)
+}
+
+/// Check the measurement of `budget`'s benchmark stays within it
+fn assert_within(budget: &Budget) {
+	let measured: Measurement = new_test_ext().execute_with(|| {
+		measure(budget.benchmark.as_bytes(), &[(BenchmarkParameter::n, budget.leaves)], 5)
+			.expect("benchmark runs on the mock runtime")
+	});
+	let name = budget.benchmark;
+	let leaves = budget.leaves;
+	let checks = [
+		("reads", u64::from(measured.reads), u64::from(budget.reads)),
+		("writes", u64::from(measured.writes), u64::from(budget.writes)),
+		("proof_size", measured.proof_size, budget.proof_size),
+	];
+	for (component, measured, expected) in checks {
+		assert!(
+			measured <= allowed(expected),
+			"`{name}` at {leaves} leaves measured {component} {measured}, over its budget of \
+			 {expected} (+{TOLERANCE_PERCENT}%). If this is expected, raise the budget in \
+			 `weight_budgets.rs` and re-run the weights."
+		);
+	}
+	if !cfg!(debug_assertions) {
+		assert!(
+			measured.ref_time <= allowed(budget.ref_time),
+			"`{name}` at {leaves} leaves took {} ps, over its budget of {} ps \
+			 (+{TOLERANCE_PERCENT}%)",
+			measured.ref_time,
+			budget.ref_time
+		);
+	}
+}
+
+#[test]
+fn deposit_stays_within_budget() {
+	DEPOSIT.iter().for_each(assert_within);
+}
+
+#[test]
+fn withdraw_stays_within_budget() {
+	WITHDRAW.iter().for_each(assert_within);
+}
+
+#[test]
+fn deposit_cost_does_not_grow_with_the_tree() {
+	let [empty, filled] = [0, 1_000].map(|leaves| {
+		new_test_ext().execute_with(|| {
+			measure(b"deposit", &[(BenchmarkParameter::n, leaves)], 1)
+				.expect("benchmark runs on the mock runtime")
+		})
+	});
+	assert_eq!((empty.reads, empty.writes), (filled.reads, filled.writes));
+	assert_eq!(empty.proof_size, filled.proof_size);
+}
+
+#[test]
+fn merkle_path_cost_does_not_grow_with_the_tree() {
+	// Both paths start from a right leaf, whose left sibling is read
+	let [small, filled] = [2, 1_000].map(|leaves| {
+		new_test_ext().execute_with(|| {
+			measure(b"merkle_path", &[(BenchmarkParameter::n, leaves)], 1)
+				.expect("benchmark runs on the mock runtime")
+		})
+	});
+	assert_eq!((small.reads, small.writes), (filled.reads, filled.writes));
+	assert_eq!(small.proof_size, filled.proof_size);
+}
@@ -3,6 +3,17 @@
 //!
 //! These are temporary weights for the Week 1 MVP
 //! Will be benchmarked properly in later weeks
+//!
+//! Deposits are charged per tree level: the `deposit` benchmarks run at several fill
+//! levels and show no slope over the number of leaves already in the tree. Every level
+//! writes its frontier entry and its node in `MerkleNodes`. A deposit of a denominated
+//! asset also appends to its pool's tree, three more reads and three more writes.
+//!
+//! Withdrawals are dominated by Groth16 verification, a few milliseconds of CPU; their
+//! benchmarks run against a stored verifying key and a real proof
+//! (`WithdrawalVerifier::benchmark_proof`). They read the key as `apply_verifying_key`
+//! prepared it, which spares each proof a pairing (`verify_prepared` against
+//! `verify_unprepared`).
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -13,35 +24,124 @@ use core::marker::PhantomData;
 
 /// Weight functions needed for pallet_privacy_bridge.
 pub trait WeightInfo {
-	fn deposit() -> Weight;
+	/// Deposit hashing its leaf through a tree of depth `d`
+	fn deposit(d: u32, ) -> Weight;
+	/// Deposit leaving its leaf to the end-of-block tree update
+	fn deposit_batched() -> Weight;
+	/// Withdrawal verifying its proof
 	fn withdraw() -> Weight;
+	/// Deposit split into `s` notes, each hashed into the tree right away
+	fn deposit_split(s: u32, ) -> Weight;
+	/// Applying an announced key, prepared for verification once
+	fn apply_verifying_key() -> Weight;
+	fn register_asset() -> Weight;
+	/// Cross-chain deposit hashing its leaf right away
+	fn deposit_from_xcm() -> Weight;
+	/// Withdrawal exported to another chain, its proof included
+	fn withdraw_to_parachain() -> Weight;
 }
 
 /// Temporary weights for privacy bridge pallet
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	fn deposit() -> Weight {
+	/// The range of component `d` is `[1, 20]`.
+	fn deposit(d: u32, ) -> Weight {
 		Weight::from_parts(50_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1_u64))
-			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(Weight::from_parts(2_500_000, 0).saturating_mul(d.into()))
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(d.into())))
+			.saturating_add(T::DbWeight::get().writes(15_u64))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(d.into())))
+	}
+	fn deposit_batched() -> Weight {
+		Weight::from_parts(100_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(7_u64))
+			.saturating_add(T::DbWeight::get().writes(28_u64))
 	}
 	fn withdraw() -> Weight {
-		Weight::from_parts(40_000_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1_u64))
-			.saturating_add(T::DbWeight::get().writes(1_u64))
+		Weight::from_parts(6_000_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(13_u64))
+			.saturating_add(T::DbWeight::get().writes(10_u64))
+	}
+	/// The range of component `s` is `[1, 16]`.
+	fn deposit_split(s: u32, ) -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(Weight::from_parts(100_000_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().reads((27_u64).saturating_mul(s.into())))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+			.saturating_add(T::DbWeight::get().writes((48_u64).saturating_mul(s.into())))
+	}
+	fn apply_verifying_key() -> Weight {
+		Weight::from_parts(2_000_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+	fn register_asset() -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	fn deposit_from_xcm() -> Weight {
+		Weight::from_parts(120_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(35_u64))
+			.saturating_add(T::DbWeight::get().writes(57_u64))
+	}
+	fn withdraw_to_parachain() -> Weight {
+		Weight::from_parts(6_150_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(17_u64))
+			.saturating_add(T::DbWeight::get().writes(12_u64))
 	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	fn deposit() -> Weight {
+	/// The range of component `d` is `[1, 20]`.
+	fn deposit(d: u32, ) -> Weight {
 		Weight::from_parts(50_000_000, 0)
-			.saturating_add(RocksDbWeight::get().reads(1_u64))
-			.saturating_add(RocksDbWeight::get().writes(2_u64))
+			.saturating_add(Weight::from_parts(2_500_000, 0).saturating_mul(d.into()))
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(d.into())))
+			.saturating_add(RocksDbWeight::get().writes(15_u64))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(d.into())))
+	}
+	fn deposit_batched() -> Weight {
+		Weight::from_parts(100_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(7_u64))
+			.saturating_add(RocksDbWeight::get().writes(28_u64))
 	}
 	fn withdraw() -> Weight {
-		Weight::from_parts(40_000_000, 0)
-			.saturating_add(RocksDbWeight::get().reads(1_u64))
-			.saturating_add(RocksDbWeight::get().writes(1_u64))
+		Weight::from_parts(6_000_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(13_u64))
+			.saturating_add(RocksDbWeight::get().writes(10_u64))
+	}
+	/// The range of component `s` is `[1, 16]`.
+	fn deposit_split(s: u32, ) -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(Weight::from_parts(100_000_000, 0).saturating_mul(s.into()))
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().reads((27_u64).saturating_mul(s.into())))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+			.saturating_add(RocksDbWeight::get().writes((48_u64).saturating_mul(s.into())))
+	}
+	fn apply_verifying_key() -> Weight {
+		Weight::from_parts(2_000_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+	fn register_asset() -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	fn deposit_from_xcm() -> Weight {
+		Weight::from_parts(120_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(35_u64))
+			.saturating_add(RocksDbWeight::get().writes(57_u64))
+	}
+	fn withdraw_to_parachain() -> Weight {
+		Weight::from_parts(6_150_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(17_u64))
+			.saturating_add(RocksDbWeight::get().writes(12_u64))
 	}
 }
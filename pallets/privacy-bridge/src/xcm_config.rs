@@ -25,8 +25,81 @@
 //! - Mock testing (no actual parachain deployment)
 
 use frame::prelude::*;
+use frame::deps::frame_support::{
+	parameter_types,
+	traits::{Contains, ProcessMessageError},
+};
 use sp_core::H256;
-use staging_xcm::v5::{Asset as XcmAsset, AssetId, Location, Fungibility};
+use sp_runtime::traits::MaybeEquivalence;
+use crate::hasher::NoteHasher;
+pub use crate::primitives::{xcm_note_randomness, XCM_COMMITMENT_VERSION};
+use alloc::{vec, vec::Vec};
+use staging_xcm::v5::{
+	Asset as XcmAsset, AssetId, Error as XcmError, InteriorLocation, Junction, Location,
+	Fungibility, Instruction, Instruction::*, OriginKind, Result as XcmResult, WeightLimit,
+	WildAsset, Xcm, XcmContext,
+};
+use staging_xcm_executor::{
+	traits::{DropAssets, Properties, ShouldExecute, TransactAsset},
+	AssetsInHolding,
+};
+
+/// Maximum number of sibling parachains in the open channel set
+pub const MAX_OPEN_CHANNELS: u32 = 256;
+
+/// Sibling parachain a message to `location` is delivered to directly, if any
+pub fn sibling_para_id(location: &Location) -> Option<u32> {
+	match location.unpack() {
+		(1, [Junction::Parachain(id), ..]) => Some(*id),
+		_ => None,
+	}
+}
+
+/// Most `parents` a deposit's origin location may have (a bridged origin: `../../GlobalConsensus`)
+pub const MAX_ORIGIN_PARENTS: u8 = 2;
+
+/// Most junctions in a deposit's origin location
+pub const MAX_ORIGIN_JUNCTIONS: usize = 4;
+
+/// Whether `location` is acceptable as the origin of an XCM deposit
+///
+/// Origins are held in storage and events, so pathological ones are refused before the
+/// deposit does anything: at most `MAX_ORIGIN_PARENTS` parents and `MAX_ORIGIN_JUNCTIONS`
+/// junctions, and no `GeneralKey` claiming more than its 32 bytes of data.
+pub fn is_valid_origin_location(location: &Location) -> bool {
+	location.parent_count() <= MAX_ORIGIN_PARENTS &&
+		location.interior().len() <= MAX_ORIGIN_JUNCTIONS &&
+		location.interior().iter().all(|junction| match junction {
+			Junction::GeneralKey { length, .. } => *length <= 32,
+			_ => true,
+		})
+}
+
+/// How the shielded value of a registered asset is held
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default)]
+pub enum CustodyMode {
+	/// Locked in the pool account (reserve-backed assets)
+	#[default]
+	Pot,
+	/// Burned on shield and minted on unshield (locally minted wrapped assets)
+	BurnMint,
+}
+
+/// Account type of a withdrawal destination's beneficiaries
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug, Default)]
+pub enum AccountFormat {
+	/// 32-byte accounts (Substrate chains; the public key behind any SS58 prefix)
+	#[default]
+	AccountId32,
+	/// 20-byte Ethereum-style keys (EVM parachains)
+	AccountKey20,
+}
+
+/// Most denominations an asset may restrict its deposits to
+pub const MAX_DENOMINATIONS: u32 = 8;
+
+/// Fixed deposit amounts of an asset, in increasing order
+pub type Denominations = BoundedVec<u128, ConstU32<MAX_DENOMINATIONS>>;
 
 /// Asset registry entry
 /// Maps XCM MultiAsset to local asset ID for privacy operations
@@ -40,6 +113,23 @@ pub struct RegisteredAsset {
 	pub min_deposit: u128,
 	/// Whether asset is active
 	pub is_active: bool,
+	/// Blocks after which an unspent note may be swept into the public claims pool
+	pub abandonment_period: Option<u32>,
+	/// How shielded value is held (fixed at registration)
+	pub custody_mode: CustodyMode,
+	/// Minimum balance (existential deposit) of the asset on destination chains
+	pub remote_min_balance: u128,
+	/// Reserve chain of the asset, or `None` when this chain is the reserve
+	pub reserve_location: Option<Location>,
+	/// Call (pallet index, call index) receiving deposit receipts on the origin chain,
+	/// or `None` to send no receipts
+	pub receipt_call: Option<[u8; 2]>,
+	/// Blocks a note must have been in the tree before it may be spent
+	pub min_note_age: Option<u32>,
+	/// Blocks after which a note may no longer be spent
+	pub max_note_age: Option<u32>,
+	/// Amounts deposits must match exactly, each its own pool; empty for any amount
+	pub denominations: Denominations,
 }
 
 impl RegisteredAsset {
@@ -49,10 +139,260 @@ impl RegisteredAsset {
 			local_id,
 			min_deposit: 0,
 			is_active: true,
+			abandonment_period: None,
+			custody_mode: CustodyMode::Pot,
+			remote_min_balance: 0,
+			reserve_location: None,
+			receipt_call: None,
+			min_note_age: None,
+			max_note_age: None,
+			denominations: Denominations::default(),
 		}
 	}
 }
 
+parameter_types! {
+	/// Suggested weight bound for `AllowShieldDeposits`: a four-instruction program
+	/// plus the commitment insertion, with headroom for the proof size of the tree write
+	pub const ShieldDepositMaxWeight: Weight = Weight::from_parts(1_000_000_000, 64 * 1024);
+}
+
+/// Matches XCM assets that are registered and active in the privacy bridge
+pub struct RegisteredAssets<T>(PhantomData<T>);
+
+impl<T: crate::Config> Contains<AssetId> for RegisteredAssets<T> {
+	fn contains(asset: &AssetId) -> bool {
+		crate::Pallet::<T>::registered_asset(asset).map_or(false, |registered| registered.is_active)
+	}
+}
+
+/// XCM barrier admitting shield deposits and nothing broader
+///
+/// Accepts exactly this program shape, with every asset matched by
+/// `RegisteredAssetsFilter` and the message weight within `MaxWeight`:
+///
+/// ```text
+/// ReserveAssetDeposited(assets) -> [ClearOrigin] -> [BuyExecution] -> DepositAsset(ShieldLocation)
+/// ```
+///
+/// A trailing `SetTopic` is not accepted here; wrap the barrier tuple in
+/// `TrailingSetTopicAsId` as usual. Slot it into the runtime's `XcmConfig::Barrier`:
+///
+/// ```ignore
+/// pub type Barrier = TrailingSetTopicAsId<(
+///     TakeWeightCredit,
+///     AllowShieldDeposits<RegisteredAssets<Runtime>, ShieldLocation, ShieldDepositMaxWeight>,
+///     // ...
+/// )>;
+/// ```
+pub struct AllowShieldDeposits<RegisteredAssetsFilter, ShieldLocation, MaxWeight>(
+	PhantomData<(RegisteredAssetsFilter, ShieldLocation, MaxWeight)>,
+);
+
+impl<RegisteredAssetsFilter, ShieldLocation, MaxWeight> ShouldExecute
+	for AllowShieldDeposits<RegisteredAssetsFilter, ShieldLocation, MaxWeight>
+where
+	RegisteredAssetsFilter: Contains<AssetId>,
+	ShieldLocation: Get<Location>,
+	MaxWeight: Get<Weight>,
+{
+	fn should_execute<RuntimeCall>(
+		_origin: &Location,
+		instructions: &mut [Instruction<RuntimeCall>],
+		max_weight: Weight,
+		_properties: &mut Properties,
+	) -> Result<(), ProcessMessageError> {
+		ensure!(
+			max_weight.all_lte(MaxWeight::get()),
+			ProcessMessageError::Overweight(MaxWeight::get())
+		);
+
+		let mut iter = instructions.iter();
+
+		match iter.next() {
+			Some(ReserveAssetDeposited(assets)) => ensure!(
+				!assets.inner().is_empty() &&
+					assets.inner().iter().all(|asset| RegisteredAssetsFilter::contains(&asset.id)),
+				ProcessMessageError::Unsupported
+			),
+			_ => return Err(ProcessMessageError::BadFormat),
+		}
+
+		let mut next = iter.next();
+		if matches!(next, Some(ClearOrigin)) {
+			next = iter.next();
+		}
+		if matches!(next, Some(BuyExecution { .. })) {
+			next = iter.next();
+		}
+
+		match next {
+			Some(DepositAsset { beneficiary, .. }) if *beneficiary == ShieldLocation::get() => {},
+			_ => return Err(ProcessMessageError::BadFormat),
+		}
+
+		// Nothing may follow the deposit (e.g. a `Transact`)
+		ensure!(iter.next().is_none(), ProcessMessageError::BadFormat);
+
+		Ok(())
+	}
+}
+
+/// Converts between registered asset locations and local asset IDs
+///
+/// Use as the asset ID converter of a `FungiblesAdapter` over the pallet's
+/// `Fungibles`, so XCM transfers of registered assets move the same balances
+/// the pallet pays out.
+pub struct RegisteredAssetIds<T>(PhantomData<T>);
+
+impl<T: crate::Config> MaybeEquivalence<Location, u32> for RegisteredAssetIds<T> {
+	fn convert(location: &Location) -> Option<u32> {
+		crate::Pallet::<T>::registered_asset(&AssetId(location.clone()))
+			.filter(|registered| registered.is_active)
+			.map(|registered| registered.local_id)
+	}
+
+	fn convert_back(local_id: &u32) -> Option<Location> {
+		crate::LocalAssetIndex::<T>::get(local_id).map(|asset_id| asset_id.0)
+	}
+}
+
+/// Asset transactor shielding deposits addressed to `ShieldLocation`
+///
+/// A `DepositAsset` to `ShieldLocation` inserts a commitment for the deposited amount
+/// and credits the assets to `PoolLocation` through `Inner`. The message topic is the
+/// depositor's note key (`primitives::note_key`), so shield messages end with
+/// `SetTopic(note_key)`:
+///
+/// ```text
+/// ReserveAssetDeposited -> ClearOrigin -> BuyExecution -> DepositAsset(ShieldLocation) -> SetTopic(note_key)
+/// ```
+///
+/// The commitment is `xcm_commitment_data` over the deposited amount and the deposit's
+/// origin: the message origin, or the asset's reserve once `ClearOrigin` has run (the
+/// only chain whose reserve deposits are trusted). A message therefore cannot shield
+/// into another chain's note or claim more than it delivered. Deposit receipts go to
+/// the same origin.
+///
+/// Everything else is passed to `Inner`. A rejected shield deposit fails the
+/// instruction, leaving the assets to the asset trap (see `ShieldTrap`).
+pub struct ShieldDepositTransactor<T, Inner, ShieldLocation, PoolLocation>(
+	PhantomData<(T, Inner, ShieldLocation, PoolLocation)>,
+);
+
+impl<T, Inner, ShieldLocation, PoolLocation> TransactAsset
+	for ShieldDepositTransactor<T, Inner, ShieldLocation, PoolLocation>
+where
+	T: crate::Config,
+	Inner: TransactAsset,
+	ShieldLocation: Get<Location>,
+	PoolLocation: Get<Location>,
+{
+	fn can_check_in(origin: &Location, what: &XcmAsset, context: &XcmContext) -> XcmResult {
+		Inner::can_check_in(origin, what, context)
+	}
+
+	fn check_in(origin: &Location, what: &XcmAsset, context: &XcmContext) {
+		Inner::check_in(origin, what, context)
+	}
+
+	fn can_check_out(dest: &Location, what: &XcmAsset, context: &XcmContext) -> XcmResult {
+		Inner::can_check_out(dest, what, context)
+	}
+
+	fn check_out(dest: &Location, what: &XcmAsset, context: &XcmContext) {
+		Inner::check_out(dest, what, context)
+	}
+
+	fn deposit_asset(what: &XcmAsset, who: &Location, context: Option<&XcmContext>) -> XcmResult {
+		if *who != ShieldLocation::get() {
+			return Inner::deposit_asset(what, who, context);
+		}
+
+		let amount = extract_asset_amount(what).ok_or(XcmError::AssetNotFound)?;
+		let context =
+			context.ok_or(XcmError::FailedToTransactAsset("shield deposit without context"))?;
+		let note_key = context.topic_or_message_id();
+		// The origin is usually cleared by now; the deposit is then the asset's reserve's
+		let registered = crate::Pallet::<T>::registered_asset(&what.id);
+		let origin = context
+			.origin
+			.as_ref()
+			.or_else(|| registered.as_ref().and_then(|asset| asset.reserve_location.as_ref()))
+			.ok_or(XcmError::FailedToTransactAsset("shield deposit of unknown origin"))?;
+
+		crate::Pallet::<T>::shield_from_xcm(&what.id, amount, &note_key, origin)
+			.map_err(|_| XcmError::FailedToTransactAsset("shield deposit rejected"))?;
+
+		Inner::deposit_asset(what, &PoolLocation::get(), Some(context))
+	}
+
+	fn withdraw_asset(
+		what: &XcmAsset,
+		who: &Location,
+		maybe_context: Option<&XcmContext>,
+	) -> Result<AssetsInHolding, XcmError> {
+		Inner::withdraw_asset(what, who, maybe_context)
+	}
+
+	fn internal_transfer_asset(
+		asset: &XcmAsset,
+		from: &Location,
+		to: &Location,
+		context: &XcmContext,
+	) -> Result<AssetsInHolding, XcmError> {
+		Inner::internal_transfer_asset(asset, from, to, context)
+	}
+}
+
+/// Asset trap recording fungible assets left in holding as trapped shield deposits
+///
+/// A shield deposit that fails mid-execution (e.g. the commitment tree is full)
+/// leaves its assets in holding. On a dedicated bridge chain every fungible
+/// deposit is shield-bound, so they are recorded against the origin and can be
+/// resolved later with `reclaim_trapped`. Non-fungible assets go to `Inner`:
+///
+/// ```ignore
+/// type AssetTrap = ShieldTrap<Runtime, PolkadotXcm>;
+/// ```
+pub struct ShieldTrap<T, Inner>(PhantomData<(T, Inner)>);
+
+impl<T: crate::Config, Inner: DropAssets> DropAssets for ShieldTrap<T, Inner> {
+	fn drop_assets(origin: &Location, mut assets: AssetsInHolding, context: &XcmContext) -> Weight {
+		let fungible = core::mem::take(&mut assets.fungible);
+		let trapped = fungible.len() as u64;
+
+		for (asset_id, amount) in fungible {
+			crate::Pallet::<T>::note_trapped(origin, asset_id, amount);
+		}
+
+		let weight = T::DbWeight::get().reads_writes(trapped, trapped);
+		if assets.is_empty() {
+			weight
+		} else {
+			weight.saturating_add(Inner::drop_assets(origin, assets, context))
+		}
+	}
+}
+
+/// Canonical form of `location`, as seen from the chain at `universal_location`
+///
+/// Locations handed to us can take detours through our own ancestors, depending on
+/// how the sender addressed them. Parents that only lead back down the same path are
+/// dropped, so equivalent forms map to the same registry key. For a chain at
+/// `[GlobalConsensus(Polkadot), Parachain(2000)]`:
+///
+/// ```text
+/// (2, [GlobalConsensus(Polkadot)])                       -> (1, [])
+/// (2, [GlobalConsensus(Polkadot), Parachain(1000)])      -> (1, [Parachain(1000)])
+/// (1, [Parachain(2000), PalletInstance(50)])             -> (0, [PalletInstance(50)])
+/// ```
+pub fn canonical_location(location: &Location, universal_location: &InteriorLocation) -> Location {
+	let mut canonical = location.clone();
+	canonical.simplify(universal_location);
+	canonical
+}
+
 /// Helper to extract amount from XCM Asset
 pub fn extract_asset_amount(asset: &XcmAsset) -> Option<u128> {
 	match &asset.fun {
@@ -69,23 +409,251 @@ pub fn construct_asset(asset_id: AssetId, amount: u128) -> XcmAsset {
 	}
 }
 
+/// Beneficiary junction of the raw account `raw` on `destination`
+///
+/// The account format is the destination's `DestinationAccountFormat`, `AccountId32` if
+/// none is set, and `raw` must be exactly as long as its accounts. SS58 addresses are
+/// passed decoded: the prefix only names the network, the account is the same.
+pub fn beneficiary_from_encoded<T: crate::Config>(
+	destination: &Location,
+	raw: &[u8],
+) -> Result<Location, crate::Error<T>> {
+	let format = crate::DestinationAccountFormat::<T>::get(destination).unwrap_or_default();
+	let junction = match format {
+		AccountFormat::AccountId32 => Junction::AccountId32 {
+			network: None,
+			id: raw.try_into().map_err(|_| crate::Error::<T>::InvalidBeneficiaryLength)?,
+		},
+		AccountFormat::AccountKey20 => Junction::AccountKey20 {
+			network: None,
+			key: raw.try_into().map_err(|_| crate::Error::<T>::InvalidBeneficiaryLength)?,
+		},
+	};
+	Ok(Location::new(0, [junction]))
+}
+
+/// Build the XCM program paying out a withdrawal on the destination chain
+///
+/// ReserveAssetDeposited -> ClearOrigin -> BuyExecution -> DepositAsset(beneficiary)
+pub fn withdrawal_program(asset: XcmAsset, beneficiary: Location) -> Xcm<()> {
+	let amount = extract_asset_amount(&asset).unwrap_or_default();
+	batch_withdrawal_program(asset.id, &[(amount, beneficiary)], 0)
+}
+
+/// Deposit instructions splitting holding of `asset_id` between `payouts`
+///
+/// Each payout but the last is deposited exactly, less `fee_share`; the last one
+/// takes what is left in holding, including unused fees and rounding.
+pub fn payout_deposits(
+	asset_id: &AssetId,
+	payouts: &[(u128, Location)],
+	fee_share: u128,
+) -> Vec<Instruction<()>> {
+	let last = payouts.len().saturating_sub(1);
+
+	payouts
+		.iter()
+		.enumerate()
+		.map(|(index, (amount, beneficiary))| {
+			let assets = if index == last {
+				WildAsset::AllCounted(1).into()
+			} else {
+				construct_asset(asset_id.clone(), amount.saturating_sub(fee_share)).into()
+			};
+			DepositAsset { assets, beneficiary: beneficiary.clone() }
+		})
+		.collect()
+}
+
+/// Build one XCM program paying out several withdrawals of the same asset
+///
+/// ReserveAssetDeposited -> ClearOrigin -> BuyExecution -> DepositAsset x payouts
+///
+/// The program has `3 + payouts.len()` instructions.
+pub fn batch_withdrawal_program(
+	asset_id: AssetId,
+	payouts: &[(u128, Location)],
+	fee_share: u128,
+) -> Xcm<()> {
+	let total = payouts.iter().fold(0u128, |total, (amount, _)| total.saturating_add(*amount));
+	let asset = construct_asset(asset_id.clone(), total);
+
+	let mut program = vec![
+		ReserveAssetDeposited(asset.clone().into()),
+		ClearOrigin,
+		BuyExecution { fees: asset, weight_limit: WeightLimit::Unlimited },
+	];
+	program.extend(payout_deposits(&asset_id, payouts, fee_share));
+
+	Xcm(program)
+}
+
+/// Confirmation of a shielded deposit, sent back to the chain it came from
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct DepositReceipt {
+	/// The inserted commitment
+	pub commitment: H256,
+	/// Index of the commitment in the Merkle tree
+	pub leaf_index: u32,
+}
+
+/// Build the XCM program delivering a deposit receipt
+///
+/// UnpaidExecution -> Transact(receipt_call ++ receipt) -> SetTopic(commitment)
+///
+/// The call is `receipt_call` (pallet index, call index on the origin chain) followed
+/// by the SCALE-encoded receipt, so the origin chain decodes it as one of its calls
+/// taking `(commitment, leaf_index)`.
+pub fn deposit_receipt_program(receipt_call: [u8; 2], receipt: &DepositReceipt) -> Xcm<()> {
+	let mut call = receipt_call.to_vec();
+	receipt.encode_to(&mut call);
+
+	Xcm(vec![
+		UnpaidExecution { weight_limit: WeightLimit::Unlimited, check_origin: None },
+		Transact {
+			origin_kind: OriginKind::SovereignAccount,
+			fallback_max_weight: None,
+			call: call.into(),
+		},
+		SetTopic(receipt.commitment.0),
+	])
+}
+
+/// Path a cross-chain withdrawal takes to its destination
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum WithdrawalRoute {
+	/// Sent straight to the destination (this chain, or the destination, is the reserve)
+	Direct,
+	/// Withdrawn at the reserve chain and deposited onward from there
+	ViaReserve(Location),
+}
+
+impl WithdrawalRoute {
+	/// Pick the route for an asset with the given reserve
+	///
+	/// A remote reserve is only skipped when it is the destination itself.
+	pub fn select(reserve: Option<&Location>, destination: &Location) -> Self {
+		match reserve {
+			Some(reserve) if reserve != destination => WithdrawalRoute::ViaReserve(reserve.clone()),
+			_ => WithdrawalRoute::Direct,
+		}
+	}
+
+	/// Number of chains executing the withdrawal after it leaves this chain
+	pub fn hops(&self) -> u32 {
+		match self {
+			WithdrawalRoute::Direct => 1,
+			WithdrawalRoute::ViaReserve(_) => 2,
+		}
+	}
+
+	/// Execution fees for the whole route, given the fee charged per hop
+	pub fn estimate_fee(&self, hop_fee: u128) -> u128 {
+		hop_fee.saturating_mul(self.hops() as u128)
+	}
+}
+
+/// Build the local XCM program routing a withdrawal through the asset's reserve
+///
+/// ```text
+/// WithdrawAsset -> InitiateReserveWithdraw(reserve)
+///                    └─ BuyExecution -> DepositReserveAsset(destination)
+///                                         └─ BuyExecution -> DepositAsset(beneficiary)
+/// ```
+///
+/// `asset` and `destination` are relative to this chain (`context`) and are
+/// reanchored for each hop; `beneficiary` is relative to the destination. The
+/// first hop buys execution for both hops, the second only for itself. Returns
+/// `None` if a location cannot be reanchored.
+pub fn reserve_withdrawal_program(
+	asset: XcmAsset,
+	reserve: Location,
+	destination: Location,
+	beneficiary: Location,
+	hop_fee: u128,
+	context: &InteriorLocation,
+) -> Option<Xcm<()>> {
+	let amount = extract_asset_amount(&asset)?;
+	reserve_batch_withdrawal_program(
+		asset.id,
+		&[(amount, beneficiary)],
+		reserve,
+		destination,
+		hop_fee,
+		context,
+	)
+}
+
+/// Batched form of `reserve_withdrawal_program`, with one deposit per payout
+///
+/// The route's fees are split evenly between the payouts (see `payout_deposits`).
+pub fn reserve_batch_withdrawal_program(
+	asset_id: AssetId,
+	payouts: &[(u128, Location)],
+	reserve: Location,
+	destination: Location,
+	hop_fee: u128,
+	context: &InteriorLocation,
+) -> Option<Xcm<()>> {
+	let route = WithdrawalRoute::ViaReserve(reserve.clone());
+	let total = payouts.iter().fold(0u128, |total, (amount, _)| total.saturating_add(*amount));
+	let fee_share = route.estimate_fee(hop_fee) / (payouts.len().max(1) as u128);
+	let asset = construct_asset(asset_id, total);
+	let id_at_reserve = asset.id.clone().reanchored(&reserve, context).ok()?;
+	let id_at_destination = asset.id.clone().reanchored(&destination, context).ok()?;
+	let destination_at_reserve = destination.reanchored(&reserve, context).ok()?;
+
+	let mut deposits = vec![BuyExecution {
+		fees: construct_asset(id_at_destination.clone(), hop_fee),
+		weight_limit: WeightLimit::Unlimited,
+	}];
+	deposits.extend(payout_deposits(&id_at_destination, payouts, fee_share));
+
+	Some(Xcm(vec![
+		WithdrawAsset(asset.into()),
+		InitiateReserveWithdraw {
+			assets: WildAsset::AllCounted(1).into(),
+			reserve,
+			xcm: Xcm(vec![
+				BuyExecution {
+					fees: construct_asset(id_at_reserve, route.estimate_fee(hop_fee)),
+					weight_limit: WeightLimit::Unlimited,
+				},
+				DepositReserveAsset {
+					assets: WildAsset::AllCounted(1).into(),
+					dest: destination_at_reserve,
+					xcm: Xcm(deposits),
+				},
+			]),
+		},
+	]))
+}
+
+/// Fixed-size digest of a deposit's origin: Blake2-256 of its SCALE encoding
+pub fn origin_digest(origin: &Location) -> [u8; 32] {
+	sp_io::hashing::blake2_256(&origin.encode())
+}
+
 /// Generate commitment from XCM asset
 ///
-/// For Week 4, we extend the commitment to include parachain origin
-pub fn xcm_commitment_data(
+/// Wraps `primitives::xcm_commitment`: XCM deposits are domain-separated from local ones,
+/// and deposits from different origins from each other.
+pub fn xcm_commitment_data<H: NoteHasher>(
 	amount: u128,
 	local_asset_id: u32,
-	randomness: &[u8; 32],
-	_origin: &Location, // Future: include in commitment
+	note_key: &[u8; 32],
+	origin: &Location,
 ) -> H256 {
-	// Week 4 MVP: Use simple_hash just like local deposits
-	// Future: Include origin parachain ID in commitment
-	crate::simple_hash::generate_commitment(amount, local_asset_id, randomness)
+	crate::primitives::xcm_commitment::<H>(amount, local_asset_id, note_key, &origin_digest(origin))
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use staging_xcm::v5::{
+		Junction::{AccountId32, GeneralIndex, GlobalConsensus, PalletInstance, Parachain},
+		NetworkId,
+	};
 
 	#[test]
 	fn test_registered_asset_creation() {
@@ -108,16 +676,173 @@ mod tests {
 	}
 
 	#[test]
-	fn test_xcm_commitment_matches_local() {
-		let amount = 1000u128;
-		let asset_id = 1u32;
-		let randomness = [42u8; 32];
-		let origin = Location::parent();
+	fn test_withdrawal_program_deposits_to_beneficiary() {
+		let asset = construct_asset(AssetId(Location::parent()), 1000);
+		let beneficiary = Location::new(0, []);
+
+		let program = withdrawal_program(asset.clone(), beneficiary.clone());
+
+		assert_eq!(program.0.len(), 4);
+		assert_eq!(program.0[0], ReserveAssetDeposited(asset.into()));
+		assert!(matches!(
+			&program.0[3],
+			DepositAsset { beneficiary: b, .. } if *b == beneficiary
+		));
+	}
+
+	#[test]
+	fn test_xcm_commitment_binds_origin() {
+		use crate::hasher::Blake2Hasher;
+
+		let note_key = [42u8; 32];
+		let from = |para_id| {
+			xcm_commitment_data::<Blake2Hasher>(
+				1000,
+				1,
+				&note_key,
+				&Location::new(1, [Parachain(para_id)]),
+			)
+		};
+
+		// Same amount, asset and note key from two parachains: two notes
+		assert_ne!(from(1), from(2));
+		assert_eq!(from(1), from(1));
+	}
+
+	#[test]
+	fn test_sibling_para_id() {
+		assert_eq!(sibling_para_id(&Location::new(1, [Parachain(2000)])), Some(2000));
+		assert_eq!(
+			sibling_para_id(&Location::new(1, [Parachain(2000), AccountId32 { network: None, id: [0; 32] }])),
+			Some(2000)
+		);
+		assert_eq!(sibling_para_id(&Location::parent()), None);
+		assert_eq!(sibling_para_id(&Location::new(0, [Parachain(2000)])), None);
+	}
+
+	#[test]
+	fn test_canonical_location() {
+		let universal: InteriorLocation = [GlobalConsensus(NetworkId::Polkadot), Parachain(2000)].into();
+		let canonical = |location: Location| canonical_location(&location, &universal);
+
+		// Relay asset
+		assert_eq!(canonical(Location::new(2, [GlobalConsensus(NetworkId::Polkadot)])), Location::parent());
+		assert_eq!(canonical(Location::parent()), Location::parent());
+
+		// Sibling asset
+		let sibling = Location::new(1, [Parachain(1000), PalletInstance(50), GeneralIndex(1)]);
+		assert_eq!(
+			canonical(Location::new(
+				2,
+				[GlobalConsensus(NetworkId::Polkadot), Parachain(1000), PalletInstance(50), GeneralIndex(1)],
+			)),
+			sibling
+		);
+		assert_eq!(canonical(sibling.clone()), sibling);
+
+		// Local asset addressed through our own parachain
+		assert_eq!(
+			canonical(Location::new(1, [Parachain(2000), PalletInstance(50)])),
+			Location::new(0, [PalletInstance(50)])
+		);
+
+		// Other consensus systems are left alone
+		let kusama = Location::new(2, [GlobalConsensus(NetworkId::Kusama), Parachain(1000)]);
+		assert_eq!(canonical(kusama.clone()), kusama);
+	}
+
+	#[test]
+	fn test_route_selection() {
+		let relay = Location::parent();
+		let sibling = Location::new(1, [Parachain(2000)]);
+
+		assert_eq!(WithdrawalRoute::select(None, &sibling), WithdrawalRoute::Direct);
+		assert_eq!(WithdrawalRoute::select(Some(&relay), &relay), WithdrawalRoute::Direct);
+		assert_eq!(
+			WithdrawalRoute::select(Some(&relay), &sibling),
+			WithdrawalRoute::ViaReserve(relay.clone())
+		);
+		assert_eq!(WithdrawalRoute::ViaReserve(relay).estimate_fee(10), 20);
+		assert_eq!(WithdrawalRoute::Direct.estimate_fee(10), 10);
+	}
+
+	#[test]
+	fn test_batch_withdrawal_program_deposits_to_each_beneficiary() {
+		let asset_id = AssetId(Location::parent());
+		let payouts: Vec<_> = (1u8..=3)
+			.map(|i| (100 * i as u128, Location::new(0, [AccountId32 { network: None, id: [i; 32] }])))
+			.collect();
 
-		// XCM commitment should match local commitment for MVP
-		let xcm_commit = xcm_commitment_data(amount, asset_id, &randomness, &origin);
-		let local_commit = crate::simple_hash::generate_commitment(amount, asset_id, &randomness);
+		let program = batch_withdrawal_program(asset_id.clone(), &payouts, 0);
 
-		assert_eq!(xcm_commit, local_commit);
+		assert_eq!(program.0.len(), 6);
+		assert_eq!(program.0[0], ReserveAssetDeposited(construct_asset(asset_id.clone(), 600).into()));
+		assert_eq!(
+			program.0[3..],
+			[
+				DepositAsset {
+					assets: construct_asset(asset_id.clone(), 100).into(),
+					beneficiary: payouts[0].1.clone(),
+				},
+				DepositAsset {
+					assets: construct_asset(asset_id, 200).into(),
+					beneficiary: payouts[1].1.clone(),
+				},
+				DepositAsset {
+					assets: WildAsset::AllCounted(1).into(),
+					beneficiary: payouts[2].1.clone(),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_reserve_withdrawal_program_for_relay_asset() {
+		// Parachain 1000 sends a relay-reserve asset to parachain 2000 via the relay
+		let context: InteriorLocation = [Parachain(1000)].into();
+		let asset = construct_asset(AssetId(Location::parent()), 1000);
+		let beneficiary = Location::new(0, [AccountId32 { network: None, id: [7u8; 32] }]);
+
+		let program = reserve_withdrawal_program(
+			asset.clone(),
+			Location::parent(),
+			Location::new(1, [Parachain(2000)]),
+			beneficiary.clone(),
+			10,
+			&context,
+		)
+		.unwrap();
+
+		assert_eq!(program.0.len(), 2);
+		assert_eq!(program.0[0], WithdrawAsset(asset.into()));
+
+		let InitiateReserveWithdraw { reserve, xcm: first_hop, .. } = &program.0[1] else {
+			panic!("expected InitiateReserveWithdraw, got {:?}", program.0[1]);
+		};
+		assert_eq!(*reserve, Location::parent());
+		assert_eq!(first_hop.0.len(), 2);
+		// The relay sees its own asset as `Here` and pays for both hops
+		assert_eq!(
+			first_hop.0[0],
+			BuyExecution {
+				fees: construct_asset(AssetId(Location::here()), 20),
+				weight_limit: WeightLimit::Unlimited,
+			}
+		);
+
+		let DepositReserveAsset { dest, xcm: second_hop, .. } = &first_hop.0[1] else {
+			panic!("expected DepositReserveAsset, got {:?}", first_hop.0[1]);
+		};
+		assert_eq!(*dest, Location::new(0, [Parachain(2000)]));
+		assert_eq!(
+			second_hop.0,
+			vec![
+				BuyExecution {
+					fees: construct_asset(AssetId(Location::parent()), 10),
+					weight_limit: WeightLimit::Unlimited,
+				},
+				DepositAsset { assets: WildAsset::AllCounted(1).into(), beneficiary },
+			]
+		);
 	}
 }
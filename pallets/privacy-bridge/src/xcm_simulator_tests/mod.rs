@@ -0,0 +1,689 @@
+//! End-to-end XCM tests on a simulated network
+//!
+//! Three parachains share the runtime in `parachain`, connected through the relay chain
+//! in `relay_chain`:
+//!
+//! ```text
+//! ParaA (1000) ──shield──> Bridge (2000) ──withdraw──> ParaA (reserve) ──> ParaB (3000)
+//! ```
+//!
+//! ParaA's token is the asset moved around: it is reserve-transferred into the privacy
+//! bridge, shielded by the asset transactor and later withdrawn through its reserve.
+//! Unlike `xcm_tests`, messages run through real barriers, transactors, traders and
+//! routers, and withdrawal proofs are verified with Groth16. Notes and the tree are hashed
+//! with `SimpleHasher`, so the test setups can prove them.
+
+mod parachain;
+mod relay_chain;
+
+use crate::xcm_config::CustodyMode;
+use crate::FeeConfig;
+use frame::{deps::frame_support::traits::fungible::Mutate, testing_prelude::*};
+use sp_core::H256;
+use staging_xcm::{latest::prelude::*, VersionedXcm};
+use staging_xcm_executor::traits::ConvertLocation;
+use xcm_simulator::{decl_test_network, decl_test_parachain, decl_test_relay_chain, TestExt};
+
+pub const ALICE: parachain::AccountId = parachain::AccountId::new([1u8; 32]);
+pub const BOB: parachain::AccountId = parachain::AccountId::new([2u8; 32]);
+pub const INITIAL_BALANCE: u128 = 1_000_000;
+
+pub const PARA_A_ID: u32 = 1000;
+pub const BRIDGE_ID: u32 = 2000;
+pub const PARA_B_ID: u32 = 3000;
+
+/// Local ID of ParaA's token on the bridge and on ParaB (0 is the chain's own token)
+const PARA_A_TOKEN: u32 = 1;
+
+decl_test_parachain! {
+	pub struct ParaA {
+		Runtime = parachain::Runtime,
+		XcmpMessageHandler = parachain::MsgQueue,
+		DmpMessageHandler = parachain::MsgQueue,
+		new_ext = para_ext(PARA_A_ID),
+	}
+}
+
+decl_test_parachain! {
+	pub struct Bridge {
+		Runtime = parachain::Runtime,
+		XcmpMessageHandler = parachain::MsgQueue,
+		DmpMessageHandler = parachain::MsgQueue,
+		new_ext = para_ext(BRIDGE_ID),
+	}
+}
+
+decl_test_parachain! {
+	pub struct ParaB {
+		Runtime = parachain::Runtime,
+		XcmpMessageHandler = parachain::MsgQueue,
+		DmpMessageHandler = parachain::MsgQueue,
+		new_ext = para_ext(PARA_B_ID),
+	}
+}
+
+decl_test_relay_chain! {
+	pub struct Relay {
+		Runtime = relay_chain::Runtime,
+		RuntimeCall = relay_chain::RuntimeCall,
+		RuntimeEvent = relay_chain::RuntimeEvent,
+		XcmConfig = relay_chain::XcmConfig,
+		MessageQueue = relay_chain::MessageQueue,
+		System = relay_chain::System,
+		new_ext = relay_ext(),
+	}
+}
+
+decl_test_network! {
+	pub struct MockNet {
+		relay_chain = Relay,
+		parachains = vec![
+			(PARA_A_ID, ParaA),
+			(BRIDGE_ID, Bridge),
+			(PARA_B_ID, ParaB),
+		],
+	}
+}
+
+pub fn para_ext(para_id: u32) -> TestState {
+	use parachain::{Balances, MsgQueue, Runtime, System};
+
+	let mut ext: TestState = frame_system::GenesisConfig::<Runtime>::default()
+		.build_storage()
+		.unwrap()
+		.into();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		MsgQueue::set_para_id(para_id.into());
+		Balances::set_balance(&ALICE, INITIAL_BALANCE);
+
+		if para_id != PARA_A_ID {
+			register_para_a_token();
+		}
+		if para_id == BRIDGE_ID {
+			configure_bridge();
+		}
+	});
+	ext
+}
+
+pub fn relay_ext() -> TestState {
+	use relay_chain::{Runtime, System};
+
+	let mut ext: TestState = frame_system::GenesisConfig::<Runtime>::default()
+		.build_storage()
+		.unwrap()
+		.into();
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// Location of parachain `para_id` as seen from a sibling
+fn sibling(para_id: u32) -> Location {
+	Location::new(1, [Parachain(para_id)])
+}
+
+/// Sovereign account of sibling `para_id` on a parachain
+fn sibling_account(para_id: u32) -> parachain::AccountId {
+	parachain::LocationToAccountId::convert_location(&sibling(para_id)).unwrap()
+}
+
+/// Register ParaA's token with the privacy bridge and create its asset
+///
+/// The chain's own token takes local ID 0, so ParaA's token gets `PARA_A_TOKEN`.
+fn register_para_a_token() {
+	use parachain::{Assets, PrivacyBridge, RuntimeOrigin};
+
+	for location in [Location::here(), sibling(PARA_A_ID)] {
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			AssetId(location),
+			100,
+			CustodyMode::Pot,
+			0,
+		));
+	}
+	assert_ok!(Assets::force_create(RuntimeOrigin::root(), PARA_A_TOKEN, ALICE, true, 1));
+}
+
+/// Route ParaA's token via its reserve, charge a flat fee for withdrawals to ParaB and set
+/// the test setups' verifying keys so withdrawals and swaps are possible
+fn configure_bridge() {
+	use crate::test_support::{swap_setup, test_setup};
+	use parachain::{PrivacyBridge, RuntimeOrigin};
+	use sp_runtime::traits::{BlakeTwo256, Hash};
+
+	// Keys are announced, approved under dual control (both origins are root here) and applied
+	for (circuit, (_, vk)) in
+		[(crate::CircuitId::Withdraw, test_setup()), (crate::CircuitId::Swap, swap_setup())]
+	{
+		let vk = crate::zksnark::serialize_vk(vk).unwrap();
+		let action =
+			crate::AdminAction::ApplyVerifyingKey { circuit, vk_hash: BlakeTwo256::hash(&vk) };
+		assert_ok!(PrivacyBridge::announce_verifying_key(
+			RuntimeOrigin::root(),
+			circuit,
+			BoundedVec::truncate_from(vk),
+			crate::hasher::HasherId::Simple,
+		));
+		assert_ok!(PrivacyBridge::propose_admin_action(RuntimeOrigin::root(), action.hash()));
+		assert_ok!(PrivacyBridge::approve_admin_action(RuntimeOrigin::root(), action.hash()));
+		assert_ok!(PrivacyBridge::apply_verifying_key(RuntimeOrigin::signed(ALICE), circuit));
+	}
+	assert_ok!(PrivacyBridge::set_reserve_location(
+		RuntimeOrigin::root(),
+		AssetId(sibling(PARA_A_ID)),
+		Some(sibling(PARA_A_ID)),
+	));
+	assert_ok!(PrivacyBridge::sync_open_channels(
+		RuntimeOrigin::root(),
+		BoundedVec::truncate_from(vec![PARA_A_ID, PARA_B_ID]),
+	));
+	assert_ok!(PrivacyBridge::set_destination_fee(
+		RuntimeOrigin::root(),
+		sibling(PARA_B_ID),
+		Some(FeeConfig { flat: WITHDRAWAL_FEE, ppm: 0 }),
+	));
+}
+
+/// Flat fee for withdrawals to ParaB
+const WITHDRAWAL_FEE: u128 = 100;
+
+/// Reserve-transfer `amount` of ParaA's token from Alice into the bridge's note of
+/// `secret` (see `para_a_note`)
+///
+/// The message carries the secret's note key, never the secret.
+fn shield_from_para_a(amount: u128, secret: [u8; 32]) {
+	let note_key = crate::primitives::note_key::<crate::hasher::SimpleHasher>(&secret);
+	let program: Xcm<parachain::RuntimeCall> = Xcm(vec![
+		WithdrawAsset((Here, amount).into()),
+		DepositReserveAsset {
+			assets: AllCounted(1).into(),
+			dest: sibling(BRIDGE_ID),
+			xcm: Xcm(vec![
+				DepositAsset {
+					assets: AllCounted(1).into(),
+					beneficiary: parachain::ShieldLocation::get(),
+				},
+				SetTopic(note_key),
+			]),
+		},
+	]);
+
+	ParaA::execute_with(|| {
+		assert_ok!(parachain::PolkadotXcm::execute(
+			parachain::RuntimeOrigin::signed(ALICE),
+			Box::new(VersionedXcm::V5(program)),
+			Weight::from_parts(1_000_000_000, 1_000_000),
+		));
+	});
+}
+
+/// Commitment and tweak of the note of `secret` `shield_from_para_a` shields
+///
+/// The origin is cleared on the way, so the bridge binds the note to ParaA as the token's
+/// reserve.
+fn para_a_note(amount: u128, secret: [u8; 32]) -> (H256, [u8; 32]) {
+	use crate::simple_hash;
+
+	let tweak = crate::Pallet::<parachain::Runtime>::xcm_withdrawal_tweak(&sibling(PARA_A_ID));
+	let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+	(simple_hash::generate_commitment(amount, PARA_A_TOKEN, &randomness), tweak)
+}
+
+#[test]
+fn test_reserve_transfer_with_shield_instruction_inserts_commitment() {
+	MockNet::reset();
+	let (commitment, _) = para_a_note(10_000, [7u8; 32]);
+
+	shield_from_para_a(10_000, [7u8; 32]);
+
+	ParaA::execute_with(|| {
+		assert_eq!(parachain::Balances::free_balance(&ALICE), INITIAL_BALANCE - 10_000);
+		assert_eq!(parachain::Balances::free_balance(&sibling_account(BRIDGE_ID)), 10_000);
+	});
+
+	Bridge::execute_with(|| {
+		use parachain::{Assets, PrivacyBridge, Runtime};
+
+		assert!(crate::CommitmentIndex::<Runtime>::contains_key(commitment));
+		assert_eq!(crate::TotalShielded::<Runtime>::get(PARA_A_TOKEN), 10_000);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), 10_000);
+		parachain::System::assert_has_event(
+			crate::Event::AssetShielded {
+				commitment,
+				asset_id: PARA_A_TOKEN,
+				depositor: PrivacyBridge::account_id(),
+				block_number: 1,
+				leaf_index: 0,
+				merkle_root: PrivacyBridge::merkle_root(),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn test_shield_deposit_below_minimum_is_trapped() {
+	MockNet::reset();
+	let (commitment, _) = para_a_note(50, [7u8; 32]);
+
+	// The minimum deposit is 100
+	shield_from_para_a(50, [7u8; 32]);
+
+	Bridge::execute_with(|| {
+		use parachain::{Assets, PrivacyBridge, Runtime};
+
+		assert!(!crate::CommitmentIndex::<Runtime>::contains_key(commitment));
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), 0);
+		assert_eq!(
+			PrivacyBridge::trapped_assets(&sibling(PARA_A_ID), &AssetId(sibling(PARA_A_ID))),
+			50
+		);
+	});
+}
+
+#[test]
+fn test_shielded_asset_withdrawn_to_other_parachain_via_reserve() {
+	MockNet::reset();
+	let amount = 10_000;
+	let secret = [8u8; 32];
+	let (commitment, tweak) = para_a_note(amount, secret);
+
+	shield_from_para_a(amount, secret);
+
+	Bridge::execute_with(|| {
+		use parachain::{Assets, PrivacyBridge, RuntimeOrigin, TREASURY};
+
+		let bob: Location = AccountId32 { network: None, id: BOB.into() }.into();
+		let recipient_hash = crate::primitives::recipient_hash(&bob);
+		let (nullifier, merkle_root, proof) =
+			prove_withdrawal(amount, tweak, secret, recipient_hash);
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(ALICE),
+			nullifier,
+			commitment,
+			merkle_root,
+			PARA_A_TOKEN,
+			amount,
+			sibling(PARA_B_ID),
+			bob,
+			WITHDRAWAL_FEE,
+			proof,
+		));
+
+		// The payout left the pool, the fee went to the treasury
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), 0);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &TREASURY), WITHDRAWAL_FEE);
+	});
+
+	// The reserve moved the backing from the bridge's sovereign account to ParaB's
+	ParaA::execute_with(|| {
+		assert_eq!(parachain::Balances::free_balance(&sibling_account(BRIDGE_ID)), WITHDRAWAL_FEE);
+		assert_eq!(
+			parachain::Balances::free_balance(&sibling_account(PARA_B_ID)),
+			amount - WITHDRAWAL_FEE
+		);
+	});
+
+	ParaB::execute_with(|| {
+		assert_eq!(parachain::Assets::balance(PARA_A_TOKEN, &BOB), amount - WITHDRAWAL_FEE);
+	});
+}
+
+#[test]
+fn test_shield_deposit_receipt_sent_to_reserve() {
+	MockNet::reset();
+	let (commitment, _) = para_a_note(10_000, [7u8; 32]);
+
+	Bridge::execute_with(|| {
+		assert_ok!(parachain::PrivacyBridge::set_receipt_call(
+			parachain::RuntimeOrigin::root(),
+			AssetId(sibling(PARA_A_ID)),
+			Some([60, 3]),
+		));
+	});
+
+	shield_from_para_a(10_000, [7u8; 32]);
+
+	// The origin is cleared before the deposit, so the receipt goes to the reserve
+	Bridge::execute_with(|| {
+		assert!(parachain::System::events().iter().any(|record| matches!(
+			&record.event,
+			parachain::RuntimeEvent::PrivacyBridge(crate::Event::DepositReceiptSent {
+				commitment: sent,
+				origin,
+				..
+			}) if *sent == commitment && *origin == sibling(PARA_A_ID)
+		)));
+	});
+}
+
+#[test]
+fn test_redelivered_shield_deposit_held_as_unattributed() {
+	MockNet::reset();
+	// The same shield message arrives twice
+	shield_from_para_a(10_000, [7u8; 32]);
+	shield_from_para_a(10_000, [7u8; 32]);
+
+	Bridge::execute_with(|| {
+		use parachain::{Assets, PrivacyBridge, Runtime};
+
+		assert_eq!(crate::CommitmentCount::<Runtime>::get(), 1);
+		assert_eq!(crate::TotalShielded::<Runtime>::get(PARA_A_TOKEN), 10_000);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), 20_000);
+		assert_eq!(
+			PrivacyBridge::unattributed_balances(&sibling(PARA_A_ID), &AssetId(sibling(PARA_A_ID))),
+			10_000
+		);
+	});
+}
+
+/// Leaves of the bridge's commitment tree in leaf order, as a wallet rebuilds them
+fn bridge_leaves() -> Vec<H256> {
+	let mut leaves: Vec<(u32, H256)> = crate::CommitmentIndex::<parachain::Runtime>::iter()
+		.map(|(commitment, leaf_index)| (leaf_index, commitment))
+		.collect();
+	leaves.sort();
+	leaves.into_iter().map(|(_, commitment)| commitment).collect()
+}
+
+/// Prove the withdrawal of the bridge's note of `amount` of ParaA's token, opened by
+/// `tweak` and `secret`, to whoever `recipient_hash` commits to
+///
+/// The proof is against the current root: (nullifier, root, proof).
+fn prove_withdrawal(
+	amount: u128,
+	tweak: [u8; 32],
+	secret: [u8; 32],
+	recipient_hash: H256,
+) -> (H256, H256, crate::ProofOf<parachain::Runtime>) {
+	use crate::{hasher::SimpleHasher, simple_hash, zksnark};
+
+	let (pk, _) = crate::test_support::test_setup();
+	let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+	let commitment = simple_hash::generate_commitment(amount, PARA_A_TOKEN, &randomness);
+	let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+	let leaves = bridge_leaves();
+	let leaf_index = leaves.iter().position(|leaf| *leaf == commitment).unwrap();
+	let (siblings, path_indices) =
+		crate::merkle_tree::generate_circuit_proof(&leaves, leaf_index).unwrap();
+	let merkle_root = parachain::PrivacyBridge::merkle_root();
+	let proof = zksnark::generate_proof::<SimpleHasher>(
+		pk,
+		nullifier.as_bytes().to_vec(),
+		commitment.as_bytes().to_vec(),
+		merkle_root.as_bytes().to_vec(),
+		recipient_hash.as_bytes().to_vec(),
+		amount,
+		PARA_A_TOKEN,
+		tweak,
+		secret,
+		siblings.into_iter().map(|sibling| sibling.0).collect(),
+		path_indices,
+	)
+	.unwrap();
+	(nullifier, merkle_root, BoundedVec::truncate_from(proof))
+}
+
+/// Alice shields ParaA's token, hands the note privately to Bob, and Bob leaves for ParaB
+/// through a relayer after proving his note with the delegated proving client
+///
+/// The pallet has no one-sided private transfer yet, so the note changes hands through
+/// `shielded_swap`: Alice's note is spent into a note only Bob can open, in exchange for a
+/// native note of his. Bob's note is committed with the circuits' hash, so the proofs his
+/// wallet builds are over the very commitment the chain holds. The composed proof is
+/// checked off chain; the exit itself carries a withdrawal proof the bridge verifies.
+#[test]
+fn test_wallet_flow_from_shield_to_cross_chain_exit() {
+	use crate::delegated_proving::{
+		serve_membership_request, verify_membership_response, MembershipRequest,
+	};
+	use crate::bridge_adapter::VersionedDestination;
+	use crate::test_support::{membership_setup, ownership_setup, swap_setup};
+	use crate::hasher::SimpleHasher;
+	use crate::{circuit::CIRCUIT_VERSION, shield::ProofEnvelope, simple_hash, zksnark};
+
+	MockNet::reset();
+	let amount = 10_000;
+	let native_amount = 1_000;
+	let relayer = parachain::AccountId::new([3u8; 32]);
+	let alice_secret = [8u8; 32];
+	let (alice_note, alice_tweak) = para_a_note(amount, alice_secret);
+	// A swap leg's envelope, proving the note of `opening` becomes `output`
+	let envelope = |note: H256, opening: ([u8; 32], [u8; 32]), value, asset_id, output_randomness| {
+		let (tweak, secret) = opening;
+		let nullifier = simple_hash::generate_nullifier(&note, &secret);
+		let output = simple_hash::generate_commitment(value, asset_id, &output_randomness);
+		let proof = zksnark::generate_swap_proof::<SimpleHasher>(
+			&swap_setup().0,
+			nullifier.as_bytes().to_vec(),
+			note.as_bytes().to_vec(),
+			output.as_bytes().to_vec(),
+			value,
+			asset_id,
+			tweak,
+			secret,
+			output_randomness,
+		)
+		.unwrap();
+		ProofEnvelope { circuit_version: CIRCUIT_VERSION, proof }
+	};
+
+	// Bob's opening of the note Alice sends him, whose randomness he derives from his key
+	let (bob_secret, link_blinding) = ([22u8; 32], [23u8; 32]);
+	let bob_randomness = simple_hash::generate_note_randomness(&bob_secret, &[0u8; 32]);
+	let bob_note = simple_hash::generate_commitment(amount, PARA_A_TOKEN, &bob_randomness);
+	let bob_nullifier = simple_hash::generate_nullifier(&bob_note, &bob_secret);
+
+	// 1. The asset is registered on the bridge and known to ParaB
+	Bridge::execute_with(|| {
+		use parachain::PrivacyBridge;
+
+		let registered = PrivacyBridge::registered_asset(&AssetId(sibling(PARA_A_ID))).unwrap();
+		assert_eq!(registered.local_id, PARA_A_TOKEN);
+		assert!(registered.is_active);
+		assert_eq!(
+			PrivacyBridge::reserve_location(&AssetId(sibling(PARA_A_ID))),
+			Some(sibling(PARA_A_ID))
+		);
+	});
+	ParaB::execute_with(|| {
+		assert!(pallet_assets::Asset::<parachain::Runtime>::contains_key(PARA_A_TOKEN));
+	});
+
+	// 2. Alice shields from ParaA
+	shield_from_para_a(amount, alice_secret);
+
+	ParaA::execute_with(|| {
+		assert_eq!(parachain::Balances::free_balance(&ALICE), INITIAL_BALANCE - amount);
+		assert_eq!(parachain::Balances::free_balance(&sibling_account(BRIDGE_ID)), amount);
+	});
+	Bridge::execute_with(|| {
+		use parachain::{Assets, PrivacyBridge, Runtime, System};
+
+		assert_eq!(crate::CommitmentIndex::<Runtime>::get(alice_note), Some(0));
+		assert_eq!(crate::TotalShielded::<Runtime>::get(PARA_A_TOKEN), amount);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), amount);
+		System::assert_has_event(
+			crate::Event::AssetShielded {
+				commitment: alice_note,
+				asset_id: PARA_A_TOKEN,
+				depositor: PrivacyBridge::account_id(),
+				block_number: 1,
+				leaf_index: 0,
+				merkle_root: PrivacyBridge::merkle_root(),
+			}
+			.into(),
+		);
+	});
+
+	// 3. Alice's note privately becomes Bob's
+	Bridge::execute_with(|| {
+		use parachain::{Balances, PrivacyBridge, Runtime, RuntimeOrigin, System};
+
+		Balances::set_balance(&BOB, INITIAL_BALANCE);
+		let bob_native_key = crate::primitives::note_key::<SimpleHasher>(&[8u8; 32]);
+		assert_ok!(PrivacyBridge::deposit(
+			RuntimeOrigin::signed(BOB),
+			native_amount,
+			0,
+			bob_native_key,
+		));
+		let bob_native_note = PrivacyBridge::generate_commitment(native_amount, 0, &bob_native_key);
+		// What Alice receives for her note
+		let alice_output_key = crate::primitives::note_key::<SimpleHasher>(&[9u8; 32]);
+		assert_eq!(crate::CommitmentIndex::<Runtime>::get(bob_native_note), Some(1));
+
+		let alice_leg = crate::SwapLeg {
+			proof_envelope: envelope(
+				alice_note,
+				(alice_tweak, alice_secret),
+				amount,
+				PARA_A_TOKEN,
+				bob_randomness,
+			),
+			nullifier: PrivacyBridge::generate_nullifier(&alice_note, &alice_secret),
+			commitment: alice_note,
+			asset_id: PARA_A_TOKEN,
+			output_commitment: bob_note,
+		};
+		let bob_leg = crate::SwapLeg {
+			proof_envelope: envelope(
+				bob_native_note,
+				([0u8; 32], [8u8; 32]),
+				native_amount,
+				0,
+				crate::primitives::note_randomness::<SimpleHasher>(&alice_output_key, &[0u8; 32]),
+			),
+			nullifier: PrivacyBridge::generate_nullifier(&bob_native_note, &[8u8; 32]),
+			commitment: bob_native_note,
+			asset_id: 0,
+			output_commitment: PrivacyBridge::generate_commitment(
+				native_amount,
+				0,
+				&alice_output_key,
+			),
+		};
+		assert_ok!(PrivacyBridge::shielded_swap(
+			RuntimeOrigin::signed(ALICE),
+			alice_leg.clone(),
+			bob_leg.clone(),
+			1,
+		));
+
+		System::assert_last_event(
+			crate::Event::NotesSwapped {
+				nullifiers: [alice_leg.nullifier, bob_leg.nullifier],
+				output_commitments: [bob_note, bob_leg.output_commitment],
+				leaf_indices: [2, 3],
+				block_number: 1,
+			}
+			.into(),
+		);
+		assert!(crate::NullifierSet::<Runtime>::get(alice_leg.nullifier));
+		assert_eq!(crate::Commitments::<Runtime>::get(bob_note).unwrap().asset_id, PARA_A_TOKEN);
+		// Nothing left the pool
+		assert_eq!(crate::TotalShielded::<Runtime>::get(PARA_A_TOKEN), amount);
+		assert_eq!(crate::TotalShielded::<Runtime>::get(0), native_amount);
+	});
+
+	// 4. Bob proves his note: membership by a proving service, ownership on his device
+	let leaves = Bridge::execute_with(bridge_leaves);
+	assert_eq!(leaves.len(), 4);
+	let (membership_pk, membership_vk) = membership_setup();
+	let (ownership_pk, ownership_vk) = ownership_setup();
+
+	let request = MembershipRequest::new(2, bob_note, link_blinding);
+	let response = serve_membership_request(membership_pk, &leaves, &request).unwrap();
+	verify_membership_response(membership_vk, &request, &response).unwrap();
+	let ownership_proof = zksnark::generate_ownership_proof::<SimpleHasher>(
+		ownership_pk,
+		bob_nullifier.as_bytes().to_vec(),
+		response.link_tag.as_bytes().to_vec(),
+		amount,
+		PARA_A_TOKEN,
+		[0u8; 32],
+		bob_secret,
+		link_blinding,
+	)
+	.unwrap();
+	assert!(zksnark::verify_composed_proof(
+		membership_vk,
+		ownership_vk,
+		&response.proof,
+		&ownership_proof,
+		response.merkle_root.as_bytes(),
+		bob_nullifier.as_bytes(),
+		response.link_tag.as_bytes(),
+	)
+	.unwrap());
+
+	// 5. A relayer submits Bob's withdrawal to ParaB
+	Bridge::execute_with(|| {
+		use parachain::{Assets, Balances, PrivacyBridge, Runtime, RuntimeOrigin, System, TREASURY};
+
+		let bob: Location = AccountId32 { network: None, id: BOB.into() }.into();
+		let recipient_hash = crate::primitives::recipient_hash(&bob);
+		let (nullifier, merkle_root, proof) =
+			prove_withdrawal(amount, [0u8; 32], bob_secret, recipient_hash);
+		assert_eq!(nullifier, bob_nullifier);
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(relayer.clone()),
+			bob_nullifier,
+			bob_note,
+			merkle_root,
+			PARA_A_TOKEN,
+			amount,
+			sibling(PARA_B_ID),
+			bob,
+			WITHDRAWAL_FEE,
+			proof,
+		));
+
+		assert!(crate::NullifierSet::<Runtime>::get(bob_nullifier));
+		assert_eq!(crate::TotalShielded::<Runtime>::get(PARA_A_TOKEN), 0);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &PrivacyBridge::account_id()), 0);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &TREASURY), WITHDRAWAL_FEE);
+		// The relayer only signs: it neither pays nor receives anything
+		assert_eq!(Balances::free_balance(&relayer), 0);
+		assert_eq!(Assets::balance(PARA_A_TOKEN, &relayer), 0);
+
+		System::assert_has_event(
+			crate::Event::AssetUnshielded {
+				nullifier: bob_nullifier,
+				asset_id: PARA_A_TOKEN,
+				block_number: 1,
+				receipt_id: crate::Pallet::<Runtime>::withdrawal_receipt_id(bob_nullifier),
+			}
+			.into(),
+		);
+		// The outbound message is bound for ParaB, carrying the amount less the fee
+		assert!(System::events().iter().any(|record| matches!(
+			&record.event,
+			parachain::RuntimeEvent::PrivacyBridge(crate::Event::WithdrawalExported {
+				nullifier,
+				asset_id: PARA_A_TOKEN,
+				amount: sent,
+				fee: WITHDRAWAL_FEE,
+				destination,
+				..
+			}) if *nullifier == bob_nullifier
+				&& *sent == amount - WITHDRAWAL_FEE
+				&& *destination == VersionedDestination::from(sibling(PARA_B_ID))
+		)));
+	});
+
+	// 6. The reserve moved the backing to ParaB, where Bob was paid
+	ParaA::execute_with(|| {
+		assert_eq!(parachain::Balances::free_balance(&sibling_account(BRIDGE_ID)), WITHDRAWAL_FEE);
+		assert_eq!(
+			parachain::Balances::free_balance(&sibling_account(PARA_B_ID)),
+			amount - WITHDRAWAL_FEE
+		);
+	});
+	ParaB::execute_with(|| {
+		assert_eq!(parachain::Assets::balance(PARA_A_TOKEN, &BOB), amount - WITHDRAWAL_FEE);
+		assert_eq!(parachain::Assets::balance(PARA_A_TOKEN, &relayer), 0);
+	});
+}
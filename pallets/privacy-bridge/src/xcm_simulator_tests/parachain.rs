@@ -0,0 +1,328 @@
+//! Parachain runtime of the XCM simulator tests
+//!
+//! Every parachain of the mock network runs this runtime. Foreign assets are held in
+//! `Assets` under the local IDs the privacy bridge registry assigns them.
+
+use crate::bridge_adapter::{
+	ExportError, MessageId, ReserveRouting, XcmExporter, XcmReceiptSender,
+};
+use crate::xcm_config::{
+	AllowShieldDeposits, RegisteredAssetIds, RegisteredAssets, ShieldDepositMaxWeight,
+	ShieldDepositTransactor, ShieldTrap,
+};
+use frame::{
+	deps::{
+		frame_support::{
+			traits::{AsEnsureOriginWithArg, ConstBool, ConstU128, ConstU32, Everything, Nothing},
+			PalletId,
+		},
+		frame_system::{EnsureRoot, EnsureSigned},
+	},
+	prelude::*,
+	runtime::prelude::*,
+	testing_prelude::*,
+};
+use pallet_xcm::{EnsureXcm, XcmPassthrough};
+use polkadot_parachain_primitives::primitives::Sibling;
+use sp_runtime::traits::IdentityLookup;
+use staging_xcm::latest::prelude::*;
+use staging_xcm_builder::{
+	AccountId32Aliases, AllowTopLevelPaidExecutionFrom, ConvertedConcreteId, EnsureXcmOrigin,
+	FixedRateOfFungible, FixedWeightBounds, FrameTransactionalProcessor, FungibleAdapter,
+	FungiblesAdapter, IsConcrete, NativeAsset, NoChecking, ParentIsPreset,
+	SiblingParachainConvertsVia, SignedAccountId32AsNative, SignedToAccountId32,
+	SovereignSignedViaLocation, TakeWeightCredit, TrailingSetTopicAsId,
+};
+use staging_xcm_executor::{traits::JustTry, XcmExecutor};
+use xcm_simulator::mock_message_queue;
+
+pub type AccountId = sp_runtime::AccountId32;
+pub type Balance = u128;
+
+#[frame_construct_runtime]
+mod parachain_runtime {
+	#[runtime::runtime]
+	#[runtime::derive(
+		RuntimeCall,
+		RuntimeEvent,
+		RuntimeError,
+		RuntimeOrigin,
+		RuntimeFreezeReason,
+		RuntimeHoldReason,
+		RuntimeSlashReason,
+		RuntimeLockId,
+		RuntimeTask
+	)]
+	pub struct Runtime;
+
+	#[runtime::pallet_index(0)]
+	pub type System = frame_system;
+	#[runtime::pallet_index(1)]
+	pub type Balances = pallet_balances;
+	#[runtime::pallet_index(2)]
+	pub type Assets = pallet_assets;
+	#[runtime::pallet_index(3)]
+	pub type MsgQueue = mock_message_queue;
+	#[runtime::pallet_index(4)]
+	pub type PolkadotXcm = pallet_xcm;
+	#[runtime::pallet_index(5)]
+	pub type PrivacyBridge = crate;
+}
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Runtime {
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<AccountId>;
+	type Block = MockBlock<Runtime>;
+	type AccountData = pallet_balances::AccountData<Balance>;
+}
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
+impl pallet_balances::Config for Runtime {
+	type Balance = Balance;
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+}
+
+#[derive_impl(pallet_assets::config_preludes::TestDefaultConfig)]
+impl pallet_assets::Config for Runtime {
+	type Balance = Balance;
+	type Currency = Balances;
+	type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<AccountId>>;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type Freezer = ();
+}
+
+parameter_types! {
+	pub const RelayNetwork: Option<NetworkId> = None;
+	pub UniversalLocation: InteriorLocation = Parachain(MsgQueue::parachain_id().into()).into();
+	pub UnitWeightCost: Weight = Weight::from_parts(1, 1);
+	pub const MaxInstructions: u32 = 100;
+	pub const MaxAssetsIntoHolding: u32 = 64;
+	pub HereLocation: Location = Location::here();
+	pub CheckingAccount: AccountId = PolkadotXcm::check_account();
+	/// Execution is paid in the chain's own token...
+	pub NativePerSecondPerByte: (AssetId, u128, u128) = (AssetId(Location::here()), 1, 1);
+	/// ...or in the token of parachain 1000, the token moved around in the tests
+	pub ParaATokenPerSecondPerByte: (AssetId, u128, u128) =
+		(AssetId(Location::new(1, [Parachain(super::PARA_A_ID)])), 1, 1);
+	/// Where shield deposits are addressed to (the privacy bridge pallet)
+	pub ShieldLocation: Location = Location::new(0, [PalletInstance(5)]);
+	/// Shielded assets are credited to the pool account
+	pub PoolLocation: Location =
+		AccountId32 { network: None, id: PrivacyBridge::account_id().into() }.into();
+}
+
+pub type LocationToAccountId = (
+	ParentIsPreset<AccountId>,
+	SiblingParachainConvertsVia<Sibling, AccountId>,
+	AccountId32Aliases<RelayNetwork, AccountId>,
+);
+
+/// The chain's own token, held in `Balances`
+pub type LocalAssetTransactor =
+	FungibleAdapter<Balances, IsConcrete<HereLocation>, LocationToAccountId, AccountId, ()>;
+
+/// Assets registered with the privacy bridge, held in `Assets` under their local IDs
+pub type RegisteredAssetsTransactor = FungiblesAdapter<
+	Assets,
+	ConvertedConcreteId<u32, Balance, RegisteredAssetIds<Runtime>, JustTry>,
+	LocationToAccountId,
+	AccountId,
+	NoChecking,
+	CheckingAccount,
+>;
+
+pub type AssetTransactor = ShieldDepositTransactor<
+	Runtime,
+	(LocalAssetTransactor, RegisteredAssetsTransactor),
+	ShieldLocation,
+	PoolLocation,
+>;
+
+pub type XcmOriginToCallOrigin = (
+	SovereignSignedViaLocation<LocationToAccountId, RuntimeOrigin>,
+	SignedAccountId32AsNative<RelayNetwork, RuntimeOrigin>,
+	XcmPassthrough<RuntimeOrigin>,
+);
+
+pub type Barrier = TrailingSetTopicAsId<(
+	TakeWeightCredit,
+	AllowShieldDeposits<RegisteredAssets<Runtime>, ShieldLocation, ShieldDepositMaxWeight>,
+	AllowTopLevelPaidExecutionFrom<Everything>,
+)>;
+
+pub type XcmRouter = super::ParachainXcmRouter<MsgQueue>;
+
+pub struct XcmConfig;
+impl staging_xcm_executor::Config for XcmConfig {
+	type RuntimeCall = RuntimeCall;
+	type XcmSender = XcmRouter;
+	type AssetTransactor = AssetTransactor;
+	type OriginConverter = XcmOriginToCallOrigin;
+	type IsReserve = NativeAsset;
+	type IsTeleporter = ();
+	type UniversalLocation = UniversalLocation;
+	type Barrier = Barrier;
+	type Weigher = FixedWeightBounds<UnitWeightCost, RuntimeCall, MaxInstructions>;
+	type Trader = (
+		FixedRateOfFungible<NativePerSecondPerByte, ()>,
+		FixedRateOfFungible<ParaATokenPerSecondPerByte, ()>,
+	);
+	type ResponseHandler = PolkadotXcm;
+	type AssetTrap = ShieldTrap<Runtime, PolkadotXcm>;
+	type AssetClaims = PolkadotXcm;
+	type SubscriptionService = PolkadotXcm;
+	type PalletInstancesInfo = ();
+	type MaxAssetsIntoHolding = MaxAssetsIntoHolding;
+	type AssetLocker = ();
+	type AssetExchanger = ();
+	type FeeManager = ();
+	type MessageExporter = ();
+	type UniversalAliases = Nothing;
+	type CallDispatcher = RuntimeCall;
+	type SafeCallFilter = Everything;
+	type Aliasers = Nothing;
+	type TransactionalProcessor = FrameTransactionalProcessor;
+	type HrmpNewChannelOpenRequestHandler = ();
+	type HrmpChannelAcceptedHandler = ();
+	type HrmpChannelClosingHandler = ();
+	type XcmRecorder = PolkadotXcm;
+}
+
+impl mock_message_queue::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type XcmExecutor = XcmExecutor<XcmConfig>;
+}
+
+pub type LocalOriginToLocation = SignedToAccountId32<RuntimeOrigin, AccountId, RelayNetwork>;
+
+impl pallet_xcm::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type SendXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, LocalOriginToLocation>;
+	type XcmRouter = XcmRouter;
+	type ExecuteXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, LocalOriginToLocation>;
+	type XcmExecuteFilter = Everything;
+	type XcmExecutor = XcmExecutor<XcmConfig>;
+	type XcmTeleportFilter = Nothing;
+	type XcmReserveTransferFilter = Everything;
+	type Weigher = FixedWeightBounds<UnitWeightCost, RuntimeCall, MaxInstructions>;
+	type UniversalLocation = UniversalLocation;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
+	type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+	type Currency = Balances;
+	type CurrencyMatcher = ();
+	type TrustedLockers = ();
+	type SovereignAccountOf = LocationToAccountId;
+	type MaxLockers = ConstU32<8>;
+	type WeightInfo = pallet_xcm::TestWeightInfo;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type MaxRemoteLockConsumers = ConstU32<0>;
+	type RemoteLockConsumerIdentifier = ();
+}
+
+parameter_types! {
+	pub const PrivacyBridgePalletId: PalletId = PalletId(*b"py/cloak");
+	/// Execution fee budgeted per hop of a withdrawal routed via its reserve
+	pub const HopFee: u128 = 1_000;
+	pub const TreasuryAccount: AccountId = TREASURY;
+	pub const MaxVerificationWeightPerBlock: Weight = Weight::MAX;
+}
+
+/// Routes withdrawals of remotely reserved assets through their reserve
+///
+/// Mirrors the runtime's routing: the local program is executed by the pool account.
+pub struct SimulatorReserveRouting;
+
+impl ReserveRouting for SimulatorReserveRouting {
+	fn reserve_of(asset: &AssetId) -> Option<Location> {
+		PrivacyBridge::reserve_location(asset)
+	}
+
+	fn hop_fee(_asset: &AssetId) -> u128 {
+		HopFee::get()
+	}
+
+	fn universal_location() -> InteriorLocation {
+		UniversalLocation::get()
+	}
+
+	fn execute(program: Xcm<()>) -> Result<MessageId, ExportError> {
+		let mut message_id = program.using_encoded(sp_io::hashing::blake2_256);
+		let max_weight = UnitWeightCost::get().saturating_mul(MaxInstructions::get() as u64);
+
+		XcmExecutor::<XcmConfig>::prepare_and_execute(
+			PoolLocation::get(),
+			program.into(),
+			&mut message_id,
+			max_weight,
+			max_weight,
+		)
+		.ensure_complete()
+		.map_err(|_| ExportError::SendFailed)?;
+
+		Ok(message_id)
+	}
+}
+
+/// Account receiving withdrawal fees
+pub const TREASURY: AccountId = AccountId::new([9u8; 32]);
+
+impl crate::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type Hasher = crate::hasher::SimpleHasher;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type TreasuryAccount = TreasuryAccount;
+	type DecoyValue = ConstU128<10>;
+	type MaxDecoysPerBlock = ConstU32<0>;
+	type Currency = Balances;
+	type Fungibles = Assets;
+	type PalletId = PrivacyBridgePalletId;
+	type ClaimDustPayouts = ConstBool<true>;
+	type Exporter = XcmExporter<XcmRouter, SimulatorReserveRouting>;
+	type TrapOrigin = EnsureXcm<Everything>;
+	type MaxDepositsPerMessage = ConstU32<32>;
+	type UniversalLocation = UniversalLocation;
+	type ReceiptSender = XcmReceiptSender<XcmRouter>;
+	type ReserveBackingAlertThreshold = ConstU128<{ u128::MAX }>;
+	type SolvencyCheckInterval = ConstU64<0>;
+	type MaxSolvencyChecks = ConstU32<16>;
+	type BatchTreeInsertion = ConstBool<false>;
+	type AnnouncementTtl = ConstU64<100>;
+	type MaxSplit = ConstU32<16>;
+	type RootHistorySize = ConstU32<100>;
+	type MaxProofAge = ();
+	type SponsorThreshold = ConstU128<0>;
+	type SponsoredFee = ConstU128<0>;
+	type SponsorPeriod = ConstU64<1>;
+	type MaxSponsoredPerAccount = ConstU32<0>;
+	type MaxSponsoredPerPeriod = ConstU32<0>;
+	type Metrics = ();
+	type MaxBatch = ConstU32<16>;
+	type MaxBatchLeavesPerBlock = ConstU32<64>;
+	type MaxBackupLen = ConstU32<256>;
+	type BackupDepositPerByte = ConstU128<1>;
+	type MaxVerificationWeightPerBlock = MaxVerificationWeightPerBlock;
+	type Randomness = crate::randomness::ParentHashRandomness<Runtime>;
+	type MaxAlertTags = ConstU32<16>;
+	type ScoreWindow = ConstU32<100>;
+	type MinAnonymityScore = ConstU32<0>;
+	type RandomnessReuseWindow = ConstU32<0>;
+	type MaxProvingJobLen = ConstU32<1_024>;
+	type MaxProvingJobs = ConstU32<64>;
+	type ProvingJobTtl = ConstU32<100>;
+	type ProvingClaimTtl = ConstU32<20>;
+	type ProverBond = ConstU128<1_000>;
+	type MinProvingBounty = ConstU128<1>;
+	type WindDownPeriod = ConstU32<100>;
+	type MaxPayouts = ConstU32<8>;
+	type CoSignerOrigin = EnsureRoot<AccountId>;
+	type AdminApprovalTtl = ConstU64<100>;
+	type ProofVerifier = crate::zksnark::Groth16Verifier;
+	type AllowSignedXcmDeposits = ConstBool<false>;
+	type UnsignedRelayFee = ();
+	type MaxProofSize = ConstU32<{ crate::zksnark::PROOF_LEN as u32 }>;
+}
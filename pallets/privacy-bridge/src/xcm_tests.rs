@@ -5,8 +5,35 @@
 use crate::{mock::*, Error, Event};
 use frame::testing_prelude::*;
 use sp_core::H256;
-use staging_xcm::v5::{AssetId, Location};
-use crate::xcm_config::RegisteredAsset;
+use staging_xcm::v5::{
+	AssetId, Assets, Instruction, Instruction::*,
+	Junction::{AccountId32, AccountKey20, GlobalConsensus, PalletInstance, Parachain},
+	Location, NetworkId, OriginKind, SendError, SendResult, SendXcm, WeightLimit, WildAsset, Xcm,
+	XcmContext, XcmHash,
+};
+use staging_xcm_executor::traits::{DropAssets, Properties, ShouldExecute, TransactAsset};
+use frame::deps::frame_support::{
+	parameter_types,
+	traits::{fungibles::Mutate, Contains, ProcessMessageError},
+};
+use crate::xcm_config::{
+	beneficiary_from_encoded, construct_asset, AccountFormat, AllowShieldDeposits, CustodyMode,
+	DepositReceipt, RegisteredAsset, RegisteredAssetIds, RegisteredAssets, ShieldDepositMaxWeight,
+	ShieldDepositTransactor, ShieldTrap,
+};
+use sp_runtime::traits::MaybeEquivalence;
+use crate::merkle_tree::TREE_DEPTH;
+use crate::{DryRunResult, FeeConfig, RejectionReason, TrapResolution};
+use crate::bridge_adapter::{
+	ExportWithdrawal, SendDepositReceipt, VersionedDestination, XcmExporter, XcmReceiptSender,
+};
+use crate::extension::{CheckDuplicateCommitment, DUPLICATE_XCM_COMMITMENT};
+use frame::deps::frame_support::dispatch::DispatchInfo;
+use sp_runtime::traits::DispatchTransaction;
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource};
+
+/// Local ID of the first registered asset (0 is reserved for the native token)
+const FIRST_ASSET: u32 = 1;
 
 #[test]
 fn test_register_asset() {
@@ -20,16 +47,18 @@ fn test_register_asset() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			min_deposit,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Verify asset is registered
 		let registered = crate::AssetRegistry::<Test>::get(&asset_id).unwrap();
-		assert_eq!(registered.local_id, 0); // First asset gets ID 0
+		assert_eq!(registered.local_id, FIRST_ASSET); // ID 0 is the native token
 		assert_eq!(registered.min_deposit, min_deposit);
 		assert!(registered.is_active);
 
 		// Verify next asset ID incremented
-		assert_eq!(crate::NextAssetId::<Test>::get(), 1);
+		assert_eq!(crate::NextAssetId::<Test>::get(), 2);
 	});
 }
 
@@ -42,17 +71,64 @@ fn test_register_multiple_assets() {
 			RuntimeOrigin::root(),
 			asset1.clone(),
 			100,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Register second asset
-		let asset2 = AssetId(Location::new(1, [])); // Parachain 1
+		let asset2 = AssetId(Location::new(1, [Parachain(1000)])); // Sibling parachain 1000
 		assert_ok!(PrivacyBridge::register_asset(
 			RuntimeOrigin::root(),
 			asset2.clone(),
 			200,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Verify counter incremented (both assets registered)
+		assert_eq!(crate::NextAssetId::<Test>::get(), 3);
+	});
+}
+
+#[test]
+fn test_register_asset_twice_rejected() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			100,
+			CustodyMode::Pot,
+			0,
+		));
+		System::assert_last_event(
+			Event::AssetRegistered {
+				xcm_asset_id: asset_id.clone(),
+				local_id: FIRST_ASSET,
+				min_deposit: 100,
+			}
+			.into(),
+		);
+
+		// Neither the same location nor an equivalent form registers it again
+		let relay_global = AssetId(Location::new(2, [GlobalConsensus(NetworkId::Polkadot)]));
+		for duplicate in [asset_id.clone(), relay_global] {
+			assert_noop!(
+				PrivacyBridge::register_asset(
+					RuntimeOrigin::root(),
+					duplicate,
+					200,
+					CustodyMode::Pot,
+					0,
+				),
+				Error::<Test>::AssetAlreadyRegistered
+			);
+		}
+
+		// The local ID still maps back to the asset, and no ID was burnt
+		assert_eq!(crate::LocalAssetIndex::<Test>::get(FIRST_ASSET), Some(asset_id.clone()));
+		let registered = crate::Pallet::<Test>::registered_asset_by_local(FIRST_ASSET);
+		assert_eq!(registered.map(|asset| asset.min_deposit), Some(100));
 		assert_eq!(crate::NextAssetId::<Test>::get(), 2);
 	});
 }
@@ -67,6 +143,8 @@ fn test_cross_chain_deposit() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			min_deposit,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Simulate cross-chain deposit from parachain
@@ -84,9 +162,9 @@ fn test_cross_chain_deposit() {
 		));
 
 		// Verify commitment was created
-		let commitment = crate::xcm_config::xcm_commitment_data(
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
 			amount,
-			0, // local_id
+			FIRST_ASSET, // local_id
 			&randomness,
 			&origin_location,
 		);
@@ -110,6 +188,8 @@ fn test_cross_chain_deposit_fails_below_minimum() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			min_deposit,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Try to deposit below minimum
@@ -125,7 +205,7 @@ fn test_cross_chain_deposit_fails_below_minimum() {
 				origin_location,
 				randomness,
 			),
-			Error::<Test>::InvalidProof // Reused error
+			Error::<Test>::DepositBelowMinimum
 		);
 	});
 }
@@ -147,8 +227,90 @@ fn test_cross_chain_deposit_unregistered_asset() {
 				origin_location,
 				randomness,
 			),
-			Error::<Test>::InvalidProof // Asset not registered
+			Error::<Test>::AssetNotRegistered
+		);
+	});
+}
+
+#[test]
+fn test_cross_chain_deposit_inactive_asset() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		assert_ok!(PrivacyBridge::set_asset_status(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			false,
+		));
+
+		assert_noop!(
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id,
+				1000,
+				Location::parent(),
+				[42u8; 32],
+			),
+			Error::<Test>::AssetInactive
+		);
+	});
+}
+
+#[test]
+fn test_cross_chain_deposit_rejects_oversized_origin() {
+	new_test_ext().execute_with(|| {
+		use staging_xcm::v5::Junction::{GeneralIndex, GeneralKey};
+
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		let deposit = |origin_location: Location| {
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				1000,
+				origin_location,
+				[42u8; 32],
+			)
+		};
+
+		// Eight junctions deep
+		let deep = Location::new(
+			1,
+			[
+				Parachain(2000),
+				PalletInstance(50),
+				GeneralIndex(1),
+				GeneralIndex(2),
+				GeneralIndex(3),
+				GeneralIndex(4),
+				GeneralIndex(5),
+				GeneralIndex(6),
+			],
+		);
+		assert_noop!(deposit(deep.clone()), Error::<Test>::InvalidOriginLocation);
+		assert_noop!(
+			deposit(Location::new(1, [Parachain(2000), GeneralKey { length: 255, data: [0; 32] }])),
+			Error::<Test>::InvalidOriginLocation
+		);
+		assert_noop!(
+			deposit(Location::new(3, [Parachain(2000)])),
+			Error::<Test>::InvalidOriginLocation
 		);
+		assert_noop!(
+			PrivacyBridge::shield_from_xcm(&asset_id, 1000, &[7u8; 32], &deep),
+			Error::<Test>::InvalidOriginLocation
+		);
+
+		// A sibling is fine
+		let sibling = Location::new(1, [Parachain(2000)]);
+		assert_ok!(deposit(sibling.clone()));
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			1000,
+			FIRST_ASSET,
+			&[42u8; 32],
+			&sibling,
+		);
+		assert_eq!(crate::XcmDepositOrigins::<Test>::get(commitment), Some(sibling));
 	});
 }
 
@@ -161,6 +323,8 @@ fn test_cross_chain_withdraw() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			100,
+			CustodyMode::Pot,
+			0,
 		));
 
 		let amount = 1000u128;
@@ -176,9 +340,9 @@ fn test_cross_chain_withdraw() {
 		));
 
 		// Generate nullifier
-		let commitment = crate::xcm_config::xcm_commitment_data(
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
 			amount,
-			0,
+			FIRST_ASSET,
 			&randomness,
 			&origin_location,
 		);
@@ -192,16 +356,139 @@ fn test_cross_chain_withdraw() {
 		assert_ok!(PrivacyBridge::withdraw_to_parachain(
 			RuntimeOrigin::signed(1),
 			nullifier,
-			0, // asset_id
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			// asset_id
 			amount,
 			destination,
 			beneficiary,
+			0,
+			mock_proof(),
 		));
 
 		// Verify nullifier was marked as used
 		assert!(crate::NullifierSet::<Test>::get(&nullifier));
 
-		// Event is emitted (assertion skipped for MVP)
+		// The withdrawal went out through the exporter
+		assert_eq!(
+			ExportedWithdrawals::get(),
+			vec![(
+				AssetId(Location::parent()),
+				amount,
+				VersionedDestination::V5(Location::new(1, [])),
+				Location::new(0, []).encode(),
+			)]
+		);
+	});
+}
+
+#[test]
+fn test_beneficiary_from_encoded_follows_destination_format() {
+	new_test_ext().execute_with(|| {
+		let evm_chain = Location::new(1, [Parachain(2004)]);
+		assert_ok!(PrivacyBridge::set_destination_account_format(
+			RuntimeOrigin::root(),
+			evm_chain.clone(),
+			Some(AccountFormat::AccountKey20),
+		));
+		System::assert_last_event(
+			Event::DestinationAccountFormatSet {
+				destination: evm_chain.clone(),
+				format: Some(AccountFormat::AccountKey20),
+			}
+			.into(),
+		);
+
+		// Destinations without a format take 32-byte accounts
+		let substrate_chain = Location::new(1, [Parachain(2000)]);
+		assert_eq!(
+			beneficiary_from_encoded::<Test>(&substrate_chain, &[7u8; 32]).ok(),
+			Some(Location::new(0, [AccountId32 { network: None, id: [7u8; 32] }]))
+		);
+		assert_eq!(
+			beneficiary_from_encoded::<Test>(&evm_chain, &[7u8; 20]).ok(),
+			Some(Location::new(0, [AccountKey20 { network: None, key: [7u8; 20] }]))
+		);
+
+		// Each format takes exactly its own length
+		assert_eq!(
+			beneficiary_from_encoded::<Test>(&substrate_chain, &[7u8; 20]).map_err(DispatchError::from),
+			Err(Error::<Test>::InvalidBeneficiaryLength.into())
+		);
+		assert_eq!(
+			beneficiary_from_encoded::<Test>(&evm_chain, &[7u8; 32]).map_err(DispatchError::from),
+			Err(Error::<Test>::InvalidBeneficiaryLength.into())
+		);
+	});
+}
+
+#[test]
+fn test_withdraw_to_parachain_encoded() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100, 100]);
+		let evm_chain = Location::new(1, [Parachain(2004)]);
+		assert_ok!(PrivacyBridge::sync_open_channels(
+			RuntimeOrigin::root(),
+			vec![2000, 2004].try_into().unwrap()
+		));
+		assert_ok!(PrivacyBridge::set_destination_account_format(
+			RuntimeOrigin::root(),
+			evm_chain.clone(),
+			Some(AccountFormat::AccountKey20),
+		));
+
+		// A 32-byte account is not an EVM address
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain_encoded(
+				RuntimeOrigin::signed(2),
+				notes[0].0,
+				notes[0].1,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				100,
+				evm_chain.clone(),
+				vec![7u8; 32].try_into().unwrap(),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::InvalidBeneficiaryLength
+		);
+
+		assert_ok!(PrivacyBridge::withdraw_to_parachain_encoded(
+			RuntimeOrigin::signed(2),
+			notes[0].0,
+			notes[0].1,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			100,
+			evm_chain.clone(),
+			vec![7u8; 20].try_into().unwrap(),
+			0,
+			mock_proof(),
+		));
+		assert_ok!(PrivacyBridge::withdraw_to_parachain_encoded(
+			RuntimeOrigin::signed(2),
+			notes[1].0,
+			notes[1].1,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			100,
+			Location::new(1, [Parachain(2000)]),
+			vec![7u8; 32].try_into().unwrap(),
+			0,
+			mock_proof(),
+		));
+
+		let beneficiaries: Vec<_> =
+			ExportedWithdrawals::get().into_iter().map(|(_, _, _, beneficiary)| beneficiary).collect();
+		assert_eq!(
+			beneficiaries,
+			vec![
+				Location::new(0, [AccountKey20 { network: None, key: [7u8; 20] }]).encode(),
+				Location::new(0, [AccountId32 { network: None, id: [7u8; 32] }]).encode(),
+			]
+		);
 	});
 }
 
@@ -214,6 +501,8 @@ fn test_cross_chain_withdraw_prevents_double_spend() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			100,
+			CustodyMode::Pot,
+			0,
 		));
 
 		let amount = 1000u128;
@@ -228,9 +517,9 @@ fn test_cross_chain_withdraw_prevents_double_spend() {
 			randomness,
 		));
 
-		let commitment = crate::xcm_config::xcm_commitment_data(
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
 			amount,
-			0,
+			FIRST_ASSET,
 			&randomness,
 			&origin_location,
 		);
@@ -244,10 +533,14 @@ fn test_cross_chain_withdraw_prevents_double_spend() {
 		assert_ok!(PrivacyBridge::withdraw_to_parachain(
 			RuntimeOrigin::signed(1),
 			nullifier,
-			0,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
 			amount,
 			destination.clone(),
 			beneficiary.clone(),
+			0,
+			mock_proof(),
 		));
 
 		// Second withdraw with same nullifier fails
@@ -255,10 +548,14 @@ fn test_cross_chain_withdraw_prevents_double_spend() {
 			PrivacyBridge::withdraw_to_parachain(
 				RuntimeOrigin::signed(1),
 				nullifier,
-				0,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
 				amount,
 				destination,
 				beneficiary,
+				0,
+				mock_proof(),
 			),
 			Error::<Test>::NullifierAlreadyUsed
 		);
@@ -274,6 +571,8 @@ fn test_full_cross_chain_privacy_flow() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			100,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// 2. User deposits from parachain A
@@ -290,9 +589,9 @@ fn test_full_cross_chain_privacy_flow() {
 		));
 
 		// 3. Commitment created and hidden
-		let commitment = crate::xcm_config::xcm_commitment_data(
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
 			amount,
-			0,
+			FIRST_ASSET,
 			&randomness,
 			&origin_a,
 		);
@@ -307,12 +606,17 @@ fn test_full_cross_chain_privacy_flow() {
 		let beneficiary = Location::new(0, []);
 
 		assert_ok!(PrivacyBridge::withdraw_to_parachain(
-			RuntimeOrigin::signed(2), // Different user
+			RuntimeOrigin::signed(2),
+			// Different user
 			nullifier,
-			0,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
 			amount,
 			destination_b,
 			beneficiary,
+			0,
+			mock_proof(),
 		));
 
 		// 6. Verify privacy: nullifier used, can't trace back
@@ -332,6 +636,8 @@ fn test_multiple_cross_chain_deposits_create_anonymity_set() {
 			RuntimeOrigin::root(),
 			asset_id.clone(),
 			100,
+			CustodyMode::Pot,
+			0,
 		));
 
 		// Multiple users deposit (creating anonymity set)
@@ -356,3 +662,2123 @@ fn test_multiple_cross_chain_deposits_create_anonymity_set() {
 		// This creates the anonymity set
 	});
 }
+
+/// Register the relay asset and deposit a note for it, returning (commitment, nullifier)
+fn shield_relay_asset(amount: u128) -> (H256, H256) {
+	let asset_id = AssetId(Location::parent());
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		asset_id.clone(),
+		0,
+		CustodyMode::Pot,
+		0,
+	));
+	assert_ok!(PrivacyBridge::deposit_from_xcm(
+		RuntimeOrigin::signed(1),
+		asset_id,
+		amount,
+		Location::parent(),
+		[42u8; 32],
+	));
+
+	let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(amount, FIRST_ASSET, &[42u8; 32], &Location::parent());
+	let nullifier = crate::Pallet::<Test>::generate_nullifier(&commitment, &[99u8; 32]);
+	(commitment, nullifier)
+}
+
+#[test]
+fn test_withdraw_to_remote_non_xcm_destination() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+
+		// An Ethereum address as beneficiary
+		let destination = VersionedDestination::V5(Location::new(
+			2,
+			[GlobalConsensus(NetworkId::Ethereum { chain_id: 1 })],
+		));
+		let beneficiary: BoundedVec<u8, _> = vec![0xEEu8; 20].try_into().unwrap();
+
+		assert_ok!(PrivacyBridge::withdraw_to_remote(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			destination.clone(),
+			beneficiary.clone(),
+			0,
+			mock_proof(),
+		));
+
+		let exported = ExportedWithdrawals::get();
+		assert_eq!(
+			exported,
+			vec![(AssetId(Location::parent()), 1000, destination.clone(), beneficiary.to_vec())]
+		);
+		let message_id = sp_io::hashing::blake2_256(&exported[0].encode());
+		System::assert_last_event(Event::WithdrawalExported {
+			nullifier,
+			asset_id: FIRST_ASSET,
+			amount: 1000,
+			fee: 0,
+			destination,
+			message_id,
+			receipt_id: crate::Pallet::<Test>::withdrawal_receipt_id(nullifier),
+		}.into());
+	});
+}
+
+#[test]
+fn test_failed_export_keeps_note_unspent() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		ExportFails::set(true);
+
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				Location::new(1, [Parachain(2000)]),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::ExportFailed
+		);
+		assert!(!crate::NullifierSet::<Test>::get(&nullifier));
+
+		// Retrying once the bridge recovers succeeds
+		ExportFails::set(false);
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			Location::new(1, [Parachain(2000)]),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+	});
+}
+
+#[test]
+fn test_deactivated_asset_refuses_deposits_but_pays_out() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let (commitment, nullifier) = shield_relay_asset(1000);
+
+		assert_noop!(
+			PrivacyBridge::set_asset_status(RuntimeOrigin::signed(1), asset_id.clone(), false),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(PrivacyBridge::set_asset_status(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			false,
+		));
+		System::assert_last_event(
+			Event::AssetStatusChanged { asset_id: asset_id.clone(), is_active: false }.into(),
+		);
+		let deposit = |randomness: [u8; 32]| {
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				1000,
+				Location::parent(),
+				randomness,
+			)
+		};
+		assert_noop!(deposit([7u8; 32]), Error::<Test>::AssetInactive);
+
+		// The note shielded before still pays out
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			Location::new(1, [Parachain(2000)]),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+
+		assert_ok!(PrivacyBridge::set_asset_status(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			true,
+		));
+		assert_ok!(deposit([7u8; 32]));
+	});
+}
+
+#[test]
+fn test_update_asset_min_deposit() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_noop!(
+			PrivacyBridge::update_asset_min_deposit(RuntimeOrigin::root(), asset_id.clone(), 500),
+			Error::<Test>::AssetNotRegistered
+		);
+		register_location(Location::parent());
+
+		assert_ok!(PrivacyBridge::update_asset_min_deposit(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			500,
+		));
+		System::assert_last_event(
+			Event::AssetMinDepositUpdated { asset_id: asset_id.clone(), min_deposit: 500 }.into(),
+		);
+		assert_eq!(PrivacyBridge::registered_assets(&asset_id).unwrap().min_deposit, 500);
+		assert_noop!(
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id,
+				499,
+				Location::parent(),
+				[7u8; 32],
+			),
+			Error::<Test>::DepositBelowMinimum
+		);
+	});
+}
+
+#[test]
+fn test_denominations_restrict_deposits_to_their_pools() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let origin_location = Location::new(1, [Parachain(2000)]);
+		register_location(Location::parent());
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+		assert_ok!(Assets::mint_into(FIRST_ASSET, &1, 10_000));
+		let denominations = |amounts: Vec<u128>| BoundedVec::truncate_from(amounts);
+
+		for invalid in [vec![1000, 100], vec![100, 100], vec![0, 100]] {
+			assert_noop!(
+				PrivacyBridge::set_denominations(
+					RuntimeOrigin::root(),
+					asset_id.clone(),
+					denominations(invalid),
+				),
+				Error::<Test>::InvalidDenomination
+			);
+		}
+		assert_ok!(PrivacyBridge::set_denominations(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			denominations(vec![100, 1000]),
+		));
+		System::assert_last_event(
+			Event::DenominationsSet {
+				asset_id: asset_id.clone(),
+				denominations: denominations(vec![100, 1000]),
+			}
+			.into(),
+		);
+
+		let xcm_deposit = |amount: u128, seed: u8| {
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				amount,
+				origin_location.clone(),
+				[seed; 32],
+			)
+		};
+		assert_ok!(xcm_deposit(100, 1));
+		assert_noop!(xcm_deposit(150, 2), Error::<Test>::InvalidDenomination);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 100, FIRST_ASSET, [3u8; 32]));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 1000, FIRST_ASSET, [4u8; 32]));
+		assert_noop!(
+			PrivacyBridge::deposit(RuntimeOrigin::signed(1), 150, FIRST_ASSET, [5u8; 32]),
+			Error::<Test>::InvalidDenomination
+		);
+
+		// Each denomination is a tree of its own
+		let xcm_note = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			100,
+			FIRST_ASSET,
+			&[1u8; 32],
+			&origin_location,
+		);
+		let local_note = crate::Pallet::<Test>::generate_commitment(100, FIRST_ASSET, &[3u8; 32]);
+		assert_eq!(PrivacyBridge::denomination_tree(FIRST_ASSET, 100u128).leaves, 2);
+		assert_eq!(PrivacyBridge::denomination_tree(FIRST_ASSET, 1000u128).leaves, 1);
+		let pool_leaf =
+			|index: u32| crate::DenominationLeaves::<Test>::get((FIRST_ASSET, 100u128), index);
+		assert_eq!(pool_leaf(0), Some(xcm_note));
+		assert_eq!(pool_leaf(1), Some(local_note));
+		assert_eq!(PrivacyBridge::denomination_of(local_note), Some(100));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 3);
+
+		// Without denominations any amount is taken again, outside the pools
+		assert_ok!(PrivacyBridge::set_denominations(
+			RuntimeOrigin::root(),
+			asset_id,
+			denominations(vec![]),
+		));
+		assert_ok!(xcm_deposit(150, 2));
+		assert_eq!(PrivacyBridge::denomination_tree(FIRST_ASSET, 100u128).leaves, 2);
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 4);
+	});
+}
+
+#[test]
+fn test_withdraw_to_remote_requires_registered_asset() {
+	new_test_ext().execute_with(|| {
+		// A note of another asset, so the proof's root is known
+		let _ = shield_relay_asset(1000);
+		assert_noop!(
+			PrivacyBridge::withdraw_to_remote(
+				RuntimeOrigin::signed(2),
+				H256::from([1u8; 32]),
+				H256::from([2u8; 32]),
+				PrivacyBridge::merkle_root(),
+				7,
+				1000,
+				VersionedDestination::V5(Location::parent()),
+				Default::default(),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::AssetNotRegistered
+		);
+	});
+}
+
+type ShieldBarrier = AllowShieldDeposits<RegisteredAssets<Test>, ShieldLocation, ShieldDepositMaxWeight>;
+
+/// Run the shield barrier over `message` as the executor would
+fn check_barrier(mut message: Xcm<()>, max_weight: Weight) -> Result<(), ProcessMessageError> {
+	let mut properties = Properties { weight_credit: Weight::zero(), message_id: None };
+	ShieldBarrier::should_execute(
+		&Location::new(1, [Parachain(1000)]),
+		message.inner_mut(),
+		max_weight,
+		&mut properties,
+	)
+}
+
+/// A conforming shield deposit of `amount` of `asset_id`
+fn shield_message(asset_id: AssetId, amount: u128) -> Vec<Instruction<()>> {
+	let asset = construct_asset(asset_id, amount);
+	vec![
+		ReserveAssetDeposited(asset.clone().into()),
+		ClearOrigin,
+		BuyExecution { fees: asset, weight_limit: WeightLimit::Unlimited },
+		DepositAsset { assets: WildAsset::AllCounted(1).into(), beneficiary: ShieldLocation::get() },
+	]
+}
+
+#[test]
+fn test_barrier_admits_shield_deposit() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0,
+		));
+
+		let weight = Weight::from_parts(1_000_000, 1024);
+		assert_ok!(check_barrier(Xcm(shield_message(asset_id.clone(), 1000)), weight));
+
+		// ClearOrigin and BuyExecution are optional
+		let mut minimal = shield_message(asset_id, 1000);
+		minimal.drain(1..3);
+		assert_ok!(check_barrier(Xcm(minimal), weight));
+	});
+}
+
+#[test]
+fn test_barrier_rejects_broader_messages() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let weight = Weight::from_parts(1_000_000, 1024);
+
+		// Unregistered asset
+		assert_eq!(
+			check_barrier(Xcm(shield_message(asset_id.clone(), 1000)), weight),
+			Err(ProcessMessageError::Unsupported)
+		);
+
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0,
+		));
+
+		// Transact-carrying message
+		let mut with_transact = shield_message(asset_id.clone(), 1000);
+		with_transact.push(Transact {
+			origin_kind: OriginKind::SovereignAccount,
+			fallback_max_weight: None,
+			call: Vec::<u8>::new().into(),
+		});
+		assert_eq!(check_barrier(Xcm(with_transact), weight), Err(ProcessMessageError::BadFormat));
+
+		// Deposit to somewhere other than the shield location
+		let mut elsewhere = shield_message(asset_id.clone(), 1000);
+		elsewhere[3] = DepositAsset {
+			assets: WildAsset::AllCounted(1).into(),
+			beneficiary: Location::new(0, [Parachain(2000)]),
+		};
+		assert_eq!(check_barrier(Xcm(elsewhere), weight), Err(ProcessMessageError::BadFormat));
+
+		// Over the weight bound
+		assert_eq!(
+			check_barrier(Xcm(shield_message(asset_id, 1000)), ShieldDepositMaxWeight::get().saturating_mul(2)),
+			Err(ProcessMessageError::Overweight(ShieldDepositMaxWeight::get()))
+		);
+	});
+}
+
+parameter_types! {
+	/// Where `ShieldTransactor` credits shielded assets
+	pub PoolLocation: Location = Location::new(0, [PalletInstance(2)]);
+}
+
+/// Inner transactor accepting every deposit
+struct AcceptingTransactor;
+
+impl TransactAsset for AcceptingTransactor {
+	fn deposit_asset(
+		_what: &staging_xcm::v5::Asset,
+		_who: &Location,
+		_context: Option<&XcmContext>,
+	) -> staging_xcm::v5::Result {
+		Ok(())
+	}
+}
+
+type ShieldTransactor =
+	ShieldDepositTransactor<Test, AcceptingTransactor, ShieldLocation, PoolLocation>;
+
+#[test]
+fn test_shield_transactor_shields_executor_deposit() {
+	new_test_ext().execute_with(|| {
+		register_location(Location::parent());
+		let origin = Location::new(1, [Parachain(2000)]);
+		let randomness = [7u8; 32];
+		let context = XcmContext {
+			origin: Some(origin.clone()),
+			message_id: [1u8; 32],
+			topic: Some(randomness),
+		};
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			1000,
+			FIRST_ASSET,
+			&randomness,
+			&origin,
+		);
+
+		// The `DepositAsset` to the shield location, as the executor runs it
+		assert_ok!(ShieldTransactor::deposit_asset(
+			&construct_asset(AssetId(Location::parent()), 1000),
+			&ShieldLocation::get(),
+			Some(&context),
+		));
+
+		assert_eq!(crate::CommitmentIndex::<Test>::get(commitment), Some(0));
+		System::assert_last_event(
+			Event::AssetShielded {
+				commitment,
+				asset_id: FIRST_ASSET,
+				depositor: PrivacyBridge::account_id(),
+				block_number: 1,
+				leaf_index: 0,
+				merkle_root: PrivacyBridge::merkle_root(),
+			}
+			.into(),
+		);
+		assert_eq!(crate::TotalShielded::<Test>::get(FIRST_ASSET), 1000);
+		assert_eq!(crate::XcmDepositOrigins::<Test>::get(commitment), Some(origin));
+	});
+}
+
+#[test]
+fn test_shield_transactor_binds_origin_and_amount() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		let randomness = [7u8; 32];
+		let deposit = |origin: Option<Location>, amount: u128| {
+			let context = XcmContext { origin, message_id: [1u8; 32], topic: Some(randomness) };
+			ShieldTransactor::deposit_asset(
+				&construct_asset(asset_id.clone(), amount),
+				&ShieldLocation::get(),
+				Some(&context),
+			)
+		};
+		let note = |amount: u128, origin: &Location| {
+			crate::xcm_config::xcm_commitment_data::<MockHasher>(
+				amount,
+				FIRST_ASSET,
+				&randomness,
+				origin,
+			)
+		};
+		let sibling = Location::new(1, [Parachain(2000)]);
+		let other = Location::new(1, [Parachain(3000)]);
+
+		// A message delivering 500 cannot shield into the note for 1000
+		assert_ok!(deposit(Some(sibling.clone()), 500));
+		assert!(crate::CommitmentIndex::<Test>::contains_key(note(500, &sibling)));
+		assert!(!crate::CommitmentIndex::<Test>::contains_key(note(1000, &sibling)));
+
+		// The same topic from another chain makes that chain's own note
+		assert_ok!(deposit(Some(other.clone()), 500));
+		assert!(crate::CommitmentIndex::<Test>::contains_key(note(500, &other)));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 2);
+		assert_eq!(PrivacyBridge::unattributed_balances(&other, &asset_id), 0);
+
+		// With the origin cleared the deposit is the reserve's, and without one it fails
+		assert_eq!(
+			deposit(None, 500),
+			Err(staging_xcm::v5::Error::FailedToTransactAsset("shield deposit of unknown origin"))
+		);
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			Some(Location::parent()),
+		));
+		assert_ok!(deposit(None, 500));
+		assert!(crate::CommitmentIndex::<Test>::contains_key(note(500, &Location::parent())));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 3);
+	});
+}
+
+#[test]
+fn test_signed_xcm_deposit_can_be_disabled() {
+	new_test_ext().execute_with(|| {
+		register_location(Location::parent());
+		AllowSignedXcmDeposits::set(false);
+
+		assert_noop!(
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				AssetId(Location::parent()),
+				1000,
+				Location::parent(),
+				[42u8; 32],
+			),
+			Error::<Test>::SignedXcmDepositsDisabled
+		);
+	});
+}
+
+/// Trap `amount` of `asset_id` from sibling parachain `para_id`, as the executor would
+fn trap_shield_deposit(para_id: u32, asset_id: AssetId, amount: u128) {
+	let origin = Location::new(1, [Parachain(para_id)]);
+	let assets: staging_xcm_executor::AssetsInHolding =
+		vec![construct_asset(asset_id, amount)].into();
+	ShieldTrap::<Test, ()>::drop_assets(&origin, assets, &XcmContext::with_message_id([0u8; 32]));
+}
+
+#[test]
+fn test_trapped_shield_deposit_can_be_shielded_later() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let origin = Location::new(1, [Parachain(2000)]);
+		let note_key = [7u8; 32];
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			1000,
+			FIRST_ASSET,
+			&note_key,
+			&origin,
+		);
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0,
+		));
+
+		// The tree is full, so the deposit fails and its assets are trapped
+		crate::CommitmentCount::<Test>::put(1u32 << TREE_DEPTH);
+		trap_shield_deposit(2000, asset_id.clone(), 1000);
+		assert_eq!(PrivacyBridge::trapped_assets(&origin, &asset_id), 1000);
+		System::assert_last_event(Event::ShieldDepositTrapped {
+			origin: origin.clone(),
+			asset_id: asset_id.clone(),
+			amount: 1000,
+		}.into());
+
+		let resolution = TrapResolution::Shield { note_key };
+		assert_noop!(
+			PrivacyBridge::reclaim_trapped(RuntimeOrigin::signed(2000), asset_id.clone(), resolution.clone()),
+			Error::<Test>::TreeFull
+		);
+
+		// Once there is room again the retry succeeds
+		crate::CommitmentCount::<Test>::put(0);
+		assert_ok!(PrivacyBridge::reclaim_trapped(
+			RuntimeOrigin::signed(2000),
+			asset_id.clone(),
+			resolution.clone(),
+		));
+
+		assert!(PrivacyBridge::commitment_info(commitment).is_some());
+		assert_eq!(PrivacyBridge::total_shielded(FIRST_ASSET), 1000);
+		assert_eq!(PrivacyBridge::trapped_assets(&origin, &asset_id), 0);
+		System::assert_last_event(Event::TrappedAssetsReclaimed {
+			origin,
+			asset_id,
+			amount: 1000,
+			resolution,
+		}.into());
+	});
+}
+
+#[test]
+fn test_trapped_shield_deposit_can_be_returned() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let origin = Location::new(1, [Parachain(2000)]);
+		let beneficiary = Location::new(0, [Parachain(7)]);
+
+		// Trapped because the asset is not registered
+		trap_shield_deposit(2000, asset_id.clone(), 500);
+		trap_shield_deposit(2000, asset_id.clone(), 300);
+
+		// Only the originating chain can resolve its trapped assets
+		assert_noop!(
+			PrivacyBridge::reclaim_trapped(
+				RuntimeOrigin::signed(2001),
+				asset_id.clone(),
+				TrapResolution::Return { beneficiary: beneficiary.clone() },
+			),
+			Error::<Test>::NoTrappedAssets
+		);
+		assert_noop!(
+			PrivacyBridge::reclaim_trapped(
+				RuntimeOrigin::signed(2000),
+				asset_id.clone(),
+				TrapResolution::Shield { note_key: [7u8; 32] },
+			),
+			Error::<Test>::AssetNotRegistered
+		);
+
+		assert_ok!(PrivacyBridge::reclaim_trapped(
+			RuntimeOrigin::signed(2000),
+			asset_id.clone(),
+			TrapResolution::Return { beneficiary: beneficiary.clone() },
+		));
+
+		assert_eq!(
+			ExportedWithdrawals::get(),
+			vec![(asset_id.clone(), 800, VersionedDestination::V5(origin.clone()), beneficiary.encode())]
+		);
+		assert_eq!(PrivacyBridge::trapped_assets(&origin, &asset_id), 0);
+	});
+}
+
+#[test]
+fn test_set_reserve_location() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_noop!(
+			PrivacyBridge::set_reserve_location(
+				RuntimeOrigin::root(),
+				asset_id.clone(),
+				Some(Location::parent()),
+			),
+			Error::<Test>::AssetNotRegistered
+		);
+
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			0,
+		));
+		assert_eq!(PrivacyBridge::reserve_location(&asset_id), None);
+
+		assert_noop!(
+			PrivacyBridge::set_reserve_location(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				Some(Location::parent()),
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			Some(Location::parent()),
+		));
+
+		assert_eq!(PrivacyBridge::reserve_location(&asset_id), Some(Location::parent()));
+		System::assert_last_event(Event::ReserveLocationSet {
+			asset_id,
+			reserve_location: Some(Location::parent()),
+		}.into());
+	});
+}
+
+/// Register the relay asset and deposit one note per amount, returning (nullifier, commitment) pairs
+fn shield_relay_notes(amounts: &[u128]) -> Vec<(H256, H256)> {
+	let asset_id = AssetId(Location::parent());
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		asset_id.clone(),
+		0,
+		CustodyMode::Pot,
+		0,
+	));
+
+	amounts
+		.iter()
+		.enumerate()
+		.map(|(i, amount)| {
+			let randomness = [i as u8 + 1; 32];
+			assert_ok!(PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				*amount,
+				Location::parent(),
+				randomness,
+			));
+			let commitment =
+				crate::xcm_config::xcm_commitment_data::<MockHasher>(*amount, FIRST_ASSET, &randomness, &Location::parent());
+			let nullifier = crate::Pallet::<Test>::generate_nullifier(&commitment, &randomness);
+			(nullifier, commitment)
+		})
+		.collect()
+}
+
+/// Encoded XCM location of the `i`th test beneficiary
+fn beneficiary(i: u8) -> BoundedVec<u8, ConstU32<{ crate::bridge_adapter::MAX_BENEFICIARY_LEN }>> {
+	Location::new(0, [AccountId32 { network: None, id: [i; 32] }]).encode().try_into().unwrap()
+}
+
+/// Queue a withdrawal of each note to `para_id`, beneficiaries numbered from 1
+fn queue_withdrawals(notes: &[(H256, H256)], amount: u128, para_id: u32) {
+	for (i, (nullifier, commitment)) in notes.iter().enumerate() {
+		assert_ok!(PrivacyBridge::queue_withdrawal_to_remote(
+			RuntimeOrigin::signed(2),
+			*nullifier,
+			*commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			amount,
+			Location::new(1, [Parachain(para_id)]).into(),
+			beneficiary(i as u8 + 1),
+			0,
+			mock_proof(),
+		));
+	}
+}
+
+#[test]
+fn test_queued_withdrawals_batch_per_destination() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100, 100, 100, 100]);
+		queue_withdrawals(&notes[..3], 100, 2000);
+		queue_withdrawals(&notes[3..], 100, 3000);
+
+		// Notes are spent at queue time, nothing is exported yet
+		assert!(notes.iter().all(|(nullifier, _)| PrivacyBridge::nullifiers(nullifier)));
+		assert!(ExportedBatches::get().is_empty());
+
+		PrivacyBridge::on_idle(1, Weight::MAX);
+
+		let mut batches = ExportedBatches::get();
+		batches.sort_by_key(|(_, _, payouts)| payouts.len());
+		assert_eq!(batches.len(), 2);
+		assert_eq!(batches[0].1, Location::new(1, [Parachain(3000)]).into());
+		assert_eq!(batches[1].1, Location::new(1, [Parachain(2000)]).into());
+		// Paid out in a random order
+		let mut payouts = batches[1].2.clone();
+		payouts.sort();
+		assert_eq!(payouts, (1..=3).map(|i| (100, beneficiary(i).to_vec())).collect::<Vec<_>>());
+
+		// Each withdrawal is marked executed with its batch's message
+		let message_id = sp_io::hashing::blake2_256(&batches[1].encode());
+		for withdrawal_id in 0..3 {
+			System::assert_has_event(Event::QueuedWithdrawalExecuted { withdrawal_id, message_id }.into());
+		}
+		assert_eq!(crate::QueuedWithdrawals::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn test_queue_payout_order_follows_randomness() {
+	// Beneficiaries of the withdrawals of one message, in payout order
+	let payout_order = |seed: u64| {
+		new_test_ext().execute_with(|| {
+			RandomnessSeed::set(seed);
+			let notes = shield_relay_notes(&[100; 4]);
+			queue_withdrawals(&notes, 100, 2000);
+			PrivacyBridge::on_idle(1, Weight::MAX);
+
+			let batches = ExportedBatches::get();
+			assert_eq!(batches.len(), 1);
+			batches[0].2.iter().map(|(_, beneficiary)| beneficiary.clone()).collect::<Vec<_>>()
+		})
+	};
+
+	let orders: Vec<_> = (0..4).map(payout_order).collect();
+	for order in &orders {
+		let mut paid = order.clone();
+		paid.sort();
+		assert_eq!(paid, (1..=4).map(|i| beneficiary(i).to_vec()).collect::<Vec<_>>());
+	}
+	// The order changes with the randomness, not with the queue
+	assert!(orders.iter().any(|order| *order != orders[0]));
+	assert_eq!(payout_order(0), orders[0]);
+}
+
+#[test]
+fn test_batches_respect_max_deposits_per_message() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100; 5]);
+		queue_withdrawals(&notes, 100, 2000);
+
+		PrivacyBridge::on_idle(1, Weight::MAX);
+
+		let mut sizes: Vec<_> =
+			ExportedBatches::get().iter().map(|(_, _, payouts)| payouts.len()).collect();
+		sizes.sort();
+		assert_eq!(sizes, vec![1, 4]);
+	});
+}
+
+#[test]
+fn test_failed_batch_marks_each_withdrawal_failed() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100, 100]);
+		queue_withdrawals(&notes, 100, 2000);
+
+		ExportFails::set(true);
+		PrivacyBridge::on_idle(1, Weight::MAX);
+
+		for withdrawal_id in 0..2 {
+			assert_eq!(
+				PrivacyBridge::queued_withdrawals(withdrawal_id).unwrap().status,
+				crate::WithdrawalStatus::Failed
+			);
+			System::assert_has_event(Event::QueuedWithdrawalFailed { withdrawal_id }.into());
+		}
+
+		// Failed withdrawals are not picked up again until retried
+		ExportFails::set(false);
+		PrivacyBridge::on_idle(2, Weight::MAX);
+		assert!(ExportedBatches::get().is_empty());
+
+		assert_noop!(
+			PrivacyBridge::retry_withdrawal(RuntimeOrigin::signed(3), 7),
+			Error::<Test>::WithdrawalNotFound
+		);
+		assert_ok!(PrivacyBridge::retry_withdrawal(RuntimeOrigin::signed(3), 0));
+		assert_noop!(
+			PrivacyBridge::retry_withdrawal(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::WithdrawalNotFailed
+		);
+
+		PrivacyBridge::on_idle(3, Weight::MAX);
+		assert_eq!(ExportedBatches::get().len(), 1);
+		assert!(PrivacyBridge::queued_withdrawals(0).is_none());
+		assert!(PrivacyBridge::queued_withdrawals(1).is_some());
+	});
+}
+
+#[test]
+fn test_metrics_follow_deposits_withdrawals_and_exports() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100, 100]);
+		queue_withdrawals(&notes, 100, 2000);
+		ExportFails::set(true);
+		PrivacyBridge::on_idle(1, Weight::MAX);
+		ExportFails::set(false);
+		assert_ok!(PrivacyBridge::retry_withdrawal(RuntimeOrigin::signed(3), 0));
+		PrivacyBridge::on_idle(2, Weight::MAX);
+
+		// Refused for its missing anchor
+		MaxProofAge::set(Some(3));
+		assert_noop!(
+			PrivacyBridge::withdraw(
+				RuntimeOrigin::signed(2),
+				H256::zero(),
+				notes[0].1,
+				PrivacyBridge::merkle_root(),
+				100,
+				FIRST_ASSET,
+				mock_proof(),
+				None,
+			),
+			Error::<Test>::ProofAnchorRequired
+		);
+		MaxProofAge::set(None);
+
+		// Batched deposits wait for the end of the block
+		BatchTreeInsertion::set(true);
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 50, 0, [9u8; 32]));
+		assert_eq!(RecordedMetrics::get().pending_leaves, 1);
+		PrivacyBridge::on_finalize(2);
+
+		assert_eq!(
+			RecordedMetrics::get(),
+			MetricCounts {
+				deposits: vec![FIRST_ASSET, FIRST_ASSET, 0],
+				withdrawals: vec![FIRST_ASSET, FIRST_ASSET],
+				proof_failures: 1,
+				pending_leaves: 0,
+				queued: 2,
+				exported: 1,
+				export_failures: 2,
+				retries: 1,
+			}
+		);
+	});
+}
+
+#[test]
+fn test_queue_drain_stops_at_weight_limit() {
+	new_test_ext().execute_with(|| {
+		let notes = shield_relay_notes(&[100, 100]);
+		queue_withdrawals(&notes, 100, 2000);
+
+		assert_eq!(PrivacyBridge::on_idle(1, Weight::zero()), Weight::zero());
+		assert!(ExportedBatches::get().is_empty());
+		assert_eq!(crate::QueuedWithdrawals::<Test>::iter().count(), 2);
+	});
+}
+
+parameter_types! {
+	/// Messages delivered through `CapturingRouter`
+	pub static SentMessages: Vec<(Location, Xcm<()>)> = vec![];
+}
+
+/// Router capturing sent messages instead of delivering them
+struct CapturingRouter;
+
+impl SendXcm for CapturingRouter {
+	type Ticket = (Location, Xcm<()>);
+
+	fn validate(
+		destination: &mut Option<Location>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let ticket = (
+			destination.take().ok_or(SendError::MissingArgument)?,
+			message.take().ok_or(SendError::MissingArgument)?,
+		);
+		Ok((ticket, Assets::new()))
+	}
+
+	fn deliver(ticket: Self::Ticket) -> Result<XcmHash, SendError> {
+		let hash = sp_io::hashing::blake2_256(&ticket.1.encode());
+		SentMessages::mutate(|sent| sent.push(ticket));
+		Ok(hash)
+	}
+}
+
+#[test]
+fn test_xcm_exporter_sends_one_message_per_batch() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let destination = Location::new(1, [Parachain(2000)]);
+		let payouts: Vec<_> = (1..=3).map(|i| (100 * i as u128, beneficiary(i).to_vec())).collect();
+
+		assert_ok!(XcmExporter::<CapturingRouter>::export_batch(
+			&asset_id,
+			&destination.clone().into(),
+			&payouts,
+		));
+
+		let sent = SentMessages::get();
+		assert_eq!(sent.len(), 1);
+		assert_eq!(sent[0].0, destination);
+
+		let message = &sent[0].1;
+		assert_eq!(message.0[0], ReserveAssetDeposited(construct_asset(asset_id, 600).into()));
+		let deposits: Vec<_> = message
+			.0
+			.iter()
+			.filter_map(|instruction| match instruction {
+				DepositAsset { beneficiary, .. } => Some(beneficiary.clone()),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(
+			deposits,
+			(1..=3)
+				.map(|i| Location::new(0, [AccountId32 { network: None, id: [i; 32] }]))
+				.collect::<Vec<_>>()
+		);
+	});
+}
+
+#[test]
+fn test_withdraw_to_parachain_sends_deposit_to_beneficiary() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		let destination = Location::new(1, [Parachain(2000)]);
+		let recipient = Location::new(0, [AccountId32 { network: None, id: [5u8; 32] }]);
+
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			destination.clone(),
+			recipient.clone(),
+			0,
+			mock_proof(),
+		));
+
+		// What the pallet hands the exporter, `XcmExporter` sends as one program
+		let exported = ExportedWithdrawals::get();
+		let [(asset_id, amount, route, beneficiary)] = &exported[..] else {
+			panic!("one withdrawal is exported");
+		};
+		assert_ok!(XcmExporter::<CapturingRouter>::export(asset_id, *amount, route, beneficiary));
+
+		let sent = SentMessages::get();
+		assert_eq!(sent.len(), 1);
+		assert_eq!(sent[0].0, destination);
+		let asset = construct_asset(AssetId(Location::parent()), 1000);
+		assert_eq!(
+			sent[0].1,
+			Xcm(vec![
+				ReserveAssetDeposited(asset.clone().into()),
+				ClearOrigin,
+				BuyExecution { fees: asset, weight_limit: WeightLimit::Unlimited },
+				DepositAsset { assets: WildAsset::AllCounted(1).into(), beneficiary: recipient },
+			])
+		);
+	});
+}
+
+#[test]
+fn test_withdrawal_requires_open_channel() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		let destination = Location::new(1, [Parachain(4000)]);
+
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				destination.clone(),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::NoChannelToDestination
+		);
+		assert!(!PrivacyBridge::nullifiers(nullifier));
+
+		assert_noop!(
+			PrivacyBridge::sync_open_channels(
+				RuntimeOrigin::signed(1),
+				vec![4000].try_into().unwrap(),
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(PrivacyBridge::sync_open_channels(
+			RuntimeOrigin::root(),
+			vec![4000, 2000, 4000].try_into().unwrap(),
+		));
+		assert_eq!(PrivacyBridge::open_channels().to_vec(), vec![2000, 4000]);
+
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			destination,
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+		assert!(PrivacyBridge::nullifiers(nullifier));
+	});
+}
+
+#[test]
+fn test_channel_check_uses_first_hop() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		let asset_id = AssetId(Location::parent());
+		let via_reserve = Location::new(1, [Parachain(1000)]);
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			Some(via_reserve),
+		));
+
+		// Parachain 2000 has a channel, but the withdrawal goes through the reserve first
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				Location::new(1, [Parachain(2000)]),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::NoChannelToDestination
+		);
+
+		// Routed through the relay, the destination needs no direct channel
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			asset_id,
+			Some(Location::parent()),
+		));
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			Location::new(1, [Parachain(4000)]),
+			Location::new(0, []),
+			0,
+			mock_proof(),
+		));
+	});
+}
+
+/// Withdraw a fresh 1000-unit note to parachain 2000 under `fee`, returning (sent, fee)
+fn withdraw_with_fee(fee: Option<FeeConfig>) -> (u128, u128) {
+	let (commitment, nullifier) = shield_relay_asset(1000);
+	let destination = Location::new(1, [Parachain(2000)]);
+	assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+	assert_ok!(Assets::mint_into(FIRST_ASSET, &PrivacyBridge::account_id(), 10_000));
+	assert_ok!(PrivacyBridge::set_destination_fee(RuntimeOrigin::root(), destination.clone(), fee));
+
+	assert_ok!(PrivacyBridge::withdraw_to_parachain(
+		RuntimeOrigin::signed(2),
+		nullifier,
+		commitment,
+		PrivacyBridge::merkle_root(),
+		FIRST_ASSET,
+		1000,
+		destination,
+		Location::new(0, []),
+		u128::MAX,
+		mock_proof(),
+	));
+
+	let (_, sent, _, _) = ExportedWithdrawals::get().pop().unwrap();
+	let charged = Assets::balance(FIRST_ASSET, TREASURY);
+	assert_eq!(sent + charged, 1000);
+	(sent, charged)
+}
+
+#[test]
+fn test_no_destination_fee_by_default() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(withdraw_with_fee(None), (1000, 0));
+	});
+}
+
+#[test]
+fn test_flat_destination_fee() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(withdraw_with_fee(Some(FeeConfig { flat: 25, ppm: 0 })), (975, 25));
+	});
+}
+
+#[test]
+fn test_proportional_destination_fee() {
+	new_test_ext().execute_with(|| {
+		// 1% of 1000
+		assert_eq!(withdraw_with_fee(Some(FeeConfig { flat: 0, ppm: 10_000 })), (990, 10));
+	});
+}
+
+#[test]
+fn test_combined_destination_fee() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(withdraw_with_fee(Some(FeeConfig { flat: 25, ppm: 10_000 })), (965, 35));
+
+		let message_id = sp_io::hashing::blake2_256(&ExportedWithdrawals::get()[0].encode());
+		let nullifier = crate::Pallet::<Test>::generate_nullifier(
+			&crate::xcm_config::xcm_commitment_data::<MockHasher>(1000, FIRST_ASSET, &[42u8; 32], &Location::parent()),
+			&[99u8; 32],
+		);
+		System::assert_last_event(Event::WithdrawalExported {
+			nullifier,
+			asset_id: FIRST_ASSET,
+			amount: 965,
+			fee: 35,
+			destination: Location::new(1, [Parachain(2000)]).into(),
+			message_id,
+			receipt_id: crate::Pallet::<Test>::withdrawal_receipt_id(nullifier),
+		}.into());
+	});
+}
+
+#[test]
+fn test_destination_fee_requires_consent() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		let destination = Location::new(1, [Parachain(2000)]);
+
+		assert_noop!(
+			PrivacyBridge::set_destination_fee(
+				RuntimeOrigin::root(),
+				destination.clone(),
+				Some(FeeConfig { flat: 0, ppm: 1_000_001 }),
+			),
+			Error::<Test>::InvalidFeeConfig
+		);
+		assert_ok!(PrivacyBridge::set_destination_fee(
+			RuntimeOrigin::root(),
+			destination.clone(),
+			Some(FeeConfig { flat: 25, ppm: 0 }),
+		));
+
+		// The caller only accepted a fee of 24
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				destination.clone(),
+				Location::new(0, []),
+				24,
+				mock_proof(),
+			),
+			Error::<Test>::FeeExceedsMaximum
+		);
+
+		assert_ok!(PrivacyBridge::set_destination_fee(
+			RuntimeOrigin::root(),
+			destination.clone(),
+			Some(FeeConfig { flat: 2000, ppm: 0 }),
+		));
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(2),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				destination,
+				Location::new(0, []),
+				u128::MAX,
+				mock_proof(),
+			),
+			Error::<Test>::FeeExceedsAmount
+		);
+	});
+}
+
+/// Register `location` as an asset without minimum deposit
+fn register_location(location: Location) {
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		AssetId(location),
+		0,
+		CustodyMode::Pot,
+		0,
+	));
+}
+
+#[test]
+fn test_relay_asset_forms_resolve_to_same_entry() {
+	new_test_ext().execute_with(|| {
+		// Registered through the global consensus system...
+		register_location(Location::new(2, [GlobalConsensus(NetworkId::Polkadot)]));
+
+		// ...and stored under the canonical form
+		let relay = AssetId(Location::parent());
+		assert_eq!(
+			PrivacyBridge::registered_assets(&relay).map(|asset| asset.asset_id),
+			Some(relay.clone())
+		);
+		assert_eq!(crate::LocalAssetIndex::<Test>::get(FIRST_ASSET), Some(relay.clone()));
+
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			relay,
+			1000,
+			Location::parent(),
+			[1u8; 32],
+		));
+	});
+}
+
+#[test]
+fn test_sibling_asset_forms_resolve_to_same_entry() {
+	new_test_ext().execute_with(|| {
+		register_location(Location::new(1, [Parachain(1000)]));
+		let global = Location::new(2, [GlobalConsensus(NetworkId::Polkadot), Parachain(1000)]);
+
+		assert!(<RegisteredAssets<Test>>::contains(&AssetId(global.clone())));
+		assert_eq!(RegisteredAssetIds::<Test>::convert(&global), Some(FIRST_ASSET));
+		assert_eq!(
+			RegisteredAssetIds::<Test>::convert(&Location::new(1, [Parachain(1000)])),
+			Some(FIRST_ASSET)
+		);
+
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			AssetId(global.clone()),
+			1000,
+			Location::new(1, [Parachain(1000)]),
+			[1u8; 32],
+		));
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			AssetId(global),
+			Some(Location::new(1, [Parachain(1000)])),
+		));
+		assert_eq!(
+			PrivacyBridge::reserve_location(&AssetId(Location::new(1, [Parachain(1000)]))),
+			Some(Location::new(1, [Parachain(1000)]))
+		);
+	});
+}
+
+#[test]
+fn test_local_asset_addressed_through_own_parachain_resolves() {
+	new_test_ext().execute_with(|| {
+		register_location(Location::new(1, [Parachain(MOCK_PARA_ID), PalletInstance(50)]));
+
+		let local = AssetId(Location::new(0, [PalletInstance(50)]));
+		assert!(PrivacyBridge::registered_assets(&local).is_some());
+		assert!(<RegisteredAssets<Test>>::contains(&local));
+	});
+}
+
+/// Receipt call used in tests: call 3 of pallet 60 on the origin chain
+const RECEIPT_CALL: [u8; 2] = [60, 3];
+
+/// Register the relay asset with receipts enabled and deposit `amount` from parachain 2000
+fn deposit_with_receipts(amount: u128) -> H256 {
+	let asset_id = AssetId(Location::parent());
+	let origin_location = Location::new(1, [Parachain(2000)]);
+	assert_ok!(PrivacyBridge::register_asset(
+		RuntimeOrigin::root(),
+		asset_id.clone(),
+		100,
+		CustodyMode::Pot,
+		0,
+	));
+	assert_ok!(PrivacyBridge::set_receipt_call(
+		RuntimeOrigin::root(),
+		asset_id.clone(),
+		Some(RECEIPT_CALL),
+	));
+
+	assert_ok!(PrivacyBridge::deposit_from_xcm(
+		RuntimeOrigin::signed(1),
+		asset_id,
+		amount,
+		origin_location.clone(),
+		[42u8; 32],
+	));
+
+	crate::xcm_config::xcm_commitment_data::<MockHasher>(amount, FIRST_ASSET, &[42u8; 32], &origin_location)
+}
+
+#[test]
+fn test_deposit_receipt_sent_to_origin() {
+	new_test_ext().execute_with(|| {
+		let commitment = deposit_with_receipts(1000);
+		let origin = Location::new(1, [Parachain(2000)]);
+
+		assert_eq!(
+			SentReceipts::get(),
+			vec![(origin.clone(), RECEIPT_CALL, DepositReceipt { commitment, leaf_index: 0 })]
+		);
+		assert!(System::events().iter().any(|record| matches!(
+			&record.event,
+			RuntimeEvent::PrivacyBridge(Event::DepositReceiptSent { commitment: sent, origin: to, .. })
+				if *sent == commitment && *to == origin
+		)));
+	});
+}
+
+#[test]
+fn test_deposit_receipts_off_by_default() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			100,
+			CustodyMode::Pot,
+			0,
+		));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id.clone(),
+			1000,
+			Location::new(1, [Parachain(2000)]),
+			[42u8; 32],
+		));
+		assert!(SentReceipts::get().is_empty());
+
+		// Disabling again stops receipts
+		assert_ok!(PrivacyBridge::set_receipt_call(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			Some(RECEIPT_CALL),
+		));
+		assert_ok!(PrivacyBridge::set_receipt_call(RuntimeOrigin::root(), asset_id.clone(), None));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id,
+			1000,
+			Location::new(1, [Parachain(2000)]),
+			[43u8; 32],
+		));
+		assert!(SentReceipts::get().is_empty());
+	});
+}
+
+#[test]
+fn test_failed_receipt_keeps_deposit() {
+	new_test_ext().execute_with(|| {
+		ExportFails::set(true);
+		let commitment = deposit_with_receipts(1000);
+
+		assert!(crate::CommitmentIndex::<Test>::contains_key(commitment));
+		System::assert_last_event(
+			Event::DepositReceiptFailed { commitment, origin: Location::new(1, [Parachain(2000)]) }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn test_set_receipt_call_requires_registered_asset() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PrivacyBridge::set_receipt_call(
+				RuntimeOrigin::root(),
+				AssetId(Location::parent()),
+				Some(RECEIPT_CALL),
+			),
+			Error::<Test>::AssetNotRegistered
+		);
+		assert_noop!(
+			PrivacyBridge::set_receipt_call(
+				RuntimeOrigin::signed(1),
+				AssetId(Location::parent()),
+				Some(RECEIPT_CALL),
+			),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn test_xcm_receipt_sender_message_payload() {
+	new_test_ext().execute_with(|| {
+		let origin = Location::new(1, [Parachain(2000)]);
+		let commitment = H256::repeat_byte(7);
+		let receipt = DepositReceipt { commitment, leaf_index: 5 };
+
+		assert_ok!(XcmReceiptSender::<CapturingRouter>::send_receipt(&origin, RECEIPT_CALL, &receipt));
+
+		let sent = SentMessages::get();
+		assert_eq!(sent.len(), 1);
+		assert_eq!(sent[0].0, origin);
+
+		// The call is the receipt call index followed by (commitment, leaf_index)
+		let mut call = RECEIPT_CALL.to_vec();
+		call.extend_from_slice(commitment.as_bytes());
+		call.extend_from_slice(&5u32.to_le_bytes());
+		assert_eq!(
+			sent[0].1,
+			Xcm(vec![
+				UnpaidExecution { weight_limit: WeightLimit::Unlimited, check_origin: None },
+				Transact {
+					origin_kind: OriginKind::SovereignAccount,
+					fallback_max_weight: None,
+					call: call.into(),
+				},
+				SetTopic(commitment.0),
+			])
+		);
+	});
+}
+
+/// Dry-run a withdrawal of `amount` of the first asset to parachain `para_id`
+fn dry_run(amount: u128, para_id: u32) -> DryRunResult {
+	PrivacyBridge::dry_run_cross_chain_withdrawal(
+		FIRST_ASSET,
+		amount,
+		Location::new(1, [Parachain(para_id)]),
+		beneficiary(1).to_vec(),
+	)
+}
+
+#[test]
+fn test_dry_run_accepts_healthy_withdrawal() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+		assert_ok!(Assets::mint_into(FIRST_ASSET, &PrivacyBridge::account_id(), 1000));
+		assert_ok!(PrivacyBridge::set_destination_fee(
+			RuntimeOrigin::root(),
+			Location::new(1, [Parachain(2000)]),
+			Some(FeeConfig { flat: 25, ppm: 0 }),
+		));
+
+		assert_eq!(
+			dry_run(1000, 2000),
+			DryRunResult { ok: true, estimated_fee: 25, reasons: vec![] }
+		);
+
+		// The dry run spent nothing; the withdrawal itself goes through
+		assert_ok!(PrivacyBridge::withdraw_to_parachain(
+			RuntimeOrigin::signed(2),
+			nullifier,
+			commitment,
+			PrivacyBridge::merkle_root(),
+			FIRST_ASSET,
+			1000,
+			Location::new(1, [Parachain(2000)]),
+			Location::new(0, []),
+			25,
+			mock_proof(),
+		));
+	});
+}
+
+#[test]
+fn test_dry_run_reports_each_rejection() {
+	new_test_ext().execute_with(|| {
+		let rejected = |result: DryRunResult| {
+			assert!(!result.ok);
+			result.reasons
+		};
+
+		// Nothing registered yet
+		assert_eq!(rejected(dry_run(1000, 2000)), vec![RejectionReason::AssetNotRegistered]);
+
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			AssetId(Location::parent()),
+			0,
+			CustodyMode::Pot,
+			500,
+		));
+		assert_eq!(rejected(dry_run(1000, 4000)), vec![RejectionReason::NoChannelToDestination]);
+		assert_eq!(rejected(dry_run(400, 2000)), vec![RejectionReason::BelowRemoteMinBalance]);
+
+		assert_ok!(PrivacyBridge::set_destination_fee(
+			RuntimeOrigin::root(),
+			Location::new(1, [Parachain(2000)]),
+			Some(FeeConfig { flat: 2000, ppm: 0 }),
+		));
+		let result = dry_run(1000, 2000);
+		assert_eq!(result.estimated_fee, 2000);
+		assert_eq!(rejected(result), vec![RejectionReason::FeeExceedsAmount]);
+
+		let result = PrivacyBridge::dry_run_cross_chain_withdrawal(
+			FIRST_ASSET,
+			1000,
+			Location::new(1, [Parachain(3000)]),
+			vec![0u8; crate::bridge_adapter::MAX_BENEFICIARY_LEN as usize + 1],
+		);
+		assert_eq!(rejected(result), vec![RejectionReason::BeneficiaryTooLong]);
+
+		// Every failed check is reported
+		assert_eq!(
+			rejected(dry_run(400, 4000)),
+			vec![RejectionReason::NoChannelToDestination, RejectionReason::BelowRemoteMinBalance]
+		);
+	});
+}
+
+#[test]
+fn test_dry_run_matches_dispatch() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		assert_ok!(PrivacyBridge::register_asset(
+			RuntimeOrigin::root(),
+			asset_id.clone(),
+			0,
+			CustodyMode::Pot,
+			500,
+		));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id,
+			400,
+			Location::parent(),
+			[42u8; 32],
+		));
+		let commitment =
+			crate::xcm_config::xcm_commitment_data::<MockHasher>(400, FIRST_ASSET, &[42u8; 32], &Location::parent());
+		let nullifier = crate::Pallet::<Test>::generate_nullifier(&commitment, &[99u8; 32]);
+
+		// The dispatch path fails with the first reason of the dry run
+		for para_id in [4000, 2000] {
+			let reason = dry_run(400, para_id).reasons[0];
+			assert_noop!(
+				PrivacyBridge::withdraw_to_parachain(
+					RuntimeOrigin::signed(2),
+					nullifier,
+					commitment,
+					PrivacyBridge::merkle_root(),
+					FIRST_ASSET,
+					400,
+					Location::new(1, [Parachain(para_id)]),
+					Location::new(0, []),
+					0,
+					mock_proof(),
+				),
+				Error::<Test>::from(reason)
+			);
+		}
+	});
+}
+
+/// Register the relay asset with the relay chain as its reserve
+fn register_reserve_backed_asset() {
+	let asset_id = AssetId(Location::parent());
+	register_location(Location::parent());
+	assert_ok!(PrivacyBridge::set_reserve_location(
+		RuntimeOrigin::root(),
+		asset_id,
+		Some(Location::parent()),
+	));
+}
+
+/// Deposit `amount` of the relay asset through XCM, returning (commitment, nullifier)
+fn deposit_reserve_backed(amount: u128, seed: u8) -> (H256, H256) {
+	assert_ok!(PrivacyBridge::deposit_from_xcm(
+		RuntimeOrigin::signed(1),
+		AssetId(Location::parent()),
+		amount,
+		Location::parent(),
+		[seed; 32],
+	));
+
+	let commitment =
+		crate::xcm_config::xcm_commitment_data::<MockHasher>(amount, FIRST_ASSET, &[seed; 32], &Location::parent());
+	(commitment, crate::Pallet::<Test>::generate_nullifier(&commitment, &[seed; 32]))
+}
+
+/// Withdraw a reserve-backed note to parachain 2000
+fn withdraw_reserve_backed(note: (H256, H256), amount: u128) -> DispatchResult {
+	PrivacyBridge::withdraw_to_parachain(
+		RuntimeOrigin::signed(2),
+		note.1,
+		note.0,
+		PrivacyBridge::merkle_root(),
+		FIRST_ASSET,
+		amount,
+		Location::new(1, [Parachain(2000)]),
+		Location::new(0, []),
+		0,
+		mock_proof(),
+	)
+}
+
+#[test]
+fn test_reserve_backing_follows_deposits_and_withdrawals() {
+	new_test_ext().execute_with(|| {
+		register_reserve_backed_asset();
+
+		let first = deposit_reserve_backed(1000, 1);
+		let _second = deposit_reserve_backed(500, 2);
+		assert_eq!(PrivacyBridge::reserve_backing(FIRST_ASSET), 1500);
+
+		assert_ok!(withdraw_reserve_backed(first, 1000));
+		assert_eq!(PrivacyBridge::reserve_backing(FIRST_ASSET), 500);
+		assert_eq!(
+			PrivacyBridge::pool_stats(FIRST_ASSET),
+			crate::PoolStats {
+				total_shielded: 500,
+				protocol_owned: 0,
+				reserve_backing: 500,
+				anonymity: PrivacyBridge::anonymity_score(FIRST_ASSET),
+			}
+		);
+	});
+}
+
+#[test]
+fn test_reserve_backing_ignores_assets_reserved_here() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		assert_eq!(PrivacyBridge::reserve_backing(FIRST_ASSET), 0);
+
+		assert_ok!(withdraw_reserve_backed((commitment, nullifier), 1000));
+		assert_eq!(PrivacyBridge::reserve_backing(FIRST_ASSET), 0);
+	});
+}
+
+#[test]
+fn test_withdrawal_above_reserve_backing_rejected() {
+	new_test_ext().execute_with(|| {
+		// Deposited before the reserve was known, so nothing is recorded as backing
+		let note = shield_relay_asset(1000);
+		assert_ok!(PrivacyBridge::set_reserve_location(
+			RuntimeOrigin::root(),
+			AssetId(Location::parent()),
+			Some(Location::parent()),
+		));
+
+		assert_noop!(withdraw_reserve_backed(note, 1000), Error::<Test>::InsufficientReserveBacking);
+	});
+}
+
+#[test]
+fn test_reserve_backing_anomaly_raised_once_per_block() {
+	new_test_ext().execute_with(|| {
+		register_reserve_backed_asset();
+
+		// The mock threshold is 5_000
+		let note = deposit_reserve_backed(4000, 1);
+		assert_ok!(withdraw_reserve_backed(note, 4000));
+		let anomaly = |event: &RuntimeEvent| {
+			matches!(event, RuntimeEvent::PrivacyBridge(Event::ReserveBackingAnomaly { .. }))
+		};
+		assert_eq!(System::events().iter().filter(|record| anomaly(&record.event)).count(), 1);
+		System::assert_has_event(
+			Event::ReserveBackingAnomaly { asset_id: FIRST_ASSET, moved: 8000 }.into(),
+		);
+
+		// More movement in the same block raises nothing new
+		deposit_reserve_backed(4000, 2);
+		assert_eq!(System::events().iter().filter(|record| anomaly(&record.event)).count(), 1);
+
+		// The window restarts with the next block
+		System::set_block_number(2);
+		System::reset_events();
+		deposit_reserve_backed(4000, 3);
+		assert_eq!(System::events().iter().filter(|record| anomaly(&record.event)).count(), 0);
+		assert_eq!(PrivacyBridge::reserve_backing(FIRST_ASSET), 8000);
+	});
+}
+
+#[test]
+fn test_try_state_detects_unbacked_reserve_ledger() {
+	new_test_ext().execute_with(|| {
+		register_reserve_backed_asset();
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+		assert_ok!(Assets::mint_into(FIRST_ASSET, &PrivacyBridge::account_id(), 1000));
+		deposit_reserve_backed(1000, 1);
+		assert_ok!(crate::Pallet::<Test>::do_try_state());
+
+		// More backing than derivatives in existence
+		crate::ReserveBacking::<Test>::insert(FIRST_ASSET, 5000);
+		assert!(crate::Pallet::<Test>::do_try_state().is_err());
+	});
+}
+
+#[test]
+fn test_redelivered_xcm_deposit_is_idempotent() {
+	new_test_ext().execute_with(|| {
+		let origin_location = Location::new(1, [Parachain(2000)]);
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+
+		for _ in 0..2 {
+			assert_ok!(PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				1000,
+				origin_location.clone(),
+				[42u8; 32],
+			));
+		}
+		let commitment =
+			crate::xcm_config::xcm_commitment_data::<MockHasher>(1000, FIRST_ASSET, &[42u8; 32], &origin_location);
+
+		// One leaf, one shielded amount; the second delivery is held for refunding
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 1);
+		assert_eq!(PrivacyBridge::total_shielded(FIRST_ASSET), 1000);
+		assert_eq!(PrivacyBridge::unattributed_balances(&origin_location, &asset_id), 1000);
+		System::assert_last_event(
+			Event::DuplicateDeposit { commitment, origin: origin_location, asset_id, amount: 1000 }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn test_same_xcm_deposit_from_two_parachains_makes_two_notes() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+
+		let origins = [Location::new(1, [Parachain(1)]), Location::new(1, [Parachain(2)])];
+		for origin_location in origins.iter() {
+			assert_ok!(PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				1000,
+				origin_location.clone(),
+				[42u8; 32],
+			));
+		}
+
+		let [first, second] = origins.map(|origin_location| {
+			crate::xcm_config::xcm_commitment_data::<MockHasher>(
+				1000,
+				FIRST_ASSET,
+				&[42u8; 32],
+				&origin_location,
+			)
+		});
+		assert_ne!(first, second);
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 2);
+		assert_eq!(PrivacyBridge::unattributed_balances(&origins[0], &asset_id), 0);
+	});
+}
+
+#[test]
+fn test_duplicate_xcm_deposit_is_rejected_in_pool() {
+	new_test_ext().execute_with(|| {
+		let origin_location = Location::new(1, [Parachain(2000)]);
+		let asset_id = AssetId(Location::parent());
+		let call = RuntimeCall::PrivacyBridge(crate::Call::deposit_from_xcm {
+			asset_id: asset_id.clone(),
+			amount: 1000,
+			origin_location: origin_location.clone(),
+			note_key: [42u8; 32],
+		});
+		let validate = |call: &RuntimeCall| {
+			CheckDuplicateCommitment::<Test>::new()
+				.validate_only(
+					RuntimeOrigin::signed(1),
+					call,
+					&DispatchInfo::default(),
+					0,
+					TransactionSource::External,
+				)
+				.map(|_| ())
+		};
+
+		// Unregistered assets are left to dispatch
+		assert_ok!(validate(&call));
+
+		register_location(Location::parent());
+		assert_ok!(validate(&call));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id,
+			1000,
+			origin_location,
+			[42u8; 32],
+		));
+
+		// The redelivery dispatch would accept is refused as a transaction
+		assert_eq!(
+			validate(&call),
+			Err(InvalidTransaction::Custom(DUPLICATE_XCM_COMMITMENT).into())
+		);
+
+		// A local deposit with the same parameters is a different note
+		let local = RuntimeCall::PrivacyBridge(crate::Call::deposit {
+			amount: 1000,
+			asset_id: FIRST_ASSET,
+			note_key: [42u8; 32],
+		});
+		assert_ok!(validate(&local));
+	});
+}
+
+#[test]
+fn test_local_and_xcm_deposits_with_same_parameters_do_not_collide() {
+	new_test_ext().execute_with(|| {
+		let origin_location = Location::new(1, [Parachain(2000)]);
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+		assert_ok!(Assets::mint_into(FIRST_ASSET, &1, 1000));
+
+		// Either path first: the other still creates its own note
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, FIRST_ASSET, [42u8; 32]));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id.clone(),
+			500,
+			origin_location.clone(),
+			[42u8; 32],
+		));
+		assert_ok!(PrivacyBridge::deposit_from_xcm(
+			RuntimeOrigin::signed(1),
+			asset_id.clone(),
+			500,
+			origin_location.clone(),
+			[43u8; 32],
+		));
+		assert_ok!(PrivacyBridge::deposit(RuntimeOrigin::signed(1), 500, FIRST_ASSET, [43u8; 32]));
+
+		for seed in [42u8, 43] {
+			let local = crate::Pallet::<Test>::generate_commitment(500, FIRST_ASSET, &[seed; 32]);
+			let xcm = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+				500,
+				FIRST_ASSET,
+				&[seed; 32],
+				&origin_location,
+			);
+			assert_ne!(local, xcm);
+			assert!(crate::Commitments::<Test>::contains_key(local));
+			assert_eq!(crate::XcmDepositOrigins::<Test>::get(xcm), Some(origin_location.clone()));
+		}
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 4);
+		assert_eq!(PrivacyBridge::unattributed_balances(&origin_location, &asset_id), 0);
+	});
+}
+
+#[test]
+fn test_leaf_index_increments_across_local_and_xcm_deposits() {
+	new_test_ext().execute_with(|| {
+		let origin_location = Location::new(1, [Parachain(2000)]);
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		assert_ok!(Assets::force_create(RuntimeOrigin::root(), FIRST_ASSET, 1, true, 1));
+		assert_ok!(Assets::mint_into(FIRST_ASSET, &1, 1000));
+
+		for seed in 1u8..=4 {
+			if seed % 2 == 0 {
+				assert_ok!(PrivacyBridge::deposit_from_xcm(
+					RuntimeOrigin::signed(1),
+					asset_id.clone(),
+					500,
+					origin_location.clone(),
+					[seed; 32],
+				));
+			} else {
+				assert_ok!(PrivacyBridge::deposit(
+					RuntimeOrigin::signed(1),
+					500,
+					FIRST_ASSET,
+					[seed; 32],
+				));
+			}
+		}
+
+		let shielded: Vec<(H256, u32, H256)> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::PrivacyBridge(Event::AssetShielded {
+					commitment,
+					leaf_index,
+					merkle_root,
+					..
+				}) => Some((commitment, leaf_index, merkle_root)),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(shielded.len(), 4);
+		for (position, (commitment, leaf_index, _)) in shielded.iter().enumerate() {
+			assert_eq!(*leaf_index, position as u32);
+			assert_eq!(PrivacyBridge::leaf_of(commitment), Some(*leaf_index));
+		}
+		// Every insertion moves the root; the last one is the current root
+		for pair in shielded.windows(2) {
+			assert_ne!(pair[0].2, pair[1].2);
+		}
+		assert_eq!(shielded[3].2, PrivacyBridge::merkle_root());
+		assert_eq!(PrivacyBridge::leaf_of(&H256::repeat_byte(9)), None);
+	});
+}
+
+#[test]
+fn test_shield_deposit_duplicate_requires_same_origin() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let origin = Location::new(1, [Parachain(2000)]);
+		let other = Location::new(1, [Parachain(3000)]);
+		let randomness = [7u8; 32];
+		register_location(Location::parent());
+
+		assert_ok!(PrivacyBridge::shield_from_xcm(&asset_id, 500, &randomness, &origin));
+		assert_ok!(PrivacyBridge::shield_from_xcm(&asset_id, 500, &randomness, &origin));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 1);
+		assert_eq!(PrivacyBridge::unattributed_balances(&origin, &asset_id), 500);
+
+		// The same randomness from another chain is that chain's own note
+		assert_ok!(PrivacyBridge::shield_from_xcm(&asset_id, 500, &randomness, &other));
+		assert_eq!(crate::CommitmentCount::<Test>::get(), 2);
+		assert_eq!(PrivacyBridge::unattributed_balances(&other, &asset_id), 0);
+
+		// Nor can another chain claim the first chain's commitment
+		let registered = PrivacyBridge::registered_asset(&asset_id).unwrap();
+		let commitment = crate::xcm_config::xcm_commitment_data::<MockHasher>(
+			500,
+			FIRST_ASSET,
+			&randomness,
+			&origin,
+		);
+		assert_noop!(
+			PrivacyBridge::shield_xcm_commitment(&registered, 500, commitment, &other),
+			Error::<Test>::CommitmentAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn test_refund_unattributed_exports_to_origin() {
+	new_test_ext().execute_with(|| {
+		let asset_id = AssetId(Location::parent());
+		let origin = Location::new(1, [Parachain(2000)]);
+		let beneficiary = Location::new(0, [AccountId32 { network: None, id: [5u8; 32] }]);
+		register_location(Location::parent());
+		for _ in 0..2 {
+			assert_ok!(PrivacyBridge::shield_from_xcm(&asset_id, 500, &[7u8; 32], &origin));
+		}
+
+		assert_noop!(
+			PrivacyBridge::refund_unattributed(
+				RuntimeOrigin::signed(1),
+				origin.clone(),
+				asset_id.clone(),
+				beneficiary.clone(),
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(PrivacyBridge::refund_unattributed(
+			RuntimeOrigin::root(),
+			origin.clone(),
+			asset_id.clone(),
+			beneficiary.clone(),
+		));
+
+		assert_eq!(
+			ExportedWithdrawals::get(),
+			vec![(asset_id.clone(), 500, VersionedDestination::V5(origin.clone()), beneficiary.encode())]
+		);
+		assert_eq!(PrivacyBridge::unattributed_balances(&origin, &asset_id), 0);
+		assert_noop!(
+			PrivacyBridge::refund_unattributed(RuntimeOrigin::root(), origin, asset_id, beneficiary),
+			Error::<Test>::NoUnattributedBalance
+		);
+	});
+}
+
+#[test]
+fn test_xcm_deposits_reject_reserved_commitment() {
+	new_test_ext().execute_with(|| {
+		MockHasherId::set(crate::hasher::HasherId::Simple);
+		let asset_id = AssetId(Location::parent());
+		register_location(Location::parent());
+		let origin_digest = crate::xcm_config::origin_digest(&Location::new(1, [Parachain(2000)]));
+		let zero_key = zero_commitment_randomness(1000, FIRST_ASSET);
+		let note_key: [u8; 32] = core::array::from_fn(|i| zero_key[i] ^ origin_digest[i]);
+
+		assert_noop!(
+			PrivacyBridge::deposit_from_xcm(
+				RuntimeOrigin::signed(1),
+				asset_id.clone(),
+				1000,
+				Location::new(1, [Parachain(2000)]),
+				note_key,
+			),
+			Error::<Test>::ReservedCommitmentValue
+		);
+		// The auto-shield path of the asset transactor, past the commitment's derivation
+		let registered = PrivacyBridge::registered_asset(&asset_id).unwrap();
+		assert_noop!(
+			PrivacyBridge::shield_xcm_commitment(
+				&registered,
+				1000,
+				H256::zero(),
+				&Location::new(1, [Parachain(2000)]),
+			),
+			Error::<Test>::ReservedCommitmentValue
+		);
+	});
+}
+
+#[test]
+fn test_withdraw_to_parachain_requires_verifying_key() {
+	new_test_ext().execute_with(|| {
+		let (commitment, nullifier) = shield_relay_asset(1000);
+		crate::VerifyingKeys::<Test>::remove(crate::CircuitId::Withdraw);
+
+		assert_noop!(
+			PrivacyBridge::withdraw_to_parachain(
+				RuntimeOrigin::signed(1),
+				nullifier,
+				commitment,
+				PrivacyBridge::merkle_root(),
+				FIRST_ASSET,
+				1000,
+				Location::parent(),
+				Location::new(0, []),
+				0,
+				mock_proof(),
+			),
+			Error::<Test>::VerifyingKeyNotSet
+		);
+	});
+}
+
+#[test]
+fn test_cross_chain_withdrawal_receipt() {
+	new_test_ext().execute_with(|| {
+		let (sent, fee) = withdraw_with_fee(Some(FeeConfig { flat: 25, ppm: 0 }));
+		let nullifier = crate::Pallet::<Test>::generate_nullifier(
+			&crate::xcm_config::xcm_commitment_data::<MockHasher>(1000, FIRST_ASSET, &[42u8; 32], &Location::parent()),
+			&[99u8; 32],
+		);
+		let receipt_id = crate::primitives::withdrawal_receipt_id(&nullifier, 1, 0);
+
+		// Both events of the withdrawal carry the same receipt
+		System::assert_has_event(Event::AssetUnshielded {
+			nullifier,
+			asset_id: FIRST_ASSET,
+			block_number: 1,
+			receipt_id,
+		}.into());
+		assert!(System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PrivacyBridge(Event::WithdrawalExported { receipt_id: id, .. })
+				if id == receipt_id
+		)));
+
+		assert_eq!(
+			PrivacyBridge::withdrawal_by_id(receipt_id),
+			Some(crate::WithdrawalRecord {
+				nullifier,
+				asset_id: FIRST_ASSET,
+				amount: sent + fee,
+				fee,
+				block_number: 1,
+				extrinsic_index: 0,
+				destination: Some(Location::new(1, [Parachain(2000)]).into()),
+			})
+		);
+	});
+}
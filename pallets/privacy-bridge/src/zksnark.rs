@@ -4,16 +4,31 @@
 //! - Proof generation (off-chain, by users)
 //! - Proof verification (on-chain, by the pallet)
 //! - Trusted setup parameter management
+//!
+//! Verification runs in the runtime and never panics: proofs and public inputs are length
+//! checked before anything is decoded or allocated for them, and every failure is a
+//! `VerifyError`. Proving and setup run off-chain and report `String` errors.
+
+#![cfg_attr(
+	not(test),
+	deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
 
 use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey, PreparedVerifyingKey};
-use ark_bn254::{Bn254, Fr as ScalarField}; // BN254 pairing-friendly curve
+use ark_bn254::{Bn254, Fq12}; // BN254 pairing-friendly curve
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
 use ark_std::rand::SeedableRng;
-use ark_ff::PrimeField; // For from_le_bytes_mod_order
 use rand_chacha::ChaCha20Rng;
 use alloc::{vec::Vec, string::String, format};
+use sp_core::{ConstU32, H256};
+use sp_runtime::BoundedVec;
 
-use crate::circuit::PrivateTransferCircuit;
+use crate::circuit::{
+	BundleWithdrawalCircuit, CircuitHasher, JoinSplitCircuit, MembershipCircuit,
+	OwnershipCircuit, PrivateTransferCircuit, SwapCircuit, TransferCircuit,
+};
+use crate::primitives::{bundle_assets, public_inputs};
 
 /// Serialized proof bytes (for storage/transmission)
 pub type SerializedProof = Vec<u8>;
@@ -21,28 +36,75 @@ pub type SerializedProof = Vec<u8>;
 /// Serialized verifying key bytes
 pub type SerializedVK = Vec<u8>;
 
+/// Serialized prepared verifying key bytes (`serialize_prepared_vk`)
+pub type SerializedPreparedVK = Vec<u8>;
+
+/// Proving and verifying key of a circuit, from a setup
+pub type SetupKeys = (ProvingKey<Bn254>, VerifyingKey<Bn254>);
+
+/// Length of a compressed Groth16 proof over BN254 (two G1 points and one G2 point)
+pub const PROOF_LEN: usize = 128;
+
+/// Proof bytes as a call carries them
+pub type ProofBytes = BoundedVec<u8, ConstU32<{ PROOF_LEN as u32 }>>;
+
+/// Longest public input a proof is verified against, in bytes
+pub const MAX_PUBLIC_INPUT_LEN: usize = 256;
+
+/// Why a proof could not be verified
+///
+/// A proof that decodes and fails the pairing check is not an error, it verifies as
+/// `false`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerifyError {
+	/// The proof is not `PROOF_LEN` bytes long
+	ProofLength,
+	/// The proof bytes do not decode to a proof
+	MalformedProof,
+	/// A public input is longer than `MAX_PUBLIC_INPUT_LEN`
+	PublicInputTooLong,
+	/// The public inputs pack into more or fewer field elements than the key expects
+	PublicInputCount,
+	/// The verifying key does not decode, or does not fit its circuit
+	MalformedVerifyingKey,
+}
+
 /// Generate a proof for a private transfer
 ///
-/// This runs off-chain (client-side) because proof generation is computationally expensive
+/// This runs off-chain (client-side) because proof generation is computationally expensive.
+/// `merkle_path` and `path_indices` place `commitment` in the tree at `merkle_root`
+/// (`merkle_tree::generate_circuit_proof`). The proof only pays out to the recipient of
+/// `recipient_hash` (`primitives::recipient_hash`). The note's randomness is derived from
+/// `secret` and `tweak`: zero for a note shielded on this chain, the origin digest for an
+/// XCM deposit's (`primitives::note_randomness`).
 ///
 /// Returns: Serialized proof bytes that can be sent in a transaction
-pub fn generate_proof(
+#[allow(clippy::too_many_arguments)]
+pub fn generate_proof<H: CircuitHasher>(
 	proving_key: &ProvingKey<Bn254>,
 	nullifier: Vec<u8>,
 	commitment: Vec<u8>,
+	merkle_root: Vec<u8>,
+	recipient_hash: Vec<u8>,
 	amount: u128,
 	asset_id: u32,
-	randomness: [u8; 32],
+	tweak: [u8; 32],
 	secret: [u8; 32],
+	merkle_path: Vec<[u8; 32]>,
+	path_indices: Vec<bool>,
 ) -> Result<SerializedProof, String> {
 	// Create circuit with all inputs
-	let circuit = PrivateTransferCircuit::new(
+	let circuit = PrivateTransferCircuit::<H>::new(
 		nullifier,
 		commitment,
+		merkle_root,
+		recipient_hash,
 		amount,
 		asset_id,
-		randomness,
+		tweak,
 		secret,
+		merkle_path,
+		path_indices,
 	);
 
 	// Generate random coins for proof (deterministic in production)
@@ -65,39 +127,666 @@ pub fn generate_proof(
 /// This is fast and can run in the blockchain runtime
 ///
 /// Returns: true if proof is valid, false otherwise
+#[allow(clippy::too_many_arguments)]
 pub fn verify_proof(
 	verifying_key: &VerifyingKey<Bn254>,
 	proof_bytes: &[u8],
 	nullifier: &[u8],
 	commitment: &[u8],
-) -> Result<bool, String> {
-	// Deserialize proof
-	let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
-		.map_err(|e| format!("Proof deserialization failed: {:?}", e))?;
+	merkle_root: &[u8],
+	asset_id: u32,
+	amount: u128,
+	recipient_hash: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_proof_prepared(
+		&prepare_vk(verifying_key),
+		proof_bytes,
+		nullifier,
+		commitment,
+		merkle_root,
+		asset_id,
+		amount,
+		recipient_hash,
+	)
+}
+
+/// `verify_proof` against a key prepared beforehand (`prepare_vk`)
+///
+/// Preparing costs a pairing, about as much as the verification itself, so verifiers
+/// checking many proofs against one key prepare it once.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_proof_prepared(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifier: &[u8],
+	commitment: &[u8],
+	merkle_root: &[u8],
+	asset_id: u32,
+	amount: u128,
+	recipient_hash: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_with_inputs(
+		prepared_key,
+		proof_bytes,
+		&[
+			nullifier,
+			commitment,
+			merkle_root,
+			&asset_id.to_le_bytes(),
+			&amount.to_le_bytes(),
+			recipient_hash,
+		],
+	)
+}
+
+/// Verifier of withdrawal proofs (`Config::ProofVerifier`)
+///
+/// Runtimes use `Groth16Verifier`. The mock runtime swaps it for one accepting a
+/// placeholder, so tests of everything around the proof need not prove their notes.
+pub trait WithdrawalVerifier {
+	/// The serialized `verifying_key` prepared for verification, as the verify functions
+	/// take it
+	///
+	/// Fails if `verifying_key` does not decode.
+	fn prepare_key(verifying_key: &[u8]) -> Result<Vec<u8>, VerifyError>;
+
+	/// Whether `proof` proves the spend of `commitment`, a leaf of the tree at
+	/// `merkle_root` shielded as `amount` of `asset_id`, with `nullifier`, paying the
+	/// recipient of `recipient_hash`, against the `prepared_key` of the serialized
+	/// verifying key
+	#[allow(clippy::too_many_arguments)]
+	fn verify(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError>;
+
+	/// Whether `proof` proves the split of a leaf of the tree at `merkle_root`, spent with
+	/// `nullifier`, into the notes of `output_commitments` of the same `asset_id`, against
+	/// the `prepared_key` of `circuit::TransferCircuit`
+	fn verify_transfer(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		output_commitments: &[H256; 2],
+	) -> Result<bool, VerifyError>;
+
+	/// Whether `proof` proves the spend of the two distinct leaves `commitments` of the tree
+	/// at `merkle_root`, with `nullifiers`, holding `amount` of `asset_id` together for the
+	/// recipient of `recipient_hash`, against the `prepared_key` of
+	/// `circuit::JoinSplitCircuit`
+	#[allow(clippy::too_many_arguments)]
+	fn verify_join(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifiers: &[H256; 2],
+		commitments: &[H256; 2],
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError>;
+
+	/// Whether `proof` proves the spend of the bundle note `commitment`, with `nullifier`,
+	/// holding `assets`, paying the recipient of `recipient_hash`, against the
+	/// `prepared_key` of `circuit::BundleWithdrawalCircuit`
+	fn verify_bundle(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		assets: &[(u32, u128)],
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError>;
+
+	/// Whether `proof` proves the spend of `commitment`, with `nullifier`, into the note of
+	/// `output_commitment` of the same asset and amount, against the `prepared_key` of
+	/// `circuit::SwapCircuit`
+	fn verify_swap(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		output_commitment: &H256,
+	) -> Result<bool, VerifyError>;
+
+	/// A verifying key and a note of `asset_id` with a proof `verify` accepts when paying
+	/// the recipient of `recipient_hash`, for benchmarks
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_proof(asset_id: u32, recipient_hash: &H256) -> Result<BenchmarkProof, String>;
+}
+
+/// A note and its withdrawal proof, with the key verifying it
+///
+/// The proof places the note in a tree of its own, at `merkle_root`, hashed with
+/// `PoseidonHasher`.
+#[cfg(feature = "runtime-benchmarks")]
+pub struct BenchmarkProof {
+	pub verifying_key: SerializedVK,
+	pub nullifier: H256,
+	pub commitment: H256,
+	pub merkle_root: H256,
+	pub asset_id: u32,
+	pub amount: u128,
+	pub proof: SerializedProof,
+}
+
+/// Verifies withdrawal proofs of `PrivateTransferCircuit` with Groth16 over BN254
+pub struct Groth16Verifier;
+
+impl WithdrawalVerifier for Groth16Verifier {
+	fn prepare_key(verifying_key: &[u8]) -> Result<Vec<u8>, VerifyError> {
+		let verifying_key = deserialize_vk(verifying_key)?;
+		serialize_prepared_vk(&prepare_vk(&verifying_key))
+	}
+
+	fn verify(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		let prepared_key = deserialize_prepared_vk(prepared_key)?;
+		verify_proof_prepared(
+			&prepared_key,
+			proof,
+			nullifier.as_bytes(),
+			commitment.as_bytes(),
+			merkle_root.as_bytes(),
+			asset_id,
+			amount,
+			recipient_hash.as_bytes(),
+		)
+	}
 
-	// Prepare public inputs
-	let mut public_inputs = Vec::new();
+	fn verify_transfer(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		merkle_root: &H256,
+		asset_id: u32,
+		output_commitments: &[H256; 2],
+	) -> Result<bool, VerifyError> {
+		let prepared_key = deserialize_prepared_vk(prepared_key)?;
+		let [first_output, second_output] = output_commitments;
+		verify_transfer_proof(
+			&prepared_key,
+			proof,
+			nullifier.as_bytes(),
+			merkle_root.as_bytes(),
+			asset_id,
+			[first_output.as_bytes(), second_output.as_bytes()],
+		)
+	}
 
-	// Convert nullifier bytes to field elements
-	for chunk in nullifier.chunks(31) { // Field elements are ~31 bytes
-		let mut bytes = [0u8; 32];
-		bytes[..chunk.len()].copy_from_slice(chunk);
-		public_inputs.push(ScalarField::from_le_bytes_mod_order(&bytes));
+	fn verify_join(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifiers: &[H256; 2],
+		commitments: &[H256; 2],
+		merkle_root: &H256,
+		asset_id: u32,
+		amount: u128,
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		let prepared_key = deserialize_prepared_vk(prepared_key)?;
+		let [first_nullifier, second_nullifier] = nullifiers;
+		let [first_commitment, second_commitment] = commitments;
+		verify_join_proof(
+			&prepared_key,
+			proof,
+			[first_nullifier.as_bytes(), second_nullifier.as_bytes()],
+			[first_commitment.as_bytes(), second_commitment.as_bytes()],
+			merkle_root.as_bytes(),
+			asset_id,
+			amount,
+			recipient_hash.as_bytes(),
+		)
+	}
+
+	fn verify_bundle(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		assets: &[(u32, u128)],
+		recipient_hash: &H256,
+	) -> Result<bool, VerifyError> {
+		let prepared_key = deserialize_prepared_vk(prepared_key)?;
+		verify_bundle_proof(
+			&prepared_key,
+			proof,
+			nullifier.as_bytes(),
+			commitment.as_bytes(),
+			assets,
+			recipient_hash.as_bytes(),
+		)
 	}
 
-	// Convert commitment bytes to field elements
-	for chunk in commitment.chunks(31) {
-		let mut bytes = [0u8; 32];
-		bytes[..chunk.len()].copy_from_slice(chunk);
-		public_inputs.push(ScalarField::from_le_bytes_mod_order(&bytes));
+	fn verify_swap(
+		prepared_key: &[u8],
+		proof: &[u8],
+		nullifier: &H256,
+		commitment: &H256,
+		output_commitment: &H256,
+	) -> Result<bool, VerifyError> {
+		let prepared_key = deserialize_prepared_vk(prepared_key)?;
+		verify_swap_proof(
+			&prepared_key,
+			proof,
+			nullifier.as_bytes(),
+			commitment.as_bytes(),
+			output_commitment.as_bytes(),
+		)
 	}
 
-	// Verify the proof!
-	let pvk = PreparedVerifyingKey::from(verifying_key.clone());
-	let is_valid = Groth16::<Bn254>::verify_proof(&pvk, &proof, &public_inputs)
-		.map_err(|e| format!("Proof verification failed: {:?}", e))?;
+	#[cfg(feature = "runtime-benchmarks")]
+	fn benchmark_proof(asset_id: u32, recipient_hash: &H256) -> Result<BenchmarkProof, String> {
+		use crate::hasher::{NoteHasher, PoseidonHasher};
+
+		let (amount, tweak, secret) = (100u128, [0u8; 32], [2u8; 32]);
+
+		// A note of the runtimes' hasher, alone in its tree
+		let note_key = crate::primitives::note_key::<PoseidonHasher>(&secret);
+		let commitment =
+			crate::primitives::note_commitment::<PoseidonHasher>(amount, asset_id, &note_key);
+		let nullifier = PoseidonHasher::hash_nullifier(&commitment, &secret);
+		let leaves = [commitment];
+		let tree_error = |error| format!("Benchmark tree failed: {:?}", error);
+		let merkle_root =
+			crate::merkle_tree::root_of::<PoseidonHasher>(&leaves).map_err(tree_error)?;
+		let merkle_path = crate::merkle_tree::path_of::<PoseidonHasher>(&leaves, 0)
+			.map_err(tree_error)?
+			.into_iter()
+			.map(|sibling| sibling.0)
+			.collect();
+
+		let (pk, vk) = generate_setup_parameters::<PoseidonHasher>()?;
+		let proof = generate_proof::<PoseidonHasher>(
+			&pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			merkle_root.as_bytes().to_vec(),
+			recipient_hash.as_bytes().to_vec(),
+			amount,
+			asset_id,
+			tweak,
+			secret,
+			merkle_path,
+			alloc::vec![false; crate::merkle_tree::TREE_DEPTH],
+		)?;
+		Ok(BenchmarkProof {
+			verifying_key: serialize_vk(&vk)?,
+			nullifier,
+			commitment,
+			merkle_root,
+			asset_id,
+			amount,
+			proof,
+		})
+	}
+}
+
+/// Generate a proof for withdrawing a multi-asset (bundle) note
+pub fn generate_bundle_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	nullifier: Vec<u8>,
+	commitment: Vec<u8>,
+	assets: Vec<(u32, u128)>,
+	recipient_hash: Vec<u8>,
+	tweak: [u8; 32],
+	secret: [u8; 32],
+) -> Result<SerializedProof, String> {
+	let circuit = BundleWithdrawalCircuit::<H>::new(
+		nullifier,
+		commitment,
+		assets,
+		recipient_hash,
+		tweak,
+		secret,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Verify a bundle withdrawal proof for the `(asset_id, amount)` pairs about to be paid out
+/// to the recipient of `recipient_hash`
+pub fn verify_bundle_proof(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifier: &[u8],
+	commitment: &[u8],
+	assets: &[(u32, u128)],
+	recipient_hash: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_with_inputs(
+		prepared_key,
+		proof_bytes,
+		&[nullifier, commitment, &bundle_assets(assets), recipient_hash],
+	)
+}
+
+/// Generate a proof for spending a note into `output_commitment` (one leg of a swap)
+#[allow(clippy::too_many_arguments)]
+pub fn generate_swap_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	nullifier: Vec<u8>,
+	commitment: Vec<u8>,
+	output_commitment: Vec<u8>,
+	amount: u128,
+	asset_id: u32,
+	tweak: [u8; 32],
+	secret: [u8; 32],
+	output_randomness: [u8; 32],
+) -> Result<SerializedProof, String> {
+	let circuit = SwapCircuit::<H>::new(
+		nullifier,
+		commitment,
+		output_commitment,
+		amount,
+		asset_id,
+		tweak,
+		secret,
+		output_randomness,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Verify a swap leg proof for the note about to be created
+pub fn verify_swap_proof(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifier: &[u8],
+	commitment: &[u8],
+	output_commitment: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_with_inputs(
+		prepared_key,
+		proof_bytes,
+		&[nullifier, commitment, output_commitment],
+	)
+}
+
+/// Generate a proof for splitting a note of the tree into two notes (a private transfer)
+#[allow(clippy::too_many_arguments)]
+pub fn generate_transfer_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	nullifier: Vec<u8>,
+	merkle_root: Vec<u8>,
+	asset_id: u32,
+	output_commitments: [Vec<u8>; 2],
+	amount: u128,
+	tweak: [u8; 32],
+	secret: [u8; 32],
+	merkle_path: Vec<[u8; 32]>,
+	path_indices: Vec<bool>,
+	output_amounts: [u128; 2],
+	output_randomness: [[u8; 32]; 2],
+) -> Result<SerializedProof, String> {
+	let circuit = TransferCircuit::<H>::new(
+		nullifier,
+		merkle_root,
+		asset_id,
+		output_commitments,
+		amount,
+		tweak,
+		secret,
+		merkle_path,
+		path_indices,
+		output_amounts,
+		output_randomness,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Verify a transfer proof for the two notes about to be created, against a prepared key
+pub fn verify_transfer_proof(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifier: &[u8],
+	merkle_root: &[u8],
+	asset_id: u32,
+	output_commitments: [&[u8]; 2],
+) -> Result<bool, VerifyError> {
+	let [first_output, second_output] = output_commitments;
+	verify_with_inputs(
+		prepared_key,
+		proof_bytes,
+		&[nullifier, merkle_root, &asset_id.to_le_bytes(), first_output, second_output],
+	)
+}
+
+/// Generate a proof for withdrawing two notes of the tree as one amount
+#[allow(clippy::too_many_arguments)]
+pub fn generate_join_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	nullifiers: [Vec<u8>; 2],
+	commitments: [Vec<u8>; 2],
+	merkle_root: Vec<u8>,
+	asset_id: u32,
+	amount: u128,
+	recipient_hash: Vec<u8>,
+	input_amounts: [u128; 2],
+	tweaks: [[u8; 32]; 2],
+	secrets: [[u8; 32]; 2],
+	merkle_paths: [Vec<[u8; 32]>; 2],
+	path_indices: [Vec<bool>; 2],
+) -> Result<SerializedProof, String> {
+	let circuit = JoinSplitCircuit::<H>::new(
+		nullifiers,
+		commitments,
+		merkle_root,
+		asset_id,
+		amount,
+		recipient_hash,
+		input_amounts,
+		tweaks,
+		secrets,
+		merkle_paths,
+		path_indices,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Verify a joined withdrawal proof for the amount about to be paid out and its recipient,
+/// against a prepared key
+#[allow(clippy::too_many_arguments)]
+pub fn verify_join_proof(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifiers: [&[u8]; 2],
+	commitments: [&[u8]; 2],
+	merkle_root: &[u8],
+	asset_id: u32,
+	amount: u128,
+	recipient_hash: &[u8],
+) -> Result<bool, VerifyError> {
+	let [first_nullifier, second_nullifier] = nullifiers;
+	let [first_commitment, second_commitment] = commitments;
+	verify_with_inputs(
+		prepared_key,
+		proof_bytes,
+		&[
+			first_nullifier,
+			second_nullifier,
+			first_commitment,
+			second_commitment,
+			merkle_root,
+			&asset_id.to_le_bytes(),
+			&amount.to_le_bytes(),
+			recipient_hash,
+		],
+	)
+}
+
+/// Serialize a Groth16 proof to bytes
+fn serialize_proof(proof: &Proof<Bn254>) -> Result<SerializedProof, String> {
+	let mut proof_bytes = Vec::new();
+	proof.serialize_compressed(&mut proof_bytes)
+		.map_err(|e| format!("Proof serialization failed: {:?}", e))?;
+	Ok(proof_bytes)
+}
+
+/// Deserialize proof bytes and verify them against the given public inputs
+///
+/// Sizes are checked first, so the work done never depends on more than `PROOF_LEN`
+/// proof bytes and the number of inputs `prepared_key` was set up for.
+fn verify_with_inputs(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	inputs: &[&[u8]],
+) -> Result<bool, VerifyError> {
+	if proof_bytes.len() != PROOF_LEN {
+		return Err(VerifyError::ProofLength);
+	}
+	if inputs.iter().any(|input| input.len() > MAX_PUBLIC_INPUT_LEN) {
+		return Err(VerifyError::PublicInputTooLong);
+	}
+	let expected_elements = prepared_key
+		.vk
+		.gamma_abc_g1
+		.len()
+		.checked_sub(1)
+		.ok_or(VerifyError::MalformedVerifyingKey)?;
+	let elements: usize = inputs.iter().map(|input| input.len().div_ceil(31)).sum();
+	if elements != expected_elements {
+		return Err(VerifyError::PublicInputCount);
+	}
+
+	let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+		.map_err(|_| VerifyError::MalformedProof)?;
+
+	let public_inputs = public_inputs(inputs);
+
+	Groth16::<Bn254>::verify_proof(prepared_key, &proof, &public_inputs)
+		.map_err(|_| VerifyError::MalformedVerifyingKey)
+}
+
+/// Generate a merkle membership proof (the delegated half of a withdrawal proof)
+///
+/// Run by a proving service; needs only the leaf, its path and the link blinding.
+pub fn generate_membership_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	merkle_root: Vec<u8>,
+	link_tag: Vec<u8>,
+	commitment: [u8; 32],
+	link_blinding: [u8; 32],
+	merkle_path: Vec<[u8; 32]>,
+	path_indices: Vec<bool>,
+) -> Result<SerializedProof, String> {
+	let circuit = MembershipCircuit::<H>::new(
+		merkle_root,
+		link_tag,
+		commitment,
+		link_blinding,
+		merkle_path,
+		path_indices,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Generate an ownership proof (the client-side half of a withdrawal proof)
+pub fn generate_ownership_proof<H: CircuitHasher>(
+	proving_key: &ProvingKey<Bn254>,
+	nullifier: Vec<u8>,
+	link_tag: Vec<u8>,
+	amount: u128,
+	asset_id: u32,
+	tweak: [u8; 32],
+	secret: [u8; 32],
+	link_blinding: [u8; 32],
+) -> Result<SerializedProof, String> {
+	let circuit = OwnershipCircuit::<H>::new(
+		nullifier,
+		link_tag,
+		amount,
+		asset_id,
+		tweak,
+		secret,
+		link_blinding,
+	);
+
+	let mut rng = ChaCha20Rng::seed_from_u64(0u64);
+	let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut rng)
+		.map_err(|e| format!("Proof generation failed: {:?}", e))?;
+
+	serialize_proof(&proof)
+}
+
+/// Verify a merkle membership proof
+pub fn verify_membership_proof(
+	verifying_key: &VerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	merkle_root: &[u8],
+	link_tag: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_with_inputs(&prepare_vk(verifying_key), proof_bytes, &[merkle_root, link_tag])
+}
+
+/// Verify an ownership proof
+pub fn verify_ownership_proof(
+	verifying_key: &VerifyingKey<Bn254>,
+	proof_bytes: &[u8],
+	nullifier: &[u8],
+	link_tag: &[u8],
+) -> Result<bool, VerifyError> {
+	verify_with_inputs(&prepare_vk(verifying_key), proof_bytes, &[nullifier, link_tag])
+}
+
+/// Verify a composed (delegated) withdrawal proof
+///
+/// The membership proof shows some leaf of the tree at `merkle_root` has link tag
+/// `link_tag`; the ownership proof shows the prover can open the note behind the same
+/// tag and derive `nullifier`. Both must verify against the shared tag.
+pub fn verify_composed_proof(
+	membership_vk: &VerifyingKey<Bn254>,
+	ownership_vk: &VerifyingKey<Bn254>,
+	membership_proof: &[u8],
+	ownership_proof: &[u8],
+	merkle_root: &[u8],
+	nullifier: &[u8],
+	link_tag: &[u8],
+) -> Result<bool, VerifyError> {
+	if !verify_membership_proof(membership_vk, membership_proof, merkle_root, link_tag)? {
+		return Ok(false);
+	}
 
-	Ok(is_valid)
+	verify_ownership_proof(ownership_vk, ownership_proof, nullifier, link_tag)
 }
 
 /// Generate trusted setup parameters (proving key + verifying key)
@@ -105,10 +794,14 @@ pub fn verify_proof(
 /// **WARNING:** This is a TRUSTED SETUP!
 /// In production, use a multi-party computation (MPC) ceremony
 /// For hackathon/demo, this simple version is fine
-pub fn generate_setup_parameters() -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), String> {
+///
+/// The circuits hash with `H`: the keys only verify proofs over notes and trees of that
+/// hasher, so they are set on chain with its `HasherId`. The setups of the other circuits
+/// below are alike.
+pub fn generate_setup_parameters<H: CircuitHasher>() -> Result<SetupKeys, String> {
 
 	// Create an empty circuit for setup
-	let circuit = PrivateTransferCircuit::empty();
+	let circuit = PrivateTransferCircuit::<H>::empty();
 
 	// Generate random parameters
 	let mut rng = ChaCha20Rng::seed_from_u64(12345u64); // Deterministic for testing
@@ -123,6 +816,84 @@ pub fn generate_setup_parameters() -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn
 	Ok((pk, vk))
 }
 
+/// Generate trusted setup parameters for the delegated membership circuit
+pub fn generate_membership_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(23456u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		MembershipCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
+/// Generate trusted setup parameters for the delegated ownership circuit
+pub fn generate_ownership_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(34567u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		OwnershipCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
+/// Generate trusted setup parameters for the bundle withdrawal circuit
+pub fn generate_bundle_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(45678u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		BundleWithdrawalCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
+/// Generate trusted setup parameters for the swap circuit
+pub fn generate_swap_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(56789u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		SwapCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
+/// Generate trusted setup parameters for the transfer circuit
+pub fn generate_transfer_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(67890u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		TransferCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
+/// Generate trusted setup parameters for the joined withdrawal circuit
+pub fn generate_join_setup<H: CircuitHasher>() -> Result<SetupKeys, String> {
+	let mut rng = ChaCha20Rng::seed_from_u64(78901u64); // Deterministic for testing
+
+	let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+		JoinSplitCircuit::<H>::empty(),
+		&mut rng,
+	).map_err(|e| format!("Setup failed: {:?}", e))?;
+	let vk = pk.vk.clone();
+
+	Ok((pk, vk))
+}
+
 /// Serialize verifying key to bytes (for storage)
 pub fn serialize_vk(vk: &VerifyingKey<Bn254>) -> Result<SerializedVK, String> {
 	let mut bytes = Vec::new();
@@ -132,27 +903,78 @@ pub fn serialize_vk(vk: &VerifyingKey<Bn254>) -> Result<SerializedVK, String> {
 }
 
 /// Deserialize verifying key from bytes
-pub fn deserialize_vk(bytes: &[u8]) -> Result<VerifyingKey<Bn254>, String> {
+pub fn deserialize_vk(bytes: &[u8]) -> Result<VerifyingKey<Bn254>, VerifyError> {
 	VerifyingKey::<Bn254>::deserialize_compressed(bytes)
-		.map_err(|e| format!("VK deserialization failed: {:?}", e))
+		.map_err(|_| VerifyError::MalformedVerifyingKey)
+}
+
+/// Prepare a verifying key for verification: the pairing `e(alpha, beta)` and the
+/// negated `gamma` and `delta` points ready for the pairing check
+pub fn prepare_vk(vk: &VerifyingKey<Bn254>) -> PreparedVerifyingKey<Bn254> {
+	ark_groth16::prepare_verifying_key(vk)
+}
+
+/// Serialize a prepared verifying key to bytes (for storage)
+///
+/// Holds the key and `e(alpha, beta)`, uncompressed. The prepared G2 points are left out:
+/// their line coefficients take tens of kilobytes and are rebuilt without a pairing.
+pub fn serialize_prepared_vk(
+	prepared_key: &PreparedVerifyingKey<Bn254>,
+) -> Result<SerializedPreparedVK, VerifyError> {
+	let mut bytes = Vec::new();
+	prepared_key
+		.vk
+		.serialize_uncompressed(&mut bytes)
+		.and_then(|_| prepared_key.alpha_g1_beta_g2.serialize_uncompressed(&mut bytes))
+		.map_err(|_| VerifyError::MalformedVerifyingKey)?;
+	Ok(bytes)
+}
+
+/// Deserialize a prepared verifying key from bytes of `serialize_prepared_vk`
+///
+/// The points are not checked to be on the curve: the bytes are only ever written from a
+/// key `deserialize_vk` checked.
+pub fn deserialize_prepared_vk(
+	bytes: &[u8],
+) -> Result<PreparedVerifyingKey<Bn254>, VerifyError> {
+	let mut reader = bytes;
+	let vk = VerifyingKey::<Bn254>::deserialize_uncompressed_unchecked(&mut reader)
+		.map_err(|_| VerifyError::MalformedVerifyingKey)?;
+	let alpha_g1_beta_g2 = Fq12::deserialize_uncompressed_unchecked(&mut reader)
+		.map_err(|_| VerifyError::MalformedVerifyingKey)?;
+	if !reader.is_empty() {
+		return Err(VerifyError::MalformedVerifyingKey);
+	}
+	Ok(PreparedVerifyingKey {
+		gamma_g2_neg_pc: (-vk.gamma_g2.into_group()).into_affine().into(),
+		delta_g2_neg_pc: (-vk.delta_g2.into_group()).into_affine().into(),
+		vk,
+		alpha_g1_beta_g2,
+	})
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::hasher::SimpleHasher;
+	use crate::test_support::{
+		bundle_setup, join_setup, lone_leaf_path, swap_setup, test_setup, transfer_setup,
+		TEST_RECIPIENT_HASH,
+	};
 
 	#[test]
 	fn test_proof_generation_and_verification() {
 		use crate::simple_hash;
 
 		// Generate setup parameters
-		let (pk, vk) = generate_setup_parameters().unwrap();
+		let (pk, vk) = test_setup();
 
 		// Test data
 		let amount = 100u128;
 		let asset_id = 0u32;
-		let randomness = [1u8; 32];
+		let tweak = [0u8; 32];
 		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
 
 		// Week 3: Generate commitment and nullifier using simple_hash
 		let commitment_hash = simple_hash::generate_commitment(amount, asset_id, &randomness);
@@ -161,19 +983,34 @@ mod tests {
 		let nullifier_hash = simple_hash::generate_nullifier(&commitment_hash, &secret);
 		let nullifier = nullifier_hash.as_bytes().to_vec();
 
-		// Generate proof
-		let proof_bytes = generate_proof(
-			&pk,
+		// Generate proof, for a tree holding only the note
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof_bytes = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier.clone(),
 			commitment.clone(),
+			commitment.clone(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			merkle_path,
+			path_indices,
 		).unwrap();
 
 		// Verify proof
-		let is_valid = verify_proof(&vk, &proof_bytes, &nullifier, &commitment).unwrap();
+		let is_valid = verify_proof(
+			vk,
+			&proof_bytes,
+			&nullifier,
+			&commitment,
+			&commitment,
+			asset_id,
+			amount,
+			&TEST_RECIPIENT_HASH,
+		)
+		.unwrap();
 
 		assert!(is_valid, "Proof should be valid!");
 	}
@@ -183,13 +1020,14 @@ mod tests {
 		use crate::simple_hash;
 
 		// Generate setup
-		let (pk, vk) = generate_setup_parameters().unwrap();
+		let (pk, vk) = test_setup();
 
 		// Test data
 		let amount = 100u128;
 		let asset_id = 0u32;
-		let randomness = [1u8; 32];
+		let tweak = [0u8; 32];
 		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
 
 		// Week 3: Generate commitment and nullifier using simple_hash
 		let commitment_hash = simple_hash::generate_commitment(amount, asset_id, &randomness);
@@ -199,34 +1037,424 @@ mod tests {
 		let nullifier = nullifier_hash.as_bytes().to_vec();
 
 		// Generate proof with correct inputs
-		let proof_bytes = generate_proof(
-			&pk,
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof_bytes = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier.clone(),
 			commitment.clone(),
+			commitment.clone(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			merkle_path,
+			path_indices,
 		).unwrap();
 
 		// Try to verify with WRONG commitment
 		let wrong_commitment = vec![0u8; 32];
-		let is_valid = verify_proof(&vk, &proof_bytes, &nullifier, &wrong_commitment).unwrap();
+		let is_valid = verify_proof(
+			vk,
+			&proof_bytes,
+			&nullifier,
+			&wrong_commitment,
+			&commitment,
+			asset_id,
+			amount,
+			&TEST_RECIPIENT_HASH,
+		)
+		.unwrap();
 
 		assert!(!is_valid, "Invalid proof should be rejected!");
+
+		// Nor does the proof pay out in another asset or amount, or to another recipient
+		let verify = |asset_id: u32, amount: u128, recipient_hash: &[u8]| {
+			verify_proof(
+				vk,
+				&proof_bytes,
+				&nullifier,
+				&commitment,
+				&commitment,
+				asset_id,
+				amount,
+				recipient_hash,
+			)
+			.unwrap()
+		};
+		assert!(verify(asset_id, amount, &TEST_RECIPIENT_HASH));
+		assert!(
+			!verify(1, amount, &TEST_RECIPIENT_HASH),
+			"Proof for another asset should be rejected!"
+		);
+		assert!(
+			!verify(asset_id, amount + 1, &TEST_RECIPIENT_HASH),
+			"Proof for another amount should be rejected!"
+		);
+		assert!(
+			!verify(asset_id, amount, &[9u8; 32]),
+			"Proof for another recipient should be rejected!"
+		);
 	}
 
 	#[test]
 	fn test_vk_serialization() {
-		let (_, vk) = generate_setup_parameters().unwrap();
+		let (_, vk) = test_setup();
 
 		// Serialize
-		let bytes = serialize_vk(&vk).unwrap();
+		let bytes = serialize_vk(vk).unwrap();
 
 		// Deserialize
 		let vk2 = deserialize_vk(&bytes).unwrap();
 
 		// Should be equal
-		assert_eq!(vk, vk2);
+		assert_eq!(*vk, vk2);
+	}
+
+	#[test]
+	fn test_prepared_vk_serialization() {
+		let (_, vk) = test_setup();
+		let prepared = prepare_vk(vk);
+
+		let bytes = serialize_prepared_vk(&prepared).unwrap();
+		assert_eq!(deserialize_prepared_vk(&bytes).unwrap(), prepared);
+
+		// Neither truncated nor padded bytes decode
+		assert_eq!(
+			deserialize_prepared_vk(&bytes[..bytes.len() - 1]),
+			Err(VerifyError::MalformedVerifyingKey)
+		);
+		let padded = [&bytes[..], &[0u8][..]].concat();
+		assert_eq!(deserialize_prepared_vk(&padded), Err(VerifyError::MalformedVerifyingKey));
+		assert_eq!(Groth16Verifier::prepare_key(&[]), Err(VerifyError::MalformedVerifyingKey));
+	}
+
+	#[test]
+	fn test_prepared_verification_matches_unprepared() {
+		use crate::simple_hash;
+
+		let (pk, vk) = test_setup();
+		let (amount, asset_id, tweak, secret) = (100u128, 0u32, [0u8; 32], [2u8; 32]);
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let commitment = simple_hash::generate_commitment(amount, asset_id, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof_bytes = generate_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
+			amount,
+			asset_id,
+			tweak,
+			secret,
+			merkle_path,
+			path_indices,
+		).unwrap();
+		let mut tampered = proof_bytes.clone();
+		tampered[0] ^= 1;
+
+		// The key as the pallet stores it, prepared and serialized
+		let prepared_key = Groth16Verifier::prepare_key(&serialize_vk(vk).unwrap()).unwrap();
+		let recipient_hash = H256(TEST_RECIPIENT_HASH);
+		for (proof, asset_id, amount, recipient_hash) in [
+			(&proof_bytes, asset_id, amount, recipient_hash),
+			(&proof_bytes, 1, amount, recipient_hash),
+			(&proof_bytes, asset_id, amount - 1, recipient_hash),
+			(&proof_bytes, asset_id, amount, H256([9; 32])),
+			(&tampered, asset_id, amount, recipient_hash),
+		] {
+			let unprepared = verify_proof(
+				vk,
+				proof,
+				nullifier.as_bytes(),
+				commitment.as_bytes(),
+				commitment.as_bytes(),
+				asset_id,
+				amount,
+				recipient_hash.as_bytes(),
+			);
+			let prepared = Groth16Verifier::verify(
+				&prepared_key,
+				proof,
+				&nullifier,
+				&commitment,
+				&commitment,
+				asset_id,
+				amount,
+				&recipient_hash,
+			);
+			assert_eq!(prepared, unprepared);
+		}
+		assert_eq!(
+			Groth16Verifier::verify(
+				&prepared_key,
+				&proof_bytes,
+				&nullifier,
+				&commitment,
+				&commitment,
+				asset_id,
+				amount,
+				&recipient_hash,
+			),
+			Ok(true)
+		);
+	}
+
+	#[test]
+	fn test_degenerate_proofs_and_inputs_are_errors() {
+		use crate::simple_hash;
+
+		let (pk, vk) = test_setup();
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let commitment = simple_hash::generate_commitment(100, 0, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+		let (nullifier, commitment) = (nullifier.as_bytes(), commitment.as_bytes());
+
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof_bytes = generate_proof::<SimpleHasher>(
+			pk,
+			nullifier.to_vec(),
+			commitment.to_vec(),
+			commitment.to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
+			100,
+			0,
+			tweak,
+			secret,
+			merkle_path,
+			path_indices,
+		).unwrap();
+		assert_eq!(proof_bytes.len(), PROOF_LEN);
+		let verify = |proof: &[u8], nullifier: &[u8]| {
+			verify_proof(vk, proof, nullifier, commitment, commitment, 0, 100, &TEST_RECIPIENT_HASH)
+		};
+		assert_eq!(verify(&proof_bytes, nullifier), Ok(true));
+
+		// Over-long, truncated, empty and garbage proofs
+		let mut over_long = proof_bytes.clone();
+		over_long.extend_from_slice(&[0u8; 1024]);
+		let refused = [over_long, proof_bytes[..PROOF_LEN - 1].to_vec(), Vec::new()];
+		for proof in refused {
+			assert_eq!(
+				verify(&proof, nullifier),
+				Err(VerifyError::ProofLength)
+			);
+		}
+		assert_eq!(
+			verify(&[0xffu8; PROOF_LEN], nullifier),
+			Err(VerifyError::MalformedProof)
+		);
+
+		// Over-long and missing public inputs
+		let huge_input = vec![1u8; MAX_PUBLIC_INPUT_LEN + 1];
+		assert_eq!(
+			verify(&proof_bytes, &huge_input),
+			Err(VerifyError::PublicInputTooLong)
+		);
+		assert_eq!(
+			verify(&proof_bytes, &[]),
+			Err(VerifyError::PublicInputCount)
+		);
+		assert_eq!(
+			verify_composed_proof(vk, vk, &[], &proof_bytes, commitment, nullifier, commitment),
+			Err(VerifyError::ProofLength)
+		);
+
+		assert_eq!(deserialize_vk(&[]), Err(VerifyError::MalformedVerifyingKey));
+	}
+
+	#[test]
+	fn test_bundle_proof_binds_assets() {
+		use crate::simple_hash;
+
+		let (pk, vk) = bundle_setup();
+
+		let assets = vec![(0u32, 100u128), (1, 250)];
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let commitment = simple_hash::generate_bundle_commitment(&assets, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+
+		let proof_bytes = generate_bundle_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			assets.clone(),
+			TEST_RECIPIENT_HASH.to_vec(),
+			tweak,
+			secret,
+		).unwrap();
+
+		let verify = |assets: &[(u32, u128)], recipient_hash: &[u8]| {
+			verify_bundle_proof(
+				&prepare_vk(vk),
+				&proof_bytes,
+				nullifier.as_bytes(),
+				commitment.as_bytes(),
+				assets,
+				recipient_hash,
+			)
+			.unwrap()
+		};
+		assert!(verify(&assets, &TEST_RECIPIENT_HASH), "Proof should be valid!");
+		// The proof does not cover a larger payout, nor pay anyone else
+		assert!(!verify(&[(0, 100), (1, 251)], &TEST_RECIPIENT_HASH));
+		assert!(!verify(&assets, &[9u8; 32]));
+	}
+	#[test]
+	fn test_swap_proof_binds_output_commitment() {
+		use crate::simple_hash;
+
+		let (pk, vk) = swap_setup();
+
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let output_randomness = [3u8; 32];
+		let commitment = simple_hash::generate_commitment(100, 1, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+		let output = simple_hash::generate_commitment(100, 1, &output_randomness);
+
+		let proof_bytes = generate_swap_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			output.as_bytes().to_vec(),
+			100,
+			1,
+			tweak,
+			secret,
+			output_randomness,
+		).unwrap();
+
+		let verify = |output: &[u8]| {
+			verify_swap_proof(
+				&prepare_vk(vk),
+				&proof_bytes,
+				nullifier.as_bytes(),
+				commitment.as_bytes(),
+				output,
+			)
+			.unwrap()
+		};
+		assert!(verify(output.as_bytes()), "Proof should be valid!");
+		// The proof does not authorize another output note
+		assert!(!verify(&[9u8; 32]));
+	}
+
+	#[test]
+	fn test_transfer_proof_binds_outputs() {
+		use crate::simple_hash;
+
+		let (pk, vk) = transfer_setup();
+
+		let tweak = [0u8; 32];
+		let secret = [2u8; 32];
+		let randomness = simple_hash::generate_note_randomness(&secret, &tweak);
+		let output_randomness = [[3u8; 32], [4u8; 32]];
+		let commitment = simple_hash::generate_commitment(100, 1, &randomness);
+		let nullifier = simple_hash::generate_nullifier(&commitment, &secret);
+		let outputs = [
+			simple_hash::generate_commitment(60, 1, &output_randomness[0]),
+			simple_hash::generate_commitment(40, 1, &output_randomness[1]),
+		];
+		let (merkle_path, path_indices) = lone_leaf_path();
+
+		let proof_bytes = generate_transfer_proof::<SimpleHasher>(
+			pk,
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			1,
+			outputs.map(|output| output.as_bytes().to_vec()),
+			100,
+			tweak,
+			secret,
+			merkle_path,
+			path_indices,
+			[60, 40],
+			output_randomness,
+		).unwrap();
+
+		let verify = |asset_id: u32, second_output: &[u8]| {
+			verify_transfer_proof(
+				&prepare_vk(vk),
+				&proof_bytes,
+				nullifier.as_bytes(),
+				commitment.as_bytes(),
+				asset_id,
+				[outputs[0].as_bytes(), second_output],
+			)
+			.unwrap()
+		};
+		assert!(verify(1, outputs[1].as_bytes()), "Proof should be valid!");
+		// The proof does not authorize another output note, nor notes of another asset
+		assert!(!verify(1, &[9u8; 32]));
+		assert!(!verify(2, outputs[1].as_bytes()));
+	}
+
+	#[test]
+	fn test_join_proof_binds_amount() {
+		use crate::{merkle_tree, simple_hash};
+
+		let (pk, vk) = join_setup();
+
+		let tweaks = [[0u8; 32], [1u8; 32]];
+		let secrets = [[2u8; 32], [4u8; 32]];
+		let randomness =
+			[0, 1].map(|i| simple_hash::generate_note_randomness(&secrets[i], &tweaks[i]));
+		let notes = [
+			simple_hash::generate_commitment(60, 1, &randomness[0]),
+			simple_hash::generate_commitment(40, 1, &randomness[1]),
+		];
+		let nullifiers = [
+			simple_hash::generate_nullifier(&notes[0], &secrets[0]),
+			simple_hash::generate_nullifier(&notes[1], &secrets[1]),
+		];
+		let root = merkle_tree::calculate_root(&notes).unwrap();
+		let path = |index: usize| {
+			let (siblings, path_indices) =
+				merkle_tree::generate_circuit_proof(&notes, index).unwrap();
+			(siblings.into_iter().map(|sibling| sibling.0).collect::<Vec<_>>(), path_indices)
+		};
+		let (first_path, first_indices) = path(0);
+		let (second_path, second_indices) = path(1);
+
+		let proof_bytes = generate_join_proof::<SimpleHasher>(
+			pk,
+			nullifiers.map(|nullifier| nullifier.as_bytes().to_vec()),
+			notes.map(|note| note.as_bytes().to_vec()),
+			root.as_bytes().to_vec(),
+			1,
+			100,
+			TEST_RECIPIENT_HASH.to_vec(),
+			[60, 40],
+			tweaks,
+			secrets,
+			[first_path, second_path],
+			[first_indices, second_indices],
+		).unwrap();
+
+		let verify = |amount: u128, recipient_hash: &[u8]| {
+			verify_join_proof(
+				&prepare_vk(vk),
+				&proof_bytes,
+				[nullifiers[0].as_bytes(), nullifiers[1].as_bytes()],
+				[notes[0].as_bytes(), notes[1].as_bytes()],
+				root.as_bytes(),
+				1,
+				amount,
+				recipient_hash,
+			)
+			.unwrap()
+		};
+		assert!(verify(100, &TEST_RECIPIENT_HASH), "Proof should be valid!");
+		// The proof does not cover a larger payout, nor another recipient
+		assert!(!verify(101, &TEST_RECIPIENT_HASH));
+		assert!(!verify(100, &[9u8; 32]));
 	}
 }
@@ -9,8 +9,13 @@
 
 #[cfg(test)]
 mod integration_tests {
-	use crate::zksnark::{generate_setup_parameters, generate_proof, verify_proof as zksnark_verify};
-	use crate::simple_hash;
+	use crate::circuit::PrivateTransferCircuit;
+	use crate::zksnark::{generate_proof, verify_proof as zksnark_verify};
+	use crate::hasher::SimpleHasher;
+	use crate::test_support::{flip_hash_bit, lone_leaf_path, test_setup, TEST_RECIPIENT_HASH};
+	use crate::{merkle_tree, simple_hash};
+	use ark_bn254::Fr;
+	use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
 	use sp_core::H256;
 
 	#[test]
@@ -18,36 +23,39 @@ mod integration_tests {
 		println!("\n=== zkSNARK Integration Test ===\n");
 
 		// Step 1: Generate trusted setup parameters
-		println!("1. Generating trusted setup parameters...");
-		let (pk, vk) = generate_setup_parameters()
-			.expect("Setup should succeed");
-		println!("   ✓ Proving key and verifying key generated");
+		println!("1. Loading trusted setup parameters...");
+		let (pk, vk) = test_setup();
+		println!("   ✓ Proving key and verifying key ready");
 
 		// Step 2: Create commitment (like in deposit())
 		println!("\n2. Creating commitment (simulating deposit)...");
 		let amount = 1000u128;
 		let asset_id = 0u32;
-		let randomness = [42u8; 32];
+		let (tweak, secret) = ([0u8; 32], [99u8; 32]);
 
-		let commitment = generate_commitment(amount, asset_id, &randomness);
+		let commitment = generate_commitment(amount, asset_id, &secret);
 		println!("   ✓ Commitment: {:?}", commitment);
 
 		// Step 3: Generate nullifier (for spending)
 		println!("\n3. Generating nullifier (for withdrawal)...");
-		let secret = [99u8; 32];
 		let nullifier = generate_nullifier(&commitment, &secret);
 		println!("   ✓ Nullifier: {:?}", nullifier);
 
-		// Step 4: Generate zkSNARK proof off-chain
+		// Step 4: Generate zkSNARK proof off-chain, for a tree holding only the note
 		println!("\n4. Generating zkSNARK proof (off-chain)...");
-		let proof_bytes = generate_proof(
-			&pk,
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof_bytes = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier.as_bytes().to_vec(),
 			commitment.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			merkle_path,
+			path_indices,
 		).expect("Proof generation should succeed");
 
 		println!("   ✓ Proof generated ({} bytes)", proof_bytes.len());
@@ -55,10 +63,14 @@ mod integration_tests {
 		// Step 5: Verify proof on-chain
 		println!("\n5. Verifying zkSNARK proof (on-chain)...");
 		let is_valid = zksnark_verify(
-			&vk,
+			vk,
 			&proof_bytes,
 			nullifier.as_bytes(),
 			commitment.as_bytes(),
+			commitment.as_bytes(),
+			asset_id,
+			amount,
+			&TEST_RECIPIENT_HASH,
 		).expect("Verification should not error");
 
 		if is_valid {
@@ -71,10 +83,14 @@ mod integration_tests {
 		println!("\n6. Testing security: wrong commitment should fail...");
 		let wrong_commitment = H256::from([1u8; 32]);
 		let is_valid_wrong = zksnark_verify(
-			&vk,
+			vk,
 			&proof_bytes,
 			nullifier.as_bytes(),
 			wrong_commitment.as_bytes(),
+			commitment.as_bytes(),
+			asset_id,
+			amount,
+			&TEST_RECIPIENT_HASH,
 		).expect("Verification should not error");
 
 		if !is_valid_wrong {
@@ -99,36 +115,45 @@ mod integration_tests {
 		println!("\n=== Testing Proof Determinism ===\n");
 
 		// Generate setup
-		let (pk, _vk) = generate_setup_parameters().unwrap();
+		let (pk, _vk) = test_setup();
 
 		// Same inputs
 		let amount = 500u128;
 		let asset_id = 0u32;
-		let randomness = [7u8; 32];
+		let tweak = [0u8; 32];
 		let secret = [13u8; 32];
 
-		let commitment = generate_commitment(amount, asset_id, &randomness);
+		let commitment = generate_commitment(amount, asset_id, &secret);
 		let nullifier = generate_nullifier(&commitment, &secret);
 
 		// Generate proof twice with same inputs
-		let proof1 = generate_proof(
-			&pk,
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof1 = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier.as_bytes().to_vec(),
 			commitment.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			merkle_path.clone(),
+			path_indices.clone(),
 		).unwrap();
 
-		let proof2 = generate_proof(
-			&pk,
+		let proof2 = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier.as_bytes().to_vec(),
 			commitment.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount,
 			asset_id,
-			randomness,
+			tweak,
 			secret,
+			merkle_path,
+			path_indices,
 		).unwrap();
 
 		// Note: Groth16 proofs are NOT deterministic due to random blinding factors
@@ -146,51 +171,136 @@ mod integration_tests {
 	fn test_different_amounts_produce_different_proofs() {
 		println!("\n=== Testing Different Amounts ===\n");
 
-		let (pk, vk) = generate_setup_parameters().unwrap();
+		let (pk, vk) = test_setup();
 
 		// Test with amount 100
 		let amount1 = 100u128;
-		let randomness = [5u8; 32];
+		let tweak = [0u8; 32];
 		let secret = [6u8; 32];
 
-		let commitment1 = generate_commitment(amount1, 0, &randomness);
+		let commitment1 = generate_commitment(amount1, 0, &secret);
 		let nullifier1 = generate_nullifier(&commitment1, &secret);
 
-		let proof1 = generate_proof(
-			&pk,
+		let (merkle_path, path_indices) = lone_leaf_path();
+		let proof1 = generate_proof::<SimpleHasher>(
+			pk,
 			nullifier1.as_bytes().to_vec(),
 			commitment1.as_bytes().to_vec(),
+			commitment1.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
 			amount1,
 			0,
-			randomness,
+			tweak,
 			secret,
+			merkle_path,
+			path_indices,
 		).unwrap();
 
 		// Proof should verify for amount1
 		let valid1 = zksnark_verify(
-			&vk,
+			vk,
 			&proof1,
 			nullifier1.as_bytes(),
 			commitment1.as_bytes(),
+			commitment1.as_bytes(),
+			0,
+			amount1,
+			&TEST_RECIPIENT_HASH,
 		).unwrap();
 
 		println!("Amount 100: Proof verifies = {}", valid1);
 
 		// Test with different amount (but try to use same proof - should fail)
 		let amount2 = 200u128;
-		let commitment2 = generate_commitment(amount2, 0, &randomness); // Different commitment
+		let commitment2 = generate_commitment(amount2, 0, &secret); // Different commitment
 
 		println!("Amount 200: Different commitment = {}", commitment1 != commitment2);
 
+		// Nor does the proof of amount 100 pay out 200
+		let valid2 = zksnark_verify(
+			vk,
+			&proof1,
+			nullifier1.as_bytes(),
+			commitment1.as_bytes(),
+			commitment1.as_bytes(),
+			0,
+			amount2,
+			&TEST_RECIPIENT_HASH,
+		).unwrap();
+
 		assert!(valid1, "Proof for amount 100 should verify");
+		assert!(!valid2, "Proof for amount 100 should not pay out 200");
 		assert_ne!(commitment1, commitment2, "Different amounts should produce different commitments");
 
 		println!("\n✓ Different amounts produce different commitments\n");
 	}
 
+	#[test]
+	fn test_membership_of_leaf_in_four_leaf_tree() {
+		let (pk, vk) = test_setup();
+
+		// Leaf 2 of four is the note being spent
+		let (amount, asset_id, tweak, secret) = (300u128, 0u32, [0u8; 32], [9u8; 32]);
+		let commitment = generate_commitment(amount, asset_id, &secret);
+		let nullifier = generate_nullifier(&commitment, &secret);
+		let leaves = [H256::repeat_byte(1), H256::repeat_byte(2), commitment, H256::repeat_byte(4)];
+		let root = merkle_tree::calculate_root(&leaves).unwrap();
+		let (siblings, path_indices) = merkle_tree::generate_circuit_proof(&leaves, 2).unwrap();
+
+		let prove = |siblings: &[H256]| {
+			generate_proof::<SimpleHasher>(
+				pk,
+				nullifier.as_bytes().to_vec(),
+				commitment.as_bytes().to_vec(),
+				root.as_bytes().to_vec(),
+				TEST_RECIPIENT_HASH.to_vec(),
+				amount,
+				asset_id,
+				tweak,
+				secret,
+				siblings.iter().map(|sibling| sibling.0).collect(),
+				path_indices.clone(),
+			)
+		};
+		let verify = |proof: &[u8], root: &H256| {
+			let (nullifier, commitment) = (nullifier.as_bytes(), commitment.as_bytes());
+			let root = root.as_bytes();
+			let recipient_hash = &TEST_RECIPIENT_HASH;
+			zksnark_verify(vk, proof, nullifier, commitment, root, asset_id, amount, recipient_hash)
+				.unwrap()
+		};
+
+		let proof = prove(&siblings).unwrap();
+		assert!(verify(&proof, &root), "Membership of leaf 2 should verify");
+		// The proof does not hold for another tree
+		assert!(!verify(&proof, &H256::repeat_byte(5)));
+
+		// A path with a flipped sibling does not lead to the root: the circuit is not
+		// satisfied, so there is no proof to make
+		let mut forged = siblings.clone();
+		forged[0] = flip_hash_bit(forged[0], 3);
+		let circuit = PrivateTransferCircuit::<SimpleHasher>::new(
+			nullifier.as_bytes().to_vec(),
+			commitment.as_bytes().to_vec(),
+			root.as_bytes().to_vec(),
+			TEST_RECIPIENT_HASH.to_vec(),
+			amount,
+			asset_id,
+			tweak,
+			secret,
+			forged.iter().map(|sibling| sibling.0).collect(),
+			path_indices,
+		);
+		let cs = ConstraintSystem::<Fr>::new_ref();
+		circuit.generate_constraints(cs.clone()).unwrap();
+		assert!(!cs.is_satisfied().unwrap(), "A forged path should be rejected");
+	}
+
 	// Helper functions (matching lib.rs - Week 3: using simple_hash)
-	fn generate_commitment(amount: u128, asset_id: u32, randomness: &[u8; 32]) -> H256 {
-		simple_hash::generate_commitment(amount, asset_id, randomness)
+	fn generate_commitment(amount: u128, asset_id: u32, secret: &[u8; 32]) -> H256 {
+		// A note shielded on this chain: its randomness derives from the secret
+		let randomness = simple_hash::generate_note_randomness(secret, &[0u8; 32]);
+		simple_hash::generate_commitment(amount, asset_id, &randomness)
 	}
 
 	fn generate_nullifier(commitment: &H256, secret: &[u8; 32]) -> H256 {
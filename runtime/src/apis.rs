@@ -33,6 +33,10 @@ use frame_support::{
 	weights::Weight,
 };
 use pallet_aura::Authorities;
+use pallet_privacy_bridge::{
+	ActionRecord, AlertMatch, AlertTagQuery, AnonymityScore, DryRunResult, PoolStats,
+	PublicCommitmentInfo, WithdrawalRecord,
+};
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
@@ -42,12 +46,13 @@ use sp_runtime::{
 	ApplyExtrinsicResult,
 };
 use sp_version::RuntimeVersion;
+use staging_xcm::v5::Location;
 
 // Local module imports
 use super::{
-	AccountId, Balance, Block, ConsensusHook, Executive, InherentDataExt, Nonce, ParachainSystem,
-	Runtime, RuntimeCall, RuntimeGenesisConfig, SessionKeys, System, TransactionPayment,
-	SLOT_DURATION, VERSION,
+	AccountId, Balance, Block, BlockNumber, ConsensusHook, Executive, InherentDataExt, Nonce,
+	ParachainSystem, PrivacyBridge, Runtime, RuntimeCall, RuntimeGenesisConfig, SessionKeys,
+	System, TransactionPayment, SLOT_DURATION, VERSION,
 };
 
 // we move some impls outside so we can easily use them with `docify`.
@@ -219,6 +224,81 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_privacy_bridge::runtime_api::PrivacyBridgeApi<Block, BlockNumber, AccountId>
+		for Runtime
+	{
+		fn dry_run_cross_chain_withdrawal(
+			asset_id: u32,
+			amount: u128,
+			destination: Location,
+			beneficiary: Vec<u8>,
+		) -> DryRunResult {
+			PrivacyBridge::dry_run_cross_chain_withdrawal(asset_id, amount, destination, beneficiary)
+		}
+
+		fn pool_stats(asset_id: u32) -> PoolStats {
+			PrivacyBridge::pool_stats(asset_id)
+		}
+
+		fn anonymity_score(asset_id: u32) -> AnonymityScore {
+			PrivacyBridge::anonymity_score(asset_id)
+		}
+
+		fn root_ttl(root: sp_core::H256) -> Option<u32> {
+			PrivacyBridge::root_ttl(root)
+		}
+
+		fn nullifiers_spent_since(
+			block: BlockNumber,
+			limit: u32,
+		) -> Vec<(sp_core::H256, BlockNumber)> {
+			PrivacyBridge::nullifiers_spent_since(block, limit)
+		}
+
+		fn receipt_count(account: AccountId) -> u32 {
+			PrivacyBridge::receipt_count(account)
+		}
+
+		fn commitment_info(
+			commitment: sp_core::H256,
+		) -> Option<PublicCommitmentInfo<BlockNumber>> {
+			PrivacyBridge::commitment_info(commitment)
+		}
+
+		fn match_alert_tags(
+			spent_since: BlockNumber,
+			queries: Vec<AlertTagQuery>,
+		) -> Vec<AlertMatch<BlockNumber>> {
+			PrivacyBridge::match_alert_tags(spent_since, queries)
+		}
+
+		fn recent_actions(since_seq: u64) -> Option<Vec<ActionRecord<BlockNumber>>> {
+			PrivacyBridge::recent_actions(since_seq)
+		}
+
+		fn withdrawal_by_id(
+			receipt_id: sp_core::H256,
+		) -> Option<WithdrawalRecord<BlockNumber>> {
+			PrivacyBridge::withdrawal_by_id(receipt_id)
+		}
+
+		fn merkle_root() -> sp_core::H256 {
+			PrivacyBridge::merkle_root()
+		}
+
+		fn known_roots() -> Vec<sp_core::H256> {
+			PrivacyBridge::known_roots()
+		}
+
+		fn merkle_path(leaf_index: u32) -> Option<Vec<sp_core::H256>> {
+			PrivacyBridge::merkle_path(leaf_index)
+		}
+
+		fn commitment_count() -> u32 {
+			PrivacyBridge::commitment_count()
+		}
+	}
+
 	#[cfg(feature = "try-runtime")]
 	impl frame_try_runtime::TryRuntime<Block> for Runtime {
 		fn on_runtime_upgrade(checks: frame_try_runtime::UpgradeCheckSelect) -> (Weight, Weight) {
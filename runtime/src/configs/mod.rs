@@ -37,14 +37,15 @@ use frame_support::{
 	dispatch::DispatchClass,
 	parameter_types,
 	traits::{
-		ConstBool, ConstU32, ConstU64, ConstU8, EitherOfDiverse, TransformOrigin, VariantCountOf,
+		AsEnsureOriginWithArg, ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, EitherOfDiverse,
+		Everything, TransformOrigin, VariantCountOf,
 	},
 	weights::{ConstantMultiplier, Weight},
 	PalletId,
 };
 use frame_system::{
 	limits::{BlockLength, BlockWeights},
-	EnsureRoot,
+	EnsureRoot, EnsureSigned,
 };
 use pallet_xcm::{EnsureXcm, IsVoiceOfBody};
 use parachains_common::message_queue::{NarrowOriginToSibling, ParaIdToSibling};
@@ -52,20 +53,24 @@ use polkadot_runtime_common::{
 	xcm_sender::NoPriceForMessageDelivery, BlockHashCount, SlowAdjustingFeeUpdate,
 };
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_runtime::Perbill;
+use sp_runtime::{traits::AccountIdConversion, Perbill};
 use sp_version::RuntimeVersion;
 use xcm::latest::prelude::BodyId;
 
 // Local module imports
 use super::{
 	weights::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight},
-	AccountId, Aura, Balance, Balances, Block, BlockNumber, CollatorSelection, ConsensusHook, Hash,
-	MessageQueue, Nonce, PalletInfo, ParachainSystem, Runtime, RuntimeCall, RuntimeEvent,
-	RuntimeFreezeReason, RuntimeHoldReason, RuntimeOrigin, RuntimeTask, Session, SessionKeys,
-	System, WeightToFee, XcmpQueue, AVERAGE_ON_INITIALIZE_RATIO, EXISTENTIAL_DEPOSIT, HOURS,
-	MAXIMUM_BLOCK_WEIGHT, MICRO_UNIT, NORMAL_DISPATCH_RATIO, SLOT_DURATION, VERSION,
+	AccountId, Assets, Aura, Balance, Balances, Block, BlockNumber, CollatorSelection,
+	ConsensusHook, Hash, MessageQueue, Nonce, PalletInfo, ParachainSystem, Runtime, RuntimeCall,
+	RuntimeEvent, RuntimeFreezeReason, RuntimeHoldReason, RuntimeOrigin, RuntimeTask, Session,
+	SessionKeys, System, WeightToFee, XcmpQueue, AVERAGE_ON_INITIALIZE_RATIO, DAYS,
+	EXISTENTIAL_DEPOSIT, HOURS, MAXIMUM_BLOCK_WEIGHT, MICRO_UNIT, MILLI_UNIT, NORMAL_DISPATCH_RATIO,
+	SLOT_DURATION, UNIT, VERSION,
+};
+use xcm_config::{
+	PrivacyBridgeReserveRouting, RelayLocation, UniversalLocation,
+	XcmOriginToTransactDispatchOrigin, XcmRouter,
 };
-use xcm_config::{RelayLocation, XcmOriginToTransactDispatchOrigin};
 
 parameter_types! {
 	pub const Version: RuntimeVersion = VERSION;
@@ -165,6 +170,38 @@ impl pallet_balances::Config for Runtime {
 	type DoneSlashHandler = ();
 }
 
+parameter_types! {
+	pub const AssetDeposit: Balance = 10 * UNIT;
+	pub const AssetAccountDeposit: Balance = MILLI_UNIT;
+	pub const MetadataDepositBase: Balance = MILLI_UNIT;
+	pub const MetadataDepositPerByte: Balance = MICRO_UNIT;
+	pub const ApprovalDeposit: Balance = MILLI_UNIT;
+}
+
+/// Local assets backing non-native assets shielded by the privacy bridge
+impl pallet_assets::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Balance = Balance;
+	type AssetId = u32;
+	type AssetIdParameter = u32;
+	type Currency = Balances;
+	type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<AccountId>>;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = AssetDeposit;
+	type AssetAccountDeposit = AssetAccountDeposit;
+	type MetadataDepositBase = MetadataDepositBase;
+	type MetadataDepositPerByte = MetadataDepositPerByte;
+	type ApprovalDeposit = ApprovalDeposit;
+	type StringLimit = ConstU32<50>;
+	type Freezer = ();
+	type Extra = ();
+	type CallbackHandle = ();
+	type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
+	type RemoveItemsLimit = ConstU32<1000>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = ();
+}
+
 parameter_types! {
 	/// Relay Chain `TransactionByteFee` / 10
 	pub const TransactionByteFee: Balance = 10 * MICRO_UNIT;
@@ -316,8 +353,85 @@ impl pallet_parachain_template::Config for Runtime {
 	type WeightInfo = pallet_parachain_template::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+	pub const PrivacyBridgePalletId: PalletId = PalletId(*b"py/cloak");
+	pub const PrivacyBridgeTreasuryId: PalletId = PalletId(*b"py/clktr");
+	pub PrivacyBridgeTreasury: AccountId = PrivacyBridgeTreasuryId::get().into_account_truncating();
+	// Withdrawals leave at least three quarters of a block to other activity
+	pub PrivacyBridgeVerificationWeight: Weight = MAXIMUM_BLOCK_WEIGHT.saturating_div(4);
+	// Unsigned withdrawals pay the treasury about what a signed one pays in fees
+	pub const PrivacyBridgeUnsignedRelayFee: Option<u128> = Some(10 * MILLI_UNIT);
+	// The relay chain's technical body co-signs the bridge's dangerous admin actions
+	pub const CoSignerBodyId: BodyId = BodyId::Technical;
+}
+
 /// Configure the privacy bridge pallet
 impl pallet_privacy_bridge::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = pallet_privacy_bridge::weights::SubstrateWeight<Runtime>;
+	type Hasher = pallet_privacy_bridge::hasher::PoseidonHasher;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type TreasuryAccount = PrivacyBridgeTreasury;
+	// Decoys are pure anonymity-set padding
+	type DecoyValue = ConstU128<0>;
+	type MaxDecoysPerBlock = ConstU32<4>;
+	type Currency = Balances;
+	type Fungibles = Assets;
+	type PalletId = PrivacyBridgePalletId;
+	// Reject dust payouts up front so the user keeps an unspent note
+	type ClaimDustPayouts = ConstBool<false>;
+	type Exporter =
+		pallet_privacy_bridge::bridge_adapter::XcmExporter<XcmRouter, PrivacyBridgeReserveRouting>;
+	type TrapOrigin = EnsureXcm<Everything>;
+	// Well below `MaxInstructions` (3 setup instructions plus one deposit each)
+	type MaxDepositsPerMessage = ConstU32<32>;
+	type UniversalLocation = UniversalLocation;
+	type ReceiptSender = pallet_privacy_bridge::bridge_adapter::XcmReceiptSender<XcmRouter>;
+	// Flag any asset whose reserve backing moves by more than 10_000 units in a block
+	type ReserveBackingAlertThreshold = ConstU128<{ 10_000 * UNIT }>;
+	type SolvencyCheckInterval = ConstU32<100>;
+	type MaxSolvencyChecks = ConstU32<16>;
+	type BatchTreeInsertion = ConstBool<false>;
+	type AnnouncementTtl = ConstU32<{ 7 * 24 * HOURS }>;
+	type MaxSplit = ConstU32<16>;
+	type RootHistorySize = ConstU32<100>;
+	// Proofs are not anchored to recent blocks
+	type MaxProofAge = ();
+	// The pot pays the fees of native deposits up to 10 UNIT, three per account a day
+	type SponsorThreshold = ConstU128<{ 10 * UNIT }>;
+	type SponsoredFee = ConstU128<{ 10 * MILLI_UNIT }>;
+	type SponsorPeriod = ConstU32<DAYS>;
+	type MaxSponsoredPerAccount = ConstU32<3>;
+	type MaxSponsoredPerPeriod = ConstU32<1_000>;
+	type Metrics = ();
+	type MaxBatch = ConstU32<256>;
+	type MaxBatchLeavesPerBlock = ConstU32<1_024>;
+	// Backups are padded to 1 KiB, about 10 MILLI_UNIT of deposit each
+	type MaxBackupLen = ConstU32<1_024>;
+	type BackupDepositPerByte = ConstU128<{ 10 * MICRO_UNIT }>;
+	type MaxVerificationWeightPerBlock = PrivacyBridgeVerificationWeight;
+	// No randomness beacon on this chain: decoys and payout order draw on the parent hash
+	type Randomness = pallet_privacy_bridge::randomness::ParentHashRandomness<Runtime>;
+	type MaxAlertTags = ConstU32<64>;
+	// Warn when an asset's unspent notes and last day's deposits add up to fewer than 50
+	type ScoreWindow = ConstU32<DAYS>;
+	type MinAnonymityScore = ConstU32<50>;
+	type RandomnessReuseWindow = ConstU32<HOURS>;
+	type MaxProvingJobLen = ConstU32<4_096>;
+	type MaxProvingJobs = ConstU32<256>;
+	type ProvingJobTtl = ConstU32<DAYS>;
+	type ProvingClaimTtl = ConstU32<HOURS>;
+	type ProverBond = ConstU128<{ 100 * MILLI_UNIT }>;
+	type MinProvingBounty = ConstU128<MILLI_UNIT>;
+	// A month to withdraw from a delisted asset, then a month for public refunds
+	type WindDownPeriod = ConstU32<{ 30 * DAYS }>;
+	type MaxPayouts = ConstU32<16>;
+	type CoSignerOrigin = EnsureXcm<IsVoiceOfBody<RelayLocation, CoSignerBodyId>>;
+	type AdminApprovalTtl = ConstU32<{ 2 * DAYS }>;
+	type ProofVerifier = pallet_privacy_bridge::zksnark::Groth16Verifier;
+	// Deposits arrive through the XCM executor only (`xcm_config::AssetTransactor`)
+	type AllowSignedXcmDeposits = ConstBool<false>;
+	type UnsignedRelayFee = PrivacyBridgeUnsignedRelayFee;
+	// Calls carry Groth16 proofs, nothing longer
+	type MaxProofSize = ConstU32<{ pallet_privacy_bridge::zksnark::PROOF_LEN as u32 }>;
 }
@@ -1,31 +1,39 @@
 use crate::{
-	AccountId, AllPalletsWithSystem, Balances, ParachainInfo, ParachainSystem, PolkadotXcm,
-	Runtime, RuntimeCall, RuntimeEvent, RuntimeOrigin, WeightToFee, XcmpQueue,
+	AccountId, AllPalletsWithSystem, Assets, Balance, Balances, ParachainInfo, ParachainSystem,
+	PolkadotXcm, PrivacyBridge, Runtime, RuntimeCall, RuntimeEvent, RuntimeOrigin, WeightToFee,
+	XcmpQueue, MILLI_UNIT,
 };
 
 use polkadot_sdk::{
 	staging_xcm as xcm, staging_xcm_builder as xcm_builder, staging_xcm_executor as xcm_executor, *,
 };
 
+use codec::Encode;
 use frame_support::{
 	parameter_types,
-	traits::{ConstU32, Contains, Everything, Nothing},
+	traits::{ConstU32, Contains, Everything, Nothing, PalletInfoAccess},
 	weights::Weight,
 };
 use frame_system::EnsureRoot;
+use pallet_privacy_bridge::bridge_adapter::{ExportError, MessageId, ReserveRouting};
+use pallet_privacy_bridge::xcm_config::{
+	AllowShieldDeposits, RegisteredAssetIds, RegisteredAssets, ShieldDepositMaxWeight,
+	ShieldDepositTransactor, ShieldTrap,
+};
 use pallet_xcm::XcmPassthrough;
 use polkadot_parachain_primitives::primitives::Sibling;
 use polkadot_runtime_common::impls::ToAuthor;
 use xcm::latest::prelude::*;
 use xcm_builder::{
 	AccountId32Aliases, AllowExplicitUnpaidExecutionFrom, AllowTopLevelPaidExecutionFrom,
-	DenyReserveTransferToRelayChain, DenyThenTry, EnsureXcmOrigin, FixedWeightBounds,
-	FrameTransactionalProcessor, FungibleAdapter, IsConcrete, NativeAsset, ParentIsPreset,
-	RelayChainAsNative, SiblingParachainAsNative, SiblingParachainConvertsVia,
-	SignedAccountId32AsNative, SignedToAccountId32, SovereignSignedViaLocation, TakeWeightCredit,
-	TrailingSetTopicAsId, UsingComponents, WithComputedOrigin, WithUniqueTopic,
+	ConvertedConcreteId, DenyReserveTransferToRelayChain, DenyThenTry, EnsureXcmOrigin,
+	FixedWeightBounds, FrameTransactionalProcessor, FungibleAdapter, FungiblesAdapter, IsConcrete,
+	NativeAsset, NoChecking, ParentIsPreset, RelayChainAsNative, SiblingParachainAsNative,
+	SiblingParachainConvertsVia, SignedAccountId32AsNative, SignedToAccountId32,
+	SovereignSignedViaLocation, TakeWeightCredit, TrailingSetTopicAsId, UsingComponents,
+	WithComputedOrigin, WithUniqueTopic,
 };
-use xcm_executor::XcmExecutor;
+use xcm_executor::{traits::JustTry, XcmExecutor};
 
 parameter_types! {
 	pub const RelayLocation: Location = Location::parent();
@@ -34,6 +42,13 @@ parameter_types! {
 	// For the real deployment, it is recommended to set `RelayNetwork` according to the relay chain
 	// and prepend `UniversalLocation` with `GlobalConsensus(RelayNetwork::get())`.
 	pub UniversalLocation: InteriorLocation = Parachain(ParachainInfo::parachain_id().into()).into();
+	pub CheckingAccount: AccountId = PolkadotXcm::check_account();
+	/// Where shield deposits are addressed to (the privacy bridge pallet)
+	pub ShieldLocation: Location =
+		Location::new(0, [PalletInstance(<PrivacyBridge as PalletInfoAccess>::index() as u8)]);
+	/// Shielded assets are credited to the pool account
+	pub PoolLocation: Location =
+		AccountId32 { network: None, id: PrivacyBridge::account_id().into() }.into();
 }
 
 /// Type for specifying how a `Location` can be converted into an `AccountId`. This is used
@@ -62,6 +77,26 @@ pub type LocalAssetTransactor = FungibleAdapter<
 	(),
 >;
 
+/// Means for transacting the assets registered with the privacy bridge, held in `Assets`
+/// under the local IDs the bridge's registry assigns them.
+pub type RegisteredAssetsTransactor = FungiblesAdapter<
+	Assets,
+	ConvertedConcreteId<u32, Balance, RegisteredAssetIds<Runtime>, JustTry>,
+	LocationToAccountId,
+	AccountId,
+	NoChecking,
+	CheckingAccount,
+>;
+
+/// Means for transacting assets, with deposits to `ShieldLocation` shielded by the privacy
+/// bridge.
+pub type AssetTransactor = ShieldDepositTransactor<
+	Runtime,
+	(LocalAssetTransactor, RegisteredAssetsTransactor),
+	ShieldLocation,
+	PoolLocation,
+>;
+
 /// This is the type we use to convert an (incoming) XCM origin into a local `Origin` instance,
 /// ready for dispatching a transaction with Xcm's `Transact`. There is an `OriginKind` which can
 /// biases the kind of local `Origin` it will become.
@@ -102,6 +137,8 @@ pub type Barrier = TrailingSetTopicAsId<
 		DenyReserveTransferToRelayChain,
 		(
 			TakeWeightCredit,
+			// Shield deposits of registered assets, bounded in weight rather than paid for
+			AllowShieldDeposits<RegisteredAssets<Runtime>, ShieldLocation, ShieldDepositMaxWeight>,
 			WithComputedOrigin<
 				(
 					AllowTopLevelPaidExecutionFrom<Everything>,
@@ -120,7 +157,7 @@ impl xcm_executor::Config for XcmConfig {
 	type RuntimeCall = RuntimeCall;
 	type XcmSender = XcmRouter;
 	// How to withdraw and deposit an asset.
-	type AssetTransactor = LocalAssetTransactor;
+	type AssetTransactor = AssetTransactor;
 	type OriginConverter = XcmOriginToTransactDispatchOrigin;
 	type IsReserve = NativeAsset;
 	type IsTeleporter = (); // Teleporting is disabled.
@@ -130,7 +167,8 @@ impl xcm_executor::Config for XcmConfig {
 	type Trader =
 		UsingComponents<WeightToFee, RelayLocation, AccountId, Balances, ToAuthor<Runtime>>;
 	type ResponseHandler = PolkadotXcm;
-	type AssetTrap = PolkadotXcm;
+	// Fungible assets left in holding are trapped shield deposits
+	type AssetTrap = ShieldTrap<Runtime, PolkadotXcm>;
 	type AssetClaims = PolkadotXcm;
 	type SubscriptionService = PolkadotXcm;
 	type PalletInstancesInfo = AllPalletsWithSystem;
@@ -192,6 +230,48 @@ impl pallet_xcm::Config for Runtime {
 	type RemoteLockConsumerIdentifier = ();
 }
 
+parameter_types! {
+	/// Execution fee budgeted per hop of a privacy bridge withdrawal routed via its reserve
+	pub const PrivacyBridgeHopFee: u128 = 10 * MILLI_UNIT;
+}
+
+/// Routes privacy bridge withdrawals of remotely reserved assets through their reserve
+///
+/// The withdrawal starts with a local program executed by the pool account. The program
+/// is built here rather than received, so it runs on weight credit like `PolkadotXcm::execute`.
+pub struct PrivacyBridgeReserveRouting;
+
+impl ReserveRouting for PrivacyBridgeReserveRouting {
+	fn reserve_of(asset: &AssetId) -> Option<Location> {
+		PrivacyBridge::reserve_location(asset)
+	}
+
+	fn hop_fee(_asset: &AssetId) -> u128 {
+		PrivacyBridgeHopFee::get()
+	}
+
+	fn universal_location() -> InteriorLocation {
+		UniversalLocation::get()
+	}
+
+	fn execute(program: Xcm<()>) -> Result<MessageId, ExportError> {
+		let mut message_id = program.using_encoded(sp_io::hashing::blake2_256);
+		let max_weight = UnitWeightCost::get().saturating_mul(MaxInstructions::get() as u64);
+
+		XcmExecutor::<XcmConfig>::prepare_and_execute(
+			PoolLocation::get(),
+			program.into(),
+			&mut message_id,
+			max_weight,
+			max_weight,
+		)
+		.ensure_complete()
+		.map_err(|_| ExportError::SendFailed)?;
+
+		Ok(message_id)
+	}
+}
+
 impl cumulus_pallet_xcm::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type XcmExecutor = XcmExecutor<XcmConfig>;
@@ -1,6 +1,7 @@
 use crate::{
 	AccountId, BalancesConfig, CollatorSelectionConfig, ParachainInfoConfig, PolkadotXcmConfig,
-	RuntimeGenesisConfig, SessionConfig, SessionKeys, SudoConfig, EXISTENTIAL_DEPOSIT,
+	PrivacyBridgeConfig, RuntimeGenesisConfig, SessionConfig, SessionKeys, SudoConfig,
+	EXISTENTIAL_DEPOSIT,
 };
 
 use alloc::{vec, vec::Vec};
@@ -30,6 +31,7 @@ fn testnet_genesis(
 	endowed_accounts: Vec<AccountId>,
 	root: AccountId,
 	id: ParaId,
+	privacy_bridge: PrivacyBridgeConfig,
 ) -> Value {
 	let config = RuntimeGenesisConfig {
 		balances: BalancesConfig {
@@ -63,12 +65,18 @@ fn testnet_genesis(
 			..Default::default()
 		},
 		sudo: SudoConfig { key: Some(root) },
+		privacy_bridge,
 		..Default::default()
 	};
 
 	serde_json::to_value(config).expect("Could not build genesis config.")
 }
 
+/// Privacy bridge config of the pallet preset `name`
+fn privacy_bridge_preset(name: &str) -> PrivacyBridgeConfig {
+	PrivacyBridgeConfig::preset(name).expect("the pallet ships this preset; qed")
+}
+
 fn local_testnet_genesis() -> Value {
 	testnet_genesis(
 		// initial collators.
@@ -79,6 +87,7 @@ fn local_testnet_genesis() -> Value {
 		Sr25519Keyring::well_known().map(|k| k.to_account_id()).collect(),
 		Sr25519Keyring::Alice.to_account_id(),
 		PARACHAIN_ID.into(),
+		privacy_bridge_preset(pallet_privacy_bridge::DEVELOPMENT_PRESET),
 	)
 }
 
@@ -92,6 +101,23 @@ fn development_config_genesis() -> Value {
 		Sr25519Keyring::well_known().map(|k| k.to_account_id()).collect(),
 		Sr25519Keyring::Alice.to_account_id(),
 		PARACHAIN_ID.into(),
+		privacy_bridge_preset(pallet_privacy_bridge::DEVELOPMENT_PRESET),
+	)
+}
+
+/// Local testnet whose privacy bridge starts with the conservative settings of a
+/// production launch
+fn conservative_genesis() -> Value {
+	testnet_genesis(
+		// initial collators.
+		vec![
+			(Sr25519Keyring::Alice.to_account_id(), Sr25519Keyring::Alice.public().into()),
+			(Sr25519Keyring::Bob.to_account_id(), Sr25519Keyring::Bob.public().into()),
+		],
+		Sr25519Keyring::well_known().map(|k| k.to_account_id()).collect(),
+		Sr25519Keyring::Alice.to_account_id(),
+		PARACHAIN_ID.into(),
+		privacy_bridge_preset(pallet_privacy_bridge::CONSERVATIVE_PRESET),
 	)
 }
 
@@ -100,6 +126,7 @@ pub fn get_preset(id: &PresetId) -> Option<vec::Vec<u8>> {
 	let patch = match id.as_ref() {
 		sp_genesis_builder::LOCAL_TESTNET_RUNTIME_PRESET => local_testnet_genesis(),
 		sp_genesis_builder::DEV_RUNTIME_PRESET => development_config_genesis(),
+		pallet_privacy_bridge::CONSERVATIVE_PRESET => conservative_genesis(),
 		_ => return None,
 	};
 	Some(
@@ -114,5 +141,6 @@ pub fn preset_names() -> Vec<PresetId> {
 	vec![
 		PresetId::from(sp_genesis_builder::DEV_RUNTIME_PRESET),
 		PresetId::from(sp_genesis_builder::LOCAL_TESTNET_RUNTIME_PRESET),
+		PresetId::from(pallet_privacy_bridge::CONSERVATIVE_PRESET),
 	]
 }
@@ -82,9 +82,15 @@ pub type TxExtension = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	// Small deposits are paid for by the privacy bridge's sponsorship pot
+	pallet_privacy_bridge::SponsorDeposit<
+		Runtime,
+		pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	>,
 	cumulus_primitives_storage_weight_reclaim::StorageWeightReclaim<Runtime>,
 	frame_metadata_hash_extension::CheckMetadataHash<Runtime>,
+	pallet_privacy_bridge::CheckDuplicateCommitment<Runtime>,
+	pallet_privacy_bridge::CheckVerificationBudget<Runtime>,
 );
 
 /// Unchecked extrinsic type as expected by this runtime.
@@ -95,7 +101,10 @@ pub type UncheckedExtrinsic =
 ///
 /// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
 #[allow(unused_parens)]
-type Migrations = ();
+type Migrations = (
+	pallet_privacy_bridge::migrations::v1::MigrateToV1<Runtime>,
+	pallet_privacy_bridge::migrations::v2::MigrateToV2<Runtime>,
+);
 
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
@@ -167,10 +176,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: alloc::borrow::Cow::Borrowed("cloak"),
 	impl_name: alloc::borrow::Cow::Borrowed("cloak"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 2,
 	impl_version: 0,
 	apis: apis::RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 4,
 	system_version: 1,
 };
 
@@ -277,6 +286,8 @@ mod runtime {
 	pub type Balances = pallet_balances;
 	#[runtime::pallet_index(11)]
 	pub type TransactionPayment = pallet_transaction_payment;
+	#[runtime::pallet_index(12)]
+	pub type Assets = pallet_assets;
 
 	// Governance
 	#[runtime::pallet_index(15)]